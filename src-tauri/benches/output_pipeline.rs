@@ -0,0 +1,39 @@
+//! Throughput of the output pipeline: `cargo bench --bench output_pipeline`.
+//!
+//! Runs each size a few times after a warm-up and reports the median per stage, so numbers
+//! can be compared across commits on the same machine.
+
+use opcode_lib::commands::output_bench::{run_output_pipeline_bench, StageResult};
+
+const SAMPLES: usize = 7;
+
+/// (lines, line size): short status lines, typical tool output, and large file reads
+const SIZES: [(usize, usize); 3] = [(200_000, 256), (50_000, 4 * 1024), (2_000, 128 * 1024)];
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
+
+fn main() {
+    for (lines, line_size) in SIZES {
+        run_output_pipeline_bench(lines / 10, line_size).expect("warm-up failed");
+        let samples: Vec<Vec<StageResult>> = (0..SAMPLES)
+            .map(|_| {
+                run_output_pipeline_bench(lines, line_size)
+                    .expect("benchmark failed")
+                    .stages
+            })
+            .collect();
+
+        println!("{} lines × {} B", lines, line_size);
+        for (index, stage) in samples[0].iter().enumerate() {
+            let mb_per_sec = median(samples.iter().map(|s| s[index].mb_per_sec).collect());
+            let lines_per_sec = median(samples.iter().map(|s| s[index].lines_per_sec).collect());
+            println!(
+                "  {:<16} {:>10.1} MB/s {:>12.0} lines/s",
+                stage.stage, mb_per_sec, lines_per_sec
+            );
+        }
+    }
+}
@@ -1,3 +1,39 @@
+use std::process::Command;
+
+/// Run a git command in the repository, returning its trimmed output
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
 fn main() {
+    // Build provenance reported by get_version_info
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let branch =
+        git(&["rev-parse", "--abbrev-ref", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some();
+    let build_timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=OPCODE_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=OPCODE_GIT_BRANCH={}", branch);
+    println!("cargo:rustc-env=OPCODE_GIT_DIRTY={}", dirty);
+    println!("cargo:rustc-env=OPCODE_BUILD_TIMESTAMP={}", build_timestamp);
+    println!(
+        "cargo:rustc-env=OPCODE_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    for path in ["../.git/HEAD", "../.git/index"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
     tauri_build::build()
 }
@@ -0,0 +1,177 @@
+#![allow(dead_code)]
+
+//! Crash-safe file replacement for config files. Contents go to a temporary file next to the
+//! target, are flushed to disk, and only then renamed over the target, so a crash or full
+//! disk leaves either the old file or the new one, never a truncated mix. Every code path
+//! that rewrites a config file (`.mcp.json`, `settings.json`, `CLAUDE.md`, command and skill
+//! files, ...) goes through here.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temporary files of concurrent writes within this process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Suffix of the copy kept by [`write_atomic_with_backup`]
+pub const BACKUP_SUFFIX: &str = "bak";
+
+/// Replace `path` with `contents` atomically
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let contents = contents.as_ref();
+    write_with(path.as_ref(), false, |file| file.write_all(contents))
+}
+
+/// Replace `path` with `contents` atomically, first copying the current file to
+/// `<path>.bak`
+pub fn write_atomic_with_backup(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+) -> io::Result<()> {
+    let contents = contents.as_ref();
+    write_with(path.as_ref(), true, |file| file.write_all(contents))
+}
+
+/// Path of the backup kept for `path`
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(BACKUP_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".tmp-{}-{}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+/// Write through `fill` into a temporary file and move it over `path`. If anything fails
+/// before the rename, the temporary file is removed and `path` is untouched.
+fn write_with(
+    path: &Path,
+    backup: bool,
+    fill: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    if path.file_name().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a file path: {}", path.display()),
+        ));
+    }
+    // Replace the file a symlink points to rather than the link itself
+    let resolved;
+    let path = if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink()) {
+        resolved = fs::canonicalize(path)?;
+        resolved.as_path()
+    } else {
+        path
+    };
+    let temp = temp_path(path);
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        fill(&mut file)?;
+        file.sync_all()?;
+        drop(file);
+
+        match fs::metadata(path) {
+            Ok(existing) => {
+                // Keep restrictive modes such as 0600 on files holding tokens
+                fs::set_permissions(&temp, existing.permissions())?;
+                if backup {
+                    fs::copy(path, backup_path(path))?;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        fs::rename(&temp, path)?;
+        sync_parent(path);
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Persist the rename itself; best effort, as not every file system supports it
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_interrupted_write_leaves_original_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        write_atomic(&path, "{\"mcpServers\":{}}").unwrap();
+
+        // The process dies halfway through writing the new contents
+        let result = write_with(&path, false, |file| {
+            file.write_all(b"{\"mcpServers\":{\"half")?;
+            Err(io::Error::other("simulated crash"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"mcpServers\":{}}");
+        assert_eq!(entries(dir.path()), vec![".mcp.json"]);
+
+        // A target that can't be replaced fails without leaving a temporary file behind
+        let blocked = dir.path().join("blocked");
+        fs::create_dir_all(blocked.join("inner")).unwrap();
+        assert!(write_atomic(&blocked, "x").is_err());
+        assert_eq!(entries(dir.path()), vec![".mcp.json", "blocked"]);
+    }
+
+    #[test]
+    fn test_backup_and_permissions_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, "old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        write_atomic_with_backup(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "old");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
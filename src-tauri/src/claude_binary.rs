@@ -13,64 +13,7 @@ use tauri::Manager;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
-/// Converts command output bytes to UTF-8 string, handling Windows encoding issues
-/// On Windows, cmd.exe outputs GBK/GB2312 encoding by default, which needs conversion
-pub fn decode_command_output(bytes: &[u8]) -> String {
-    // First try UTF-8 (most common case)
-    if let Ok(utf8_str) = std::str::from_utf8(bytes) {
-        return utf8_str.to_string();
-    }
-    
-    // On Windows, try GBK encoding (Chinese Windows default)
-    #[cfg(target_os = "windows")]
-    {
-        use encoding_rs::GBK;
-        let (decoded, _, had_errors) = GBK.decode(bytes);
-        if !had_errors {
-            return decoded.to_string();
-        }
-    }
-    
-    // Fallback to lossy UTF-8 conversion
-    String::from_utf8_lossy(bytes).to_string()
-}
-
-/// Async helper function to read and decode a line with proper encoding handling
-/// This is used for streaming command output where encoding conversion is needed
-pub async fn read_decoded_line<R: tokio::io::AsyncReadExt + Unpin>(
-    reader: &mut tokio::io::BufReader<R>,
-) -> tokio::io::Result<Option<String>> {
-    use tokio::io::AsyncReadExt;
-    let mut buffer = Vec::new();
-    loop {
-        let byte = match reader.read_u8().await {
-            Ok(b) => b,
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                if buffer.is_empty() {
-                    return Ok(None);
-                }
-                break;
-            }
-            Err(e) => return Err(e),
-        };
-        
-        if byte == b'\n' {
-            break;
-        }
-        
-        if byte != b'\r' {
-            buffer.push(byte);
-        }
-    }
-    
-    if buffer.is_empty() {
-        return Ok(Some(String::new()));
-    }
-    
-    // Decode using the same logic as decode_command_output
-    let decoded = decode_command_output(&buffer);
-    Ok(Some(decoded))
-}
+pub use crate::decoding::{decode_command_output, read_decoded_line};
 
 /// Type of Claude installation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -97,10 +40,15 @@ pub struct ClaudeInstallation {
 /// Main function to find the Claude binary
 /// Checks database first for stored path and preference, then prioritizes accordingly
 pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, String> {
+    find_claude_binary_in(app_handle.path().app_data_dir().ok().as_deref())
+}
+
+/// Same as `find_claude_binary`, for callers without an app handle (e.g. the CLI)
+pub fn find_claude_binary_in(app_data_dir: Option<&std::path::Path>) -> Result<String, String> {
     info!("Searching for claude binary...");
 
     // First check if we have a stored path and preference in the database
-    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+    if let Some(app_data_dir) = app_data_dir {
         let db_path = app_data_dir.join("agents.db");
         if db_path.exists() {
             if let Ok(conn) = rusqlite::Connection::open(&db_path) {
@@ -563,7 +511,7 @@ fn find_standard_installations() -> Vec<ClaudeInstallation> {
 }
 
 /// Get Claude version by running --version command
-fn get_claude_version(path: &str) -> Result<Option<String>, String> {
+pub(crate) fn get_claude_version(path: &str) -> Result<Option<String>, String> {
     match Command::new(path).arg("--version").output() {
         Ok(output) => {
             if output.status.success() {
@@ -642,7 +590,7 @@ fn select_best_installation(installations: Vec<ClaudeInstallation>) -> Option<Cl
 }
 
 /// Compare two version strings
-fn compare_versions(a: &str, b: &str) -> Ordering {
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
     // Simple semantic version comparison
     let a_parts: Vec<u32> = a
         .split('.')
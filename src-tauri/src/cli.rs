@@ -0,0 +1,286 @@
+use clap::{Parser, Subcommand};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use opcode_lib::commands::approval_mcp::{serve_stdio, ApprovalEndpoint, TOKEN_ENV};
+use opcode_lib::commands::mcp::parse_mcp_server_names;
+use opcode_lib::commands::replay::answer_stub_hook;
+use opcode_lib::commands::usage::get_usage_stats;
+use opcode_lib::headless::{Headless, RunOutput};
+
+#[derive(Parser)]
+#[command(name = "opcode-cli")]
+#[command(about = "Run opcode agents and inspect opcode data without the GUI")]
+struct Args {
+    /// App data directory holding agents.db (defaults to the GUI's)
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage and run agents
+    #[command(subcommand)]
+    Agents(AgentsCommand),
+    /// Show agent run history
+    Runs {
+        /// Only show runs of this agent (id or name)
+        #[arg(long)]
+        agent: Option<String>,
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    /// Manage MCP servers
+    #[command(subcommand)]
+    Mcp(McpCommand),
+    /// Show token usage and cost
+    Usage {
+        /// Only include the last N days
+        #[arg(long)]
+        days: Option<u32>,
+    },
+    /// PreToolUse hook answering tool calls from a recorded run during a replay
+    #[command(hide = true)]
+    ReplayStub {
+        /// Stubs file written by the replay
+        stubs: PathBuf,
+    },
+    /// Stdio MCP server asking the opcode app to approve tool use of an agent run
+    #[command(hide = true)]
+    ApprovalMcp {
+        /// Approval endpoint of the running app
+        #[arg(long)]
+        endpoint: String,
+        #[arg(long)]
+        run_id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentsCommand {
+    /// List agents
+    List,
+    /// Run an agent and stream its output
+    Run {
+        /// Agent id or name
+        agent: String,
+        /// Project directory to run in
+        #[arg(long, default_value = ".")]
+        project: PathBuf,
+        /// Task to run (defaults to the agent's default task)
+        #[arg(long)]
+        task: Option<String>,
+        /// Model override
+        #[arg(long)]
+        model: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum McpCommand {
+    /// List configured servers
+    List,
+    /// Show a server's configuration
+    Get { name: String },
+    /// Add a server from its JSON configuration
+    AddJson {
+        name: String,
+        json: String,
+        #[arg(long, default_value = "local")]
+        scope: String,
+    },
+    /// Remove a server
+    Remove { name: String },
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(args).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    // Runs once per tool call of a replay, so it stays away from the database
+    if let Command::ReplayStub { stubs } = &args.command {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| e.to_string())?;
+        println!("{}", answer_stub_hook(stubs, &input)?);
+        return Ok(());
+    }
+    if let Command::ApprovalMcp { endpoint, run_id } = &args.command {
+        let token = std::env::var(TOKEN_ENV).map_err(|_| format!("{} is not set", TOKEN_ENV))?;
+        let endpoint = ApprovalEndpoint {
+            url: endpoint.clone(),
+            token,
+        };
+        return serve_stdio(endpoint, *run_id).await;
+    }
+
+    let headless = Headless::open(args.data_dir)?;
+
+    match args.command {
+        Command::Agents(AgentsCommand::List) => {
+            let agents = headless.list_agents()?;
+            if args.json {
+                return print_json(&agents);
+            }
+            for agent in agents {
+                println!(
+                    "{:>4}  {:<24} {}",
+                    agent.id.unwrap_or_default(),
+                    agent.name,
+                    agent.model
+                );
+            }
+        }
+        Command::Agents(AgentsCommand::Run {
+            agent,
+            project,
+            task,
+            model,
+        }) => {
+            let agent = headless.find_agent(&agent)?;
+            let project = project
+                .canonicalize()
+                .map_err(|e| format!("Invalid project directory: {}", e))?;
+            let task = task
+                .or_else(|| agent.default_task.clone())
+                .filter(|task| !task.trim().is_empty())
+                .ok_or("No task given and the agent has no default task")?;
+
+            let json = args.json;
+            let run = headless
+                .run_agent(
+                    &agent,
+                    &project.to_string_lossy(),
+                    &task,
+                    model,
+                    |output| match output {
+                        // Raw stream-json in JSON mode, assistant text otherwise
+                        RunOutput::Stdout(line) if json => println!("{}", line),
+                        RunOutput::Stdout(line) => print_assistant_text(line),
+                        RunOutput::Stderr(line) => eprintln!("{}", line),
+                    },
+                )
+                .await?;
+
+            if !json {
+                println!(
+                    "\nRun {} {} (session {})",
+                    run.id.unwrap_or_default(),
+                    run.status,
+                    run.session_id
+                );
+            }
+            if run.status != "completed" {
+                std::process::exit(1);
+            }
+        }
+        Command::Runs { agent, limit } => {
+            let agent_id = match agent {
+                Some(agent) => headless.find_agent(&agent)?.id,
+                None => None,
+            };
+            let runs = headless.list_runs(agent_id, limit)?;
+            if args.json {
+                return print_json(&runs);
+            }
+            for run in runs {
+                println!(
+                    "{:>5}  {:<10} {:<20} {:<24} {}",
+                    run.id.unwrap_or_default(),
+                    run.status,
+                    run.created_at,
+                    run.agent_name,
+                    run.task.lines().next().unwrap_or_default()
+                );
+            }
+        }
+        Command::Mcp(McpCommand::List) => {
+            let output = headless.mcp(vec!["list".to_string()])?;
+            if args.json {
+                return print_json(&parse_mcp_server_names(output.trim()));
+            }
+            print!("{}", output);
+        }
+        Command::Mcp(McpCommand::Get { name }) => {
+            print!("{}", headless.mcp(vec!["get".to_string(), name])?);
+        }
+        Command::Mcp(McpCommand::AddJson { name, json, scope }) => {
+            print!(
+                "{}",
+                headless.mcp(vec![
+                    "add-json".to_string(),
+                    name,
+                    json,
+                    "-s".to_string(),
+                    scope
+                ])?
+            );
+        }
+        Command::Mcp(McpCommand::Remove { name }) => {
+            print!("{}", headless.mcp(vec!["remove".to_string(), name])?);
+        }
+        Command::Usage { days } => {
+            let stats = serde_json::to_value(get_usage_stats(days)?).map_err(|e| e.to_string())?;
+            if args.json {
+                return print_json(&stats);
+            }
+            println!("Total cost:     ${:.2}", stats["total_cost"].as_f64().unwrap_or(0.0));
+            println!("Total tokens:   {}", stats["total_tokens"].as_u64().unwrap_or(0));
+            println!("Sessions:       {}", stats["total_sessions"].as_u64().unwrap_or(0));
+            if let Some(models) = stats["by_model"].as_array() {
+                println!();
+                for model in models {
+                    println!(
+                        "{:<32} ${:>9.2} {:>12} tokens",
+                        model["model"].as_str().unwrap_or_default(),
+                        model["total_cost"].as_f64().unwrap_or(0.0),
+                        model["total_tokens"].as_u64().unwrap_or(0)
+                    );
+                }
+            }
+        }
+        Command::ReplayStub { .. } | Command::ApprovalMcp { .. } => {
+            unreachable!("handled before opening the database")
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the text blocks of an assistant message from a stream-json line
+fn print_assistant_text(line: &str) {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(line) else {
+        return;
+    };
+    if json["type"] != "assistant" {
+        return;
+    }
+    if let Some(blocks) = json["message"]["content"].as_array() {
+        for block in blocks {
+            if let Some(text) = block["text"].as_str() {
+                println!("{}", text);
+            }
+        }
+        let _ = std::io::stdout().flush();
+    }
+}
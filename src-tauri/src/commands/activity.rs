@@ -0,0 +1,497 @@
+#![allow(dead_code)]
+
+//! A persisted feed of notable events: agent runs starting and finishing, retries,
+//! sessions discovered by the session watcher, MCP server changes. Subsystems call
+//! `record_activity`; the frontend reads the feed with `get_activity` and tracks what
+//! the user has already seen with `mark_activity_seen`.
+
+use rusqlite::{params, Connection, Result as SqliteResult, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::settings::{get_setting_as, set_setting_as};
+use super::webhooks::{RunWebhookPayload, WebhookEvent};
+
+/// Event emitted for every new activity entry
+pub const ACTIVITY_EVENT: &str = "activity:new";
+
+/// app_settings key holding the id of the newest entry the user has seen
+const LAST_SEEN_KEY: &str = "activity_last_seen_id";
+
+/// Entries kept; older ones are dropped as new ones arrive
+const MAX_ACTIVITY_ENTRIES: i64 = 10_000;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// What happened
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    RunStarted,
+    RunCompleted,
+    RunFailed,
+    RunRetryScheduled,
+    RunStalled,
+    RunBudgetExceeded,
+    SessionDiscovered,
+    McpServerAdded,
+    McpServerRemoved,
+    McpCapabilitiesChanged,
+    BudgetThreshold,
+    ScheduledRun,
+    DailyDigest,
+    McpServerLimitExceeded,
+}
+
+impl ActivityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityKind::RunStarted => "run_started",
+            ActivityKind::RunCompleted => "run_completed",
+            ActivityKind::RunFailed => "run_failed",
+            ActivityKind::RunRetryScheduled => "run_retry_scheduled",
+            ActivityKind::RunStalled => "run_stalled",
+            ActivityKind::RunBudgetExceeded => "run_budget_exceeded",
+            ActivityKind::SessionDiscovered => "session_discovered",
+            ActivityKind::McpServerAdded => "mcp_server_added",
+            ActivityKind::McpServerRemoved => "mcp_server_removed",
+            ActivityKind::McpCapabilitiesChanged => "mcp_capabilities_changed",
+            ActivityKind::BudgetThreshold => "budget_threshold",
+            ActivityKind::ScheduledRun => "scheduled_run",
+            ActivityKind::DailyDigest => "daily_digest",
+            ActivityKind::McpServerLimitExceeded => "mcp_server_limit_exceeded",
+        }
+    }
+
+    fn from_str(kind: &str) -> Option<Self> {
+        serde_json::from_value(Value::String(kind.to_string())).ok()
+    }
+}
+
+/// A new entry, before it is stored
+#[derive(Debug, Clone)]
+pub struct NewActivity {
+    pub kind: ActivityKind,
+    pub title: String,
+    pub project_path: Option<String>,
+    pub run_id: Option<i64>,
+    pub session_id: Option<String>,
+    pub detail: Option<Value>,
+}
+
+impl NewActivity {
+    pub fn new(kind: ActivityKind, title: impl Into<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            project_path: None,
+            run_id: None,
+            session_id: None,
+            detail: None,
+        }
+    }
+
+    pub fn project(mut self, project_path: impl Into<String>) -> Self {
+        self.project_path = Some(project_path.into());
+        self
+    }
+
+    pub fn run(mut self, run_id: i64) -> Self {
+        self.run_id = Some(run_id);
+        self
+    }
+
+    pub fn session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn detail(mut self, detail: Value) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+/// A stored feed entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    pub id: i64,
+    pub kind: ActivityKind,
+    pub title: String,
+    pub project_path: Option<String>,
+    pub run_id: Option<i64>,
+    pub session_id: Option<String>,
+    pub detail: Option<Value>,
+    pub created_at: String,
+}
+
+/// Which entries to return; every field narrows the result
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityFilter {
+    pub kinds: Vec<ActivityKind>,
+    pub project_path: Option<String>,
+    /// Inclusive lower bound, `YYYY-MM-DD HH:MM:SS`
+    pub since: Option<String>,
+    /// Only entries the user hasn't seen yet
+    pub unseen_only: bool,
+    /// Case-insensitive match on the title
+    pub query: Option<String>,
+}
+
+/// Keyset pagination: pass the previous page's `next_cursor` as `before_id`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityPagination {
+    pub limit: Option<usize>,
+    pub before_id: Option<i64>,
+}
+
+/// A page of entries, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityPage {
+    pub entries: Vec<ActivityEntry>,
+    /// `before_id` for the next page, if there is one
+    pub next_cursor: Option<i64>,
+    pub unseen_count: i64,
+}
+
+pub fn init_activity_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS activity (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            title TEXT NOT NULL,
+            project_path TEXT,
+            run_id INTEGER,
+            session_id TEXT,
+            detail TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_activity_kind ON activity(kind)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_activity_project_path ON activity(project_path)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Store an entry and return it
+pub fn insert_activity(conn: &Connection, activity: &NewActivity) -> SqliteResult<ActivityEntry> {
+    conn.execute(
+        "INSERT INTO activity (kind, title, project_path, run_id, session_id, detail)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            activity.kind.as_str(),
+            activity.title,
+            activity.project_path,
+            activity.run_id,
+            activity.session_id,
+            activity.detail.as_ref().map(|detail| detail.to_string()),
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM activity WHERE id <= ?1",
+        params![id - MAX_ACTIVITY_ENTRIES],
+    )?;
+    conn.query_row(
+        &format!("SELECT {} FROM activity WHERE id = ?1", ENTRY_COLUMNS),
+        params![id],
+        entry_from_row,
+    )
+}
+
+/// Record an entry through the managed database and announce it to the frontend.
+/// Failures are logged; the feed never gets in the way of the event itself.
+pub fn record_activity(app: &AppHandle, activity: NewActivity) {
+    let Some(db) = app.try_state::<AgentDb>() else {
+        return;
+    };
+    let entry = match db.0.lock() {
+        Ok(conn) => insert_activity(&conn, &activity).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match entry {
+        Ok(entry) => {
+            if let Err(e) = app.emit(ACTIVITY_EVENT, &entry) {
+                log::warn!("Failed to emit activity event: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to record {} activity: {}", activity.kind.as_str(), e),
+    }
+}
+
+/// Record an agent run lifecycle event
+pub fn record_run_event(app: &AppHandle, payload: &RunWebhookPayload) {
+    let (kind, verb) = match payload.event {
+        WebhookEvent::RunStarted => (ActivityKind::RunStarted, "started"),
+        WebhookEvent::RunCompleted => (ActivityKind::RunCompleted, "completed"),
+        WebhookEvent::RunFailed => (ActivityKind::RunFailed, "failed"),
+        WebhookEvent::DailyDigest => return,
+    };
+    record_activity(
+        app,
+        NewActivity::new(kind, format!("{} {}", payload.agent_name, verb))
+            .project(payload.project_path.clone())
+            .run(payload.run_id)
+            .detail(serde_json::json!({
+                "task": payload.task,
+                "status": payload.status,
+                "duration_ms": payload.duration_ms,
+                "cost_usd": payload.cost_usd,
+                "summary": payload.summary,
+            })),
+    );
+}
+
+const ENTRY_COLUMNS: &str = "id, kind, title, project_path, run_id, session_id, detail, created_at";
+
+fn entry_from_row(row: &rusqlite::Row) -> SqliteResult<ActivityEntry> {
+    let kind: String = row.get(1)?;
+    let detail: Option<String> = row.get(6)?;
+    Ok(ActivityEntry {
+        id: row.get(0)?,
+        kind: ActivityKind::from_str(&kind).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                1,
+                rusqlite::types::Type::Text,
+                format!("unknown activity kind {}", kind).into(),
+            )
+        })?,
+        title: row.get(2)?,
+        project_path: row.get(3)?,
+        run_id: row.get(4)?,
+        session_id: row.get(5)?,
+        detail: detail.and_then(|detail| serde_json::from_str(&detail).ok()),
+        created_at: row.get(7)?,
+    })
+}
+
+fn last_seen_id(conn: &Connection) -> i64 {
+    get_setting_as(conn, LAST_SEEN_KEY).unwrap_or(0)
+}
+
+/// Query a page of the feed
+pub fn query_activity(
+    conn: &Connection,
+    filter: &ActivityFilter,
+    pagination: &ActivityPagination,
+) -> SqliteResult<ActivityPage> {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if !filter.kinds.is_empty() {
+        let placeholders = vec!["?"; filter.kinds.len()].join(", ");
+        conditions.push(format!("kind IN ({})", placeholders));
+        for kind in &filter.kinds {
+            values.push(Box::new(kind.as_str()));
+        }
+    }
+    if let Some(project_path) = &filter.project_path {
+        conditions.push("project_path = ?".to_string());
+        values.push(Box::new(project_path.clone()));
+    }
+    if let Some(since) = &filter.since {
+        conditions.push("created_at >= ?".to_string());
+        values.push(Box::new(since.clone()));
+    }
+    if filter.unseen_only {
+        conditions.push("id > ?".to_string());
+        values.push(Box::new(last_seen_id(conn)));
+    }
+    if let Some(query) = filter.query.as_ref().filter(|q| !q.trim().is_empty()) {
+        conditions.push("title LIKE ? ESCAPE '\\'".to_string());
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        values.push(Box::new(format!("%{}%", escaped)));
+    }
+    if let Some(before_id) = pagination.before_id {
+        conditions.push("id < ?".to_string());
+        values.push(Box::new(before_id));
+    }
+
+    let limit = pagination
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    // Fetch one extra row to know whether another page exists
+    let sql = format!(
+        "SELECT {} FROM activity {} ORDER BY id DESC LIMIT {}",
+        ENTRY_COLUMNS,
+        where_clause,
+        limit + 1
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let params: Vec<&dyn ToSql> = values.iter().map(|value| value.as_ref()).collect();
+    let mut entries = stmt
+        .query_map(params.as_slice(), entry_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let next_cursor = if entries.len() > limit {
+        entries.truncate(limit);
+        entries.last().map(|entry| entry.id)
+    } else {
+        None
+    };
+    let unseen_count = conn.query_row(
+        "SELECT COUNT(*) FROM activity WHERE id > ?1",
+        params![last_seen_id(conn)],
+        |row| row.get(0),
+    )?;
+
+    Ok(ActivityPage {
+        entries,
+        next_cursor,
+        unseen_count,
+    })
+}
+
+/// Read the activity feed, newest first
+#[tauri::command]
+pub async fn get_activity(
+    db: State<'_, AgentDb>,
+    filter: Option<ActivityFilter>,
+    pagination: Option<ActivityPagination>,
+) -> Result<ActivityPage, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_activity(
+        &conn,
+        &filter.unwrap_or_default(),
+        &pagination.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Mark everything up to `id` (or the newest entry) as seen
+#[tauri::command]
+pub async fn mark_activity_seen(db: State<'_, AgentDb>, id: Option<i64>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let id = match id {
+        Some(id) => id,
+        None => conn
+            .query_row("SELECT COALESCE(MAX(id), 0) FROM activity", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?,
+    };
+    // Never move the marker backwards
+    if id > last_seen_id(&conn) {
+        set_setting_as(&conn, LAST_SEEN_KEY, &id)?;
+    }
+    Ok(())
+}
+
+/// Delete all activity
+#[tauri::command]
+pub async fn clear_activity(db: State<'_, AgentDb>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM activity", [])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_activity_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_query_filters_and_paginates() {
+        let conn = conn();
+        for i in 0..5 {
+            insert_activity(
+                &conn,
+                &NewActivity::new(ActivityKind::RunCompleted, format!("Run {} completed", i))
+                    .project("/work/app")
+                    .run(i),
+            )
+            .unwrap();
+        }
+        insert_activity(
+            &conn,
+            &NewActivity::new(ActivityKind::SessionDiscovered, "New session")
+                .session("abc")
+                .detail(serde_json::json!({"external": true})),
+        )
+        .unwrap();
+
+        let filter = ActivityFilter {
+            kinds: vec![ActivityKind::RunCompleted],
+            ..Default::default()
+        };
+        let page = query_activity(
+            &conn,
+            &filter,
+            &ActivityPagination {
+                limit: Some(3),
+                before_id: None,
+            },
+        )
+        .unwrap();
+        let runs: Vec<_> = page.entries.iter().map(|e| e.run_id.unwrap()).collect();
+        assert_eq!(runs, vec![4, 3, 2]);
+        assert_eq!(page.unseen_count, 6);
+
+        let next = query_activity(
+            &conn,
+            &filter,
+            &ActivityPagination {
+                limit: Some(3),
+                before_id: page.next_cursor,
+            },
+        )
+        .unwrap();
+        assert_eq!(next.entries.len(), 2);
+        assert_eq!(next.next_cursor, None);
+
+        let search = ActivityFilter {
+            query: Some("session".to_string()),
+            ..Default::default()
+        };
+        let found = query_activity(&conn, &search, &ActivityPagination::default()).unwrap();
+        assert_eq!(found.entries.len(), 1);
+        assert_eq!(found.entries[0].detail, Some(serde_json::json!({"external": true})));
+    }
+
+    #[test]
+    fn test_unseen_tracks_last_seen_id() {
+        let conn = conn();
+        let first = insert_activity(&conn, &NewActivity::new(ActivityKind::RunStarted, "a")).unwrap();
+        insert_activity(&conn, &NewActivity::new(ActivityKind::RunFailed, "b")).unwrap();
+        set_setting_as(&conn, LAST_SEEN_KEY, &first.id).unwrap();
+
+        let unseen = ActivityFilter {
+            unseen_only: true,
+            ..Default::default()
+        };
+        let page = query_activity(&conn, &unseen, &ActivityPagination::default()).unwrap();
+        assert_eq!(page.unseen_count, 1);
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].kind, ActivityKind::RunFailed);
+    }
+}
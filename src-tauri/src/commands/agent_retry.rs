@@ -0,0 +1,406 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::agents::{agent_run_from_row, AgentDb, AgentRun, AGENT_RUN_COLUMNS};
+
+/// Upper bound for attempts so a misconfigured policy can't loop forever
+const MAX_ALLOWED_ATTEMPTS: u32 = 10;
+
+/// Upper bound for a single backoff delay (1 hour)
+const MAX_ALLOWED_BACKOFF_MS: u64 = 60 * 60 * 1000;
+
+/// Classification of why an agent run failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The API rejected the request because of rate limiting (HTTP 429)
+    RateLimit,
+    /// The API was overloaded (HTTP 529)
+    Overloaded,
+    /// The process exited with a non-zero status for any other reason
+    NonZeroExit,
+    /// The process produced no output before the startup timeout
+    NoOutput,
+}
+
+impl FailureKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::RateLimit => "rate_limit",
+            FailureKind::Overloaded => "overloaded",
+            FailureKind::NonZeroExit => "non_zero_exit",
+            FailureKind::NoOutput => "no_output",
+        }
+    }
+}
+
+/// Per-agent retry policy applied by the execution pipeline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first run (1 disables retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff_ms: u64,
+    /// Factor applied to the delay after every attempt
+    pub backoff_multiplier: f64,
+    /// Cap for the computed delay
+    pub max_backoff_ms: u64,
+    /// Failure kinds that trigger a retry
+    pub retry_on: Vec<FailureKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff_ms: 30_000,
+            backoff_multiplier: 2.0,
+            max_backoff_ms: 10 * 60 * 1000,
+            retry_on: vec![FailureKind::RateLimit, FailureKind::Overloaded],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Validate the policy before persisting it
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 || self.max_attempts > MAX_ALLOWED_ATTEMPTS {
+            return Err(format!(
+                "max_attempts must be between 1 and {}",
+                MAX_ALLOWED_ATTEMPTS
+            ));
+        }
+        if self.backoff_multiplier < 1.0 || !self.backoff_multiplier.is_finite() {
+            return Err("backoff_multiplier must be at least 1.0".to_string());
+        }
+        if self.max_backoff_ms > MAX_ALLOWED_BACKOFF_MS {
+            return Err(format!(
+                "max_backoff_ms cannot exceed {} ms",
+                MAX_ALLOWED_BACKOFF_MS
+            ));
+        }
+        if self.initial_backoff_ms > self.max_backoff_ms {
+            return Err("initial_backoff_ms cannot exceed max_backoff_ms".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a run that failed with `failure` on attempt `attempt` should be retried
+    pub fn should_retry(&self, failure: FailureKind, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&failure)
+    }
+
+    /// Delay before starting the attempt that follows `attempt`
+    pub fn delay_after_attempt(&self, attempt: u32) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let delay = self.initial_backoff_ms as f64 * self.backoff_multiplier.powi(exponent);
+        let delay = delay.min(self.max_backoff_ms as f64) as u64;
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+/// Accumulates signals from a run's output so the failure can be classified on exit
+#[derive(Debug, Default)]
+pub struct FailureClassifier {
+    saw_rate_limit: bool,
+    saw_overload: bool,
+    result_is_error: bool,
+}
+
+/// Whether lowercased text reports HTTP status `code` as a whole number right after "error",
+/// "status" or "http", as in `API Error: 429` or `"status": 429`. A bare number could be a
+/// port, a line number or a byte count.
+fn reports_status(lower: &str, code: &str) -> bool {
+    lower.match_indices(code).any(|(at, _)| {
+        let before = &lower[..at];
+        let after = &lower[at + code.len()..];
+        let bounded = !before.ends_with(|c: char| c.is_ascii_alphanumeric())
+            && !after.starts_with(|c: char| c.is_ascii_alphanumeric());
+        let context: String = {
+            let mut tail: Vec<char> = before.chars().rev().take(16).collect();
+            tail.reverse();
+            tail.into_iter().collect()
+        };
+        bounded
+            && ["error", "status", "http"]
+                .iter()
+                .any(|word| context.contains(word))
+    })
+}
+
+/// Whether lowercased error text describes a rate limit
+pub(crate) fn mentions_rate_limit(lower: &str) -> bool {
+    lower.contains("rate_limit_error")
+        || lower.contains("too many requests")
+        || lower.contains("rate limit exceeded")
+        || lower.contains("rate limit reached")
+        || reports_status(lower, "429")
+}
+
+/// Whether lowercased error text describes an overloaded API
+pub(crate) fn mentions_overload(lower: &str) -> bool {
+    lower.contains("overloaded_error")
+        || lower.contains("error: overloaded")
+        || reports_status(lower, "529")
+}
+
+impl FailureClassifier {
+    /// Inspect a stdout line (stream-json) for error signals
+    pub fn observe_stdout(&mut self, line: &str) {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+            if json.get("type").and_then(|t| t.as_str()) == Some("result")
+                && json.get("is_error").and_then(|e| e.as_bool()) == Some(true)
+            {
+                self.result_is_error = true;
+                if let Some(result) = json.get("result").and_then(|r| r.as_str()) {
+                    self.scan_text(result);
+                }
+            }
+            if json.get("type").and_then(|t| t.as_str()) == Some("error") {
+                self.scan_text(line);
+            }
+        }
+    }
+
+    /// Inspect a stderr line for error signals
+    pub fn observe_stderr(&mut self, line: &str) {
+        self.scan_text(line);
+    }
+
+    fn scan_text(&mut self, text: &str) {
+        let lower = text.to_lowercase();
+        if mentions_rate_limit(&lower) {
+            self.saw_rate_limit = true;
+        }
+        if mentions_overload(&lower) {
+            self.saw_overload = true;
+        }
+    }
+
+    /// Classify the run outcome; `None` means the run succeeded
+    pub fn classify(&self, exit_code: Option<i32>) -> Option<FailureKind> {
+        let exited_cleanly = matches!(exit_code, Some(0) | None);
+        if exited_cleanly && !self.result_is_error {
+            return None;
+        }
+        if self.saw_rate_limit {
+            Some(FailureKind::RateLimit)
+        } else if self.saw_overload {
+            Some(FailureKind::Overloaded)
+        } else {
+            Some(FailureKind::NonZeroExit)
+        }
+    }
+}
+
+/// Create the retry policy table
+pub fn init_retry_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_retry_policies (
+            agent_id INTEGER PRIMARY KEY,
+            max_attempts INTEGER NOT NULL DEFAULT 1,
+            initial_backoff_ms INTEGER NOT NULL DEFAULT 30000,
+            backoff_multiplier REAL NOT NULL DEFAULT 2.0,
+            max_backoff_ms INTEGER NOT NULL DEFAULT 600000,
+            retry_on TEXT NOT NULL DEFAULT '[]',
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Load the retry policy for an agent, falling back to the default (no retries)
+pub fn load_retry_policy(conn: &Connection, agent_id: i64) -> SqliteResult<RetryPolicy> {
+    let policy = conn
+        .query_row(
+            "SELECT max_attempts, initial_backoff_ms, backoff_multiplier, max_backoff_ms, retry_on
+             FROM agent_retry_policies WHERE agent_id = ?1",
+            params![agent_id],
+            |row| {
+                let retry_on: String = row.get(4)?;
+                Ok(RetryPolicy {
+                    max_attempts: row.get(0)?,
+                    initial_backoff_ms: row.get::<_, i64>(1)? as u64,
+                    backoff_multiplier: row.get(2)?,
+                    max_backoff_ms: row.get::<_, i64>(3)? as u64,
+                    retry_on: serde_json::from_str(&retry_on).unwrap_or_default(),
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(policy.unwrap_or_default())
+}
+
+/// Persist an agent's retry policy
+pub fn save_retry_policy(conn: &Connection, agent_id: i64, policy: &RetryPolicy) -> Result<(), String> {
+    let retry_on = serde_json::to_string(&policy.retry_on)
+        .map_err(|e| format!("Failed to serialize retry conditions: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO agent_retry_policies (agent_id, max_attempts, initial_backoff_ms, backoff_multiplier, max_backoff_ms, retry_on)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(agent_id) DO UPDATE SET
+            max_attempts = ?2, initial_backoff_ms = ?3, backoff_multiplier = ?4,
+            max_backoff_ms = ?5, retry_on = ?6, updated_at = CURRENT_TIMESTAMP",
+        params![
+            agent_id,
+            policy.max_attempts,
+            policy.initial_backoff_ms as i64,
+            policy.backoff_multiplier,
+            policy.max_backoff_ms as i64,
+            retry_on
+        ],
+    )
+    .map_err(|e| format!("Failed to save retry policy: {}", e))?;
+    Ok(())
+}
+
+/// Get the retry policy configured for an agent
+#[tauri::command]
+pub async fn get_agent_retry_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<RetryPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_retry_policy(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Set the retry policy for an agent
+#[tauri::command]
+pub async fn set_agent_retry_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    policy: RetryPolicy,
+) -> Result<RetryPolicy, String> {
+    policy.validate()?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_retry_policy(&conn, agent_id, &policy)?;
+
+    Ok(policy)
+}
+
+/// Get every attempt belonging to the same retry chain as `run_id`, oldest first
+#[tauri::command]
+pub async fn get_agent_run_retry_chain(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<AgentRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let root_id: i64 = conn
+        .query_row(
+            "SELECT COALESCE(parent_run_id, id) FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE id = ?1 OR parent_run_id = ?1 ORDER BY attempt ASC, id ASC",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let runs = stmt
+        .query_map(params![root_id], agent_run_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_success() {
+        let classifier = FailureClassifier::default();
+        assert_eq!(classifier.classify(Some(0)), None);
+    }
+
+    #[test]
+    fn test_classify_rate_limit_from_stderr() {
+        let mut classifier = FailureClassifier::default();
+        classifier.observe_stderr("API Error: 429 {\"type\":\"rate_limit_error\"}");
+        assert_eq!(classifier.classify(Some(1)), Some(FailureKind::RateLimit));
+    }
+
+    #[test]
+    fn test_numbers_in_ordinary_errors_are_not_rate_limits() {
+        let mut classifier = FailureClassifier::default();
+        classifier.observe_stderr("Error: connect ECONNREFUSED 127.0.0.1:4290");
+        classifier.observe_stderr("    at main (src/index.ts:429:15)");
+        classifier.observe_stderr("wrote 529 bytes to out.json");
+        classifier.observe_stderr("request 5291 took 429ms");
+        assert_eq!(classifier.classify(Some(1)), Some(FailureKind::NonZeroExit));
+
+        let mut classifier = FailureClassifier::default();
+        classifier.observe_stderr(r#"{"status": 529, "message": "busy"}"#);
+        assert_eq!(classifier.classify(Some(1)), Some(FailureKind::Overloaded));
+    }
+
+    #[test]
+    fn test_classify_error_result_with_clean_exit() {
+        let mut classifier = FailureClassifier::default();
+        classifier.observe_stdout(
+            r#"{"type":"result","subtype":"error_during_execution","is_error":true,"result":"API Error: Overloaded"}"#,
+        );
+        assert_eq!(classifier.classify(Some(0)), Some(FailureKind::Overloaded));
+    }
+
+    #[test]
+    fn test_classify_plain_non_zero_exit() {
+        let mut classifier = FailureClassifier::default();
+        classifier.observe_stdout(r#"{"type":"assistant","message":{}}"#);
+        assert_eq!(classifier.classify(Some(2)), Some(FailureKind::NonZeroExit));
+    }
+
+    #[test]
+    fn test_should_retry_respects_attempts_and_conditions() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            ..RetryPolicy::default()
+        };
+        assert!(policy.should_retry(FailureKind::RateLimit, 1));
+        assert!(policy.should_retry(FailureKind::RateLimit, 2));
+        assert!(!policy.should_retry(FailureKind::RateLimit, 3));
+        assert!(!policy.should_retry(FailureKind::NonZeroExit, 1));
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff_ms: 1_000,
+            backoff_multiplier: 3.0,
+            max_backoff_ms: 5_000,
+            retry_on: vec![FailureKind::NonZeroExit],
+        };
+        assert_eq!(policy.delay_after_attempt(1).as_millis(), 1_000);
+        assert_eq!(policy.delay_after_attempt(2).as_millis(), 3_000);
+        assert_eq!(policy.delay_after_attempt(3).as_millis(), 5_000);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_policies() {
+        let mut policy = RetryPolicy::default();
+        policy.max_attempts = 0;
+        assert!(policy.validate().is_err());
+
+        let mut policy = RetryPolicy::default();
+        policy.backoff_multiplier = 0.5;
+        assert!(policy.validate().is_err());
+
+        assert!(RetryPolicy::default().validate().is_ok());
+    }
+}
@@ -5,7 +5,7 @@ use chrono;
 use dirs;
 use log::{debug, error, info, warn};
 use reqwest;
-use rusqlite::{params, Connection, Result as SqliteResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::io::{BufRead, BufReader};
@@ -13,9 +13,42 @@ use std::process::Stdio;
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager, State};
 // Sidecar support removed; using system binary execution only
+use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader as TokioBufReader;
 use tokio::process::Command;
 
+use super::agent_retry::{FailureClassifier, FailureKind};
+use super::artifacts::{artifacts_root_for_db, collect_run_artifacts};
+use super::db_maintenance::{
+    add_column_if_missing, backup_before_migration, mark_schema_current, migration_error,
+};
+use super::error::OpcodeError;
+use super::event_broker::{publish, Channel};
+use super::file_changes::{save_run_file_changes, FileChangeTracker};
+use super::model_policy::{load_model_policy, served_model_from_line, ModelPolicy};
+use super::thinking::RunThinking;
+use super::tool_rules::{known_mcp_tools, ToolRules};
+use super::verbosity::RunVerbosity;
+use super::notifications::{notify, run_outcome_body, NotificationEvent};
+use super::permission_relay::PermissionRelayState;
+use super::rollback::create_pre_run_checkpoint;
+use super::run_guards::RunGuards;
+use super::sandbox::{
+    load_agent_sandbox_profile, record_violation, SandboxScratch, SandboxViolationDetector,
+};
+use super::webhooks::{dispatch_run_event, summarize_stream_output, RunWebhookPayload, WebhookEvent};
+use crate::process::{OutputStream, StreamLine};
+
+/// How long a run may take to print its first line before it is stopped
+const FIRST_OUTPUT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// First-output timeout of container runs, which have to start the container first
+const CONTAINER_FIRST_OUTPUT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long ssh may take to connect and start the remote shell. A remote run's first-output
+/// timeout only starts once it has.
+const REMOTE_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Finds the full path to the claude binary
 /// This is necessary because macOS apps have a limited PATH environment
 fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
@@ -45,14 +78,20 @@ fn create_performance_indexes(conn: &Connection) -> SqliteResult<()> {
 /// Migrate existing agent_runs table columns
 fn migrate_agent_runs_table(conn: &Connection) -> SqliteResult<()> {
     let migrations = [
-        "ALTER TABLE agent_runs ADD COLUMN session_id TEXT",
-        "ALTER TABLE agent_runs ADD COLUMN status TEXT DEFAULT 'pending'",
-        "ALTER TABLE agent_runs ADD COLUMN pid INTEGER",
-        "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
+        ("session_id", "TEXT"),
+        ("status", "TEXT DEFAULT 'pending'"),
+        ("pid", "INTEGER"),
+        ("process_started_at", "TEXT"),
+        ("parent_run_id", "INTEGER"),
+        ("attempt", "INTEGER DEFAULT 1"),
+        ("served_model", "TEXT"),
+        ("verbosity", "TEXT"),
+        ("provider_profile_id", "INTEGER"),
+        ("guards", "TEXT"),
     ];
 
-    for migration in &migrations {
-        let _ = conn.execute(migration, []);
+    for (column, definition) in &migrations {
+        add_column_if_missing(conn, "agent_runs", column, definition)?;
     }
 
     // Update existing records
@@ -60,6 +99,7 @@ fn migrate_agent_runs_table(conn: &Connection) -> SqliteResult<()> {
     conn.execute("UPDATE agent_runs SET status = 'completed' WHERE status IS NULL AND completed_at IS NOT NULL", [])?;
     conn.execute("UPDATE agent_runs SET status = 'failed' WHERE status IS NULL AND completed_at IS NOT NULL AND session_id = ''", [])?;
     conn.execute("UPDATE agent_runs SET status = 'pending' WHERE status IS NULL", [])?;
+    conn.execute("UPDATE agent_runs SET attempt = 1 WHERE attempt IS NULL", [])?;
 
     Ok(())
 }
@@ -79,6 +119,10 @@ pub struct Agent {
     pub hooks: Option<String>, // JSON string of hooks configuration
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub thinking: Option<RunThinking>, // Extended thinking requested for the agent's runs
+    #[serde(default)]
+    pub tool_rules: Option<ToolRules>, // Allowed and disallowed tools for the agent's runs
 }
 
 /// Represents an agent execution run
@@ -97,6 +141,68 @@ pub struct AgentRun {
     pub process_started_at: Option<String>,
     pub created_at: String,
     pub completed_at: Option<String>,
+    pub parent_run_id: Option<i64>, // Original run this attempt retries, if any
+    pub attempt: i64,               // 1 for the original run, incremented per retry
+    pub served_model: Option<String>, // Model reported by the CLI as having served the run
+    #[serde(default)]
+    pub verbosity: Option<RunVerbosity>, // Diagnostics requested for the run, if any
+}
+
+/// Columns selected for an `Agent`, in the order expected by `agent_from_row`
+pub(crate) const AGENT_COLUMNS: &str = "id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at, thinking, tool_rules";
+
+/// Map a row selected with `AGENT_COLUMNS` into an `Agent`
+pub(crate) fn agent_from_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+    Ok(Agent {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        icon: row.get(2)?,
+        system_prompt: row.get(3)?,
+        default_task: row.get(4)?,
+        model: row
+            .get::<_, String>(5)
+            .unwrap_or_else(|_| "sonnet".to_string()),
+        enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
+        enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
+        enable_network: row.get::<_, bool>(8).unwrap_or(false),
+        hooks: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        thinking: RunThinking::from_column(row.get(12)?),
+        tool_rules: ToolRules::from_column(row.get(13)?),
+    })
+}
+
+/// Columns selected for an `AgentRun`, in the order expected by `agent_run_from_row`
+pub(crate) const AGENT_RUN_COLUMNS: &str = "id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, parent_run_id, attempt, served_model, verbosity";
+
+/// Map a row selected with `AGENT_RUN_COLUMNS` into an `AgentRun`
+pub(crate) fn agent_run_from_row(row: &rusqlite::Row) -> rusqlite::Result<AgentRun> {
+    Ok(AgentRun {
+        id: Some(row.get(0)?),
+        agent_id: row.get(1)?,
+        agent_name: row.get(2)?,
+        agent_icon: row.get(3)?,
+        task: row.get(4)?,
+        model: row.get(5)?,
+        project_path: row.get(6)?,
+        session_id: row.get(7)?,
+        status: row
+            .get::<_, String>(8)
+            .unwrap_or_else(|_| "pending".to_string()),
+        pid: row
+            .get::<_, Option<i64>>(9)
+            .ok()
+            .flatten()
+            .map(|p| p as u32),
+        process_started_at: row.get(10)?,
+        created_at: row.get(11)?,
+        completed_at: row.get(12)?,
+        parent_run_id: row.get(13)?,
+        attempt: row.get::<_, Option<i64>>(14)?.unwrap_or(1),
+        served_model: row.get(15)?,
+        verbosity: RunVerbosity::from_column(row.get(16)?),
+    })
 }
 
 /// Represents runtime metrics calculated from JSONL
@@ -257,8 +363,8 @@ pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
     }
 }
 
-/// Initialize the agents database
-pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
+/// Initialize the agents database, backing it up first when its schema is out of date
+pub fn init_database(app: &AppHandle) -> Result<Connection, String> {
     let app_dir = app
         .path()
         .app_data_dir()
@@ -266,6 +372,17 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
     std::fs::create_dir_all(&app_dir).expect("Failed to create app data dir");
 
     let db_path = app_dir.join("agents.db");
+    super::encryption::unseal_database(&db_path)?;
+    let backup = backup_before_migration(&db_path)?;
+    let conn =
+        open_database(&db_path).map_err(|e| migration_error(&e, &db_path, backup.as_ref()))?;
+    super::session_index::set_store_path(&db_path);
+    Ok(conn)
+}
+
+/// Open the agents database at `db_path`, creating and migrating tables as needed.
+/// Shared by the app and the headless CLI.
+pub fn open_database(db_path: &std::path::Path) -> SqliteResult<Connection> {
     let conn = Connection::open(db_path)?;
 
     // Create agents table
@@ -282,30 +399,22 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             enable_network BOOLEAN NOT NULL DEFAULT 0,
             hooks TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            thinking TEXT,
+            tool_rules TEXT
         )",
         [],
     )?;
 
     // Add columns to existing table if they don't exist
-    let _ = conn.execute("ALTER TABLE agents ADD COLUMN default_task TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN model TEXT DEFAULT 'sonnet'",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE agents ADD COLUMN hooks TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN enable_file_read BOOLEAN DEFAULT 1",
-        [],
-    );
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN enable_file_write BOOLEAN DEFAULT 1",
-        [],
-    );
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN enable_network BOOLEAN DEFAULT 0",
-        [],
-    );
+    add_column_if_missing(&conn, "agents", "default_task", "TEXT")?;
+    add_column_if_missing(&conn, "agents", "model", "TEXT DEFAULT 'sonnet'")?;
+    add_column_if_missing(&conn, "agents", "hooks", "TEXT")?;
+    add_column_if_missing(&conn, "agents", "enable_file_read", "BOOLEAN DEFAULT 1")?;
+    add_column_if_missing(&conn, "agents", "enable_file_write", "BOOLEAN DEFAULT 1")?;
+    add_column_if_missing(&conn, "agents", "enable_network", "BOOLEAN DEFAULT 0")?;
+    add_column_if_missing(&conn, "agents", "thinking", "TEXT")?;
+    add_column_if_missing(&conn, "agents", "tool_rules", "TEXT")?;
 
     // Create agent_runs table
     conn.execute(
@@ -323,6 +432,12 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             process_started_at TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             completed_at TEXT,
+            parent_run_id INTEGER,
+            attempt INTEGER NOT NULL DEFAULT 1,
+            served_model TEXT,
+            verbosity TEXT,
+            provider_profile_id INTEGER,
+            guards TEXT,
             FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
         )",
         [],
@@ -358,6 +473,99 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Create retry policy table
+    super::agent_retry::init_retry_tables(&conn)?;
+
+    // Create watchdog policy table
+    super::run_watchdog::init_watchdog_tables(&conn)?;
+
+    // Create tool approval settings table
+    super::approval_mcp::init_approval_tables(&conn)?;
+
+    // Create offline cache and MCP operation queue tables
+    super::offline::init_offline_tables(&conn)?;
+
+    // Create webhook tables
+    super::webhooks::init_webhook_tables(&conn)?;
+
+    // Create file change manifest table
+    super::file_changes::init_file_change_tables(&conn)?;
+
+    // Create sandbox profile tables
+    super::sandbox::init_sandbox_tables(&conn)?;
+
+    // Create pre-run checkpoint and rollback manifest tables
+    super::rollback::init_rollback_tables(&conn)?;
+
+    // Create model fallback policy table
+    super::model_policy::init_model_policy_tables(&conn)?;
+
+    // Create workspace tables
+    super::workspaces::init_workspace_tables(&conn)?;
+
+    // Create recently used projects table
+    super::recent_projects::init_recent_project_tables(&conn)?;
+
+    // Create activity feed table
+    super::activity::init_activity_tables(&conn)?;
+
+    // Create prompt template table
+    super::prompt_templates::init_prompt_template_tables(&conn)?;
+
+    // Create MCP capability report table
+    super::mcp_capabilities::init_mcp_capability_tables(&conn)?;
+
+    // Create session insight table
+    super::session_insights::init_session_insight_tables(&conn)?;
+
+    // Create local usage counter table
+    super::telemetry::init_telemetry_tables(&conn)?;
+
+    // Create session transcript index tables
+    super::session_index::init_session_index_tables(&conn)?;
+
+    // Create experiment tables
+    super::experiments::init_experiment_tables(&conn)?;
+
+    // Create run artifact tables
+    super::artifacts::init_artifact_tables(&conn)?;
+
+    // Create worktree tables
+    super::worktrees::init_worktree_tables(&conn)?;
+
+    // Create parallel batch tables
+    super::batches::init_batch_tables(&conn)?;
+
+    // Create run queue tables
+    super::run_queue::init_run_queue_tables(&conn)?;
+
+    // Create model price tables
+    super::pricing::init_pricing_tables(&conn)?;
+
+    // Create imported usage history tables
+    super::usage_backfill::init_usage_backfill_tables(&conn)?;
+
+    // Create MCP stack tables
+    super::mcp_stacks::init_mcp_stack_tables(&conn)?;
+
+    // Create provider profile tables
+    super::provider_profiles::init_provider_profile_tables(&conn)?;
+
+    // Create daily digest table
+    super::digests::init_digest_tables(&conn)?;
+
+    // Create MCP server limit and violation tables
+    super::mcp_guardrails::init_mcp_guardrail_tables(&conn)?;
+
+    // Create SSH remote host tables
+    super::remote_hosts::init_remote_host_tables(&conn)?;
+
+    // Create Docker execution tables
+    super::containers::init_container_tables(&conn)?;
+
+    // Create SSH tunnel tables
+    super::ssh_tunnels::init_tunnel_tables(&conn)?;
+
     // Create settings table for app-wide settings
     conn.execute(
         "CREATE TABLE IF NOT EXISTS app_settings (
@@ -380,6 +588,11 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Bring stored settings up to the current schema
+    super::settings::migrate_settings(&conn)?;
+
+    mark_schema_current(&conn)?;
+
     Ok(conn)
 }
 
@@ -389,28 +602,14 @@ pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare("SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents ORDER BY created_at DESC")
+        .prepare(&format!(
+            "SELECT {} FROM agents ORDER BY created_at DESC",
+            AGENT_COLUMNS
+        ))
         .map_err(|e| e.to_string())?;
 
     let agents = stmt
-        .query_map([], |row| {
-            Ok(Agent {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                icon: row.get(2)?,
-                system_prompt: row.get(3)?,
-                default_task: row.get(4)?,
-                model: row
-                    .get::<_, String>(5)
-                    .unwrap_or_else(|_| "sonnet".to_string()),
-                enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                hooks: row.get(9)?,
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
-            })
-        })
+        .query_map([], agent_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -431,16 +630,26 @@ pub async fn create_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    thinking: Option<RunThinking>,
+    tool_rules: Option<ToolRules>,
 ) -> Result<Agent, String> {
+    if let Some(thinking) = &thinking {
+        thinking.validate()?;
+    }
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(tool_rules) = &tool_rules {
+        tool_rules.validate(&known_mcp_tools(&conn))?;
+    }
     let model = model.unwrap_or_else(|| "sonnet".to_string());
     let enable_file_read = enable_file_read.unwrap_or(true);
     let enable_file_write = enable_file_write.unwrap_or(true);
     let enable_network = enable_network.unwrap_or(false);
+    let thinking = thinking.and_then(|thinking| thinking.to_column());
+    let tool_rules = tool_rules.and_then(|tool_rules| tool_rules.to_column());
 
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks],
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, thinking, tool_rules) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, thinking, tool_rules],
     )
     .map_err(|e| e.to_string())?;
 
@@ -449,24 +658,9 @@ pub async fn create_agent(
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -487,13 +681,21 @@ pub async fn update_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    thinking: Option<RunThinking>,
+    tool_rules: Option<ToolRules>,
 ) -> Result<Agent, String> {
+    if let Some(thinking) = &thinking {
+        thinking.validate()?;
+    }
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(tool_rules) = &tool_rules {
+        tool_rules.validate(&known_mcp_tools(&conn))?;
+    }
     let model = model.unwrap_or_else(|| "sonnet".to_string());
 
     // Build dynamic query based on provided parameters
     let mut query =
-        "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, hooks = ?6"
+        "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, hooks = ?6, thinking = ?7, tool_rules = ?8"
             .to_string();
     let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![
         Box::new(name),
@@ -502,8 +704,10 @@ pub async fn update_agent(
         Box::new(default_task),
         Box::new(model),
         Box::new(hooks),
+        Box::new(thinking.and_then(|thinking| thinking.to_column())),
+        Box::new(tool_rules.and_then(|tool_rules| tool_rules.to_column())),
     ];
-    let mut param_count = 6;
+    let mut param_count = 8;
 
     if let Some(efr) = enable_file_read {
         param_count += 1;
@@ -534,24 +738,9 @@ pub async fn update_agent(
     // Fetch the updated agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -576,24 +765,9 @@ pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String>
 
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get::<_, String>(5).unwrap_or_else(|_| "sonnet".to_string()),
-                    enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                    enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                    enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -609,43 +783,23 @@ pub async fn list_agent_runs(
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let query = if agent_id.is_some() {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC"
+        format!(
+            "SELECT {} FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC",
+            AGENT_RUN_COLUMNS
+        )
     } else {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs ORDER BY created_at DESC"
+        format!(
+            "SELECT {} FROM agent_runs ORDER BY created_at DESC",
+            AGENT_RUN_COLUMNS
+        )
     };
 
-    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
-
-    let run_mapper = |row: &rusqlite::Row| -> rusqlite::Result<AgentRun> {
-        Ok(AgentRun {
-            id: Some(row.get(0)?),
-            agent_id: row.get(1)?,
-            agent_name: row.get(2)?,
-            agent_icon: row.get(3)?,
-            task: row.get(4)?,
-            model: row.get(5)?,
-            project_path: row.get(6)?,
-            session_id: row.get(7)?,
-            status: row
-                .get::<_, String>(8)
-                .unwrap_or_else(|_| "pending".to_string()),
-            pid: row
-                .get::<_, Option<i64>>(9)
-                .ok()
-                .flatten()
-                .map(|p| p as u32),
-            process_started_at: row.get(10)?,
-            created_at: row.get(11)?,
-            completed_at: row.get(12)?,
-        })
-    };
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
     let runs = if let Some(aid) = agent_id {
-        stmt.query_map(params![aid], run_mapper)
+        stmt.query_map(params![aid], agent_run_from_row)
     } else {
-        stmt.query_map(params![], run_mapper)
+        stmt.query_map(params![], agent_run_from_row)
     }
     .map_err(|e| e.to_string())?
     .collect::<Result<Vec<_>, _>>()
@@ -661,26 +815,9 @@ pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun,
 
     let run = conn
         .query_row(
-            "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-             FROM agent_runs WHERE id = ?1",
+            &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
             params![id],
-            |row| {
-                Ok(AgentRun {
-                    id: Some(row.get(0)?),
-                    agent_id: row.get(1)?,
-                    agent_name: row.get(2)?,
-                    agent_icon: row.get(3)?,
-                    task: row.get(4)?,
-                    model: row.get(5)?,
-                    project_path: row.get(6)?,
-                    session_id: row.get(7)?,
-                    status: row.get::<_, String>(8).unwrap_or_else(|_| "pending".to_string()),
-                    pid: row.get::<_, Option<i64>>(9).ok().flatten().map(|p| p as u32),
-                    process_started_at: row.get(10)?,
-                    created_at: row.get(11)?,
-                    completed_at: row.get(12)?,
-                })
-            },
+            agent_run_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -716,20 +853,89 @@ pub async fn list_agent_runs_with_metrics(
 
 /// Execute a CC agent with streaming output
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_agent(
     app: AppHandle,
     agent_id: i64,
     project_path: String,
     task: String,
     model: Option<String>,
+    verbosity: Option<RunVerbosity>,
+    provider_profile_id: Option<i64>,
+    guards: Option<RunGuards>,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, String> {
+    start_agent_run(
+        app,
+        agent_id,
+        project_path,
+        task,
+        model,
+        false,
+        verbosity,
+        provider_profile_id,
+        guards,
+        db,
+        registry,
+    )
+    .await
+}
+
+/// Run a finished agent run's task again with debug logging turned on for the CLI and its
+/// MCP servers
+#[tauri::command]
+pub async fn rerun_agent_with_diagnostics(app: AppHandle, run_id: i64) -> Result<i64, String> {
+    retry_agent_run(app, run_id, None, Some(RunVerbosity::diagnostics())).await
+}
+
+/// Start an agent run. With `isolate`, the run gets a worktree of its own whenever the
+/// project is a git checkout, whatever the agent's worktree policy says. Without a
+/// `provider_profile_id`, the project's provider profile is used, if it has one. `guards`
+/// stop the run once it crosses a turn, token or cost limit.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn start_agent_run(
+    app: AppHandle,
+    agent_id: i64,
+    project_path: String,
+    task: String,
+    model: Option<String>,
+    isolate: bool,
+    verbosity: Option<RunVerbosity>,
+    provider_profile_id: Option<i64>,
+    guards: Option<RunGuards>,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
     info!("Executing agent {} with task: {}", agent_id, task);
+    if let Some(verbosity) = &verbosity {
+        verbosity.validate()?;
+    }
+    if let Some(guards) = &guards {
+        guards.validate()?;
+    }
 
     // Get the agent from database
     let agent = get_agent(db.clone(), agent_id).await?;
     let execution_model = model.unwrap_or(agent.model.clone());
+    super::team_policy::check_run_allowed(&project_path, Some(&execution_model))?;
+    super::recent_projects::touch_recent_project(&app, &project_path, "agent");
+    super::telemetry::record(super::telemetry::Category::Run, "agent");
+
+    // Profiles are assigned to the project, not to the worktree the run may move to
+    let provider_profile_id = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::provider_profiles::profile_for_run(&conn, &project_path, provider_profile_id)?
+            .and_then(|profile| profile.id)
+    };
+
+    // Agents isolated in a worktree run there instead of in the project itself
+    let worktree =
+        super::worktrees::prepare_run_worktree(&app, agent_id, &agent.name, &project_path, isolate)?;
+    let project_path = worktree
+        .as_ref()
+        .map(|worktree| worktree.working_dir.to_string_lossy().to_string())
+        .unwrap_or(project_path);
 
     // Create .claude/settings.json with agent hooks if it doesn't exist
     if let Some(hooks_json) = &agent.hooks {
@@ -758,7 +964,8 @@ pub async fn execute_agent(
             let settings_content = serde_json::to_string_pretty(&settings)
                 .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-            std::fs::write(&settings_path, settings_content)
+            super::config_snapshots::snapshot_config_file(&settings_path, "agent hooks")?;
+            crate::atomic_file::write_atomic(&settings_path, settings_content)
                 .map_err(|e| format!("Failed to write settings.json: {}", e))?;
 
             info!(
@@ -774,11 +981,27 @@ pub async fn execute_agent(
     let run_id = {
         let conn = db.0.lock().map_err(|e| e.to_string())?;
         conn.execute(
-            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![agent_id, agent.name, agent.icon, task, execution_model, project_path, ""],
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, verbosity, provider_profile_id, guards) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                agent_id,
+                agent.name,
+                agent.icon,
+                task,
+                execution_model,
+                project_path,
+                "",
+                verbosity.and_then(|verbosity| verbosity.to_column()),
+                provider_profile_id,
+                guards.and_then(|guards| guards.to_column())
+            ],
         )
         .map_err(|e| e.to_string())?;
-        conn.last_insert_rowid()
+        let run_id = conn.last_insert_rowid();
+        if let Some(worktree) = &worktree {
+            super::worktrees::record_run_worktree(&conn, run_id, worktree)
+                .map_err(|e| e.to_string())?;
+        }
+        run_id
     };
 
     // Find Claude binary
@@ -792,18 +1015,7 @@ pub async fn execute_agent(
     };
 
     // Build arguments
-    let args = vec![
-        "-p".to_string(),
-        task.clone(),
-        "--system-prompt".to_string(),
-        agent.system_prompt.clone(),
-        "--model".to_string(),
-        execution_model.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
-    ];
+    let args = build_agent_args(&agent, &task, &execution_model);
 
     // Always use system binary execution (sidecar removed)
     spawn_agent_system(
@@ -822,8 +1034,247 @@ pub async fn execute_agent(
     .await
 }
 
+/// Build the Claude CLI arguments for an agent run
+pub(crate) fn build_agent_args(agent: &Agent, task: &str, execution_model: &str) -> Vec<String> {
+    let task = match &agent.thinking {
+        Some(thinking) => thinking.apply_to_prompt(task),
+        None => task.to_string(),
+    };
+    let mut args = vec![
+        "-p".to_string(),
+        task,
+        "--system-prompt".to_string(),
+        agent.system_prompt.clone(),
+        "--model".to_string(),
+        execution_model.to_string(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+    ];
+    if let Some(tool_rules) = &agent.tool_rules {
+        tool_rules.apply_to_args(&mut args);
+    }
+    args
+}
+
+/// Start a new attempt of a failed run, linked to the original run record, optionally on a
+/// different model or with different diagnostics. Boxed so the monitor task spawned by
+/// `spawn_agent_system` can call back into it.
+pub(crate) fn retry_agent_run(
+    app: AppHandle,
+    failed_run_id: i64,
+    model_override: Option<String>,
+    verbosity_override: Option<RunVerbosity>,
+) -> futures::future::BoxFuture<'static, Result<i64, String>> {
+    Box::pin(async move {
+        let db = app.state::<AgentDb>();
+        let registry = app.state::<crate::process::ProcessRegistryState>();
+
+        let failed_run = get_agent_run(db.clone(), failed_run_id).await?;
+        let agent = get_agent(db.clone(), failed_run.agent_id).await?;
+        let root_run_id = failed_run.parent_run_id.unwrap_or(failed_run_id);
+        let attempt = failed_run.attempt + 1;
+        let model = model_override.unwrap_or(failed_run.model);
+        let verbosity = verbosity_override.or(failed_run.verbosity);
+
+        let run_id = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, parent_run_id, attempt, verbosity, provider_profile_id, guards)
+                 SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, provider_profile_id, guards FROM agent_runs WHERE id = ?11",
+                params![
+                    failed_run.agent_id,
+                    agent.name,
+                    agent.icon,
+                    failed_run.task,
+                    model,
+                    failed_run.project_path,
+                    "",
+                    root_run_id,
+                    attempt,
+                    verbosity.and_then(|verbosity| verbosity.to_column()),
+                    failed_run_id
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            conn.last_insert_rowid()
+        };
+
+        info!(
+            "🔁 Retrying agent run {} as run {} (attempt {})",
+            root_run_id, run_id, attempt
+        );
+
+        let claude_path = find_claude_binary(&app)?;
+        let args = build_agent_args(&agent, &failed_run.task, &model);
+
+        spawn_agent_system(
+            app.clone(),
+            run_id,
+            failed_run.agent_id,
+            agent.name.clone(),
+            claude_path,
+            args,
+            failed_run.project_path,
+            failed_run.task,
+            model,
+            db,
+            registry,
+        )
+        .await
+    })
+}
+
+/// Schedule another attempt of a failed run if the agent's retry policy allows it
+async fn schedule_retry_if_allowed(
+    app: &AppHandle,
+    db_path: &std::path::Path,
+    run_id: i64,
+    failure: FailureKind,
+) {
+    let (agent_id, attempt, policy) = match Connection::open(db_path).and_then(|conn| {
+        let (agent_id, attempt): (i64, Option<i64>) = conn.query_row(
+            "SELECT agent_id, attempt FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let policy = super::agent_retry::load_retry_policy(&conn, agent_id)?;
+        Ok((agent_id, attempt.unwrap_or(1), policy))
+    }) {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to load retry policy for run {}: {}", run_id, e);
+            return;
+        }
+    };
+
+    if !policy.should_retry(failure, attempt as u32) {
+        return;
+    }
+
+    let delay = policy.delay_after_attempt(attempt as u32);
+    info!(
+        "🔁 Agent {} run {} failed ({}), retrying in {}ms",
+        agent_id,
+        run_id,
+        failure.as_str(),
+        delay.as_millis()
+    );
+    super::activity::record_activity(
+        app,
+        super::activity::NewActivity::new(
+            super::activity::ActivityKind::RunRetryScheduled,
+            format!("Retrying run {} (attempt {})", run_id, attempt + 1),
+        )
+        .run(run_id)
+        .detail(serde_json::json!({
+            "delay_ms": delay.as_millis() as u64,
+            "failure": failure,
+        })),
+    );
+    let _ = app.emit(
+        &format!("agent-retry-scheduled:{}", run_id),
+        serde_json::json!({
+            "run_id": run_id,
+            "attempt": attempt + 1,
+            "delay_ms": delay.as_millis() as u64,
+            "failure": failure,
+        }),
+    );
+
+    tokio::time::sleep(delay).await;
+
+    match retry_agent_run(app.clone(), run_id, None, None).await {
+        Ok(new_run_id) => {
+            let _ = app.emit(&format!("agent-retry:{}", run_id), new_run_id);
+        }
+        Err(e) => error!("Failed to retry agent run {}: {}", run_id, e),
+    }
+}
+
+/// Immediately restart a run that hit rate limits or overload on the next model in the agent's
+/// fallback chain. Returns false when there is no model left to fail over to.
+async fn fail_over_to_next_model(
+    app: &AppHandle,
+    db_path: &std::path::Path,
+    run_id: i64,
+    failure: FailureKind,
+) -> bool {
+    if !ModelPolicy::fails_over_on(failure) {
+        return false;
+    }
+
+    let (model, policy) = match Connection::open(db_path).and_then(|conn| {
+        let (agent_id, model): (i64, String) = conn.query_row(
+            "SELECT agent_id, model FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((model, load_model_policy(&conn, agent_id)?))
+    }) {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to load model policy for run {}: {}", run_id, e);
+            return false;
+        }
+    };
+
+    let Some(next_model) = policy.next_after(&model).map(|m| m.to_string()) else {
+        return false;
+    };
+
+    info!(
+        "🔀 Agent run {} failed on {} ({}), failing over to {}",
+        run_id,
+        model,
+        failure.as_str(),
+        next_model
+    );
+    let _ = app.emit(
+        &format!("agent-model-fallback:{}", run_id),
+        serde_json::json!({
+            "run_id": run_id,
+            "from_model": model,
+            "to_model": next_model,
+            "failure": failure,
+        }),
+    );
+
+    match retry_agent_run(app.clone(), run_id, Some(next_model), None).await {
+        Ok(new_run_id) => {
+            let _ = app.emit(&format!("agent-retry:{}", run_id), new_run_id);
+            true
+        }
+        Err(e) => {
+            error!("Failed to fail over agent run {}: {}", run_id, e);
+            false
+        }
+    }
+}
+
+/// Record a sandbox violation in the run log and notify the frontend
+fn report_sandbox_violation(
+    app: &AppHandle,
+    db_path: &std::path::Path,
+    run_id: i64,
+    source: &str,
+    detail: &str,
+) {
+    warn!("🛡️ Sandbox violation in run {} ({}): {}", run_id, source, detail);
+    if let Ok(conn) = Connection::open(db_path) {
+        if let Err(e) = record_violation(&conn, run_id, source, detail) {
+            error!("❌ Failed to record sandbox violation: {}", e);
+        }
+    }
+    let _ = app.emit(
+        &format!("agent-sandbox-violation:{}", run_id),
+        serde_json::json!({ "source": source, "detail": detail }),
+    );
+}
+
 /// Creates a system binary command for agent execution
-fn create_agent_system_command(
+pub(crate) fn create_agent_system_command(
     claude_path: &str,
     args: Vec<String>,
     project_path: &str,
@@ -883,8 +1334,218 @@ async fn spawn_agent_system(
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
-    // Build the command
-    let mut cmd = create_agent_system_command(&claude_path, args, &project_path);
+    // Every run starts here, retries and model fallbacks included, so the team policy is
+    // enforced here. Runs in a worktree follow the policy of the repository it belongs to.
+    let policy_project = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::worktrees::load_run_worktree(&conn, run_id)
+            .map_err(|e| e.to_string())?
+            .map(|worktree| worktree.repo_path)
+            .unwrap_or_else(|| project_path.clone())
+    };
+    if let Err(e) =
+        super::team_policy::check_run_allowed(&policy_project, Some(&execution_model))
+    {
+        warn!("Run {} blocked: {}", run_id, e);
+        if let Ok(conn) = db.0.lock() {
+            let _ = conn.execute(
+                "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('pending', 'running')",
+                params![run_id],
+            );
+        }
+        return Err(e);
+    }
+
+    // Apply the agent's sandbox profile, if one is attached, any requested diagnostics, the
+    // agent's thinking budget, the run's provider profile, tool approval and budget guards
+    let (sandbox, verbosity, thinking, provider_profile, approval, guards) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let sandbox = load_agent_sandbox_profile(&conn, agent_id).map_err(|e| e.to_string())?;
+        let approval = super::approval_mcp::load_approval_enabled(&conn, agent_id)
+            .map_err(|e| e.to_string())?;
+        let (verbosity, provider_profile_id, guards): (Option<String>, Option<i64>, Option<String>) = conn
+            .query_row(
+                "SELECT verbosity, provider_profile_id, guards FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| e.to_string())?;
+        let thinking: Option<String> = conn
+            .query_row(
+                "SELECT thinking FROM agents WHERE id = ?1",
+                params![agent_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten();
+        let provider_profile = match provider_profile_id {
+            Some(id) => super::provider_profiles::load_profile(&conn, id)
+                .map_err(|e| e.to_string())?,
+            None => None,
+        };
+        (
+            sandbox,
+            RunVerbosity::from_column(verbosity).unwrap_or_default(),
+            RunThinking::from_column(thinking).unwrap_or_default(),
+            provider_profile,
+            approval,
+            RunGuards::from_column(guards).unwrap_or_default(),
+        )
+    };
+    let provider_env = match &provider_profile {
+        Some(profile) => {
+            info!("Using provider profile '{}'", profile.name);
+            profile.env_vars()?
+        }
+        None => Vec::new(),
+    };
+
+    // Projects assigned to an SSH host run there, with the environment set on the remote side
+    let remote_host = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::remote_hosts::host_for_project(&conn, &project_path).map_err(|e| e.to_string())?
+    };
+    if remote_host.is_some() && sandbox.is_some() {
+        return Err("Sandbox profiles can't be applied to runs on a remote host".to_string());
+    }
+    if remote_host.is_some() && approval {
+        return Err("Tool approval needs a local run; turn it off for remote hosts".to_string());
+    }
+
+    // Projects set up for Docker run in a throwaway container with the project mounted
+    let container = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::containers::container_for_project(&conn, &project_path)
+            .map_err(|e| e.to_string())?
+    };
+    let container = match container {
+        Some(_) if remote_host.is_some() => {
+            return Err("A project can't run both in a container and on a remote host".to_string())
+        }
+        Some(_) if sandbox.is_some() => {
+            return Err("Sandbox profiles can't be applied to runs in a container".to_string())
+        }
+        Some(_) if approval => {
+            return Err("Tool approval needs a local run; turn it off for containers".to_string())
+        }
+        Some(config) => {
+            super::containers::ensure_image(&app, &config).await?;
+            Some(config.resolve()?)
+        }
+        None => None,
+    };
+
+    let mut args = super::approval_mcp::apply_to_agent_run(&app, approval, run_id, args).await?;
+    args.extend(verbosity.claude_args());
+    args.extend(guards.claude_args());
+    // Deletes the host's written-out private key once the run is over
+    let mut ssh_key = None;
+    // The remote run's environment, sent over ssh's stdin rather than its command line
+    let mut remote_env_input = None;
+    // The sandboxed run's own Claude config, deleted once the run is over
+    let mut sandbox_scratch = None;
+    let (program, args) = match (&remote_host, &container, &sandbox) {
+        (Some(host), _, _) => {
+            info!("🌐 Running on remote host '{}'", host.name);
+            let env: Vec<(String, String)> = verbosity
+                .env()
+                .into_iter()
+                .chain(thinking.env())
+                .map(|(name, value)| (name.to_string(), value))
+                .chain(provider_env.iter().cloned())
+                .collect();
+            ssh_key = super::remote_hosts::key_file(host)?;
+            remote_env_input = Some(super::remote_hosts::env_input(&env)?);
+            host.wrap_command(args, &project_path, ssh_key.as_ref().map(|key| key.path()))?
+        }
+        (None, Some(container), _) => {
+            info!("🐳 Running in container image '{}'", container.image);
+            let env_names: Vec<String> = verbosity
+                .env()
+                .into_iter()
+                .chain(thinking.env())
+                .map(|(name, _)| name.to_string())
+                .chain(provider_env.iter().map(|(name, _)| name.clone()))
+                .collect();
+            container.wrap_command(run_id, args, &env_names, &project_path)
+        }
+        (None, None, Some(profile)) => {
+            info!("🛡️ Applying sandbox profile '{}'", profile.name);
+            let mut args = args;
+            args.extend(profile.claude_args());
+            let wrapped = SandboxScratch::create(run_id).and_then(|scratch| {
+                let wrapped = profile.wrap_command(&claude_path, args, &project_path, &scratch)?;
+                sandbox_scratch = Some(scratch);
+                Ok(wrapped)
+            });
+            match wrapped {
+                Ok(wrapped) => wrapped,
+                Err(e) => {
+                    // Never fall back to an unconfined run
+                    if let Ok(conn) = db.0.lock() {
+                        if let Err(e) = record_violation(&conn, run_id, "setup", &e) {
+                            error!("❌ Failed to record sandbox violation: {}", e);
+                        }
+                        let _ = conn.execute(
+                            "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('pending', 'running')",
+                            params![run_id],
+                        );
+                    }
+                    let _ = app.emit(
+                        &format!("agent-sandbox-violation:{}", run_id),
+                        serde_json::json!({ "source": "setup", "detail": e }),
+                    );
+                    return Err(e);
+                }
+            }
+        }
+        (None, None, None) => (claude_path.clone(), args),
+    };
+
+    // Build the command; a remote project's path may not exist locally
+    let local_dir = match &remote_host {
+        Some(_) => dirs::home_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .to_string_lossy()
+            .to_string(),
+        None => project_path.clone(),
+    };
+    let mut cmd = create_agent_system_command(&program, args, &local_dir);
+    if remote_env_input.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    for (key, value) in verbosity.env().into_iter().chain(thinking.env()) {
+        cmd.env(key, value);
+    }
+    super::provider_profiles::apply_env(&mut cmd, &provider_env);
+    if let Some(scratch) = &sandbox_scratch {
+        for (key, value) in scratch.env() {
+            cmd.env(key, value);
+        }
+    }
+
+    // The sandbox wrapper replaces the program, so keep Claude's own directory on PATH
+    if program != claude_path && container.is_none() {
+        if let Some(claude_dir) = std::path::Path::new(&claude_path).parent() {
+            let current_path = std::env::var("PATH").unwrap_or_default();
+            cmd.env("PATH", format!("{}:{}", claude_dir.display(), current_path));
+        }
+    }
+
+    // Snapshot the project first so the run can be aborted and rolled back
+    if remote_host.is_none() {
+        if let Err(e) = create_pre_run_checkpoint(&app, run_id, &project_path).await {
+            warn!("Failed to create pre-run checkpoint for run {}: {}", run_id, e);
+        }
+    }
+
+    // Capture the pre-run file baseline before anything can touch the project
+    let file_change_tracker = if remote_host.is_none() {
+        FileChangeTracker::with_baseline(&project_path)
+    } else {
+        FileChangeTracker::new(&project_path)
+    };
 
     // Spawn the process
     info!("🚀 Spawning Claude system process...");
@@ -893,7 +1554,27 @@ async fn spawn_agent_system(
         format!("Failed to spawn Claude: {}", e)
     })?;
 
-    info!("🔌 Using Stdio::null() for stdin - no input expected");
+    if let Some(input) = remote_env_input {
+        // Closing stdin afterwards leaves claude with no input, as for local runs
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(e) = stdin.write_all(input.as_bytes()).await {
+                // Without its environment the run could reach the wrong provider
+                let _ = child.kill().await;
+                if let Ok(conn) = db.0.lock() {
+                    let _ = conn.execute(
+                        "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('pending', 'running')",
+                        params![run_id],
+                    );
+                }
+                return Err(format!(
+                    "Failed to send the run's environment to the remote host: {}",
+                    e
+                ));
+            }
+        }
+    } else {
+        info!("🔌 Using Stdio::null() for stdin - no input expected");
+    }
 
     // Get the PID and register the process
     let pid = child.id().unwrap_or(0);
@@ -930,6 +1611,7 @@ async fn spawn_agent_system(
     let session_id = std::sync::Arc::new(Mutex::new(String::new()));
     let live_output = std::sync::Arc::new(Mutex::new(String::new()));
     let start_time = std::time::Instant::now();
+    let started_at = std::time::SystemTime::now();
 
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
@@ -939,6 +1621,26 @@ async fn spawn_agent_system(
     let first_output = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_output_clone = first_output.clone();
     let db_path_for_stdout = db_path.clone(); // Clone the db_path for the stdout task
+    let classifier = std::sync::Arc::new(Mutex::new(FailureClassifier::default()));
+    let classifier_stdout = classifier.clone();
+    let file_changes = std::sync::Arc::new(Mutex::new(file_change_tracker));
+    let file_changes_stdout = file_changes.clone();
+    super::rollback::track_run(run_id, file_changes.clone());
+    let served_model = std::sync::Arc::new(Mutex::new(None::<String>));
+    let served_model_stdout = served_model.clone();
+    let sandboxed = sandbox.is_some();
+    let first_output_timeout = if container.is_some() {
+        CONTAINER_FIRST_OUTPUT_TIMEOUT
+    } else {
+        FIRST_OUTPUT_TIMEOUT
+    };
+    // Local runs start right away; remote ones once the remote shell reports in on stderr
+    let connected =
+        std::sync::Arc::new(std::sync::atomic::AtomicBool::new(remote_host.is_none()));
+    let connected_stderr = connected.clone();
+    let db_path_for_stdout_violations = db_path.clone();
+    let app_for_stdout_violations = app.clone();
+    let guards_stdout = guards.clone();
 
     let stdout_task = tokio::spawn(async move {
         info!("📖 Starting to read Claude stdout...");
@@ -972,6 +1674,44 @@ async fn spawn_agent_system(
             // Also store in process registry for cross-session access
             let _ = registry_clone.append_live_output(run_id, &line);
 
+            // Track error signals for failure classification
+            if let Ok(mut classifier) = classifier_stdout.lock() {
+                classifier.observe_stdout(&line);
+            }
+
+            // Remember which model actually answered
+            if let Some(model) = served_model_from_line(&line) {
+                if let Ok(mut served) = served_model_stdout.lock() {
+                    *served = Some(model);
+                }
+            }
+
+            // The CLI ends the run itself when it reaches --max-turns
+            if let Some(exceeded) = guards_stdout.max_turns_reached(run_id, &line) {
+                super::run_guards::record_budget_exceeded(
+                    &app_for_stdout_violations,
+                    Some(&db_path_for_stdout_violations),
+                    &exceeded,
+                );
+            }
+
+            // Snapshot files before tools touch them for the run's change manifest
+            if let Ok(mut tracker) = file_changes_stdout.lock() {
+                tracker.observe_stdout(&line);
+            }
+
+            if sandboxed {
+                if let Some(detail) = SandboxViolationDetector.check_stdout(&line) {
+                    report_sandbox_violation(
+                        &app_for_stdout_violations,
+                        &db_path_for_stdout_violations,
+                        run_id,
+                        "tool",
+                        &detail,
+                    );
+                }
+            }
+
             // Extract session ID from JSONL output
             if let Ok(json) = serde_json::from_str::<JsonValue>(&line) {
                 // Claude Code uses "session_id" (underscore), not "sessionId"
@@ -1010,9 +1750,16 @@ async fn spawn_agent_system(
             }
 
             // Emit the line to the frontend with run_id for isolation
-            let _ = app_handle.emit(&format!("agent-output:{}", run_id), &line);
+            let _ = publish(&app_handle, Channel::AgentOutput, &format!("agent-output:{}", run_id), &line);
             // Also emit to the generic event for backward compatibility
-            let _ = app_handle.emit("agent-output", &line);
+            let _ = publish(&app_handle, Channel::AgentOutput, "agent-output", &line);
+            // Both streams in order, tagged so stderr can be highlighted
+            let _ = publish(
+                &app_handle,
+                Channel::AgentOutput,
+                &format!("agent-stream:{}", run_id),
+                StreamLine::new(OutputStream::Stdout, line),
+            );
         }
 
         info!(
@@ -1024,6 +1771,9 @@ async fn spawn_agent_system(
     let app_handle_stderr = app.clone();
     let first_error = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_error_clone = first_error.clone();
+    let classifier_stderr = classifier.clone();
+    let db_path_for_stderr = db_path.clone();
+    let registry_stderr = registry.0.clone();
 
     let stderr_task = tokio::spawn(async move {
         info!("📖 Starting to read Claude stderr...");
@@ -1031,6 +1781,13 @@ async fn spawn_agent_system(
         let mut error_count = 0;
 
         while let Ok(Some(line)) = crate::claude_binary::read_decoded_line(&mut reader).await {
+            if !connected_stderr.load(std::sync::atomic::Ordering::Relaxed)
+                && line.trim() == super::remote_hosts::READY_MARKER
+            {
+                info!("🌐 Connected to the remote host");
+                connected_stderr.store(true, std::sync::atomic::Ordering::Relaxed);
+                continue;
+            }
             error_count += 1;
 
             // Log first error
@@ -1040,10 +1797,31 @@ async fn spawn_agent_system(
             }
 
             error!("stderr[{}]: {}", error_count, line);
+            if let Ok(mut classifier) = classifier_stderr.lock() {
+                classifier.observe_stderr(&line);
+            }
+            if sandboxed {
+                if let Some(detail) = SandboxViolationDetector.check_stderr(&line) {
+                    report_sandbox_violation(
+                        &app_handle_stderr,
+                        &db_path_for_stderr,
+                        run_id,
+                        "os",
+                        &detail,
+                    );
+                }
+            }
+            let _ = registry_stderr.append_live_stderr(run_id, &line);
             // Emit error lines to the frontend with run_id for isolation
-            let _ = app_handle_stderr.emit(&format!("agent-error:{}", run_id), &line);
+            let _ = publish(&app_handle_stderr, Channel::AgentOutput, &format!("agent-error:{}", run_id), &line);
             // Also emit to the generic event for backward compatibility
-            let _ = app_handle_stderr.emit("agent-error", &line);
+            let _ = publish(&app_handle_stderr, Channel::AgentOutput, "agent-error", &line);
+            let _ = publish(
+                &app_handle_stderr,
+                Channel::AgentOutput,
+                &format!("agent-stream:{}", run_id),
+                StreamLine::new(OutputStream::Stderr, line),
+            );
         }
 
         if error_count > 0 {
@@ -1056,6 +1834,26 @@ async fn spawn_agent_system(
         }
     });
 
+    let agent_name_for_monitor = agent_name.clone();
+    let project_path_for_monitor = project_path.clone();
+    let task_for_monitor = task.clone();
+
+    dispatch_run_event(
+        &app,
+        RunWebhookPayload {
+            event: WebhookEvent::RunStarted,
+            run_id,
+            agent_name: agent_name.clone(),
+            project_path: project_path.clone(),
+            task: task.clone(),
+            status: "running".to_string(),
+            duration_ms: None,
+            cost_usd: None,
+            summary: None,
+            timestamp: now.clone(),
+        },
+    );
+
     // Register the process in the registry for live output tracking (after stdout/stderr setup)
     registry
         .0
@@ -1072,25 +1870,71 @@ async fn spawn_agent_system(
         .map_err(|e| format!("Failed to register process: {}", e))?;
     info!("📋 Registered process in registry");
 
+    // Flag the run if it goes quiet for longer than the agent's watchdog allows
+    super::run_watchdog::spawn_run_watchdog(
+        app.clone(),
+        registry.0.clone(),
+        db_path.clone(),
+        run_id,
+        agent_id,
+    );
+
+    // Stop the run once its streamed usage crosses a token or cost guard
+    super::run_guards::spawn_guard_monitor(
+        app.clone(),
+        registry.0.clone(),
+        run_id,
+        guards,
+        Some(db_path.clone()),
+    );
+
     let db_path_for_monitor = db_path.clone(); // Clone for the monitor task
+    let registry_for_monitor = registry.0.clone();
 
     // Monitor process status and wait for completion
     tokio::spawn(async move {
+        // Hold the ssh key file and sandbox scratch config until the run has exited
+        let _ssh_key = ssh_key;
+        let _sandbox_scratch = sandbox_scratch;
         info!("🕐 Starting process monitoring...");
 
-        // Wait for first output with timeout
-        for i in 0..300 {
-            // 30 seconds (300 * 100ms)
+        // Wait for first output with timeout; a remote run's wait starts once ssh has connected
+        let mut timed_out = false;
+        let mut timeout_summary = String::new();
+        let poll = std::time::Duration::from_millis(100);
+        let mut connecting = std::time::Duration::ZERO;
+        let mut waiting = std::time::Duration::ZERO;
+        loop {
             if first_output.load(std::sync::atomic::Ordering::Relaxed) {
                 info!(
                     "✅ Output detected after {}ms, continuing normal execution",
-                    i * 100
+                    (connecting + waiting).as_millis()
                 );
                 break;
             }
+            // Exited without output; the normal finish path classifies it
+            if stdout_task.is_finished() {
+                break;
+            }
 
-            if i == 299 {
-                warn!("⏰ TIMEOUT: No output from Claude process after 30 seconds");
+            if connected.load(std::sync::atomic::Ordering::Relaxed) {
+                waiting += poll;
+            } else {
+                connecting += poll;
+            }
+            if connecting >= REMOTE_CONNECT_TIMEOUT || waiting >= first_output_timeout {
+                timeout_summary = if connecting >= REMOTE_CONNECT_TIMEOUT {
+                    format!(
+                        "No connection to the remote host within {} seconds",
+                        REMOTE_CONNECT_TIMEOUT.as_secs()
+                    )
+                } else {
+                    format!(
+                        "No output from Claude within {} seconds",
+                        first_output_timeout.as_secs()
+                    )
+                };
+                warn!("⏰ TIMEOUT: {}", timeout_summary);
                 warn!("💡 This usually means:");
                 warn!("   1. Claude process is waiting for user input");
                 warn!("   3. Claude failed to initialize but didn't report an error");
@@ -1123,20 +1967,17 @@ async fn spawn_agent_system(
                     }
                 }
 
-                // Update database
-                if let Ok(conn) = Connection::open(&db_path_for_monitor) {
-                    let _ = conn.execute(
-                        "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
-                        params![run_id],
-                    );
+                // Children holding the output pipes would keep the readers below waiting
+                if let Err(e) = registry_for_monitor.kill_process_tree(run_id).await {
+                    warn!("🔍 Failed to kill process tree for run {}: {}", run_id, e);
                 }
 
-                let _ = app.emit("agent-complete", false);
-                let _ = app.emit(&format!("agent-complete:{}", run_id), false);
-                return;
+                // Finish through the normal path so the manifest, worktree and retries are handled
+                timed_out = true;
+                break;
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            tokio::time::sleep(poll).await;
         }
 
         // Wait for reading tasks to complete
@@ -1154,21 +1995,79 @@ async fn spawn_agent_system(
             String::new()
         };
 
-        // Wait for process completion and update status
-        info!("✅ Claude process execution monitoring complete");
+        // Wait for process completion and classify the outcome
+        let exit_code = registry_for_monitor
+            .wait_for_exit(run_id, std::time::Duration::from_secs(10))
+            .await
+            .unwrap_or(None);
+        // Approval calls still waiting can no longer be answered
+        app.state::<PermissionRelayState>().detach(run_id);
+        let failure = if timed_out {
+            Some(FailureKind::NoOutput)
+        } else {
+            classifier
+                .lock()
+                .ok()
+                .and_then(|classifier| classifier.classify(exit_code))
+        };
+        let final_status = if failure.is_some() { "failed" } else { "completed" };
+        info!(
+            "✅ Claude process execution monitoring complete (exit code: {:?}, status: {})",
+            exit_code, final_status
+        );
+
+        // Record which files the run created, modified, or deleted
+        if let Ok(tracker) = file_changes.lock() {
+            let changes = tracker.finish();
+            if let Ok(conn) = Connection::open(&db_path_for_monitor) {
+                if let Err(e) = save_run_file_changes(&conn, run_id, &changes) {
+                    error!("❌ Failed to save file changes for run {}: {}", run_id, e);
+                } else if !changes.is_empty() {
+                    info!("📝 Recorded {} file change(s) for run {}", changes.len(), run_id);
+                }
+            }
+        }
+        if let Ok(conn) = Connection::open(&db_path_for_monitor) {
+            if let Err(e) = super::rollback::finish_run(&conn, run_id) {
+                error!("❌ Failed to save rollback manifest for run {}: {}", run_id, e);
+            }
+        }
+
+        // Keep the files matching the agent's artifact globs
+        if let Ok(conn) = Connection::open(&db_path_for_monitor) {
+            match collect_run_artifacts(
+                &conn,
+                &artifacts_root_for_db(&db_path_for_monitor),
+                run_id,
+                agent_id,
+                &project_path_for_monitor,
+                started_at,
+            ) {
+                Ok(artifacts) if !artifacts.is_empty() => {
+                    info!("📦 Kept {} artifact(s) for run {}", artifacts.len(), run_id)
+                }
+                Ok(_) => {}
+                Err(e) => error!("❌ Failed to collect artifacts for run {}: {}", run_id, e),
+            }
+        }
+
+        let served_model_for_run = served_model.lock().ok().and_then(|m| m.clone());
 
-        // Update the run record with session ID and mark as completed - open a new connection
+        // Update the run record with session ID and final status - open a new connection.
+        // Runs that were cancelled in the meantime keep their status.
+        let mut status_updated = false;
         if let Ok(conn) = Connection::open(&db_path_for_monitor) {
             info!(
                 "🔄 Updating database with extracted session ID: {}",
                 extracted_session_id
             );
             match conn.execute(
-                "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
-                params![extracted_session_id, run_id],
+                "UPDATE agent_runs SET session_id = ?1, status = ?2, served_model = ?3, completed_at = CURRENT_TIMESTAMP WHERE id = ?4 AND status = 'running'",
+                params![extracted_session_id, final_status, served_model_for_run, run_id],
             ) {
                 Ok(rows_affected) => {
                     if rows_affected > 0 {
+                        status_updated = true;
                         info!("✅ Successfully updated agent run {} with session ID: {}", run_id, extracted_session_id);
                     } else {
                         warn!("⚠️ No rows affected when updating agent run {} with session ID", run_id);
@@ -1178,6 +2077,18 @@ async fn spawn_agent_system(
                     error!("❌ Failed to update agent run {} with session ID: {}", run_id, e);
                 }
             }
+            // Runs stopped by a budget guard keep their status but still record the session
+            if !status_updated {
+                let _ = conn.execute(
+                    "UPDATE agent_runs SET session_id = ?1, served_model = ?2 WHERE id = ?3 AND status = ?4",
+                    params![
+                        extracted_session_id,
+                        served_model_for_run,
+                        run_id,
+                        super::run_guards::BUDGET_EXCEEDED_STATUS
+                    ],
+                );
+            }
         } else {
             error!(
                 "❌ Failed to open database to update session ID for run {}",
@@ -1185,10 +2096,74 @@ async fn spawn_agent_system(
             );
         }
 
+        // Commit the run's changes in its worktree, if it has one, and tidy up
+        if let Ok(conn) = Connection::open(&db_path_for_monitor) {
+            match super::worktrees::finish_run_worktree(&conn, run_id, status_updated && failure.is_none()) {
+                Ok(Some(worktree)) => info!(
+                    "🌿 Worktree for run {} is {} (branch {})",
+                    run_id, worktree.status, worktree.branch
+                ),
+                Ok(None) => {}
+                Err(e) => error!("❌ Failed to finish worktree for run {}: {}", run_id, e),
+            }
+        }
+
         // Cleanup will be handled by the cleanup_finished_processes function
 
-        let _ = app.emit("agent-complete", true);
-        let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+        let _ = app.emit("agent-complete", failure.is_none());
+        let _ = app.emit(&format!("agent-complete:{}", run_id), failure.is_none());
+
+        if status_updated {
+            let (cost_usd, summary) = if timed_out {
+                (None, Some(timeout_summary.clone()))
+            } else {
+                live_output
+                    .lock()
+                    .map(|output| summarize_stream_output(&output))
+                    .unwrap_or((None, None))
+            };
+            dispatch_run_event(
+                &app,
+                RunWebhookPayload {
+                    event: if failure.is_none() {
+                        WebhookEvent::RunCompleted
+                    } else {
+                        WebhookEvent::RunFailed
+                    },
+                    run_id,
+                    agent_name: agent_name_for_monitor.clone(),
+                    project_path: project_path_for_monitor.clone(),
+                    task: task_for_monitor.clone(),
+                    status: final_status.to_string(),
+                    duration_ms: Some(duration_ms),
+                    cost_usd,
+                    summary,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                },
+            );
+
+            let body = run_outcome_body(run_id, duration_ms, failure.map(|kind| kind.as_str()));
+            match failure {
+                None => notify(
+                    &app,
+                    NotificationEvent::RunCompleted,
+                    &format!("{} finished", agent_name_for_monitor),
+                    &body,
+                ),
+                Some(_) => notify(
+                    &app,
+                    NotificationEvent::RunFailed,
+                    &format!("{} failed", agent_name_for_monitor),
+                    &body,
+                ),
+            }
+        }
+
+        if let (Some(failure), true) = (failure, status_updated) {
+            if !fail_over_to_next_model(&app, &db_path_for_monitor, run_id, failure).await {
+                schedule_retry_if_allowed(&app, &db_path_for_monitor, run_id, failure).await;
+            }
+        }
     });
 
     Ok(run_id)
@@ -1203,35 +2178,15 @@ pub async fn list_running_sessions(
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // First get all running sessions from the database
-    let mut stmt = conn.prepare(
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
 
     let mut runs = stmt
-        .query_map([], |row| {
-            Ok(AgentRun {
-                id: Some(row.get(0)?),
-                agent_id: row.get(1)?,
-                agent_name: row.get(2)?,
-                agent_icon: row.get(3)?,
-                task: row.get(4)?,
-                model: row.get(5)?,
-                project_path: row.get(6)?,
-                session_id: row.get(7)?,
-                status: row
-                    .get::<_, String>(8)
-                    .unwrap_or_else(|_| "pending".to_string()),
-                pid: row
-                    .get::<_, Option<i64>>(9)
-                    .ok()
-                    .flatten()
-                    .map(|p| p as u32),
-                process_started_at: row.get(10)?,
-                created_at: row.get(11)?,
-                completed_at: row.get(12)?,
-            })
-        })
+        .query_map([], agent_run_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -1401,13 +2356,36 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
     Ok(cleaned_up)
 }
 
-/// Get live output from a running process
+/// Get live output from a running process; stdout unless another `stream` is asked for
 #[tauri::command]
 pub async fn get_live_session_output(
     registry: State<'_, crate::process::ProcessRegistryState>,
     run_id: i64,
-) -> Result<String, String> {
-    registry.0.get_live_output(run_id)
+    stream: Option<OutputStream>,
+) -> Result<String, OpcodeError> {
+    Ok(registry
+        .0
+        .get_live_output(run_id, Some(stream.unwrap_or(OutputStream::Stdout)))?)
+}
+
+/// Get the live output lines of a running process tagged with their stream, filtered to
+/// one stream when `stream` is given
+#[tauri::command]
+pub async fn get_live_output(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+    stream: Option<OutputStream>,
+) -> Result<Vec<StreamLine>, OpcodeError> {
+    Ok(registry.0.get_live_output_lines(run_id, stream)?)
+}
+
+/// Get the running token and cost totals for an active run
+#[tauri::command]
+pub async fn get_live_run_usage(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+) -> Result<Option<crate::process::usage::RunUsage>, OpcodeError> {
+    Ok(registry.0.get_run_usage(run_id)?)
 }
 
 /// Get real-time output for a running session by reading its JSONL file with live output fallback
@@ -1422,7 +2400,7 @@ pub async fn get_session_output(
 
     // If no session ID yet, try to get live output from registry
     if run.session_id.is_empty() {
-        let live_output = registry.0.get_live_output(run_id)?;
+        let live_output = registry.0.get_live_output(run_id, Some(OutputStream::Stdout))?;
         if !live_output.is_empty() {
             return Ok(live_output);
         }
@@ -1482,7 +2460,7 @@ pub async fn get_session_output(
                     e
                 );
                 // Fallback to live output if file read fails
-                let live_output = registry.0.get_live_output(run_id)?;
+                let live_output = registry.0.get_live_output(run_id, Some(OutputStream::Stdout))?;
                 Ok(live_output)
             }
         }
@@ -1496,7 +2474,7 @@ pub async fn get_session_output(
             Ok(content) => Ok(content),
             Err(_) => {
                 // Final fallback to live output
-                let live_output = registry.0.get_live_output(run_id)?;
+                let live_output = registry.0.get_live_output(run_id, Some(OutputStream::Stdout))?;
                 Ok(live_output)
             }
         }
@@ -1823,24 +2801,9 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| format!("Failed to fetch created agent: {}", e))?;
 
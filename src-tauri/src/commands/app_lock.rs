@@ -0,0 +1,436 @@
+#![allow(dead_code)]
+
+//! App lock for shared machines. When enabled, the app locks on launch and/or after a
+//! configurable idle time, and while locked the backend rejects every command except the few
+//! needed to unlock, so API keys and transcripts can't be read through the UI.
+//!
+//! Unlocking takes either an app password (stored as a PBKDF2 hash) or the operating
+//! system's own authentication prompt: the administrator prompt on macOS (Touch ID where the
+//! system offers it), polkit on Linux (fingerprint where PAM is set up for it) and Windows
+//! Hello on Windows.
+
+use base64::Engine;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use super::agents::AgentDb;
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the `AppLockSettings`
+pub const APP_LOCK_KEY: &str = "app_lock";
+
+/// app_settings key holding the `PasswordHash` of the app password
+const APP_LOCK_PASSWORD_KEY: &str = "app_lock_password";
+
+/// Emitted with the `AppLockStatus` whenever the app locks or unlocks
+pub const APP_LOCK_EVENT: &str = "app-lock-changed";
+
+/// Commands that still run while the app is locked
+const ALLOWED_WHILE_LOCKED: &[&str] = &["get_app_lock_status", "unlock_app", "lock_app"];
+
+/// Commands that don't count as user activity, such as status polling
+const PASSIVE_COMMANDS: &[&str] = &["get_app_lock_status", "get_offline_status"];
+
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+
+/// Failed unlock attempts allowed before unlocking is paused
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCKOUT: Duration = Duration::from_secs(30);
+
+/// How often the idle monitor checks for inactivity
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+pub const LOCKED_MESSAGE: &str = "opcode is locked";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LOCKED: AtomicBool = AtomicBool::new(false);
+/// Idle seconds before locking; 0 never locks for inactivity
+static IDLE_LOCK_SECS: AtomicU64 = AtomicU64::new(0);
+/// Unix time of the last command the user triggered
+static LAST_ACTIVITY: AtomicU64 = AtomicU64::new(0);
+static FAILED_ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+/// Unix time until which unlock attempts are refused
+static LOCKED_OUT_UNTIL: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnlockMethod {
+    #[default]
+    Password,
+    /// The operating system's authentication prompt
+    System,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AppLockSettings {
+    pub enabled: bool,
+    pub lock_on_launch: bool,
+    /// Minutes without activity before locking; `None` never locks for inactivity
+    pub idle_minutes: Option<u32>,
+    pub method: UnlockMethod,
+}
+
+impl Default for AppLockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lock_on_launch: true,
+            idle_minutes: Some(15),
+            method: UnlockMethod::Password,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockStatus {
+    pub settings: AppLockSettings,
+    pub locked: bool,
+    pub password_set: bool,
+    /// Seconds until unlocking is allowed again after too many failed attempts
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PasswordHash {
+    salt: String,
+    hash: String,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn is_locked() -> bool {
+    LOCKED.load(Ordering::Relaxed)
+}
+
+/// Whether an app_settings key belongs to the app lock and must stay out of the generic
+/// settings commands
+pub fn is_app_lock_key(key: &str) -> bool {
+    key == APP_LOCK_KEY || key == APP_LOCK_PASSWORD_KEY
+}
+
+/// Cache the lock settings for the command guard and idle monitor
+pub fn apply_app_lock_settings(settings: Option<AppLockSettings>) {
+    let settings = settings.unwrap_or_default();
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    IDLE_LOCK_SECS.store(
+        settings
+            .idle_minutes
+            .filter(|_| settings.enabled)
+            .map_or(0, |minutes| u64::from(minutes) * 60),
+        Ordering::Relaxed,
+    );
+    if !settings.enabled {
+        LOCKED.store(false, Ordering::Relaxed);
+    }
+    LAST_ACTIVITY.store(now_secs(), Ordering::Relaxed);
+}
+
+/// Whether `command` may run now, recording it as user activity when it does
+pub fn check_command(command: &str) -> Result<(), String> {
+    if is_locked() && !ALLOWED_WHILE_LOCKED.contains(&command) {
+        return Err(LOCKED_MESSAGE.to_string());
+    }
+    if !PASSIVE_COMMANDS.contains(&command) {
+        LAST_ACTIVITY.store(now_secs(), Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Wrap the app's invoke handler so commands are rejected while the app is locked
+pub fn guard<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| match check_command(invoke.message.command()) {
+        Ok(()) => handler(invoke),
+        Err(e) => {
+            invoke.resolver.reject(e);
+            true
+        }
+    }
+}
+
+fn hash_password(password: &str, salt: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    hash
+}
+
+fn new_password_hash(password: &str) -> Result<PasswordHash, String> {
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| "Failed to generate random bytes".to_string())?;
+    let engine = base64::engine::general_purpose::STANDARD;
+    Ok(PasswordHash {
+        salt: engine.encode(salt),
+        hash: engine.encode(hash_password(password, &salt)),
+    })
+}
+
+fn verify_password(stored: &PasswordHash, password: &str) -> bool {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let (Ok(salt), Ok(hash)) = (engine.decode(&stored.salt), engine.decode(&stored.hash)) else {
+        return false;
+    };
+    pbkdf2::verify(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        &salt,
+        password.as_bytes(),
+        &hash,
+    )
+    .is_ok()
+}
+
+/// Ask the operating system to authenticate the current user
+fn system_authenticate() -> Result<bool, String> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("osascript");
+        command.args([
+            "-e",
+            "do shell script \"true\" with prompt \"opcode wants to unlock.\" with administrator privileges",
+        ]);
+        command
+    };
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        let mut command = std::process::Command::new("pkexec");
+        command.arg("true");
+        command
+    };
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        const WINDOWS_HELLO: &str = r#"
+Add-Type -AssemblyName System.Runtime.WindowsRuntime
+$asTask = ([System.WindowsRuntimeSystemExtensions].GetMethods() | Where-Object { $_.Name -eq 'AsTask' -and $_.GetParameters().Count -eq 1 -and $_.GetParameters()[0].ParameterType.Name -eq 'IAsyncOperation`1' })[0]
+[Windows.Security.Credentials.UI.UserConsentVerifier, Windows.Security.Credentials.UI, ContentType = WindowsRuntime] | Out-Null
+$operation = [Windows.Security.Credentials.UI.UserConsentVerifier]::RequestVerificationAsync('Unlock opcode')
+$task = $asTask.MakeGenericMethod([Windows.Security.Credentials.UI.UserConsentVerificationResult]).Invoke($null, @($operation))
+$task.Wait()
+if ($task.Result -eq 'Verified') { exit 0 } else { exit 1 }
+"#;
+        let mut command = std::process::Command::new("powershell");
+        command.args(["-NoProfile", "-NonInteractive", "-Command", WINDOWS_HELLO]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+    return Err("System authentication is not supported on this platform".to_string());
+
+    #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+    command
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .map_err(|e| format!("Failed to start system authentication: {}", e))
+}
+
+fn status(conn: &Connection) -> AppLockStatus {
+    let until = LOCKED_OUT_UNTIL.load(Ordering::Relaxed);
+    let now = now_secs();
+    AppLockStatus {
+        settings: get_setting_as(conn, APP_LOCK_KEY).unwrap_or_default(),
+        locked: is_locked(),
+        password_set: get_setting_as::<PasswordHash>(conn, APP_LOCK_PASSWORD_KEY).is_some(),
+        retry_after_secs: (until > now).then(|| until - now),
+    }
+}
+
+fn set_locked(app: &AppHandle, locked: bool) {
+    LOCKED.store(locked, Ordering::Relaxed);
+    LAST_ACTIVITY.store(now_secs(), Ordering::Relaxed);
+    let db = app.state::<AgentDb>();
+    let status = db.0.lock().map(|conn| status(&conn));
+    if let Ok(status) = status {
+        let _ = app.emit(APP_LOCK_EVENT, status);
+    }
+    // Hide or bring back the run names in the tray menu
+    super::tray::refresh_tray(app);
+}
+
+/// Lock on launch when configured, and lock after the idle time from then on
+pub fn init_app_lock(app: &AppHandle) {
+    let settings: AppLockSettings = {
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            return;
+        };
+        get_setting_as(&conn, APP_LOCK_KEY).unwrap_or_default()
+    };
+    if settings.enabled && settings.lock_on_launch {
+        LOCKED.store(true, Ordering::Relaxed);
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let idle_limit = IDLE_LOCK_SECS.load(Ordering::Relaxed);
+            if idle_limit == 0 || !ENABLED.load(Ordering::Relaxed) || is_locked() {
+                continue;
+            }
+            let idle = now_secs().saturating_sub(LAST_ACTIVITY.load(Ordering::Relaxed));
+            if idle >= idle_limit {
+                log::info!("Locking after {}s without activity", idle);
+                set_locked(&app, true);
+            }
+        }
+    });
+}
+
+/// Lock state, settings and whether a password is set
+#[tauri::command]
+pub async fn get_app_lock_status(db: State<'_, AgentDb>) -> Result<AppLockStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(status(&conn))
+}
+
+/// Change the app lock settings, optionally setting a new app password. Password unlock
+/// can't be enabled without a password.
+#[tauri::command]
+pub async fn set_app_lock_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    settings: AppLockSettings,
+    password: Option<String>,
+) -> Result<AppLockStatus, String> {
+    if settings.idle_minutes == Some(0) {
+        return Err("The idle time must be at least one minute".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(password) = &password {
+        if password.chars().count() < 4 {
+            return Err("The app password must be at least 4 characters".to_string());
+        }
+        set_setting_as(&conn, APP_LOCK_PASSWORD_KEY, &new_password_hash(password)?)?;
+    }
+    let password_set = get_setting_as::<PasswordHash>(&conn, APP_LOCK_PASSWORD_KEY).is_some();
+    if settings.enabled && settings.method == UnlockMethod::Password && !password_set {
+        return Err("Set an app password before enabling the lock".to_string());
+    }
+    set_setting_as(&conn, APP_LOCK_KEY, &settings)?;
+    super::settings::notify_settings_changed(&app, &conn, &[APP_LOCK_KEY]);
+    Ok(status(&conn))
+}
+
+/// Lock the app now
+#[tauri::command]
+pub async fn lock_app(app: AppHandle) -> Result<(), String> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Err("The app lock is not enabled".to_string());
+    }
+    set_locked(&app, true);
+    Ok(())
+}
+
+/// Unlock with the app password, or through the system prompt when that's the configured
+/// method and no password is given
+#[tauri::command]
+pub async fn unlock_app(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    password: Option<String>,
+) -> Result<AppLockStatus, String> {
+    if !is_locked() {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        return Ok(status(&conn));
+    }
+    let until = LOCKED_OUT_UNTIL.load(Ordering::Relaxed);
+    if until > now_secs() {
+        return Err(format!(
+            "Too many failed attempts; try again in {}s",
+            until - now_secs()
+        ));
+    }
+
+    let (settings, stored) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            get_setting_as::<AppLockSettings>(&conn, APP_LOCK_KEY).unwrap_or_default(),
+            get_setting_as::<PasswordHash>(&conn, APP_LOCK_PASSWORD_KEY),
+        )
+    };
+    let verified = match (password, settings.method) {
+        (Some(password), _) => stored.is_some_and(|stored| verify_password(&stored, &password)),
+        (None, UnlockMethod::System) => tokio::task::spawn_blocking(system_authenticate)
+            .await
+            .map_err(|e| e.to_string())??,
+        (None, UnlockMethod::Password) => return Err("Enter the app password".to_string()),
+    };
+
+    if !verified {
+        let failures = FAILED_ATTEMPTS.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= MAX_FAILED_ATTEMPTS {
+            FAILED_ATTEMPTS.store(0, Ordering::Relaxed);
+            LOCKED_OUT_UNTIL.store(now_secs() + LOCKOUT.as_secs(), Ordering::Relaxed);
+        }
+        log::warn!("Failed attempt to unlock the app");
+        return Err("Authentication failed".to_string());
+    }
+    FAILED_ATTEMPTS.store(0, Ordering::Relaxed);
+    set_locked(&app, false);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(status(&conn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_hash_verifies_only_the_password() {
+        let stored = new_password_hash("hunter22").unwrap();
+        assert!(verify_password(&stored, "hunter22"));
+        assert!(!verify_password(&stored, "hunter23"));
+        assert_ne!(new_password_hash("hunter22").unwrap().salt, stored.salt);
+    }
+
+    #[test]
+    fn test_locked_app_rejects_all_but_unlock_commands() {
+        apply_app_lock_settings(Some(AppLockSettings {
+            enabled: true,
+            ..Default::default()
+        }));
+        LOCKED.store(true, Ordering::Relaxed);
+        assert_eq!(
+            check_command("list_agents"),
+            Err(LOCKED_MESSAGE.to_string())
+        );
+        assert!(check_command("unlock_app").is_ok());
+        assert!(check_command("get_app_lock_status").is_ok());
+
+        // Disabling the lock releases it
+        apply_app_lock_settings(None);
+        assert!(!is_locked());
+        assert!(check_command("list_agents").is_ok());
+    }
+
+    #[test]
+    fn test_app_lock_keys_are_protected() {
+        assert!(is_app_lock_key(APP_LOCK_KEY));
+        assert!(is_app_lock_key(APP_LOCK_PASSWORD_KEY));
+        assert!(!is_app_lock_key("theme"));
+    }
+}
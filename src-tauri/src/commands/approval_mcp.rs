@@ -0,0 +1,450 @@
+#![allow(dead_code)]
+
+//! Built-in approval MCP server for human-in-the-loop agent runs. Agents with approval turned
+//! on run without `--dangerously-skip-permissions`; instead the run gets an `opcode-approval`
+//! MCP server through `--mcp-config` and its `approve` tool as `--permission-prompt-tool`.
+//!
+//! The server is the app's own binary run as `opcode approval-mcp` (installed builds don't
+//! ship `opcode-cli`, which has the same command), a stdio MCP server the CLI spawns. Each
+//! call of `approve` is forwarded to a token-protected HTTP endpoint the app serves on
+//! localhost, which announces it as a `run:permission-request` event and holds the call open
+//! until `respond_permission` answers it, so the tool blocks until the user decides.
+
+use axum::extract::State as AxumState;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+use tokio::net::TcpListener;
+
+use super::agents::AgentDb;
+use super::permission_relay::{
+    announce, permission_decision, PermissionRelayState, PermissionRequest,
+};
+
+/// Name of the server in the run's MCP config
+pub const SERVER_NAME: &str = "opcode-approval";
+
+/// Value of `--permission-prompt-tool` for approval runs
+pub const PERMISSION_PROMPT_TOOL: &str = "mcp__opcode-approval__approve";
+
+/// First argument that starts the app binary as the approval MCP server instead of the GUI
+pub const APPROVAL_MCP_COMMAND: &str = "approval-mcp";
+
+/// Environment variable carrying the endpoint's token to the server process
+pub const TOKEN_ENV: &str = "OPCODE_APPROVAL_TOKEN";
+
+/// MCP protocol version answered to `initialize`
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Where the app takes approval calls
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApprovalEndpoint {
+    pub url: String,
+    pub token: String,
+}
+
+/// The app's approval endpoint, started with the first approval run
+#[derive(Default)]
+pub struct ApprovalServerState {
+    endpoint: tokio::sync::Mutex<Option<ApprovalEndpoint>>,
+}
+
+/// One call of the `approve` tool, as forwarded to the app
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApprovalCall {
+    pub run_id: i64,
+    pub tool_name: String,
+    #[serde(default)]
+    pub input: Value,
+    pub tool_use_id: Option<String>,
+}
+
+/// Create the approval settings table
+pub fn init_approval_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_approval_settings (
+            agent_id INTEGER PRIMARY KEY,
+            enabled BOOLEAN NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Whether an agent's runs ask the user before using tools; off by default
+pub fn load_approval_enabled(conn: &Connection, agent_id: i64) -> SqliteResult<bool> {
+    Ok(conn
+        .query_row(
+            "SELECT enabled FROM agent_approval_settings WHERE agent_id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(false))
+}
+
+/// Arguments routing a run's permission prompts through the approval server instead of
+/// skipping them
+pub fn approval_args(
+    args: Vec<String>,
+    cli_path: &Path,
+    endpoint: &ApprovalEndpoint,
+    run_id: i64,
+) -> Vec<String> {
+    let mut approval: Vec<String> = args
+        .into_iter()
+        .filter(|arg| arg != "--dangerously-skip-permissions")
+        .collect();
+    approval.push("--mcp-config".to_string());
+    approval.push(mcp_config(cli_path, endpoint, run_id).to_string());
+    approval.push("--permission-prompt-tool".to_string());
+    approval.push(PERMISSION_PROMPT_TOOL.to_string());
+    approval
+}
+
+/// MCP config adding the approval server for one run
+pub fn mcp_config(cli_path: &Path, endpoint: &ApprovalEndpoint, run_id: i64) -> Value {
+    json!({
+        "mcpServers": {
+            SERVER_NAME: {
+                "type": "stdio",
+                "command": cli_path.to_string_lossy(),
+                "args": [
+                    APPROVAL_MCP_COMMAND,
+                    "--endpoint",
+                    endpoint.url,
+                    "--run-id",
+                    run_id.to_string()
+                ],
+                "env": { TOKEN_ENV: endpoint.token },
+            }
+        }
+    })
+}
+
+/// Approval arguments for an agent run, when the agent has approval turned on
+pub async fn apply_to_agent_run(
+    app: &AppHandle,
+    enabled: bool,
+    run_id: i64,
+    args: Vec<String>,
+) -> Result<Vec<String>, String> {
+    if !enabled {
+        return Ok(args);
+    }
+    let cli_path = app_binary_path()?;
+    let endpoint = ensure_endpoint(app).await?;
+    log::info!("Run {} asks for approval through {}", run_id, endpoint.url);
+    Ok(approval_args(args, &cli_path, &endpoint, run_id))
+}
+
+/// The running app's binary, which serves the approval MCP server when started with
+/// `APPROVAL_MCP_COMMAND`
+pub(crate) fn app_binary_path() -> Result<std::path::PathBuf, String> {
+    std::env::current_exe().map_err(|e| format!("Failed to locate the app binary: {}", e))
+}
+
+/// The approval endpoint, started on first use
+pub async fn ensure_endpoint(app: &AppHandle) -> Result<ApprovalEndpoint, String> {
+    let state = app.state::<ApprovalServerState>();
+    let mut endpoint = state.endpoint.lock().await;
+    if let Some(endpoint) = endpoint.as_ref() {
+        return Ok(endpoint.clone());
+    }
+
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .map_err(|e| format!("Failed to start approval endpoint: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to get local address: {}", e))?
+        .port();
+    let started = ApprovalEndpoint {
+        url: format!("http://127.0.0.1:{}/approve", port),
+        token: uuid::Uuid::new_v4().simple().to_string(),
+    };
+
+    let router = Router::new()
+        .route("/approve", post(handle_approval))
+        .with_state((app.clone(), started.token.clone()));
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            log::error!("Approval endpoint error: {}", e);
+        }
+    });
+    log::info!("Approval endpoint listening on port {}", port);
+
+    *endpoint = Some(started.clone());
+    Ok(started)
+}
+
+/// Hold an `approve` call open until the user answers it
+async fn handle_approval(
+    AxumState((app, token)): AxumState<(AppHandle, String)>,
+    headers: HeaderMap,
+    Json(call): Json<ApprovalCall>,
+) -> Result<Json<Value>, StatusCode> {
+    let authorized = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        == Some(token.as_str());
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let request = PermissionRequest {
+        run_id: call.run_id,
+        request_id: uuid::Uuid::new_v4().to_string(),
+        tool_name: call.tool_name,
+        input: call.input,
+        tool_use_id: call.tool_use_id,
+        suggestions: None,
+        received_at: Utc::now(),
+    };
+    let input = request.input.clone();
+    let decision = app
+        .state::<PermissionRelayState>()
+        .wait_for(request.clone());
+    announce(&app, &request);
+
+    Ok(Json(decision.await.unwrap_or_else(|_| {
+        permission_decision(&input, false, Some("The run ended before the user decided"))
+    })))
+}
+
+/// Answer to an MCP request that needs no approval call, `None` for notifications
+fn protocol_response(message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let result = match message.get("method").and_then(Value::as_str) {
+        Some("initialize") => json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": SERVER_NAME, "version": env!("CARGO_PKG_VERSION") },
+        }),
+        Some("tools/list") => json!({
+            "tools": [{
+                "name": "approve",
+                "description": "Ask the opcode user whether a tool may run",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "tool_name": { "type": "string" },
+                        "input": { "type": "object" },
+                        "tool_use_id": { "type": "string" },
+                    },
+                    "required": ["tool_name", "input"],
+                },
+            }],
+        }),
+        Some("ping") => json!({}),
+        _ => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": "Method not found" },
+            }))
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// Forward an `approve` call to the app and wrap its decision as the tool's result
+async fn call_approve(
+    client: &reqwest::Client,
+    endpoint: &ApprovalEndpoint,
+    run_id: i64,
+    arguments: &Value,
+) -> Value {
+    let call = ApprovalCall {
+        run_id,
+        tool_name: arguments
+            .get("tool_name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        input: arguments.get("input").cloned().unwrap_or_else(|| json!({})),
+        tool_use_id: arguments
+            .get("tool_use_id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    };
+    let response = client
+        .post(&endpoint.url)
+        .bearer_auth(&endpoint.token)
+        .json(&call)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let decision = match response {
+        Ok(response) => response.json::<Value>().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+    .unwrap_or_else(|e| {
+        permission_decision(
+            &call.input,
+            false,
+            Some(&format!("opcode could not be asked for approval: {}", e)),
+        )
+    });
+    json!({ "content": [{ "type": "text", "text": decision.to_string() }] })
+}
+
+/// Serve `--endpoint <url> --run-id <id>`, the arguments after `APPROVAL_MCP_COMMAND`, with
+/// the endpoint token from `TOKEN_ENV`
+pub fn serve_from_args(args: &[String]) -> Result<(), String> {
+    let mut url = None;
+    let mut run_id = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--endpoint" => url = args.next().cloned(),
+            "--run-id" => run_id = args.next().and_then(|id| id.parse::<i64>().ok()),
+            other => return Err(format!("Unexpected argument {}", other)),
+        }
+    }
+    let endpoint = ApprovalEndpoint {
+        url: url.ok_or("--endpoint is required")?,
+        token: std::env::var(TOKEN_ENV).map_err(|_| format!("{} is not set", TOKEN_ENV))?,
+    };
+    let run_id = run_id.ok_or("--run-id needs a run id")?;
+    tauri::async_runtime::block_on(serve_stdio(endpoint, run_id))
+}
+
+/// Serve the approval MCP server on stdin/stdout until the CLI closes it. Runs inside
+/// `opcode approval-mcp` (see `serve_from_args`) or `opcode-cli approval-mcp`.
+pub async fn serve_stdio(endpoint: ApprovalEndpoint, run_id: i64) -> Result<(), String> {
+    let client = reqwest::Client::new();
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let response = if message.get("method").and_then(Value::as_str) == Some("tools/call") {
+            let Some(id) = message.get("id").cloned() else {
+                continue;
+            };
+            let arguments = message
+                .get("params")
+                .and_then(|params| params.get("arguments"))
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            let result = call_approve(&client, &endpoint, run_id, &arguments).await;
+            json!({ "jsonrpc": "2.0", "id": id, "result": result })
+        } else {
+            match protocol_response(&message) {
+                Some(response) => response,
+                None => continue,
+            }
+        };
+        writeln!(stdout, "{}", response).map_err(|e| e.to_string())?;
+        stdout.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Whether an agent's runs ask the user before using tools
+#[tauri::command]
+pub async fn get_agent_approval(db: State<'_, AgentDb>, agent_id: i64) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_approval_enabled(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Turn approval of tool use on or off for an agent's runs
+#[tauri::command]
+pub async fn set_agent_approval(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    enabled: bool,
+) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO agent_approval_settings (agent_id, enabled) VALUES (?1, ?2)
+         ON CONFLICT(agent_id) DO UPDATE SET enabled = ?2, updated_at = CURRENT_TIMESTAMP",
+        params![agent_id, enabled],
+    )
+    .map_err(|e| format!("Failed to save approval setting: {}", e))?;
+    Ok(enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_args_replace_skipped_permissions() {
+        let endpoint = ApprovalEndpoint {
+            url: "http://127.0.0.1:4000/approve".to_string(),
+            token: "secret".to_string(),
+        };
+        let args = approval_args(
+            vec![
+                "-p".to_string(),
+                "task".to_string(),
+                "--dangerously-skip-permissions".to_string(),
+            ],
+            Path::new("/opt/opcode/opcode-cli"),
+            &endpoint,
+            12,
+        );
+        assert_eq!(args.len(), 6);
+        assert_eq!(args[2], "--mcp-config");
+        assert_eq!(args[4], "--permission-prompt-tool");
+        assert_eq!(args[5], PERMISSION_PROMPT_TOOL);
+
+        let config: Value = serde_json::from_str(&args[3]).unwrap();
+        let server = &config["mcpServers"][SERVER_NAME];
+        assert_eq!(server["command"], "/opt/opcode/opcode-cli");
+        assert_eq!(
+            server["args"],
+            json!(["approval-mcp", "--endpoint", endpoint.url, "--run-id", "12"])
+        );
+        assert_eq!(server["env"][TOKEN_ENV], "secret");
+    }
+
+    #[test]
+    fn test_protocol_responses() {
+        let init =
+            protocol_response(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"})).unwrap();
+        assert_eq!(init["result"]["serverInfo"]["name"], SERVER_NAME);
+
+        let tools =
+            protocol_response(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"})).unwrap();
+        assert_eq!(tools["result"]["tools"][0]["name"], "approve");
+
+        assert!(protocol_response(
+            &json!({"jsonrpc": "2.0", "method": "notifications/initialized"})
+        )
+        .is_none());
+        let unknown =
+            protocol_response(&json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list"}))
+                .unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_approval_setting_defaults_to_off() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_approval_tables(&conn).unwrap();
+        assert!(!load_approval_enabled(&conn, 1).unwrap());
+        conn.execute(
+            "INSERT INTO agent_approval_settings (agent_id, enabled) VALUES (1, 1)",
+            [],
+        )
+        .unwrap();
+        assert!(load_approval_enabled(&conn, 1).unwrap());
+    }
+}
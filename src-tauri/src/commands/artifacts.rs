@@ -0,0 +1,424 @@
+#![allow(dead_code)]
+
+//! Output artifacts of agent runs. Agents declare globs relative to the project (e.g.
+//! `reports/*.md`, `coverage/**`); when a run finishes, files matching them that the run
+//! wrote are copied to `<app data>/artifacts/<run id>/`, so they survive later runs.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+
+/// Upper bound on globs per agent
+const MAX_GLOBS: usize = 20;
+
+/// Upper bound on files copied per run, so a broad glob can't copy the whole project
+const MAX_ARTIFACTS_PER_RUN: usize = 200;
+
+/// Files larger than this are left in the working tree
+const MAX_ARTIFACT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Directories never searched for artifacts
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules"];
+
+/// A file kept from an agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunArtifact {
+    pub id: i64,
+    pub run_id: i64,
+    /// Path relative to the project
+    pub path: String,
+    /// Where the copy is kept
+    pub stored_path: String,
+    pub size_bytes: i64,
+    pub created_at: String,
+}
+
+/// Create the artifact tables
+pub fn init_artifact_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_artifact_globs (
+            agent_id INTEGER PRIMARY KEY,
+            globs TEXT NOT NULL DEFAULT '[]',
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_artifacts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            stored_path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_run_artifacts_run ON run_artifacts(run_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+const ARTIFACT_COLUMNS: &str = "id, run_id, path, stored_path, size_bytes, created_at";
+
+fn artifact_from_row(row: &rusqlite::Row) -> SqliteResult<RunArtifact> {
+    Ok(RunArtifact {
+        id: row.get(0)?,
+        run_id: row.get(1)?,
+        path: row.get(2)?,
+        stored_path: row.get(3)?,
+        size_bytes: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Check and normalize artifact globs before saving them
+pub fn validate_globs(globs: &[String]) -> Result<Vec<String>, String> {
+    if globs.len() > MAX_GLOBS {
+        return Err(format!("At most {} artifact globs are allowed", MAX_GLOBS));
+    }
+    let mut normalized = Vec::new();
+    for glob in globs {
+        let glob = glob.trim().replace('\\', "/");
+        if glob.is_empty() {
+            return Err("Artifact globs cannot be empty".to_string());
+        }
+        if glob.starts_with('/') || glob.contains(':') || glob.split('/').any(|part| part == "..") {
+            return Err(format!("Artifact glob '{}' must stay inside the project", glob));
+        }
+        glob::Pattern::new(&glob).map_err(|e| format!("Invalid artifact glob '{}': {}", glob, e))?;
+        if !normalized.contains(&glob) {
+            normalized.push(glob);
+        }
+    }
+    Ok(normalized)
+}
+
+/// Artifact globs declared for an agent
+pub fn load_artifact_globs(conn: &Connection, agent_id: i64) -> SqliteResult<Vec<String>> {
+    let globs: Option<String> = conn
+        .query_row(
+            "SELECT globs FROM agent_artifact_globs WHERE agent_id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(globs
+        .and_then(|g| serde_json::from_str(&g).ok())
+        .unwrap_or_default())
+}
+
+/// Project files matching the globs, relative to the project and sorted. With `since`, only
+/// files modified at or after it are returned.
+pub fn match_artifacts(project: &Path, globs: &[String], since: Option<SystemTime>) -> Vec<PathBuf> {
+    let patterns: Vec<glob::Pattern> = globs
+        .iter()
+        .filter_map(|glob| glob::Pattern::new(glob).ok())
+        .collect();
+    if patterns.is_empty() {
+        return Vec::new();
+    }
+    let options = glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+
+    let mut matches: Vec<PathBuf> = walkdir::WalkDir::new(project)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !SKIPPED_DIRS.iter().any(|dir| entry.file_name() == *dir)
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| match (since, entry.metadata().ok().and_then(|m| m.modified().ok())) {
+            (Some(since), Some(modified)) => modified >= since,
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .filter_map(|entry| entry.path().strip_prefix(project).ok().map(|p| p.to_path_buf()))
+        .filter(|relative| {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches_with(&relative, options))
+        })
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Directory holding the artifacts of every run
+fn artifacts_root(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("artifacts"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Artifacts directory next to the agents database, for code that only has the database path
+pub fn artifacts_root_for_db(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|dir| dir.join("artifacts"))
+        .unwrap_or_else(|| PathBuf::from("artifacts"))
+}
+
+/// Copy the files an agent's artifact globs match into `<artifacts_root>/<run id>/` and record
+/// them. Only files modified since the run started are kept.
+pub fn collect_run_artifacts(
+    conn: &Connection,
+    artifacts_root: &Path,
+    run_id: i64,
+    agent_id: i64,
+    project_path: &str,
+    since: SystemTime,
+) -> Result<Vec<RunArtifact>, String> {
+    let globs = load_artifact_globs(conn, agent_id).map_err(|e| e.to_string())?;
+    if globs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let project = Path::new(project_path);
+    let run_dir = artifacts_root.join(run_id.to_string());
+    let matches = match_artifacts(project, &globs, Some(since));
+    if matches.len() > MAX_ARTIFACTS_PER_RUN {
+        log::warn!(
+            "Run {} matched {} artifacts; keeping the first {}",
+            run_id,
+            matches.len(),
+            MAX_ARTIFACTS_PER_RUN
+        );
+    }
+
+    for relative in matches.into_iter().take(MAX_ARTIFACTS_PER_RUN) {
+        let source = project.join(&relative);
+        let size = match std::fs::metadata(&source) {
+            Ok(metadata) if metadata.len() <= MAX_ARTIFACT_BYTES => metadata.len(),
+            Ok(_) => {
+                log::warn!("Skipping artifact {} of run {}: too large", relative.display(), run_id);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let target = run_dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create artifacts directory: {}", e))?;
+        }
+        if let Err(e) = std::fs::copy(&source, &target) {
+            log::warn!("Failed to copy artifact {} of run {}: {}", relative.display(), run_id, e);
+            continue;
+        }
+        if let Err(e) = super::encryption::encrypt_file(&target) {
+            log::warn!("Dropping artifact {} of run {}: {}", relative.display(), run_id, e);
+            let _ = std::fs::remove_file(&target);
+            continue;
+        }
+        conn.execute(
+            "INSERT INTO run_artifacts (run_id, path, stored_path, size_bytes) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                run_id,
+                relative.to_string_lossy().replace('\\', "/"),
+                target.to_string_lossy(),
+                size as i64
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    load_run_artifacts(conn, run_id).map_err(|e| e.to_string())
+}
+
+fn load_run_artifacts(conn: &Connection, run_id: i64) -> SqliteResult<Vec<RunArtifact>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM run_artifacts WHERE run_id = ?1 ORDER BY path",
+        ARTIFACT_COLUMNS
+    ))?;
+    let artifacts = stmt
+        .query_map(params![run_id], artifact_from_row)?
+        .collect::<SqliteResult<Vec<_>>>();
+    artifacts
+}
+
+/// Open a file with the system's default application
+fn open_with_system(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+/// Get the artifact globs declared for an agent
+#[tauri::command]
+pub async fn get_agent_artifact_globs(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_artifact_globs(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Set the artifact globs for an agent; an empty list turns collection off
+#[tauri::command]
+pub async fn set_agent_artifact_globs(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    globs: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let globs = validate_globs(&globs)?;
+    let json = serde_json::to_string(&globs)
+        .map_err(|e| format!("Failed to serialize artifact globs: {}", e))?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO agent_artifact_globs (agent_id, globs) VALUES (?1, ?2)
+         ON CONFLICT(agent_id) DO UPDATE SET globs = ?2, updated_at = CURRENT_TIMESTAMP",
+        params![agent_id, json],
+    )
+    .map_err(|e| format!("Failed to save artifact globs: {}", e))?;
+
+    Ok(globs)
+}
+
+/// List the artifacts kept from a run
+#[tauri::command]
+pub async fn list_run_artifacts(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<RunArtifact>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_run_artifacts(&conn, run_id).map_err(|e| e.to_string())
+}
+
+/// Open a kept artifact with the system's default application
+#[tauri::command]
+pub async fn open_artifact(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<RunArtifact, String> {
+    let artifact = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            &format!("SELECT {} FROM run_artifacts WHERE id = ?1", ARTIFACT_COLUMNS),
+            params![id],
+            artifact_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Artifact {} not found", id))?
+    };
+
+    // Only open files under the artifacts directory, whatever the database says
+    let stored = PathBuf::from(&artifact.stored_path);
+    let root = artifacts_root(&app)?;
+    let inside = match (stored.canonicalize(), root.canonicalize()) {
+        (Ok(stored), Ok(root)) => stored.starts_with(root),
+        _ => false,
+    };
+    if !inside {
+        return Err(format!("Artifact file is missing: {}", artifact.path));
+    }
+
+    // Encrypted artifacts are opened from a decrypted copy in the temp directory
+    let raw = std::fs::read(&stored).map_err(|e| e.to_string())?;
+    if super::encryption::is_encrypted_data(&raw) {
+        let data = super::encryption::read_file(&stored)?;
+        let copy = std::env::temp_dir()
+            .join("opcode-artifacts")
+            .join(id.to_string())
+            .join(stored.file_name().unwrap_or_default());
+        if let Some(parent) = copy.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&copy, data).map_err(|e| format!("Failed to decrypt artifact: {}", e))?;
+        open_with_system(&copy)?;
+    } else {
+        open_with_system(&stored)?;
+    }
+    Ok(artifact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_globs_keeps_them_inside_the_project() {
+        let globs = vec![" reports/*.md ".to_string(), "coverage/**".to_string(), "reports/*.md".to_string()];
+        assert_eq!(validate_globs(&globs).unwrap(), vec!["reports/*.md", "coverage/**"]);
+        assert!(validate_globs(&["../secrets/*".to_string()]).is_err());
+        assert!(validate_globs(&["/etc/*".to_string()]).is_err());
+        assert!(validate_globs(&["reports/[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_collect_copies_matching_files() {
+        let project = tempfile::tempdir().unwrap();
+        let root = project.path();
+        std::fs::create_dir_all(root.join("reports")).unwrap();
+        std::fs::create_dir_all(root.join("coverage/html")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules/pkg/reports")).unwrap();
+        std::fs::write(root.join("reports/summary.md"), "# Summary").unwrap();
+        std::fs::write(root.join("reports/data.json"), "{}").unwrap();
+        std::fs::write(root.join("coverage/html/index.html"), "<html>").unwrap();
+        std::fs::write(root.join("node_modules/pkg/reports/x.md"), "x").unwrap();
+
+        let globs = vec!["reports/*.md".to_string(), "coverage/**".to_string()];
+        assert_eq!(
+            match_artifacts(root, &globs, None),
+            vec![PathBuf::from("coverage/html/index.html"), PathBuf::from("reports/summary.md")]
+        );
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        init_artifact_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agent_artifact_globs (agent_id, globs) VALUES (1, ?1)",
+            params![serde_json::to_string(&globs).unwrap()],
+        )
+        .unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let artifacts = collect_run_artifacts(
+            &conn,
+            store.path(),
+            7,
+            1,
+            &root.to_string_lossy(),
+            SystemTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        assert_eq!(artifacts.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(store.path().join("7/reports/summary.md")).unwrap(),
+            "# Summary"
+        );
+
+        // Nothing written since the run started, nothing kept
+        let later = SystemTime::now() + std::time::Duration::from_secs(60);
+        assert!(match_artifacts(root, &globs, Some(later)).is_empty());
+    }
+}
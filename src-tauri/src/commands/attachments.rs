@@ -0,0 +1,366 @@
+#![allow(dead_code)]
+
+//! Files and pasted images attached to session prompts.
+//!
+//! Files inside the project are referenced where they are. Anything else (files from
+//! elsewhere, clipboard images) is copied into a per-project staging directory under the
+//! system temp dir, which is passed to Claude with `--add-dir`. The prompt gets an
+//! `@path` reference per attachment so Claude reads them.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Images larger than this are rejected by the API
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Limit for text files and PDFs
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Staged files older than this are removed when new ones are staged
+const STAGED_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    Image,
+    Pdf,
+    Text,
+}
+
+/// An attachment ready to be passed to a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StagedAttachment {
+    /// Original file name, for display
+    pub name: String,
+    /// Absolute path Claude will read
+    pub path: String,
+    pub kind: AttachmentKind,
+    pub mime_type: String,
+    pub size: u64,
+    /// True if the file was copied into the staging directory
+    pub staged: bool,
+}
+
+/// Root of all staging directories
+fn staging_root() -> PathBuf {
+    std::env::temp_dir().join("opcode-attachments")
+}
+
+/// Staging directory for one project
+pub fn staging_dir(project_path: &str) -> PathBuf {
+    let digest = Sha256::digest(project_path.as_bytes());
+    let key: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    staging_root().join(key)
+}
+
+fn image_mime(extension: &str) -> Option<&'static str> {
+    IMAGE_TYPES
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|(_, mime)| *mime)
+}
+
+/// Determine the kind of a file from its extension and first bytes
+fn classify(path: &Path, size: u64) -> Result<(AttachmentKind, String), String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let name = path.display();
+
+    if let Some(mime) = image_mime(&extension) {
+        if size > MAX_IMAGE_BYTES {
+            return Err(format!("{} is larger than the 5 MB image limit", name));
+        }
+        return Ok((AttachmentKind::Image, mime.to_string()));
+    }
+    if size > MAX_FILE_BYTES {
+        return Err(format!("{} is larger than the 10 MB attachment limit", name));
+    }
+    if extension == "pdf" {
+        return Ok((AttachmentKind::Pdf, "application/pdf".to_string()));
+    }
+
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(8192).read_to_end(&mut head))
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    let valid_utf8 = match std::str::from_utf8(&head) {
+        Ok(_) => true,
+        // A multi-byte character cut off at the end of the sample is fine
+        Err(e) => e.error_len().is_none(),
+    };
+    if head.contains(&0) || !valid_utf8 {
+        return Err(format!(
+            "{} is a binary file; only text, PDFs and images can be attached",
+            name
+        ));
+    }
+    Ok((AttachmentKind::Text, "text/plain".to_string()))
+}
+
+/// Replace characters that would break an `@path` reference
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    if sanitized.trim_matches('.').is_empty() {
+        "attachment".to_string()
+    } else {
+        sanitized
+    }
+}
+
+/// Remove staged files older than `STAGED_MAX_AGE`
+fn prune_staging_dir(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > STAGED_MAX_AGE);
+        if expired {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn write_staged(project_path: &str, name: &str, bytes: &[u8]) -> Result<PathBuf, String> {
+    let dir = staging_dir(project_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    prune_staging_dir(&dir);
+    let path = dir.join(format!(
+        "{}-{}",
+        &uuid::Uuid::new_v4().simple().to_string()[..8],
+        sanitize_file_name(name)
+    ));
+    fs::write(&path, bytes).map_err(|e| format!("Failed to stage attachment: {}", e))?;
+    Ok(path)
+}
+
+/// Validate a local file and stage it if Claude can't reach it from the project
+pub fn stage_file(project_path: &str, source_path: &str) -> Result<StagedAttachment, String> {
+    let source = Path::new(source_path);
+    let metadata =
+        fs::metadata(source).map_err(|e| format!("Cannot attach {}: {}", source_path, e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", source_path));
+    }
+    let (kind, mime_type) = classify(source, metadata.len())?;
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "attachment".to_string());
+
+    // Files in the project are referenced in place unless their path can't be written as @path
+    let source = source
+        .canonicalize()
+        .map_err(|e| format!("Cannot attach {}: {}", source_path, e))?;
+    let in_project = Path::new(project_path)
+        .canonicalize()
+        .is_ok_and(|project| source.starts_with(project));
+    let referencable = !source.to_string_lossy().chars().any(char::is_whitespace);
+    if in_project && referencable {
+        return Ok(StagedAttachment {
+            name,
+            path: source.to_string_lossy().to_string(),
+            kind,
+            mime_type,
+            size: metadata.len(),
+            staged: false,
+        });
+    }
+
+    let bytes = fs::read(&source).map_err(|e| format!("Failed to read {}: {}", source_path, e))?;
+    let path = write_staged(project_path, &name, &bytes)?;
+    Ok(StagedAttachment {
+        name,
+        path: path.to_string_lossy().to_string(),
+        kind,
+        mime_type,
+        size: metadata.len(),
+        staged: true,
+    })
+}
+
+/// Stage a pasted image given as base64, optionally as a `data:` URL
+pub fn stage_image_data(
+    project_path: &str,
+    data: &str,
+    mime_type: &str,
+) -> Result<StagedAttachment, String> {
+    let (extension, mime) = IMAGE_TYPES
+        .iter()
+        .find(|(_, mime)| mime.eq_ignore_ascii_case(mime_type))
+        .ok_or_else(|| format!("Unsupported image type: {}", mime_type))?;
+    let encoded = match data.split_once(";base64,") {
+        Some((_, encoded)) => encoded,
+        None => data,
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Invalid image data: {}", e))?;
+    if bytes.len() as u64 > MAX_IMAGE_BYTES {
+        return Err("Pasted image is larger than the 5 MB image limit".to_string());
+    }
+
+    let name = format!("pasted-{}.{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), extension);
+    let path = write_staged(project_path, &name, &bytes)?;
+    Ok(StagedAttachment {
+        name,
+        path: path.to_string_lossy().to_string(),
+        kind: AttachmentKind::Image,
+        mime_type: mime.to_string(),
+        size: bytes.len() as u64,
+        staged: true,
+    })
+}
+
+/// Add attachment references to a prompt and return the extra CLI arguments they need.
+/// Attachments must be in the project or its staging directory.
+pub fn apply_attachments(
+    project_path: &str,
+    prompt: &str,
+    attachments: &[StagedAttachment],
+) -> Result<(String, Vec<String>), String> {
+    if attachments.is_empty() {
+        return Ok((prompt.to_string(), Vec::new()));
+    }
+    let staging = staging_dir(project_path);
+    let project = Path::new(project_path)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(project_path));
+
+    let mut references = Vec::new();
+    let mut uses_staging = false;
+    for attachment in attachments {
+        let path = Path::new(&attachment.path);
+        if !path.is_file() {
+            return Err(format!("Attachment {} no longer exists", attachment.name));
+        }
+        if path.starts_with(&staging) {
+            uses_staging = true;
+        } else if !path.starts_with(&project) {
+            return Err(format!(
+                "Attachment {} must be staged before it can be sent",
+                attachment.name
+            ));
+        }
+        references.push(format!("@{}", attachment.path));
+    }
+
+    let prompt = format!("{}\n\nAttachments:\n{}", prompt.trim_end(), references.join("\n"));
+    let args = if uses_staging {
+        vec!["--add-dir".to_string(), staging.to_string_lossy().to_string()]
+    } else {
+        Vec::new()
+    };
+    Ok((prompt, args))
+}
+
+/// Validate a local file for use as a prompt attachment, staging it if needed
+#[tauri::command]
+pub async fn stage_attachment(
+    project_path: String,
+    file_path: String,
+) -> Result<StagedAttachment, String> {
+    stage_file(&project_path, &file_path)
+}
+
+/// Stage an image pasted from the clipboard (base64 or data URL)
+#[tauri::command]
+pub async fn stage_clipboard_image(
+    project_path: String,
+    data: String,
+    mime_type: String,
+) -> Result<StagedAttachment, String> {
+    stage_image_data(&project_path, &data, &mime_type)
+}
+
+/// Remove all staged attachments for a project
+#[tauri::command]
+pub async fn clear_staged_attachments(project_path: String) -> Result<(), String> {
+    let dir = staging_dir(&project_path);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear attachments: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_file_references_project_files_in_place() {
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let project_path = project.path().to_string_lossy().to_string();
+        fs::write(project.path().join("notes.md"), "# Notes").unwrap();
+        fs::write(outside.path().join("log file.txt"), "error: boom").unwrap();
+        fs::write(outside.path().join("blob.bin"), [0u8, 1, 2, 3]).unwrap();
+
+        let local = stage_file(&project_path, &project.path().join("notes.md").to_string_lossy())
+            .unwrap();
+        assert!(!local.staged);
+        assert_eq!(local.kind, AttachmentKind::Text);
+
+        let external =
+            stage_file(&project_path, &outside.path().join("log file.txt").to_string_lossy())
+                .unwrap();
+        assert!(external.staged);
+        assert!(external.path.ends_with("-log_file.txt"));
+
+        assert!(stage_file(&project_path, &outside.path().join("blob.bin").to_string_lossy()).is_err());
+
+        let (prompt, args) = apply_attachments(&project_path, "Fix it", &[local, external.clone()]).unwrap();
+        assert!(prompt.starts_with("Fix it\n\nAttachments:\n@"));
+        assert!(prompt.ends_with(&format!("@{}", external.path)));
+        assert_eq!(args[0], "--add-dir");
+
+        fs::remove_dir_all(staging_dir(&project_path)).unwrap();
+    }
+
+    #[test]
+    fn test_stage_image_data_checks_type_and_size() {
+        let project_path = "/tmp/opcode-attachment-test-project";
+        let data = format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(b"\x89PNG fake")
+        );
+        let image = stage_image_data(project_path, &data, "image/png").unwrap();
+        assert_eq!(image.kind, AttachmentKind::Image);
+        assert_eq!(fs::read(&image.path).unwrap(), b"\x89PNG fake");
+
+        assert!(stage_image_data(project_path, &data, "image/tiff").is_err());
+        let outside = PathBuf::from("/etc/hosts");
+        let attachment = StagedAttachment {
+            path: outside.to_string_lossy().to_string(),
+            staged: false,
+            ..image
+        };
+        assert!(apply_attachments(project_path, "hi", &[attachment]).is_err());
+
+        fs::remove_dir_all(staging_dir(project_path)).unwrap();
+    }
+}
@@ -0,0 +1,477 @@
+#![allow(dead_code)]
+
+//! How the `claude` CLI authenticates, worked out from the same places it looks: the
+//! environment (including the `env` block of `~/.claude/settings.json`), an `apiKeyHelper`,
+//! the claude.ai OAuth credentials and the account recorded in `~/.claude.json`. Used to
+//! explain runs failing with 401s, e.g. a stale `ANTHROPIC_API_KEY` overriding a working
+//! subscription login, or an expired OAuth token.
+//!
+//! Nothing here talks to the API, so a key that is present but revoked still looks fine.
+
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Environment variables the CLI reads for authentication
+pub(crate) const AUTH_ENV_VARS: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "ANTHROPIC_AUTH_TOKEN",
+    "ANTHROPIC_BASE_URL",
+    "CLAUDE_CODE_USE_BEDROCK",
+    "CLAUDE_CODE_USE_VERTEX",
+    "AWS_REGION",
+    "AWS_PROFILE",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "AWS_BEARER_TOKEN_BEDROCK",
+    "CLOUD_ML_REGION",
+    "ANTHROPIC_VERTEX_PROJECT_ID",
+    "GOOGLE_APPLICATION_CREDENTIALS",
+];
+
+/// Keychain item the CLI keeps its OAuth credentials in on macOS
+const MACOS_CREDENTIALS_SERVICE: &str = "Claude Code-credentials";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// claude.ai subscription login
+    ClaudeAi,
+    /// Console API key stored by `claude login`
+    ConsoleApiKey,
+    ApiKeyEnv,
+    AuthTokenEnv,
+    ApiKeyHelper,
+    Bedrock,
+    Vertex,
+    None,
+}
+
+/// The logged-in account from `~/.claude.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuthAccount {
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub account_uuid: Option<String>,
+    pub organization_name: Option<String>,
+    pub organization_uuid: Option<String>,
+    pub organization_role: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuthStatus {
+    /// Method the CLI will use
+    pub method: AuthMethod,
+    /// Where the credential comes from, e.g. `environment` or `settings.json env`
+    pub source: Option<String>,
+    pub credentials_present: bool,
+    /// RFC 3339 expiry of the OAuth access token
+    pub expires_at: Option<String>,
+    /// `None` when the expiry can't be determined
+    pub expired: Option<bool>,
+    /// `pro`, `max`, ... for claude.ai logins
+    pub subscription_type: Option<String>,
+    pub account: Option<AuthAccount>,
+    /// Last characters of the API key in use
+    pub key_hint: Option<String>,
+    pub base_url: Option<String>,
+    /// Other configured methods the active one takes precedence over
+    pub overridden: Vec<AuthMethod>,
+    /// Likely causes of authentication failures, for the user to act on
+    pub problems: Vec<String>,
+}
+
+/// Everything the status is derived from, gathered up front so resolution is pure
+#[derive(Debug, Clone, Default)]
+pub struct AuthSources {
+    /// Process environment, limited to `AUTH_ENV_VARS`
+    pub env: BTreeMap<String, String>,
+    /// `env` block of `~/.claude/settings.json`
+    pub settings_env: BTreeMap<String, String>,
+    pub api_key_helper: Option<String>,
+    /// `~/.claude.json`
+    pub claude_json: Value,
+    /// `claudeAiOauth` entry of the CLI's stored credentials
+    pub oauth: Option<Value>,
+    pub now_ms: i64,
+}
+
+impl AuthSources {
+    /// A variable and where it is set; settings override the process environment, as in the CLI
+    fn var(&self, name: &str) -> Option<(&str, &'static str)> {
+        fn lookup<'a>(map: &'a BTreeMap<String, String>, name: &str) -> Option<&'a str> {
+            map.get(name)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        }
+        lookup(&self.settings_env, name)
+            .map(|value| (value, "settings.json env"))
+            .or_else(|| lookup(&self.env, name).map(|value| (value, "environment")))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.var(name)
+            .is_some_and(|(value, _)| !matches!(value, "0" | "false"))
+    }
+}
+
+fn key_hint(key: &str) -> String {
+    let tail: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("…{}", tail)
+}
+
+fn account(claude_json: &Value) -> Option<AuthAccount> {
+    let oauth = claude_json.get("oauthAccount")?;
+    let field = |key: &str| oauth.get(key).and_then(Value::as_str).map(str::to_string);
+    Some(AuthAccount {
+        email: field("emailAddress"),
+        display_name: field("displayName"),
+        account_uuid: field("accountUuid"),
+        organization_name: field("organizationName"),
+        organization_uuid: field("organizationUuid"),
+        organization_role: field("organizationRole"),
+    })
+}
+
+/// Work out the active method and its problems
+pub fn resolve(sources: &AuthSources) -> AuthStatus {
+    let mut status = AuthStatus {
+        method: AuthMethod::None,
+        source: None,
+        credentials_present: false,
+        expires_at: None,
+        expired: None,
+        subscription_type: None,
+        account: account(&sources.claude_json),
+        key_hint: None,
+        base_url: sources
+            .var("ANTHROPIC_BASE_URL")
+            .map(|(url, _)| url.to_string()),
+        overridden: Vec::new(),
+        problems: Vec::new(),
+    };
+
+    let console_key = sources
+        .claude_json
+        .get("primaryApiKey")
+        .and_then(Value::as_str)
+        .filter(|key| !key.is_empty());
+    let oauth_token = sources
+        .oauth
+        .as_ref()
+        .and_then(|oauth| oauth.get("accessToken"))
+        .and_then(Value::as_str)
+        .filter(|token| !token.is_empty());
+
+    // Highest precedence first, as the CLI picks them
+    let mut configured = Vec::new();
+    if sources.flag("CLAUDE_CODE_USE_BEDROCK") {
+        configured.push(AuthMethod::Bedrock);
+    }
+    if sources.flag("CLAUDE_CODE_USE_VERTEX") {
+        configured.push(AuthMethod::Vertex);
+    }
+    if sources.var("ANTHROPIC_AUTH_TOKEN").is_some() {
+        configured.push(AuthMethod::AuthTokenEnv);
+    }
+    if sources.var("ANTHROPIC_API_KEY").is_some() {
+        configured.push(AuthMethod::ApiKeyEnv);
+    }
+    if sources.api_key_helper.is_some() {
+        configured.push(AuthMethod::ApiKeyHelper);
+    }
+    if oauth_token.is_some() {
+        configured.push(AuthMethod::ClaudeAi);
+    }
+    if console_key.is_some() {
+        configured.push(AuthMethod::ConsoleApiKey);
+    }
+
+    let Some(&method) = configured.first() else {
+        status
+            .problems
+            .push("No credentials found: run `claude login` or set ANTHROPIC_API_KEY".to_string());
+        return status;
+    };
+    status.method = method;
+    status.overridden = configured[1..].to_vec();
+
+    match method {
+        AuthMethod::Bedrock => {
+            status.source = sources
+                .var("CLAUDE_CODE_USE_BEDROCK")
+                .map(|(_, s)| s.to_string());
+            status.credentials_present = [
+                "AWS_PROFILE",
+                "AWS_ACCESS_KEY_ID",
+                "AWS_BEARER_TOKEN_BEDROCK",
+            ]
+            .iter()
+            .any(|name| sources.var(name).is_some())
+                || dirs::home_dir()
+                    .is_some_and(|home| home.join(".aws").join("credentials").is_file());
+            if sources.var("AWS_REGION").is_none() {
+                status
+                    .problems
+                    .push("Bedrock is enabled but AWS_REGION is not set".to_string());
+            }
+            if !status.credentials_present {
+                status
+                    .problems
+                    .push("Bedrock is enabled but no AWS credentials were found".to_string());
+            }
+        }
+        AuthMethod::Vertex => {
+            status.source = sources
+                .var("CLAUDE_CODE_USE_VERTEX")
+                .map(|(_, s)| s.to_string());
+            status.credentials_present = sources.var("GOOGLE_APPLICATION_CREDENTIALS").is_some()
+                || dirs::config_dir().is_some_and(|config| {
+                    config
+                        .join("gcloud")
+                        .join("application_default_credentials.json")
+                        .is_file()
+                });
+            for required in ["CLOUD_ML_REGION", "ANTHROPIC_VERTEX_PROJECT_ID"] {
+                if sources.var(required).is_none() {
+                    status
+                        .problems
+                        .push(format!("Vertex is enabled but {} is not set", required));
+                }
+            }
+            if !status.credentials_present {
+                status.problems.push(
+                    "Vertex is enabled but no Google application credentials were found"
+                        .to_string(),
+                );
+            }
+        }
+        AuthMethod::AuthTokenEnv | AuthMethod::ApiKeyEnv => {
+            let name = if method == AuthMethod::AuthTokenEnv {
+                "ANTHROPIC_AUTH_TOKEN"
+            } else {
+                "ANTHROPIC_API_KEY"
+            };
+            let (key, source) = sources.var(name).unwrap_or_default();
+            status.source = Some(source.to_string());
+            status.credentials_present = true;
+            status.key_hint = Some(key_hint(key));
+            if method == AuthMethod::ApiKeyEnv
+                && status.base_url.is_none()
+                && !key.starts_with("sk-ant-")
+            {
+                status
+                    .problems
+                    .push("ANTHROPIC_API_KEY does not look like an Anthropic API key".to_string());
+            }
+        }
+        AuthMethod::ApiKeyHelper => {
+            status.source = Some("settings.json apiKeyHelper".to_string());
+            status.credentials_present = true;
+        }
+        AuthMethod::ClaudeAi => {
+            let oauth = sources.oauth.as_ref();
+            status.source = Some("claude.ai login".to_string());
+            status.credentials_present = true;
+            status.subscription_type = oauth
+                .and_then(|oauth| oauth.get("subscriptionType"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let expires_ms = oauth
+                .and_then(|oauth| oauth.get("expiresAt"))
+                .and_then(Value::as_i64);
+            if let Some(expires_ms) = expires_ms {
+                status.expires_at = Utc
+                    .timestamp_millis_opt(expires_ms)
+                    .single()
+                    .map(|at| at.to_rfc3339());
+                let expired = expires_ms <= sources.now_ms;
+                status.expired = Some(expired);
+                let refreshable = oauth
+                    .and_then(|oauth| oauth.get("refreshToken"))
+                    .and_then(Value::as_str)
+                    .is_some_and(|token| !token.is_empty());
+                if expired && !refreshable {
+                    status
+                        .problems
+                        .push("The claude.ai login has expired: run `claude login`".to_string());
+                }
+            }
+        }
+        AuthMethod::ConsoleApiKey => {
+            status.source = Some("~/.claude.json".to_string());
+            status.credentials_present = true;
+            status.key_hint = console_key.map(key_hint);
+        }
+        AuthMethod::None => {}
+    }
+
+    if matches!(method, AuthMethod::ApiKeyEnv | AuthMethod::AuthTokenEnv)
+        && status.overridden.contains(&AuthMethod::ClaudeAi)
+    {
+        status.problems.push(format!(
+            "{} takes precedence over the claude.ai login; unset it to use the subscription",
+            if method == AuthMethod::ApiKeyEnv {
+                "ANTHROPIC_API_KEY"
+            } else {
+                "ANTHROPIC_AUTH_TOKEN"
+            }
+        ));
+    }
+    status
+}
+
+/// The CLI's stored OAuth credentials: the keychain on macOS, `~/.claude/.credentials.json`
+/// elsewhere
+fn load_oauth_credentials() -> Option<Value> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("security")
+            .args([
+                "find-generic-password",
+                "-s",
+                MACOS_CREDENTIALS_SERVICE,
+                "-w",
+            ])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success());
+        if let Some(output) = output {
+            let credentials: Value = serde_json::from_slice(&output.stdout).ok()?;
+            return credentials.get("claudeAiOauth").cloned();
+        }
+    }
+    let path = dirs::home_dir()?.join(".claude").join(".credentials.json");
+    let credentials: Value = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    credentials.get("claudeAiOauth").cloned()
+}
+
+fn read_json(path: std::path::PathBuf) -> Value {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or(Value::Null)
+}
+
+fn gather_sources() -> AuthSources {
+    let home = dirs::home_dir();
+    let settings = home
+        .as_ref()
+        .map(|home| read_json(home.join(".claude").join("settings.json")))
+        .unwrap_or(Value::Null);
+    AuthSources {
+        env: AUTH_ENV_VARS
+            .iter()
+            .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+            .collect(),
+        settings_env: settings
+            .get("env")
+            .and_then(Value::as_object)
+            .map(|env| {
+                env.iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        api_key_helper: settings
+            .get("apiKeyHelper")
+            .and_then(Value::as_str)
+            .filter(|helper| !helper.trim().is_empty())
+            .map(str::to_string),
+        claude_json: home
+            .map(|home| read_json(home.join(".claude.json")))
+            .unwrap_or(Value::Null),
+        oauth: load_oauth_credentials(),
+        now_ms: Utc::now().timestamp_millis(),
+    }
+}
+
+/// How the CLI is authenticated and what is likely wrong with it
+#[tauri::command]
+pub async fn get_auth_status() -> Result<AuthStatus, String> {
+    tauri::async_runtime::spawn_blocking(|| resolve(&gather_sources()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn env(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_api_key_overrides_subscription_login() {
+        let sources = AuthSources {
+            env: env(&[("ANTHROPIC_API_KEY", "sk-ant-api03-stale-1234")]),
+            claude_json: json!({
+                "oauthAccount": { "emailAddress": "dev@example.com", "organizationName": "Acme" }
+            }),
+            oauth: Some(
+                json!({ "accessToken": "tok", "expiresAt": 2_000, "subscriptionType": "max" }),
+            ),
+            now_ms: 1_000,
+            ..Default::default()
+        };
+        let status = resolve(&sources);
+        assert_eq!(status.method, AuthMethod::ApiKeyEnv);
+        assert_eq!(status.source.as_deref(), Some("environment"));
+        assert_eq!(status.key_hint.as_deref(), Some("…1234"));
+        assert_eq!(status.overridden, [AuthMethod::ClaudeAi]);
+        assert!(status.problems[0].contains("takes precedence"));
+        let account = status.account.unwrap();
+        assert_eq!(account.email.as_deref(), Some("dev@example.com"));
+        assert_eq!(account.organization_name.as_deref(), Some("Acme"));
+
+        // An empty value in settings doesn't count, and doesn't mask the environment
+        let mut sources = sources;
+        sources.env.clear();
+        sources.settings_env = env(&[("ANTHROPIC_API_KEY", " ")]);
+        let status = resolve(&sources);
+        assert_eq!(status.method, AuthMethod::ClaudeAi);
+        assert_eq!(status.subscription_type.as_deref(), Some("max"));
+        assert_eq!(status.expired, Some(false));
+        assert!(status.problems.is_empty());
+    }
+
+    #[test]
+    fn test_expired_login_and_missing_cloud_config_are_reported() {
+        let status = resolve(&AuthSources {
+            oauth: Some(json!({ "accessToken": "tok", "expiresAt": 500 })),
+            now_ms: 1_000,
+            ..Default::default()
+        });
+        assert_eq!(status.expired, Some(true));
+        assert!(status.problems[0].contains("expired"));
+
+        let status = resolve(&AuthSources {
+            settings_env: env(&[("CLAUDE_CODE_USE_VERTEX", "1")]),
+            ..Default::default()
+        });
+        assert_eq!(status.method, AuthMethod::Vertex);
+        assert_eq!(status.source.as_deref(), Some("settings.json env"));
+        assert!(status
+            .problems
+            .iter()
+            .any(|problem| problem.contains("CLOUD_ML_REGION")));
+
+        let status = resolve(&AuthSources::default());
+        assert_eq!(status.method, AuthMethod::None);
+        assert!(!status.credentials_present);
+    }
+}
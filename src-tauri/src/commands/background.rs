@@ -0,0 +1,127 @@
+#![allow(dead_code)]
+
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, WebviewWindowBuilder};
+
+use super::agents::AgentDb;
+use super::error::OpcodeError;
+use crate::process::{OutputStream, ProcessRegistryState, RunSummary};
+
+/// app_settings key for keeping the backend alive after the last window closes
+const BACKGROUND_MODE_KEY: &str = "background_mode_enabled";
+
+/// Label of the main window created from tauri.conf.json
+const MAIN_WINDOW_LABEL: &str = "main";
+
+/// A run still in progress, with the output buffered while the UI was closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReattachRun {
+    pub summary: RunSummary,
+    pub output: String,
+}
+
+fn load_background_mode(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![BACKGROUND_MODE_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+    .map(|value| value == "true")
+    .unwrap_or(false)
+}
+
+/// Whether closing the last window should leave the backend running in the tray
+pub fn background_mode_enabled(app: &AppHandle) -> bool {
+    app.try_state::<AgentDb>()
+        .and_then(|db| db.0.lock().ok().map(|conn| load_background_mode(&conn)))
+        .unwrap_or(false)
+}
+
+/// Called when the last window closed; returns true if the app should keep running
+pub fn keep_running_in_background(app: &AppHandle) -> bool {
+    if !background_mode_enabled(app) {
+        return false;
+    }
+
+    let active_runs = app
+        .try_state::<ProcessRegistryState>()
+        .and_then(|registry| registry.0.get_running_processes().ok())
+        .map(|runs| runs.len())
+        .unwrap_or(0);
+    info!(
+        "Window closed, continuing in the background with {} active run(s)",
+        active_runs
+    );
+    true
+}
+
+/// Show and focus the main window, recreating it if it was closed in background mode
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        return;
+    }
+
+    let Some(config) = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|window| window.label == MAIN_WINDOW_LABEL)
+        .cloned()
+    else {
+        warn!("No main window configuration found");
+        return;
+    };
+
+    match WebviewWindowBuilder::from_config(app, &config).and_then(|builder| builder.build()) {
+        Ok(window) => {
+            info!("Reopened main window");
+            let _ = window.set_focus();
+        }
+        Err(e) => warn!("Failed to reopen main window: {}", e),
+    }
+}
+
+/// Get whether background mode is enabled
+#[tauri::command]
+pub async fn get_background_mode(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_background_mode(&conn))
+}
+
+/// Enable or disable background mode
+#[tauri::command]
+pub async fn set_background_mode(db: State<'_, AgentDb>, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![BACKGROUND_MODE_KEY, enabled.to_string()],
+    )
+    .map_err(|e| format!("Failed to save background mode: {}", e))?;
+    Ok(())
+}
+
+/// Runs that kept going while the UI was closed, so a reopened window can re-attach to
+/// their output streams (`agent-output:{run_id}` / `claude-output:{session_id}`)
+#[tauri::command]
+pub async fn get_reattach_state(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<ReattachRun>, OpcodeError> {
+    registry
+        .0
+        .get_run_summaries()?
+        .into_iter()
+        .map(|summary| {
+            let output = registry.0.get_live_output(summary.run_id, Some(OutputStream::Stdout))?;
+            Ok(ReattachRun { summary, output })
+        })
+        .collect()
+}
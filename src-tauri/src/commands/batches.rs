@@ -0,0 +1,466 @@
+#![allow(dead_code)]
+
+//! Parallel dispatch of one task to several agents. Each run of a batch gets a worktree of
+//! its own when the project is a git checkout (otherwise it shares the directory and relies
+//! on its pre-run checkpoint), and the runs are grouped in the process registry so the batch
+//! can be watched, cancelled and compared as a whole.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use super::agents::{AgentDb, AgentRunMetrics};
+use super::run_queue::{record_run_priority, RunPriority};
+use super::worktrees::RunWorktree;
+use crate::process::ProcessRegistryState;
+
+/// Upper bound on agents in one batch
+const MAX_BATCH_AGENTS: usize = 8;
+
+/// One agent's run within a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRun {
+    pub agent_id: i64,
+    pub agent_name: Option<String>,
+    /// `None` when the run could not be started
+    pub run_id: Option<i64>,
+    /// Run status, or `failed` when it could not be started
+    pub status: String,
+    pub error: Option<String>,
+    /// Branch the run works on, when it has a worktree
+    pub branch: Option<String>,
+}
+
+/// A group of runs started on the same task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunBatch {
+    pub id: String,
+    pub task: String,
+    pub project_path: String,
+    pub model: Option<String>,
+    /// `running`, `completed`, `failed`, `partial` or `cancelled`
+    pub status: String,
+    pub created_at: String,
+    pub cancelled_at: Option<String>,
+    pub runs: Vec<BatchRun>,
+}
+
+/// Outcome of one run of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRunResult {
+    #[serde(flatten)]
+    pub run: BatchRun,
+    pub metrics: Option<AgentRunMetrics>,
+    /// Final answer of the run
+    pub output: Option<String>,
+    pub files_changed: usize,
+    pub worktree: Option<RunWorktree>,
+}
+
+/// Consolidated results of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResults {
+    pub batch: RunBatch,
+    pub results: Vec<BatchRunResult>,
+    pub total_tokens: i64,
+    pub total_cost_usd: f64,
+}
+
+pub fn init_batch_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_batches (
+            id TEXT PRIMARY KEY,
+            task TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            model TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            cancelled_at TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_batch_members (
+            batch_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            agent_id INTEGER NOT NULL,
+            run_id INTEGER,
+            error TEXT,
+            PRIMARY KEY (batch_id, position),
+            FOREIGN KEY (batch_id) REFERENCES run_batches(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Overall status of a batch from the statuses of its runs
+pub fn batch_status(statuses: &[&str], cancelled: bool) -> &'static str {
+    if statuses.iter().any(|s| *s == "pending" || *s == "running") {
+        return "running";
+    }
+    if cancelled {
+        return "cancelled";
+    }
+    let completed = statuses.iter().filter(|s| **s == "completed").count();
+    if completed == statuses.len() {
+        "completed"
+    } else if completed == 0 {
+        "failed"
+    } else {
+        "partial"
+    }
+}
+
+fn load_batch(conn: &Connection, batch_id: &str) -> Result<RunBatch, String> {
+    let (task, project_path, model, created_at, cancelled_at) = conn
+        .query_row(
+            "SELECT task, project_path, model, created_at, cancelled_at FROM run_batches WHERE id = ?1",
+            params![batch_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Batch {} not found", batch_id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.agent_id, COALESCE(r.agent_name, a.name), m.run_id, r.status, m.error, w.branch
+             FROM run_batch_members m
+             LEFT JOIN agent_runs r ON r.id = m.run_id
+             LEFT JOIN agents a ON a.id = m.agent_id
+             LEFT JOIN run_worktrees w ON w.run_id = m.run_id
+             WHERE m.batch_id = ?1
+             ORDER BY m.position",
+        )
+        .map_err(|e| e.to_string())?;
+    let runs = stmt
+        .query_map(params![batch_id], |row| {
+            let status: Option<String> = row.get(3)?;
+            Ok(BatchRun {
+                agent_id: row.get(0)?,
+                agent_name: row.get(1)?,
+                run_id: row.get(2)?,
+                status: status.unwrap_or_else(|| "failed".to_string()),
+                error: row.get(4)?,
+                branch: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    let statuses: Vec<&str> = runs.iter().map(|run| run.status.as_str()).collect();
+    let status = batch_status(&statuses, cancelled_at.is_some()).to_string();
+    Ok(RunBatch {
+        id: batch_id.to_string(),
+        task,
+        project_path,
+        model,
+        status,
+        created_at,
+        cancelled_at,
+        runs,
+    })
+}
+
+/// Run the same task with several agents at once
+#[tauri::command]
+pub async fn execute_agents_parallel(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    agent_ids: Vec<i64>,
+    project_path: String,
+    task: String,
+    model: Option<String>,
+) -> Result<RunBatch, String> {
+    if agent_ids.len() < 2 {
+        return Err("Pick at least two agents to run in parallel".to_string());
+    }
+    if agent_ids.len() > MAX_BATCH_AGENTS {
+        return Err(format!(
+            "At most {} agents can run in parallel",
+            MAX_BATCH_AGENTS
+        ));
+    }
+    if task.trim().is_empty() {
+        return Err("Task cannot be empty".to_string());
+    }
+    if !std::path::Path::new(&project_path).is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+    if !super::worktrees::is_git_checkout(&project_path) {
+        log::warn!(
+            "{} is not a git checkout; parallel runs will share the working tree",
+            project_path
+        );
+    }
+
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO run_batches (id, task, project_path, model) VALUES (?1, ?2, ?3, ?4)",
+            params![batch_id, task, project_path, model],
+        )
+        .map_err(|e| format!("Failed to create batch: {}", e))?;
+    }
+
+    let mut run_ids = Vec::new();
+    for (position, agent_id) in agent_ids.iter().enumerate() {
+        let result = super::agents::start_agent_run(
+            app.clone(),
+            *agent_id,
+            project_path.clone(),
+            task.clone(),
+            model.clone(),
+            true,
+            None,
+            None,
+            None,
+            db.clone(),
+            registry.clone(),
+        )
+        .await;
+        let (run_id, error) = match result {
+            Ok(run_id) => {
+                run_ids.push(run_id);
+                (Some(run_id), None)
+            }
+            Err(e) => {
+                log::warn!(
+                    "Batch {}: agent {} failed to start: {}",
+                    batch_id,
+                    agent_id,
+                    e
+                );
+                (None, Some(e))
+            }
+        };
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Some(run_id) = run_id {
+            record_run_priority(&conn, run_id, RunPriority::Batch).map_err(|e| e.to_string())?;
+        }
+        conn.execute(
+            "INSERT INTO run_batch_members (batch_id, position, agent_id, run_id, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![batch_id, position as i64, agent_id, run_id, error],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    registry.0.register_batch(&batch_id, run_ids)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let batch = load_batch(&conn, &batch_id)?;
+    let _ = app.emit("batch-started", &batch);
+    Ok(batch)
+}
+
+/// Get a batch with the current status of each run
+#[tauri::command]
+pub async fn get_batch_status(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    batch_id: String,
+) -> Result<RunBatch, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let batch = load_batch(&conn, &batch_id)?;
+    if batch.status != "running" {
+        registry.0.remove_batch(&batch_id)?;
+    }
+    Ok(batch)
+}
+
+/// List batches, newest first
+#[tauri::command]
+pub async fn list_batches(
+    db: State<'_, AgentDb>,
+    project_path: Option<String>,
+) -> Result<Vec<RunBatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let ids = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM run_batches WHERE ?1 IS NULL OR project_path = ?1 ORDER BY created_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(params![project_path], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<SqliteResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+    ids.iter().map(|id| load_batch(&conn, id)).collect()
+}
+
+/// Cancel every run of a batch that is still going. Returns the cancelled run ids.
+#[tauri::command]
+pub async fn cancel_batch(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    batch_id: String,
+) -> Result<Vec<i64>, String> {
+    let run_ids = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let batch = load_batch(&conn, &batch_id)?;
+        conn.execute(
+            "UPDATE run_batches SET cancelled_at = COALESCE(cancelled_at, CURRENT_TIMESTAMP) WHERE id = ?1",
+            params![batch_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut cancelled = Vec::new();
+        for run_id in batch.runs.iter().filter_map(|run| run.run_id) {
+            // Mark as cancelled before killing so the monitor doesn't record a failure or retry
+            let updated = conn
+                .execute(
+                    "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('pending', 'running')",
+                    params![run_id],
+                )
+                .map_err(|e| e.to_string())?;
+            if updated > 0 {
+                cancelled.push(run_id);
+            }
+        }
+        cancelled
+    };
+
+    for run_id in &run_ids {
+        match registry.0.kill_process_tree(*run_id).await {
+            Ok(_) => {
+                let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+            }
+            Err(e) => log::warn!("Failed to kill run {} of batch {}: {}", run_id, batch_id, e),
+        }
+    }
+    registry.0.remove_batch(&batch_id)?;
+
+    Ok(run_ids)
+}
+
+/// Outputs, metrics, changed files and branches of every run in a batch
+#[tauri::command]
+pub async fn get_batch_results(
+    db: State<'_, AgentDb>,
+    batch_id: String,
+) -> Result<BatchResults, String> {
+    let (batch, runs, worktrees, changes) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let batch = load_batch(&conn, &batch_id)?;
+        let mut runs = Vec::new();
+        let mut worktrees = Vec::new();
+        let mut changes = Vec::new();
+        for run in &batch.runs {
+            let Some(run_id) = run.run_id else {
+                runs.push(None);
+                worktrees.push(None);
+                changes.push(0);
+                continue;
+            };
+            runs.push(
+                conn.query_row(
+                    &format!(
+                        "SELECT {} FROM agent_runs WHERE id = ?1",
+                        super::agents::AGENT_RUN_COLUMNS
+                    ),
+                    params![run_id],
+                    super::agents::agent_run_from_row,
+                )
+                .ok(),
+            );
+            worktrees.push(
+                super::worktrees::load_run_worktree(&conn, run_id)
+                    .ok()
+                    .flatten(),
+            );
+            changes.push(
+                conn.query_row(
+                    "SELECT COUNT(*) FROM agent_run_file_changes WHERE run_id = ?1",
+                    params![run_id],
+                    |row| row.get::<_, i64>(0),
+                )
+                .unwrap_or(0) as usize,
+            );
+        }
+        (batch, runs, worktrees, changes)
+    };
+
+    let mut results = Vec::new();
+    let mut total_tokens = 0;
+    let mut total_cost_usd = 0.0;
+    for (((run, agent_run), worktree), files_changed) in batch
+        .runs
+        .iter()
+        .cloned()
+        .zip(runs)
+        .zip(worktrees)
+        .zip(changes)
+    {
+        let (metrics, output) = match agent_run {
+            Some(agent_run) => {
+                let with_metrics = super::agents::get_agent_run_with_metrics(agent_run).await;
+                let output = with_metrics
+                    .output
+                    .as_deref()
+                    .and_then(super::experiments::final_output);
+                (with_metrics.metrics, output)
+            }
+            None => (None, None),
+        };
+        if let Some(metrics) = &metrics {
+            total_tokens += metrics.total_tokens.unwrap_or(0);
+            total_cost_usd += metrics.cost_usd.unwrap_or(0.0);
+        }
+        results.push(BatchRunResult {
+            run,
+            metrics,
+            output,
+            files_changed,
+            worktree,
+        });
+    }
+
+    Ok(BatchResults {
+        batch,
+        results,
+        total_tokens,
+        total_cost_usd,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_status_summarizes_runs() {
+        assert_eq!(batch_status(&["completed", "running"], false), "running");
+        assert_eq!(
+            batch_status(&["completed", "completed"], false),
+            "completed"
+        );
+        assert_eq!(batch_status(&["completed", "failed"], false), "partial");
+        assert_eq!(batch_status(&["failed", "failed"], false), "failed");
+        assert_eq!(batch_status(&["completed", "cancelled"], true), "cancelled");
+    }
+
+    #[test]
+    fn test_registry_tracks_batch_runs() {
+        let registry = crate::process::ProcessRegistry::new();
+        registry.register_batch("b1", vec![1, 2]).unwrap();
+        assert_eq!(registry.get_batch_runs("b1").unwrap(), Some(vec![1, 2]));
+        // Neither run has a registered process
+        assert!(registry.get_running_batch_runs("b1").unwrap().is_empty());
+        registry.remove_batch("b1").unwrap();
+        assert_eq!(registry.get_batch_runs("b1").unwrap(), None);
+    }
+}
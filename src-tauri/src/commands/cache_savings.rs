@@ -0,0 +1,200 @@
+#![allow(dead_code)]
+
+//! How much prompt caching saved: every usage entry is priced twice, once as billed and once
+//! as if its cached tokens had been sent as regular input. Cache writes cost more than input,
+//! so the savings are net of that premium and can be negative for sessions that write caches
+//! they never read back.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::pricing::{active_price, cost_at};
+use super::usage::{day_in_range, get_all_usage_entries, DateRange, UsageEntry};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheSavings {
+    pub input_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    /// Cost with caching, as billed
+    pub actual_cost: f64,
+    /// Cost had every cached token been sent as regular input
+    pub uncached_cost: f64,
+    /// `uncached_cost - actual_cost`
+    pub savings: f64,
+    /// Share of prompt tokens served from the cache
+    pub hit_rate: f64,
+}
+
+impl CacheSavings {
+    fn add(&mut self, entry: &UsageEntry) {
+        // Models without a known price can't be compared
+        let Some(price) = active_price(&entry.model) else {
+            return;
+        };
+        self.input_tokens += entry.input_tokens;
+        self.cache_creation_tokens += entry.cache_creation_tokens;
+        self.cache_read_tokens += entry.cache_read_tokens;
+        self.actual_cost += cost_at(
+            &price,
+            entry.input_tokens,
+            entry.output_tokens,
+            entry.cache_creation_tokens,
+            entry.cache_read_tokens,
+        );
+        self.uncached_cost += cost_at(
+            &price,
+            entry.input_tokens + entry.cache_creation_tokens + entry.cache_read_tokens,
+            entry.output_tokens,
+            0,
+            0,
+        );
+        self.savings = self.uncached_cost - self.actual_cost;
+        let prompt_tokens = self.input_tokens + self.cache_creation_tokens + self.cache_read_tokens;
+        self.hit_rate = if prompt_tokens > 0 {
+            self.cache_read_tokens as f64 / prompt_tokens as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectCacheSavings {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub savings: CacheSavings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyCacheSavings {
+    pub date: String,
+    #[serde(flatten)]
+    pub savings: CacheSavings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheSavingsReport {
+    pub total: CacheSavings,
+    /// Largest savings first
+    pub by_project: Vec<ProjectCacheSavings>,
+    /// Oldest day first
+    pub by_day: Vec<DailyCacheSavings>,
+}
+
+/// Aggregate savings of the entries that fall within `range`
+pub fn summarize_cache_savings(
+    entries: &[UsageEntry],
+    range: &DateRange,
+) -> Result<CacheSavingsReport, String> {
+    let bounds = range.bounds()?;
+    let mut total = CacheSavings::default();
+    let mut by_project: BTreeMap<&str, CacheSavings> = BTreeMap::new();
+    let mut by_day: BTreeMap<String, CacheSavings> = BTreeMap::new();
+
+    for entry in entries {
+        let Some(date) = day_in_range(&entry.timestamp, bounds) else {
+            continue;
+        };
+        total.add(entry);
+        by_project
+            .entry(entry.project_path.as_str())
+            .or_default()
+            .add(entry);
+        by_day.entry(date.to_string()).or_default().add(entry);
+    }
+
+    let mut by_project: Vec<ProjectCacheSavings> = by_project
+        .into_iter()
+        .map(|(project_path, savings)| ProjectCacheSavings {
+            project_path: project_path.to_string(),
+            savings,
+        })
+        .collect();
+    by_project.sort_by(|a, b| b.savings.savings.total_cmp(&a.savings.savings));
+
+    Ok(CacheSavingsReport {
+        total,
+        by_project,
+        by_day: by_day
+            .into_iter()
+            .map(|(date, savings)| DailyCacheSavings { date, savings })
+            .collect(),
+    })
+}
+
+/// Savings from prompt caching per project and per day
+#[tauri::command]
+pub async fn get_cache_savings(range: Option<DateRange>) -> Result<CacheSavingsReport, String> {
+    let claude_path = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+    let range = range.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        summarize_cache_savings(&get_all_usage_entries(&claude_path), &range)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, project: &str, input: u64, write: u64, read: u64) -> UsageEntry {
+        UsageEntry {
+            timestamp: timestamp.to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            input_tokens: input,
+            output_tokens: 0,
+            cache_creation_tokens: write,
+            cache_read_tokens: read,
+            cost: 0.0,
+            session_id: "s1".to_string(),
+            project_path: project.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_savings_are_net_of_the_cache_write_premium() {
+        let entries = [
+            // 1M cached tokens read: $3.00 as input vs $0.30 cached
+            entry("2025-03-01T10:00:00Z", "/work/app", 0, 0, 1_000_000),
+            // 1M tokens written to a cache: $3.75 vs $3.00 as input
+            entry("2025-03-02T10:00:00Z", "/work/lib", 0, 1_000_000, 0),
+        ];
+        let report = summarize_cache_savings(&entries, &DateRange::default()).unwrap();
+
+        assert!((report.total.savings - (2.70 - 0.75)).abs() < 1e-9);
+        assert!((report.total.hit_rate - 0.5).abs() < 1e-9);
+        assert_eq!(report.by_project[0].project_path, "/work/app");
+        assert!((report.by_project[1].savings.savings + 0.75).abs() < 1e-9);
+        let days: Vec<&str> = report.by_day.iter().map(|day| day.date.as_str()).collect();
+        assert_eq!(days, vec!["2025-03-01", "2025-03-02"]);
+    }
+
+    #[test]
+    fn test_range_and_unknown_models_are_excluded() {
+        let mut unknown = entry("2025-03-01T10:00:00Z", "/work/app", 0, 0, 1_000_000);
+        unknown.model = "some-other-model".to_string();
+        let entries = [
+            unknown,
+            entry("2025-02-28T23:00:00Z", "/work/app", 0, 0, 1_000_000),
+            entry("2025-03-01T10:00:00Z", "/work/app", 1_000_000, 0, 0),
+        ];
+        let range = DateRange {
+            start_date: Some("2025-03-01".to_string()),
+            end_date: None,
+        };
+        let report = summarize_cache_savings(&entries, &range).unwrap();
+        assert_eq!(report.total.input_tokens, 1_000_000);
+        assert_eq!(report.total.cache_read_tokens, 0);
+        assert_eq!(report.total.savings, 0.0);
+
+        let invalid = DateRange {
+            start_date: Some("March".to_string()),
+            end_date: None,
+        };
+        assert!(summarize_cache_savings(&entries, &invalid).is_err());
+    }
+}
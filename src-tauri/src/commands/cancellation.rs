@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+
+//! Cancellation for long-running commands. The frontend passes an `operation_id` with the
+//! call and can abort it with `cancel_operation(id)`; the command holds an
+//! `OperationGuard` and checks its token (or awaits `cancelled()`) at each step.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::error::OpcodeError;
+
+/// Message used for errors caused by cancellation
+pub const CANCELLED_MESSAGE: &str = "Operation cancelled";
+
+#[derive(Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+/// Shared flag that flips once when an operation is cancelled
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<TokenInner>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// `Err` with the cancellation message once cancelled
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED_MESSAGE.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resolves when the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.0.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Tokens of operations currently running, by operation id
+#[derive(Default)]
+pub struct CancellationRegistry {
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl CancellationRegistry {
+    pub fn global() -> &'static CancellationRegistry {
+        static REGISTRY: OnceLock<CancellationRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(CancellationRegistry::default)
+    }
+
+    /// Register an operation; without an id the token simply can't be cancelled from outside
+    pub fn register(&'static self, operation_id: Option<String>) -> OperationGuard {
+        let token = CancellationToken::new();
+        if let Some(id) = &operation_id {
+            if let Ok(mut tokens) = self.tokens.lock() {
+                tokens.insert(id.clone(), token.clone());
+            }
+        }
+        OperationGuard {
+            registry: self,
+            operation_id,
+            token,
+        }
+    }
+
+    /// Cancel an operation; false when no operation with that id is running
+    pub fn cancel(&self, operation_id: &str) -> bool {
+        let token = self
+            .tokens
+            .lock()
+            .ok()
+            .and_then(|mut tokens| tokens.remove(operation_id));
+        match token {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn running(&self) -> Vec<String> {
+        self.tokens
+            .lock()
+            .map(|tokens| tokens.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Keeps an operation registered while it runs
+pub struct OperationGuard {
+    registry: &'static CancellationRegistry,
+    operation_id: Option<String>,
+    token: CancellationToken,
+}
+
+impl OperationGuard {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Some(id) = &self.operation_id {
+            if let Ok(mut tokens) = self.registry.tokens.lock() {
+                tokens.remove(id);
+            }
+        }
+    }
+}
+
+/// Cancel a running operation started with `operation_id`
+#[tauri::command]
+pub async fn cancel_operation(id: String) -> Result<bool, OpcodeError> {
+    let cancelled = CancellationRegistry::global().cancel(&id);
+    if cancelled {
+        log::info!("Cancelled operation {}", id);
+    }
+    Ok(cancelled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_cancels_by_id_and_forgets_finished_operations() {
+        let registry: &'static CancellationRegistry = Box::leak(Box::default());
+        let guard = registry.register(Some("op-1".to_string()));
+        assert_eq!(registry.running(), vec!["op-1"]);
+
+        assert!(registry.cancel("op-1"));
+        assert!(guard.token().is_cancelled());
+        assert_eq!(guard.token().check(), Err(CANCELLED_MESSAGE.to_string()));
+        assert!(!registry.cancel("op-1"));
+
+        let other = registry.register(Some("op-2".to_string()));
+        drop(other);
+        assert!(registry.running().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_future_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = {
+            let token = token.clone();
+            tokio::spawn(async move { token.cancelled().await })
+        };
+        tokio::task::yield_now().await;
+        token.cancel();
+        waiter.await.unwrap();
+        // Already cancelled tokens resolve immediately
+        token.cancelled().await;
+    }
+}
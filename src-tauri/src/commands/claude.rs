@@ -23,6 +23,17 @@ use tower_http::{
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 
+use super::attachments::{apply_attachments, StagedAttachment};
+use super::error::OpcodeError;
+use super::event_broker::{publish, Channel};
+use super::model_policy::ModelPolicy;
+use super::permission_relay::{relay_args_if, PermissionRelayState};
+use super::run_guards::RunGuards;
+use super::thinking::RunThinking;
+use super::tool_rules::{known_mcp_tools, ToolRules};
+use super::verbosity::RunVerbosity;
+use crate::process::{InterruptMode, OutputStream, StreamLine};
+
 /// Maximum allowed file size (10MB)
 const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
 
@@ -276,7 +287,7 @@ fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
 }
 
 /// Gets the path to the ~/.claude directory
-fn get_claude_dir() -> Result<PathBuf> {
+pub(crate) fn get_claude_dir() -> Result<PathBuf> {
     dirs::home_dir()
         .context("Could not find home directory")?
         .join(".claude")
@@ -332,7 +343,7 @@ fn decode_project_path(encoded: &str) -> String {
 }
 
 /// Checks if a string is a valid UUID format
-fn is_valid_uuid(s: &str) -> bool {
+pub(crate) fn is_valid_uuid(s: &str) -> bool {
     // UUID format: 8-4-4-4-12 hex digits
     // Example: 550e8400-e29b-41d4-a716-446655440000
     let parts: Vec<&str> = s.split('-').collect();
@@ -643,6 +654,10 @@ fn create_system_command(claude_path: &str, args: Vec<String>, project_path: &st
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
     
+    // Lead a process group so an interrupt or kill also reaches the tools Claude spawned
+    #[cfg(unix)]
+    cmd.process_group(0);
+
     // On Windows, ensure CREATE_NO_WINDOW flag is set to prevent opening cmd window
     #[cfg(target_os = "windows")]
     {
@@ -961,6 +976,7 @@ pub async fn get_claude_settings() -> Result<ClaudeSettings, String> {
 
     let data: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse settings JSON: {}", e))?;
+    super::config_merge::record_merge_base(&settings_path);
 
     Ok(ClaudeSettings { data })
 }
@@ -1146,7 +1162,7 @@ pub async fn save_system_prompt(content: String) -> Result<String, String> {
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
     let claude_md_path = claude_dir.join("CLAUDE.md");
 
-    fs::write(&claude_md_path, content).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
+    crate::atomic_file::write_atomic(&claude_md_path, content).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
 
     Ok("System prompt saved successfully".to_string())
 }
@@ -1163,8 +1179,10 @@ pub async fn save_claude_settings(settings: serde_json::Value) -> Result<String,
     let json_string = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    fs::write(&settings_path, json_string)
+    super::config_snapshots::snapshot_config_file(&settings_path, "save settings")?;
+    crate::atomic_file::write_atomic(&settings_path, json_string)
         .map_err(|e| format!("Failed to write settings file: {}", e))?;
+    super::config_merge::record_merge_base(&settings_path);
 
     Ok("Settings saved successfully".to_string())
 }
@@ -1288,7 +1306,7 @@ pub async fn save_claude_md_file(file_path: String, content: String) -> Result<S
             .map_err(|e| format!("Failed to create parent directory: {}", e))?;
     }
 
-    fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+    crate::atomic_file::write_atomic(&path, content).map_err(|e| format!("Failed to write file: {}", e))?;
 
     Ok("File saved successfully".to_string())
 }
@@ -1365,13 +1383,41 @@ pub async fn load_session_history(
     Ok(messages)
 }
 
+/// Validate a session's tool rules and add their flags to its arguments
+fn apply_tool_rules(
+    app: &AppHandle,
+    tool_rules: Option<ToolRules>,
+    args: &mut Vec<String>,
+) -> Result<(), String> {
+    let Some(tool_rules) = tool_rules else {
+        return Ok(());
+    };
+    let mcp_tools = {
+        let db = app.state::<super::agents::AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        known_mcp_tools(&conn)
+    };
+    tool_rules.validate(&mcp_tools)?;
+    tool_rules.apply_to_args(args);
+    Ok(())
+}
+
 /// Execute a new interactive Claude Code session with streaming output
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_claude_code(
     app: AppHandle,
     project_path: String,
     prompt: String,
     model: String,
+    fallback_models: Option<Vec<String>>,
+    attachments: Option<Vec<StagedAttachment>>,
+    verbosity: Option<RunVerbosity>,
+    provider_profile_id: Option<i64>,
+    thinking: Option<RunThinking>,
+    interactive_permissions: Option<bool>,
+    tool_rules: Option<ToolRules>,
+    guards: Option<RunGuards>,
 ) -> Result<(), String> {
     log::info!(
         "Starting new Claude Code session in: {} with model: {}",
@@ -1379,11 +1425,18 @@ pub async fn execute_claude_code(
         model
     );
 
+    super::team_policy::check_run_allowed(&project_path, Some(&model))?;
     let claude_path = find_claude_binary(&app)?;
-
-    let args = vec![
+    let (prompt, attachment_args) =
+        apply_attachments(&project_path, &prompt, &attachments.unwrap_or_default())?;
+    let thinking = thinking.unwrap_or_default();
+    thinking.validate()?;
+    let guards = guards.unwrap_or_default();
+    guards.validate()?;
+
+    let mut args = vec![
         "-p".to_string(),
-        prompt.clone(),
+        thinking.apply_to_prompt(&prompt),
         "--model".to_string(),
         model.clone(),
         "--output-format".to_string(),
@@ -1391,18 +1444,45 @@ pub async fn execute_claude_code(
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(ModelPolicy::cli_fallback_args(
+        &model,
+        &fallback_models.unwrap_or_default(),
+    ));
+    args.extend(attachment_args);
+    let verbosity = verbosity.unwrap_or_default();
+    verbosity.validate()?;
+    args.extend(verbosity.claude_args());
+    args.extend(guards.claude_args());
+    apply_tool_rules(&app, tool_rules, &mut args)?;
+
+    let provider_env =
+        super::provider_profiles::run_env(&app, &project_path, provider_profile_id)?;
 
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let (args, relayed_prompt) = relay_args_if(interactive_permissions, args);
+    let mut cmd = create_system_command(&claude_path, args, &project_path);
+    for (key, value) in verbosity.env().into_iter().chain(thinking.env()) {
+        cmd.env(key, value);
+    }
+    super::provider_profiles::apply_env(&mut cmd, &provider_env);
+    spawn_claude_process(app, cmd, prompt, model, project_path, relayed_prompt, guards).await
 }
 
 /// Continue an existing Claude Code conversation with streaming output
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn continue_claude_code(
     app: AppHandle,
     project_path: String,
     prompt: String,
     model: String,
+    fallback_models: Option<Vec<String>>,
+    attachments: Option<Vec<StagedAttachment>>,
+    verbosity: Option<RunVerbosity>,
+    provider_profile_id: Option<i64>,
+    thinking: Option<RunThinking>,
+    interactive_permissions: Option<bool>,
+    tool_rules: Option<ToolRules>,
+    guards: Option<RunGuards>,
 ) -> Result<(), String> {
     log::info!(
         "Continuing Claude Code conversation in: {} with model: {}",
@@ -1410,12 +1490,19 @@ pub async fn continue_claude_code(
         model
     );
 
+    super::team_policy::check_run_allowed(&project_path, Some(&model))?;
     let claude_path = find_claude_binary(&app)?;
-
-    let args = vec![
+    let (prompt, attachment_args) =
+        apply_attachments(&project_path, &prompt, &attachments.unwrap_or_default())?;
+    let thinking = thinking.unwrap_or_default();
+    thinking.validate()?;
+    let guards = guards.unwrap_or_default();
+    guards.validate()?;
+
+    let mut args = vec![
         "-c".to_string(), // Continue flag
         "-p".to_string(),
-        prompt.clone(),
+        thinking.apply_to_prompt(&prompt),
         "--model".to_string(),
         model.clone(),
         "--output-format".to_string(),
@@ -1423,19 +1510,110 @@ pub async fn continue_claude_code(
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(ModelPolicy::cli_fallback_args(
+        &model,
+        &fallback_models.unwrap_or_default(),
+    ));
+    args.extend(attachment_args);
+    let verbosity = verbosity.unwrap_or_default();
+    verbosity.validate()?;
+    args.extend(verbosity.claude_args());
+    args.extend(guards.claude_args());
+    apply_tool_rules(&app, tool_rules, &mut args)?;
+
+    let provider_env =
+        super::provider_profiles::run_env(&app, &project_path, provider_profile_id)?;
+
+    let (args, relayed_prompt) = relay_args_if(interactive_permissions, args);
+    let mut cmd = create_system_command(&claude_path, args, &project_path);
+    for (key, value) in verbosity.env().into_iter().chain(thinking.env()) {
+        cmd.env(key, value);
+    }
+    super::provider_profiles::apply_env(&mut cmd, &provider_env);
+    spawn_claude_process(app, cmd, prompt, model, project_path, relayed_prompt, guards).await
+}
+
+/// Run a saved slash command as a one-off task, streaming output like a new session.
+///
+/// Claude versions that expand custom commands in print mode get `-p "/command args"`;
+/// older ones get the command body inlined with its arguments.
+#[tauri::command]
+pub async fn run_slash_command(
+    app: AppHandle,
+    project_path: String,
+    command: String,
+    args: Option<String>,
+    model: Option<String>,
+) -> Result<(), String> {
+    let slash_command =
+        super::slash_commands::find_slash_command(&project_path, &command).await?;
+    let claude_path = find_claude_binary(&app)?;
+    let version = crate::claude_binary::get_claude_version(&claude_path)
+        .ok()
+        .flatten();
+    let native = super::slash_commands::supports_print_mode_slash_commands(version.as_deref());
+    let (prompt, allowed_tools) = super::slash_commands::compose_slash_command_prompt(
+        &slash_command,
+        args.as_deref().unwrap_or_default(),
+        native,
+    );
+    let model = model.unwrap_or_else(|| "sonnet".to_string());
 
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    log::info!(
+        "Running slash command {} in {} (claude {}, {})",
+        slash_command.full_command,
+        project_path,
+        version.as_deref().unwrap_or("unknown"),
+        if native { "native" } else { "inlined" }
+    );
+
+    let mut args = vec![
+        "-p".to_string(),
+        prompt.clone(),
+        "--model".to_string(),
+        model.clone(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+    ];
+    if !allowed_tools.is_empty() {
+        args.push("--allowedTools".to_string());
+        args.push(allowed_tools.join(","));
+    }
+
+    let provider_env = super::provider_profiles::run_env(&app, &project_path, None)?;
+    let mut cmd = create_system_command(&claude_path, args, &project_path);
+    super::provider_profiles::apply_env(&mut cmd, &provider_env);
+    spawn_claude_process(
+        app,
+        cmd,
+        prompt,
+        model,
+        project_path,
+        None,
+        RunGuards::default(),
+    )
+    .await
 }
 
 /// Resume an existing Claude Code session by ID with streaming output
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn resume_claude_code(
     app: AppHandle,
     project_path: String,
     session_id: String,
     prompt: String,
     model: String,
+    fallback_models: Option<Vec<String>>,
+    attachments: Option<Vec<StagedAttachment>>,
+    verbosity: Option<RunVerbosity>,
+    provider_profile_id: Option<i64>,
+    thinking: Option<RunThinking>,
+    interactive_permissions: Option<bool>,
+    tool_rules: Option<ToolRules>,
+    guards: Option<RunGuards>,
 ) -> Result<(), String> {
     log::info!(
         "Resuming Claude Code session: {} in: {} with model: {}",
@@ -1458,13 +1636,20 @@ pub async fn resume_claude_code(
 
     log::info!("Using actual Claude session ID: {}", actual_session_id);
 
+    super::team_policy::check_run_allowed(&project_path, Some(&model))?;
     let claude_path = find_claude_binary(&app)?;
-
-    let args = vec![
+    let (prompt, attachment_args) =
+        apply_attachments(&project_path, &prompt, &attachments.unwrap_or_default())?;
+    let thinking = thinking.unwrap_or_default();
+    thinking.validate()?;
+    let guards = guards.unwrap_or_default();
+    guards.validate()?;
+
+    let mut args = vec![
         "--resume".to_string(),
         actual_session_id.clone(),
         "-p".to_string(),
-        prompt.clone(),
+        thinking.apply_to_prompt(&prompt),
         "--model".to_string(),
         model.clone(),
         "--output-format".to_string(),
@@ -1472,9 +1657,27 @@ pub async fn resume_claude_code(
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(ModelPolicy::cli_fallback_args(
+        &model,
+        &fallback_models.unwrap_or_default(),
+    ));
+    args.extend(attachment_args);
+    let verbosity = verbosity.unwrap_or_default();
+    verbosity.validate()?;
+    args.extend(verbosity.claude_args());
+    args.extend(guards.claude_args());
+    apply_tool_rules(&app, tool_rules, &mut args)?;
+
+    let provider_env =
+        super::provider_profiles::run_env(&app, &project_path, provider_profile_id)?;
 
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let (args, relayed_prompt) = relay_args_if(interactive_permissions, args);
+    let mut cmd = create_system_command(&claude_path, args, &project_path);
+    for (key, value) in verbosity.env().into_iter().chain(thinking.env()) {
+        cmd.env(key, value);
+    }
+    super::provider_profiles::apply_env(&mut cmd, &provider_env);
+    spawn_claude_process(app, cmd, prompt, model, project_path, relayed_prompt, guards).await
 }
 
 /// Cancel the currently running Claude Code execution
@@ -1611,12 +1814,56 @@ pub async fn cancel_claude_execution(
     Ok(())
 }
 
+/// Interrupt a running Claude session. By default the session is interrupted gracefully,
+/// like Ctrl+C: the CLI abandons the current turn and exits on its own. With `force` the
+/// session and every process it spawned are killed immediately.
+#[tauri::command]
+pub async fn interrupt_session(
+    app: AppHandle,
+    session_id: String,
+    force: Option<bool>,
+) -> Result<bool, OpcodeError> {
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let Some(process_info) = registry.0.get_claude_session_by_id(&session_id)? else {
+        log::warn!("Session {} not found in ProcessRegistry", session_id);
+        return Ok(false);
+    };
+
+    let mode = if force.unwrap_or(false) {
+        InterruptMode::Hard
+    } else {
+        InterruptMode::Graceful
+    };
+    log::info!(
+        "Interrupting session {} (PID {}): {:?}",
+        session_id,
+        process_info.pid,
+        mode
+    );
+    let interrupted = registry.0.interrupt_process(process_info.run_id, mode).await?;
+
+    if interrupted && mode == InterruptMode::Hard {
+        // The legacy handle points at the same process; drop it so it isn't killed twice
+        let claude_state = app.state::<ClaudeProcessState>();
+        let mut current_process = claude_state.current_process.lock().await;
+        if current_process.as_ref().and_then(|child| child.id()) == Some(process_info.pid) {
+            if let Some(mut child) = current_process.take() {
+                let _ = child.wait().await;
+            }
+        }
+        let _ = app.emit(&format!("claude-cancelled:{}", session_id), true);
+    }
+    let _ = app.emit(&format!("claude-interrupted:{}", session_id), mode);
+
+    Ok(interrupted)
+}
+
 /// Get all running Claude sessions
 #[tauri::command]
 pub async fn list_running_claude_sessions(
     registry: tauri::State<'_, crate::process::ProcessRegistryState>,
-) -> Result<Vec<crate::process::ProcessInfo>, String> {
-    registry.0.get_running_claude_sessions()
+) -> Result<Vec<crate::process::ProcessInfo>, OpcodeError> {
+    Ok(registry.0.get_running_claude_sessions()?)
 }
 
 /// Get live output from a Claude session
@@ -1624,10 +1871,10 @@ pub async fn list_running_claude_sessions(
 pub async fn get_claude_session_output(
     registry: tauri::State<'_, crate::process::ProcessRegistryState>,
     session_id: String,
-) -> Result<String, String> {
+) -> Result<String, OpcodeError> {
     // Find the process by session ID
     if let Some(process_info) = registry.0.get_claude_session_by_id(&session_id)? {
-        registry.0.get_live_output(process_info.run_id)
+        Ok(registry.0.get_live_output(process_info.run_id, Some(OutputStream::Stdout))?)
     } else {
         Ok(String::new())
     }
@@ -1640,15 +1887,37 @@ async fn spawn_claude_process(
     prompt: String,
     model: String,
     project_path: String,
+    relayed_prompt: Option<String>,
+    guards: RunGuards,
 ) -> Result<(), String> {
     use std::sync::Mutex;
-    use tokio::io::{BufReader};
+    use tokio::io::{AsyncWriteExt, BufReader};
+
+    super::recent_projects::touch_recent_project(&app, &project_path, "session");
+    super::telemetry::record(super::telemetry::Category::Run, "claude_session");
+
+    // Relayed runs read their prompt and permission answers from stdin
+    if relayed_prompt.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
 
     // Spawn the process
     let mut child = cmd
         .spawn()
         .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
 
+    let mut relay_stdin = None;
+    if let Some(relayed_prompt) = relayed_prompt {
+        let mut stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+        let mut message = super::permission_relay::prompt_message_line(&relayed_prompt);
+        message.push('\n');
+        stdin
+            .write_all(message.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to send prompt to Claude: {}", e))?;
+        relay_stdin = Some(stdin);
+    }
+
     // Get stdout and stderr
     let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
@@ -1695,6 +1964,7 @@ async fn spawn_claude_process(
         let project_path_clone = project_path.clone();
         let prompt_clone = prompt.clone();
         let model_clone = model.clone();
+        let mut relay_stdin = relay_stdin;
 
         tokio::spawn(async move {
             log::info!("📖 Starting to read Claude stdout...");
@@ -1733,6 +2003,18 @@ async fn spawn_claude_process(
                                 ) {
                                     Ok(run_id) => {
                                         log::info!("Registered Claude session with run_id: {}", run_id);
+                                        if let Some(stdin) = relay_stdin.take() {
+                                            app_handle
+                                                .state::<PermissionRelayState>()
+                                                .attach(run_id, stdin);
+                                        }
+                                        super::run_guards::spawn_guard_monitor(
+                                            app_handle.clone(),
+                                            registry.clone(),
+                                            run_id,
+                                            guards.clone(),
+                                            None,
+                                        );
                                         let mut run_id_guard = run_id_holder_clone.lock().unwrap();
                                         *run_id_guard = Some(run_id);
                                     }
@@ -1746,19 +2028,39 @@ async fn spawn_claude_process(
                 }
 
                 // Store live output in registry if we have a run_id
-                if let Some(run_id) = *run_id_holder_clone.lock().unwrap() {
+                let run_id = *run_id_holder_clone.lock().unwrap();
+                if let Some(run_id) = run_id {
                     let _ = registry.append_live_output(run_id, &line);
+
+                    let relay = app_handle.state::<PermissionRelayState>();
+                    if let Some(request) =
+                        super::permission_relay::parse_permission_request(run_id, &line)
+                    {
+                        super::permission_relay::relay_request(&app_handle, &relay, request);
+                    } else if super::permission_relay::is_result_line(&line) {
+                        relay.detach(run_id);
+                    }
+
+                    if let Some(exceeded) = guards.max_turns_reached(run_id, &line) {
+                        super::run_guards::record_budget_exceeded(&app_handle, None, &exceeded);
+                    }
                 }
 
                 // Emit the line to the frontend with session isolation if we have session ID
                 if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
                     log::debug!("Emitting claude-output:{} (line {})", session_id, line_count);
-                    let _ = app_handle.emit(&format!("claude-output:{}", session_id), &line);
+                    let _ = publish(&app_handle, Channel::ClaudeOutput, &format!("claude-output:{}", session_id), &line);
+                    let _ = publish(
+                        &app_handle,
+                        Channel::ClaudeOutput,
+                        &format!("claude-stream:{}", session_id),
+                        StreamLine::new(OutputStream::Stdout, line.clone()),
+                    );
                 } else {
                     log::debug!("No session ID yet, only emitting generic event (line {})", line_count);
                 }
                 // Also emit to the generic event for backward compatibility
-                let _ = app_handle.emit("claude-output", &line);
+                let _ = publish(&app_handle, Channel::ClaudeOutput, "claude-output", &line);
             }
             log::info!("📖 Finished reading Claude stdout. Total lines: {}", line_count);
         })
@@ -1768,6 +2070,8 @@ async fn spawn_claude_process(
     let stderr_task = {
         let app_handle = app.clone();
         let session_id_holder_clone = session_id_holder.clone();
+        let run_id_holder_clone = run_id_holder.clone();
+        let registry = registry.clone();
 
         tokio::spawn(async move {
             log::info!("📖 Starting to read Claude stderr...");
@@ -1776,12 +2080,21 @@ async fn spawn_claude_process(
             while let Ok(Some(line)) = crate::claude_binary::read_decoded_line(&mut reader).await {
                 error_count += 1;
                 log::error!("Claude stderr[{}]: {}", error_count, line);
+                if let Some(run_id) = *run_id_holder_clone.lock().unwrap() {
+                    let _ = registry.append_live_stderr(run_id, &line);
+                }
                 // Emit error lines to the frontend with session isolation if we have session ID
                 if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
-                    let _ = app_handle.emit(&format!("claude-error:{}", session_id), &line);
+                    let _ = publish(&app_handle, Channel::ClaudeOutput, &format!("claude-error:{}", session_id), &line);
+                    let _ = publish(
+                        &app_handle,
+                        Channel::ClaudeOutput,
+                        &format!("claude-stream:{}", session_id),
+                        StreamLine::new(OutputStream::Stderr, line.clone()),
+                    );
                 }
                 // Also emit to the generic event for backward compatibility
-                let _ = app_handle.emit("claude-error", &line);
+                let _ = publish(&app_handle, Channel::ClaudeOutput, "claude-error", &line);
             }
             if error_count > 0 {
                 log::warn!("📖 Finished reading Claude stderr. Total error lines: {}", error_count);
@@ -1792,8 +2105,10 @@ async fn spawn_claude_process(
     };
 
     // Wait for the process to complete with optimized variable capture
+    let started_at = std::time::Instant::now();
     let wait_task = {
         let app_handle = app.clone();
+        let project_path = project_path.clone();
         let claude_state_wait = claude_state.current_process.clone();
         let session_id_holder_clone = session_id_holder.clone();
         let run_id_holder = run_id_holder.clone();
@@ -1817,6 +2132,7 @@ async fn spawn_claude_process(
                         }
                         // Also emit to the generic event for backward compatibility
                         let _ = app_handle.emit("claude-complete", status.success());
+                        notify_session_finished(&app_handle, &project_path, started_at, status.success());
                     }
                     Err(e) => {
                         log::error!("Failed to wait for Claude process: {}", e);
@@ -1828,12 +2144,14 @@ async fn spawn_claude_process(
                         }
                         // Also emit to the generic event for backward compatibility
                         let _ = app_handle.emit("claude-complete", false);
+                        notify_session_finished(&app_handle, &project_path, started_at, false);
                     }
                 }
             }
 
             // Unregister from ProcessRegistry if we have a run_id
             if let Some(run_id) = *run_id_holder.lock().unwrap() {
+                app_handle.state::<PermissionRelayState>().detach(run_id);
                 let _ = registry.unregister_process(run_id);
             }
 
@@ -1848,6 +2166,42 @@ async fn spawn_claude_process(
     Ok(())
 }
 
+/// Notify about a finished Claude session if it ran long enough to be worth a notification
+fn notify_session_finished(
+    app: &AppHandle,
+    project_path: &str,
+    started_at: std::time::Instant,
+    success: bool,
+) {
+    use crate::commands::notifications::{current_settings, notify, NotificationEvent};
+
+    let elapsed = started_at.elapsed();
+    if elapsed.as_secs() < current_settings(app).min_session_duration_secs {
+        return;
+    }
+
+    let project_name = std::path::Path::new(project_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| project_path.to_string());
+
+    if success {
+        notify(
+            app,
+            NotificationEvent::RunCompleted,
+            "Claude session finished",
+            &format!("{} ({}s)", project_name, elapsed.as_secs()),
+        );
+    } else {
+        notify(
+            app,
+            NotificationEvent::RunFailed,
+            "Claude session failed",
+            &format!("{} ({}s)", project_name, elapsed.as_secs()),
+        );
+    }
+}
+
 /// Lists files and directories in a given path
 #[tauri::command]
 pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileEntry>, String> {
@@ -2798,7 +3152,8 @@ pub async fn update_hooks_config(
     let json_string = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    fs::write(&settings_path, json_string)
+    super::config_snapshots::snapshot_config_file(&settings_path, "update hooks")?;
+    crate::atomic_file::write_atomic(&settings_path, json_string)
         .map_err(|e| format!("Failed to write settings: {}", e))?;
 
     Ok("Hooks configuration updated successfully".to_string())
@@ -2997,8 +3352,8 @@ pub async fn send_claude_message(
         .await
         .map_err(|e| format!("Failed to execute Claude command: {}", e))?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let stdout = crate::decoding::decode_command_output(&output.stdout);
+    let stderr = crate::decoding::decode_command_output(&output.stderr);
 
     if !output.status.success() {
         log::error!("Claude command failed: {}", stderr);
@@ -0,0 +1,244 @@
+#![allow(dead_code)]
+
+//! Shared gate for shelling out to the `claude` CLI. Identical in-flight invocations share
+//! one result, read-only commands are debounced (a repeat within the window reuses the last
+//! result), and the number of concurrent processes is capped.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::cancellation::{CancellationToken, CANCELLED_MESSAGE};
+
+/// Maximum `claude` processes running at once through the invoker
+const MAX_CONCURRENT: usize = 4;
+
+/// How long a finished read-only command's result is reused
+fn debounce_window(args: &[String]) -> Duration {
+    match args.first().map(String::as_str) {
+        Some("list") => Duration::from_millis(1500),
+        Some("get") => Duration::from_millis(1000),
+        _ => Duration::ZERO,
+    }
+}
+
+/// Commands that change configuration; they invalidate debounced results
+pub fn is_mutating(args: &[String]) -> bool {
+    matches!(
+        args.first().map(String::as_str),
+        Some("add" | "add-json" | "remove" | "reset-project-choices" | "add-from-claude-desktop")
+    )
+}
+
+type CliResult = Result<String, String>;
+
+/// A running invocation that later callers can wait on
+struct InFlight {
+    result: Mutex<Option<CliResult>>,
+    done: Condvar,
+}
+
+#[derive(Default)]
+struct InvokerState {
+    in_flight: HashMap<Vec<String>, Arc<InFlight>>,
+    recent: HashMap<Vec<String>, (Instant, CliResult)>,
+    running: usize,
+}
+
+/// Counters for diagnostics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct InvokerStats {
+    pub spawned: u64,
+    pub coalesced: u64,
+    pub debounced: u64,
+}
+
+pub struct CliInvoker {
+    state: Mutex<InvokerState>,
+    slot_freed: Condvar,
+    max_concurrent: usize,
+    stats: Mutex<InvokerStats>,
+}
+
+impl CliInvoker {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Mutex::new(InvokerState::default()),
+            slot_freed: Condvar::new(),
+            max_concurrent: max_concurrent.max(1),
+            stats: Mutex::new(InvokerStats::default()),
+        }
+    }
+
+    /// The process-wide invoker
+    pub fn global() -> &'static CliInvoker {
+        static INVOKER: OnceLock<CliInvoker> = OnceLock::new();
+        INVOKER.get_or_init(|| CliInvoker::new(MAX_CONCURRENT))
+    }
+
+    pub fn stats(&self) -> InvokerStats {
+        *self.stats.lock().unwrap()
+    }
+
+    fn count(&self, update: impl FnOnce(&mut InvokerStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            update(&mut stats);
+        }
+    }
+
+    /// Run `invoke` for `args` unless an identical call is in flight or was answered
+    /// within the debounce window. A caller waiting on someone else's call stops waiting
+    /// when its own `token` is cancelled; the call itself gets the token of whoever started it.
+    pub fn run(
+        &self,
+        args: &[String],
+        token: &CancellationToken,
+        invoke: impl FnOnce(&CancellationToken) -> CliResult,
+    ) -> CliResult {
+        token.check()?;
+        let key = args.to_vec();
+        let window = debounce_window(args);
+
+        let mut state = self.state.lock().unwrap();
+        if is_mutating(args) {
+            state.recent.clear();
+        }
+        if let Some((at, result)) = state.recent.get(&key) {
+            if at.elapsed() < window {
+                self.count(|s| s.debounced += 1);
+                return result.clone();
+            }
+        }
+        if let Some(in_flight) = state.in_flight.get(&key).cloned() {
+            drop(state);
+            self.count(|s| s.coalesced += 1);
+            let mut result = in_flight.result.lock().unwrap();
+            while result.is_none() {
+                if token.is_cancelled() {
+                    return Err(CANCELLED_MESSAGE.to_string());
+                }
+                result = in_flight
+                    .done
+                    .wait_timeout(result, Duration::from_millis(100))
+                    .unwrap()
+                    .0;
+            }
+            return result.clone().unwrap();
+        }
+
+        let in_flight = Arc::new(InFlight {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        });
+        state.in_flight.insert(key.clone(), in_flight.clone());
+        while state.running >= self.max_concurrent {
+            if token.is_cancelled() {
+                state.in_flight.remove(&key);
+                drop(state);
+                *in_flight.result.lock().unwrap() = Some(Err(CANCELLED_MESSAGE.to_string()));
+                in_flight.done.notify_all();
+                return Err(CANCELLED_MESSAGE.to_string());
+            }
+            state = self
+                .slot_freed
+                .wait_timeout(state, Duration::from_millis(100))
+                .unwrap()
+                .0;
+        }
+        state.running += 1;
+        drop(state);
+
+        self.count(|s| s.spawned += 1);
+        let result = invoke(token);
+
+        let mut state = self.state.lock().unwrap();
+        state.running -= 1;
+        state.in_flight.remove(&key);
+        if is_mutating(args) {
+            // Reads that ran alongside the mutation may have cached the old state
+            state.recent.clear();
+        }
+        state.recent.retain(|_, (at, _)| at.elapsed() < Duration::from_secs(10));
+        // Cancelled calls say nothing about the current state
+        if !window.is_zero() && token.check().is_ok() {
+            state.recent.insert(key, (Instant::now(), result.clone()));
+        }
+        drop(state);
+        self.slot_freed.notify_one();
+
+        *in_flight.result.lock().unwrap() = Some(result.clone());
+        in_flight.done.notify_all();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_identical_calls_are_coalesced_and_debounced() {
+        let invoker = Arc::new(CliInvoker::new(4));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let invoker = invoker.clone();
+                let calls = calls.clone();
+                std::thread::spawn(move || {
+                    invoker.run(&args(&["list"]), &CancellationToken::new(), |_| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(100));
+                        Ok("servers".to_string())
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), "servers");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Within the debounce window the cached result is reused, until a mutation
+        let again = invoker.run(&args(&["list"]), &CancellationToken::new(), |_| Ok("fresh".to_string())).unwrap();
+        assert_eq!(again, "servers");
+        invoker.run(&args(&["remove", "x"]), &CancellationToken::new(), |_| Ok(String::new())).unwrap();
+        let after = invoker.run(&args(&["list"]), &CancellationToken::new(), |_| Ok("fresh".to_string())).unwrap();
+        assert_eq!(after, "fresh");
+
+        let stats = invoker.stats();
+        assert_eq!(stats.spawned, 3);
+        assert_eq!(stats.coalesced + stats.debounced, 8);
+    }
+
+    #[test]
+    fn test_concurrency_is_capped() {
+        let invoker = Arc::new(CliInvoker::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..6)
+            .map(|i| {
+                let (invoker, running, peak) = (invoker.clone(), running.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    invoker.run(&args(&["get", &format!("server-{}", i)]), &CancellationToken::new(), |_| {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(50));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(String::new())
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
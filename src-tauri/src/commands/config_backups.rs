@@ -0,0 +1,1034 @@
+#![allow(dead_code)]
+
+//! Scheduled backups of opcode's state to a directory the user picks: the agents database
+//! (which holds agents, runs and app settings), `~/.claude.json` with its user and local MCP
+//! servers, the Claude settings files and the user's subagents in `~/.claude/agents`.
+//!
+//! A backup is either a plain directory with a `manifest.json`, or one zstd-compressed
+//! archive that can be encrypted with a passphrase kept in the OS keychain. Retention keeps
+//! the newest backups and drops those past a maximum age. `restore_backup` always works out
+//! what a restore would change first; with `dry_run` that plan is all it returns. Restoring
+//! never removes files that weren't part of the backup.
+
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use walkdir::WalkDir;
+
+use super::agents::AgentDb;
+use super::app_config::{decrypt_bundle, encrypt_bundle, is_encrypted_bundle};
+use super::config_snapshots::snapshot_config_file;
+use super::db_maintenance::replace_database;
+use super::keychain;
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the `BackupSettings`
+const BACKUP_SETTINGS_KEY: &str = "config_backups";
+
+/// Keychain account holding the passphrase of encrypted backups
+const PASSPHRASE_ACCOUNT: &str = "config-backup-passphrase";
+
+/// Current backup format; bumped on incompatible changes
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Start of every backup's file or directory name, followed by its creation time
+const BACKUP_PREFIX: &str = "opcode-backup-";
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%3f";
+
+const ARCHIVE_EXTENSION: &str = "opbak";
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Entry of the agents database
+const DB_ENTRY: &str = "db/agents.db";
+
+/// Files backed up from the home directory, as `home/<name>`
+const HOME_FILES: &[&str] = &[".claude.json"];
+
+/// Files backed up from `~/.claude`, as `claude/<name>`
+const CLAUDE_FILES: &[&str] = &["settings.json", "settings.local.json"];
+
+/// Directories backed up from `~/.claude` with everything in them
+const CLAUDE_DIRS: &[&str] = &["agents"];
+
+/// How often the scheduler checks whether a backup is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Delay before the first check, so a backup doesn't compete with startup
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(2 * 60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Take backups on the schedule
+    pub enabled: bool,
+    /// Absolute path of the directory backups are written to
+    pub directory: Option<String>,
+    pub interval_hours: u32,
+    /// Write one compressed archive instead of a directory
+    pub compress: bool,
+    /// Encrypt the archive with the passphrase in the keychain; implies `compress`
+    pub encrypt: bool,
+    /// Backups kept in the directory; older ones are deleted
+    pub keep_last: usize,
+    /// Backups older than this are deleted, though the newest is always kept
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            interval_hours: 24,
+            compress: true,
+            encrypt: false,
+            keep_last: 7,
+            max_age_days: None,
+        }
+    }
+}
+
+impl BackupSettings {
+    fn archive(&self) -> bool {
+        self.compress || self.encrypt
+    }
+}
+
+/// A backup found in the backup directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+    /// A compressed archive rather than a directory
+    pub archive: bool,
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupFileEntry {
+    /// Path inside the backup, such as `db/agents.db` or `claude/agents/reviewer.md`
+    pub name: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub files: Vec<BackupFileEntry>,
+}
+
+/// Everything in an archive backup, before compression
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupArchive {
+    manifest: BackupManifest,
+    /// Base64 contents by entry name
+    files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreAction {
+    /// The file doesn't exist and will be created
+    Create,
+    Overwrite,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyChangeKind {
+    /// Only in the backup; restoring adds it
+    Added,
+    /// Only in the current file; restoring removes it
+    Removed,
+    Changed,
+}
+
+/// A JSON key that differs between the current file and the backup, as `a.b`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyChange {
+    pub key: String,
+    pub kind: KeyChangeKind,
+}
+
+/// A table whose row count differs between the current database and the backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableChange {
+    pub table: String,
+    pub current_rows: u64,
+    pub backup_rows: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreChange {
+    pub name: String,
+    /// Where the entry is restored to on this machine
+    pub target: String,
+    pub action: RestoreAction,
+    pub current_size: Option<u64>,
+    pub backup_size: u64,
+    /// For JSON files
+    pub key_changes: Vec<KeyChange>,
+    /// For the database
+    pub table_changes: Vec<TableChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestorePlan {
+    pub backup_path: String,
+    pub created_at: String,
+    pub app_version: String,
+    pub dry_run: bool,
+    pub changes: Vec<RestoreChange>,
+    /// Id of the database backup taken before the database was replaced, for
+    /// `db_restore_backup`
+    pub pre_restore_backup: Option<String>,
+}
+
+/// Where the backed-up files live on this machine
+#[derive(Debug, Clone)]
+pub struct BackupSources {
+    pub db_path: PathBuf,
+    pub home: PathBuf,
+}
+
+impl BackupSources {
+    fn from_app(app: &AppHandle) -> Result<Self, String> {
+        let db_path = app
+            .path()
+            .app_data_dir()
+            .map(|dir| dir.join("agents.db"))
+            .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+        let home = dirs::home_dir().ok_or("Could not find home directory")?;
+        Ok(Self { db_path, home })
+    }
+
+    fn claude_dir(&self) -> PathBuf {
+        self.home.join(".claude")
+    }
+
+    /// Where entry `name` is restored to
+    fn target(&self, name: &str) -> Result<PathBuf, String> {
+        if name == DB_ENTRY {
+            return Ok(self.db_path.clone());
+        }
+        if let Some(file) = name.strip_prefix("home/") {
+            if HOME_FILES.contains(&file) {
+                return Ok(self.home.join(file));
+            }
+        }
+        if let Some(rest) = name.strip_prefix("claude/") {
+            let known = CLAUDE_FILES.contains(&rest)
+                || CLAUDE_DIRS
+                    .iter()
+                    .any(|dir| rest.starts_with(&format!("{}/", dir)));
+            if known {
+                return Ok(self.claude_dir().join(entry_path(rest)?));
+            }
+        }
+        Err(format!("Unknown backup entry: {}", name))
+    }
+}
+
+/// `name` as a relative path, refusing anything that could leave the directory it's joined to
+fn entry_path(name: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(name);
+    let plain = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if name.is_empty() || !plain {
+        return Err(format!("Invalid backup entry: {}", name));
+    }
+    Ok(path)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Contents of every backed-up file by entry name. `conn` is the open agents database, copied
+/// with `VACUUM INTO` so the copy is consistent while the app keeps writing.
+pub fn collect_files(
+    conn: &Connection,
+    sources: &BackupSources,
+) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut files = BTreeMap::new();
+
+    let temp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let copy = temp.path().join("agents.db");
+    conn.execute("VACUUM INTO ?1", params![copy.to_string_lossy()])
+        .map_err(|e| format!("Failed to copy the database: {}", e))?;
+    files.insert(
+        DB_ENTRY.to_string(),
+        fs::read(&copy).map_err(|e| e.to_string())?,
+    );
+
+    for name in HOME_FILES {
+        if let Some(data) = read_if_exists(&sources.home.join(name))? {
+            files.insert(format!("home/{}", name), data);
+        }
+    }
+    let claude_dir = sources.claude_dir();
+    for name in CLAUDE_FILES {
+        if let Some(data) = read_if_exists(&claude_dir.join(name))? {
+            files.insert(format!("claude/{}", name), data);
+        }
+    }
+    for dir in CLAUDE_DIRS {
+        for entry in WalkDir::new(claude_dir.join(dir))
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let Ok(relative) = entry.path().strip_prefix(&claude_dir) else {
+                continue;
+            };
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let data = fs::read(entry.path())
+                .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+            files.insert(format!("claude/{}", name), data);
+        }
+    }
+    Ok(files)
+}
+
+/// Write `files` as a new backup into `dir`
+pub fn write_backup(
+    dir: &Path,
+    files: &BTreeMap<String, Vec<u8>>,
+    settings: &BackupSettings,
+    passphrase: Option<&str>,
+) -> Result<BackupInfo, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let now = Utc::now();
+    let stem = format!("{}{}", BACKUP_PREFIX, now.format(TIMESTAMP_FORMAT));
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: now.to_rfc3339(),
+        files: files
+            .iter()
+            .map(|(name, data)| BackupFileEntry {
+                name: name.clone(),
+                size_bytes: data.len() as u64,
+                sha256: sha256_hex(data),
+            })
+            .collect(),
+    };
+
+    let path = if settings.archive() {
+        let archive = BackupArchive {
+            manifest,
+            files: files
+                .iter()
+                .map(|(name, data)| {
+                    (
+                        name.clone(),
+                        base64::engine::general_purpose::STANDARD.encode(data),
+                    )
+                })
+                .collect(),
+        };
+        let json = serde_json::to_vec(&archive).map_err(|e| e.to_string())?;
+        let mut data = zstd::stream::encode_all(json.as_slice(), 3)
+            .map_err(|e| format!("Failed to compress backup: {}", e))?;
+        if settings.encrypt {
+            let passphrase =
+                passphrase.ok_or("Encrypted backups need a passphrase in the keychain")?;
+            data = encrypt_bundle(&data, passphrase)?;
+        }
+        let path = dir.join(format!("{}.{}", stem, ARCHIVE_EXTENSION));
+        crate::atomic_file::write_atomic(&path, data)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        path
+    } else {
+        let path = dir.join(&stem);
+        for (name, data) in files {
+            let file = path.join(entry_path(name)?);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            crate::atomic_file::write_atomic(&file, data)
+                .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+        }
+        // Written last, so a directory without it is an interrupted backup
+        let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        crate::atomic_file::write_atomic(path.join(MANIFEST_NAME), manifest)
+            .map_err(|e| format!("Failed to write backup manifest: {}", e))?;
+        path
+    };
+
+    backup_info(&path).ok_or_else(|| "Backup was not written".to_string())
+}
+
+fn backup_info(path: &Path) -> Option<BackupInfo> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let rest = name.strip_prefix(BACKUP_PREFIX)?;
+    let timestamp = rest
+        .strip_suffix(&format!(".{}", ARCHIVE_EXTENSION))
+        .unwrap_or(rest);
+    let created_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc()
+        .to_rfc3339();
+
+    if path.is_dir() {
+        if !path.join(MANIFEST_NAME).is_file() {
+            return None;
+        }
+        let size_bytes = WalkDir::new(path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum();
+        return Some(BackupInfo {
+            path: path.to_string_lossy().to_string(),
+            created_at,
+            size_bytes,
+            archive: false,
+            encrypted: false,
+        });
+    }
+
+    if path.extension().and_then(|ext| ext.to_str()) != Some(ARCHIVE_EXTENSION) {
+        return None;
+    }
+    let data = fs::read(path).ok()?;
+    Some(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        created_at,
+        size_bytes: data.len() as u64,
+        archive: true,
+        encrypted: is_encrypted_bundle(&data),
+    })
+}
+
+/// Backups in `dir`, newest first
+pub fn list_backups_in(dir: &Path) -> Vec<BackupInfo> {
+    let mut backups: Vec<BackupInfo> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| backup_info(&entry.path()))
+        .collect();
+    // Names start with a sortable timestamp
+    backups.sort_by(|a, b| b.path.cmp(&a.path));
+    backups
+}
+
+/// Delete the backups in `dir` that the retention settings no longer keep; the newest is
+/// always kept. Returns the deleted paths.
+pub fn prune_backups(dir: &Path, settings: &BackupSettings, now: DateTime<Utc>) -> Vec<String> {
+    let mut removed = Vec::new();
+    for (index, backup) in list_backups_in(dir).into_iter().enumerate().skip(1) {
+        let too_many = index >= settings.keep_last.max(1);
+        let too_old = settings.max_age_days.is_some_and(|days| {
+            DateTime::parse_from_rfc3339(&backup.created_at).is_ok_and(|created| {
+                now - created.with_timezone(&Utc) > chrono::Duration::days(days as i64)
+            })
+        });
+        if !too_many && !too_old {
+            continue;
+        }
+        let result = if backup.archive {
+            fs::remove_file(&backup.path)
+        } else {
+            fs::remove_dir_all(&backup.path)
+        };
+        match result {
+            Ok(()) => removed.push(backup.path),
+            Err(e) => warn!("Failed to remove old backup {}: {}", backup.path, e),
+        }
+    }
+    removed
+}
+
+/// Read a backup's manifest and files, checking every file against its checksum
+pub fn load_backup(
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(BackupManifest, BTreeMap<String, Vec<u8>>), String> {
+    let (manifest, mut files) = if path.is_dir() {
+        let manifest: BackupManifest = serde_json::from_slice(
+            &fs::read(path.join(MANIFEST_NAME))
+                .map_err(|e| format!("Not a complete backup: {}", e))?,
+        )
+        .map_err(|e| format!("Invalid backup manifest: {}", e))?;
+        let mut files = BTreeMap::new();
+        for entry in &manifest.files {
+            let file = path.join(entry_path(&entry.name)?);
+            let data =
+                fs::read(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+            files.insert(entry.name.clone(), data);
+        }
+        (manifest, files)
+    } else {
+        let mut data =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if is_encrypted_bundle(&data) {
+            let passphrase = passphrase.ok_or("This backup is encrypted; enter its passphrase")?;
+            data = decrypt_bundle(&data, passphrase)?;
+        }
+        let json = zstd::stream::decode_all(data.as_slice())
+            .map_err(|e| format!("Not an opcode backup: {}", e))?;
+        let archive: BackupArchive =
+            serde_json::from_slice(&json).map_err(|e| format!("Invalid backup: {}", e))?;
+        let mut files = BTreeMap::new();
+        for (name, encoded) in archive.files {
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("Invalid backup entry {}: {}", name, e))?;
+            files.insert(name, data);
+        }
+        (archive.manifest, files)
+    };
+
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "This backup was made by a newer opcode ({}); update to restore it",
+            manifest.app_version
+        ));
+    }
+    for entry in &manifest.files {
+        let data = files
+            .get(&entry.name)
+            .ok_or_else(|| format!("Backup is missing {}", entry.name))?;
+        if sha256_hex(data) != entry.sha256 {
+            return Err(format!("{} in the backup is corrupted", entry.name));
+        }
+    }
+    files.retain(|name, _| manifest.files.iter().any(|entry| &entry.name == name));
+    Ok((manifest, files))
+}
+
+/// Keys that differ between two JSON documents, one level into objects both sides have
+fn json_key_changes(current: &Value, backup: &Value) -> Vec<KeyChange> {
+    fn compare(
+        prefix: &str,
+        current: &Value,
+        backup: &Value,
+        depth: usize,
+        out: &mut Vec<KeyChange>,
+    ) {
+        let (Value::Object(current), Value::Object(backup)) = (current, backup) else {
+            return;
+        };
+        let key = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            }
+        };
+        for (name, backup_value) in backup {
+            match current.get(name) {
+                None => out.push(KeyChange {
+                    key: key(name),
+                    kind: KeyChangeKind::Added,
+                }),
+                Some(current_value) if current_value != backup_value => {
+                    let nested = depth > 0 && current_value.is_object() && backup_value.is_object();
+                    if nested {
+                        compare(&key(name), current_value, backup_value, depth - 1, out);
+                    } else {
+                        out.push(KeyChange {
+                            key: key(name),
+                            kind: KeyChangeKind::Changed,
+                        });
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        for name in current.keys().filter(|name| !backup.contains_key(*name)) {
+            out.push(KeyChange {
+                key: key(name),
+                kind: KeyChangeKind::Removed,
+            });
+        }
+    }
+
+    let mut changes = Vec::new();
+    compare("", current, backup, 1, &mut changes);
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+fn table_row_counts(conn: &Connection) -> Result<BTreeMap<String, u64>, String> {
+    let tables: Vec<String> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?
+    };
+    let mut counts = BTreeMap::new();
+    for table in tables {
+        let count: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\"")),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        counts.insert(table, count as u64);
+    }
+    Ok(counts)
+}
+
+/// Tables whose row counts differ between the open database and a backed-up copy
+fn database_changes(conn: &Connection, backup: &[u8]) -> Result<Vec<TableChange>, String> {
+    let temp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let copy = temp.path().join("agents.db");
+    fs::write(&copy, backup).map_err(|e| e.to_string())?;
+    let backup_counts = table_row_counts(&Connection::open(&copy).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("The database in the backup can't be read: {}", e))?;
+    let current_counts = table_row_counts(conn)?;
+
+    let mut tables: Vec<&String> = backup_counts.keys().chain(current_counts.keys()).collect();
+    tables.sort();
+    tables.dedup();
+    Ok(tables
+        .into_iter()
+        .filter_map(|table| {
+            let current_rows = current_counts.get(table).copied().unwrap_or(0);
+            let backup_rows = backup_counts.get(table).copied().unwrap_or(0);
+            (current_rows != backup_rows).then(|| TableChange {
+                table: table.clone(),
+                current_rows,
+                backup_rows,
+            })
+        })
+        .collect())
+}
+
+/// What restoring `files` would change on this machine
+pub fn plan_restore(
+    conn: &Connection,
+    sources: &BackupSources,
+    files: &BTreeMap<String, Vec<u8>>,
+) -> Result<Vec<RestoreChange>, String> {
+    let mut changes = Vec::new();
+    for (name, data) in files {
+        let target = sources.target(name)?;
+        let mut change = RestoreChange {
+            name: name.clone(),
+            target: target.to_string_lossy().to_string(),
+            action: RestoreAction::Overwrite,
+            current_size: None,
+            backup_size: data.len() as u64,
+            key_changes: Vec::new(),
+            table_changes: Vec::new(),
+        };
+
+        if name == DB_ENTRY {
+            change.current_size = fs::metadata(&target).ok().map(|m| m.len());
+            change.table_changes = database_changes(conn, data)?;
+        } else {
+            match read_if_exists(&target)? {
+                None => change.action = RestoreAction::Create,
+                Some(current) => {
+                    change.current_size = Some(current.len() as u64);
+                    if current == *data {
+                        change.action = RestoreAction::Unchanged;
+                    } else if let (Ok(current), Ok(backup)) = (
+                        serde_json::from_slice::<Value>(&current),
+                        serde_json::from_slice::<Value>(data),
+                    ) {
+                        change.key_changes = json_key_changes(&current, &backup);
+                    }
+                }
+            }
+        }
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Work out what restoring the backup at `path` changes and, unless `dry_run`, restore it.
+/// Config files are snapshotted before they are overwritten and the database is backed up
+/// before it is replaced, so both can be undone.
+pub fn restore(
+    conn: &mut Connection,
+    sources: &BackupSources,
+    path: &Path,
+    passphrase: Option<&str>,
+    dry_run: bool,
+) -> Result<RestorePlan, String> {
+    let (manifest, files) = load_backup(path, passphrase)?;
+    let changes = plan_restore(conn, sources, &files)?;
+    let mut plan = RestorePlan {
+        backup_path: path.to_string_lossy().to_string(),
+        created_at: manifest.created_at,
+        app_version: manifest.app_version,
+        dry_run,
+        changes,
+        pre_restore_backup: None,
+    };
+    if dry_run {
+        return Ok(plan);
+    }
+
+    for change in &plan.changes {
+        if change.action == RestoreAction::Unchanged {
+            continue;
+        }
+        let data = &files[&change.name];
+        let target = PathBuf::from(&change.target);
+        if change.name == DB_ENTRY {
+            let before = replace_database(conn, &target, data)?;
+            plan.pre_restore_backup = Some(before.id);
+            super::settings::apply_settings(conn);
+            continue;
+        }
+        snapshot_config_file(&target, "restore backup")?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        crate::atomic_file::write_atomic(&target, data)
+            .map_err(|e| format!("Failed to restore {}: {}", target.display(), e))?;
+    }
+    info!(
+        "Restored backup {} ({} entries)",
+        plan.backup_path,
+        plan.changes.len()
+    );
+    Ok(plan)
+}
+
+pub fn load_backup_settings(conn: &Connection) -> BackupSettings {
+    get_setting_as(conn, BACKUP_SETTINGS_KEY).unwrap_or_default()
+}
+
+fn backup_directory(settings: &BackupSettings) -> Result<PathBuf, String> {
+    settings
+        .directory
+        .as_deref()
+        .map(PathBuf::from)
+        .ok_or_else(|| "Choose a backup directory first".to_string())
+}
+
+/// Take a backup with the saved settings and apply retention afterwards
+fn run_backup(app: &AppHandle) -> Result<BackupInfo, String> {
+    let sources = BackupSources::from_app(app)?;
+    let (settings, files) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let settings = load_backup_settings(&conn);
+        (settings, collect_files(&conn, &sources)?)
+    };
+    let dir = backup_directory(&settings)?;
+    let passphrase = keychain::load_secret(PASSPHRASE_ACCOUNT);
+    let backup = write_backup(&dir, &files, &settings, passphrase.as_deref())?;
+    for removed in prune_backups(&dir, &settings, Utc::now()) {
+        info!("Removed old backup {}", removed);
+    }
+    Ok(backup)
+}
+
+/// Whether a scheduled backup is due in the configured directory
+fn backup_due(settings: &BackupSettings, now: DateTime<Utc>) -> bool {
+    let Some(dir) = settings.directory.as_deref().filter(|_| settings.enabled) else {
+        return false;
+    };
+    match list_backups_in(Path::new(dir)).first() {
+        Some(latest) => DateTime::parse_from_rfc3339(&latest.created_at).is_ok_and(|created| {
+            now - created.with_timezone(&Utc)
+                >= chrono::Duration::hours(settings.interval_hours.max(1) as i64)
+        }),
+        None => true,
+    }
+}
+
+/// Start the background task that takes backups on the configured schedule
+pub fn spawn_backup_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            let settings = {
+                let db = app.state::<AgentDb>();
+                let settings =
+                    db.0.lock()
+                        .map(|conn| load_backup_settings(&conn))
+                        .unwrap_or_default();
+                settings
+            };
+            if backup_due(&settings, Utc::now()) {
+                let handle = app.clone();
+                match tauri::async_runtime::spawn_blocking(move || run_backup(&handle)).await {
+                    Ok(Ok(backup)) => {
+                        info!("💾 Wrote backup {}", backup.path);
+                        let _ = app.emit("config-backup-created", &backup);
+                    }
+                    Ok(Err(e)) => warn!("Scheduled backup failed: {}", e),
+                    Err(e) => warn!("Scheduled backup task failed: {}", e),
+                }
+            }
+            tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_backup_settings(db: State<'_, AgentDb>) -> Result<BackupSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_backup_settings(&conn))
+}
+
+/// Save the backup settings; `passphrase` replaces the one kept for encrypted backups
+#[tauri::command]
+pub async fn set_backup_settings(
+    db: State<'_, AgentDb>,
+    settings: BackupSettings,
+    passphrase: Option<String>,
+) -> Result<BackupSettings, String> {
+    if settings.interval_hours == 0 {
+        return Err("The backup interval must be at least 1 hour".to_string());
+    }
+    if settings.keep_last == 0 || settings.max_age_days == Some(0) {
+        return Err("Keep at least one backup".to_string());
+    }
+    match settings.directory.as_deref() {
+        Some(dir) if !Path::new(dir).is_absolute() => {
+            return Err("The backup directory must be an absolute path".to_string());
+        }
+        None if settings.enabled => {
+            return Err("Choose a backup directory first".to_string());
+        }
+        _ => {}
+    }
+    if let Some(passphrase) = passphrase.as_deref().filter(|p| !p.is_empty()) {
+        keychain::store_secret(PASSPHRASE_ACCOUNT, passphrase)?;
+    }
+    if settings.encrypt && keychain::load_secret(PASSPHRASE_ACCOUNT).is_none() {
+        return Err("Set a passphrase for encrypted backups".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, BACKUP_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+/// Take a backup now with the saved settings
+#[tauri::command]
+pub async fn create_config_backup(app: AppHandle) -> Result<BackupInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || run_backup(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Backups in the configured directory, newest first
+#[tauri::command]
+pub async fn list_config_backups(db: State<'_, AgentDb>) -> Result<Vec<BackupInfo>, String> {
+    let settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_backup_settings(&conn)
+    };
+    Ok(settings
+        .directory
+        .map(|dir| list_backups_in(Path::new(&dir)))
+        .unwrap_or_default())
+}
+
+/// Restore a backup. With `dry_run` only the changes a restore would make are returned.
+/// Encrypted backups use `passphrase`, or the one in the keychain when it's not given.
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    path: String,
+    dry_run: Option<bool>,
+    passphrase: Option<String>,
+) -> Result<RestorePlan, String> {
+    let sources = BackupSources::from_app(&app)?;
+    let passphrase = passphrase.or_else(|| keychain::load_secret(PASSPHRASE_ACCOUNT));
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    restore(
+        &mut conn,
+        &sources,
+        Path::new(&path),
+        passphrase.as_deref(),
+        dry_run.unwrap_or(false),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(dir: &Path) -> (Connection, BackupSources) {
+        let home = dir.join("home");
+        fs::create_dir_all(home.join(".claude/agents")).unwrap();
+        fs::write(
+            home.join(".claude.json"),
+            r#"{"mcpServers":{"github":{"command":"gh-mcp"}},"theme":"dark"}"#,
+        )
+        .unwrap();
+        fs::write(home.join(".claude/settings.json"), r#"{"model":"opus"}"#).unwrap();
+        fs::write(home.join(".claude/agents/reviewer.md"), "# Reviewer").unwrap();
+
+        let db_path = dir.join("agents.db");
+        let conn = super::super::agents::open_database(&db_path).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('reviewer', 'bot', 'Review')",
+            [],
+        )
+        .unwrap();
+        (conn, BackupSources { db_path, home })
+    }
+
+    #[test]
+    fn test_encrypted_archive_round_trip_and_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let (conn, sources) = sources(dir.path());
+        let files = collect_files(&conn, &sources).unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec![
+                "claude/agents/reviewer.md",
+                "claude/settings.json",
+                DB_ENTRY,
+                "home/.claude.json"
+            ]
+        );
+
+        let backups_dir = dir.path().join("backups");
+        let settings = BackupSettings {
+            encrypt: true,
+            keep_last: 2,
+            ..BackupSettings::default()
+        };
+        assert!(write_backup(&backups_dir, &files, &settings, None).is_err());
+        let backup = write_backup(&backups_dir, &files, &settings, Some("hunter2")).unwrap();
+        assert!(backup.archive && backup.encrypted);
+
+        let path = Path::new(&backup.path);
+        assert!(load_backup(path, None).is_err());
+        assert!(load_backup(path, Some("wrong")).is_err());
+        let (manifest, loaded) = load_backup(path, Some("hunter2")).unwrap();
+        assert_eq!(manifest.files.len(), 4);
+        assert_eq!(loaded, files);
+
+        let plain = BackupSettings {
+            compress: false,
+            ..settings.clone()
+        };
+        for _ in 0..2 {
+            std::thread::sleep(Duration::from_millis(5));
+            write_backup(&backups_dir, &files, &plain, None).unwrap();
+        }
+        let removed = prune_backups(&backups_dir, &plain, Utc::now());
+        assert_eq!(removed, vec![backup.path.clone()]);
+        let remaining = list_backups_in(&backups_dir);
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().all(|backup| !backup.archive));
+        assert_eq!(
+            load_backup(Path::new(&remaining[0].path), None).unwrap().1,
+            files
+        );
+    }
+
+    #[test]
+    fn test_restore_plans_changes_before_applying() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut conn, sources) = sources(dir.path());
+        let files = collect_files(&conn, &sources).unwrap();
+        let backup = write_backup(
+            &dir.path().join("backups"),
+            &files,
+            &BackupSettings::default(),
+            None,
+        )
+        .unwrap();
+
+        fs::write(
+            sources.home.join(".claude.json"),
+            r#"{"mcpServers":{"github":{"command":"gh-mcp"},"slack":{"command":"slack-mcp"}}}"#,
+        )
+        .unwrap();
+        fs::remove_file(sources.home.join(".claude/agents/reviewer.md")).unwrap();
+        conn.execute("DELETE FROM agents", []).unwrap();
+
+        let plan = restore(&mut conn, &sources, Path::new(&backup.path), None, true).unwrap();
+        let action = |name: &str| {
+            plan.changes
+                .iter()
+                .find(|change| change.name == name)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            action("claude/settings.json").action,
+            RestoreAction::Unchanged
+        );
+        assert_eq!(
+            action("claude/agents/reviewer.md").action,
+            RestoreAction::Create
+        );
+        assert_eq!(
+            action("home/.claude.json").key_changes,
+            vec![
+                KeyChange {
+                    key: "mcpServers.slack".to_string(),
+                    kind: KeyChangeKind::Removed
+                },
+                KeyChange {
+                    key: "theme".to_string(),
+                    kind: KeyChangeKind::Added
+                },
+            ]
+        );
+        assert_eq!(
+            action(DB_ENTRY).table_changes,
+            vec![TableChange {
+                table: "agents".to_string(),
+                current_rows: 0,
+                backup_rows: 1
+            }]
+        );
+        // A dry run leaves everything as it is
+        assert!(!sources.home.join(".claude/agents/reviewer.md").exists());
+
+        assert!(sources.target("../etc/passwd").is_err());
+        assert!(sources.target("claude/agents/../../x").is_err());
+    }
+}
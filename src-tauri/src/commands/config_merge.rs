@@ -0,0 +1,347 @@
+#![allow(dead_code)]
+
+//! Three-way merge of edits to Claude settings files (`.claude/settings*.json`) and
+//! `.mcp.json`. When opcode reads or writes one of them, the contents are kept as the merge
+//! base. An edit made in opcode is then merged key by key with whatever is on disk now, so
+//! keys changed by the user or another machine in the meantime survive; only keys changed
+//! differently on both sides are reported as conflicts, to be resolved one by one.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::config_snapshots::{absolute, snapshot_config_file, snapshot_dir};
+
+/// A key both sides changed differently. `None` means the key is absent on that side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyConflict {
+    /// JSON pointer of the key, e.g. `/permissions/allow`
+    pub key: String,
+    pub base: Option<Value>,
+    /// The value edited in opcode
+    pub local: Option<Value>,
+    /// The value in the file now
+    pub disk: Option<Value>,
+}
+
+/// Which value a conflicting key takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSide {
+    Local,
+    Disk,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeOutcome {
+    /// False while conflicts are unresolved; the file is left as it is
+    pub written: bool,
+    /// The merged contents; conflicting keys keep the value on disk
+    pub merged: Value,
+    pub conflicts: Vec<KeyConflict>,
+}
+
+fn merge_base_dir() -> PathBuf {
+    snapshot_dir().join("merge-bases")
+}
+
+fn base_path(dir: &Path, file: &Path) -> PathBuf {
+    let hash: String = Sha256::digest(absolute(file).to_string_lossy().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    dir.join(format!("{}.json", hash))
+}
+
+/// Only settings files and `.mcp.json` are merged
+fn is_mergeable(file: &Path) -> bool {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let in_claude_dir = file
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir == ".claude");
+    name == ".mcp.json"
+        || (in_claude_dir && name.starts_with("settings") && name.ends_with(".json"))
+}
+
+fn read_json(file: &Path) -> Result<Option<Value>, String> {
+    match fs::read_to_string(file) {
+        Ok(content) if content.trim().is_empty() => Ok(None),
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {}: {}", file.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", file.display(), e)),
+    }
+}
+
+/// Keep the current contents of `file` in `dir` as the base of its next merge
+pub fn record_base_in(dir: &Path, file: &Path) -> Result<(), String> {
+    let target = base_path(dir, file);
+    match fs::read(file) {
+        Ok(content) => {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            crate::atomic_file::write_atomic(&target, content)
+                .map_err(|e| format!("Failed to save merge base: {}", e))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let _ = fs::remove_file(&target);
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to read {}: {}", file.display(), e)),
+    }
+}
+
+/// Keep the contents of a config file opcode just read or wrote as its merge base
+pub fn record_merge_base(file: &Path) {
+    if let Err(e) = record_base_in(&merge_base_dir(), file) {
+        log::warn!("Failed to record merge base of {}: {}", file.display(), e);
+    }
+}
+
+fn escape_key(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Merge one value. Objects are merged key by key; anything else is taken from the side that
+/// changed it, and is a conflict when both did.
+fn merge_value(
+    pointer: &str,
+    base: Option<&Value>,
+    local: Option<&Value>,
+    disk: Option<&Value>,
+    conflicts: &mut Vec<KeyConflict>,
+) -> Option<Value> {
+    if local == disk || local == base {
+        return disk.cloned();
+    }
+    if disk == base {
+        return local.cloned();
+    }
+    if let (Some(Value::Object(local)), Some(Value::Object(disk))) = (local, disk) {
+        let empty = Map::new();
+        let base = match base {
+            Some(Value::Object(base)) => base,
+            _ => &empty,
+        };
+        let keys: BTreeSet<&String> = base.keys().chain(local.keys()).chain(disk.keys()).collect();
+        let mut merged = Map::new();
+        for key in keys {
+            let value = merge_value(
+                &format!("{}/{}", pointer, escape_key(key)),
+                base.get(key),
+                local.get(key),
+                disk.get(key),
+                conflicts,
+            );
+            if let Some(value) = value {
+                merged.insert(key.clone(), value);
+            }
+        }
+        return Some(Value::Object(merged));
+    }
+    conflicts.push(KeyConflict {
+        key: pointer.to_string(),
+        base: base.cloned(),
+        local: local.cloned(),
+        disk: disk.cloned(),
+    });
+    disk.cloned()
+}
+
+/// Three-way merge of `local` and `disk` against `base`
+pub fn merge_json(
+    base: Option<&Value>,
+    local: &Value,
+    disk: Option<&Value>,
+) -> (Value, Vec<KeyConflict>) {
+    let mut conflicts = Vec::new();
+    let merged = merge_value("", base, Some(local), disk, &mut conflicts)
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    (merged, conflicts)
+}
+
+/// Set or remove the key at `pointer`, whose parent exists in `root`
+fn set_pointer(root: &mut Value, pointer: &str, value: Option<Value>) {
+    let Some((parent, key)) = pointer.rsplit_once('/') else {
+        *root = value.unwrap_or_else(|| Value::Object(Map::new()));
+        return;
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+    if let Some(Value::Object(parent)) = root.pointer_mut(parent) {
+        match value {
+            Some(value) => {
+                parent.insert(key, value);
+            }
+            None => {
+                parent.remove(&key);
+            }
+        }
+    }
+}
+
+/// Merge `edited` into `file` against its recorded base and write the result, taking the
+/// side given in `resolutions` for each conflicting key. Nothing is written while a conflict
+/// has no resolution. Without a recorded base, the edit wins over every key it changes.
+pub fn merge_into_file_in(
+    dir: &Path,
+    file: &Path,
+    edited: &Value,
+    resolutions: &BTreeMap<String, MergeSide>,
+) -> Result<MergeOutcome, String> {
+    if !is_mergeable(file) {
+        return Err(format!(
+            "{} is not a Claude settings file or .mcp.json",
+            file.display()
+        ));
+    }
+    let disk = read_json(file)?;
+    let base = match read_json(&base_path(dir, file)) {
+        Ok(Some(base)) => Some(base),
+        _ => disk.clone(),
+    };
+    let (mut merged, conflicts) = merge_json(base.as_ref(), edited, disk.as_ref());
+
+    let mut unresolved = Vec::new();
+    for conflict in conflicts {
+        match resolutions.get(&conflict.key) {
+            Some(MergeSide::Local) => set_pointer(&mut merged, &conflict.key, conflict.local),
+            Some(MergeSide::Disk) => {}
+            None => unresolved.push(conflict),
+        }
+    }
+    if !unresolved.is_empty() {
+        return Ok(MergeOutcome {
+            written: false,
+            merged,
+            conflicts: unresolved,
+        });
+    }
+
+    let content = serde_json::to_string_pretty(&merged)
+        .map_err(|e| format!("Failed to serialize {}: {}", file.display(), e))?;
+    snapshot_config_file(file, "merge edit")?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    crate::atomic_file::write_atomic(file, content)
+        .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+    record_base_in(dir, file)?;
+    Ok(MergeOutcome {
+        written: true,
+        merged,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Read a settings file or `.mcp.json` for editing; what's read becomes the merge base
+#[tauri::command]
+pub async fn read_config_for_merge(path: String) -> Result<Value, String> {
+    let file = PathBuf::from(&path);
+    if !is_mergeable(&file) {
+        return Err(format!(
+            "{} is not a Claude settings file or .mcp.json",
+            path
+        ));
+    }
+    let value = read_json(&file)?.unwrap_or_else(|| Value::Object(Map::new()));
+    record_merge_base(&file);
+    Ok(value)
+}
+
+/// Save an edited settings file or `.mcp.json`, merged with changes made to it elsewhere
+/// since opcode read it. Conflicting keys are returned unwritten until `resolutions` picks
+/// a side for each, keyed by JSON pointer.
+#[tauri::command]
+pub async fn save_config_merged(
+    path: String,
+    content: Value,
+    resolutions: Option<BTreeMap<String, MergeSide>>,
+) -> Result<MergeOutcome, String> {
+    merge_into_file_in(
+        &merge_base_dir(),
+        Path::new(&path),
+        &content,
+        &resolutions.unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_keeps_changes_from_both_sides() {
+        let base = json!({ "model": "sonnet", "permissions": { "allow": ["Read"], "deny": [] }, "theme": "dark" });
+        let local = json!({ "model": "opus", "permissions": { "allow": ["Read"], "deny": ["Bash(rm:*)"] }, "theme": "dark" });
+        let disk = json!({ "model": "sonnet", "permissions": { "allow": ["Read", "Edit"], "deny": [] }, "env": { "A": "1" } });
+
+        let (merged, conflicts) = merge_json(Some(&base), &local, Some(&disk));
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            merged,
+            json!({
+                "model": "opus",
+                "permissions": { "allow": ["Read", "Edit"], "deny": ["Bash(rm:*)"] },
+                "env": { "A": "1" }
+            })
+        );
+
+        let disk = json!({ "model": "haiku", "permissions": { "allow": ["Read"], "deny": [] }, "theme": "dark" });
+        let (merged, conflicts) = merge_json(Some(&base), &local, Some(&disk));
+        assert_eq!(
+            conflicts,
+            vec![KeyConflict {
+                key: "/model".to_string(),
+                base: Some(json!("sonnet")),
+                local: Some(json!("opus")),
+                disk: Some(json!("haiku")),
+            }]
+        );
+        // Unresolved keys keep what's on disk
+        assert_eq!(merged["model"], json!("haiku"));
+        assert_eq!(merged["permissions"]["deny"], json!(["Bash(rm:*)"]));
+    }
+
+    #[test]
+    fn test_file_is_written_once_conflicts_are_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let bases = dir.path().join("bases");
+        let file = dir.path().join("project/.claude/settings.json");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, r#"{"model":"sonnet","hooks":{}}"#).unwrap();
+        record_base_in(&bases, &file).unwrap();
+
+        // Someone else changes the file after opcode read it
+        fs::write(&file, r#"{"model":"haiku","hooks":{},"env":{"A":"1"}}"#).unwrap();
+        let edited = json!({ "model": "opus", "hooks": {} });
+        let outcome = merge_into_file_in(&bases, &file, &edited, &BTreeMap::new()).unwrap();
+        assert!(!outcome.written);
+        assert_eq!(outcome.conflicts[0].key, "/model");
+        assert!(fs::read_to_string(&file).unwrap().contains("haiku"));
+
+        let resolutions = BTreeMap::from([("/model".to_string(), MergeSide::Local)]);
+        let outcome = merge_into_file_in(&bases, &file, &edited, &resolutions).unwrap();
+        assert!(outcome.written);
+        let written: Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            json!({ "model": "opus", "hooks": {}, "env": { "A": "1" } })
+        );
+
+        assert!(merge_into_file_in(
+            &bases,
+            &dir.path().join("notes.json"),
+            &edited,
+            &resolutions
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,244 @@
+#![allow(dead_code)]
+
+//! Snapshots of Claude Code configuration files (`.mcp.json`, `~/.claude.json`,
+//! `settings*.json`) taken before opcode writes to them, so a bad edit or import can be
+//! rolled back.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Snapshots kept per file; older ones are removed when a new one is taken
+const MAX_SNAPSHOTS_PER_FILE: usize = 50;
+
+/// Distinguishes snapshots taken within the same millisecond
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+/// A saved copy of a config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    /// Absolute path of the file the snapshot was taken from
+    pub file: String,
+    /// False when the file did not exist yet; restoring removes it
+    pub existed: bool,
+    pub size: u64,
+    /// What was about to change the file, e.g. `mcp add` or `save settings`
+    pub reason: String,
+    pub created_at: String,
+}
+
+pub fn snapshot_dir() -> PathBuf {
+    let log_dir = crate::logger::log_dir();
+    log_dir
+        .parent()
+        .map(|dir| dir.join("config-history"))
+        .unwrap_or_else(|| log_dir.join("config-history"))
+}
+
+/// `~/.claude.json`, which `claude mcp` edits for user and local scoped servers
+pub fn user_claude_json() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude.json"))
+}
+
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+fn validate_snapshot_id(id: &str) -> Result<(), String> {
+    let valid = id.starts_with("snap-") && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid snapshot id: {}", id))
+    }
+}
+
+fn read_meta(path: &Path) -> Option<ConfigSnapshot> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Snapshots in `dir`, newest first, optionally only those of `file`
+pub fn list_snapshots_in(dir: &Path, file: Option<&Path>) -> Vec<ConfigSnapshot> {
+    let file = file.map(|file| absolute(file).to_string_lossy().to_string());
+    let mut snapshots: Vec<ConfigSnapshot> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+                .filter_map(|path| read_meta(&path))
+                .filter(|snapshot| file.as_ref().is_none_or(|file| &snapshot.file == file))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Ids sort chronologically
+    snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+    snapshots
+}
+
+fn remove_snapshot(dir: &Path, id: &str) {
+    let _ = fs::remove_file(dir.join(format!("{}.json", id)));
+    let _ = fs::remove_file(dir.join(format!("{}.snapshot", id)));
+}
+
+fn snapshot_content(dir: &Path, snapshot: &ConfigSnapshot) -> Option<Vec<u8>> {
+    if !snapshot.existed {
+        return None;
+    }
+    fs::read(dir.join(format!("{}.snapshot", snapshot.id))).ok()
+}
+
+/// Save the current contents of `file` into `dir`. Nothing is saved when the file is
+/// unchanged since its last snapshot.
+pub fn snapshot_file_in(dir: &Path, file: &Path, reason: &str) -> Result<Option<ConfigSnapshot>, String> {
+    let file = absolute(file);
+    let content = match fs::read(&file) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read {}: {}", file.display(), e)),
+    };
+
+    let existing = list_snapshots_in(dir, Some(&file));
+    if let Some(latest) = existing.first() {
+        if snapshot_content(dir, latest) == content {
+            return Ok(None);
+        }
+    }
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let now = chrono::Utc::now();
+    let id = format!(
+        "snap-{}-{:04}",
+        now.format("%Y%m%dT%H%M%S%3f"),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed) % 10_000
+    );
+    let snapshot = ConfigSnapshot {
+        id: id.clone(),
+        file: file.to_string_lossy().to_string(),
+        existed: content.is_some(),
+        size: content.as_ref().map(|c| c.len() as u64).unwrap_or(0),
+        reason: reason.to_string(),
+        created_at: now.to_rfc3339(),
+    };
+    if let Some(content) = &content {
+        crate::atomic_file::write_atomic(dir.join(format!("{}.snapshot", id)), content)
+            .map_err(|e| format!("Failed to save snapshot: {}", e))?;
+    }
+    let meta = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    crate::atomic_file::write_atomic(dir.join(format!("{}.json", id)), meta)
+        .map_err(|e| format!("Failed to save snapshot: {}", e))?;
+
+    for old in existing.iter().skip(MAX_SNAPSHOTS_PER_FILE - 1) {
+        remove_snapshot(dir, &old.id);
+    }
+    Ok(Some(snapshot))
+}
+
+/// Put a file back the way it was when snapshot `id` was taken. The current contents are
+/// snapshotted first, so a restore can itself be undone.
+pub fn restore_snapshot_in(dir: &Path, id: &str) -> Result<ConfigSnapshot, String> {
+    validate_snapshot_id(id)?;
+    let snapshot = read_meta(&dir.join(format!("{}.json", id)))
+        .ok_or_else(|| format!("Snapshot {} not found", id))?;
+    let file = PathBuf::from(&snapshot.file);
+    let content = if snapshot.existed {
+        Some(
+            snapshot_content(dir, &snapshot)
+                .ok_or_else(|| format!("Snapshot {} is missing its contents", id))?,
+        )
+    } else {
+        None
+    };
+
+    snapshot_file_in(dir, &file, &format!("restore {}", id))?;
+    match content {
+        Some(content) => {
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            crate::atomic_file::write_atomic(&file, content)
+                .map_err(|e| format!("Failed to restore {}: {}", file.display(), e))?;
+        }
+        None => {
+            if file.exists() {
+                fs::remove_file(&file)
+                    .map_err(|e| format!("Failed to remove {}: {}", file.display(), e))?;
+            }
+        }
+    }
+    log::info!("Restored {} from snapshot {}", snapshot.file, id);
+    Ok(snapshot)
+}
+
+/// Snapshot a config file before opcode writes to it
+pub fn snapshot_config_file(file: &Path, reason: &str) -> Result<(), String> {
+    snapshot_file_in(&snapshot_dir(), file, reason).map(|_| ())
+}
+
+/// List snapshots, newest first; pass `file` to only list those of one file
+#[tauri::command]
+pub async fn list_config_snapshots(file: Option<String>) -> Result<Vec<ConfigSnapshot>, String> {
+    Ok(list_snapshots_in(
+        &snapshot_dir(),
+        file.as_deref().map(Path::new),
+    ))
+}
+
+/// Restore a config file from a snapshot
+#[tauri::command]
+pub async fn restore_config_snapshot(id: String) -> Result<ConfigSnapshot, String> {
+    restore_snapshot_in(&snapshot_dir(), &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_and_restore_round_trip() {
+        let history = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let file = project.path().join(".mcp.json");
+        fs::write(&file, "{\"mcpServers\":{}}").unwrap();
+
+        let first = snapshot_file_in(history.path(), &file, "save").unwrap().unwrap();
+        // Unchanged contents are not snapshotted twice
+        assert!(snapshot_file_in(history.path(), &file, "save").unwrap().is_none());
+
+        fs::write(&file, "broken").unwrap();
+        restore_snapshot_in(history.path(), &first.id).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{\"mcpServers\":{}}");
+
+        // The restore snapshotted the broken version, so it can be undone too
+        let snapshots = list_snapshots_in(history.path(), Some(&file));
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].reason, format!("restore {}", first.id));
+        assert!(restore_snapshot_in(history.path(), "../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_restoring_a_missing_file_snapshot_removes_it() {
+        let history = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let file = project.path().join("settings.local.json");
+
+        let snapshot = snapshot_file_in(history.path(), &file, "create").unwrap().unwrap();
+        assert!(!snapshot.existed);
+        fs::write(&file, "{}").unwrap();
+
+        restore_snapshot_in(history.path(), &snapshot.id).unwrap();
+        assert!(!file.exists());
+        assert_eq!(
+            list_snapshots_in(history.path(), None).len(),
+            2
+        );
+    }
+}
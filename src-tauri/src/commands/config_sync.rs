@@ -0,0 +1,1294 @@
+#![allow(dead_code)]
+
+//! Git-backed sync of the shareable configuration: agents (with their retry, model and
+//! sandbox policies), MCP stacks, prompt templates and sandbox profiles. Each item is one
+//! JSON file in a repository the user picks, so a team can keep their setups converged
+//! through an ordinary git remote.
+//!
+//! A sync writes the items changed locally since the last sync into the repository and
+//! commits them, pulls the remote branch, imports whatever the merge produced and pushes.
+//! When the pull conflicts the merge is left in progress and the conflicting files are
+//! reported; each is resolved by keeping the local or the remote side before the next sync.
+//!
+//! Secrets stay out of the repository: MCP server env and header values are written empty,
+//! and an import keeps the values already configured locally.
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use super::agent_retry::{load_retry_policy, save_retry_policy, RetryPolicy};
+use super::agents::{agent_from_row, AgentDb, AGENT_COLUMNS};
+use super::mcp::MCPServerConfig;
+use super::model_policy::load_model_policy;
+use super::sandbox::{load_agent_sandbox_profile, profile_from_row, PROFILE_COLUMNS};
+use super::settings::{get_setting_as, set_setting_as};
+use super::thinking::RunThinking;
+use super::tool_rules::ToolRules;
+use super::worktrees::{commit_worktree, git, identity_args, slug};
+
+/// app_settings key holding the `ConfigSyncSettings`
+const SYNC_SETTINGS_KEY: &str = "config_sync";
+
+/// app_settings key holding what the last sync left in the repository
+const SYNC_STATE_KEY: &str = "config_sync_state";
+
+/// Written in place of MCP env and header values
+const REDACTED_VALUE: &str = "";
+
+const COMMIT_MESSAGE: &str = "Update opcode configuration";
+
+/// How long a pull or push may wait on the remote before it is given up
+const REMOTE_GIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigSyncSettings {
+    /// Local checkout of the sync repository; created when missing
+    pub repo_path: Option<String>,
+    /// Pulled from and pushed to as `origin`; without one the repository is only local
+    pub remote_url: Option<String>,
+    pub branch: String,
+}
+
+impl Default for ConfigSyncSettings {
+    fn default() -> Self {
+        Self {
+            repo_path: None,
+            remote_url: None,
+            branch: "main".to_string(),
+        }
+    }
+}
+
+/// The kinds of items that are synced, each in a directory of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncKind {
+    SandboxProfile,
+    Agent,
+    McpStack,
+    PromptTemplate,
+}
+
+impl SyncKind {
+    /// Sandbox profiles come first, as agents refer to them by name
+    pub const ALL: [SyncKind; 4] = [
+        SyncKind::SandboxProfile,
+        SyncKind::Agent,
+        SyncKind::McpStack,
+        SyncKind::PromptTemplate,
+    ];
+
+    fn dir(self) -> &'static str {
+        match self {
+            SyncKind::SandboxProfile => "sandbox-profiles",
+            SyncKind::Agent => "agents",
+            SyncKind::McpStack => "mcp-stacks",
+            SyncKind::PromptTemplate => "prompt-templates",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            SyncKind::SandboxProfile => "sandbox_profiles",
+            SyncKind::Agent => "agents",
+            SyncKind::McpStack => "mcp_stacks",
+            SyncKind::PromptTemplate => "prompt_templates",
+        }
+    }
+
+    fn name_column(self) -> &'static str {
+        match self {
+            SyncKind::PromptTemplate => "title",
+            _ => "name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncedAgent {
+    name: String,
+    icon: String,
+    system_prompt: String,
+    default_task: Option<String>,
+    model: String,
+    enable_file_read: bool,
+    enable_file_write: bool,
+    enable_network: bool,
+    hooks: Option<String>,
+    #[serde(default)]
+    thinking: Option<RunThinking>,
+    #[serde(default)]
+    tool_rules: Option<ToolRules>,
+    #[serde(default)]
+    retry_policy: Option<RetryPolicy>,
+    #[serde(default)]
+    fallback_models: Vec<String>,
+    /// Name of the attached sandbox profile
+    #[serde(default)]
+    sandbox_profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncedStack {
+    name: String,
+    description: Option<String>,
+    servers: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncedTemplate {
+    title: String,
+    body: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncedProfile {
+    name: String,
+    allowed_directories: Vec<String>,
+    network_enabled: bool,
+    denied_commands: Vec<String>,
+}
+
+/// An item as stored in the repository
+#[derive(Debug, Clone, PartialEq)]
+struct ExportedItem {
+    kind: SyncKind,
+    name: String,
+    value: Value,
+}
+
+impl ExportedItem {
+    fn content(&self) -> String {
+        let mut content = serde_json::to_string_pretty(&self.value).unwrap_or_default();
+        content.push('\n');
+        content
+    }
+}
+
+/// A file as the last sync left it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncedFile {
+    kind: SyncKind,
+    name: String,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SyncState {
+    /// By path in the repository
+    files: BTreeMap<String, SyncedFile>,
+    last_synced_at: Option<String>,
+    last_commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// Commit of the local changes, or of a resolved merge
+    pub committed: Option<String>,
+    pub pulled: bool,
+    pub pushed: bool,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Files the merge couldn't reconcile; nothing was imported
+    pub conflicts: Vec<String>,
+    /// Files in the repository that couldn't be imported, with the reason
+    pub skipped: Vec<String>,
+    /// A failed push doesn't fail the sync; the commits go out with the next one
+    pub push_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub configured: bool,
+    pub last_synced_at: Option<String>,
+    pub last_commit: Option<String>,
+    /// Items added, changed or deleted locally since the last sync
+    pub local_changes: usize,
+    pub merging: bool,
+    pub conflicts: Vec<String>,
+}
+
+/// Which side of a conflict to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictSide {
+    Local,
+    Remote,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// `value` with object keys in order, so an item always serializes the same way
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sorted(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sorted).collect()),
+        other => other,
+    }
+}
+
+fn to_value<T: Serialize>(item: &T) -> Result<Value, String> {
+    serde_json::to_value(item)
+        .map(sorted)
+        .map_err(|e| e.to_string())
+}
+
+/// A server config with its env and header values blanked
+fn redact_server(server: &MCPServerConfig) -> Result<Value, String> {
+    let mut server = server.clone();
+    for value in server.env.values_mut() {
+        *value = REDACTED_VALUE.to_string();
+    }
+    for value in server
+        .headers
+        .iter_mut()
+        .flat_map(|headers| headers.values_mut())
+    {
+        *value = REDACTED_VALUE.to_string();
+    }
+    to_value(&server)
+}
+
+/// `server` with its redacted values taken from the local config of the same server
+fn unredact_server(
+    mut server: MCPServerConfig,
+    local: Option<&MCPServerConfig>,
+) -> MCPServerConfig {
+    let Some(local) = local else {
+        return server;
+    };
+    for (key, value) in server.env.iter_mut() {
+        if value == REDACTED_VALUE {
+            if let Some(local_value) = local.env.get(key) {
+                *value = local_value.clone();
+            }
+        }
+    }
+    if let (Some(headers), Some(local_headers)) = (server.headers.as_mut(), local.headers.as_ref())
+    {
+        for (key, value) in headers.iter_mut() {
+            if value == REDACTED_VALUE {
+                if let Some(local_value) = local_headers.get(key) {
+                    *value = local_value.clone();
+                }
+            }
+        }
+    }
+    server
+}
+
+fn query_all<T>(
+    conn: &Connection,
+    sql: &str,
+    map: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], map).map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+fn export_kind(conn: &Connection, kind: SyncKind) -> Result<Vec<ExportedItem>, String> {
+    let mut items = Vec::new();
+    match kind {
+        SyncKind::SandboxProfile => {
+            let profiles = query_all(
+                conn,
+                &format!(
+                    "SELECT {} FROM sandbox_profiles ORDER BY name",
+                    PROFILE_COLUMNS
+                ),
+                profile_from_row,
+            )?;
+            for profile in profiles {
+                let value = to_value(&SyncedProfile {
+                    name: profile.name.clone(),
+                    allowed_directories: profile.allowed_directories,
+                    network_enabled: profile.network_enabled,
+                    denied_commands: profile.denied_commands,
+                })?;
+                items.push((profile.name, value));
+            }
+        }
+        SyncKind::Agent => {
+            let agents = query_all(
+                conn,
+                &format!("SELECT {} FROM agents ORDER BY name, id", AGENT_COLUMNS),
+                agent_from_row,
+            )?;
+            for agent in agents {
+                let id = agent.id.unwrap_or_default();
+                let value = to_value(&SyncedAgent {
+                    name: agent.name.clone(),
+                    icon: agent.icon,
+                    system_prompt: agent.system_prompt,
+                    default_task: agent.default_task,
+                    model: agent.model,
+                    enable_file_read: agent.enable_file_read,
+                    enable_file_write: agent.enable_file_write,
+                    enable_network: agent.enable_network,
+                    hooks: agent.hooks,
+                    thinking: agent.thinking,
+                    tool_rules: agent.tool_rules,
+                    retry_policy: Some(load_retry_policy(conn, id).map_err(|e| e.to_string())?),
+                    fallback_models: load_model_policy(conn, id)
+                        .map_err(|e| e.to_string())?
+                        .fallbacks,
+                    sandbox_profile: load_agent_sandbox_profile(conn, id)
+                        .map_err(|e| e.to_string())?
+                        .map(|profile| profile.name),
+                })?;
+                items.push((agent.name, value));
+            }
+        }
+        SyncKind::McpStack => {
+            let stacks = query_all(
+                conn,
+                "SELECT name, description, servers FROM mcp_stacks ORDER BY name",
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )?;
+            for (name, description, servers) in stacks {
+                let servers: BTreeMap<String, MCPServerConfig> =
+                    serde_json::from_str(&servers).unwrap_or_default();
+                let value = to_value(&SyncedStack {
+                    name: name.clone(),
+                    description,
+                    servers: servers
+                        .iter()
+                        .map(|(name, server)| Ok((name.clone(), redact_server(server)?)))
+                        .collect::<Result<_, String>>()?,
+                })?;
+                items.push((name, value));
+            }
+        }
+        SyncKind::PromptTemplate => {
+            let templates = query_all(
+                conn,
+                "SELECT title, body, tags FROM prompt_templates ORDER BY title",
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )?;
+            for (title, body, tags) in templates {
+                let value = to_value(&SyncedTemplate {
+                    title: title.clone(),
+                    body,
+                    tags: serde_json::from_str(&tags).unwrap_or_default(),
+                })?;
+                items.push((title, value));
+            }
+        }
+    }
+    Ok(items
+        .into_iter()
+        .map(|(name, value)| ExportedItem { kind, name, value })
+        .collect())
+}
+
+/// Every shareable item by its path in the repository
+fn export_items(conn: &Connection) -> Result<BTreeMap<String, ExportedItem>, String> {
+    let mut items = BTreeMap::new();
+    for kind in SyncKind::ALL {
+        for item in export_kind(conn, kind)? {
+            let mut path = format!("{}/{}.json", kind.dir(), slug(&item.name));
+            if items.contains_key(&path) {
+                // Names that only differ in punctuation or case
+                path = format!(
+                    "{}/{}-{}.json",
+                    kind.dir(),
+                    slug(&item.name),
+                    &sha256_hex(item.name.as_bytes())[..8]
+                );
+            }
+            items.insert(path, item);
+        }
+    }
+    Ok(items)
+}
+
+fn synced_files(items: &BTreeMap<String, ExportedItem>) -> BTreeMap<String, SyncedFile> {
+    items
+        .iter()
+        .map(|(path, item)| {
+            (
+                path.clone(),
+                SyncedFile {
+                    kind: item.kind,
+                    name: item.name.clone(),
+                    sha256: sha256_hex(item.content().as_bytes()),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Paths of the items changed locally since the last sync, and of those deleted
+fn local_changes(
+    repo: &Path,
+    items: &BTreeMap<String, ExportedItem>,
+    state: &SyncState,
+) -> (Vec<String>, Vec<String>) {
+    let current = synced_files(items);
+    let changed = current
+        .iter()
+        .filter(|(path, file)| match state.files.get(*path) {
+            Some(synced) => synced.sha256 != file.sha256,
+            // Never synced from here: a copy already in the repository wins
+            None => !repo.join(path).exists(),
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    let deleted = state
+        .files
+        .keys()
+        .filter(|path| !current.contains_key(*path))
+        .cloned()
+        .collect();
+    (changed, deleted)
+}
+
+/// Name of the item stored in a synced file
+fn item_name(kind: SyncKind, value: &Value) -> Option<String> {
+    value
+        .get(kind.name_column())
+        .and_then(Value::as_str)
+        .filter(|name| !name.trim().is_empty())
+        .map(str::to_string)
+}
+
+/// The items in the repository by path, plus the files that couldn't be read
+fn read_tree(repo: &Path) -> (BTreeMap<String, ExportedItem>, Vec<String>) {
+    let mut items = BTreeMap::new();
+    let mut skipped = Vec::new();
+    for kind in SyncKind::ALL {
+        let Ok(entries) = fs::read_dir(repo.join(kind.dir())) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !file_name.ends_with(".json") {
+                continue;
+            }
+            let path = format!("{}/{}", kind.dir(), file_name);
+            let value = fs::read_to_string(entry.path())
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    serde_json::from_str::<Value>(&content).map_err(|e| e.to_string())
+                });
+            match value {
+                Ok(value) => match item_name(kind, &value) {
+                    Some(name) => {
+                        items.insert(
+                            path,
+                            ExportedItem {
+                                kind,
+                                name,
+                                value: sorted(value),
+                            },
+                        );
+                    }
+                    None => skipped.push(format!("{}: no {}", path, kind.name_column())),
+                },
+                Err(e) => skipped.push(format!("{}: {}", path, e)),
+            }
+        }
+    }
+    (items, skipped)
+}
+
+fn id_by_name(tx: &Transaction, kind: SyncKind, name: &str) -> Result<Option<i64>, String> {
+    tx.query_row(
+        &format!(
+            "SELECT id FROM {} WHERE {} = ?1 ORDER BY id LIMIT 1",
+            kind.table(),
+            kind.name_column()
+        ),
+        params![name],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Create or update the item `value` describes; returns whether it was created
+fn import_item(tx: &Transaction, kind: SyncKind, value: &Value) -> Result<bool, String> {
+    match kind {
+        SyncKind::SandboxProfile => {
+            let profile: SyncedProfile =
+                serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            let allowed =
+                serde_json::to_string(&profile.allowed_directories).map_err(|e| e.to_string())?;
+            let denied =
+                serde_json::to_string(&profile.denied_commands).map_err(|e| e.to_string())?;
+            let existing = id_by_name(tx, kind, &profile.name)?;
+            match existing {
+                Some(id) => tx.execute(
+                    "UPDATE sandbox_profiles SET allowed_directories = ?1, network_enabled = ?2, denied_commands = ?3 WHERE id = ?4",
+                    params![allowed, profile.network_enabled, denied, id],
+                ),
+                None => tx.execute(
+                    "INSERT INTO sandbox_profiles (name, allowed_directories, network_enabled, denied_commands) VALUES (?1, ?2, ?3, ?4)",
+                    params![profile.name, allowed, profile.network_enabled, denied],
+                ),
+            }
+            .map_err(|e| format!("Failed to save sandbox profile: {}", e))?;
+            Ok(existing.is_none())
+        }
+        SyncKind::Agent => {
+            let agent: SyncedAgent =
+                serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            let thinking = agent.thinking.as_ref().and_then(RunThinking::to_column);
+            let tool_rules = agent.tool_rules.as_ref().and_then(ToolRules::to_column);
+            let existing = id_by_name(tx, kind, &agent.name)?;
+            let agent_id = match existing {
+                Some(id) => {
+                    tx.execute(
+                        "UPDATE agents SET icon = ?1, system_prompt = ?2, default_task = ?3, model = ?4, enable_file_read = ?5, enable_file_write = ?6, enable_network = ?7, hooks = ?8, thinking = ?9, tool_rules = ?10, updated_at = CURRENT_TIMESTAMP WHERE id = ?11",
+                        params![agent.icon, agent.system_prompt, agent.default_task, agent.model, agent.enable_file_read, agent.enable_file_write, agent.enable_network, agent.hooks, thinking, tool_rules, id],
+                    )
+                    .map_err(|e| format!("Failed to update agent: {}", e))?;
+                    id
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, thinking, tool_rules) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params![agent.name, agent.icon, agent.system_prompt, agent.default_task, agent.model, agent.enable_file_read, agent.enable_file_write, agent.enable_network, agent.hooks, thinking, tool_rules],
+                    )
+                    .map_err(|e| format!("Failed to create agent: {}", e))?;
+                    tx.last_insert_rowid()
+                }
+            };
+
+            if let Some(policy) = &agent.retry_policy {
+                save_retry_policy(tx, agent_id, policy)?;
+            }
+            let fallbacks =
+                serde_json::to_string(&agent.fallback_models).map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT INTO agent_model_policies (agent_id, fallback_models) VALUES (?1, ?2)
+                 ON CONFLICT(agent_id) DO UPDATE SET fallback_models = ?2, updated_at = CURRENT_TIMESTAMP",
+                params![agent_id, fallbacks],
+            )
+            .map_err(|e| format!("Failed to save model policy: {}", e))?;
+            let profile_id = match &agent.sandbox_profile {
+                Some(name) => id_by_name(tx, SyncKind::SandboxProfile, name)?,
+                None => None,
+            };
+            match profile_id {
+                Some(profile_id) => tx.execute(
+                    "INSERT OR REPLACE INTO agent_sandbox_profiles (agent_id, profile_id) VALUES (?1, ?2)",
+                    params![agent_id, profile_id],
+                ),
+                None => tx.execute(
+                    "DELETE FROM agent_sandbox_profiles WHERE agent_id = ?1",
+                    params![agent_id],
+                ),
+            }
+            .map_err(|e| e.to_string())?;
+            Ok(existing.is_none())
+        }
+        SyncKind::McpStack => {
+            let stack: SyncedStack =
+                serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            let existing = id_by_name(tx, kind, &stack.name)?;
+            let local: HashMap<String, MCPServerConfig> = match existing {
+                Some(id) => tx
+                    .query_row(
+                        "SELECT servers FROM mcp_stacks WHERE id = ?1",
+                        params![id],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .ok()
+                    .and_then(|servers| serde_json::from_str(&servers).ok())
+                    .unwrap_or_default(),
+                None => HashMap::new(),
+            };
+            let mut servers = BTreeMap::new();
+            for (name, server) in stack.servers {
+                let server: MCPServerConfig = serde_json::from_value(server)
+                    .map_err(|e| format!("Invalid server {}: {}", name, e))?;
+                let server = unredact_server(server, local.get(&name));
+                servers.insert(name, server);
+            }
+            let servers = serde_json::to_string(&servers).map_err(|e| e.to_string())?;
+            match existing {
+                Some(id) => tx.execute(
+                    "UPDATE mcp_stacks SET description = ?1, servers = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+                    params![stack.description, servers, id],
+                ),
+                None => tx.execute(
+                    "INSERT INTO mcp_stacks (name, description, servers) VALUES (?1, ?2, ?3)",
+                    params![stack.name, stack.description, servers],
+                ),
+            }
+            .map_err(|e| format!("Failed to save MCP stack: {}", e))?;
+            Ok(existing.is_none())
+        }
+        SyncKind::PromptTemplate => {
+            let template: SyncedTemplate =
+                serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            let tags = serde_json::to_string(&template.tags).map_err(|e| e.to_string())?;
+            let existing = id_by_name(tx, kind, &template.title)?;
+            match existing {
+                Some(id) => tx.execute(
+                    "UPDATE prompt_templates SET body = ?1, tags = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+                    params![template.body, tags, id],
+                ),
+                None => tx.execute(
+                    "INSERT INTO prompt_templates (title, body, tags) VALUES (?1, ?2, ?3)",
+                    params![template.title, template.body, tags],
+                ),
+            }
+            .map_err(|e| format!("Failed to save prompt template: {}", e))?;
+            Ok(existing.is_none())
+        }
+    }
+}
+
+/// Delete the item named `name`; returns whether there was one
+fn delete_item(tx: &Transaction, kind: SyncKind, name: &str) -> Result<bool, String> {
+    if kind == SyncKind::McpStack {
+        tx.execute(
+            "DELETE FROM mcp_stack_applications WHERE stack_id IN (SELECT id FROM mcp_stacks WHERE name = ?1)",
+            params![name],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    let deleted = tx
+        .execute(
+            &format!(
+                "DELETE FROM {} WHERE {} = ?1",
+                kind.table(),
+                kind.name_column()
+            ),
+            params![name],
+        )
+        .map_err(|e| format!("Failed to delete {}: {}", name, e))?;
+    Ok(deleted > 0)
+}
+
+/// Bring the database in line with the items in the repository. Items that were synced
+/// before but are gone from it were deleted elsewhere and are deleted here too.
+fn import_tree(
+    conn: &mut Connection,
+    tree: &BTreeMap<String, ExportedItem>,
+    state: &SyncState,
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let local: HashMap<(SyncKind, String), Value> = export_items(conn)?
+        .into_values()
+        .map(|item| ((item.kind, item.name), item.value))
+        .collect();
+    let in_tree: HashSet<(SyncKind, &str)> = tree
+        .values()
+        .map(|item| (item.kind, item.name.as_str()))
+        .collect();
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for kind in SyncKind::ALL {
+        for (path, item) in tree.iter().filter(|(_, item)| item.kind == kind) {
+            if local.get(&(kind, item.name.clone())) == Some(&item.value) {
+                continue;
+            }
+            match import_item(&tx, kind, &item.value) {
+                Ok(true) => report.added += 1,
+                Ok(false) => report.updated += 1,
+                Err(e) => report.skipped.push(format!("{}: {}", path, e)),
+            }
+        }
+    }
+    for file in state.files.values() {
+        if !in_tree.contains(&(file.kind, file.name.as_str()))
+            && delete_item(&tx, file.kind, &file.name)?
+        {
+            report.removed += 1;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// The checkout at `repo_path`, initialised on the configured branch when it isn't a
+/// repository yet, with `origin` pointing at the configured remote
+fn prepare_repo(settings: &ConfigSyncSettings) -> Result<PathBuf, String> {
+    let repo = PathBuf::from(
+        settings
+            .repo_path
+            .as_deref()
+            .ok_or("Choose a repository for config sync first")?,
+    );
+    if !repo.join(".git").exists() {
+        fs::create_dir_all(&repo)
+            .map_err(|e| format!("Failed to create {}: {}", repo.display(), e))?;
+        git(&repo, &["init", "-q", "-b", &settings.branch])?;
+    }
+    if let Some(url) = settings.remote_url.as_deref() {
+        match git(&repo, &["remote", "get-url", "origin"]) {
+            Ok(current) if current == url => {}
+            Ok(_) => {
+                git(&repo, &["remote", "set-url", "origin", url])?;
+            }
+            Err(_) => {
+                git(&repo, &["remote", "add", "origin", url])?;
+            }
+        }
+    }
+    Ok(repo)
+}
+
+fn is_merging(repo: &Path) -> bool {
+    git(repo, &["rev-parse", "-q", "--verify", "MERGE_HEAD"]).is_ok()
+}
+
+/// Files with unresolved conflicts
+fn conflicted_files(repo: &Path) -> Result<Vec<String>, String> {
+    Ok(git(repo, &["diff", "--name-only", "--diff-filter=U"])?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+fn has_origin(repo: &Path) -> bool {
+    git(repo, &["remote", "get-url", "origin"]).is_ok()
+}
+
+/// Run a git command that talks to the remote. Git may not prompt for credentials (they
+/// have to come from a credential helper or agent), and it is killed when the remote
+/// doesn't answer within `REMOTE_GIT_TIMEOUT`.
+fn remote_git(repo: &Path, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "core.askPass="])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("SSH_ASKPASS_REQUIRE", "never")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW = 0x08000000
+        command.creation_flags(0x08000000);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    let deadline = Instant::now() + REMOTE_GIT_TIMEOUT;
+    // Output is small with -q, so the pipes can't fill up while waiting
+    while child
+        .try_wait()
+        .map_err(|e| format!("Failed to wait for git: {}", e))?
+        .is_none()
+    {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "git {} timed out after {} seconds",
+                args.first().copied().unwrap_or_default(),
+                REMOTE_GIT_TIMEOUT.as_secs()
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(crate::decoding::decode_command_output(&output.stdout)
+            .trim()
+            .to_string())
+    } else {
+        Err(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            crate::decoding::decode_command_output(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Merge the remote branch; Ok(false) when the remote doesn't have it yet
+fn pull(repo: &Path, branch: &str) -> Result<bool, String> {
+    if remote_git(repo, &["ls-remote", "--heads", "origin", branch])?.is_empty() {
+        return Ok(false);
+    }
+    let mut args = identity_args(repo);
+    args.extend([
+        "pull",
+        "-q",
+        "--no-rebase",
+        "--no-edit",
+        "--allow-unrelated-histories",
+        "origin",
+        branch,
+    ]);
+    remote_git(repo, &args)?;
+    Ok(true)
+}
+
+fn write_local_changes(
+    repo: &Path,
+    items: &BTreeMap<String, ExportedItem>,
+    state: &SyncState,
+) -> Result<(), String> {
+    let (changed, deleted) = local_changes(repo, items, state);
+    for path in &changed {
+        let target = repo.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        crate::atomic_file::write_atomic(&target, items[path].content())
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    for path in &deleted {
+        let target = repo.join(path);
+        if target.exists() {
+            fs::remove_file(&target).map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn load_state(conn: &Connection) -> SyncState {
+    get_setting_as(conn, SYNC_STATE_KEY).unwrap_or_default()
+}
+
+pub fn load_sync_settings(conn: &Connection) -> ConfigSyncSettings {
+    get_setting_as(conn, SYNC_SETTINGS_KEY).unwrap_or_default()
+}
+
+/// Commit the items changed locally, or conclude a merge whose conflicts have all been
+/// resolved. The report lists the conflicts when some are left.
+fn commit_local(
+    conn: &mut Connection,
+    settings: &ConfigSyncSettings,
+) -> Result<(PathBuf, SyncState, SyncReport), String> {
+    let repo = prepare_repo(settings)?;
+    let mut state = load_state(conn);
+    let mut report = SyncReport::default();
+
+    if is_merging(&repo) {
+        report.conflicts = conflicted_files(&repo)?;
+        if !report.conflicts.is_empty() {
+            return Ok((repo, state, report));
+        }
+        // Not commit_worktree: keeping every local side leaves nothing to show in the status,
+        // but the merge still has to be concluded
+        git(&repo, &["add", "-A"])?;
+        let mut args = identity_args(&repo);
+        args.extend(["commit", "-q", "--no-verify", "--no-edit"]);
+        git(&repo, &args)?;
+        report.committed = git(&repo, &["rev-parse", "HEAD"]).ok();
+    } else {
+        let items = export_items(conn)?;
+        write_local_changes(&repo, &items, &state)?;
+        report.committed = commit_worktree(&repo, COMMIT_MESSAGE)?;
+        // The repository has the local changes now; don't write them again after a conflict
+        state.files = synced_files(&items);
+        set_setting_as(conn, SYNC_STATE_KEY, &state)?;
+    }
+    Ok((repo, state, report))
+}
+
+/// Merge the remote branch, if there is a remote. The report lists the conflicts when the
+/// merge stops on some.
+fn pull_remote(repo: &Path, branch: &str, report: &mut SyncReport) -> Result<(), String> {
+    if !has_origin(repo) {
+        return Ok(());
+    }
+    match pull(repo, branch) {
+        Ok(pulled) => report.pulled = pulled,
+        Err(e) => {
+            report.conflicts = conflicted_files(repo)?;
+            if report.conflicts.is_empty() {
+                return Err(e);
+            }
+            info!(
+                "Config sync stopped on {} conflicting files",
+                report.conflicts.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Import whatever the merge produced
+fn import_merged(
+    conn: &mut Connection,
+    repo: &Path,
+    state: &SyncState,
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let (tree, skipped) = read_tree(repo);
+    report.skipped = skipped;
+    import_tree(conn, &tree, state, report)
+}
+
+/// Push the merged result; returns the commit that was synced
+fn push_remote(repo: &Path, branch: &str, report: &mut SyncReport) -> Option<String> {
+    let head = git(repo, &["rev-parse", "HEAD"]).ok();
+    if has_origin(repo) && head.is_some() {
+        let refspec = format!("HEAD:{}", branch);
+        match remote_git(repo, &["push", "-q", "origin", &refspec]) {
+            Ok(_) => report.pushed = true,
+            Err(e) => report.push_error = Some(e),
+        }
+    }
+    head
+}
+
+fn record_sync(conn: &Connection, head: Option<String>, report: &SyncReport) -> Result<(), String> {
+    let state = SyncState {
+        files: synced_files(&export_items(conn)?),
+        last_synced_at: Some(Utc::now().to_rfc3339()),
+        last_commit: head,
+    };
+    set_setting_as(conn, SYNC_STATE_KEY, &state)?;
+    info!(
+        "Config sync: {} added, {} updated, {} removed",
+        report.added, report.updated, report.removed
+    );
+    Ok(())
+}
+
+/// Commit the local changes, pull and import the remote ones, and push. Conflicts leave the
+/// merge in progress and are returned without importing anything.
+pub fn sync(conn: &mut Connection, settings: &ConfigSyncSettings) -> Result<SyncReport, String> {
+    let (repo, state, mut report) = commit_local(conn, settings)?;
+    if !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+    pull_remote(&repo, &settings.branch, &mut report)?;
+    if !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+    import_merged(conn, &repo, &state, &mut report)?;
+    let head = push_remote(&repo, &settings.branch, &mut report);
+    record_sync(conn, head, &report)?;
+    Ok(report)
+}
+
+/// Keep one side of a conflicting file; returns the conflicts that are left
+pub fn resolve_conflict(
+    repo: &Path,
+    path: &str,
+    keep: ConflictSide,
+) -> Result<Vec<String>, String> {
+    if Path::new(path)
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("{} is not a path inside the repository", path));
+    }
+    if !conflicted_files(repo)?.iter().any(|file| file == path) {
+        return Err(format!("{} has no conflict", path));
+    }
+    let side = match keep {
+        ConflictSide::Local => "--ours",
+        ConflictSide::Remote => "--theirs",
+    };
+    // The side that deleted the file has nothing to check out
+    if git(repo, &["checkout", side, "--", path]).is_ok() {
+        git(repo, &["add", "--", path])?;
+    } else {
+        git(repo, &["rm", "-q", "--", path])?;
+    }
+    conflicted_files(repo)
+}
+
+fn configured_repo(conn: &Connection) -> Result<PathBuf, String> {
+    load_sync_settings(conn)
+        .repo_path
+        .map(PathBuf::from)
+        .ok_or_else(|| "Config sync is not set up".to_string())
+}
+
+#[tauri::command]
+pub async fn get_config_sync_settings(
+    db: State<'_, AgentDb>,
+) -> Result<ConfigSyncSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_sync_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_config_sync_settings(
+    db: State<'_, AgentDb>,
+    settings: ConfigSyncSettings,
+) -> Result<ConfigSyncSettings, String> {
+    if let Some(repo) = settings.repo_path.as_deref() {
+        if !Path::new(repo).is_absolute() {
+            return Err("The sync repository must be an absolute path".to_string());
+        }
+    }
+    if settings.branch.trim().is_empty() || settings.branch.contains(char::is_whitespace) {
+        return Err("Invalid branch name".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if load_sync_settings(&conn).repo_path != settings.repo_path {
+        // What was synced with another repository says nothing about this one
+        set_setting_as(&conn, SYNC_STATE_KEY, &SyncState::default())?;
+    }
+    set_setting_as(&conn, SYNC_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+/// Commit local changes, pull and import remote ones, and push
+#[tauri::command]
+pub async fn sync_config_now(db: State<'_, AgentDb>) -> Result<SyncReport, String> {
+    // The database is locked only for the local steps, never while git talks to the remote
+    let (settings, repo, state, report) = {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let settings = load_sync_settings(&conn);
+        let (repo, state, report) = commit_local(&mut conn, &settings)?;
+        (settings, repo, state, report)
+    };
+    if !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+
+    let branch = settings.branch.clone();
+    let (repo, mut report) = tokio::task::spawn_blocking(move || {
+        let mut report = report;
+        pull_remote(&repo, &branch, &mut report).map(|()| (repo, report))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    if !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+
+    {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        import_merged(&mut conn, &repo, &state, &mut report)?;
+    }
+
+    let (head, report) = tokio::task::spawn_blocking(move || {
+        let head = push_remote(&repo, &settings.branch, &mut report);
+        (head, report)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_sync(&conn, head, &report)?;
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_config_sync_status(db: State<'_, AgentDb>) -> Result<SyncStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let state = load_state(&conn);
+    let mut status = SyncStatus {
+        last_synced_at: state.last_synced_at.clone(),
+        last_commit: state.last_commit.clone(),
+        ..SyncStatus::default()
+    };
+    let Ok(repo) = configured_repo(&conn) else {
+        return Ok(status);
+    };
+    status.configured = true;
+    let (changed, deleted) = local_changes(&repo, &export_items(&conn)?, &state);
+    status.local_changes = changed.len() + deleted.len();
+    if repo.join(".git").exists() && is_merging(&repo) {
+        status.merging = true;
+        status.conflicts = conflicted_files(&repo)?;
+    }
+    Ok(status)
+}
+
+/// Resolve a conflict by keeping the local or the remote version of `path`; the next sync
+/// completes the merge once none are left
+#[tauri::command]
+pub async fn resolve_config_sync_conflict(
+    db: State<'_, AgentDb>,
+    path: String,
+    keep: ConflictSide,
+) -> Result<Vec<String>, String> {
+    let repo = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        configured_repo(&conn)?
+    };
+    resolve_conflict(&repo, &path, keep)
+}
+
+/// Give up on a conflicting merge; the local changes stay committed for the next sync
+#[tauri::command]
+pub async fn abort_config_sync(db: State<'_, AgentDb>) -> Result<(), String> {
+    let repo = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        configured_repo(&conn)?
+    };
+    git(&repo, &["merge", "--abort"]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::agents::open_database;
+    use super::*;
+
+    fn machine(dir: &Path, name: &str, remote: &Path) -> (Connection, ConfigSyncSettings) {
+        let conn = open_database(&dir.join(format!("{}.db", name))).unwrap();
+        let settings = ConfigSyncSettings {
+            repo_path: Some(dir.join(name).to_string_lossy().to_string()),
+            remote_url: Some(remote.to_string_lossy().to_string()),
+            branch: "main".to_string(),
+        };
+        (conn, settings)
+    }
+
+    fn template_body(conn: &Connection, title: &str) -> String {
+        conn.query_row(
+            "SELECT body FROM prompt_templates WHERE title = ?1",
+            params![title],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sync_between_machines_with_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        git(
+            dir.path(),
+            &["init", "-q", "--bare", "-b", "main", "remote.git"],
+        )
+        .unwrap();
+        let (mut a, a_settings) = machine(dir.path(), "a", &remote);
+        let (mut b, b_settings) = machine(dir.path(), "b", &remote);
+
+        a.execute(
+            "INSERT INTO prompt_templates (title, body) VALUES ('Review', 'Review the diff')",
+            [],
+        )
+        .unwrap();
+        let report = sync(&mut a, &a_settings).unwrap();
+        assert!(report.committed.is_some() && report.pushed);
+
+        let report = sync(&mut b, &b_settings).unwrap();
+        assert_eq!(report.added, 1);
+        assert_eq!(template_body(&b, "Review"), "Review the diff");
+
+        a.execute(
+            "UPDATE prompt_templates SET body = 'Review carefully' WHERE title = 'Review'",
+            [],
+        )
+        .unwrap();
+        b.execute(
+            "UPDATE prompt_templates SET body = 'Review quickly' WHERE title = 'Review'",
+            [],
+        )
+        .unwrap();
+        sync(&mut a, &a_settings).unwrap();
+        let report = sync(&mut b, &b_settings).unwrap();
+        assert_eq!(report.conflicts, vec!["prompt-templates/review.json"]);
+        // Nothing is imported while the merge is unresolved
+        assert_eq!(template_body(&b, "Review"), "Review quickly");
+
+        let b_repo = PathBuf::from(b_settings.repo_path.clone().unwrap());
+        let left = resolve_conflict(
+            &b_repo,
+            "prompt-templates/review.json",
+            ConflictSide::Remote,
+        )
+        .unwrap();
+        assert!(left.is_empty());
+        let report = sync(&mut b, &b_settings).unwrap();
+        assert!(report.conflicts.is_empty() && report.pushed);
+        assert_eq!(report.updated, 1);
+        assert_eq!(template_body(&b, "Review"), "Review carefully");
+
+        // Deletions travel too
+        b.execute("DELETE FROM prompt_templates WHERE title = 'Review'", [])
+            .unwrap();
+        sync(&mut b, &b_settings).unwrap();
+        let report = sync(&mut a, &a_settings).unwrap();
+        assert_eq!(report.removed, 1);
+    }
+
+    #[test]
+    fn test_stack_secrets_stay_out_of_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = open_database(&dir.path().join("agents.db")).unwrap();
+        let servers =
+            r#"{"github":{"type":"stdio","command":"gh-mcp","env":{"GITHUB_TOKEN":"ghp_secret"}}}"#;
+        conn.execute(
+            "INSERT INTO mcp_stacks (name, servers) VALUES ('Team', ?1)",
+            params![servers],
+        )
+        .unwrap();
+        let repo = dir.path().join("repo");
+        let settings = ConfigSyncSettings {
+            repo_path: Some(repo.to_string_lossy().to_string()),
+            ..ConfigSyncSettings::default()
+        };
+        sync(&mut conn, &settings).unwrap();
+
+        let content = fs::read_to_string(repo.join("mcp-stacks/team.json")).unwrap();
+        assert!(!content.contains("ghp_secret"));
+        assert!(content.contains("GITHUB_TOKEN"));
+
+        // A change made elsewhere keeps the local token
+        fs::write(
+            repo.join("mcp-stacks/team.json"),
+            content.replace("gh-mcp", "gh-mcp-v2"),
+        )
+        .unwrap();
+        commit_worktree(&repo, "Upgrade the GitHub server").unwrap();
+        let report = sync(&mut conn, &settings).unwrap();
+        assert_eq!(report.updated, 1);
+        let stored: String = conn
+            .query_row(
+                "SELECT servers FROM mcp_stacks WHERE name = 'Team'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(stored.contains("gh-mcp-v2") && stored.contains("ghp_secret"));
+    }
+}
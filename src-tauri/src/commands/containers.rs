@@ -0,0 +1,967 @@
+#![allow(dead_code)]
+
+//! Docker execution backend. A project set up for containers runs its agents in a throwaway
+//! container: `docker run --rm` of the chosen image, or of the project's devcontainer, with the
+//! project mounted as the workspace. The run's environment (diagnostics, thinking budget and
+//! provider profile) is passed by name with `-e NAME`, so secret values reach the container
+//! through the docker CLI's environment rather than its command line. Output streams through
+//! the same pipeline as a local run.
+//!
+//! The container only sees the project, unless the user opts in to mounting `~/.claude` so
+//! the CLI inside can use the local login instead of a provider profile.
+//!
+//! Images are pulled, or built from the devcontainer's Dockerfile, ahead of a run with their
+//! progress streamed as events; a run pulls a missing image itself before starting the
+//! container, which never pulls. Killing a run only stops the docker CLI, so a janitor removes
+//! run containers whose run has ended. Dependency directories (`node_modules`, cargo's
+//! `target`) live in per-project volumes and the npm and cargo download caches in a shared
+//! one, keeping them warm across runs and away from the host's platform-specific builds.
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::mcp_import::strip_jsonc;
+use super::worktrees::slug;
+use crate::process::ProcessRegistryState;
+
+/// Label carrying the run id on every run container
+pub const RUN_LABEL: &str = "opcode.run";
+
+/// Label carrying the project path on run containers and cache volumes
+pub const PROJECT_LABEL: &str = "opcode.project";
+
+/// Label marking volumes created as caches
+const CACHE_LABEL: &str = "opcode.cache";
+
+/// Volume shared by all projects for the npm and cargo download caches
+const SHARED_CACHE_VOLUME: &str = "opcode-cache";
+const SHARED_CACHE_DIR: &str = "/opcode-cache";
+
+/// Event carrying each line of output of an image pull or build
+pub const IMAGE_PROGRESS_EVENT: &str = "container-image-progress";
+
+/// How often stale run containers are looked for
+const JANITOR_INTERVAL: Duration = Duration::from_secs(120);
+
+/// Containers this new may belong to a run that isn't registered yet
+const STARTUP_GRACE_SECS: i64 = 60;
+
+/// Where `~/.claude` is mounted when the user opts in, pointed to by `CLAUDE_CONFIG_DIR`
+const CONTAINER_CLAUDE_DIR: &str = "/opcode-claude";
+
+fn default_claude_path() -> String {
+    "claude".to_string()
+}
+
+fn default_cache_volumes() -> bool {
+    true
+}
+
+/// How a project's agents run in a container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerConfig {
+    pub project_path: String,
+    /// Image to run; `None` uses the project's devcontainer
+    pub image: Option<String>,
+    /// claude inside the image
+    #[serde(default = "default_claude_path")]
+    pub claude_path: String,
+    /// Mount `~/.claude` so the CLI uses the local login; the container can then read it
+    #[serde(default)]
+    pub mount_claude_config: bool,
+    /// Keep dependency directories and download caches in volumes reused between runs
+    #[serde(default = "default_cache_volumes")]
+    pub cache_volumes: bool,
+    pub updated_at: Option<String>,
+}
+
+/// The parts of a devcontainer.json a run needs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Devcontainer {
+    pub config_path: String,
+    pub image: Option<String>,
+    /// Absolute, when the image is built from a Dockerfile
+    pub dockerfile: Option<String>,
+    pub build_context: Option<String>,
+    pub build_args: BTreeMap<String, String>,
+    /// Where the project is mounted; `/workspaces/<name>` by default
+    pub workspace_folder: Option<String>,
+    /// `remoteUser`, else `containerUser`
+    pub user: Option<String>,
+    pub container_env: BTreeMap<String, String>,
+}
+
+/// Image, mount point, user and fixed variables of a run container
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedContainer {
+    pub image: String,
+    pub workspace: String,
+    pub user: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub claude_path: String,
+    pub mount_claude_config: bool,
+    pub volumes: Vec<CacheVolume>,
+}
+
+/// A named volume mounted into run containers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheVolume {
+    pub name: String,
+    pub target: String,
+}
+
+/// A line of output of `docker pull` or `docker build`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageProgress {
+    pub project_path: String,
+    pub image: String,
+    pub line: String,
+}
+
+/// A container started for an agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunContainer {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    /// `running`, `exited`, `created`, ...
+    pub state: String,
+    pub status: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub run_id: Option<i64>,
+    pub project_path: Option<String>,
+}
+
+pub fn init_container_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_containers (
+            project_path TEXT PRIMARY KEY,
+            image TEXT,
+            claude_path TEXT NOT NULL DEFAULT 'claude',
+            mount_claude_config INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    super::db_maintenance::add_column_if_missing(
+        conn,
+        "project_containers",
+        "cache_volumes",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    Ok(())
+}
+
+/// The container setup of `project_path`, if its agents run in one
+pub fn container_for_project(
+    conn: &Connection,
+    project_path: &str,
+) -> SqliteResult<Option<ContainerConfig>> {
+    conn.query_row(
+        "SELECT project_path, image, claude_path, mount_claude_config, cache_volumes, updated_at
+         FROM project_containers WHERE project_path = ?1",
+        params![project_path],
+        |row| {
+            Ok(ContainerConfig {
+                project_path: row.get(0)?,
+                image: row.get(1)?,
+                claude_path: row.get(2)?,
+                mount_claude_config: row.get(3)?,
+                cache_volumes: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+}
+
+fn string_map(value: Option<&Value>) -> BTreeMap<String, String> {
+    value
+        .and_then(Value::as_object)
+        .map(|map| {
+            map.iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `.devcontainer/devcontainer.json`, else `.devcontainer.json`
+pub fn find_devcontainer(project: &Path) -> Option<PathBuf> {
+    [
+        project.join(".devcontainer").join("devcontainer.json"),
+        project.join(".devcontainer.json"),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Parse a devcontainer.json, resolving build paths against its directory
+pub fn parse_devcontainer(config_path: &Path, content: &str) -> Result<Devcontainer, String> {
+    let config: Value = serde_json::from_str(&strip_jsonc(content))
+        .map_err(|e| format!("Failed to parse {}: {}", config_path.display(), e))?;
+    let base = config_path.parent().unwrap_or(Path::new("."));
+    let build = config.get("build");
+    let dockerfile = build
+        .and_then(|build| build.get("dockerfile"))
+        .or_else(|| config.get("dockerFile"))
+        .and_then(Value::as_str);
+    let context = build
+        .and_then(|build| build.get("context"))
+        .or_else(|| config.get("context"))
+        .and_then(Value::as_str)
+        .unwrap_or(".");
+    let resolve = |path: &str| base.join(path).to_string_lossy().to_string();
+    let devcontainer = Devcontainer {
+        config_path: config_path.to_string_lossy().to_string(),
+        image: config
+            .get("image")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        dockerfile: dockerfile.map(resolve),
+        build_context: dockerfile.map(|_| resolve(context)),
+        build_args: string_map(build.and_then(|build| build.get("args"))),
+        workspace_folder: config
+            .get("workspaceFolder")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        user: config
+            .get("remoteUser")
+            .or_else(|| config.get("containerUser"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        container_env: string_map(config.get("containerEnv")),
+    };
+    if devcontainer.image.is_none() && devcontainer.dockerfile.is_none() {
+        return Err(format!(
+            "{} has neither an image nor a Dockerfile; Docker Compose setups are not supported",
+            config_path.display()
+        ));
+    }
+    Ok(devcontainer)
+}
+
+pub fn read_devcontainer(project: &Path) -> Result<Devcontainer, String> {
+    let path = find_devcontainer(project)
+        .ok_or_else(|| format!("{} has no devcontainer.json", project.display()))?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_devcontainer(&path, &content)
+}
+
+/// Readable and unique name for a project in image tags and volume names
+fn project_key(project_path: &str) -> String {
+    let name = Path::new(project_path)
+        .file_name()
+        .map(|name| slug(&name.to_string_lossy()))
+        .unwrap_or_default();
+    let hash: String = Sha256::digest(project_path.as_bytes())
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}-{}", name, hash)
+}
+
+/// Tag of the image built from a project's devcontainer Dockerfile
+pub fn devcontainer_image_tag(project_path: &str) -> String {
+    format!("opcode-devcontainer-{}", project_key(project_path))
+}
+
+/// Volumes and variables keeping the project's dependencies and download caches warm
+pub fn cache_volumes(
+    project_path: &str,
+    workspace: &str,
+) -> (Vec<CacheVolume>, BTreeMap<String, String>) {
+    let project = Path::new(project_path);
+    let key = project_key(project_path);
+    let mut volumes = vec![CacheVolume {
+        name: SHARED_CACHE_VOLUME.to_string(),
+        target: SHARED_CACHE_DIR.to_string(),
+    }];
+    let mut env = BTreeMap::new();
+    if project.join("package.json").is_file() {
+        volumes.push(CacheVolume {
+            name: format!("opcode-{}-node-modules", key),
+            target: format!("{}/node_modules", workspace),
+        });
+        env.insert(
+            "npm_config_cache".to_string(),
+            format!("{}/npm", SHARED_CACHE_DIR),
+        );
+    }
+    if project.join("Cargo.toml").is_file() {
+        volumes.push(CacheVolume {
+            name: format!("opcode-{}-target", key),
+            target: format!("{}/target", workspace),
+        });
+        env.insert(
+            "CARGO_HOME".to_string(),
+            format!("{}/cargo", SHARED_CACHE_DIR),
+        );
+    }
+    (volumes, env)
+}
+
+fn docker_succeeds(args: &[&str]) -> bool {
+    std::process::Command::new("docker")
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn docker_output(args: &[&str]) -> Result<String, String> {
+    let output = std::process::Command::new("docker")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run docker: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn docker_image_exists(image: &str) -> bool {
+    docker_succeeds(&["image", "inspect", "--format", "{{.Id}}", image])
+}
+
+impl ContainerConfig {
+    /// Image and mount point of the run container, from the devcontainer when no image is set
+    pub fn resolve(&self) -> Result<ResolvedContainer, String> {
+        let project = Path::new(&self.project_path);
+        let default_workspace = format!(
+            "/workspaces/{}",
+            project
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "project".to_string())
+        );
+        let resolved = |image: String, devcontainer: Option<Devcontainer>| {
+            let devcontainer = devcontainer.unwrap_or_default();
+            let workspace = devcontainer.workspace_folder.unwrap_or(default_workspace);
+            let mut env = devcontainer.container_env;
+            let volumes = if self.cache_volumes {
+                let (volumes, cache_env) = cache_volumes(&self.project_path, &workspace);
+                env.extend(cache_env);
+                volumes
+            } else {
+                Vec::new()
+            };
+            ResolvedContainer {
+                image,
+                workspace,
+                user: devcontainer.user,
+                env,
+                claude_path: self.claude_path.clone(),
+                mount_claude_config: self.mount_claude_config,
+                volumes,
+            }
+        };
+        let container = match &self.image {
+            Some(image) => resolved(image.clone(), None),
+            None => {
+                let devcontainer = read_devcontainer(project)?;
+                let image = match &devcontainer.image {
+                    Some(image) => image.clone(),
+                    None => {
+                        let tag = devcontainer_image_tag(&self.project_path);
+                        if !docker_image_exists(&tag) {
+                            return Err(format!(
+                                "The devcontainer image {} has not been built yet; build it first",
+                                tag
+                            ));
+                        }
+                        tag
+                    }
+                };
+                resolved(image, Some(devcontainer))
+            }
+        };
+        container.prepare_volumes(&self.project_path)?;
+        Ok(container)
+    }
+
+    /// The image to pull, unless the devcontainer builds one from a Dockerfile
+    fn pull_image(&self) -> Result<String, String> {
+        if let Some(image) = &self.image {
+            return Ok(image.clone());
+        }
+        read_devcontainer(Path::new(&self.project_path))?
+            .image
+            .ok_or_else(|| {
+                "The devcontainer is built from a Dockerfile; build it instead".to_string()
+            })
+    }
+}
+
+/// `--user` for files the run writes to belong to the user, when the devcontainer names none
+fn local_user() -> Option<String> {
+    #[cfg(unix)]
+    {
+        // SAFETY: getuid and getgid have no preconditions and can't fail
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Some(format!("{}:{}", uid, gid))
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+impl ResolvedContainer {
+    /// Create missing cache volumes, owned by the user the run executes as. Docker creates
+    /// volume mount points as root, which an unprivileged run user couldn't write to.
+    fn prepare_volumes(&self, project_path: &str) -> Result<(), String> {
+        let owner = self.user.clone().or_else(local_user);
+        for volume in &self.volumes {
+            if docker_succeeds(&["volume", "inspect", &volume.name]) {
+                continue;
+            }
+            let project_label = format!("{}={}", PROJECT_LABEL, project_path);
+            let mut create = vec!["volume", "create", "--label", CACHE_LABEL];
+            if volume.name != SHARED_CACHE_VOLUME {
+                create.extend(["--label", project_label.as_str()]);
+            }
+            create.push(&volume.name);
+            docker_output(&create)?;
+            if let Some(owner) = &owner {
+                let mount = format!("{}:/volume", volume.name);
+                docker_output(&[
+                    "run",
+                    "--rm",
+                    "--pull=never",
+                    "--user",
+                    "0",
+                    "-v",
+                    mount.as_str(),
+                    "--entrypoint",
+                    "chown",
+                    self.image.as_str(),
+                    owner.as_str(),
+                    "/volume",
+                ])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `docker run` of claude with `args` for run `run_id`. `env_names` are passed by name,
+    /// taking their values from the docker CLI's environment.
+    pub fn wrap_command(
+        &self,
+        run_id: i64,
+        args: Vec<String>,
+        env_names: &[String],
+        project_path: &str,
+    ) -> (String, Vec<String>) {
+        let mut docker_args: Vec<String> = vec![
+            "run".to_string(),
+            "--rm".to_string(),
+            // `ensure_image` pulled it; a pull here would be silent and trip the run's watchdog
+            "--pull=never".to_string(),
+            "--init".to_string(),
+            "--name".to_string(),
+            format!("opcode-run-{}", run_id),
+            "--label".to_string(),
+            format!("{}={}", RUN_LABEL, run_id),
+            "--label".to_string(),
+            format!("{}={}", PROJECT_LABEL, project_path),
+            "-v".to_string(),
+            format!("{}:{}", project_path, self.workspace),
+            "-w".to_string(),
+            self.workspace.clone(),
+        ];
+        if let Some(user) = &self.user {
+            docker_args.extend(["--user".to_string(), user.clone()]);
+        } else if let Some(user) = local_user() {
+            // An arbitrary uid has no home directory in the image
+            docker_args.extend([
+                "--user".to_string(),
+                user,
+                "-e".to_string(),
+                "HOME=/tmp".to_string(),
+            ]);
+        }
+        if self.mount_claude_config {
+            if let Some(claude_dir) = dirs::home_dir().map(|home| home.join(".claude")) {
+                docker_args.extend([
+                    "-v".to_string(),
+                    format!("{}:{}", claude_dir.display(), CONTAINER_CLAUDE_DIR),
+                    "-e".to_string(),
+                    format!("CLAUDE_CONFIG_DIR={}", CONTAINER_CLAUDE_DIR),
+                ]);
+            }
+        }
+        for volume in &self.volumes {
+            docker_args.extend([
+                "-v".to_string(),
+                format!("{}:{}", volume.name, volume.target),
+            ]);
+        }
+        for (name, value) in &self.env {
+            docker_args.extend(["-e".to_string(), format!("{}={}", name, value)]);
+        }
+        for name in env_names {
+            docker_args.extend(["-e".to_string(), name.clone()]);
+        }
+        docker_args.push(self.image.clone());
+        docker_args.push(self.claude_path.clone());
+        docker_args.extend(args);
+        ("docker".to_string(), docker_args)
+    }
+}
+
+/// Get how a project's agents run in a container, if they do
+#[tauri::command]
+pub async fn get_project_container(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<Option<ContainerConfig>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    container_for_project(&conn, &project_path).map_err(|e| e.to_string())
+}
+
+/// Run a project's agents in a container, or on the host again with `None`
+#[tauri::command]
+pub async fn set_project_container(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    config: Option<ContainerConfig>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match config {
+        Some(config) => {
+            let image = config
+                .image
+                .map(|image| image.trim().to_string())
+                .filter(|image| !image.is_empty());
+            if image.as_deref().is_some_and(|image| image.starts_with('-')) {
+                return Err("Invalid image name".to_string());
+            }
+            if image.is_none() {
+                read_devcontainer(Path::new(&project_path))?;
+            }
+            conn.execute(
+                "INSERT OR REPLACE INTO project_containers (project_path, image, claude_path, mount_claude_config, cache_volumes, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)",
+                params![
+                    project_path,
+                    image,
+                    config.claude_path,
+                    config.mount_claude_config,
+                    config.cache_volumes
+                ],
+            )
+        }
+        None => conn.execute(
+            "DELETE FROM project_containers WHERE project_path = ?1",
+            params![project_path],
+        ),
+    }
+    .map_err(|e| format!("Failed to set project container: {}", e))?;
+    Ok(())
+}
+
+/// The project's devcontainer as a run would use it
+#[tauri::command]
+pub async fn inspect_devcontainer(project_path: String) -> Result<Devcontainer, String> {
+    read_devcontainer(Path::new(&project_path))
+}
+
+/// Run docker, emitting each line of its output as progress of `image`
+async fn run_with_progress(
+    app: &AppHandle,
+    project_path: &str,
+    image: &str,
+    args: Vec<String>,
+) -> Result<(), String> {
+    let mut child = tokio::process::Command::new("docker")
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run docker: {}", e))?;
+    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+    let emit = |line: String| {
+        let progress = ImageProgress {
+            project_path: project_path.to_string(),
+            image: image.to_string(),
+            line,
+        };
+        let _ = app.emit(IMAGE_PROGRESS_EVENT, &progress);
+    };
+    let read_stdout = async {
+        let mut reader = tokio::io::BufReader::new(stdout);
+        while let Ok(Some(line)) = crate::claude_binary::read_decoded_line(&mut reader).await {
+            emit(line);
+        }
+    };
+    // docker build reports its progress on stderr; keep the tail for the error
+    let read_stderr = async {
+        let mut reader = tokio::io::BufReader::new(stderr);
+        let mut tail: Vec<String> = Vec::new();
+        while let Ok(Some(line)) = crate::claude_binary::read_decoded_line(&mut reader).await {
+            tail.push(line.clone());
+            if tail.len() > 5 {
+                tail.remove(0);
+            }
+            emit(line);
+        }
+        tail
+    };
+    let (_, tail) = tokio::join!(read_stdout, read_stderr);
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for docker: {}", e))?;
+    if !status.success() {
+        return Err(format!("docker {} failed: {}", args[0], tail.join("\n")));
+    }
+    Ok(())
+}
+
+fn load_config(db: &AgentDb, project_path: &str) -> Result<ContainerConfig, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    container_for_project(&conn, project_path)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} is not set up to run in a container", project_path))
+}
+
+/// Pull the image a project's runs use, reporting progress as `container-image-progress`
+#[tauri::command]
+pub async fn pull_container_image(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<String, String> {
+    let image = load_config(&db, &project_path)?.pull_image()?;
+    run_with_progress(
+        &app,
+        &project_path,
+        &image,
+        vec!["pull".to_string(), image.clone()],
+    )
+    .await?;
+    Ok(image)
+}
+
+/// Pull the image of a project's runs unless it is there already, reporting progress as
+/// `container-image-progress`. Runs call this before `resolve`, so neither the cache volume
+/// setup nor `docker run` pull it silently while the run waits for output.
+pub async fn ensure_image(app: &AppHandle, config: &ContainerConfig) -> Result<(), String> {
+    // Images built from a Dockerfile are checked by `resolve`
+    let Ok(image) = config.pull_image() else {
+        return Ok(());
+    };
+    if docker_image_exists(&image) {
+        return Ok(());
+    }
+    info!("Pulling container image {} before the run", image);
+    run_with_progress(
+        app,
+        &config.project_path,
+        &image,
+        vec!["pull".to_string(), image.clone()],
+    )
+    .await
+}
+
+/// Build the image of the project's devcontainer Dockerfile, reporting progress as
+/// `container-image-progress`
+#[tauri::command]
+pub async fn build_devcontainer_image(
+    app: AppHandle,
+    project_path: String,
+) -> Result<String, String> {
+    let devcontainer = read_devcontainer(Path::new(&project_path))?;
+    let (Some(dockerfile), Some(context)) = (devcontainer.dockerfile, devcontainer.build_context)
+    else {
+        return Err("The devcontainer uses a prebuilt image; pull it instead".to_string());
+    };
+    let image = devcontainer_image_tag(&project_path);
+    let mut args = vec![
+        "build".to_string(),
+        "--progress=plain".to_string(),
+        "--label".to_string(),
+        format!("{}={}", PROJECT_LABEL, project_path),
+        "-t".to_string(),
+        image.clone(),
+        "-f".to_string(),
+        dockerfile,
+    ];
+    for (name, value) in &devcontainer.build_args {
+        args.extend(["--build-arg".to_string(), format!("{}={}", name, value)]);
+    }
+    args.push(context);
+    run_with_progress(&app, &project_path, &image, args).await?;
+    Ok(image)
+}
+
+/// Parse `docker ps` lines in the format `list_containers` asks for
+fn parse_containers(output: &str) -> Vec<RunContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [id, name, image, state, status, created_at, run_id, project_path] = fields[..]
+            else {
+                return None;
+            };
+            // e.g. `2024-05-01 10:00:00 +0000 UTC`
+            let created_at = created_at
+                .rsplit_once(' ')
+                .map_or(created_at, |(time, _)| time);
+            Some(RunContainer {
+                id: id.to_string(),
+                name: name.to_string(),
+                image: image.to_string(),
+                state: state.to_string(),
+                status: status.to_string(),
+                created_at: DateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S %z")
+                    .ok()
+                    .map(|time| time.with_timezone(&Utc)),
+                run_id: run_id.parse().ok(),
+                project_path: Some(project_path.to_string()).filter(|path| !path.is_empty()),
+            })
+        })
+        .collect()
+}
+
+fn list_containers() -> Result<Vec<RunContainer>, String> {
+    let format = format!(
+        "{{{{.ID}}}}\t{{{{.Names}}}}\t{{{{.Image}}}}\t{{{{.State}}}}\t{{{{.Status}}}}\t{{{{.CreatedAt}}}}\t{{{{.Label \"{}\"}}}}\t{{{{.Label \"{}\"}}}}",
+        RUN_LABEL, PROJECT_LABEL
+    );
+    let filter = format!("label={}", RUN_LABEL);
+    let output = docker_output(&["ps", "--all", "--filter", &filter, "--format", &format])?;
+    Ok(parse_containers(&output))
+}
+
+/// Remove run containers whose run is no longer running, returning their names
+fn remove_stale_containers(app: &AppHandle) -> Result<Vec<String>, String> {
+    let running: Vec<i64> = app
+        .state::<ProcessRegistryState>()
+        .0
+        .get_running_agent_processes()?
+        .iter()
+        .map(|process| process.run_id)
+        .collect();
+    let now = Utc::now();
+    let stale: Vec<RunContainer> = list_containers()?
+        .into_iter()
+        .filter(|container| {
+            !container
+                .run_id
+                .is_some_and(|run_id| running.contains(&run_id))
+        })
+        .filter(|container| {
+            container.created_at.map_or(true, |created| {
+                (now - created).num_seconds() > STARTUP_GRACE_SECS
+            })
+        })
+        .collect();
+    let mut removed = Vec::new();
+    for container in stale {
+        match docker_output(&["rm", "--force", &container.id]) {
+            Ok(_) => removed.push(container.name),
+            Err(e) => warn!("Failed to remove container {}: {}", container.name, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// List the containers started for agent runs, including stopped ones
+#[tauri::command]
+pub async fn list_run_containers() -> Result<Vec<RunContainer>, String> {
+    list_containers()
+}
+
+/// Remove the containers of runs that have ended or were killed
+#[tauri::command]
+pub async fn cleanup_run_containers(app: AppHandle) -> Result<Vec<String>, String> {
+    remove_stale_containers(&app)
+}
+
+/// List the cache volumes of a project, or of all projects with the shared cache
+#[tauri::command]
+pub async fn list_container_caches(project_path: Option<String>) -> Result<Vec<String>, String> {
+    let mut args = vec![
+        "volume".to_string(),
+        "ls".to_string(),
+        "--filter".to_string(),
+        format!("label={}", CACHE_LABEL),
+        "--format".to_string(),
+        "{{.Name}}".to_string(),
+    ];
+    if let Some(project_path) = &project_path {
+        args.extend([
+            "--filter".to_string(),
+            format!("label={}={}", PROJECT_LABEL, project_path),
+        ]);
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    Ok(docker_output(&args)?.lines().map(str::to_string).collect())
+}
+
+/// Remove cache volumes, so the next run starts from fresh dependencies
+#[tauri::command]
+pub async fn clear_container_caches(project_path: Option<String>) -> Result<Vec<String>, String> {
+    let volumes = list_container_caches(project_path).await?;
+    for volume in &volumes {
+        docker_output(&["volume", "rm", volume])?;
+    }
+    Ok(volumes)
+}
+
+/// Remove leftover run containers at startup, then whenever runs end
+pub fn spawn_container_janitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if which::which("docker").is_err() {
+            return;
+        }
+        info!("Container janitor started");
+        loop {
+            match remove_stale_containers(&app) {
+                Ok(removed) if !removed.is_empty() => {
+                    info!("Removed {} stale run containers", removed.len())
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Failed to clean up run containers: {}", e),
+            }
+            tokio::time::sleep(JANITOR_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devcontainer_with_comments_resolves_build_paths() {
+        let content = r#"{
+            // Built locally
+            "name": "app",
+            "build": { "dockerfile": "Dockerfile", "context": "..", "args": { "NODE": "20" } },
+            "workspaceFolder": "/work",
+            "remoteUser": "node",
+            "containerEnv": { "CI": "1", },
+        }"#;
+        let devcontainer = parse_devcontainer(
+            Path::new("/src/app/.devcontainer/devcontainer.json"),
+            content,
+        )
+        .unwrap();
+        assert_eq!(
+            devcontainer.dockerfile.as_deref(),
+            Some("/src/app/.devcontainer/Dockerfile")
+        );
+        assert_eq!(
+            devcontainer.build_context.as_deref(),
+            Some("/src/app/.devcontainer/..")
+        );
+        assert_eq!(devcontainer.build_args["NODE"], "20");
+        assert_eq!(devcontainer.workspace_folder.as_deref(), Some("/work"));
+        assert_eq!(devcontainer.user.as_deref(), Some("node"));
+        assert_eq!(devcontainer.container_env["CI"], "1");
+
+        assert!(parse_devcontainer(
+            Path::new("/src/app/.devcontainer.json"),
+            r#"{ "dockerComposeFile": "compose.yml" }"#
+        )
+        .is_err());
+        assert!(devcontainer_image_tag("/src/My App").starts_with("opcode-devcontainer-my-app-"));
+    }
+
+    #[test]
+    fn test_run_container_mounts_the_project_and_passes_env_by_name() {
+        let container = ResolvedContainer {
+            image: "node:20".to_string(),
+            workspace: "/workspaces/app".to_string(),
+            user: Some("node".to_string()),
+            env: BTreeMap::from([("CI".to_string(), "1".to_string())]),
+            claude_path: "claude".to_string(),
+            mount_claude_config: false,
+            volumes: vec![CacheVolume {
+                name: "opcode-app-1234-node-modules".to_string(),
+                target: "/workspaces/app/node_modules".to_string(),
+            }],
+        };
+        let (program, args) = container.wrap_command(
+            7,
+            vec!["-p".to_string(), "task".to_string()],
+            &["ANTHROPIC_API_KEY".to_string()],
+            "/src/app",
+        );
+        assert_eq!(program, "docker");
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--rm",
+                "--pull=never",
+                "--init",
+                "--name",
+                "opcode-run-7",
+                "--label",
+                "opcode.run=7",
+                "--label",
+                "opcode.project=/src/app",
+                "-v",
+                "/src/app:/workspaces/app",
+                "-w",
+                "/workspaces/app",
+                "--user",
+                "node",
+                "-v",
+                "opcode-app-1234-node-modules:/workspaces/app/node_modules",
+                "-e",
+                "CI=1",
+                "-e",
+                "ANTHROPIC_API_KEY",
+                "node:20",
+                "claude",
+                "-p",
+                "task",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_containers_parse_from_docker_ps() {
+        let output = "3f2a\topcode-run-12\tnode:20\trunning\tUp 5 minutes\t2024-05-01 10:00:00 +0000 UTC\t12\t/src/app\n\
+                      9b1c\topcode-run-x\tnode:20\texited\tExited (0)\tgarbage\t\t\n\
+                      truncated line";
+        let containers = parse_containers(output);
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].run_id, Some(12));
+        assert_eq!(containers[0].project_path.as_deref(), Some("/src/app"));
+        assert_eq!(
+            containers[0].created_at.map(|time| time.to_rfc3339()),
+            Some("2024-05-01T10:00:00+00:00".to_string())
+        );
+        assert_eq!(containers[1].run_id, None);
+        assert_eq!(containers[1].project_path, None);
+        assert_eq!(containers[1].created_at, None);
+    }
+}
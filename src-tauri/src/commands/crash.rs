@@ -0,0 +1,286 @@
+#![allow(dead_code)]
+
+//! Local crash reports. A panic hook (and the app's startup error path) writes a JSON
+//! report with the backtrace, the last log lines and the runs that were active, so users
+//! can attach it to an issue. Uploading is opt-in through the
+//! `crash_reports_upload_consent` setting.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::settings::get_setting_as;
+use crate::process::{ProcessRegistry, RunSummary};
+
+/// Reports kept on disk; older ones are removed when a new one is written
+const MAX_CRASH_REPORTS: usize = 50;
+
+/// How long the panic hook waits for the process registry before giving up on active runs
+const ACTIVE_RUNS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// app_settings key recording the user's consent to upload crash reports
+pub const UPLOAD_CONSENT_KEY: &str = "crash_reports_upload_consent";
+
+/// app_settings key with the endpoint crash reports are uploaded to
+pub const UPLOAD_URL_KEY: &str = "crash_report_upload_url";
+
+/// Registry used to list active runs in reports, set once the app is running
+static REGISTRY: OnceLock<Arc<ProcessRegistry>> = OnceLock::new();
+
+/// A crash written to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    /// `panic` or `error`
+    pub kind: String,
+    pub message: String,
+    /// `file:line` of the panic
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: Option<String>,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub created_at: String,
+    pub recent_logs: Vec<String>,
+    pub active_runs: Vec<RunSummary>,
+}
+
+/// List entry for a crash report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReportSummary {
+    pub id: String,
+    pub kind: String,
+    pub message: String,
+    pub created_at: String,
+}
+
+/// Directory crash reports are written to, next to the logs
+pub fn crash_dir() -> PathBuf {
+    let log_dir = crate::logger::log_dir();
+    log_dir
+        .parent()
+        .map(|dir| dir.join("crash-reports"))
+        .unwrap_or_else(|| log_dir.join("crash-reports"))
+}
+
+/// Install the panic hook; the previous hook still runs afterwards
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let location = info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line()));
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+
+        match write_report("panic", message, location, Some(backtrace)) {
+            Ok(id) => log::error!("Panic recorded as crash report {}", id),
+            Err(e) => eprintln!("Failed to write crash report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+/// Let crash reports include the runs active at the time of the crash
+pub fn set_crash_registry(registry: Arc<ProcessRegistry>) {
+    let _ = REGISTRY.set(registry);
+}
+
+/// Record a fatal error that didn't come from a panic
+pub fn record_error(source: &str, error: &str) {
+    match write_report("error", format!("{}: {}", source, error), None, None) {
+        Ok(id) => log::error!("Error recorded as crash report {}", id),
+        Err(e) => eprintln!("Failed to write crash report: {}", e),
+    }
+}
+
+/// Active runs, collected on a helper thread so a crash while the registry is locked
+/// can't hang the hook
+fn active_runs() -> Vec<RunSummary> {
+    let Some(registry) = REGISTRY.get().cloned() else {
+        return Vec::new();
+    };
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(registry.get_run_summaries().unwrap_or_default());
+    });
+    rx.recv_timeout(ACTIVE_RUNS_TIMEOUT).unwrap_or_default()
+}
+
+fn write_report(
+    kind: &str,
+    message: String,
+    location: Option<String>,
+    backtrace: Option<String>,
+) -> Result<String, String> {
+    let now = chrono::Utc::now();
+    let id = format!("crash-{}-{}", now.format("%Y%m%dT%H%M%S%3f"), std::process::id());
+    let report = CrashReport {
+        id: id.clone(),
+        kind: kind.to_string(),
+        message,
+        location,
+        thread: std::thread::current().name().map(|name| name.to_string()),
+        backtrace,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        created_at: now.to_rfc3339(),
+        recent_logs: crate::logger::recent_log_lines(),
+        active_runs: active_runs(),
+    };
+
+    let dir = crash_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(format!("{}.json", id)), json).map_err(|e| e.to_string())?;
+    prune_reports(&dir);
+    Ok(id)
+}
+
+/// Report files, newest first
+fn report_paths(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension().is_some_and(|ext| ext == "json")
+                        && path
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| name.starts_with("crash-"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Ids start with a sortable timestamp
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+fn prune_reports(dir: &std::path::Path) {
+    for path in report_paths(dir).into_iter().skip(MAX_CRASH_REPORTS) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn validate_report_id(id: &str) -> Result<(), String> {
+    let valid = id.starts_with("crash-")
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid crash report id: {}", id))
+    }
+}
+
+fn read_report(id: &str) -> Result<CrashReport, String> {
+    validate_report_id(id)?;
+    let path = crash_dir().join(format!("{}.json", id));
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read crash report {}: {}", id, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid crash report {}: {}", id, e))
+}
+
+/// List crash reports, newest first
+#[tauri::command]
+pub async fn list_crash_reports() -> Result<Vec<CrashReportSummary>, String> {
+    let reports = report_paths(&crash_dir())
+        .into_iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|content| serde_json::from_str::<CrashReport>(&content).ok())
+        .map(|report| CrashReportSummary {
+            id: report.id,
+            kind: report.kind,
+            message: report.message,
+            created_at: report.created_at,
+        })
+        .collect();
+    Ok(reports)
+}
+
+/// Get a crash report by id
+#[tauri::command]
+pub async fn get_crash_report(id: String) -> Result<CrashReport, String> {
+    read_report(&id)
+}
+
+/// Delete a crash report
+#[tauri::command]
+pub async fn delete_crash_report(id: String) -> Result<(), String> {
+    validate_report_id(&id)?;
+    std::fs::remove_file(crash_dir().join(format!("{}.json", id)))
+        .map_err(|e| format!("Failed to delete crash report {}: {}", id, e))
+}
+
+/// Upload a crash report; requires upload consent and a configured endpoint
+#[tauri::command]
+pub async fn upload_crash_report(db: State<'_, AgentDb>, id: String) -> Result<(), String> {
+    let (consent, url) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            get_setting_as::<bool>(&conn, UPLOAD_CONSENT_KEY).unwrap_or(false),
+            get_setting_as::<String>(&conn, UPLOAD_URL_KEY),
+        )
+    };
+    if !consent {
+        return Err("Crash report uploads are disabled; enable them in settings first".to_string());
+    }
+    let url = url
+        .filter(|url| !url.trim().is_empty())
+        .ok_or("No crash report upload URL is configured")?;
+
+    let report = read_report(&id)?;
+    reqwest::Client::new()
+        .post(&url)
+        .header("User-Agent", format!("opcode/{}", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .json(&report)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to upload crash report: {}", e))?;
+    log::info!("Uploaded crash report {}", id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_ids_cannot_escape_the_crash_dir() {
+        assert!(validate_report_id("crash-20240501T100000123-42").is_ok());
+        assert!(validate_report_id("crash-../../etc/passwd").is_err());
+        assert!(validate_report_id("agents").is_err());
+    }
+
+    #[test]
+    fn test_report_paths_newest_first_and_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..(MAX_CRASH_REPORTS + 3) {
+            std::fs::write(dir.path().join(format!("crash-2024{:04}-1.json", i)), "{}").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        prune_reports(dir.path());
+        let paths = report_paths(dir.path());
+        assert_eq!(paths.len(), MAX_CRASH_REPORTS);
+        assert!(paths[0].ends_with(format!("crash-2024{:04}-1.json", MAX_CRASH_REPORTS + 2)));
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,377 @@
+#![allow(dead_code)]
+
+//! Maintenance for the agents database: vacuum, integrity checks, and backups. A backup is
+//! taken automatically before the schema is migrated, so a failed or unwanted migration can
+//! be rolled back with `db_restore_backup`.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::{open_database, AgentDb};
+
+/// Bump when `open_database` changes existing tables, so the next launch takes a backup first
+pub const SCHEMA_VERSION: i32 = 1;
+
+/// Backups kept in the backups directory; older ones are deleted
+const MAX_BACKUPS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbBackup {
+    /// File stem, used to restore the backup
+    pub id: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VacuumReport {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    /// Messages from `PRAGMA integrity_check`, empty when the database is sound
+    pub problems: Vec<String>,
+    /// Rows whose foreign key points at a missing parent, as `table -> parent`
+    pub foreign_key_violations: Vec<String>,
+}
+
+/// Backups live next to the database they were taken from
+pub fn backups_dir(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|dir| dir.join("db_backups"))
+        .unwrap_or_else(|| PathBuf::from("db_backups"))
+}
+
+fn backup_from_path(path: &Path) -> Option<DbBackup> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let created_at = metadata
+        .modified()
+        .ok()
+        .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+        .unwrap_or_default();
+    Some(DbBackup {
+        id: path.file_stem()?.to_string_lossy().to_string(),
+        path: path.to_string_lossy().to_string(),
+        size_bytes: metadata.len(),
+        created_at,
+    })
+}
+
+/// Backups of the database at `db_path`, newest first
+pub fn list_backups(db_path: &Path) -> Vec<DbBackup> {
+    let mut backups: Vec<DbBackup> = std::fs::read_dir(backups_dir(db_path))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("db"))
+        .filter_map(|path| backup_from_path(&path))
+        .collect();
+    // Ids start with a sortable timestamp
+    backups.sort_by(|a, b| b.id.cmp(&a.id));
+    backups
+}
+
+/// Copy the database behind `conn` into the backups directory
+pub fn backup_database(
+    conn: &Connection,
+    db_path: &Path,
+    reason: &str,
+) -> Result<DbBackup, String> {
+    let dir = backups_dir(db_path);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let id = format!(
+        "agents-{}-{}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S%3f"),
+        reason
+    );
+    let path = dir.join(format!("{}.db", id));
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
+    if let Err(e) = super::encryption::encrypt_file(&path) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+
+    for old in list_backups(db_path).into_iter().skip(MAX_BACKUPS) {
+        let _ = std::fs::remove_file(&old.path);
+    }
+    backup_from_path(&path).ok_or_else(|| "Backup was not written".to_string())
+}
+
+fn schema_version(conn: &Connection) -> SqliteResult<i32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Back up an existing database whose schema is older than `SCHEMA_VERSION`
+pub fn backup_before_migration(db_path: &Path) -> Result<Option<DbBackup>, String> {
+    if std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0) == 0 {
+        return Ok(None);
+    }
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    let version = schema_version(&conn).map_err(|e| e.to_string())?;
+    if version >= SCHEMA_VERSION {
+        return Ok(None);
+    }
+    let backup = backup_database(&conn, db_path, &format!("pre-migration-v{}", version))?;
+    log::info!(
+        "Backed up database before migrating schema v{} -> v{}: {}",
+        version,
+        SCHEMA_VERSION,
+        backup.path
+    );
+    Ok(Some(backup))
+}
+
+/// Record that the schema is current
+pub fn mark_schema_current(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+}
+
+/// Explain a failed migration and how to recover from it
+pub fn migration_error(
+    error: &rusqlite::Error,
+    db_path: &Path,
+    backup: Option<&DbBackup>,
+) -> String {
+    let recovery = match backup {
+        Some(backup) => format!(
+            "The database as it was before the upgrade is saved at {}. Restore it with db_restore_backup(\"{}\") after downgrading, or move {} aside to start with an empty database.",
+            backup.path,
+            backup.id,
+            db_path.display()
+        ),
+        None => format!(
+            "Move {} aside to start with an empty database, or report this error.",
+            db_path.display()
+        ),
+    };
+    format!("Database migration failed: {}. {}", error, recovery)
+}
+
+/// Add a column unless the table already has it. Unlike ignoring the error of a bare
+/// `ALTER TABLE`, this reports failures other than the column already existing.
+pub fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> SqliteResult<bool> {
+    let exists = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))?
+        .query_row(params![column], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        return Ok(false);
+    }
+    conn.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+        [],
+    )?;
+    Ok(true)
+}
+
+pub fn check_integrity(conn: &Connection) -> SqliteResult<IntegrityReport> {
+    let problems: Vec<String> = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<SqliteResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|message| message != "ok")
+        .collect();
+
+    let foreign_key_violations: Vec<String> = conn
+        .prepare("PRAGMA foreign_key_check")?
+        .query_map([], |row| {
+            Ok(format!(
+                "{} row {} -> {}",
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                row.get::<_, String>(2)?
+            ))
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(IntegrityReport {
+        ok: problems.is_empty() && foreign_key_violations.is_empty(),
+        problems,
+        foreign_key_violations,
+    })
+}
+
+fn db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("agents.db"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Rebuild the database file to reclaim space left by deleted rows
+#[tauri::command]
+pub async fn db_vacuum(app: AppHandle, db: State<'_, AgentDb>) -> Result<VacuumReport, String> {
+    let path = db_path(&app)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let bytes_before = file_size(&path);
+    conn.execute_batch("VACUUM")
+        .map_err(|e| format!("Failed to vacuum database: {}", e))?;
+    Ok(VacuumReport {
+        bytes_before,
+        bytes_after: file_size(&path),
+    })
+}
+
+/// Run SQLite's integrity and foreign key checks
+#[tauri::command]
+pub async fn db_integrity_check(db: State<'_, AgentDb>) -> Result<IntegrityReport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    check_integrity(&conn).map_err(|e| format!("Failed to check database integrity: {}", e))
+}
+
+#[tauri::command]
+pub async fn db_list_backups(app: AppHandle) -> Result<Vec<DbBackup>, String> {
+    Ok(list_backups(&db_path(&app)?))
+}
+
+#[tauri::command]
+pub async fn db_create_backup(app: AppHandle, db: State<'_, AgentDb>) -> Result<DbBackup, String> {
+    let path = db_path(&app)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    backup_database(&conn, &path, "manual")
+}
+
+/// Replace the database behind `conn` with `data`, the contents of a plain SQLite file. The
+/// current database is backed up first and put back when the replacement can't be opened;
+/// the backup is returned so the replacement can be undone.
+pub fn replace_database(
+    conn: &mut Connection,
+    path: &Path,
+    data: &[u8],
+) -> Result<DbBackup, String> {
+    let before_restore = backup_database(conn, path, "pre-restore")?;
+
+    // Release the file before overwriting it
+    *conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    let replace = |data: &[u8]| -> Result<Connection, String> {
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        std::fs::write(path, data).map_err(|e| format!("Failed to restore backup: {}", e))?;
+        open_database(path).map_err(|e| migration_error(&e, path, Some(&before_restore)))
+    };
+
+    match replace(data) {
+        Ok(restored) => {
+            *conn = restored;
+            Ok(before_restore)
+        }
+        Err(e) => {
+            let previous = super::encryption::read_file(Path::new(&before_restore.path))?;
+            *conn = replace(&previous)?;
+            Err(e)
+        }
+    }
+}
+
+/// Replace the database with a backup. The current database is backed up first, so the
+/// restore itself can be undone.
+#[tauri::command]
+pub async fn db_restore_backup(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    id: String,
+) -> Result<DbBackup, String> {
+    let path = db_path(&app)?;
+    let backup = list_backups(&path)
+        .into_iter()
+        .find(|backup| backup.id == id)
+        .ok_or_else(|| format!("Backup {} not found", id))?;
+    let data = super::encryption::read_file(Path::new(&backup.path))?;
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    match replace_database(&mut conn, &path, &data) {
+        Ok(_) => {
+            log::info!("Restored database from backup {}", backup.id);
+            Ok(backup)
+        }
+        Err(e) => {
+            log::error!("Failed to restore backup {}: {}", backup.id, e);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_before_migration_only_for_old_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("agents.db");
+        assert!(backup_before_migration(&db_path).unwrap().is_none());
+
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch("CREATE TABLE agents (id INTEGER PRIMARY KEY, name TEXT); INSERT INTO agents (name) VALUES ('reviewer');")
+                .unwrap();
+        }
+        let backup = backup_before_migration(&db_path).unwrap().unwrap();
+        assert!(backup.id.ends_with("pre-migration-v0"));
+        let copy = Connection::open(&backup.path).unwrap();
+        let name: String = copy
+            .query_row("SELECT name FROM agents", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "reviewer");
+
+        mark_schema_current(&Connection::open(&db_path).unwrap()).unwrap();
+        assert!(backup_before_migration(&db_path).unwrap().is_none());
+        assert_eq!(list_backups(&db_path).len(), 1);
+    }
+
+    #[test]
+    fn test_add_column_if_missing_and_integrity() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             CREATE TABLE agents (id INTEGER PRIMARY KEY);
+             CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, agent_id INTEGER REFERENCES agents(id));
+             INSERT INTO agent_runs (agent_id) VALUES (42);",
+        )
+        .unwrap();
+
+        assert!(
+            add_column_if_missing(&conn, "agent_runs", "attempt", "INTEGER DEFAULT 1").unwrap()
+        );
+        assert!(
+            !add_column_if_missing(&conn, "agent_runs", "attempt", "INTEGER DEFAULT 1").unwrap()
+        );
+        assert!(add_column_if_missing(&conn, "missing_table", "x", "TEXT").is_err());
+
+        let report = check_integrity(&conn).unwrap();
+        assert!(!report.ok);
+        assert!(report.problems.is_empty());
+        assert_eq!(
+            report.foreign_key_violations,
+            vec!["agent_runs row 1 -> agents"]
+        );
+    }
+}
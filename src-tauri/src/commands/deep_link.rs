@@ -0,0 +1,504 @@
+#![allow(dead_code)]
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+
+use super::agents::AgentDb;
+use crate::process::ProcessRegistryState;
+
+pub const DEEP_LINK_SCHEME: &str = "opcode";
+
+/// Pending links the user hasn't confirmed within this time are dropped
+const PENDING_LINK_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+const MAX_TASK_LENGTH: usize = 10_000;
+const MAX_TEMPLATE_LENGTH: usize = 64 * 1024;
+
+/// An action requested through an `opcode://` link
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+    /// `opcode://agent/run?id=<agent id>&project=<absolute path>[&task=<task>]`
+    RunAgent {
+        agent_id: i64,
+        project_path: String,
+        task: Option<String>,
+    },
+    /// `opcode://mcp/add?name=<server name>&template=<server JSON>[&scope=local|project|user]`
+    AddMcpServer {
+        name: String,
+        template: String,
+        scope: String,
+        /// Exact command line the server runs, or its URL for remote servers
+        launch: String,
+        /// Names of the environment variables the template sets
+        env_keys: Vec<String>,
+    },
+}
+
+impl DeepLinkAction {
+    /// Human readable summary shown in the confirmation prompt
+    pub fn describe(&self) -> String {
+        match self {
+            DeepLinkAction::RunAgent {
+                agent_id,
+                project_path,
+                task,
+            } => match task {
+                Some(task) => format!(
+                    "Run agent #{} in {} with task: {}",
+                    agent_id, project_path, task
+                ),
+                None => format!("Run agent #{} in {}", agent_id, project_path),
+            },
+            DeepLinkAction::AddMcpServer {
+                name,
+                scope,
+                launch,
+                env_keys,
+                ..
+            } => {
+                let mut description =
+                    format!("Add MCP server '{}' ({} scope): {}", name, scope, launch);
+                if !env_keys.is_empty() {
+                    description.push_str(&format!(" with env {}", env_keys.join(", ")));
+                }
+                description
+            }
+        }
+    }
+}
+
+/// A parsed link waiting for the user to confirm it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDeepLink {
+    pub token: String,
+    pub url: String,
+    pub action: DeepLinkAction,
+    pub description: String,
+    #[serde(skip)]
+    received_at: Option<std::time::Instant>,
+}
+
+/// Links received from the OS that are waiting for confirmation
+#[derive(Default)]
+pub struct DeepLinkState(Mutex<HashMap<String, PendingDeepLink>>);
+
+/// Parse and validate an `opcode://` URL
+pub fn parse_deep_link(raw: &str) -> Result<DeepLinkAction, String> {
+    let url = Url::parse(raw.trim()).map_err(|e| format!("Invalid link: {}", e))?;
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!("Unsupported link scheme '{}'", url.scheme()));
+    }
+
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let param = |key: &str| -> Result<String, String> {
+        params
+            .get(key)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("Missing '{}' parameter", key))
+    };
+
+    let route = format!(
+        "{}{}",
+        url.host_str().unwrap_or_default(),
+        url.path().trim_end_matches('/')
+    );
+    match route.as_str() {
+        "agent/run" => {
+            let agent_id = param("id")?
+                .parse::<i64>()
+                .map_err(|_| "Agent id must be a number".to_string())?;
+            let project_path = param("project")?;
+            if !Path::new(&project_path).is_absolute() {
+                return Err("Project path must be absolute".to_string());
+            }
+            let task = params
+                .get("task")
+                .map(|task| task.trim().to_string())
+                .filter(|task| !task.is_empty());
+            if task.as_ref().map(|t| t.len()).unwrap_or(0) > MAX_TASK_LENGTH {
+                return Err("Task is too long".to_string());
+            }
+            Ok(DeepLinkAction::RunAgent {
+                agent_id,
+                project_path,
+                task,
+            })
+        }
+        "mcp/add" => {
+            let name = param("name")?;
+            if !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err("Server name may only contain letters, numbers, - and _".to_string());
+            }
+            let template = param("template")?;
+            if template.len() > MAX_TEMPLATE_LENGTH {
+                return Err("Server template is too large".to_string());
+            }
+            let config: serde_json::Value = serde_json::from_str(&template)
+                .map_err(|e| format!("Server template is not valid JSON: {}", e))?;
+            if !config.is_object() {
+                return Err("Server template must be a JSON object".to_string());
+            }
+            let scope = params
+                .get("scope")
+                .cloned()
+                .unwrap_or_else(|| "local".to_string());
+            if !matches!(scope.as_str(), "local" | "project" | "user") {
+                return Err(format!("Unsupported scope '{}'", scope));
+            }
+            let launch = template_launch(&config)?;
+            let env_keys = config
+                .get("env")
+                .and_then(|env| env.as_object())
+                .map(|env| env.keys().cloned().collect())
+                .unwrap_or_default();
+            Ok(DeepLinkAction::AddMcpServer {
+                name,
+                template,
+                scope,
+                launch,
+                env_keys,
+            })
+        }
+        other => Err(format!("Unknown link action '{}'", other)),
+    }
+}
+
+/// Command line (or URL) an MCP server template launches, spelled out for the confirmation
+fn template_launch(config: &serde_json::Value) -> Result<String, String> {
+    if let Some(command) = config.get("command").and_then(|c| c.as_str()) {
+        let args = config
+            .get("args")
+            .and_then(|a| a.as_array())
+            .map(|args| args.iter().map(|arg| arg.as_str().map(quote_arg)).collect())
+            .unwrap_or(Some(Vec::new()))
+            .ok_or("Server template args must be strings")?;
+        return Ok(std::iter::once(quote_arg(command))
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" "));
+    }
+    config
+        .get("url")
+        .and_then(|u| u.as_str())
+        .map(|url| url.to_string())
+        .ok_or_else(|| "Server template needs a command or url".to_string())
+}
+
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Queue a link received from the OS and ask the UI to confirm it.
+/// Nothing runs until `confirm_deep_link` is called with the returned token.
+pub fn handle_deep_link(app: &AppHandle, raw: &str) {
+    info!("Received deep link: {}", raw);
+    if super::app_lock::is_locked() {
+        warn!("Rejected deep link while the app is locked");
+        super::background::show_main_window(app);
+        let _ = app.emit(
+            "deep-link:rejected",
+            serde_json::json!({
+                "url": raw,
+                "error": "opcode is locked; unlock it and open the link again"
+            }),
+        );
+        return;
+    }
+    let action = match parse_deep_link(raw) {
+        Ok(action) => action,
+        Err(e) => {
+            warn!("Rejected deep link {}: {}", raw, e);
+            let _ = app.emit(
+                "deep-link:rejected",
+                serde_json::json!({ "url": raw, "error": e }),
+            );
+            return;
+        }
+    };
+
+    let pending = PendingDeepLink {
+        token: uuid::Uuid::new_v4().to_string(),
+        url: raw.to_string(),
+        description: action.describe(),
+        action,
+        received_at: Some(std::time::Instant::now()),
+    };
+
+    if let Some(state) = app.try_state::<DeepLinkState>() {
+        if let Ok(mut links) = state.0.lock() {
+            prune_expired(&mut links);
+            links.insert(pending.token.clone(), pending.clone());
+        }
+    }
+
+    super::background::show_main_window(app);
+    let _ = app.emit("deep-link:pending", &pending);
+}
+
+/// Handle `opcode://` URLs passed on the command line (Windows and Linux launch the app
+/// with the link as an argument)
+pub fn handle_startup_args(app: &AppHandle) {
+    handle_link_args(app, std::env::args().skip(1));
+}
+
+/// Handle the command line of a second launch, forwarded by the single-instance plugin
+/// instead of starting another app with its own database and schedulers
+pub fn handle_second_instance(app: &AppHandle, argv: Vec<String>) {
+    if handle_link_args(app, argv.into_iter().skip(1)) == 0 {
+        super::background::show_main_window(app);
+    }
+}
+
+/// Queue every `opcode://` link among `args`, returning how many there were
+fn handle_link_args(app: &AppHandle, args: impl IntoIterator<Item = String>) -> usize {
+    let prefix = format!("{}://", DEEP_LINK_SCHEME);
+    let mut handled = 0;
+    for arg in args {
+        if arg.starts_with(&prefix) {
+            handle_deep_link(app, &arg);
+            handled += 1;
+        }
+    }
+    handled
+}
+
+fn prune_expired(links: &mut HashMap<String, PendingDeepLink>) {
+    links.retain(|_, link| {
+        link.received_at
+            .map(|at| at.elapsed() < PENDING_LINK_TTL)
+            .unwrap_or(false)
+    });
+}
+
+/// Register the `opcode://` scheme with the OS for the current user.
+/// macOS picks the scheme up from the bundle's Info.plist instead.
+pub fn register_url_scheme() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let exe = exe.to_string_lossy();
+
+    #[cfg(target_os = "windows")]
+    {
+        let key = format!("HKCU\\Software\\Classes\\{}", DEEP_LINK_SCHEME);
+        let command_key = format!("{}\\shell\\open\\command", key);
+        let command = format!("\"{}\" \"%1\"", exe);
+        let entries: [(&str, Option<&str>, &str); 3] = [
+            (&key, None, "URL:opcode protocol"),
+            (&key, Some("URL Protocol"), ""),
+            (&command_key, None, &command),
+        ];
+        for (path, name, value) in entries {
+            let mut cmd = std::process::Command::new("reg");
+            cmd.args(["add", path]);
+            match name {
+                Some(name) => cmd.args(["/v", name]),
+                None => cmd.arg("/ve"),
+            };
+            let status = cmd
+                .args(["/d", value, "/f"])
+                .status()
+                .map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err(format!("Failed to write registry key {}", path));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let applications = dirs::data_dir()
+            .ok_or("Could not find data directory")?
+            .join("applications");
+        std::fs::create_dir_all(&applications).map_err(|e| e.to_string())?;
+        let desktop_file = "opcode-url-handler.desktop";
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=opcode\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+            exe, DEEP_LINK_SCHEME
+        );
+        crate::atomic_file::write_atomic(applications.join(desktop_file), entry).map_err(|e| e.to_string())?;
+        let _ = std::process::Command::new("xdg-mime")
+            .args([
+                "default",
+                desktop_file,
+                &format!("x-scheme-handler/{}", DEEP_LINK_SCHEME),
+            ])
+            .status();
+    }
+
+    #[cfg(target_os = "macos")]
+    let _ = exe;
+
+    Ok(())
+}
+
+/// Get links waiting for confirmation, e.g. ones received before the UI loaded
+#[tauri::command]
+pub async fn get_pending_deep_links(
+    state: State<'_, DeepLinkState>,
+) -> Result<Vec<PendingDeepLink>, String> {
+    let mut links = state.0.lock().map_err(|e| e.to_string())?;
+    prune_expired(&mut links);
+    Ok(links.values().cloned().collect())
+}
+
+/// Discard a pending link without running it
+#[tauri::command]
+pub async fn dismiss_deep_link(
+    state: State<'_, DeepLinkState>,
+    token: String,
+) -> Result<(), String> {
+    let mut links = state.0.lock().map_err(|e| e.to_string())?;
+    links.remove(&token);
+    Ok(())
+}
+
+/// Run a pending link after the user confirmed it
+#[tauri::command]
+pub async fn confirm_deep_link(
+    app: AppHandle,
+    state: State<'_, DeepLinkState>,
+    token: String,
+) -> Result<serde_json::Value, String> {
+    let pending = {
+        let mut links = state.0.lock().map_err(|e| e.to_string())?;
+        prune_expired(&mut links);
+        links
+            .remove(&token)
+            .ok_or("This link has expired or was already handled")?
+    };
+    info!("Running confirmed deep link: {}", pending.description);
+
+    let result = match pending.action {
+        DeepLinkAction::RunAgent {
+            agent_id,
+            project_path,
+            task,
+        } => {
+            if !Path::new(&project_path).is_dir() {
+                return Err(format!("Project directory does not exist: {}", project_path));
+            }
+            let db = app.state::<AgentDb>();
+            let agent = super::agents::get_agent(db.clone(), agent_id).await?;
+            let task = task
+                .or(agent.default_task)
+                .filter(|task| !task.trim().is_empty())
+                .ok_or("The link has no task and the agent has no default task")?;
+            let run_id = super::agents::execute_agent(
+                app.clone(),
+                agent_id,
+                project_path,
+                task,
+                None,
+                None,
+                None,
+                None,
+                db,
+                app.state::<ProcessRegistryState>(),
+            )
+            .await?;
+            serde_json::json!({ "run_id": run_id })
+        }
+        DeepLinkAction::AddMcpServer {
+            name,
+            template,
+            scope,
+            ..
+        } => {
+            let result = super::mcp::mcp_add_json(app.clone(), name, template, scope).await?;
+            serde_json::to_value(result).map_err(|e| e.to_string())?
+        }
+    };
+
+    Ok(result)
+}
+
+/// Log registration failures without blocking startup
+pub fn init_deep_links(app: &AppHandle) {
+    if let Err(e) = register_url_scheme() {
+        error!("Failed to register {}:// URL scheme: {}", DEEP_LINK_SCHEME, e);
+    }
+    handle_startup_args(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_agent_run_link() {
+        let action =
+            parse_deep_link("opcode://agent/run?id=7&project=%2Fhome%2Fme%2Fapp&task=Fix%20tests")
+                .unwrap();
+        assert_eq!(
+            action,
+            DeepLinkAction::RunAgent {
+                agent_id: 7,
+                project_path: "/home/me/app".to_string(),
+                task: Some("Fix tests".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_links() {
+        assert!(parse_deep_link("https://agent/run?id=1&project=/tmp").is_err());
+        assert!(parse_deep_link("opcode://agent/run?id=abc&project=/tmp").is_err());
+        assert!(parse_deep_link("opcode://agent/run?id=1&project=relative").is_err());
+        assert!(parse_deep_link("opcode://mcp/add?name=bad%20name&template=%7B%7D").is_err());
+        assert!(parse_deep_link("opcode://mcp/add?name=fs&template=%5B%5D").is_err());
+        assert!(parse_deep_link("opcode://settings/reset").is_err());
+    }
+
+    #[test]
+    fn test_parse_mcp_add_link() {
+        let action = parse_deep_link(
+            "opcode://mcp/add?name=fs&template=%7B%22command%22%3A%22npx%22%7D&scope=user",
+        )
+        .unwrap();
+        assert_eq!(
+            action,
+            DeepLinkAction::AddMcpServer {
+                name: "fs".to_string(),
+                template: r#"{"command":"npx"}"#.to_string(),
+                scope: "user".to_string(),
+                launch: "npx".to_string(),
+                env_keys: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_mcp_add_description_shows_command_and_env() {
+        let template = serde_json::json!({
+            "command": "npx",
+            "args": ["-y", "@scope/server", "--root", "/my docs"],
+            "env": {"API_KEY": "secret", "REGION": "eu"}
+        })
+        .to_string();
+        let link = Url::parse_with_params(
+            "opcode://mcp/add",
+            &[("name", "docs"), ("template", template.as_str())],
+        )
+        .unwrap();
+        let description = parse_deep_link(link.as_str()).unwrap().describe();
+        assert_eq!(
+            description,
+            "Add MCP server 'docs' (local scope): npx -y @scope/server --root '/my docs' \
+             with env API_KEY, REGION"
+        );
+        assert!(!description.contains("secret"));
+    }
+}
@@ -0,0 +1,676 @@
+#![allow(dead_code)]
+
+//! Daily digests: a Markdown report of what agents did on a day — runs by status, failures,
+//! cost and token totals, and the files that changed most. A background task writes the
+//! digest for the previous day once the configured hour has passed, and
+//! `generate_daily_digest` writes one on demand. Digests are stored per day and can be
+//! posted to webhooks subscribed to `digest.daily`.
+
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, Timelike};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::settings::{get_setting_as, set_setting_as};
+use super::usage::{get_all_usage_entries, UsageEntry};
+use super::webhooks::{WebhookEvent, WebhookPayload};
+
+/// app_settings key holding the `DigestSettings`
+const DIGEST_SETTINGS_KEY: &str = "daily_digest";
+
+/// How often the scheduler checks whether yesterday's digest is due
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Runs listed in the Markdown report; the summary keeps them all
+const MAX_LISTED_RUNS: usize = 25;
+
+/// Files listed under notable changes
+const MAX_NOTABLE_FILES: usize = 10;
+
+const TASK_MAX_CHARS: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    /// Write the previous day's digest automatically
+    pub enabled: bool,
+    /// Local hour (0-23) after which the previous day's digest is written
+    pub hour: u32,
+    /// Post scheduled digests to webhooks subscribed to `digest.daily`
+    pub post_to_webhooks: bool,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hour: 9,
+            post_to_webhooks: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestRun {
+    pub run_id: i64,
+    pub agent_name: String,
+    pub project_path: String,
+    pub task: String,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestProjectCost {
+    pub project_path: String,
+    pub cost_usd: f64,
+    pub tokens: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestFile {
+    pub path: String,
+    /// Runs that touched the file
+    pub runs: u32,
+    pub additions: i64,
+    pub deletions: i64,
+}
+
+/// Everything a digest reports, before rendering
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DigestSummary {
+    pub day: String,
+    pub runs: Vec<DigestRun>,
+    pub runs_by_status: BTreeMap<String, usize>,
+    /// Sessions and agent runs with usage on the day
+    pub sessions: usize,
+    pub cost_usd: f64,
+    pub tokens: u64,
+    pub by_project: Vec<DigestProjectCost>,
+    pub files: Vec<DigestFile>,
+}
+
+impl DigestSummary {
+    pub fn failures(&self) -> impl Iterator<Item = &DigestRun> {
+        self.runs
+            .iter()
+            .filter(|run| run.status != "completed" && run.status != "running")
+    }
+}
+
+/// A stored digest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyDigest {
+    pub day: String,
+    pub markdown: String,
+    pub summary: DigestSummary,
+    pub generated_at: String,
+    pub posted: bool,
+}
+
+/// JSON body posted to webhooks subscribed to `digest.daily`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestWebhookPayload {
+    pub event: WebhookEvent,
+    pub day: String,
+    pub markdown: String,
+    pub summary: DigestSummary,
+    pub timestamp: String,
+}
+
+impl WebhookPayload for DigestWebhookPayload {
+    fn event(&self) -> WebhookEvent {
+        WebhookEvent::DailyDigest
+    }
+}
+
+pub fn init_digest_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_digests (
+            day TEXT PRIMARY KEY,
+            markdown TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            generated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            posted BOOLEAN NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn load_digest_settings(conn: &Connection) -> DigestSettings {
+    get_setting_as(conn, DIGEST_SETTINGS_KEY).unwrap_or_default()
+}
+
+fn parse_day(day: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid day {:?}, expected YYYY-MM-DD: {}", day, e))
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.lines().next().unwrap_or_default().trim();
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(max_chars).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+/// Agent runs started on the (local) day, oldest first
+pub fn collect_runs(conn: &Connection, day: NaiveDate) -> SqliteResult<Vec<DigestRun>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_name, project_path, task, status FROM agent_runs
+         WHERE date(created_at, 'localtime') = ?1 ORDER BY id ASC",
+    )?;
+    let runs = stmt
+        .query_map(params![day.to_string()], |row| {
+            Ok(DigestRun {
+                run_id: row.get(0)?,
+                agent_name: row.get(1)?,
+                project_path: row.get(2)?,
+                task: row.get(3)?,
+                status: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(runs)
+}
+
+/// Files changed by the day's runs, most often touched first
+pub fn collect_file_changes(conn: &Connection, day: NaiveDate) -> SqliteResult<Vec<DigestFile>> {
+    let mut stmt = conn.prepare(
+        "SELECT fc.path, COUNT(DISTINCT fc.run_id), SUM(COALESCE(fc.additions, 0)), SUM(COALESCE(fc.deletions, 0))
+         FROM agent_run_file_changes fc JOIN agent_runs r ON r.id = fc.run_id
+         WHERE date(r.created_at, 'localtime') = ?1
+         GROUP BY fc.path
+         ORDER BY COUNT(DISTINCT fc.run_id) DESC, SUM(COALESCE(fc.additions, 0) + COALESCE(fc.deletions, 0)) DESC, fc.path ASC
+         LIMIT ?2",
+    )?;
+    let files = stmt
+        .query_map(params![day.to_string(), MAX_NOTABLE_FILES as i64], |row| {
+            Ok(DigestFile {
+                path: row.get(0)?,
+                runs: row.get(1)?,
+                additions: row.get(2)?,
+                deletions: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(files)
+}
+
+/// Fill in cost, token and session totals from the usage entries on the (local) day
+pub fn summarize_usage(summary: &mut DigestSummary, entries: &[UsageEntry], day: NaiveDate) {
+    let mut sessions = HashSet::new();
+    let mut by_project: HashMap<&str, DigestProjectCost> = HashMap::new();
+    for entry in entries {
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+            continue;
+        };
+        if timestamp.with_timezone(&Local).date_naive() != day {
+            continue;
+        }
+        let tokens = entry.input_tokens
+            + entry.output_tokens
+            + entry.cache_creation_tokens
+            + entry.cache_read_tokens;
+        summary.cost_usd += entry.cost;
+        summary.tokens += tokens;
+        sessions.insert(entry.session_id.as_str());
+
+        let project = by_project
+            .entry(entry.project_path.as_str())
+            .or_insert_with(|| DigestProjectCost {
+                project_path: entry.project_path.clone(),
+                cost_usd: 0.0,
+                tokens: 0,
+            });
+        project.cost_usd += entry.cost;
+        project.tokens += tokens;
+    }
+
+    summary.sessions = sessions.len();
+    summary.by_project = by_project.into_values().collect();
+    summary
+        .by_project
+        .sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+}
+
+/// Everything the digest for `day` reports
+pub fn build_summary(
+    conn: &Connection,
+    entries: &[UsageEntry],
+    day: NaiveDate,
+) -> SqliteResult<DigestSummary> {
+    let runs = collect_runs(conn, day)?;
+    let mut runs_by_status = BTreeMap::new();
+    for run in &runs {
+        *runs_by_status.entry(run.status.clone()).or_insert(0) += 1;
+    }
+    let mut summary = DigestSummary {
+        day: day.to_string(),
+        runs,
+        runs_by_status,
+        files: collect_file_changes(conn, day)?,
+        ..DigestSummary::default()
+    };
+    summarize_usage(&mut summary, entries, day);
+    Ok(summary)
+}
+
+fn project_name(project_path: &str) -> &str {
+    project_path
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(project_path)
+}
+
+/// The digest as Markdown
+pub fn render_markdown(summary: &DigestSummary) -> String {
+    let mut out = format!("# Agent digest for {}\n\n", summary.day);
+
+    out.push_str("## Runs\n\n");
+    if summary.runs.is_empty() {
+        out.push_str("No agent runs.\n\n");
+    } else {
+        let statuses: Vec<String> = summary
+            .runs_by_status
+            .iter()
+            .map(|(status, count)| format!("{} {}", count, status.replace('_', " ")))
+            .collect();
+        out.push_str(&format!(
+            "{} run(s): {}.\n\n",
+            summary.runs.len(),
+            statuses.join(", ")
+        ));
+        for run in summary.runs.iter().take(MAX_LISTED_RUNS) {
+            out.push_str(&format!(
+                "- **{}** in `{}` — {} ({}, run {})\n",
+                run.agent_name,
+                project_name(&run.project_path),
+                truncate(&run.task, TASK_MAX_CHARS),
+                run.status.replace('_', " "),
+                run.run_id
+            ));
+        }
+        if summary.runs.len() > MAX_LISTED_RUNS {
+            out.push_str(&format!(
+                "- …and {} more\n",
+                summary.runs.len() - MAX_LISTED_RUNS
+            ));
+        }
+        out.push('\n');
+    }
+
+    let failures: Vec<&DigestRun> = summary.failures().collect();
+    if !failures.is_empty() {
+        out.push_str("## Needs attention\n\n");
+        for run in failures {
+            out.push_str(&format!(
+                "- Run {} ({}) ended as **{}**: {}\n",
+                run.run_id,
+                run.agent_name,
+                run.status.replace('_', " "),
+                truncate(&run.task, TASK_MAX_CHARS)
+            ));
+        }
+        out.push('\n');
+    }
+
+    out.push_str("## Cost\n\n");
+    out.push_str(&format!(
+        "${:.2} across {} session(s), {} tokens.\n",
+        summary.cost_usd, summary.sessions, summary.tokens
+    ));
+    if !summary.by_project.is_empty() {
+        out.push('\n');
+        for project in &summary.by_project {
+            out.push_str(&format!(
+                "- `{}`: ${:.2} ({} tokens)\n",
+                project_name(&project.project_path),
+                project.cost_usd,
+                project.tokens
+            ));
+        }
+    }
+    out.push('\n');
+
+    if !summary.files.is_empty() {
+        out.push_str("## Notable file changes\n\n");
+        for file in &summary.files {
+            out.push_str(&format!(
+                "- `{}` — {} run(s), +{} / -{}\n",
+                file.path, file.runs, file.additions, file.deletions
+            ));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn digest_from_row(row: &rusqlite::Row) -> rusqlite::Result<DailyDigest> {
+    let summary: String = row.get(2)?;
+    Ok(DailyDigest {
+        day: row.get(0)?,
+        markdown: row.get(1)?,
+        summary: serde_json::from_str(&summary).unwrap_or_default(),
+        generated_at: row.get(3)?,
+        posted: row.get(4)?,
+    })
+}
+
+pub fn load_digest(conn: &Connection, day: &str) -> SqliteResult<Option<DailyDigest>> {
+    conn.query_row(
+        "SELECT day, markdown, summary, generated_at, posted FROM daily_digests WHERE day = ?1",
+        params![day],
+        digest_from_row,
+    )
+    .optional()
+}
+
+fn save_digest(conn: &Connection, digest: &DailyDigest) -> Result<(), String> {
+    let summary = serde_json::to_string(&digest.summary).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO daily_digests (day, markdown, summary, generated_at, posted)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(day) DO UPDATE SET
+            markdown = ?2, summary = ?3, generated_at = ?4, posted = posted OR ?5",
+        params![
+            digest.day,
+            digest.markdown,
+            summary,
+            digest.generated_at,
+            digest.posted
+        ],
+    )
+    .map_err(|e| format!("Failed to save digest: {}", e))?;
+    Ok(())
+}
+
+/// Build, store and optionally post the digest for `day`
+fn generate(app: &AppHandle, day: NaiveDate, post: bool) -> Result<DailyDigest, String> {
+    let claude_path = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+    let entries = get_all_usage_entries(&claude_path);
+
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let summary = build_summary(&conn, &entries, day).map_err(|e| e.to_string())?;
+    let digest = DailyDigest {
+        day: day.to_string(),
+        markdown: render_markdown(&summary),
+        summary,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        posted: post,
+    };
+    save_digest(&conn, &digest)?;
+    drop(conn);
+
+    if post {
+        super::webhooks::dispatch(
+            app,
+            DigestWebhookPayload {
+                event: WebhookEvent::DailyDigest,
+                day: digest.day.clone(),
+                markdown: digest.markdown.clone(),
+                summary: digest.summary.clone(),
+                timestamp: digest.generated_at.clone(),
+            },
+        );
+    }
+    super::activity::record_activity(
+        app,
+        super::activity::NewActivity::new(
+            super::activity::ActivityKind::DailyDigest,
+            format!("Digest for {}", digest.day),
+        )
+        .detail(serde_json::json!({
+            "runs": digest.summary.runs.len(),
+            "cost_usd": digest.summary.cost_usd,
+            "posted": post,
+        })),
+    );
+    let _ = app.emit("digest-generated", &digest);
+    Ok(digest)
+}
+
+/// Start the background task that writes the previous day's digest once a day
+pub fn spawn_digest_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(DIGEST_CHECK_INTERVAL).await;
+
+            let now = Local::now();
+            let yesterday = now.date_naive() - ChronoDuration::days(1);
+            let (settings, due) = {
+                let db = app.state::<AgentDb>();
+                let Ok(conn) = db.0.lock() else {
+                    continue;
+                };
+                let settings = load_digest_settings(&conn);
+                let written = load_digest(&conn, &yesterday.to_string())
+                    .ok()
+                    .flatten()
+                    .is_some();
+                let due = settings.enabled && now.hour() >= settings.hour && !written;
+                (settings, due)
+            };
+            if !due {
+                continue;
+            }
+
+            let handle = app.clone();
+            let post = settings.post_to_webhooks;
+            match tauri::async_runtime::spawn_blocking(move || generate(&handle, yesterday, post))
+                .await
+            {
+                Ok(Ok(digest)) => log::info!("📰 Wrote the digest for {}", digest.day),
+                Ok(Err(e)) => log::warn!("Failed to write the digest for {}: {}", yesterday, e),
+                Err(e) => log::warn!("Digest task failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Write the digest for `day` (today by default), replacing a stored one
+#[tauri::command]
+pub async fn generate_daily_digest(
+    app: AppHandle,
+    day: Option<String>,
+    post: Option<bool>,
+) -> Result<DailyDigest, String> {
+    let day = match day {
+        Some(day) => parse_day(&day)?,
+        None => Local::now().date_naive(),
+    };
+    let post = post.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || generate(&app, day, post))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_daily_digest(
+    db: State<'_, AgentDb>,
+    day: String,
+) -> Result<Option<DailyDigest>, String> {
+    parse_day(&day)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_digest(&conn, &day).map_err(|e| e.to_string())
+}
+
+/// Stored digests, newest first
+#[tauri::command]
+pub async fn list_daily_digests(
+    db: State<'_, AgentDb>,
+    limit: Option<usize>,
+) -> Result<Vec<DailyDigest>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT day, markdown, summary, generated_at, posted FROM daily_digests
+             ORDER BY day DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let digests = stmt
+        .query_map(params![limit.unwrap_or(30) as i64], digest_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(digests)
+}
+
+#[tauri::command]
+pub async fn get_digest_settings(db: State<'_, AgentDb>) -> Result<DigestSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_digest_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_digest_settings(
+    db: State<'_, AgentDb>,
+    settings: DigestSettings,
+) -> Result<DigestSettings, String> {
+    if settings.hour > 23 {
+        return Err("hour must be between 0 and 23".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, DIGEST_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(timestamp: &str, project: &str, session: &str, cost: f64) -> UsageEntry {
+        UsageEntry {
+            timestamp: timestamp.to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost,
+            session_id: session.to_string(),
+            project_path: project.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_summary_collects_runs_costs_and_files() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, agent_name TEXT, project_path TEXT,
+                task TEXT, status TEXT, created_at TEXT);
+             CREATE TABLE agent_run_file_changes (run_id INTEGER, path TEXT, change_type TEXT,
+                additions INTEGER, deletions INTEGER);",
+        )
+        .unwrap();
+        let today = Local::now().date_naive();
+        let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        for (id, status) in [(1, "completed"), (2, "failed"), (3, "completed")] {
+            conn.execute(
+                "INSERT INTO agent_runs VALUES (?1, 'Reviewer', '/work/app', 'Review the diff', ?2, ?3)",
+                params![id, status, now],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO agent_runs VALUES (4, 'Old', '/work/app', 'Old task', 'failed', '2001-01-01 12:00:00')",
+            [],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "INSERT INTO agent_run_file_changes VALUES (1, 'src/lib.rs', 'modified', 10, 2);
+             INSERT INTO agent_run_file_changes VALUES (3, 'src/lib.rs', 'modified', 1, 1);
+             INSERT INTO agent_run_file_changes VALUES (2, 'README.md', 'modified', 40, 0);
+             INSERT INTO agent_run_file_changes VALUES (4, 'old.rs', 'deleted', 0, 9);",
+        )
+        .unwrap();
+
+        let stamp = Local::now().to_rfc3339();
+        let entries = vec![
+            usage(&stamp, "/work/app", "s1", 0.25),
+            usage(&stamp, "/work/app", "s1", 0.25),
+            usage(&stamp, "/work/lib", "s2", 1.0),
+            usage("2001-01-01T12:00:00Z", "/work/app", "s3", 9.0),
+        ];
+
+        let summary = build_summary(&conn, &entries, today).unwrap();
+        assert_eq!(summary.runs.len(), 3);
+        assert_eq!(summary.runs_by_status.get("completed"), Some(&2));
+        assert_eq!(summary.failures().count(), 1);
+        assert_eq!(summary.sessions, 2);
+        assert!((summary.cost_usd - 1.5).abs() < 1e-9);
+        assert_eq!(summary.tokens, 450);
+        assert_eq!(summary.by_project[0].project_path, "/work/lib");
+        assert_eq!(
+            summary
+                .files
+                .iter()
+                .map(|file| (file.path.as_str(), file.runs))
+                .collect::<Vec<_>>(),
+            vec![("src/lib.rs", 2), ("README.md", 1)]
+        );
+    }
+
+    #[test]
+    fn test_markdown_lists_failures_and_notable_files() {
+        let summary = DigestSummary {
+            day: "2026-03-02".to_string(),
+            runs: vec![
+                DigestRun {
+                    run_id: 7,
+                    agent_name: "Fixer".to_string(),
+                    project_path: "/work/app".to_string(),
+                    task: "Fix the flaky test\nwith details".to_string(),
+                    status: "budget_exceeded".to_string(),
+                },
+                DigestRun {
+                    run_id: 8,
+                    agent_name: "Fixer".to_string(),
+                    project_path: "/work/app".to_string(),
+                    task: "Bump deps".to_string(),
+                    status: "completed".to_string(),
+                },
+            ],
+            runs_by_status: BTreeMap::from([
+                ("budget_exceeded".to_string(), 1),
+                ("completed".to_string(), 1),
+            ]),
+            sessions: 2,
+            cost_usd: 1.234,
+            tokens: 5000,
+            by_project: vec![],
+            files: vec![DigestFile {
+                path: "src/lib.rs".to_string(),
+                runs: 2,
+                additions: 11,
+                deletions: 3,
+            }],
+        };
+
+        let markdown = render_markdown(&summary);
+        assert!(markdown.starts_with("# Agent digest for 2026-03-02"));
+        assert!(markdown.contains("2 run(s): 1 budget exceeded, 1 completed."));
+        assert!(
+            markdown.contains("- Run 7 (Fixer) ended as **budget exceeded**: Fix the flaky test\n")
+        );
+        assert!(!markdown.contains("with details"));
+        assert!(markdown.contains("$1.23 across 2 session(s), 5000 tokens."));
+        assert!(markdown.contains("- `src/lib.rs` — 2 run(s), +11 / -3"));
+
+        assert_eq!(
+            truncate(&"x".repeat(100), 10),
+            format!("{}…", "x".repeat(10))
+        );
+        assert_eq!(project_name("/work/app/"), "app");
+    }
+}
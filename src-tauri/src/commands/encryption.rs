@@ -0,0 +1,486 @@
+#![allow(dead_code)]
+
+//! Optional encryption at rest for opcode's local stores: the agents database, its backups
+//! and kept run artifacts. Data is encrypted with AES-256-GCM under a random key held in the
+//! OS keychain and unlocked at app start.
+//!
+//! SQLite itself stays unencrypted while the app runs, as every connection opens the file by
+//! path. Instead the database is sealed into `agents.db.enc` periodically and at exit, and the
+//! plaintext working copy is removed at exit; at the next start it's decrypted again. After a
+//! crash the leftover working copy is newer than the sealed file and is used (and resealed)
+//! instead. Backups and artifacts are encrypted file by file and read back through
+//! [`read_file`].
+
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, State};
+use walkdir::WalkDir;
+
+use super::agents::AgentDb;
+use super::keychain;
+
+/// Leading bytes identifying a file encrypted by opcode
+const FILE_MAGIC: &[u8] = b"OPCODEENC1";
+
+/// Keychain account holding the base64 storage key
+const KEY_ACCOUNT: &str = "storage-encryption-key";
+
+const KEY_LEN: usize = 32;
+
+/// Suffix of the sealed database next to the working copy
+pub const SEALED_SUFFIX: &str = "enc";
+
+/// How often the running app reseals the database when it changed
+const SEAL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether this process decrypted the working copy and so removes it at exit
+static OWNS_WORKING_COPY: AtomicBool = AtomicBool::new(false);
+
+/// The unlocked storage key
+static KEY: Mutex<Option<[u8; KEY_LEN]>> = Mutex::new(None);
+
+/// Whether new backups and artifacts are written encrypted
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Path of the sealed copy of `db_path`
+pub fn sealed_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(SEALED_SUFFIX);
+    db_path.with_file_name(name)
+}
+
+pub fn is_encrypted_data(data: &[u8]) -> bool {
+    data.starts_with(FILE_MAGIC)
+}
+
+pub fn encrypt(plaintext: &[u8], key: &[u8; KEY_LEN]) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| "Failed to generate random bytes".to_string())?;
+    let key = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key).map_err(|_| "Invalid storage key".to_string())?,
+    );
+    let mut data = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(FILE_MAGIC),
+        &mut data,
+    )
+    .map_err(|_| "Failed to encrypt data".to_string())?;
+
+    let mut out = Vec::with_capacity(FILE_MAGIC.len() + NONCE_LEN + data.len());
+    out.extend_from_slice(FILE_MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+pub fn decrypt(data: &[u8], key: &[u8; KEY_LEN]) -> Result<Vec<u8>, String> {
+    let rest = data
+        .strip_prefix(FILE_MAGIC)
+        .ok_or("Not an encrypted opcode file")?;
+    if rest.len() < NONCE_LEN {
+        return Err("Encrypted file is truncated".to_string());
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let key = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key).map_err(|_| "Invalid storage key".to_string())?,
+    );
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "Invalid nonce".to_string())?;
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(FILE_MAGIC), &mut buffer)
+        .map_err(|_| "Wrong storage key or corrupted file".to_string())?;
+    Ok(plaintext.to_vec())
+}
+
+/// The storage key, from the cache or the keychain
+fn load_key() -> Option<[u8; KEY_LEN]> {
+    let mut cached = KEY.lock().ok()?;
+    if cached.is_none() {
+        let encoded = keychain::load_secret(KEY_ACCOUNT)?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok()?;
+        *cached = Some(bytes.try_into().ok()?);
+    }
+    *cached
+}
+
+fn require_key() -> Result<[u8; KEY_LEN], String> {
+    load_key()
+        .ok_or_else(|| "The storage encryption key isn't available in the OS keychain".to_string())
+}
+
+/// The existing key, or a new one saved to the keychain
+fn load_or_create_key() -> Result<[u8; KEY_LEN], String> {
+    if let Some(key) = load_key() {
+        return Ok(key);
+    }
+    let mut key = [0u8; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| "Failed to generate random bytes".to_string())?;
+    keychain::store_secret(
+        KEY_ACCOUNT,
+        &base64::engine::general_purpose::STANDARD.encode(key),
+    )?;
+    *KEY.lock().map_err(|e| e.to_string())? = Some(key);
+    Ok(key)
+}
+
+/// Read a file that may have been encrypted by [`encrypt_file`]
+pub fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if is_encrypted_data(&data) {
+        decrypt(&data, &require_key()?)
+    } else {
+        Ok(data)
+    }
+}
+
+/// Encrypt a file in place when encryption is on
+pub fn encrypt_file(path: &Path) -> Result<(), String> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if is_encrypted_data(&data) {
+        return Ok(());
+    }
+    let encrypted = encrypt(&data, &require_key()?)?;
+    crate::atomic_file::write_atomic(path, encrypted)
+        .map_err(|e| format!("Failed to encrypt {}: {}", path.display(), e))
+}
+
+fn decrypt_file(path: &Path, key: &[u8; KEY_LEN]) -> Result<bool, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !is_encrypted_data(&data) {
+        return Ok(false);
+    }
+    crate::atomic_file::write_atomic(path, decrypt(&data, key)?)
+        .map_err(|e| format!("Failed to decrypt {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Decrypt the sealed database into its working copy before it's opened. Without a sealed
+/// file encryption is off and nothing happens.
+pub fn unseal_database(db_path: &Path) -> Result<(), String> {
+    let sealed = sealed_path(db_path);
+    if !sealed.exists() {
+        ENABLED.store(false, Ordering::Relaxed);
+        return Ok(());
+    }
+    let key = require_key()?;
+    ENABLED.store(true, Ordering::Relaxed);
+
+    if db_path.exists() {
+        // Another process is running on the working copy, or the last one didn't exit cleanly
+        if modified(db_path) >= modified(&sealed) {
+            log::warn!("Using the unsealed database left at {}", db_path.display());
+            return Ok(());
+        }
+    }
+    let plaintext = decrypt(
+        &fs::read(&sealed).map_err(|e| format!("Failed to read sealed database: {}", e))?,
+        &key,
+    )?;
+    write_private(db_path, &plaintext)?;
+    OWNS_WORKING_COPY.store(true, Ordering::Relaxed);
+    log::info!("Unsealed the encrypted database");
+    Ok(())
+}
+
+/// Write a file only the current user can read
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+    let _ = fs::remove_file(path);
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+    #[cfg(not(unix))]
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Write a consistent encrypted snapshot of the database to its sealed file
+pub fn seal_database(conn: &rusqlite::Connection, db_path: &Path) -> Result<(), String> {
+    let key = require_key()?;
+    let snapshot = db_path.with_file_name(format!(
+        ".{}.seal-{}",
+        db_path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&snapshot);
+    let result = conn
+        .execute(
+            "VACUUM INTO ?1",
+            rusqlite::params![snapshot.to_string_lossy()],
+        )
+        .map_err(|e| format!("Failed to snapshot database: {}", e))
+        .and_then(|_| fs::read(&snapshot).map_err(|e| e.to_string()))
+        .and_then(|plaintext| encrypt(&plaintext, &key))
+        .and_then(|sealed| {
+            crate::atomic_file::write_atomic(sealed_path(db_path), sealed)
+                .map_err(|e| format!("Failed to write sealed database: {}", e))
+        });
+    let _ = fs::remove_file(&snapshot);
+    result
+}
+
+/// Seal the database and, if this process unsealed it, remove the plaintext working copy.
+/// Called when the app exits.
+pub fn close_database(conn: &rusqlite::Connection, db_path: &Path) {
+    if !is_enabled() {
+        return;
+    }
+    if let Err(e) = seal_database(conn, db_path) {
+        log::error!(
+            "Failed to seal the database, keeping the working copy: {}",
+            e
+        );
+        return;
+    }
+    if OWNS_WORKING_COPY.load(Ordering::Relaxed) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(format!("{}{}", db_path.display(), suffix));
+        }
+        log::info!("Sealed the database and removed the working copy");
+    }
+}
+
+/// Directories holding backups and artifacts next to the database
+fn output_dirs(db_path: &Path) -> Vec<PathBuf> {
+    vec![
+        super::db_maintenance::backups_dir(db_path),
+        super::artifacts::artifacts_root_for_db(db_path),
+    ]
+}
+
+/// Encrypt or decrypt every file under `dirs`, returning how many changed
+fn convert_files(dirs: &[PathBuf], key: &[u8; KEY_LEN], encrypt: bool) -> Result<usize, String> {
+    let mut converted = 0;
+    for dir in dirs {
+        for entry in WalkDir::new(dir)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let path = entry.path();
+            let changed = if encrypt {
+                let data = fs::read(path).map_err(|e| e.to_string())?;
+                if is_encrypted_data(&data) {
+                    false
+                } else {
+                    crate::atomic_file::write_atomic(path, self::encrypt(&data, key)?)
+                        .map_err(|e| format!("Failed to encrypt {}: {}", path.display(), e))?;
+                    true
+                }
+            } else {
+                decrypt_file(path, key)?
+            };
+            if changed {
+                converted += 1;
+            }
+        }
+    }
+    Ok(converted)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    /// Whether the key is in the keychain
+    pub key_available: bool,
+    /// When the database was last sealed
+    pub sealed_at: Option<String>,
+    /// Files encrypted or decrypted by the last migration
+    pub converted_files: usize,
+}
+
+fn status(db_path: &Path, converted_files: usize) -> EncryptionStatus {
+    EncryptionStatus {
+        enabled: is_enabled(),
+        key_available: load_key().is_some(),
+        sealed_at: modified(&sealed_path(db_path))
+            .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339()),
+        converted_files,
+    }
+}
+
+fn db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("agents.db"))
+        .map_err(|e| e.to_string())
+}
+
+/// Reseal the database in the background whenever it changed
+pub fn spawn_database_sealer(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut sealed_version = None;
+        let mut interval = tokio::time::interval(SEAL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !is_enabled() {
+                continue;
+            }
+            let Ok(path) = db_path(&app) else {
+                continue;
+            };
+            let version = [
+                modified(&path),
+                modified(Path::new(&format!("{}-wal", path.display()))),
+            ];
+            if sealed_version == Some(version) {
+                continue;
+            }
+            let db = app.state::<AgentDb>();
+            let result = match db.0.lock() {
+                Ok(conn) => seal_database(&conn, &path),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(()) => sealed_version = Some(version),
+                Err(e) => log::warn!("Failed to seal the database: {}", e),
+            }
+        }
+    });
+}
+
+/// Seal and remove the working copy as the app exits
+pub fn seal_on_exit(app: &AppHandle) {
+    let Ok(path) = db_path(app) else {
+        return;
+    };
+    if let Some(db) = app.try_state::<AgentDb>() {
+        if let Ok(conn) = db.0.lock() {
+            close_database(&conn, &path);
+        }
+    }
+}
+
+/// Whether local stores are encrypted at rest
+#[tauri::command]
+pub async fn get_storage_encryption_status(app: AppHandle) -> Result<EncryptionStatus, String> {
+    Ok(status(&db_path(&app)?, 0))
+}
+
+/// Turn encryption at rest on or off, converting the database, backups and artifacts in
+/// place. Turning it on creates the key in the OS keychain.
+#[tauri::command]
+pub async fn migrate_storage_encryption(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    enable: bool,
+) -> Result<EncryptionStatus, String> {
+    let path = db_path(&app)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let converted = if enable {
+        let key = load_or_create_key()?;
+        ENABLED.store(true, Ordering::Relaxed);
+        if let Err(e) = seal_database(&conn, &path) {
+            ENABLED.store(false, Ordering::Relaxed);
+            return Err(e);
+        }
+        // The working copy predates encryption, so it goes at exit
+        OWNS_WORKING_COPY.store(true, Ordering::Relaxed);
+        convert_files(&output_dirs(&path), &key, true)?
+    } else {
+        let Some(key) = load_key() else {
+            return Err(
+                "The storage encryption key isn't available, so encrypted files can't be read"
+                    .to_string(),
+            );
+        };
+        let converted = convert_files(&output_dirs(&path), &key, false)?;
+        // The working copy is current; it simply stays once the sealed file is gone
+        fs::remove_file(sealed_path(&path))
+            .or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            })
+            .map_err(|e| format!("Failed to remove the sealed database: {}", e))?;
+        ENABLED.store(false, Ordering::Relaxed);
+        OWNS_WORKING_COPY.store(false, Ordering::Relaxed);
+        converted
+    };
+    log::info!(
+        "Storage encryption {}; converted {} file(s)",
+        if enable { "enabled" } else { "disabled" },
+        converted
+    );
+    Ok(status(&path, converted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: [u8; KEY_LEN] = [7; KEY_LEN];
+
+    #[test]
+    fn test_encrypt_roundtrip_and_wrong_key() {
+        let sealed = encrypt(b"SELECT secret", &KEY_A).unwrap();
+        assert!(is_encrypted_data(&sealed));
+        assert!(!sealed
+            .windows(b"secret".len())
+            .any(|window| window == b"secret"));
+        assert_eq!(decrypt(&sealed, &KEY_A).unwrap(), b"SELECT secret");
+        assert!(decrypt(&sealed, &[8; KEY_LEN]).is_err());
+        assert!(decrypt(&sealed[..FILE_MAGIC.len() + 4], &KEY_A).is_err());
+        assert!(decrypt(b"plain", &KEY_A).is_err());
+    }
+
+    #[test]
+    fn test_convert_files_encrypts_and_decrypts_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts = dir.path().join("artifacts");
+        fs::create_dir_all(artifacts.join("7")).unwrap();
+        fs::write(artifacts.join("7/report.md"), "# Proprietary").unwrap();
+        let dirs = vec![artifacts.clone(), dir.path().join("missing")];
+
+        assert_eq!(convert_files(&dirs, &KEY_A, true).unwrap(), 1);
+        let stored = fs::read(artifacts.join("7/report.md")).unwrap();
+        assert!(is_encrypted_data(&stored));
+        // Already encrypted files are left alone
+        assert_eq!(convert_files(&dirs, &KEY_A, true).unwrap(), 0);
+
+        assert_eq!(convert_files(&dirs, &KEY_A, false).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(artifacts.join("7/report.md")).unwrap(),
+            "# Proprietary"
+        );
+        assert_eq!(
+            sealed_path(Path::new("/data/agents.db")),
+            Path::new("/data/agents.db.enc")
+        );
+    }
+}
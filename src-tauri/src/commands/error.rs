@@ -0,0 +1,274 @@
+#![allow(dead_code)]
+
+//! Structured errors for Tauri commands. The frontend gets `{ kind, message, details, hint }`
+//! instead of a bare string, so it can tell "CLI not installed" from "validation failed"
+//! from "server not found" and show a fix. Errors built from a catalog message also carry
+//! its `code` and `params` so the UI can show them in its own language.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::mcp::ValidationError;
+use super::messages::{Message, MessageCode};
+
+/// Category of an error
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The `claude` binary could not be found
+    CliNotFound,
+    /// The CLI ran but reported a failure
+    CliFailed,
+    Validation,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    Io,
+    Database,
+    Parse,
+    Process,
+    Unsupported,
+    /// Aborted through `cancel_operation`
+    Cancelled,
+    /// The CLI didn't answer in time and was killed
+    Timeout,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn for_io(error: &std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            std::io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+            _ => ErrorKind::Io,
+        }
+    }
+
+    /// What the user can do about it, when there's something generic to say
+    fn default_hint(&self) -> Option<&'static str> {
+        match self {
+            ErrorKind::CliNotFound => Some(
+                "Install Claude Code (npm install -g @anthropic-ai/claude-code) or pick the binary in Settings",
+            ),
+            ErrorKind::PermissionDenied => Some("Check the file permissions and try again"),
+            ErrorKind::Database => Some("Restart opcode; if it persists, run database maintenance"),
+            ErrorKind::Timeout => Some(
+                "Check that `claude mcp list` answers in a terminal, or raise the MCP command timeout in Settings",
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Error returned by commands
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpcodeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub details: Option<Value>,
+    /// Remediation hint for the user
+    pub hint: Option<String>,
+    /// Catalog message `message` was rendered from
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub localized: Option<Message>,
+}
+
+impl OpcodeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: None,
+            hint: kind.default_hint().map(|hint| hint.to_string()),
+            localized: None,
+        }
+    }
+
+    /// Error whose text comes from the message catalog, rendered in the current locale
+    pub fn localized(kind: ErrorKind, message: Message) -> Self {
+        Self {
+            localized: Some(message.clone()),
+            ..Self::new(kind, message.to_string())
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Validation, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn cancelled() -> Self {
+        Self::localized(ErrorKind::Cancelled, Message::new(MessageCode::OperationCancelled))
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Best-effort category for a message from an existing `String` error path
+    pub fn classify(message: &str) -> ErrorKind {
+        let lower = message.to_lowercase();
+        if lower.contains(&super::cancellation::CANCELLED_MESSAGE.to_lowercase()) {
+            ErrorKind::Cancelled
+        } else if lower.contains(&super::mcp::TIMED_OUT_MESSAGE.to_lowercase()) {
+            ErrorKind::Timeout
+        } else if lower.contains("claude code not found")
+            || lower.contains("no valid claude installation")
+            || lower.contains("claude binary")
+        {
+            ErrorKind::CliNotFound
+        } else if lower.starts_with("command failed") {
+            if lower.contains("no mcp server found") || lower.contains("not found") {
+                ErrorKind::NotFound
+            } else if lower.contains("already exists") {
+                ErrorKind::AlreadyExists
+            } else {
+                ErrorKind::CliFailed
+            }
+        } else if lower.contains("not allowed") || lower.contains("invalid") || lower.contains("cannot be empty") {
+            ErrorKind::Validation
+        } else if lower.contains("not found") || lower.contains("does not exist") {
+            ErrorKind::NotFound
+        } else if lower.contains("already exists") {
+            ErrorKind::AlreadyExists
+        } else if lower.contains("permission denied") {
+            ErrorKind::PermissionDenied
+        } else if lower.contains("failed to parse") {
+            ErrorKind::Parse
+        } else if lower.contains("not yet implemented") || lower.contains("not supported") {
+            ErrorKind::Unsupported
+        } else {
+            ErrorKind::Internal
+        }
+    }
+}
+
+impl std::fmt::Display for OpcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for OpcodeError {}
+
+impl From<String> for OpcodeError {
+    fn from(message: String) -> Self {
+        Self::new(Self::classify(&message), message)
+    }
+}
+
+impl From<&str> for OpcodeError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl From<anyhow::Error> for OpcodeError {
+    fn from(error: anyhow::Error) -> Self {
+        error.to_string().into()
+    }
+}
+
+impl From<ValidationError> for OpcodeError {
+    fn from(error: ValidationError) -> Self {
+        Self::localized(ErrorKind::Validation, error.to_message())
+    }
+}
+
+impl From<std::io::Error> for OpcodeError {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(ErrorKind::for_io(&error), error.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for OpcodeError {
+    fn from(error: rusqlite::Error) -> Self {
+        match error {
+            rusqlite::Error::QueryReturnedNoRows => Self::not_found("No matching record"),
+            error => Self::new(ErrorKind::Database, error.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for OpcodeError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::new(ErrorKind::Parse, error.to_string())
+    }
+}
+
+/// Lets `String`-returning code keep using `?` on commands that return `OpcodeError`
+impl From<OpcodeError> for String {
+    fn from(error: OpcodeError) -> Self {
+        error.message
+    }
+}
+
+/// Result type for commands
+pub type OpcodeResult<T> = Result<T, OpcodeError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_string_errors_are_classified() {
+        let error: OpcodeError =
+            "Claude Code not found. Please ensure it's installed in one of these locations: PATH".into();
+        assert_eq!(error.kind, ErrorKind::CliNotFound);
+        assert!(error.hint.is_some());
+
+        let error: OpcodeError = anyhow::anyhow!("Command failed: No MCP server found with name: x").into();
+        assert_eq!(error.kind, ErrorKind::NotFound);
+
+        let error: OpcodeError = ValidationError::EmptyField("Server name".to_string()).into();
+        assert_eq!(error.kind, ErrorKind::Validation);
+
+        let error: OpcodeError = "Command not allowed: rm".to_string().into();
+        assert_eq!(error.kind, ErrorKind::Validation);
+
+        let error: OpcodeError = std::io::Error::from(std::io::ErrorKind::PermissionDenied).into();
+        assert_eq!(error.kind, ErrorKind::PermissionDenied);
+
+        let error: OpcodeError = anyhow::anyhow!("{} after 60s: claude mcp list", super::super::mcp::TIMED_OUT_MESSAGE).into();
+        assert_eq!(error.kind, ErrorKind::Timeout);
+        assert!(error.hint.is_some());
+    }
+
+    #[test]
+    fn test_serialized_shape() {
+        let error = OpcodeError::not_found("MCP server fs not found")
+            .with_details(serde_json::json!({ "server": "fs" }))
+            .with_hint("Add it first");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "not_found",
+                "message": "MCP server fs not found",
+                "details": { "server": "fs" },
+                "hint": "Add it first"
+            })
+        );
+        let message: String = error.into();
+        assert_eq!(message, "MCP server fs not found");
+
+        let error: OpcodeError = ValidationError::EmptyField("Server name".to_string()).into();
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(value["code"], "validation.empty_field");
+        assert_eq!(value["params"]["field"], "Server name");
+        assert_eq!(serde_json::from_value::<OpcodeError>(value).unwrap(), error);
+    }
+}
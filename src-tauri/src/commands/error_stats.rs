@@ -0,0 +1,340 @@
+#![allow(dead_code)]
+
+//! Rate-limit, overload and other API errors found in session and agent run transcripts,
+//! aggregated by type, hour of day, model and project so heavy agents can be scheduled away
+//! from the hours they tend to fail.
+
+use chrono::{DateTime, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::agent_retry::{mentions_overload, mentions_rate_limit};
+use super::usage::{day_in_range, DateRange};
+
+/// Errors listed individually in the report
+const RECENT_ERRORS: usize = 20;
+
+/// Longest error message kept per event
+const MAX_MESSAGE_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorKind {
+    RateLimit,
+    Overloaded,
+    ApiError,
+}
+
+impl ApiErrorKind {
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if mentions_rate_limit(&lower) {
+            ApiErrorKind::RateLimit
+        } else if mentions_overload(&lower) {
+            ApiErrorKind::Overloaded
+        } else {
+            ApiErrorKind::ApiError
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiErrorEvent {
+    pub kind: ApiErrorKind,
+    pub timestamp: String,
+    /// Local hour of day, 0-23
+    pub hour: u32,
+    pub model: String,
+    pub project_path: String,
+    pub session_id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ErrorCounts {
+    pub total: u64,
+    pub rate_limit: u64,
+    pub overloaded: u64,
+    pub api_error: u64,
+}
+
+impl ErrorCounts {
+    fn add(&mut self, kind: ApiErrorKind) {
+        self.total += 1;
+        match kind {
+            ApiErrorKind::RateLimit => self.rate_limit += 1,
+            ApiErrorKind::Overloaded => self.overloaded += 1,
+            ApiErrorKind::ApiError => self.api_error += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourlyErrors {
+    pub hour: u32,
+    #[serde(flatten)]
+    pub counts: ErrorCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedErrors {
+    pub name: String,
+    #[serde(flatten)]
+    pub counts: ErrorCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorStats {
+    pub totals: ErrorCounts,
+    /// All 24 hours, midnight first
+    pub by_hour: Vec<HourlyErrors>,
+    /// Most errors first
+    pub by_model: Vec<NamedErrors>,
+    /// Most errors first
+    pub by_project: Vec<NamedErrors>,
+    /// Newest first
+    pub recent: Vec<ApiErrorEvent>,
+}
+
+/// Text of the error an entry reports, if it reports one
+fn error_message(json: &Value) -> Option<String> {
+    let entry_type = json.get("type").and_then(|t| t.as_str());
+    let text_of_content = |json: &Value| {
+        json.pointer("/message/content")
+            .and_then(|content| content.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+    };
+
+    // Claude Code records failed requests as synthetic assistant messages
+    if json.get("isApiErrorMessage").and_then(|v| v.as_bool()) == Some(true) {
+        return text_of_content(json).or_else(|| Some("API error".to_string()));
+    }
+    match entry_type {
+        Some("result") if json.get("is_error").and_then(|v| v.as_bool()) == Some(true) => json
+            .get("result")
+            .and_then(|r| r.as_str())
+            .map(str::to_string)
+            .or_else(|| Some("Run failed".to_string())),
+        Some("error") => Some(
+            json.pointer("/error/message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| json.to_string()),
+        ),
+        Some("system") if json.get("level").and_then(|l| l.as_str()) == Some("error") => json
+            .get("content")
+            .and_then(|c| c.as_str())
+            .map(str::to_string),
+        _ => None,
+    }
+}
+
+/// API errors in one transcript
+pub fn parse_error_events(content: &str, fallback_project: &str) -> Vec<ApiErrorEvent> {
+    let mut events = Vec::new();
+    let mut model = "unknown".to_string();
+    let mut project_path: Option<String> = None;
+
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let Ok(json) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if project_path.is_none() {
+            project_path = json.get("cwd").and_then(|c| c.as_str()).map(str::to_string);
+        }
+        // Error messages are attributed to the model the session was last using
+        if let Some(name) = json.pointer("/message/model").and_then(|m| m.as_str()) {
+            if !name.starts_with('<') {
+                model = name.to_string();
+            }
+        }
+
+        let Some(message) = error_message(&json) else {
+            continue;
+        };
+        let Some(timestamp) = json.get("timestamp").and_then(|t| t.as_str()) else {
+            continue;
+        };
+        let Ok(time) = DateTime::parse_from_rfc3339(timestamp) else {
+            continue;
+        };
+        events.push(ApiErrorEvent {
+            kind: ApiErrorKind::classify(&message),
+            timestamp: timestamp.to_string(),
+            hour: time.with_timezone(&Local).hour(),
+            model: model.clone(),
+            project_path: project_path
+                .clone()
+                .unwrap_or_else(|| fallback_project.to_string()),
+            session_id: json
+                .get("sessionId")
+                .or_else(|| json.get("session_id"))
+                .and_then(|s| s.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            message: message.chars().take(MAX_MESSAGE_CHARS).collect(),
+        });
+    }
+    events
+}
+
+fn ranked(counts: BTreeMap<String, ErrorCounts>) -> Vec<NamedErrors> {
+    let mut named: Vec<NamedErrors> = counts
+        .into_iter()
+        .map(|(name, counts)| NamedErrors { name, counts })
+        .collect();
+    named.sort_by_key(|named| std::cmp::Reverse(named.counts.total));
+    named
+}
+
+/// Aggregate the events that fall within `range`
+pub fn summarize_errors(
+    mut events: Vec<ApiErrorEvent>,
+    range: &DateRange,
+) -> Result<ErrorStats, String> {
+    let bounds = range.bounds()?;
+    events.retain(|event| day_in_range(&event.timestamp, bounds).is_some());
+
+    let mut totals = ErrorCounts::default();
+    let mut by_hour: Vec<HourlyErrors> = (0..24)
+        .map(|hour| HourlyErrors {
+            hour,
+            counts: ErrorCounts::default(),
+        })
+        .collect();
+    let mut by_model: BTreeMap<String, ErrorCounts> = BTreeMap::new();
+    let mut by_project: BTreeMap<String, ErrorCounts> = BTreeMap::new();
+
+    for event in &events {
+        totals.add(event.kind);
+        by_hour[event.hour as usize % 24].counts.add(event.kind);
+        by_model
+            .entry(event.model.clone())
+            .or_default()
+            .add(event.kind);
+        by_project
+            .entry(event.project_path.clone())
+            .or_default()
+            .add(event.kind);
+    }
+
+    events.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    events.truncate(RECENT_ERRORS);
+
+    Ok(ErrorStats {
+        totals,
+        by_hour,
+        by_model: ranked(by_model),
+        by_project: ranked(by_project),
+        recent: events,
+    })
+}
+
+fn collect_error_events(projects_dir: &Path) -> Vec<ApiErrorEvent> {
+    walkdir::WalkDir::new(projects_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
+        .flat_map(|entry| {
+            let project = entry
+                .path()
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            std::fs::read_to_string(entry.path())
+                .map(|content| parse_error_events(&content, &project))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Rate-limit, overload and API error counts from all transcripts
+#[tauri::command]
+pub async fn get_error_stats(range: Option<DateRange>) -> Result<ErrorStats, String> {
+    let projects_dir = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude")
+        .join("projects");
+    let range = range.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        summarize_errors(collect_error_events(&projects_dir), &range)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_events_finds_api_errors() {
+        let transcript = [
+            r#"{"type":"user","cwd":"/work/app","sessionId":"s1","timestamp":"2025-03-01T10:00:00Z","message":{"role":"user","content":"hi"}}"#,
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2025-03-01T10:00:05Z","message":{"model":"claude-opus-4-20250514","content":[{"type":"text","text":"Hello"}]}}"#,
+            r#"{"type":"assistant","sessionId":"s1","isApiErrorMessage":true,"timestamp":"2025-03-01T10:01:00Z","message":{"model":"<synthetic>","content":[{"type":"text","text":"API Error: 529 {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\"}}"}]}}"#,
+            r#"{"type":"result","is_error":true,"session_id":"s1","timestamp":"2025-03-01T10:02:00Z","result":"Request failed: 429 Too Many Requests"}"#,
+            r#"{"type":"system","level":"error","sessionId":"s1","timestamp":"2025-03-01T10:03:00Z","content":"API Error: 500 Internal server error"}"#,
+            r#"{"type":"system","level":"info","sessionId":"s1","timestamp":"2025-03-01T10:04:00Z","content":"Compacted"}"#,
+        ]
+        .join("\n");
+
+        let events = parse_error_events(&transcript, "-work-app");
+        let kinds: Vec<ApiErrorKind> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ApiErrorKind::Overloaded,
+                ApiErrorKind::RateLimit,
+                ApiErrorKind::ApiError
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|event| event.model == "claude-opus-4-20250514"));
+        assert!(events.iter().all(|event| event.project_path == "/work/app"));
+        assert_eq!(events[1].session_id, "s1");
+    }
+
+    #[test]
+    fn test_summarize_errors_groups_by_hour_model_and_project() {
+        let event = |timestamp: &str, hour, model: &str, kind| ApiErrorEvent {
+            kind,
+            timestamp: timestamp.to_string(),
+            hour,
+            model: model.to_string(),
+            project_path: "/work/app".to_string(),
+            session_id: "s1".to_string(),
+            message: String::new(),
+        };
+        let events = vec![
+            event("2025-03-01T14:00:00Z", 14, "opus", ApiErrorKind::RateLimit),
+            event("2025-03-01T14:30:00Z", 14, "opus", ApiErrorKind::Overloaded),
+            event("2025-03-02T09:00:00Z", 9, "sonnet", ApiErrorKind::RateLimit),
+            event("2025-02-01T09:00:00Z", 9, "sonnet", ApiErrorKind::RateLimit),
+        ];
+        let range = DateRange {
+            start_date: Some("2025-03-01".to_string()),
+            end_date: Some("2025-03-31".to_string()),
+        };
+        let stats = summarize_errors(events, &range).unwrap();
+
+        assert_eq!(stats.totals.total, 3);
+        assert_eq!(stats.totals.rate_limit, 2);
+        assert_eq!(stats.by_hour.len(), 24);
+        assert_eq!(stats.by_hour[14].counts.total, 2);
+        assert_eq!(stats.by_hour[9].counts.total, 1);
+        assert_eq!(stats.by_model[0].name, "opus");
+        assert_eq!(stats.by_project[0].counts.total, 3);
+        assert_eq!(stats.recent[0].timestamp, "2025-03-02T09:00:00Z");
+    }
+}
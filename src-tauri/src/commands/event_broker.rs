@@ -0,0 +1,280 @@
+#![allow(dead_code)]
+
+//! Event broker for the backend's event streams. Events are published on named channels
+//! (live output, usage ticks, MCP status, connectivity) and delivered only to the windows
+//! subscribed to the channel, instead of being emitted to every window.
+//!
+//! Windows that never called `subscribe_events` keep receiving every channel, so existing
+//! listeners work unchanged. Payloads over the channel's size budget are replaced by a
+//! [`TruncatedPayload`] with a preview and an id to fetch the full payload on demand.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+/// Characters of an oversized payload kept as its preview
+const PREVIEW_CHARS: usize = 2_000;
+
+/// Total size of the full payloads kept for fetching; the oldest are dropped first
+const PAYLOAD_STORE_BYTES: usize = 64 * 1024 * 1024;
+
+/// A named stream of events
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    /// stdout and stderr lines of agent runs
+    AgentOutput,
+    /// stdout and stderr lines of interactive Claude sessions
+    ClaudeOutput,
+    /// Live token and cost totals of running processes
+    Usage,
+    /// MCP config and capability changes
+    McpStatus,
+    /// Offline mode and connectivity changes
+    Connectivity,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] = [
+        Channel::AgentOutput,
+        Channel::ClaudeOutput,
+        Channel::Usage,
+        Channel::McpStatus,
+        Channel::Connectivity,
+    ];
+
+    /// Largest serialized payload sent as is
+    pub fn budget(self) -> usize {
+        match self {
+            // Output lines carry whole tool results, such as file reads
+            Channel::AgentOutput | Channel::ClaudeOutput => 256 * 1024,
+            Channel::Usage | Channel::McpStatus | Channel::Connectivity => 64 * 1024,
+        }
+    }
+}
+
+/// Sent in place of a payload over the channel's budget
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TruncatedPayload {
+    pub truncated: bool,
+    /// Pass to `fetch_event_payload` for the full payload
+    pub payload_id: String,
+    /// Size of the full serialized payload in bytes
+    pub size: usize,
+    /// Start of the payload: the text itself for strings, the JSON otherwise
+    pub preview: String,
+}
+
+#[derive(Default)]
+struct PayloadStore {
+    entries: VecDeque<(String, String)>,
+    bytes: usize,
+}
+
+impl PayloadStore {
+    fn insert(&mut self, id: String, json: String) {
+        self.bytes += json.len();
+        self.entries.push_back((id, json));
+        while self.bytes > PAYLOAD_STORE_BYTES && self.entries.len() > 1 {
+            if let Some((_, evicted)) = self.entries.pop_front() {
+                self.bytes -= evicted.len();
+            }
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry_id, _)| entry_id == id)
+            .map(|(_, json)| json.as_str())
+    }
+}
+
+/// Channel subscriptions per window and the full payloads of truncated events
+#[derive(Default)]
+pub struct EventBroker {
+    subscriptions: Mutex<HashMap<String, BTreeSet<Channel>>>,
+    payloads: Mutex<PayloadStore>,
+    next_payload: AtomicU64,
+}
+
+impl EventBroker {
+    /// Whether the window labelled `label` receives `channel`
+    pub fn wants(&self, label: &str, channel: Channel) -> bool {
+        self.subscriptions
+            .lock()
+            .map(|subscriptions| {
+                subscriptions
+                    .get(label)
+                    .is_none_or(|channels| channels.contains(&channel))
+            })
+            .unwrap_or(true)
+    }
+
+    /// Add channels to a window's subscriptions, opting it out of the others
+    pub fn subscribe(&self, label: &str, channels: &[Channel]) -> BTreeSet<Channel> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        let subscribed = subscriptions.entry(label.to_string()).or_default();
+        subscribed.extend(channels.iter().copied());
+        subscribed.clone()
+    }
+
+    /// Remove channels from a window's subscriptions
+    pub fn unsubscribe(&self, label: &str, channels: &[Channel]) -> BTreeSet<Channel> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        let subscribed = subscriptions.entry(label.to_string()).or_default();
+        for channel in channels {
+            subscribed.remove(channel);
+        }
+        subscribed.clone()
+    }
+
+    /// Forget a closed window
+    pub fn remove_window(&self, label: &str) {
+        if let Ok(mut subscriptions) = self.subscriptions.lock() {
+            subscriptions.remove(label);
+        }
+    }
+
+    /// The payload to send on `channel`: `payload` itself within the budget, otherwise a
+    /// [`TruncatedPayload`] whose full payload is kept for fetching
+    pub fn prepare(&self, channel: Channel, payload: Value) -> Value {
+        let json = payload.to_string();
+        if json.len() <= channel.budget() {
+            return payload;
+        }
+        let preview = match &payload {
+            Value::String(text) => text.chars().take(PREVIEW_CHARS).collect(),
+            _ => json.chars().take(PREVIEW_CHARS).collect(),
+        };
+        let payload_id = format!(
+            "payload-{}",
+            self.next_payload.fetch_add(1, Ordering::Relaxed)
+        );
+        let truncated = TruncatedPayload {
+            truncated: true,
+            payload_id: payload_id.clone(),
+            size: json.len(),
+            preview,
+        };
+        if let Ok(mut payloads) = self.payloads.lock() {
+            payloads.insert(payload_id, json);
+        }
+        serde_json::to_value(truncated).unwrap_or(Value::Null)
+    }
+
+    /// Full payload of a truncated event, while it's still kept
+    pub fn payload(&self, payload_id: &str) -> Option<Value> {
+        let payloads = self.payloads.lock().ok()?;
+        serde_json::from_str(payloads.get(payload_id)?).ok()
+    }
+}
+
+/// Publish `event` on `channel` to the windows subscribed to it
+pub fn publish<S: Serialize>(
+    app: &AppHandle,
+    channel: Channel,
+    event: &str,
+    payload: S,
+) -> tauri::Result<()> {
+    let payload = serde_json::to_value(payload)?;
+    let Some(broker) = app.try_state::<EventBroker>() else {
+        return app.emit(event, payload);
+    };
+    let payload = broker.prepare(channel, payload);
+    let windows = app.webview_windows();
+    let targets: Vec<&String> = windows
+        .keys()
+        .filter(|label| broker.wants(label, channel))
+        .collect();
+    if targets.len() == windows.len() {
+        return app.emit(event, payload);
+    }
+    for label in targets {
+        app.emit_to(EventTarget::webview_window(label.as_str()), event, &payload)?;
+    }
+    Ok(())
+}
+
+/// Channels events are published on
+#[tauri::command]
+pub async fn list_event_channels() -> Result<Vec<Channel>, String> {
+    Ok(Channel::ALL.to_vec())
+}
+
+/// Subscribe the calling window to channels; from then on it only receives the channels it
+/// subscribed to
+#[tauri::command]
+pub async fn subscribe_events(
+    window: WebviewWindow,
+    broker: State<'_, EventBroker>,
+    channels: Vec<Channel>,
+) -> Result<BTreeSet<Channel>, String> {
+    Ok(broker.subscribe(window.label(), &channels))
+}
+
+/// Unsubscribe the calling window from channels
+#[tauri::command]
+pub async fn unsubscribe_events(
+    window: WebviewWindow,
+    broker: State<'_, EventBroker>,
+    channels: Vec<Channel>,
+) -> Result<BTreeSet<Channel>, String> {
+    Ok(broker.unsubscribe(window.label(), &channels))
+}
+
+/// Full payload of an event that was sent truncated
+#[tauri::command]
+pub async fn fetch_event_payload(
+    broker: State<'_, EventBroker>,
+    payload_id: String,
+) -> Result<Value, String> {
+    broker
+        .payload(&payload_id)
+        .ok_or_else(|| format!("Event payload {} is no longer available", payload_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_receive_everything_until_they_subscribe() {
+        let broker = EventBroker::default();
+        assert!(broker.wants("main", Channel::Usage));
+
+        broker.subscribe("main", &[Channel::AgentOutput, Channel::Usage]);
+        assert!(broker.wants("main", Channel::AgentOutput));
+        assert!(!broker.wants("main", Channel::McpStatus));
+        assert!(broker.wants("other", Channel::McpStatus));
+
+        let left = broker.unsubscribe("main", &[Channel::Usage]);
+        assert_eq!(left, BTreeSet::from([Channel::AgentOutput]));
+        assert!(!broker.wants("main", Channel::Usage));
+
+        broker.remove_window("main");
+        assert!(broker.wants("main", Channel::Usage));
+    }
+
+    #[test]
+    fn test_oversized_payloads_are_truncated_and_fetchable() {
+        let broker = EventBroker::default();
+        let small = Value::String("ok".to_string());
+        assert_eq!(broker.prepare(Channel::Usage, small.clone()), small);
+
+        let line = "x".repeat(Channel::AgentOutput.budget() + 1);
+        let sent = broker.prepare(Channel::AgentOutput, Value::String(line.clone()));
+        let truncated: TruncatedPayload = serde_json::from_value(sent).unwrap();
+        assert!(truncated.truncated);
+        assert_eq!(truncated.preview.len(), PREVIEW_CHARS);
+        assert_eq!(truncated.size, line.len() + 2);
+        assert_eq!(
+            broker.payload(&truncated.payload_id),
+            Some(Value::String(line))
+        );
+        assert_eq!(broker.payload("payload-missing"), None);
+    }
+}
@@ -0,0 +1,768 @@
+#![allow(dead_code)]
+
+//! A/B prompt experiments: several prompt/model variants of one task, run against the same
+//! project checkpoint so their outputs and metrics can be compared side by side.
+//!
+//! Variants run in batches of `max_parallel`. The project is checkpointed before the first
+//! batch and restored before each following one, and again once the experiment finishes.
+//! Variants in the same batch share the working tree, so parallel batches suit tasks that
+//! read the project rather than edit it.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::{AgentDb, AgentRun, AgentRunMetrics, AGENT_RUN_COLUMNS};
+use super::cancellation::{CancellationRegistry, CancellationToken};
+use super::run_queue::{record_run_priority, RunPriority};
+use crate::process::ProcessRegistryState;
+
+/// Agent runs allowed at once across the app before an experiment holds back its next batch
+pub const MAX_CONCURRENT_AGENT_RUNS: usize = 4;
+
+/// How often a running variant's run record is checked
+const RUN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A prompt/model variant of an experiment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentVariant {
+    pub id: Option<i64>,
+    pub label: String,
+    pub agent_id: i64,
+    /// Overrides the agent's model
+    pub model: Option<String>,
+    /// Overrides the experiment's task
+    pub task: Option<String>,
+    /// Agent run started for this variant
+    #[serde(default)]
+    pub run_id: Option<i64>,
+    /// `pending`, `running`, `completed`, `failed` or `cancelled`
+    #[serde(default = "default_variant_status")]
+    pub status: String,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+fn default_variant_status() -> String {
+    "pending".to_string()
+}
+
+/// An experiment and its variants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub id: Option<i64>,
+    pub name: String,
+    pub task: String,
+    pub project_path: String,
+    /// Variants started together in each batch
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel: i64,
+    /// `draft`, `running`, `completed`, `failed` or `cancelled`
+    #[serde(default = "default_experiment_status")]
+    pub status: String,
+    /// Checkpoint every variant starts from
+    #[serde(default)]
+    pub checkpoint_id: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub created_at: Option<String>,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub variants: Vec<ExperimentVariant>,
+}
+
+fn default_max_parallel() -> i64 {
+    1
+}
+
+fn default_experiment_status() -> String {
+    "draft".to_string()
+}
+
+/// Outcome of one variant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantResult {
+    pub variant: ExperimentVariant,
+    pub run: Option<AgentRun>,
+    pub metrics: Option<AgentRunMetrics>,
+    /// Final answer of the run
+    pub output: Option<String>,
+}
+
+/// Comparison across the variants that completed
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExperimentSummary {
+    pub completed: usize,
+    pub failed: usize,
+    pub total_tokens: i64,
+    pub total_cost_usd: f64,
+    /// Variant ids of the best completed variant by each measure
+    pub fastest: Option<i64>,
+    pub cheapest: Option<i64>,
+    pub fewest_tokens: Option<i64>,
+}
+
+/// Results of an experiment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentResults {
+    pub experiment: Experiment,
+    pub variants: Vec<VariantResult>,
+    pub summary: ExperimentSummary,
+}
+
+pub fn init_experiment_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS experiments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            task TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            max_parallel INTEGER NOT NULL DEFAULT 1,
+            status TEXT NOT NULL DEFAULT 'draft',
+            checkpoint_id TEXT,
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            started_at TEXT,
+            completed_at TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS experiment_variants (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            experiment_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            label TEXT NOT NULL,
+            agent_id INTEGER NOT NULL,
+            model TEXT,
+            task TEXT,
+            run_id INTEGER,
+            status TEXT NOT NULL DEFAULT 'pending',
+            error TEXT,
+            FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const EXPERIMENT_COLUMNS: &str =
+    "id, name, task, project_path, max_parallel, status, checkpoint_id, error, created_at, started_at, completed_at";
+
+const VARIANT_COLUMNS: &str = "id, label, agent_id, model, task, run_id, status, error";
+
+fn experiment_from_row(row: &rusqlite::Row) -> SqliteResult<Experiment> {
+    Ok(Experiment {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        task: row.get(2)?,
+        project_path: row.get(3)?,
+        max_parallel: row.get(4)?,
+        status: row.get(5)?,
+        checkpoint_id: row.get(6)?,
+        error: row.get(7)?,
+        created_at: row.get(8)?,
+        started_at: row.get(9)?,
+        completed_at: row.get(10)?,
+        variants: Vec::new(),
+    })
+}
+
+fn variant_from_row(row: &rusqlite::Row) -> SqliteResult<ExperimentVariant> {
+    Ok(ExperimentVariant {
+        id: Some(row.get(0)?),
+        label: row.get(1)?,
+        agent_id: row.get(2)?,
+        model: row.get(3)?,
+        task: row.get(4)?,
+        run_id: row.get(5)?,
+        status: row.get(6)?,
+        error: row.get(7)?,
+    })
+}
+
+fn load_experiment(conn: &Connection, id: i64) -> Result<Experiment, String> {
+    let mut experiment = conn
+        .query_row(
+            &format!("SELECT {} FROM experiments WHERE id = ?1", EXPERIMENT_COLUMNS),
+            params![id],
+            experiment_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Experiment {} not found", id))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM experiment_variants WHERE experiment_id = ?1 ORDER BY position",
+            VARIANT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    experiment.variants = stmt
+        .query_map(params![id], variant_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(experiment)
+}
+
+/// Operation id the experiment's orchestration registers for cancellation
+fn operation_id(id: i64) -> String {
+    format!("experiment-{}", id)
+}
+
+/// Checkpoint session holding the experiment's baseline
+fn checkpoint_session_id(id: i64) -> String {
+    format!("experiment-{}", id)
+}
+
+/// Final answer of a run from its session JSONL: the `result` entry when the CLI wrote one,
+/// otherwise the text of the last assistant message
+pub fn final_output(jsonl: &str) -> Option<String> {
+    let entries: Vec<JsonValue> = jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    if let Some(result) = entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.get("type").and_then(|t| t.as_str()) == Some("result"))
+        .find_map(|entry| entry.get("result").and_then(|r| r.as_str()))
+    {
+        return Some(result.to_string());
+    }
+
+    entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.get("type").and_then(|t| t.as_str()) == Some("assistant"))
+        .find_map(|entry| {
+            let content = entry.get("message")?.get("content")?;
+            let text = match content {
+                JsonValue::String(text) => text.clone(),
+                JsonValue::Array(blocks) => blocks
+                    .iter()
+                    .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+                    .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => return None,
+            };
+            (!text.trim().is_empty()).then_some(text)
+        })
+}
+
+/// Compare the variants that completed
+pub fn summarize(results: &[VariantResult]) -> ExperimentSummary {
+    let mut summary = ExperimentSummary::default();
+    let mut fastest: Option<(i64, i64)> = None;
+    let mut cheapest: Option<(f64, i64)> = None;
+    let mut fewest_tokens: Option<(i64, i64)> = None;
+
+    for result in results {
+        match result.variant.status.as_str() {
+            "completed" => summary.completed += 1,
+            "failed" => summary.failed += 1,
+            _ => {}
+        }
+        let Some(metrics) = &result.metrics else {
+            continue;
+        };
+        summary.total_tokens += metrics.total_tokens.unwrap_or(0);
+        summary.total_cost_usd += metrics.cost_usd.unwrap_or(0.0);
+
+        let (Some(id), "completed") = (result.variant.id, result.variant.status.as_str()) else {
+            continue;
+        };
+        if let Some(duration) = metrics.duration_ms {
+            if fastest.is_none_or(|(best, _)| duration < best) {
+                fastest = Some((duration, id));
+            }
+        }
+        if let Some(cost) = metrics.cost_usd {
+            if cheapest.is_none_or(|(best, _)| cost < best) {
+                cheapest = Some((cost, id));
+            }
+        }
+        if let Some(tokens) = metrics.total_tokens {
+            if fewest_tokens.is_none_or(|(best, _)| tokens < best) {
+                fewest_tokens = Some((tokens, id));
+            }
+        }
+    }
+
+    summary.fastest = fastest.map(|(_, id)| id);
+    summary.cheapest = cheapest.map(|(_, id)| id);
+    summary.fewest_tokens = fewest_tokens.map(|(_, id)| id);
+    summary
+}
+
+fn set_checkpoint(app: &AppHandle, id: i64, checkpoint_id: &str) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    if let Err(e) = conn.execute(
+        "UPDATE experiments SET checkpoint_id = ?2 WHERE id = ?1",
+        params![id, checkpoint_id],
+    ) {
+        log::warn!("Failed to update experiment {}: {}", id, e);
+    }
+}
+
+fn update_variant(app: &AppHandle, variant_id: i64, status: &str, run_id: Option<i64>, error: Option<String>) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    if let Err(e) = conn.execute(
+        "UPDATE experiment_variants SET status = ?2, run_id = COALESCE(?3, run_id), error = ?4 WHERE id = ?1",
+        params![variant_id, status, run_id, error],
+    ) {
+        log::warn!("Failed to update experiment variant {}: {}", variant_id, e);
+    }
+}
+
+/// Experiment variants yield to interactive runs in the run queue
+fn mark_batch_priority(app: &AppHandle, run_id: i64) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    if let Err(e) = record_run_priority(&conn, run_id, RunPriority::Batch) {
+        log::warn!("Failed to record priority for run {}: {}", run_id, e);
+    }
+}
+
+fn mark_run_cancelled(app: &AppHandle, run_id: i64) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    let _ = conn.execute(
+        "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('pending', 'running')",
+        params![run_id],
+    );
+}
+
+/// Record the experiment's final status; variants that never finished count as cancelled
+fn finish_experiment(app: &AppHandle, id: i64, status: &str, error: Option<String>) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    let _ = conn.execute(
+        "UPDATE experiment_variants SET status = 'cancelled' WHERE experiment_id = ?1 AND status IN ('pending', 'running')",
+        params![id],
+    );
+    if let Err(e) = conn.execute(
+        "UPDATE experiments SET status = ?2, error = ?3, completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id, status, error],
+    ) {
+        log::warn!("Failed to update experiment {}: {}", id, e);
+    }
+}
+
+/// Let the UI know the experiment changed
+fn publish(app: &AppHandle, id: i64) {
+    let db = app.state::<AgentDb>();
+    let experiment = match db.0.lock() {
+        Ok(conn) => load_experiment(&conn, id),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Ok(experiment) = experiment {
+        let _ = app.emit(&format!("experiment-updated:{}", id), &experiment);
+    }
+}
+
+/// Wait until the registry has room for `needed` more agent runs
+async fn wait_for_run_slots(app: &AppHandle, needed: usize, token: &CancellationToken) -> Result<(), String> {
+    loop {
+        let running = app
+            .state::<ProcessRegistryState>()
+            .0
+            .get_running_agent_processes()
+            .map(|processes| processes.len())
+            .unwrap_or(0);
+        if running + needed <= MAX_CONCURRENT_AGENT_RUNS {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = token.cancelled() => return token.check(),
+            _ = tokio::time::sleep(RUN_POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// Wait for an agent run to finish and return its final status. Cancelling kills the run.
+async fn wait_for_run(app: &AppHandle, run_id: i64, token: &CancellationToken) -> String {
+    loop {
+        let status = {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock();
+            conn.ok().and_then(|conn| {
+                conn.query_row("SELECT status FROM agent_runs WHERE id = ?1", params![run_id], |row| {
+                    row.get::<_, String>(0)
+                })
+                .ok()
+            })
+        };
+        match status.as_deref() {
+            Some("pending") | Some("running") => {}
+            Some(status) => return status.to_string(),
+            None => return "failed".to_string(),
+        }
+
+        tokio::select! {
+            _ = token.cancelled() => {
+                // Mark first so the run monitor doesn't record a failure or retry
+                mark_run_cancelled(app, run_id);
+                if let Err(e) = app.state::<ProcessRegistryState>().0.kill_process(run_id).await {
+                    log::warn!("Failed to kill experiment run {}: {}", run_id, e);
+                }
+                return "cancelled".to_string();
+            }
+            _ = tokio::time::sleep(RUN_POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// Run every variant of the experiment from its baseline checkpoint
+async fn orchestrate(app: &AppHandle, experiment: &Experiment, token: &CancellationToken) -> Result<(), String> {
+    let id = experiment.id.ok_or("Experiment has no id")?;
+    let session_id = checkpoint_session_id(id);
+    let (_, checkpoint_id) = super::rollback::snapshot_project(
+        app,
+        &session_id,
+        &experiment.project_path,
+        format!("Before experiment {}", id),
+    )
+    .await?;
+    set_checkpoint(app, id, &checkpoint_id);
+
+    let parallel = (experiment.max_parallel.max(1) as usize).min(MAX_CONCURRENT_AGENT_RUNS);
+    let mut result = Ok(());
+    for (index, batch) in experiment.variants.chunks(parallel).enumerate() {
+        if let Err(e) = token.check() {
+            result = Err(e);
+            break;
+        }
+        if index > 0 {
+            super::rollback::restore_project_snapshot(app, &session_id, &experiment.project_path, &checkpoint_id)
+                .await?;
+        }
+        if let Err(e) = wait_for_run_slots(app, batch.len(), token).await {
+            result = Err(e);
+            break;
+        }
+
+        let mut runs = Vec::new();
+        for variant in batch {
+            let Some(variant_id) = variant.id else {
+                continue;
+            };
+            let task = variant.task.clone().unwrap_or_else(|| experiment.task.clone());
+            match super::agents::execute_agent(
+                app.clone(),
+                variant.agent_id,
+                experiment.project_path.clone(),
+                task,
+                variant.model.clone(),
+                None,
+                None,
+                None,
+                app.state::<AgentDb>(),
+                app.state::<ProcessRegistryState>(),
+            )
+            .await
+            {
+                Ok(run_id) => {
+                    mark_batch_priority(app, run_id);
+                    update_variant(app, variant_id, "running", Some(run_id), None);
+                    runs.push((variant_id, run_id));
+                }
+                Err(e) => {
+                    log::warn!("Experiment {} variant '{}' failed to start: {}", id, variant.label, e);
+                    update_variant(app, variant_id, "failed", None, Some(e));
+                }
+            }
+        }
+        publish(app, id);
+
+        for (variant_id, run_id) in runs {
+            let status = wait_for_run(app, run_id, token).await;
+            update_variant(app, variant_id, &status, None, None);
+            publish(app, id);
+        }
+    }
+
+    // Leave the project as it was before the experiment
+    super::rollback::restore_project_snapshot(app, &session_id, &experiment.project_path, &checkpoint_id)
+        .await?;
+    result
+}
+
+/// Create an experiment with its variants
+#[tauri::command]
+pub async fn create_experiment(db: State<'_, AgentDb>, experiment: Experiment) -> Result<Experiment, String> {
+    if experiment.name.trim().is_empty() {
+        return Err("Experiment name cannot be empty".to_string());
+    }
+    if experiment.task.trim().is_empty()
+        && experiment
+            .variants
+            .iter()
+            .any(|variant| variant.task.as_deref().is_none_or(|task| task.trim().is_empty()))
+    {
+        return Err("Every variant needs a task".to_string());
+    }
+    if experiment.variants.len() < 2 {
+        return Err("An experiment needs at least two variants".to_string());
+    }
+    if !std::path::Path::new(&experiment.project_path).is_dir() {
+        return Err(format!("Project directory not found: {}", experiment.project_path));
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO experiments (name, task, project_path, max_parallel) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            experiment.name.trim(),
+            experiment.task,
+            experiment.project_path,
+            experiment.max_parallel.clamp(1, MAX_CONCURRENT_AGENT_RUNS as i64)
+        ],
+    )
+    .map_err(|e| format!("Failed to create experiment: {}", e))?;
+    let id = tx.last_insert_rowid();
+    for (position, variant) in experiment.variants.iter().enumerate() {
+        let label = if variant.label.trim().is_empty() {
+            format!("Variant {}", position + 1)
+        } else {
+            variant.label.trim().to_string()
+        };
+        tx.execute(
+            "INSERT INTO experiment_variants (experiment_id, position, label, agent_id, model, task) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, position as i64, label, variant.agent_id, variant.model, variant.task],
+        )
+        .map_err(|e| format!("Failed to create experiment variant: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_experiment(&conn, id)
+}
+
+/// List experiments, newest first
+#[tauri::command]
+pub async fn list_experiments(
+    db: State<'_, AgentDb>,
+    project_path: Option<String>,
+) -> Result<Vec<Experiment>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let ids = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM experiments WHERE ?1 IS NULL OR project_path = ?1 ORDER BY created_at DESC, id DESC")
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(params![project_path], |row| row.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<SqliteResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+    ids.into_iter().map(|id| load_experiment(&conn, id)).collect()
+}
+
+#[tauri::command]
+pub async fn get_experiment(db: State<'_, AgentDb>, id: i64) -> Result<Experiment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_experiment(&conn, id)
+}
+
+/// Delete an experiment. Its agent runs are kept.
+#[tauri::command]
+pub async fn delete_experiment(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if load_experiment(&conn, id)?.status == "running" {
+        return Err("Cancel the experiment before deleting it".to_string());
+    }
+    conn.execute("DELETE FROM experiment_variants WHERE experiment_id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete experiment: {}", e))?;
+    conn.execute("DELETE FROM experiments WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete experiment: {}", e))?;
+    Ok(())
+}
+
+/// Start running an experiment's variants in the background. Progress is emitted as
+/// `experiment-updated:<id>`; running it again starts every variant afresh.
+#[tauri::command]
+pub async fn run_experiment(app: AppHandle, db: State<'_, AgentDb>, id: i64) -> Result<Experiment, String> {
+    let experiment = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if load_experiment(&conn, id)?.status == "running" {
+            return Err(format!("Experiment {} is already running", id));
+        }
+        conn.execute(
+            "UPDATE experiments SET status = 'running', error = NULL, started_at = CURRENT_TIMESTAMP, completed_at = NULL WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE experiment_variants SET status = 'pending', run_id = NULL, error = NULL WHERE experiment_id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        load_experiment(&conn, id)?
+    };
+
+    let guard = CancellationRegistry::global().register(Some(operation_id(id)));
+    let run = experiment.clone();
+    tauri::async_runtime::spawn(async move {
+        let token = guard.token();
+        let result = orchestrate(&app, &run, token).await;
+        let (status, error) = match result {
+            Ok(()) => ("completed", None),
+            Err(_) if token.is_cancelled() => ("cancelled", None),
+            Err(e) => ("failed", Some(e)),
+        };
+        match &error {
+            Some(e) => log::error!("Experiment {} failed: {}", id, e),
+            None => log::info!("Experiment {} {}", id, status),
+        }
+        finish_experiment(&app, id, status, error);
+        publish(&app, id);
+    });
+
+    Ok(experiment)
+}
+
+/// Stop a running experiment; variants still running are killed
+#[tauri::command]
+pub async fn cancel_experiment(db: State<'_, AgentDb>, id: i64) -> Result<bool, String> {
+    if CancellationRegistry::global().cancel(&operation_id(id)) {
+        return Ok(true);
+    }
+    // Nothing is orchestrating it any more, e.g. the app was restarted mid-run
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE experiments SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(updated > 0)
+}
+
+/// Outputs and metrics of every variant, with a comparison of the completed ones
+#[tauri::command]
+pub async fn get_experiment_results(db: State<'_, AgentDb>, id: i64) -> Result<ExperimentResults, String> {
+    let (experiment, runs) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let experiment = load_experiment(&conn, id)?;
+        let runs = experiment
+            .variants
+            .iter()
+            .map(|variant| {
+                variant.run_id.and_then(|run_id| {
+                    conn.query_row(
+                        &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
+                        params![run_id],
+                        super::agents::agent_run_from_row,
+                    )
+                    .ok()
+                })
+            })
+            .collect::<Vec<_>>();
+        (experiment, runs)
+    };
+
+    let mut variants = Vec::new();
+    for (variant, run) in experiment.variants.iter().cloned().zip(runs) {
+        let (run, metrics, output) = match run {
+            Some(run) => {
+                let with_metrics = super::agents::get_agent_run_with_metrics(run).await;
+                let output = with_metrics.output.as_deref().and_then(final_output);
+                (Some(with_metrics.run), with_metrics.metrics, output)
+            }
+            None => (None, None, None),
+        };
+        variants.push(VariantResult {
+            variant,
+            run,
+            metrics,
+            output,
+        });
+    }
+
+    let summary = summarize(&variants);
+    Ok(ExperimentResults {
+        experiment,
+        variants,
+        summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: i64, status: &str, duration_ms: i64, tokens: i64, cost: f64) -> VariantResult {
+        VariantResult {
+            variant: ExperimentVariant {
+                id: Some(id),
+                label: format!("v{}", id),
+                agent_id: 1,
+                model: None,
+                task: None,
+                run_id: Some(id),
+                status: status.to_string(),
+                error: None,
+            },
+            run: None,
+            metrics: Some(AgentRunMetrics {
+                duration_ms: Some(duration_ms),
+                total_tokens: Some(tokens),
+                cost_usd: Some(cost),
+                message_count: Some(4),
+            }),
+            output: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_picks_best_completed_variants() {
+        let results = vec![
+            result(1, "completed", 9_000, 1_200, 0.02),
+            result(2, "completed", 4_000, 3_000, 0.05),
+            // Failed runs count towards totals but never win
+            result(3, "failed", 1_000, 100, 0.001),
+        ];
+        let summary = summarize(&results);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.total_tokens, 4_300);
+        assert_eq!(summary.fastest, Some(2));
+        assert_eq!(summary.cheapest, Some(1));
+        assert_eq!(summary.fewest_tokens, Some(1));
+    }
+
+    #[test]
+    fn test_final_output_prefers_result_entry() {
+        let session = [
+            r#"{"type":"user","message":{"role":"user","content":"Fix it"}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Edit"}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Fixed the bug."}]}}"#,
+        ]
+        .join("\n");
+        assert_eq!(final_output(&session).as_deref(), Some("Fixed the bug."));
+
+        let stream = format!("{}\n{}", session, r#"{"type":"result","result":"Done: 1 file changed"}"#);
+        assert_eq!(final_output(&stream).as_deref(), Some("Done: 1 file changed"));
+        assert_eq!(final_output("not json"), None);
+    }
+}
@@ -0,0 +1,655 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Files larger than this are tracked without diff stats
+const MAX_SNAPSHOT_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeType {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl FileChangeType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FileChangeType::Created => "created",
+            FileChangeType::Modified => "modified",
+            FileChangeType::Deleted => "deleted",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "created" => FileChangeType::Created,
+            "deleted" => FileChangeType::Deleted,
+            _ => FileChangeType::Modified,
+        }
+    }
+}
+
+/// A file touched by an agent run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Path relative to the project when inside it, absolute otherwise
+    pub path: String,
+    pub change_type: FileChangeType,
+    /// Added lines, `None` when the file is binary or too large to diff
+    pub additions: Option<u32>,
+    /// Removed lines, `None` when the file is binary or too large to diff
+    pub deletions: Option<u32>,
+}
+
+/// Content of a file before the run first touched it
+#[derive(Debug, Clone)]
+pub(crate) enum Snapshot {
+    Missing,
+    Text(String),
+    Opaque,
+}
+
+fn take_snapshot(path: &Path) -> Snapshot {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => {
+            if metadata.len() > MAX_SNAPSHOT_BYTES {
+                return Snapshot::Opaque;
+            }
+            match std::fs::read_to_string(path) {
+                Ok(content) => Snapshot::Text(content),
+                Err(_) => Snapshot::Opaque,
+            }
+        }
+        Ok(_) => Snapshot::Opaque,
+        Err(_) => Snapshot::Missing,
+    }
+}
+
+fn git(project_path: &Path, args: &[&str]) -> Option<std::process::Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(project_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+}
+
+/// State of a git project captured before the run starts, so files can be
+/// compared against their pre-run content no matter when the tool_use line
+/// is read.
+#[derive(Debug)]
+struct GitBaseline {
+    /// Commit holding the working tree of tracked files at run start
+    base: String,
+    /// Untracked, non-ignored files that existed at run start
+    untracked: HashSet<PathBuf>,
+}
+
+impl GitBaseline {
+    fn capture(project_path: &Path) -> Option<Self> {
+        // `git stash create` records dirty tracked files without touching the
+        // working tree and prints nothing when the tree is clean
+        let stash = git(project_path, &["stash", "create"])?;
+        let mut base = String::from_utf8_lossy(&stash.stdout).trim().to_string();
+        if base.is_empty() {
+            let head = git(project_path, &["rev-parse", "HEAD"])?;
+            base = String::from_utf8_lossy(&head.stdout).trim().to_string();
+        }
+
+        let listed = git(
+            project_path,
+            &["ls-files", "--others", "--exclude-standard", "-z"],
+        )?;
+        let untracked = listed
+            .stdout
+            .split(|b| *b == 0)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| project_path.join(String::from_utf8_lossy(entry).as_ref()))
+            .collect();
+
+        Some(Self { base, untracked })
+    }
+
+    /// Pre-run content of a project file, `None` when git can't tell
+    fn snapshot(&self, project_path: &Path, path: &Path) -> Option<Snapshot> {
+        let relative = path.strip_prefix(project_path).ok()?;
+        let spec = format!(
+            "{}:./{}",
+            self.base,
+            relative.to_string_lossy().replace('\\', "/")
+        );
+
+        if let Some(size) = git(project_path, &["cat-file", "-s", &spec]) {
+            let size: u64 = String::from_utf8_lossy(&size.stdout)
+                .trim()
+                .parse()
+                .unwrap_or(u64::MAX);
+            if size > MAX_SNAPSHOT_BYTES {
+                return Some(Snapshot::Opaque);
+            }
+            let blob = git(project_path, &["cat-file", "-p", &spec])?;
+            return Some(match String::from_utf8(blob.stdout) {
+                Ok(content) => Snapshot::Text(content),
+                Err(_) => Snapshot::Opaque,
+            });
+        }
+
+        if self.untracked.contains(path) {
+            return None;
+        }
+        let ignored = Command::new("git")
+            .args(["check-ignore", "-q"])
+            .arg(relative)
+            .current_dir(project_path)
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(true);
+        if ignored {
+            return None;
+        }
+
+        // Neither tracked, untracked nor ignored at run start: it didn't exist
+        Some(Snapshot::Missing)
+    }
+}
+
+/// Rebuild the content an Edit/MultiEdit call started from when the edit has
+/// already been applied to `current`
+fn undo_edits(current: &str, input: &serde_json::Value) -> Option<String> {
+    let edits: Vec<&serde_json::Value> = match input.get("edits").and_then(|e| e.as_array()) {
+        Some(edits) => edits.iter().collect(),
+        None => vec![input],
+    };
+    let edits: Vec<(&str, &str, bool)> = edits
+        .iter()
+        .map(|edit| {
+            Some((
+                edit.get("old_string")?.as_str()?,
+                edit.get("new_string")?.as_str()?,
+                edit.get("replace_all")
+                    .and_then(|r| r.as_bool())
+                    .unwrap_or(false),
+            ))
+        })
+        .collect::<Option<_>>()?;
+
+    // Only undo when the last edit is visibly applied
+    let (last_old, last_new, _) = *edits.last()?;
+    if last_new.is_empty() || !current.contains(last_new) || current.contains(last_old) {
+        return None;
+    }
+
+    let mut content = current.to_string();
+    for (old, new, replace_all) in edits.into_iter().rev() {
+        if new.is_empty() || !content.contains(new) {
+            return None;
+        }
+        content = if replace_all {
+            content.replace(new, old)
+        } else {
+            content.replacen(new, old, 1)
+        };
+    }
+    Some(content)
+}
+
+/// Records the files an agent run touches by watching tool_use events and
+/// comparing each file against its content from before the run started.
+#[derive(Debug)]
+pub struct FileChangeTracker {
+    project_path: PathBuf,
+    originals: BTreeMap<PathBuf, Snapshot>,
+    baseline: Option<GitBaseline>,
+}
+
+impl FileChangeTracker {
+    pub fn new(project_path: impl Into<PathBuf>) -> Self {
+        Self {
+            project_path: project_path.into(),
+            originals: BTreeMap::new(),
+            baseline: None,
+        }
+    }
+
+    /// Create a tracker and capture the project's git state; call this before
+    /// the run is spawned
+    pub fn with_baseline(project_path: impl Into<PathBuf>) -> Self {
+        let mut tracker = Self::new(project_path);
+        tracker.baseline = GitBaseline::capture(&tracker.project_path);
+        tracker
+    }
+
+    /// Inspect a stream-json stdout line for file-touching tool calls
+    pub fn observe_stdout(&mut self, line: &str) {
+        let Ok(msg) = serde_json::from_str::<serde_json::Value>(line) else {
+            return;
+        };
+        let Some(content) = msg
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+        else {
+            return;
+        };
+
+        for item in content {
+            if item.get("type").and_then(|t| t.as_str()) != Some("tool_use") {
+                continue;
+            }
+            let tool = item.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+            let Some(input) = item.get("input") else {
+                continue;
+            };
+
+            match tool.to_lowercase().as_str() {
+                "edit" | "write" | "multiedit" | "notebookedit" => {
+                    let file_path = input
+                        .get("file_path")
+                        .or_else(|| input.get("notebook_path"))
+                        .and_then(|p| p.as_str());
+                    if let Some(file_path) = file_path {
+                        self.touch_with_input(file_path, Some(input));
+                    }
+                }
+                "bash" => {
+                    if let Some(command) = input.get("command").and_then(|c| c.as_str()) {
+                        for path in bash_touched_paths(command) {
+                            self.touch(&path);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Snapshot a file the first time it is touched
+    pub fn touch(&mut self, file_path: &str) {
+        self.touch_with_input(file_path, None);
+    }
+
+    /// Record a file's pre-run content the first time it is touched. The tool
+    /// may already have run by the time its line is read, so prefer the git
+    /// baseline, then the Edit input, and only fall back to the current file.
+    fn touch_with_input(&mut self, file_path: &str, input: Option<&serde_json::Value>) {
+        let path = self.resolve(file_path);
+        if self.originals.contains_key(&path) {
+            return;
+        }
+
+        let from_baseline = self
+            .baseline
+            .as_ref()
+            .and_then(|baseline| baseline.snapshot(&self.project_path, &path));
+        let snapshot = from_baseline.unwrap_or_else(|| match take_snapshot(&path) {
+            Snapshot::Text(current) => Snapshot::Text(
+                input
+                    .and_then(|input| undo_edits(&current, input))
+                    .unwrap_or(current),
+            ),
+            other => other,
+        });
+
+        self.originals.insert(path, snapshot);
+    }
+
+    /// Touched files with their pre-run content
+    pub(crate) fn originals(&self) -> impl Iterator<Item = (&Path, &Snapshot)> {
+        self.originals
+            .iter()
+            .map(|(path, snapshot)| (path.as_path(), snapshot))
+    }
+
+    fn resolve(&self, file_path: &str) -> PathBuf {
+        let path = Path::new(file_path);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.project_path.join(path)
+        }
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.project_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Compare every touched file with its snapshot and return the net changes
+    pub fn finish(&self) -> Vec<FileChange> {
+        let mut changes = Vec::new();
+
+        for (path, original) in &self.originals {
+            let current = take_snapshot(path);
+            let change = match (original, &current) {
+                (Snapshot::Missing, Snapshot::Missing) => None,
+                (Snapshot::Missing, Snapshot::Text(new)) => Some((
+                    FileChangeType::Created,
+                    Some(count_lines(new)),
+                    Some(0),
+                )),
+                (Snapshot::Missing, Snapshot::Opaque) => {
+                    Some((FileChangeType::Created, None, None))
+                }
+                (Snapshot::Text(old), Snapshot::Missing) => Some((
+                    FileChangeType::Deleted,
+                    Some(0),
+                    Some(count_lines(old)),
+                )),
+                (_, Snapshot::Missing) => Some((FileChangeType::Deleted, None, None)),
+                (Snapshot::Text(old), Snapshot::Text(new)) => {
+                    if old == new {
+                        None
+                    } else {
+                        let (additions, deletions) = diff_line_stats(old, new);
+                        Some((FileChangeType::Modified, Some(additions), Some(deletions)))
+                    }
+                }
+                // Can't diff binary/large files; report them as touched
+                _ => Some((FileChangeType::Modified, None, None)),
+            };
+
+            if let Some((change_type, additions, deletions)) = change {
+                changes.push(FileChange {
+                    path: self.display_path(path),
+                    change_type,
+                    additions,
+                    deletions,
+                });
+            }
+        }
+
+        changes
+    }
+}
+
+fn count_lines(content: &str) -> u32 {
+    content.lines().count() as u32
+}
+
+/// Approximate added/removed line counts by comparing line multisets
+fn diff_line_stats(old: &str, new: &str) -> (u32, u32) {
+    let mut remaining: HashMap<&str, i64> = HashMap::new();
+    for line in old.lines() {
+        *remaining.entry(line).or_insert(0) += 1;
+    }
+
+    let mut additions = 0u32;
+    for line in new.lines() {
+        match remaining.get_mut(line) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => additions += 1,
+        }
+    }
+
+    let deletions = remaining.values().filter(|c| **c > 0).sum::<i64>() as u32;
+    (additions, deletions)
+}
+
+/// Best-effort extraction of paths removed or moved by simple shell commands
+fn bash_touched_paths(command: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+
+    for segment in command.split(['&', ';', '|']) {
+        let mut tokens = segment.split_whitespace();
+        let Some(program) = tokens.next() else {
+            continue;
+        };
+
+        if matches!(program, "rm" | "mv" | "git") {
+            let args: Vec<&str> = tokens.collect();
+            let args = if program == "git" {
+                // git rm / git mv
+                match args.first() {
+                    Some(&"rm") | Some(&"mv") => &args[1..],
+                    _ => continue,
+                }
+            } else {
+                &args[..]
+            };
+
+            for arg in args {
+                if arg.starts_with('-') || arg.contains('*') {
+                    continue;
+                }
+                paths.push(arg.trim_matches(|c| c == '"' || c == '\'').to_string());
+            }
+        }
+    }
+
+    paths
+}
+
+/// Create the file change table
+pub fn init_file_change_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_file_changes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            change_type TEXT NOT NULL,
+            additions INTEGER,
+            deletions INTEGER,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_run_file_changes_run ON agent_run_file_changes(run_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Replace the recorded file changes of a run
+pub fn save_run_file_changes(
+    conn: &Connection,
+    run_id: i64,
+    changes: &[FileChange],
+) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM agent_run_file_changes WHERE run_id = ?1",
+        params![run_id],
+    )?;
+    for change in changes {
+        conn.execute(
+            "INSERT INTO agent_run_file_changes (run_id, path, change_type, additions, deletions)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                run_id,
+                change.path,
+                change.change_type.as_str(),
+                change.additions,
+                change.deletions
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Get the files created, modified, or deleted by an agent run
+#[tauri::command]
+pub async fn get_run_file_changes(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<FileChange>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, change_type, additions, deletions FROM agent_run_file_changes
+             WHERE run_id = ?1 ORDER BY path ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let changes = stmt
+        .query_map(params![run_id], |row| {
+            Ok(FileChange {
+                path: row.get(0)?,
+                change_type: FileChangeType::from_str(&row.get::<_, String>(1)?),
+                additions: row.get(2)?,
+                deletions: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_use_line(name: &str, input: serde_json::Value) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "tool_use", "name": name, "input": input}]}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_tracks_created_modified_and_deleted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("edit.txt"), "a\nb\nc\n").unwrap();
+        std::fs::write(dir.path().join("gone.txt"), "x\ny\n").unwrap();
+        std::fs::write(dir.path().join("same.txt"), "same\n").unwrap();
+
+        let mut tracker = FileChangeTracker::new(dir.path());
+        tracker.observe_stdout(&tool_use_line(
+            "Edit",
+            serde_json::json!({"file_path": dir.path().join("edit.txt")}),
+        ));
+        tracker.observe_stdout(&tool_use_line(
+            "Write",
+            serde_json::json!({"file_path": "new.txt"}),
+        ));
+        tracker.observe_stdout(&tool_use_line(
+            "Bash",
+            serde_json::json!({"command": "rm -f gone.txt && ls"}),
+        ));
+        tracker.observe_stdout(&tool_use_line(
+            "Write",
+            serde_json::json!({"file_path": "same.txt"}),
+        ));
+
+        std::fs::write(dir.path().join("edit.txt"), "a\nB\nc\nd\n").unwrap();
+        std::fs::write(dir.path().join("new.txt"), "1\n2\n").unwrap();
+        std::fs::remove_file(dir.path().join("gone.txt")).unwrap();
+
+        let changes = tracker.finish();
+        assert_eq!(
+            changes,
+            vec![
+                FileChange {
+                    path: "edit.txt".to_string(),
+                    change_type: FileChangeType::Modified,
+                    additions: Some(2),
+                    deletions: Some(1),
+                },
+                FileChange {
+                    path: "gone.txt".to_string(),
+                    change_type: FileChangeType::Deleted,
+                    additions: Some(0),
+                    deletions: Some(2),
+                },
+                FileChange {
+                    path: "new.txt".to_string(),
+                    change_type: FileChangeType::Created,
+                    additions: Some(2),
+                    deletions: Some(0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uses_pre_run_content_when_tool_ran_before_line_is_read() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("edit.txt"), "fn a() {}\nfn b() {}\n").unwrap();
+
+        let mut tracker = FileChangeTracker::new(dir.path());
+        // The edit lands on disk before its tool_use line is observed
+        std::fs::write(dir.path().join("edit.txt"), "fn a() {}\nfn c() {}\n").unwrap();
+        tracker.observe_stdout(&tool_use_line(
+            "Edit",
+            serde_json::json!({
+                "file_path": "edit.txt",
+                "old_string": "fn b() {}",
+                "new_string": "fn c() {}"
+            }),
+        ));
+
+        let changes = tracker.finish();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change_type, FileChangeType::Modified);
+        assert_eq!(
+            (changes[0].additions, changes[0].deletions),
+            (Some(1), Some(1))
+        );
+
+        // In a git project the baseline captured at start covers writes too
+        let repo = tempfile::tempdir().unwrap();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(repo.path())
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false)
+        };
+        if !run(&["init", "-q"]) {
+            return;
+        }
+        std::fs::write(repo.path().join("lib.rs"), "one\ntwo\n").unwrap();
+        run(&["add", "lib.rs"]);
+        run(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-qm",
+            "init",
+        ]);
+
+        let mut tracker = FileChangeTracker::with_baseline(repo.path());
+        std::fs::write(repo.path().join("lib.rs"), "one\nthree\n").unwrap();
+        std::fs::write(repo.path().join("new.rs"), "x\n").unwrap();
+        tracker.observe_stdout(&tool_use_line(
+            "Write",
+            serde_json::json!({"file_path": "lib.rs"}),
+        ));
+        tracker.observe_stdout(&tool_use_line(
+            "Write",
+            serde_json::json!({"file_path": "new.rs"}),
+        ));
+
+        let changes = tracker.finish();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "lib.rs");
+        assert_eq!(changes[0].change_type, FileChangeType::Modified);
+        assert_eq!(changes[1].path, "new.rs");
+        assert_eq!(changes[1].change_type, FileChangeType::Created);
+    }
+
+    #[test]
+    fn test_bash_touched_paths() {
+        assert_eq!(
+            bash_touched_paths("git mv old.rs new.rs; rm -rf build/* target"),
+            vec!["old.rs", "new.rs", "target"]
+        );
+        assert!(bash_touched_paths("cargo test | grep ok").is_empty());
+    }
+}
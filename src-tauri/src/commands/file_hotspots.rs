@@ -0,0 +1,329 @@
+#![allow(dead_code)]
+
+//! File hotspots: the files Claude changed most often in a project and when, read from the
+//! Edit, MultiEdit, Write and NotebookEdit calls recorded in the session index. Paths inside
+//! the session's working directory are shown relative to it, so the same file edited from
+//! several sessions is counted once.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use super::project_stats::edit_tools_sql;
+use super::session_index::{canonical, index_all, open_store, projects_dir};
+use super::usage::DateRange;
+
+/// Files listed in a report
+const HOTSPOT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileHotspot {
+    /// Relative to the session's working directory when inside it
+    pub path: String,
+    pub edits: u64,
+    /// Sessions that changed the file
+    pub sessions: u64,
+    pub first_edited: Option<String>,
+    pub last_edited: Option<String>,
+    /// Edits per tool, most used first
+    pub tools: Vec<HotspotTool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotspotTool {
+    pub tool: String,
+    pub edits: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyEdits {
+    pub date: String,
+    pub edits: u64,
+    /// Distinct files changed that day
+    pub files: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileHotspotReport {
+    pub project_id: String,
+    pub total_edits: u64,
+    pub files_edited: u64,
+    /// Most edited first
+    pub hotspots: Vec<FileHotspot>,
+    /// Oldest first
+    pub daily: Vec<DailyEdits>,
+}
+
+/// One edit call as read from the index
+struct EditRow {
+    file_path: String,
+    cwd: Option<String>,
+    timestamp: Option<String>,
+    session: String,
+    tool: String,
+}
+
+#[derive(Default)]
+struct FileTally {
+    edits: u64,
+    sessions: HashSet<String>,
+    first_edited: Option<String>,
+    last_edited: Option<String>,
+    tools: HashMap<String, u64>,
+}
+
+/// The path as shown in the report: relative to `cwd` when the file is inside it
+fn display_path(file_path: &str, cwd: Option<&str>) -> String {
+    cwd.and_then(|cwd| Path::new(file_path).strip_prefix(cwd).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(|relative| relative.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_path.to_string())
+}
+
+fn edit_rows(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_id: &str,
+    range: &DateRange,
+) -> Result<Vec<EditRow>, String> {
+    let (start, end) = range.bounds()?;
+    let prefix = canonical(projects_dir).to_string_lossy().to_string();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT t.file_path, f.cwd, m.timestamp, t.path, t.tool_name
+             FROM session_index_tool_uses t
+             JOIN session_index_files f ON f.path = t.path
+             LEFT JOIN session_index_messages m ON m.path = t.path AND m.position = t.position
+             WHERE substr(f.path, 1, length(?1)) = ?1 AND f.project_dir = ?2
+               AND t.file_path IS NOT NULL AND t.tool_name IN ({})
+               AND (?3 IS NULL OR date(m.timestamp) >= ?3)
+               AND (?4 IS NULL OR date(m.timestamp) <= ?4)",
+            edit_tools_sql()
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![
+                prefix,
+                project_id,
+                start.map(|day| day.to_string()),
+                end.map(|day| day.to_string())
+            ],
+            |row| {
+                Ok(EditRow {
+                    file_path: row.get(0)?,
+                    cwd: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    session: row.get(3)?,
+                    tool: row.get(4)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Hotspots of the project directory `project_id` under `projects_dir`, from the index as it is
+pub fn file_hotspots(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_id: &str,
+    range: &DateRange,
+) -> Result<FileHotspotReport, String> {
+    let mut files: HashMap<String, FileTally> = HashMap::new();
+    let mut days: BTreeMap<String, (u64, HashSet<String>)> = BTreeMap::new();
+    let rows = edit_rows(conn, projects_dir, project_id, range)?;
+    let total_edits = rows.len() as u64;
+
+    for row in rows {
+        let path = display_path(&row.file_path, row.cwd.as_deref());
+        let tally = files.entry(path.clone()).or_default();
+        tally.edits += 1;
+        tally.sessions.insert(row.session);
+        *tally.tools.entry(row.tool).or_default() += 1;
+
+        let Some(timestamp) = row.timestamp else {
+            continue;
+        };
+        if tally
+            .first_edited
+            .as_ref()
+            .is_none_or(|first| timestamp < *first)
+        {
+            tally.first_edited = Some(timestamp.clone());
+        }
+        if tally
+            .last_edited
+            .as_ref()
+            .is_none_or(|last| timestamp > *last)
+        {
+            tally.last_edited = Some(timestamp.clone());
+        }
+        if let Some(date) = timestamp.get(..10) {
+            let day = days.entry(date.to_string()).or_default();
+            day.0 += 1;
+            day.1.insert(path);
+        }
+    }
+
+    let files_edited = files.len() as u64;
+    let mut hotspots: Vec<FileHotspot> = files
+        .into_iter()
+        .map(|(path, tally)| {
+            let mut tools: Vec<HotspotTool> = tally
+                .tools
+                .into_iter()
+                .map(|(tool, edits)| HotspotTool { tool, edits })
+                .collect();
+            tools.sort_by(|a, b| b.edits.cmp(&a.edits).then_with(|| a.tool.cmp(&b.tool)));
+            FileHotspot {
+                path,
+                edits: tally.edits,
+                sessions: tally.sessions.len() as u64,
+                first_edited: tally.first_edited,
+                last_edited: tally.last_edited,
+                tools,
+            }
+        })
+        .collect();
+    hotspots.sort_by(|a, b| {
+        b.edits
+            .cmp(&a.edits)
+            .then_with(|| b.last_edited.cmp(&a.last_edited))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    hotspots.truncate(HOTSPOT_LIMIT);
+
+    Ok(FileHotspotReport {
+        project_id: project_id.to_string(),
+        total_edits,
+        files_edited,
+        hotspots,
+        daily: days
+            .into_iter()
+            .map(|(date, (edits, files))| DailyEdits {
+                date,
+                edits,
+                files: files.len() as u64,
+            })
+            .collect(),
+    })
+}
+
+/// The files Claude changed most often in a project, and when
+#[tauri::command]
+pub async fn get_file_hotspots(
+    project: String,
+    range: Option<DateRange>,
+) -> Result<FileHotspotReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = open_store().ok_or("The session index is not available")?;
+        let projects_dir = projects_dir()?;
+        index_all(&conn, &projects_dir)?;
+        file_hotspots(&conn, &projects_dir, &project, &range.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::session_index::init_session_index_tables;
+    use super::*;
+    use serde_json::json;
+
+    fn edits(session: &str, timestamp: &str, calls: &[(&str, &str)]) -> String {
+        let content: Vec<serde_json::Value> = calls
+            .iter()
+            .map(|(tool, path)| {
+                json!({ "type": "tool_use", "name": tool, "input": { "file_path": path } })
+            })
+            .collect();
+        json!({
+            "type": "assistant",
+            "timestamp": timestamp,
+            "sessionId": session,
+            "cwd": "/work/app",
+            "message": { "role": "assistant", "content": content },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_hotspots_rank_files_across_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("s1.jsonl"),
+            [
+                edits(
+                    "s1",
+                    "2025-04-01T09:00:00Z",
+                    &[
+                        ("Edit", "/work/app/src/lib.rs"),
+                        ("Read", "/work/app/README.md"),
+                    ],
+                ),
+                edits(
+                    "s1",
+                    "2025-04-01T09:10:00Z",
+                    &[("Write", "/work/app/src/new.rs")],
+                ),
+            ]
+            .join("\n")
+                + "\n",
+        )
+        .unwrap();
+        std::fs::write(
+            project.join("s2.jsonl"),
+            [
+                edits(
+                    "s2",
+                    "2025-04-03T15:00:00Z",
+                    &[("MultiEdit", "/work/app/src/lib.rs")],
+                ),
+                edits("s2", "2025-05-01T15:00:00Z", &[("Edit", "/etc/hosts")]),
+            ]
+            .join("\n")
+                + "\n",
+        )
+        .unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_session_index_tables(&conn).unwrap();
+        index_all(&conn, dir.path()).unwrap();
+
+        let report = file_hotspots(&conn, dir.path(), "-work-app", &DateRange::default()).unwrap();
+        assert_eq!(report.total_edits, 4);
+        assert_eq!(report.files_edited, 3);
+        let lib = &report.hotspots[0];
+        assert_eq!(lib.path, "src/lib.rs");
+        assert_eq!((lib.edits, lib.sessions), (2, 2));
+        assert_eq!(lib.first_edited.as_deref(), Some("2025-04-01T09:00:00Z"));
+        assert_eq!(lib.last_edited.as_deref(), Some("2025-04-03T15:00:00Z"));
+        // Files outside the working directory keep their absolute path
+        assert!(report
+            .hotspots
+            .iter()
+            .any(|hotspot| hotspot.path == "/etc/hosts"));
+        assert_eq!(
+            report.daily[0],
+            DailyEdits {
+                date: "2025-04-01".to_string(),
+                edits: 2,
+                files: 2,
+            }
+        );
+
+        let april = DateRange {
+            start_date: Some("2025-04-01".to_string()),
+            end_date: Some("2025-04-30".to_string()),
+        };
+        let report = file_hotspots(&conn, dir.path(), "-work-app", &april).unwrap();
+        assert_eq!(report.total_edits, 3);
+        assert_eq!(report.daily.len(), 2);
+    }
+}
@@ -0,0 +1,115 @@
+#![allow(dead_code)]
+
+//! Minimal OS keychain access through the platform's own CLI tools
+//! (`security` on macOS, `secret-tool` on Linux). Windows has no CLI that can
+//! read credentials back, so secrets are reported as unavailable there.
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use std::process::{Command, Stdio};
+
+/// Service name every opcode secret is stored under
+const KEYCHAIN_SERVICE: &str = "opcode";
+
+/// Store `secret` under `account`, replacing any existing value
+pub fn store_secret(account: &str, secret: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        let status = Command::new("security")
+            .args([
+                "add-generic-password",
+                "-U",
+                "-s",
+                KEYCHAIN_SERVICE,
+                "-a",
+                account,
+                "-w",
+                secret,
+            ])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| format!("Failed to run security: {}", e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("Failed to store '{}' in the keychain", account))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut child = Command::new("secret-tool")
+            .args([
+                "store",
+                &format!("--label=opcode: {}", account),
+                "service",
+                KEYCHAIN_SERVICE,
+                "account",
+                account,
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to run secret-tool: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            use std::io::Write;
+            stdin
+                .write_all(secret.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        let status = child.wait().map_err(|e| e.to_string())?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("Failed to store '{}' in the keyring", account))
+        }
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        let _ = (account, secret);
+        Err("The OS keychain is not supported on this platform".to_string())
+    }
+}
+
+/// Read the secret stored under `account`, if any
+pub fn load_secret(account: &str) -> Option<String> {
+    #[cfg(target_os = "macos")]
+    let output = Command::new("security")
+        .args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account,
+            "-w",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    #[cfg(target_os = "linux")]
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", KEYCHAIN_SERVICE, "account", account])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        let _ = account;
+        return None;
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        if !output.status.success() {
+            return None;
+        }
+        let secret = crate::decoding::decode_command_output(&output.stdout)
+            .trim_end_matches(['\r', '\n'])
+            .to_string();
+        Some(secret).filter(|secret| !secret.is_empty())
+    }
+}
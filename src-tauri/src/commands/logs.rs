@@ -0,0 +1,211 @@
+#![allow(dead_code)]
+
+use log::LevelFilter;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tauri::{AppHandle, State};
+
+use super::agents::AgentDb;
+use super::settings::{notify_settings_changed, set_setting_value};
+use crate::logger;
+
+/// app_settings key holding the runtime log level
+pub const LOG_LEVEL_KEY: &str = "log_level";
+
+/// Entries returned when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 1000;
+
+/// A parsed log record
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogEntry {
+    /// Local time, `YYYY-MM-DD HH:MM:SS.mmm`
+    pub timestamp: String,
+    pub level: String,
+    /// Source `file:line`
+    pub location: String,
+    pub message: String,
+}
+
+fn log_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^\[(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d{3})\] .*?\[(ERROR|WARN|INFO|DEBUG|TRACE)\] (\S+:\S+) - (.*)$",
+        )
+        .unwrap()
+    })
+}
+
+/// Parse the lines of a log file; lines that don't start a record continue the previous one
+pub fn parse_log_lines(content: &str) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in content.lines() {
+        match log_line_regex().captures(line) {
+            Some(caps) => entries.push(LogEntry {
+                timestamp: caps[1].to_string(),
+                level: caps[2].to_string(),
+                location: caps[3].to_string(),
+                message: caps[4].to_string(),
+            }),
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Parse `since` as RFC 3339 or local `YYYY-MM-DD[ HH:MM:SS]` into the log timestamp format
+fn normalize_since(since: &str) -> Result<String, String> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(since) {
+        return Ok(time
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string());
+    }
+    if let Ok(time) = chrono::NaiveDateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S") {
+        return Ok(time.format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Ok(format!("{} 00:00:00.000", date));
+    }
+    Err(format!("Invalid 'since' time: {}", since))
+}
+
+/// Filter entries by minimum level, start time and a case-insensitive query
+fn filter_entries(
+    entries: Vec<LogEntry>,
+    min_level: LevelFilter,
+    since: Option<&str>,
+    query: Option<&str>,
+) -> Vec<LogEntry> {
+    let query = query.map(|q| q.to_lowercase());
+    entries
+        .into_iter()
+        .filter(|entry| {
+            log::Level::from_str(&entry.level)
+                .map(|level| level <= min_level)
+                .unwrap_or(true)
+        })
+        .filter(|entry| since.is_none_or(|since| entry.timestamp.as_str() >= since))
+        .filter(|entry| {
+            query.as_ref().is_none_or(|q| {
+                entry.message.to_lowercase().contains(q) || entry.location.to_lowercase().contains(q)
+            })
+        })
+        .collect()
+}
+
+/// Read recent log entries, newest last
+#[tauri::command]
+pub async fn get_app_logs(
+    level: Option<String>,
+    since: Option<String>,
+    query: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    let min_level = match level.as_deref() {
+        Some(level) => LevelFilter::from_str(level).map_err(|_| format!("Invalid log level: {}", level))?,
+        None => LevelFilter::Trace,
+    };
+    let since = since.as_deref().map(normalize_since).transpose()?;
+    let since_date = since
+        .as_deref()
+        .and_then(|since| chrono::NaiveDate::parse_from_str(&since[..10], "%Y-%m-%d").ok());
+
+    let mut files: Vec<_> = std::fs::read_dir(logger::log_dir())
+        .map_err(|e| format!("Failed to read log directory: {}", e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| logger::log_file_date(&path).map(|date| (date, path)))
+        .filter(|(date, _)| since_date.is_none_or(|since| *date >= since))
+        .collect();
+    // Same-day files sort by their rollover number, e.g. .log before .1.log before .2.log
+    files.sort_by_key(|(date, path)| (*date, path.as_os_str().len(), path.clone()));
+
+    let mut entries = Vec::new();
+    for (_, path) in files {
+        let content = match std::fs::read(&path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                log::warn!("Failed to read log file {:?}: {}", path, e);
+                continue;
+            }
+        };
+        entries.extend(filter_entries(
+            parse_log_lines(&content),
+            min_level,
+            since.as_deref(),
+            query.as_deref(),
+        ));
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_LOG_LIMIT);
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+/// Set the log level at runtime, or restore the default filters with `None`
+#[tauri::command]
+pub async fn set_log_level(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    level: Option<String>,
+) -> Result<(), String> {
+    if let Some(level) = &level {
+        LevelFilter::from_str(level).map_err(|_| format!("Invalid log level: {}", level))?;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = level.map(|level| serde_json::Value::String(level.to_lowercase()));
+    set_setting_value(&conn, LOG_LEVEL_KEY, value.as_ref())?;
+    notify_settings_changed(&app, &conn, &[LOG_LEVEL_KEY]);
+    Ok(())
+}
+
+/// Apply a stored log level, if any
+pub fn apply_log_level(level: Option<String>) {
+    logger::set_level_override(level.and_then(|level| LevelFilter::from_str(&level).ok()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "[2024-05-01 10:00:00.000] ℹ️  [CLAUDE INFO] [INFO] src/commands/claude.rs:10 - Spawning claude
+[2024-05-01 10:00:01.500] ❌ [ERROR] [ERROR] src/commands/mcp.rs:42 - Failed to add server
+stderr: permission denied
+[2024-05-01 10:00:02.000] 🔍 [DEBUG] [DEBUG] src/process/registry.rs:7 - Registered run 3";
+
+    #[test]
+    fn test_parse_log_lines_joins_continuations() {
+        let entries = parse_log_lines(SAMPLE);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].level, "INFO");
+        assert_eq!(entries[0].location, "src/commands/claude.rs:10");
+        assert_eq!(
+            entries[1].message,
+            "Failed to add server\nstderr: permission denied"
+        );
+    }
+
+    #[test]
+    fn test_filter_entries_by_level_since_and_query() {
+        let entries = parse_log_lines(SAMPLE);
+        let warnings = filter_entries(entries.clone(), LevelFilter::Warn, None, None);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].level, "ERROR");
+
+        let since = normalize_since("2024-05-01 10:00:01").unwrap();
+        let recent = filter_entries(entries.clone(), LevelFilter::Trace, Some(&since), None);
+        assert_eq!(recent.len(), 2);
+
+        let mcp = filter_entries(entries, LevelFilter::Trace, None, Some("PERMISSION"));
+        assert_eq!(mcp.len(), 1);
+        assert!(normalize_since("yesterday").is_err());
+    }
+}
@@ -2,13 +2,26 @@
 
 use anyhow::{Context, Result};
 use dirs;
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use tauri::AppHandle;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use super::activity::{record_activity, ActivityKind, NewActivity};
+use super::cancellation::{CancellationRegistry, CancellationToken, CANCELLED_MESSAGE};
+use super::error::{ErrorKind, OpcodeError};
+use super::mcp_reachability::{
+    cached_probe, explain_error, probe, probed_statuses, store_probe, ReachabilityProbe,
+};
+use super::mcp_scope::{recommend_scope, ScopeRecommendation};
+use super::messages::{Message, MessageCode};
+use super::offline::QueuedMcpOperation;
+use super::providers::{ClaudeCliRunner, FsProvider, SystemClaudeCli, SystemFs};
+use super::team_policy::load_policy;
 
 // ============================================================================
 // 常量定义
@@ -23,9 +36,32 @@ const DANGEROUS_HEADER_CHARS: &[char] = &['\n', '\r', '\0'];
 /// 允许的命令路径前缀
 const ALLOWED_PATH_PREFIXES: &[&str] = &[
     "/usr/", "/bin/", "/sbin/", "/Applications/",
-    "C:\\Program Files\\", "C:\\Windows\\System32\\"
 ];
 
+/// 允许的 Windows 命令目录（环境变量在校验时解析，比较时不区分大小写）
+const ALLOWED_WINDOWS_DIRS: &[&str] = &[
+    "%ProgramFiles%\\",
+    "%ProgramFiles(x86)%\\",
+    "%SystemRoot%\\System32\\",
+    "%ProgramData%\\chocolatey\\bin\\",
+    "%APPDATA%\\npm\\",
+    "%APPDATA%\\nvm\\",
+    "%LOCALAPPDATA%\\Programs\\",
+    "%LOCALAPPDATA%\\Volta\\bin\\",
+    "%USERPROFILE%\\scoop\\shims\\",
+];
+
+/// 未设置时使用的 Windows 默认目录
+const WINDOWS_DEFAULT_DIRS: &[(&str, &str)] = &[
+    ("ProgramFiles", "C:\\Program Files"),
+    ("ProgramFiles(x86)", "C:\\Program Files (x86)"),
+    ("SystemRoot", "C:\\Windows"),
+    ("ProgramData", "C:\\ProgramData"),
+];
+
+/// Windows 路径中的危险字符（允许 `(`、`)`、`~` 等常见路径字符）
+const DANGEROUS_WINDOWS_PATH_CHARS: &[char] = &[';', '&', '|', '$', '`', '<', '>', '\n', '\r', '*', '?', '"', '^', '!', '%'];
+
 /// 最大服务器名称长度
 const MAX_SERVER_NAME_LENGTH: usize = 128;
 
@@ -64,6 +100,38 @@ impl std::fmt::Display for ValidationError {
 
 impl std::error::Error for ValidationError {}
 
+impl ValidationError {
+    /// Catalog message for the frontend to localize
+    pub fn to_message(&self) -> Message {
+        match self {
+            ValidationError::EmptyField(field) => {
+                Message::new(MessageCode::ValidationEmptyField).param("field", field)
+            }
+            ValidationError::InvalidCharacters(field, chars) => {
+                Message::new(MessageCode::ValidationInvalidCharacters)
+                    .param("field", field)
+                    .param("characters", chars)
+            }
+            ValidationError::InvalidLength(field, len) => {
+                Message::new(MessageCode::ValidationInvalidLength)
+                    .param("field", field)
+                    .param("length", len)
+            }
+            ValidationError::InvalidFormat(field, format) => {
+                Message::new(MessageCode::ValidationInvalidFormat)
+                    .param("field", field)
+                    .param("reason", format)
+            }
+            ValidationError::PathTraversal(path) => {
+                Message::new(MessageCode::ValidationPathTraversal).param("path", path)
+            }
+            ValidationError::UnauthorizedPath(path) => {
+                Message::new(MessageCode::ValidationUnauthorizedPath).param("path", path)
+            }
+        }
+    }
+}
+
 impl From<ValidationError> for String {
     fn from(error: ValidationError) -> Self {
         error.to_string()
@@ -93,6 +161,10 @@ fn validate_command(cmd: &str) -> Result<String, ValidationError> {
     let cmd = cmd.trim();
     validate_length("Command", cmd, MAX_SERVER_NAME_LENGTH)?;
 
+    if is_windows_path(cmd) {
+        return validate_windows_command(cmd, &|name| std::env::var(name).ok());
+    }
+
     if contains_dangerous_chars(cmd, DANGEROUS_SHELL_CHARS) {
         return Err(ValidationError::InvalidCharacters(
             "Command".to_string(),
@@ -117,6 +189,90 @@ fn validate_command(cmd: &str) -> Result<String, ValidationError> {
     Ok(cmd.to_string())
 }
 
+/// Drive-letter, UNC or `%VAR%`-prefixed paths, checked on every platform so configs
+/// written for Windows validate the same way anywhere
+fn is_windows_path(cmd: &str) -> bool {
+    let bytes = cmd.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    drive || cmd.starts_with("\\\\") || cmd.starts_with('%')
+}
+
+/// 解析 `%NAME%` 形式的环境变量
+fn expand_windows_vars(path: &str, env: &dyn Fn(&str) -> Option<String>) -> Result<String, ValidationError> {
+    let lookup = |name: &str| {
+        env(name).filter(|value| !value.is_empty()).or_else(|| {
+            WINDOWS_DEFAULT_DIRS
+                .iter()
+                .find(|(default, _)| default.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.to_string())
+        })
+    };
+
+    let mut expanded = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            return Err(ValidationError::InvalidFormat(
+                "Command".to_string(),
+                "unterminated environment variable".to_string(),
+            ));
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        let value = lookup(name).ok_or_else(|| {
+            ValidationError::InvalidFormat(
+                "Command".to_string(),
+                format!("environment variable %{}% is not set", name),
+            )
+        })?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[start + len + 2..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// 验证 Windows 命令路径：盘符、UNC、环境变量和允许的目录
+fn validate_windows_command(cmd: &str, env: &dyn Fn(&str) -> Option<String>) -> Result<String, ValidationError> {
+    let expanded = expand_windows_vars(cmd, env)?.replace('/', "\\");
+    // `\\?\C:\...` 长路径前缀指向本地盘符
+    let path = expanded.strip_prefix("\\\\?\\").unwrap_or(&expanded);
+
+    if path.starts_with("\\\\") || path.to_ascii_uppercase().starts_with("UNC\\") {
+        return Err(ValidationError::UnauthorizedPath(format!("network share {}", cmd)));
+    }
+
+    let bytes = path.as_bytes();
+    if !(bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\') {
+        return Err(ValidationError::InvalidFormat(
+            "Command".to_string(),
+            "expected an absolute path such as C:\\Program Files\\nodejs\\npx.cmd".to_string(),
+        ));
+    }
+
+    if contains_dangerous_chars(path, DANGEROUS_WINDOWS_PATH_CHARS) {
+        return Err(ValidationError::InvalidCharacters(
+            "Command".to_string(),
+            "shell metacharacters".to_string(),
+        ));
+    }
+
+    if path.split('\\').any(|component| component == "..") {
+        return Err(ValidationError::PathTraversal(cmd.to_string()));
+    }
+
+    let lower = path.to_lowercase();
+    let allowed = ALLOWED_WINDOWS_DIRS
+        .iter()
+        .filter_map(|dir| expand_windows_vars(dir, env).ok())
+        .any(|dir| lower.starts_with(&dir.replace('/', "\\").to_lowercase()));
+    if !allowed {
+        return Err(ValidationError::UnauthorizedPath(cmd.to_string()));
+    }
+
+    Ok(path.to_string())
+}
+
 /// 验证 URL
 fn validate_url(url: &str) -> Result<String, ValidationError> {
     let url = url.trim();
@@ -314,27 +470,206 @@ fn find_claude_binary(app_handle: &AppHandle) -> Result<String> {
     crate::claude_binary::find_claude_binary(app_handle).map_err(|e| anyhow::anyhow!(e))
 }
 
-/// 执行 claude mcp 命令
-fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<String>) -> Result<String> {
-    info!("Executing claude mcp command with args: {:?}", args);
+/// app_settings key holding how long a `claude mcp` command may run, in seconds
+pub const MCP_COMMAND_TIMEOUT_KEY: &str = "mcp_command_timeout_secs";
+
+/// Used when no timeout is configured
+pub const DEFAULT_MCP_COMMAND_TIMEOUT_SECS: u64 = 60;
 
+/// Start of the error returned when a `claude mcp` command is killed for taking too long
+pub const TIMED_OUT_MESSAGE: &str = "Command timed out";
+
+static MCP_COMMAND_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_MCP_COMMAND_TIMEOUT_SECS);
+
+/// Apply the timeout stored in settings; `None` or 0 restores the default
+pub fn apply_mcp_command_timeout(secs: Option<u64>) {
+    let secs = secs.filter(|secs| *secs > 0).unwrap_or(DEFAULT_MCP_COMMAND_TIMEOUT_SECS);
+    MCP_COMMAND_TIMEOUT_SECS.store(secs, Ordering::Relaxed);
+}
+
+fn mcp_command_timeout() -> Duration {
+    Duration::from_secs(MCP_COMMAND_TIMEOUT_SECS.load(Ordering::Relaxed))
+}
+
+/// 执行 claude mcp 命令. The process runs on the blocking pool so a wedged CLI can't stall
+/// the async runtime; it is killed once the configured timeout passes.
+async fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<String>) -> Result<String> {
     let claude_path = find_claude_binary(app_handle)?;
-    let mut cmd = create_command_with_env(&claude_path);
-    cmd.arg("mcp");
-    for arg in args {
-        cmd.arg(arg);
+    tauri::async_runtime::spawn_blocking(move || run_claude_mcp_command(&claude_path, args))
+        .await
+        .map_err(|e| anyhow::anyhow!("claude mcp command panicked: {}", e))?
+}
+
+/// Run `claude mcp <args>` with a known binary path. Goes through the shared CLI invoker,
+/// so identical concurrent calls spawn one process and refreshes are debounced.
+pub fn run_claude_mcp_command(claude_path: &str, args: Vec<String>) -> Result<String> {
+    run_claude_mcp_command_cancellable(claude_path, args, &CancellationToken::new())
+}
+
+/// Like `run_claude_mcp_command`, killing the process when `token` is cancelled
+pub fn run_claude_mcp_command_cancellable(
+    claude_path: &str,
+    args: Vec<String>,
+    token: &CancellationToken,
+) -> Result<String> {
+    super::cli_invoker::CliInvoker::global()
+        .run(&args, token, |token| {
+            spawn_claude_mcp_command(claude_path, &args, token).map_err(|e| e.to_string())
+        })
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+fn spawn_claude_mcp_command(claude_path: &str, args: &[String], token: &CancellationToken) -> Result<String> {
+    spawn_claude_mcp_command_with_timeout(claude_path, args, token, mcp_command_timeout())
+}
+
+fn spawn_claude_mcp_command_with_timeout(
+    claude_path: &str,
+    args: &[String],
+    token: &CancellationToken,
+    timeout: Duration,
+) -> Result<String> {
+    info!("Executing claude mcp command with args: {:?}", args);
+
+    // Subcommands that rewrite ~/.claude.json (and .mcp.json for project scope)
+    if super::cli_invoker::is_mutating(args) {
+        let reason = format!("mcp {}", args[0]);
+        let mut files: Vec<PathBuf> = super::config_snapshots::user_claude_json().into_iter().collect();
+        if args.iter().any(|arg| arg == "project") {
+            files.push(PathBuf::from(".mcp.json"));
+        }
+        for file in files {
+            super::config_snapshots::snapshot_config_file(&file, &reason)
+                .map_err(|e| anyhow::anyhow!(e))?;
+        }
     }
 
-    let output = cmd.output().context("Failed to execute claude command")?;
+    let mut cmd = create_command_with_env(claude_path);
+    cmd.arg("mcp");
+    cmd.args(args);
 
-    if output.status.success() {
-        Ok(crate::claude_binary::decode_command_output(&output.stdout))
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let mut child = cmd.spawn().context("Failed to execute claude command")?;
+    // Drain the pipes on threads so a chatty process can't block while we poll for exit
+    let stdout = child.stdout.take().map(read_pipe);
+    let stderr = child.stderr.take().map(read_pipe);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for claude command")? {
+            break status;
+        }
+        if token.is_cancelled() {
+            info!("Killing cancelled claude mcp command: {:?}", args);
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!(CANCELLED_MESSAGE));
+        }
+        if Instant::now() >= deadline {
+            warn!("Killing claude mcp command after {:?}: {:?}", timeout, args);
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!(
+                "{} after {}s: claude mcp {}",
+                TIMED_OUT_MESSAGE,
+                timeout.as_secs(),
+                args.first().map(String::as_str).unwrap_or_default()
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let collect = |pipe: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        pipe.and_then(|handle| handle.join().ok()).unwrap_or_default()
+    };
+    let (stdout, stderr) = (collect(stdout), collect(stderr));
+
+    if status.success() {
+        Ok(crate::claude_binary::decode_command_output(&stdout))
     } else {
-        let stderr = crate::claude_binary::decode_command_output(&output.stderr);
+        let stderr = crate::claude_binary::decode_command_output(&stderr);
         Err(anyhow::anyhow!("Command failed: {}", stderr))
     }
 }
 
+fn read_pipe(mut pipe: impl std::io::Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+/// Parse server names from the text output of `claude mcp list`
+pub fn parse_mcp_server_names(output: &str) -> Vec<String> {
+    let mut server_names = Vec::new();
+    let lines: Vec<&str> = output.lines().collect();
+    debug!("Total lines in output: {}", lines.len());
+    for (idx, line) in lines.iter().enumerate() {
+        debug!("Line {}: {:?}", idx, line);
+    }
+
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        debug!("Processing line {}: {:?}", i, line);
+
+        // Check if this line starts a new server entry
+        if let Some(colon_pos) = line.find(':') {
+            debug!("Found colon at position {} in line: {:?}", colon_pos, line);
+            // Make sure this is a server name line (not part of a path)
+            // Server names typically don't contain '/' or '\'
+            let potential_name = line[..colon_pos].trim();
+            debug!("Potential server name: {:?}", potential_name);
+
+            if !potential_name.contains('/') && !potential_name.contains('\\') {
+                debug!("Valid server name detected: {:?}", potential_name);
+                server_names.push(potential_name.to_string());
+                debug!("Added server name to list: {:?}", potential_name);
+
+                // Skip to next server (skip continuation lines)
+                i += 1;
+                while i < lines.len() {
+                    let next_line = lines[i];
+                    debug!("Checking next line {} for continuation: {:?}", i, next_line);
+
+                    // If the next line starts with a server name pattern, break
+                    if next_line.contains(':') {
+                        let potential_next_name =
+                            next_line.split(':').next().unwrap_or("").trim();
+                        debug!(
+                            "Found colon in next line, potential name: {:?}",
+                            potential_next_name
+                        );
+                        if !potential_next_name.is_empty()
+                            && !potential_next_name.contains('/')
+                            && !potential_next_name.contains('\\')
+                        {
+                            debug!("Next line is a new server, breaking");
+                            break;
+                        }
+                    }
+                    // Otherwise, this line is a continuation - skip it
+                    debug!("Line {} is a continuation, skipping", i);
+                    i += 1;
+                }
+
+                continue;
+            } else {
+                debug!("Skipping line - name contains path separators");
+            }
+        } else {
+            debug!("No colon found in line {}", i);
+        }
+
+        i += 1;
+    }
+
+    server_names
+}
+
 /// Represents an MCP server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPServer {
@@ -371,6 +706,9 @@ pub struct ServerStatus {
     pub error: Option<String>,
     /// Last checked timestamp
     pub last_checked: Option<u64>,
+    /// Last reachability probe, for SSE/HTTP servers
+    #[serde(default)]
+    pub reachability: Option<ReachabilityProbe>,
 }
 
 /// MCP configuration file paths
@@ -407,12 +745,30 @@ pub struct MCPServerConfig {
     pub headers: Option<HashMap<String, String>>,
 }
 
+/// A server to add with `claude mcp add`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NewMcpServer {
+    pub name: String,
+    pub transport: String,
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub url: Option<String>,
+    pub scope: String,
+    pub headers: HashMap<String, String>,
+    /// Project the server is added for; enables path-based scope advice and the team policy
+    pub project_path: Option<String>,
+}
+
 /// Result of adding a server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddServerResult {
     pub success: bool,
     pub message: String,
     pub server_name: Option<String>,
+    /// Advisory scope for the server, with the reasons behind it
+    #[serde(default)]
+    pub recommendation: Option<ScopeRecommendation>,
 }
 
 /// Import result for multiple servers
@@ -447,26 +803,137 @@ pub async fn mcp_add(
     url: Option<String>,
     scope: String,
     headers: HashMap<String, String>,
-) -> Result<AddServerResult, String> {
+    project_path: Option<String>,
+) -> Result<AddServerResult, OpcodeError> {
+    let cli = match SystemClaudeCli::for_app(&app) {
+        Ok(cli) => cli,
+        Err(e) => {
+            error!("Failed to add MCP server: {}", e);
+            return Ok(AddServerResult {
+                success: false,
+                message: e,
+                server_name: None,
+                recommendation: None,
+            });
+        }
+    };
+    // A `tunnel://` URL is stored as the tunnel's local end, which the CLI can reach
+    let tunnel = url.as_deref().map(|url| super::ssh_tunnels::resolve_tunnel_url(&app, url));
+    let (url, tunnel_id) = match tunnel {
+        Some(Ok(Some((local_url, tunnel_id)))) => (Some(local_url), Some(tunnel_id)),
+        Some(Err(e)) => {
+            return Ok(AddServerResult {
+                success: false,
+                message: e,
+                server_name: None,
+                recommendation: None,
+            });
+        }
+        _ => (url, None),
+    };
+    let server = NewMcpServer { name, transport, command, args, env, url, scope, headers, project_path };
+    if super::offline::is_offline() {
+        return Ok(super::offline::queued_add_result(&app, QueuedMcpOperation::Add { server }));
+    }
+    let scope = server.scope.clone();
+    // Probe remote servers first, so an unreachable URL is explained before it fails to connect
+    let reachability = match (&server.url, server.transport.as_str()) {
+        (Some(url), "sse" | "http") => Some(probe(url, &server.headers, Some(&server.name)).await),
+        _ => None,
+    };
+    let mut result = add_server(&cli, server);
+    if let (true, Some(name), Some(reachability)) =
+        (result.success, &result.server_name, reachability)
+    {
+        if let Some(summary) = reachability.summary() {
+            result.message =
+                format!("{}\nWarning: the server is not reachable ({})", result.message, summary);
+        }
+        store_probe(name, reachability);
+    }
+    if let (true, Some(name), Some(tunnel_id)) = (result.success, &result.server_name, tunnel_id) {
+        if let Err(e) = super::ssh_tunnels::link_server(&app, name, tunnel_id) {
+            warn!("Failed to link MCP server {} to its tunnel: {}", name, e);
+        }
+    }
+    if let (true, Some(name)) = (result.success, &result.server_name) {
+        record_activity(
+            &app,
+            NewActivity::new(ActivityKind::McpServerAdded, format!("MCP server {} added", name))
+                .detail(serde_json::json!({ "scope": scope })),
+        );
+    }
+    Ok(result)
+}
+
+/// Validate `server` and add it with `claude mcp add`. Problems are reported in the
+/// result rather than as an error, so the form can show them. The result carries a scope
+/// recommendation, which the project's team policy can make binding.
+pub fn add_server(cli: &dyn ClaudeCliRunner, server: NewMcpServer) -> AddServerResult {
+    let mut recommendation = recommend_scope(&server);
+    let policy = match server.project_path.as_deref().map(|path| load_policy(Path::new(path))) {
+        Some(Ok(policy)) => policy,
+        Some(Err(e)) => {
+            warn!("Ignoring team policy: {}", e);
+            None
+        }
+        None => None,
+    };
+    if let Some(policy) = &policy {
+        recommendation.enforced = policy.mcp.enforce_recommended_scope;
+    }
+    let violation = policy
+        .filter(|policy| policy.enforce)
+        .and_then(|policy| policy.server_violation(&server.name));
+
+    let mut result = if let Some(violation) = violation {
+        AddServerResult {
+            success: false,
+            message: violation.message,
+            server_name: None,
+            recommendation: None,
+        }
+    } else if recommendation.enforced && recommendation.scope != server.scope {
+        AddServerResult {
+            success: false,
+            message: format!(
+                "Team policy requires {} scope for this server: {}",
+                recommendation.scope,
+                recommendation.reasons.join("; ")
+            ),
+            server_name: None,
+            recommendation: None,
+        }
+    } else {
+        run_add(cli, server)
+    };
+    result.recommendation = Some(recommendation);
+    result
+}
+
+fn run_add(cli: &dyn ClaudeCliRunner, server: NewMcpServer) -> AddServerResult {
+    let NewMcpServer { name, transport, command, args, env, url, scope, headers, .. } = server;
     info!("Adding MCP server: {} with transport: {}", name, transport);
 
     // 验证服务器名称
     if let Err(e) = validate_server_name(&name) {
-        return Ok(AddServerResult {
+        return AddServerResult {
             success: false,
             message: format!("Invalid server name: {}", e),
             server_name: None,
-        });
+            recommendation: None,
+        };
     }
 
     // 验证环境变量名
     for key in env.keys() {
         if let Err(e) = validate_env_var_name(key) {
-            return Ok(AddServerResult {
+            return AddServerResult {
                 success: false,
                 message: format!("Invalid environment variable name '{}': {}", key, e),
                 server_name: None,
-            });
+                recommendation: None,
+            };
         }
     }
 
@@ -499,19 +966,21 @@ pub async fn mcp_add(
         for (key, value) in &headers {
             // 验证头部名称和值
             if let Err(e) = validate_header_name(key) {
-                return Ok(AddServerResult {
+                return AddServerResult {
                     success: false,
                     message: format!("Invalid header name '{}': {}", key, e),
                     server_name: None,
-                });
+                    recommendation: None,
+                };
             }
 
             if let Err(e) = validate_header_value(value) {
-                return Ok(AddServerResult {
+                return AddServerResult {
                     success: false,
                     message: format!("Invalid header value for '{}': {}", key, e),
                     server_name: None,
-                });
+                    recommendation: None,
+                };
             }
 
             cmd_args.push("--header".to_string());
@@ -529,11 +998,12 @@ pub async fn mcp_add(
             let validated_cmd = match validate_command(cmd) {
                 Ok(v) => v,
                 Err(e) => {
-                    return Ok(AddServerResult {
+                    return AddServerResult {
                         success: false,
                         message: format!("Invalid command: {}", e),
                         server_name: None,
-                    });
+                        recommendation: None,
+                    };
                 }
             };
 
@@ -548,21 +1018,23 @@ pub async fn mcp_add(
                 let validated_arg = match validate_arg(arg) {
                     Ok(v) => v,
                     Err(e) => {
-                        return Ok(AddServerResult {
+                        return AddServerResult {
                             success: false,
                             message: format!("Invalid argument '{}': {}", arg, e),
                             server_name: None,
-                        });
+                            recommendation: None,
+                        };
                     }
                 };
                 cmd_args.push(validated_arg);
             }
         } else {
-            return Ok(AddServerResult {
+            return AddServerResult {
                 success: false,
                 message: "Command is required for stdio transport".to_string(),
                 server_name: None,
-            });
+                recommendation: None,
+            };
         }
     } else if transport == "sse" {
         if let Some(url_str) = &url {
@@ -570,137 +1042,95 @@ pub async fn mcp_add(
             let validated_url = match validate_url(url_str) {
                 Ok(v) => v,
                 Err(e) => {
-                    return Ok(AddServerResult {
+                    return AddServerResult {
                         success: false,
                         message: format!("Invalid URL: {}", e),
                         server_name: None,
-                    });
+                        recommendation: None,
+                    };
                 }
             };
             cmd_args.push(validated_url);
         } else {
-            return Ok(AddServerResult {
+            return AddServerResult {
                 success: false,
                 message: "URL is required for SSE transport".to_string(),
                 server_name: None,
-            });
+                recommendation: None,
+            };
         }
     }
 
-    match execute_claude_mcp_command(&app, cmd_args) {
+    match cli.run_mcp(&cmd_args, &CancellationToken::new()) {
         Ok(output) => {
             info!("Successfully added MCP server: {}", name);
-            Ok(AddServerResult {
+            AddServerResult {
                 success: true,
                 message: output.trim().to_string(),
                 server_name: Some(name),
-            })
+                recommendation: None,
+            }
         }
         Err(e) => {
             error!("Failed to add MCP server: {}", e);
-            Ok(AddServerResult {
+            AddServerResult {
                 success: false,
-                message: e.to_string(),
+                message: e,
                 server_name: None,
-            })
+                recommendation: None,
+            }
         }
     }
 }
 
-/// Lists all configured MCP servers
+/// Lists all configured MCP servers. Pass `operation_id` to make it cancellable
+/// through `cancel_operation`.
 #[tauri::command]
-pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
+pub async fn mcp_list(app: AppHandle, operation_id: Option<String>) -> Result<Vec<MCPServer>, OpcodeError> {
+    let operation = CancellationRegistry::global().register(operation_id);
+    let cli = SystemClaudeCli::for_app(&app)?;
+    list_servers(&cli, operation.token(), &|name| get_mcp_server_tools(&app, name))
+}
+
+/// Names from `claude mcp list`, then details for each from `claude mcp get`. `tools`
+/// looks up the tools of a server that answered.
+pub fn list_servers(
+    cli: &dyn ClaudeCliRunner,
+    token: &CancellationToken,
+    tools: &dyn Fn(&str) -> Result<Vec<String>, String>,
+) -> Result<Vec<MCPServer>, OpcodeError> {
     info!("Listing MCP servers");
 
-    match execute_claude_mcp_command(&app, vec!["list".to_string()]) {
+    match cli.run_mcp(&["list".to_string()], token) {
         Ok(output) => {
-            info!("Raw output from 'claude mcp list': {:?}", output);
+            debug!("Raw output from 'claude mcp list': {:?}", output);
             let trimmed = output.trim();
-            info!("Trimmed output: {:?}", trimmed);
+            debug!("Trimmed output: {:?}", trimmed);
 
             // Check if no servers are configured
             if trimmed.contains("No MCP servers configured") || trimmed.is_empty() {
-                info!("No servers found - empty or 'No MCP servers' message");
+                debug!("No servers found - empty or 'No MCP servers' message");
                 return Ok(vec![]);
             }
 
-            // Parse the text output to get server names
-            let mut server_names = Vec::new();
-            let lines: Vec<&str> = trimmed.lines().collect();
-            info!("Total lines in output: {}", lines.len());
-            for (idx, line) in lines.iter().enumerate() {
-                info!("Line {}: {:?}", idx, line);
-            }
-
-            let mut i = 0;
-
-            while i < lines.len() {
-                let line = lines[i];
-                info!("Processing line {}: {:?}", i, line);
-
-                // Check if this line starts a new server entry
-                if let Some(colon_pos) = line.find(':') {
-                    info!("Found colon at position {} in line: {:?}", colon_pos, line);
-                    // Make sure this is a server name line (not part of a path)
-                    // Server names typically don't contain '/' or '\'
-                    let potential_name = line[..colon_pos].trim();
-                    info!("Potential server name: {:?}", potential_name);
-
-                    if !potential_name.contains('/') && !potential_name.contains('\\') {
-                        info!("Valid server name detected: {:?}", potential_name);
-                        server_names.push(potential_name.to_string());
-                        info!("Added server name to list: {:?}", potential_name);
-
-                        // Skip to next server (skip continuation lines)
-                        i += 1;
-                        while i < lines.len() {
-                            let next_line = lines[i];
-                            info!("Checking next line {} for continuation: {:?}", i, next_line);
-
-                            // If the next line starts with a server name pattern, break
-                            if next_line.contains(':') {
-                                let potential_next_name =
-                                    next_line.split(':').next().unwrap_or("").trim();
-                                info!(
-                                    "Found colon in next line, potential name: {:?}",
-                                    potential_next_name
-                                );
-                                if !potential_next_name.is_empty()
-                                    && !potential_next_name.contains('/')
-                                    && !potential_next_name.contains('\\')
-                                {
-                                    info!("Next line is a new server, breaking");
-                                    break;
-                                }
-                            }
-                            // Otherwise, this line is a continuation - skip it
-                            info!("Line {} is a continuation, skipping", i);
-                            i += 1;
-                        }
-
-                        continue;
-                    } else {
-                        info!("Skipping line - name contains path separators");
-                    }
-                } else {
-                    info!("No colon found in line {}", i);
-                }
-
-                i += 1;
-            }
+            let server_names = parse_mcp_server_names(trimmed);
 
             info!("Found {} MCP servers total", server_names.len());
             for (idx, name) in server_names.iter().enumerate() {
-                info!("Server {}: name='{}'", idx, name);
+                debug!("Server {}: name='{}'", idx, name);
             }
 
             // Get detailed information for each server including correct scope
             let mut servers = Vec::new();
             for name in server_names {
-                info!("Getting details for server: {:?}", name);
-                match mcp_get(app.clone(), name.clone()).await {
+                if token.is_cancelled() {
+                    return Err(OpcodeError::cancelled());
+                }
+                debug!("Getting details for server: {:?}", name);
+                match get_server(cli, name.clone(), token, tools) {
+                    Err(e) if e.kind == ErrorKind::Cancelled => return Err(e),
                     Ok(server_details) => {
-                        info!("Successfully got details for server '{}': scope={}, transport={}",
+                        debug!("Successfully got details for server '{}': scope={}, transport={}",
                               name, server_details.scope, server_details.transport);
                         servers.push(server_details);
                     }
@@ -721,6 +1151,7 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
                                 running: false,
                                 error: Some(format!("Failed to get details: {}", e)),
                                 last_checked: None,
+                                reachability: None,
                             },
                             tools: None,
                         });
@@ -732,20 +1163,33 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
         }
         Err(e) => {
             error!("Failed to list MCP servers: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
 
-/// Gets details for a specific MCP server
+/// Gets details for a specific MCP server. Pass `operation_id` to make it cancellable
+/// through `cancel_operation`.
 #[tauri::command]
-pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String> {
+pub async fn mcp_get(app: AppHandle, name: String, operation_id: Option<String>) -> Result<MCPServer, OpcodeError> {
+    let operation = CancellationRegistry::global().register(operation_id);
+    let cli = SystemClaudeCli::for_app(&app)?;
+    get_server(&cli, name, operation.token(), &|name| get_mcp_server_tools(&app, name))
+}
+
+/// Details of one server parsed from `claude mcp get`
+pub fn get_server(
+    cli: &dyn ClaudeCliRunner,
+    name: String,
+    token: &CancellationToken,
+    tools: &dyn Fn(&str) -> Result<Vec<String>, String>,
+) -> Result<MCPServer, OpcodeError> {
     info!("Getting MCP server details for: {}", name);
 
     // 验证服务器名称
     validate_server_name(&name)?;
 
-    match execute_claude_mcp_command(&app, vec!["get".to_string(), name.clone()]) {
+    match cli.run_mcp(&["get".to_string(), name.clone()], token) {
         Ok(output) => {
             // Parse the structured text output
             let mut scope = "local".to_string();
@@ -763,14 +1207,15 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
 
                 if line.starts_with("Scope:") {
                     let scope_part = line.replace("Scope:", "").trim().to_string();
+                    // "User config (available in all your projects)" mentions projects too
                     if scope_part.to_lowercase().contains("local") {
                         scope = "local".to_string();
-                    } else if scope_part.to_lowercase().contains("project") {
-                        scope = "project".to_string();
                     } else if scope_part.to_lowercase().contains("user")
                         || scope_part.to_lowercase().contains("global")
                     {
                         scope = "user".to_string();
+                    } else if scope_part.to_lowercase().contains("project") {
+                        scope = "project".to_string();
                     }
                 } else if line.starts_with("Status:") {
                     let status_part = line.replace("Status:", "").trim().to_string();
@@ -798,7 +1243,7 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
             }
 
             // Get the available tools for this MCP server
-            let tools = match get_mcp_server_tools(&app, &name).await {
+            let tools = match tools(&name) {
                 Ok(tool_list) => Some(tool_list),
                 Err(e) => {
                     warn!("Failed to get tools for server {}: {}", name, e);
@@ -819,27 +1264,33 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
                 tools,
                 status: ServerStatus {
                     running: is_connected,
-                    error: status_error,
+                    error: explain_error(&name, status_error),
                     last_checked: Some(std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
                         .unwrap_or_default()
                         .as_secs()),
+                    reachability: cached_probe(&name),
                 },
             })
         }
         Err(e) => {
             error!("Failed to get MCP server: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
 
 /// Gets the available tools for an MCP server using enhanced inference and pattern matching
-async fn get_mcp_server_tools(_app: &AppHandle, server_name: &str) -> Result<Vec<String>, String> {
+fn get_mcp_server_tools(_app: &AppHandle, server_name: &str) -> Result<Vec<String>, String> {
     info!("Getting tools for MCP server: {}", server_name);
 
+    // Prefer the tools the server itself reported on its last probe
+    if let Some(tools) = super::mcp_capabilities::stored_tool_names(_app, server_name) {
+        return Ok(tools);
+    }
+
     // Try to get real tools from running sessions
-    let real_tools = extract_tools_from_running_sessions(_app, server_name).await?;
+    let real_tools = extract_tools_from_running_sessions(_app, server_name)?;
 
     if !real_tools.is_empty() {
         info!("Found {} real tools for server {}", real_tools.len(), server_name);
@@ -852,7 +1303,7 @@ async fn get_mcp_server_tools(_app: &AppHandle, server_name: &str) -> Result<Vec
 }
 
 /// Extracts MCP tools from currently running Claude sessions
-async fn extract_tools_from_running_sessions(_app: &AppHandle, _server_name: &str) -> Result<Vec<String>, String> {
+fn extract_tools_from_running_sessions(_app: &AppHandle, _server_name: &str) -> Result<Vec<String>, String> {
     // This would search through active JSONL files for system:init messages
     // and extract tools specific to the given server name
     // For now, return empty to use inference
@@ -943,17 +1394,37 @@ fn generate_mcp_tools_for_server(server_name: &str) -> Vec<String> {
 
 /// Removes an MCP server
 #[tauri::command]
-pub async fn mcp_remove(app: AppHandle, name: String) -> Result<String, String> {
+pub async fn mcp_remove(app: AppHandle, name: String) -> Result<String, OpcodeError> {
+    if super::offline::is_offline() {
+        return Ok(super::offline::queue_for_app(&app, QueuedMcpOperation::Remove { name })?);
+    }
+    let cli = SystemClaudeCli::for_app(&app)?;
+    let output = remove_server(&cli, &name)?;
+    record_activity(
+        &app,
+        NewActivity::new(ActivityKind::McpServerRemoved, format!("MCP server {} removed", name)),
+    );
+    if let Some(db) = app.try_state::<super::agents::AgentDb>() {
+        if let Ok(conn) = db.0.lock() {
+            let _ = super::mcp_capabilities::delete_report(&conn, &name);
+            let _ = super::ssh_tunnels::unlink_server(&conn, &name);
+        }
+    }
+    Ok(output)
+}
+
+/// Remove a server with `claude mcp remove`
+pub fn remove_server(cli: &dyn ClaudeCliRunner, name: &str) -> Result<String, OpcodeError> {
     info!("Removing MCP server: {}", name);
 
-    match execute_claude_mcp_command(&app, vec!["remove".to_string(), name.clone()]) {
+    match cli.run_mcp(&["remove".to_string(), name.to_string()], &CancellationToken::new()) {
         Ok(output) => {
             info!("Successfully removed MCP server: {}", name);
             Ok(output.trim().to_string())
         }
         Err(e) => {
             error!("Failed to remove MCP server: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -965,7 +1436,7 @@ pub async fn mcp_add_json(
     name: String,
     json_config: String,
     scope: String,
-) -> Result<AddServerResult, String> {
+) -> Result<AddServerResult, OpcodeError> {
     info!(
         "Adding MCP server from JSON: {} with scope: {}",
         name, scope
@@ -978,13 +1449,26 @@ pub async fn mcp_add_json(
     cmd_args.push("-s".to_string());
     cmd_args.push(scope.clone());
 
-    match execute_claude_mcp_command(&app, cmd_args) {
+    if super::offline::is_offline() {
+        return Ok(super::offline::queued_add_result(
+            &app,
+            QueuedMcpOperation::AddJson { name, json_config, scope },
+        ));
+    }
+
+    match execute_claude_mcp_command(&app, cmd_args).await {
         Ok(output) => {
             info!("Successfully added MCP server from JSON: {}", name);
+            record_activity(
+                &app,
+                NewActivity::new(ActivityKind::McpServerAdded, format!("MCP server {} added", name))
+                    .detail(serde_json::json!({ "scope": scope })),
+            );
             Ok(AddServerResult {
                 success: true,
                 message: output.trim().to_string(),
                 server_name: Some(name),
+                recommendation: None,
             })
         }
         Err(e) => {
@@ -993,6 +1477,7 @@ pub async fn mcp_add_json(
                 success: false,
                 message: e.to_string(),
                 server_name: None,
+                recommendation: None,
             })
         }
     }
@@ -1001,7 +1486,7 @@ pub async fn mcp_add_json(
 
 /// Starts Claude Code as an MCP server
 #[tauri::command]
-pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
+pub async fn mcp_serve(app: AppHandle) -> Result<String, OpcodeError> {
     info!("Starting Claude Code as MCP server");
 
     // Start the server in a separate process
@@ -1009,7 +1494,7 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
         Ok(path) => path,
         Err(e) => {
             error!("Failed to find claude binary: {}", e);
-            return Err(e.to_string());
+            return Err(e.into());
         }
     };
 
@@ -1023,53 +1508,52 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
         }
         Err(e) => {
             error!("Failed to start MCP server: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
 
 /// Tests connection to an MCP server
 #[tauri::command]
-pub async fn mcp_test_connection(app: AppHandle, name: String) -> Result<String, String> {
+pub async fn mcp_test_connection(app: AppHandle, name: String) -> Result<String, OpcodeError> {
     info!("Testing connection to MCP server: {}", name);
 
     // For now, we'll use the get command to test if the server exists
-    match execute_claude_mcp_command(&app, vec!["get".to_string(), name.clone()]) {
+    match execute_claude_mcp_command(&app, vec!["get".to_string(), name.clone()]).await {
         Ok(_) => Ok(format!("Connection to {} successful", name)),
-        Err(e) => Err(e.to_string()),
+        Err(e) => Err(e.into()),
     }
 }
 
 /// Resets project-scoped server approval choices
 #[tauri::command]
-pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<String, String> {
+pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<String, OpcodeError> {
     info!("Resetting MCP project choices");
 
-    match execute_claude_mcp_command(&app, vec!["reset-project-choices".to_string()]) {
+    match execute_claude_mcp_command(&app, vec!["reset-project-choices".to_string()]).await {
         Ok(output) => {
             info!("Successfully reset MCP project choices");
             Ok(output.trim().to_string())
         }
         Err(e) => {
             error!("Failed to reset project choices: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
 
 /// Gets the status of MCP servers
 #[tauri::command]
-pub async fn mcp_get_server_status() -> Result<HashMap<String, ServerStatus>, String> {
+pub async fn mcp_get_server_status() -> Result<HashMap<String, ServerStatus>, OpcodeError> {
     info!("Getting MCP server status");
 
-    // TODO: Implement actual status checking
-    // For now, return empty status
-    Ok(HashMap::new())
+    // Only SSE/HTTP servers are probed; stdio servers are checked by `claude mcp list`
+    Ok(probed_statuses())
 }
 
 /// Gets the MCP configuration file paths
 #[tauri::command]
-pub async fn mcp_get_config_paths(project_path: Option<String>) -> Result<MCPConfigPaths, String> {
+pub async fn mcp_get_config_paths(project_path: Option<String>) -> Result<MCPConfigPaths, OpcodeError> {
     info!("Getting MCP config paths");
 
     // Get home directory for user config
@@ -1102,28 +1586,41 @@ pub async fn mcp_get_config_paths(project_path: Option<String>) -> Result<MCPCon
 
 /// Reads .mcp.json from the current project
 #[tauri::command]
-pub async fn mcp_read_project_config(project_path: String) -> Result<MCPProjectConfig, String> {
+pub async fn mcp_read_project_config(project_path: String) -> Result<MCPProjectConfig, OpcodeError> {
+    let config = read_project_config(&SystemFs, &project_path)?;
+    super::config_merge::record_merge_base(&PathBuf::from(&project_path).join(".mcp.json"));
+    Ok(config)
+}
+
+/// Parse `<project>/.mcp.json`; a missing file is an empty config
+pub fn read_project_config(fs: &dyn FsProvider, project_path: &str) -> Result<MCPProjectConfig, OpcodeError> {
     info!("Reading .mcp.json from project: {}", project_path);
 
-    let mcp_json_path = PathBuf::from(&project_path).join(".mcp.json");
+    let mcp_json_path = PathBuf::from(project_path).join(".mcp.json");
 
-    if !mcp_json_path.exists() {
+    if !fs.exists(&mcp_json_path) {
         return Ok(MCPProjectConfig {
             mcp_servers: HashMap::new(),
         });
     }
 
-    match fs::read_to_string(&mcp_json_path) {
+    match fs.read_to_string(&mcp_json_path) {
         Ok(content) => match serde_json::from_str::<MCPProjectConfig>(&content) {
             Ok(config) => Ok(config),
             Err(e) => {
                 error!("Failed to parse .mcp.json: {}", e);
-                Err(format!("Failed to parse .mcp.json: {}", e))
+                Err(OpcodeError::localized(
+                    ErrorKind::Parse,
+                    Message::new(MessageCode::McpProjectConfigParseFailed).param("error", e),
+                ))
             }
         },
         Err(e) => {
             error!("Failed to read .mcp.json: {}", e);
-            Err(format!("Failed to read .mcp.json: {}", e))
+            Err(OpcodeError::localized(
+                ErrorKind::for_io(&e),
+                Message::new(MessageCode::McpProjectConfigReadFailed).param("error", e),
+            ))
         }
     }
 }
@@ -1141,21 +1638,30 @@ pub async fn mcp_update(
     url: Option<String>,
     scope: String,
     headers: HashMap<String, String>,
-) -> Result<AddServerResult, String> {
+) -> Result<AddServerResult, OpcodeError> {
     info!("Updating MCP server: {} -> {}", old_name, name);
 
+    // Offline: queue the removal; the add below queues itself right after it
+    if super::offline::is_offline() {
+        if let Err(e) = super::offline::queue_for_app(&app, QueuedMcpOperation::Remove { name: old_name }) {
+            return Ok(AddServerResult { success: false, message: e, server_name: None, recommendation: None });
+        }
+        return mcp_add(app, name, transport, command, args, env, url, scope, headers, None).await;
+    }
+
     // Step 1: 删除旧服务器
-    if let Err(e) = execute_claude_mcp_command(&app, vec!["remove".to_string(), old_name.clone()]) {
+    if let Err(e) = execute_claude_mcp_command(&app, vec!["remove".to_string(), old_name.clone()]).await {
         error!("Failed to remove old server: {}", e);
         return Ok(AddServerResult {
             success: false,
             message: format!("Failed to remove old server: {}", e),
             server_name: None,
+            recommendation: None,
         });
     }
 
     // Step 2: 添加新配置
-    mcp_add(app, name, transport, command, args, env, url, scope, headers).await
+    mcp_add(app, name, transport, command, args, env, url, scope, headers, None).await
 }
 
 /// Saves .mcp.json to the current project
@@ -1163,16 +1669,104 @@ pub async fn mcp_update(
 pub async fn mcp_save_project_config(
     project_path: String,
     config: MCPProjectConfig,
-) -> Result<String, String> {
+) -> Result<String, OpcodeError> {
+    let mcp_json_path = PathBuf::from(&project_path).join(".mcp.json");
+    super::config_snapshots::snapshot_config_file(&mcp_json_path, "save project MCP config")?;
+    let saved = save_project_config(&SystemFs, &project_path, &config)?;
+    super::config_merge::record_merge_base(&mcp_json_path);
+    Ok(saved)
+}
+
+/// Write `config` to `<project>/.mcp.json`
+pub fn save_project_config(
+    fs: &dyn FsProvider,
+    project_path: &str,
+    config: &MCPProjectConfig,
+) -> Result<String, OpcodeError> {
     info!("Saving .mcp.json to project: {}", project_path);
 
-    let mcp_json_path = PathBuf::from(&project_path).join(".mcp.json");
+    let mcp_json_path = PathBuf::from(project_path).join(".mcp.json");
 
-    let json_content = serde_json::to_string_pretty(&config)
+    let json_content = serde_json::to_string_pretty(config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
-    fs::write(&mcp_json_path, json_content)
-        .map_err(|e| format!("Failed to write .mcp.json: {}", e))?;
+    fs.write(&mcp_json_path, &json_content)
+        .map_err(|e| OpcodeError::new(ErrorKind::for_io(&e), format!("Failed to write .mcp.json: {}", e)))?;
 
     Ok("Project MCP configuration saved".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn windows_env(name: &str) -> Option<String> {
+        match name.to_ascii_uppercase().as_str() {
+            "APPDATA" => Some("C:\\Users\\dev\\AppData\\Roaming".to_string()),
+            "LOCALAPPDATA" => Some("C:\\Users\\dev\\AppData\\Local".to_string()),
+            "PROGRAMFILES" => Some("D:\\Apps".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_windows_commands_in_typical_node_locations_are_accepted() {
+        let accepted = [
+            ("D:\\Apps\\nodejs\\npx.cmd", "D:\\Apps\\nodejs\\npx.cmd"),
+            ("d:/apps/nodejs/node.exe", "d:\\apps\\nodejs\\node.exe"),
+            ("%ProgramFiles%\\nodejs\\npx.cmd", "D:\\Apps\\nodejs\\npx.cmd"),
+            (
+                "%ProgramFiles(x86)%\\nodejs\\node.exe",
+                "C:\\Program Files (x86)\\nodejs\\node.exe",
+            ),
+            (
+                "%APPDATA%\\npm\\npx.cmd",
+                "C:\\Users\\dev\\AppData\\Roaming\\npm\\npx.cmd",
+            ),
+            (
+                "\\\\?\\C:\\Windows\\System32\\wsl.exe",
+                "C:\\Windows\\System32\\wsl.exe",
+            ),
+        ];
+        for (cmd, resolved) in accepted {
+            assert_eq!(validate_windows_command(cmd, &windows_env).unwrap(), resolved, "{}", cmd);
+        }
+        // Bare names are resolved through PATH as before
+        assert_eq!(validate_command("npx.cmd").unwrap(), "npx.cmd");
+    }
+
+    #[test]
+    fn test_windows_commands_outside_allowed_dirs_are_rejected() {
+        let reject = |cmd: &str| validate_windows_command(cmd, &windows_env).unwrap_err();
+        assert!(matches!(reject("C:\\Users\\dev\\Downloads\\npx.cmd"), ValidationError::UnauthorizedPath(_)));
+        assert!(matches!(reject("\\\\fileserver\\tools\\node.exe"), ValidationError::UnauthorizedPath(_)));
+        assert!(matches!(reject("\\\\?\\UNC\\fileserver\\tools\\node.exe"), ValidationError::UnauthorizedPath(_)));
+        assert!(matches!(reject("D:\\Apps\\..\\Temp\\node.exe"), ValidationError::PathTraversal(_)));
+        assert!(matches!(reject("D:\\Apps\\nodejs\\npx.cmd & calc"), ValidationError::InvalidCharacters(..)));
+        assert!(matches!(reject("C:node.exe"), ValidationError::InvalidFormat(..)));
+        assert!(matches!(reject("%NODE_HOME%\\node.exe"), ValidationError::InvalidFormat(..)));
+        assert!(validate_command("C:\\Users\\dev\\npx.cmd").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wedged_cli_is_killed_on_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let claude = dir.path().join("claude");
+        std::fs::write(&claude, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&claude, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let started = Instant::now();
+        let error = spawn_claude_mcp_command_with_timeout(
+            claude.to_str().unwrap(),
+            &["list".to_string()],
+            &CancellationToken::new(),
+            Duration::from_millis(300),
+        )
+        .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(OpcodeError::from(error).kind, ErrorKind::Timeout);
+    }
+}
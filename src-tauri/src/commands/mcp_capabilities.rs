@@ -0,0 +1,707 @@
+#![allow(dead_code)]
+
+//! Probes MCP servers with the protocol's `initialize` handshake to learn what they
+//! actually report (name, version, capabilities, tools, resources, prompts), keeps the
+//! last report per server and flags changes between checks.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use super::activity::{record_activity, ActivityKind, NewActivity};
+use super::agents::AgentDb;
+use super::cancellation::{CancellationRegistry, CancellationToken, CANCELLED_MESSAGE};
+use super::event_broker::{publish, Channel};
+use super::tls_settings::configure_client;
+
+/// Emitted when a probe finds a server's report differs from the previous one
+pub const MCP_CAPABILITIES_CHANGED_EVENT: &str = "mcp:capabilities-changed";
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// What a server reported about itself during `initialize`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpCapabilityReport {
+    pub server: String,
+    /// `serverInfo.name`
+    pub server_name: Option<String>,
+    /// `serverInfo.version`
+    pub server_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// Declared capability keys, e.g. `tools`, `resources`, `prompts`, `logging`, `sampling`
+    pub capabilities: Vec<String>,
+    /// The raw `capabilities` object
+    pub capabilities_raw: Value,
+    pub tools: Vec<String>,
+    pub resources: Vec<String>,
+    pub prompts: Vec<String>,
+    pub checked_at: Option<String>,
+}
+
+/// Differences between two reports for the same server
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct McpCapabilityChanges {
+    pub server: String,
+    pub previous_version: Option<String>,
+    pub version: Option<String>,
+    pub capabilities_added: Vec<String>,
+    pub capabilities_removed: Vec<String>,
+    pub tools_added: Vec<String>,
+    pub tools_removed: Vec<String>,
+    pub resources_added: Vec<String>,
+    pub resources_removed: Vec<String>,
+    pub prompts_added: Vec<String>,
+    pub prompts_removed: Vec<String>,
+}
+
+impl McpCapabilityChanges {
+    pub fn is_empty(&self) -> bool {
+        self.previous_version == self.version
+            && self.capabilities_added.is_empty()
+            && self.capabilities_removed.is_empty()
+            && self.tools_added.is_empty()
+            && self.tools_removed.is_empty()
+            && self.resources_added.is_empty()
+            && self.resources_removed.is_empty()
+            && self.prompts_added.is_empty()
+            && self.prompts_removed.is_empty()
+    }
+
+    /// Short human-readable summary, e.g. "tools changed since last check"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.previous_version != self.version {
+            parts.push(format!(
+                "version {} → {}",
+                self.previous_version.as_deref().unwrap_or("unknown"),
+                self.version.as_deref().unwrap_or("unknown")
+            ));
+        }
+        for (label, added, removed) in [
+            ("capabilities", &self.capabilities_added, &self.capabilities_removed),
+            ("tools", &self.tools_added, &self.tools_removed),
+            ("resources", &self.resources_added, &self.resources_removed),
+            ("prompts", &self.prompts_added, &self.prompts_removed),
+        ] {
+            if !added.is_empty() || !removed.is_empty() {
+                parts.push(format!("{} changed since last check", label));
+            }
+        }
+        parts.join(", ")
+    }
+}
+
+/// Result of `mcp_probe_server`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpProbeResult {
+    pub report: McpCapabilityReport,
+    /// `None` on the first check, or when nothing changed
+    pub changes: Option<McpCapabilityChanges>,
+}
+
+pub fn init_mcp_capability_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_server_capabilities (
+            server TEXT PRIMARY KEY,
+            server_name TEXT,
+            server_version TEXT,
+            protocol_version TEXT,
+            capabilities TEXT NOT NULL DEFAULT '{}',
+            tools TEXT NOT NULL DEFAULT '[]',
+            resources TEXT NOT NULL DEFAULT '[]',
+            prompts TEXT NOT NULL DEFAULT '[]',
+            checked_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn capability_keys(capabilities: &Value) -> Vec<String> {
+    let mut keys: Vec<String> = capabilities
+        .as_object()
+        .map(|object| object.keys().cloned().collect())
+        .unwrap_or_default();
+    keys.sort();
+    keys
+}
+
+fn report_from_row(row: &rusqlite::Row) -> SqliteResult<McpCapabilityReport> {
+    let capabilities_raw: Value =
+        serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_else(|_| json!({}));
+    let list = |idx: usize| -> SqliteResult<Vec<String>> {
+        Ok(serde_json::from_str(&row.get::<_, String>(idx)?).unwrap_or_default())
+    };
+    Ok(McpCapabilityReport {
+        server: row.get(0)?,
+        server_name: row.get(1)?,
+        server_version: row.get(2)?,
+        protocol_version: row.get(3)?,
+        capabilities: capability_keys(&capabilities_raw),
+        capabilities_raw,
+        tools: list(5)?,
+        resources: list(6)?,
+        prompts: list(7)?,
+        checked_at: row.get(8)?,
+    })
+}
+
+const REPORT_COLUMNS: &str = "server, server_name, server_version, protocol_version, capabilities, tools, resources, prompts, checked_at";
+
+pub fn load_report(conn: &Connection, server: &str) -> SqliteResult<Option<McpCapabilityReport>> {
+    conn.query_row(
+        &format!("SELECT {} FROM mcp_server_capabilities WHERE server = ?1", REPORT_COLUMNS),
+        params![server],
+        report_from_row,
+    )
+    .optional()
+}
+
+pub fn save_report(conn: &Connection, report: &McpCapabilityReport) -> SqliteResult<()> {
+    let to_json = |value: &Vec<String>| serde_json::to_string(value).unwrap_or_else(|_| "[]".into());
+    conn.execute(
+        "INSERT INTO mcp_server_capabilities
+            (server, server_name, server_version, protocol_version, capabilities, tools, resources, prompts, checked_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CURRENT_TIMESTAMP)
+         ON CONFLICT(server) DO UPDATE SET
+            server_name = excluded.server_name,
+            server_version = excluded.server_version,
+            protocol_version = excluded.protocol_version,
+            capabilities = excluded.capabilities,
+            tools = excluded.tools,
+            resources = excluded.resources,
+            prompts = excluded.prompts,
+            checked_at = excluded.checked_at",
+        params![
+            report.server,
+            report.server_name,
+            report.server_version,
+            report.protocol_version,
+            report.capabilities_raw.to_string(),
+            to_json(&report.tools),
+            to_json(&report.resources),
+            to_json(&report.prompts),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Every stored report, by server name
+pub fn load_reports(conn: &Connection) -> SqliteResult<Vec<McpCapabilityReport>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM mcp_server_capabilities ORDER BY server ASC",
+        REPORT_COLUMNS
+    ))?;
+    let reports = stmt
+        .query_map([], report_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(reports)
+}
+
+/// Drop the stored report when a server is removed
+pub fn delete_report(conn: &Connection, server: &str) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM mcp_server_capabilities WHERE server = ?1",
+        params![server],
+    )?;
+    Ok(())
+}
+
+fn list_diff(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let old: BTreeSet<&String> = old.iter().collect();
+    let new: BTreeSet<&String> = new.iter().collect();
+    (
+        new.difference(&old).map(|s| s.to_string()).collect(),
+        old.difference(&new).map(|s| s.to_string()).collect(),
+    )
+}
+
+/// Compare a fresh report with the previous one
+pub fn diff_reports(old: &McpCapabilityReport, new: &McpCapabilityReport) -> McpCapabilityChanges {
+    let (capabilities_added, capabilities_removed) = list_diff(&old.capabilities, &new.capabilities);
+    let (tools_added, tools_removed) = list_diff(&old.tools, &new.tools);
+    let (resources_added, resources_removed) = list_diff(&old.resources, &new.resources);
+    let (prompts_added, prompts_removed) = list_diff(&old.prompts, &new.prompts);
+    McpCapabilityChanges {
+        server: new.server.clone(),
+        previous_version: old.server_version.clone(),
+        version: new.server_version.clone(),
+        capabilities_added,
+        capabilities_removed,
+        tools_added,
+        tools_removed,
+        resources_added,
+        resources_removed,
+        prompts_added,
+        prompts_removed,
+    }
+}
+
+/// Tool names from the last probe, in the `mcp__<server>__<tool>` form Claude Code uses
+pub fn stored_tool_names(app: &AppHandle, server: &str) -> Option<Vec<String>> {
+    let db = app.try_state::<AgentDb>()?;
+    let conn = db.0.lock().ok()?;
+    let report = load_report(&conn, server).ok()??;
+    if report.tools.is_empty() {
+        return None;
+    }
+    Some(
+        report
+            .tools
+            .iter()
+            .map(|tool| format!("mcp__{}__{}", server, tool))
+            .collect(),
+    )
+}
+
+/// Find a server's configuration: local (per-project in `~/.claude.json`), then project
+/// (`.mcp.json`), then user scope
+pub fn find_server_config(name: &str, project_path: Option<&str>) -> Option<Value> {
+    let claude_json: Option<Value> = dirs::home_dir()
+        .map(|home| home.join(".claude.json"))
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    if let (Some(config), Some(project)) = (&claude_json, project_path) {
+        if let Some(server) = config
+            .get("projects")
+            .and_then(|projects| projects.get(project))
+            .and_then(|project| project.get("mcpServers"))
+            .and_then(|servers| servers.get(name))
+        {
+            return Some(server.clone());
+        }
+    }
+    if let Some(project) = project_path {
+        let mcp_json = PathBuf::from(project).join(".mcp.json");
+        if let Some(server) = fs::read_to_string(mcp_json)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|config| config.get("mcpServers")?.get(name).cloned())
+        {
+            return Some(server);
+        }
+    }
+    claude_json.and_then(|config| config.get("mcpServers")?.get(name).cloned())
+}
+
+fn string_map(value: Option<&Value>) -> HashMap<String, String> {
+    value
+        .and_then(|v| v.as_object())
+        .map(|object| {
+            object
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn initialize_request() -> Value {
+    request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "opcode", "version": env!("CARGO_PKG_VERSION") }
+        }),
+    )
+}
+
+/// The `result` of a JSON-RPC response, or its error message
+fn response_result(response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        return Err(error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown error")
+            .to_string());
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+/// Find the response to request `id` in a Streamable HTTP body, which is either
+/// plain JSON or a server-sent event stream with `data:` lines
+pub fn parse_http_response(body: &str, id: u64) -> Option<Value> {
+    let matches = |value: &Value| value.get("id").and_then(|v| v.as_u64()) == Some(id);
+    if let Ok(value) = serde_json::from_str::<Value>(body.trim()) {
+        return match value {
+            Value::Array(items) => items.into_iter().find(|item| matches(item)),
+            value if matches(&value) => Some(value),
+            _ => None,
+        };
+    }
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .find(|value| matches(value))
+}
+
+/// Names from a `tools/list`, `resources/list` or `prompts/list` result
+fn item_names(result: &Value, key: &str, field: &str) -> Vec<String> {
+    result
+        .get(key)
+        .and_then(|items| items.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.get(field)?.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Build a report from the `initialize` result and a lister for the declared capabilities
+fn build_report(
+    server: &str,
+    init: Value,
+    mut list: impl FnMut(u64, &str) -> Result<Value, String>,
+) -> McpCapabilityReport {
+    let capabilities_raw = init.get("capabilities").cloned().unwrap_or_else(|| json!({}));
+    let declares = |key: &str| capabilities_raw.get(key).is_some();
+    let mut names = |id: u64, method: &str, key: &str, field: &str| -> Vec<String> {
+        match list(id, method) {
+            Ok(result) => item_names(&result, key, field),
+            Err(e) => {
+                log::warn!("{} failed for MCP server {}: {}", method, server, e);
+                Vec::new()
+            }
+        }
+    };
+    let tools = if declares("tools") { names(2, "tools/list", "tools", "name") } else { Vec::new() };
+    let resources = if declares("resources") {
+        names(3, "resources/list", "resources", "uri")
+    } else {
+        Vec::new()
+    };
+    let prompts = if declares("prompts") { names(4, "prompts/list", "prompts", "name") } else { Vec::new() };
+
+    let info = init.get("serverInfo");
+    let field = |value: Option<&Value>| value.and_then(|v| v.as_str()).map(|s| s.to_string());
+    McpCapabilityReport {
+        server: server.to_string(),
+        server_name: field(info.and_then(|i| i.get("name"))),
+        server_version: field(info.and_then(|i| i.get("version"))),
+        protocol_version: field(init.get("protocolVersion")),
+        capabilities: capability_keys(&capabilities_raw),
+        capabilities_raw,
+        tools,
+        resources,
+        prompts,
+        checked_at: None,
+    }
+}
+
+/// Probe a stdio server: spawn it, handshake over newline-delimited JSON-RPC, then kill it
+pub fn probe_stdio(
+    server: &str,
+    command: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+    timeout: Duration,
+    token: &CancellationToken,
+) -> Result<McpCapabilityReport, String> {
+    let mut cmd = crate::claude_binary::create_command_with_env(command);
+    cmd.args(args)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", command, e))?;
+    let mut stdin = child.stdin.take().ok_or("Server stdin unavailable")?;
+    let stdout = child.stdout.take().ok_or("Server stdout unavailable")?;
+
+    // Lines are read on a thread so every wait can be bounded by the deadline
+    let (tx, rx) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = Instant::now() + timeout;
+    let mut call = |message: Value, id: Option<u64>| -> Result<Value, String> {
+        writeln!(stdin, "{}", message).map_err(|e| format!("Failed to write to server: {}", e))?;
+        stdin.flush().map_err(|e| e.to_string())?;
+        let Some(id) = id else {
+            return Ok(Value::Null);
+        };
+        loop {
+            token.check()?;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err("Timed out waiting for the server to respond".to_string());
+            }
+            // Wake up regularly to notice cancellation
+            let line = match rx.recv_timeout(remaining.min(Duration::from_millis(100))) {
+                Ok(line) => line,
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err("Server exited before responding".to_string())
+                }
+            };
+            // Servers may interleave notifications and log lines
+            let Ok(value) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if value.get("id").and_then(|v| v.as_u64()) == Some(id) {
+                return response_result(value);
+            }
+        }
+    };
+
+    let result = call(initialize_request(), Some(1)).and_then(|init| {
+        call(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }), None)?;
+        Ok(build_report(server, init, |id, method| {
+            call(request(id, method, json!({})), Some(id))
+        }))
+    });
+    let _ = child.kill();
+    let _ = child.wait();
+    result
+}
+
+/// Probe a Streamable HTTP server
+pub async fn probe_http(
+    server: &str,
+    url: &str,
+    headers: &HashMap<String, String>,
+    timeout: Duration,
+) -> Result<McpCapabilityReport, String> {
+    let client = configure_client(reqwest::Client::builder(), Some(server))
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut session_id: Option<String> = None;
+
+    async fn post(
+        client: &reqwest::Client,
+        url: &str,
+        headers: &HashMap<String, String>,
+        session_id: &mut Option<String>,
+        message: Value,
+    ) -> Result<Option<Value>, String> {
+        let id = message.get("id").and_then(|v| v.as_u64());
+        let mut request = client
+            .post(url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(&message);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        if let Some(session) = session_id.as_deref() {
+            request = request.header("Mcp-Session-Id", session);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Server returned {}", response.status()));
+        }
+        if let Some(session) = response.headers().get("mcp-session-id") {
+            *session_id = session.to_str().ok().map(|s| s.to_string());
+        }
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        match id {
+            Some(id) => parse_http_response(&body, id)
+                .map(Some)
+                .ok_or_else(|| "No response in server reply".to_string()),
+            None => Ok(None),
+        }
+    }
+
+    let init = post(&client, url, headers, &mut session_id, initialize_request())
+        .await?
+        .map(response_result)
+        .unwrap_or(Ok(Value::Null))?;
+    post(
+        &client,
+        url,
+        headers,
+        &mut session_id,
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .await?;
+
+    let mut listed: HashMap<u64, Value> = HashMap::new();
+    for (id, method, key) in [(2, "tools/list", "tools"), (3, "resources/list", "resources"), (4, "prompts/list", "prompts")] {
+        if init.get("capabilities").and_then(|c| c.get(key)).is_none() {
+            continue;
+        }
+        match post(&client, url, headers, &mut session_id, request(id, method, json!({}))).await {
+            Ok(Some(response)) => {
+                if let Ok(result) = response_result(response) {
+                    listed.insert(id, result);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("{} failed for MCP server {}: {}", method, server, e),
+        }
+    }
+    Ok(build_report(server, init, |id, _| {
+        listed.remove(&id).ok_or_else(|| "not listed".to_string())
+    }))
+}
+
+/// Probe a configured server by name
+async fn probe_server(
+    name: &str,
+    project_path: Option<&str>,
+    token: &CancellationToken,
+) -> Result<McpCapabilityReport, String> {
+    let config = find_server_config(name, project_path)
+        .ok_or_else(|| format!("MCP server {} not found in configuration", name))?;
+    let transport = config.get("type").and_then(|t| t.as_str()).unwrap_or("stdio");
+    match transport {
+        "stdio" => {
+            let command = config
+                .get("command")
+                .and_then(|c| c.as_str())
+                .ok_or("Server has no command")?
+                .to_string();
+            let args: Vec<String> = config
+                .get("args")
+                .and_then(|a| a.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default();
+            let env = string_map(config.get("env"));
+            let server = name.to_string();
+            let token = token.clone();
+            tokio::task::spawn_blocking(move || {
+                probe_stdio(&server, &command, &args, &env, PROBE_TIMEOUT, &token)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        }
+        "http" => {
+            let url = config.get("url").and_then(|u| u.as_str()).ok_or("Server has no URL")?;
+            let headers = string_map(config.get("headers"));
+            // Dropping the request future aborts the connection
+            tokio::select! {
+                report = probe_http(name, url, &headers, PROBE_TIMEOUT) => report,
+                _ = token.cancelled() => Err(CANCELLED_MESSAGE.to_string()),
+            }
+        }
+        other => Err(format!("Probing {} servers is not supported", other)),
+    }
+}
+
+/// Connect to an MCP server, record what it reports and flag changes since the last check.
+/// Pass `operation_id` to make it cancellable through `cancel_operation`.
+#[tauri::command]
+pub async fn mcp_probe_server(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    name: String,
+    project_path: Option<String>,
+    operation_id: Option<String>,
+) -> Result<McpProbeResult, String> {
+    log::info!("Probing MCP server {}", name);
+    let operation = CancellationRegistry::global().register(operation_id);
+    let report = probe_server(&name, project_path.as_deref(), operation.token()).await?;
+
+    let (report, changes) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let previous = load_report(&conn, &name).map_err(|e| e.to_string())?;
+        save_report(&conn, &report).map_err(|e| e.to_string())?;
+        let changes = previous
+            .map(|previous| diff_reports(&previous, &report))
+            .filter(|changes| !changes.is_empty());
+        let report = load_report(&conn, &name)
+            .map_err(|e| e.to_string())?
+            .unwrap_or(report);
+        (report, changes)
+    };
+
+    if let Some(changes) = &changes {
+        log::info!("MCP server {}: {}", name, changes.summary());
+        if let Err(e) = publish(&app, Channel::McpStatus, MCP_CAPABILITIES_CHANGED_EVENT, changes) {
+            log::warn!("Failed to emit capability change: {}", e);
+        }
+        let mut activity = NewActivity::new(
+            ActivityKind::McpCapabilitiesChanged,
+            format!("MCP server {}: {}", name, changes.summary()),
+        )
+        .detail(serde_json::to_value(changes).unwrap_or(Value::Null));
+        if let Some(project) = &project_path {
+            activity = activity.project(project.clone());
+        }
+        record_activity(&app, activity);
+    }
+    Ok(McpProbeResult { report, changes })
+}
+
+/// Last recorded report for every probed server
+#[tauri::command]
+pub async fn mcp_get_capability_reports(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<McpCapabilityReport>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_reports(&conn).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_and_persistence() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_mcp_capability_tables(&conn).unwrap();
+
+        let init = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "serverInfo": { "name": "files", "version": "1.0.0" },
+            "capabilities": { "tools": {}, "logging": {} }
+        });
+        let old = build_report("files", init, |_, _| Ok(json!({ "tools": [{ "name": "read" }, { "name": "write" }] })));
+        save_report(&conn, &old).unwrap();
+        let stored = load_report(&conn, "files").unwrap().unwrap();
+        assert_eq!(stored.tools, vec!["read", "write"]);
+        assert_eq!(stored.capabilities, vec!["logging", "tools"]);
+        assert_eq!(stored.server_version.as_deref(), Some("1.0.0"));
+
+        let mut new = stored.clone();
+        new.server_version = Some("1.1.0".to_string());
+        new.tools = vec!["read".to_string(), "search".to_string()];
+        let changes = diff_reports(&stored, &new);
+        assert_eq!(changes.tools_added, vec!["search"]);
+        assert_eq!(changes.tools_removed, vec!["write"]);
+        assert!(changes.capabilities_added.is_empty());
+        assert_eq!(
+            changes.summary(),
+            "version 1.0.0 → 1.1.0, tools changed since last check"
+        );
+        assert!(diff_reports(&stored, &stored).is_empty());
+    }
+
+    #[test]
+    fn test_parse_http_response_json_and_sse() {
+        let json_body = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2024-11-05"}}"#;
+        assert_eq!(
+            parse_http_response(json_body, 1).unwrap()["result"]["protocolVersion"],
+            "2024-11-05"
+        );
+        assert!(parse_http_response(json_body, 2).is_none());
+
+        let sse_body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\nevent: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"tools\":[]}}\n\n";
+        let response = parse_http_response(sse_body, 2).unwrap();
+        assert_eq!(response_result(response).unwrap(), json!({ "tools": [] }));
+    }
+}
@@ -0,0 +1,294 @@
+#![allow(dead_code)]
+
+//! Watches the MCP config files (`~/.claude.json`, `.claude/settings.local.json` and
+//! `.mcp.json`) for edits made outside opcode and reports which servers changed.
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, State};
+
+use super::event_broker::{publish, Channel};
+
+/// Emitted when the servers configured in a watched file change
+pub const MCP_CONFIG_CHANGED_EVENT: &str = "mcp:config-changed";
+
+/// A config file being watched and the scopes it holds servers for
+#[derive(Debug, Clone)]
+pub(crate) struct WatchedConfig {
+    pub path: PathBuf,
+    pub scope: &'static str,
+}
+
+/// Running watcher; dropping it stops the watch and its event thread
+pub struct McpConfigWatcher {
+    _watcher: RecommendedWatcher,
+    project_path: Option<String>,
+}
+
+/// State holding the active MCP config watcher, if any
+#[derive(Default)]
+pub struct McpConfigWatcherState(pub Mutex<Option<McpConfigWatcher>>);
+
+/// Payload of `mcp:config-changed`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct McpConfigChange {
+    /// `user`, `local` or `project`
+    pub scope: String,
+    pub path: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Set when the file no longer parses; the previous server list is kept
+    pub parse_error: Option<String>,
+}
+
+impl McpConfigChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.parse_error.is_none()
+    }
+}
+
+type ServerMap = BTreeMap<String, Value>;
+
+fn server_map(value: Option<&Value>) -> ServerMap {
+    value
+        .and_then(|servers| servers.as_object())
+        .map(|servers| servers.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// Servers per scope in a config file. `~/.claude.json` holds user servers at the top
+/// level and local servers under `projects.<path>`.
+pub fn parse_servers(
+    content: &str,
+    scope: &str,
+    project_path: Option<&str>,
+) -> Result<BTreeMap<String, ServerMap>, String> {
+    let mut scopes = BTreeMap::new();
+    if content.trim().is_empty() {
+        scopes.insert(scope.to_string(), ServerMap::new());
+        return Ok(scopes);
+    }
+    let config: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    scopes.insert(scope.to_string(), server_map(config.get("mcpServers")));
+    if scope == "user" {
+        if let Some(project) = project_path {
+            let local = config
+                .get("projects")
+                .and_then(|projects| projects.get(project))
+                .and_then(|project| project.get("mcpServers"));
+            scopes.insert("local".to_string(), server_map(local));
+        }
+    }
+    Ok(scopes)
+}
+
+/// Compare two server maps by name and configuration
+pub fn diff_servers(scope: &str, path: &Path, old: &ServerMap, new: &ServerMap) -> McpConfigChange {
+    let old_names: BTreeSet<&String> = old.keys().collect();
+    let new_names: BTreeSet<&String> = new.keys().collect();
+    McpConfigChange {
+        scope: scope.to_string(),
+        path: path.to_string_lossy().to_string(),
+        added: new_names.difference(&old_names).map(|s| s.to_string()).collect(),
+        removed: old_names.difference(&new_names).map(|s| s.to_string()).collect(),
+        changed: new
+            .iter()
+            .filter(|(name, config)| old.get(*name).is_some_and(|old| old != *config))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        parse_error: None,
+    }
+}
+
+/// Last parsed servers per file and scope
+#[derive(Default)]
+pub struct ConfigState {
+    servers: HashMap<(PathBuf, String), ServerMap>,
+}
+
+impl ConfigState {
+    /// Re-read `path` and return what changed since the last read
+    pub fn reload(&mut self, path: &Path, scope: &str, project_path: Option<&str>) -> Vec<McpConfigChange> {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let scopes = match parse_servers(&content, scope, project_path) {
+            Ok(scopes) => scopes,
+            // Editors often save partially written files; report it and wait for the next save
+            Err(e) => {
+                return vec![McpConfigChange {
+                    scope: scope.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    parse_error: Some(e),
+                    ..Default::default()
+                }]
+            }
+        };
+        let mut changes = Vec::new();
+        for (scope, servers) in scopes {
+            let key = (path.to_path_buf(), scope.clone());
+            let old = self.servers.get(&key).cloned().unwrap_or_default();
+            let change = diff_servers(&scope, path, &old, &servers);
+            if !change.is_empty() {
+                changes.push(change);
+            }
+            self.servers.insert(key, servers);
+        }
+        changes
+    }
+}
+
+pub(crate) fn watched_configs(project_path: Option<&str>) -> Vec<WatchedConfig> {
+    let mut configs = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        configs.push(WatchedConfig { path: home.join(".claude.json"), scope: "user" });
+    }
+    if let Some(project) = project_path {
+        let project = PathBuf::from(project);
+        configs.push(WatchedConfig {
+            path: project.join(".claude").join("settings.local.json"),
+            scope: "local",
+        });
+        configs.push(WatchedConfig { path: project.join(".mcp.json"), scope: "project" });
+    }
+    configs
+}
+
+fn start_watcher(app: AppHandle, project_path: Option<String>) -> Result<McpConfigWatcher, String> {
+    let configs = watched_configs(project_path.as_deref());
+    let mut state = ConfigState::default();
+    for config in &configs {
+        state.reload(&config.path, config.scope, project_path.as_deref());
+    }
+
+    let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to create MCP config watcher: {}", e))?;
+    // Parent directories are watched so files created later, or replaced by a rename, are seen
+    let mut dirs: Vec<PathBuf> = configs
+        .iter()
+        .filter_map(|config| config.path.parent().map(|p| p.to_path_buf()))
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in &dirs {
+        if dir.is_dir() {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch {}: {}", dir.display(), e);
+            }
+        }
+    }
+
+    let project = project_path.clone();
+    std::thread::spawn(move || {
+        for event in rx {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("MCP config watcher error: {}", e);
+                    continue;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                continue;
+            }
+            for config in configs.iter().filter(|config| event.paths.contains(&config.path)) {
+                for change in state.reload(&config.path, config.scope, project.as_deref()) {
+                    log::info!(
+                        "MCP config {} changed: +{:?} -{:?} ~{:?}",
+                        change.path,
+                        change.added,
+                        change.removed,
+                        change.changed
+                    );
+                    if let Err(e) = publish(&app, Channel::McpStatus, MCP_CONFIG_CHANGED_EVENT, &change) {
+                        log::warn!("Failed to emit MCP config change: {}", e);
+                    }
+                }
+            }
+        }
+        log::info!("MCP config watcher stopped");
+    });
+
+    Ok(McpConfigWatcher {
+        _watcher: watcher,
+        project_path,
+    })
+}
+
+/// Watch the MCP config files, including the project ones when `project_path` is given.
+/// Calling it again with another project moves the watch.
+#[tauri::command]
+pub async fn start_mcp_config_watcher(
+    app: AppHandle,
+    state: State<'_, McpConfigWatcherState>,
+    project_path: Option<String>,
+) -> Result<(), String> {
+    let mut watcher = state.0.lock().map_err(|e| e.to_string())?;
+    if watcher
+        .as_ref()
+        .is_some_and(|watcher| watcher.project_path == project_path)
+    {
+        return Ok(());
+    }
+    *watcher = Some(start_watcher(app, project_path)?);
+    Ok(())
+}
+
+/// Stop watching the MCP config files
+#[tauri::command]
+pub async fn stop_mcp_config_watcher(state: State<'_, McpConfigWatcherState>) -> Result<(), String> {
+    state.0.lock().map_err(|e| e.to_string())?.take();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_servers_reads_user_and_local_scopes() {
+        let content = r#"{
+            "mcpServers": {"fs": {"command": "mcp-fs"}},
+            "projects": {"/work/app": {"mcpServers": {"db": {"command": "mcp-db"}}}}
+        }"#;
+        let scopes = parse_servers(content, "user", Some("/work/app")).unwrap();
+        assert_eq!(scopes["user"].keys().collect::<Vec<_>>(), vec!["fs"]);
+        assert_eq!(scopes["local"].keys().collect::<Vec<_>>(), vec!["db"]);
+
+        let scopes = parse_servers("", "project", None).unwrap();
+        assert!(scopes["project"].is_empty());
+        assert!(parse_servers("{ not json", "project", None).is_err());
+    }
+
+    #[test]
+    fn test_reload_reports_added_removed_and_changed_servers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        fs::write(&path, r#"{"mcpServers": {"a": {"command": "x"}, "b": {"command": "y"}}}"#).unwrap();
+
+        let mut state = ConfigState::default();
+        let initial = state.reload(&path, "project", None);
+        assert_eq!(initial[0].added, vec!["a", "b"]);
+
+        fs::write(&path, r#"{"mcpServers": {"a": {"command": "z"}, "c": {"command": "y"}}}"#).unwrap();
+        let changes = state.reload(&path, "project", None);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].added, vec!["c"]);
+        assert_eq!(changes[0].removed, vec!["b"]);
+        assert_eq!(changes[0].changed, vec!["a"]);
+
+        assert!(state.reload(&path, "project", None).is_empty());
+
+        fs::write(&path, "{").unwrap();
+        let changes = state.reload(&path, "project", None);
+        assert!(changes[0].parse_error.is_some());
+    }
+}
@@ -0,0 +1,601 @@
+#![allow(dead_code)]
+
+//! Resource guardrails for stdio MCP servers. The CLI starts a run's stdio servers as child
+//! processes, so every server of a run opcode supervises sits somewhere below the run's
+//! process. A monitoring loop samples the process table, finds each server with configured
+//! limits by its command line, and kills a server (with everything it started) once its
+//! memory, CPU time or runtime goes over a limit. Every kill is recorded and announced with
+//! an `mcp:limit-exceeded` event, so a runaway indexer shows up instead of eating the machine.
+
+use log::{info, warn};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::activity::{record_activity, ActivityKind, NewActivity};
+use super::agents::AgentDb;
+use super::mcp_capabilities::find_server_config;
+use crate::process::ProcessRegistryState;
+
+/// How often running servers are sampled
+const GUARDRAIL_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Violations returned when no limit is given
+const DEFAULT_VIOLATION_LIMIT: i64 = 100;
+
+/// Limits of one server; `None` leaves that resource unlimited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerLimits {
+    pub server: String,
+    /// Resident memory of the server and the processes it started
+    pub max_rss_mb: Option<u64>,
+    /// CPU time used by the server and the processes it started
+    pub max_cpu_secs: Option<u64>,
+    /// Wall-clock time since the server started
+    pub max_runtime_secs: Option<u64>,
+}
+
+impl McpServerLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_rss_mb.is_none() && self.max_cpu_secs.is_none() && self.max_runtime_secs.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.server.trim().is_empty() {
+            return Err("Server name cannot be empty".to_string());
+        }
+        if [self.max_rss_mb, self.max_cpu_secs, self.max_runtime_secs].contains(&Some(0)) {
+            return Err("Limits must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// The first limit `tree` is over, given the server's process first
+    fn exceeded_by(&self, tree: &[&ProcessSample]) -> Option<(LimitKind, u64, u64)> {
+        let rss_mb = tree.iter().map(|process| process.rss_kb).sum::<u64>() / 1024;
+        let cpu_secs = tree.iter().map(|process| process.cpu_secs).sum::<u64>();
+        let runtime_secs = tree
+            .first()
+            .map(|process| process.elapsed_secs)
+            .unwrap_or(0);
+        [
+            (LimitKind::Rss, rss_mb, self.max_rss_mb),
+            (LimitKind::Cpu, cpu_secs, self.max_cpu_secs),
+            (LimitKind::Runtime, runtime_secs, self.max_runtime_secs),
+        ]
+        .into_iter()
+        .find_map(|(kind, observed, max)| {
+            max.filter(|max| observed > *max)
+                .map(|max| (kind, observed, max))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    Rss,
+    Cpu,
+    Runtime,
+}
+
+impl LimitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitKind::Rss => "rss",
+            LimitKind::Cpu => "cpu",
+            LimitKind::Runtime => "runtime",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            LimitKind::Rss => "MB",
+            LimitKind::Cpu => "CPU seconds",
+            LimitKind::Runtime => "seconds",
+        }
+    }
+}
+
+/// A server killed for going over a limit; also the payload of `mcp:limit-exceeded`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LimitViolation {
+    pub id: Option<i64>,
+    pub server: String,
+    /// Run whose process tree the server belonged to
+    pub run_id: i64,
+    pub pid: u32,
+    pub limit: LimitKind,
+    pub observed: u64,
+    pub max: u64,
+    pub killed: bool,
+    pub created_at: Option<String>,
+}
+
+/// One process as sampled from the process table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSample {
+    pub pid: u32,
+    pub ppid: u32,
+    pub rss_kb: u64,
+    pub cpu_secs: u64,
+    pub elapsed_secs: u64,
+    pub command_line: String,
+}
+
+/// Create the limit and violation tables
+pub fn init_mcp_guardrail_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_server_limits (
+            server TEXT PRIMARY KEY,
+            max_rss_mb INTEGER,
+            max_cpu_secs INTEGER,
+            max_runtime_secs INTEGER,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_limit_violations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            server TEXT NOT NULL,
+            run_id INTEGER NOT NULL,
+            pid INTEGER NOT NULL,
+            limit_kind TEXT NOT NULL,
+            observed INTEGER NOT NULL,
+            max INTEGER NOT NULL,
+            killed INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn load_limits(conn: &Connection) -> SqliteResult<Vec<McpServerLimits>> {
+    let mut stmt = conn.prepare(
+        "SELECT server, max_rss_mb, max_cpu_secs, max_runtime_secs FROM mcp_server_limits ORDER BY server",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(McpServerLimits {
+            server: row.get(0)?,
+            max_rss_mb: row.get::<_, Option<i64>>(1)?.map(|v| v as u64),
+            max_cpu_secs: row.get::<_, Option<i64>>(2)?.map(|v| v as u64),
+            max_runtime_secs: row.get::<_, Option<i64>>(3)?.map(|v| v as u64),
+        })
+    })?;
+    rows.collect()
+}
+
+/// Save a server's limits; limits with nothing set are removed
+pub fn save_limits(conn: &Connection, limits: &McpServerLimits) -> Result<(), String> {
+    limits.validate()?;
+    if limits.is_empty() {
+        conn.execute(
+            "DELETE FROM mcp_server_limits WHERE server = ?1",
+            params![limits.server],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO mcp_server_limits (server, max_rss_mb, max_cpu_secs, max_runtime_secs)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(server) DO UPDATE SET max_rss_mb = ?2, max_cpu_secs = ?3, max_runtime_secs = ?4, updated_at = CURRENT_TIMESTAMP",
+        params![
+            limits.server,
+            limits.max_rss_mb.map(|v| v as i64),
+            limits.max_cpu_secs.map(|v| v as i64),
+            limits.max_runtime_secs.map(|v| v as i64)
+        ],
+    )
+    .map_err(|e| format!("Failed to save MCP server limits: {}", e))?;
+    Ok(())
+}
+
+fn record_violation(conn: &Connection, violation: &LimitViolation) -> SqliteResult<i64> {
+    conn.execute(
+        "INSERT INTO mcp_limit_violations (server, run_id, pid, limit_kind, observed, max, killed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            violation.server,
+            violation.run_id,
+            violation.pid,
+            violation.limit.as_str(),
+            violation.observed as i64,
+            violation.max as i64,
+            violation.killed
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn load_violations(conn: &Connection, limit: i64) -> SqliteResult<Vec<LimitViolation>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, server, run_id, pid, limit_kind, observed, max, killed, created_at
+         FROM mcp_limit_violations ORDER BY id DESC LIMIT ?1",
+    )?;
+    let rows = stmt.query_map(params![limit], |row| {
+        let kind: String = row.get(4)?;
+        Ok(LimitViolation {
+            id: Some(row.get(0)?),
+            server: row.get(1)?,
+            run_id: row.get(2)?,
+            pid: row.get(3)?,
+            limit: serde_json::from_value(Value::String(kind)).unwrap_or(LimitKind::Rss),
+            observed: row.get::<_, i64>(5)? as u64,
+            max: row.get::<_, i64>(6)? as u64,
+            killed: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Seconds of a `ps` time column: `[[DD-]HH:]MM:SS[.ff]`
+fn parse_ps_duration(value: &str) -> Option<u64> {
+    let (days, clock) = match value.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, value),
+    };
+    let mut secs = 0.0;
+    for part in clock.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(days * 86_400 + secs as u64)
+}
+
+/// Parse the output of `ps -A -o pid=,ppid=,rss=,time=,etime=,args=`
+pub fn parse_ps_output(output: &str) -> Vec<ProcessSample> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let rss_kb = fields.next()?.parse().ok()?;
+            let cpu_secs = parse_ps_duration(fields.next()?)?;
+            let elapsed_secs = parse_ps_duration(fields.next()?)?;
+            Some(ProcessSample {
+                pid,
+                ppid,
+                rss_kb,
+                cpu_secs,
+                elapsed_secs,
+                command_line: fields.collect::<Vec<_>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn sample_processes() -> Result<Vec<ProcessSample>, String> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,rss=,time=,etime=,args="])
+        .output()
+        .map_err(|e| format!("Failed to run ps: {}", e))?;
+    if !output.status.success() {
+        return Err("ps failed".to_string());
+    }
+    Ok(parse_ps_output(&crate::decoding::decode_command_output(
+        &output.stdout,
+    )))
+}
+
+/// Not supported on Windows yet; no servers are found, so nothing is killed
+#[cfg(not(unix))]
+fn sample_processes() -> Result<Vec<ProcessSample>, String> {
+    Ok(Vec::new())
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Whether a process was started as `command args...`. Launchers that are scripts show up
+/// behind their interpreter (`node /usr/bin/npx ...`), so the command may be the second word.
+pub fn matches_server(command_line: &str, command: &str, args: &[String]) -> bool {
+    let command = file_name(command);
+    let launched = command_line
+        .split_whitespace()
+        .take(2)
+        .any(|word| file_name(word) == command);
+    launched && (args.is_empty() || command_line.contains(&args.join(" ")))
+}
+
+/// The stdio servers below `root_pid`, each with its process first and then everything it
+/// started. A server's own children are never taken for another server.
+pub fn find_server_trees<'a>(
+    samples: &'a [ProcessSample],
+    root_pid: u32,
+    servers: &[(String, String, Vec<String>)],
+) -> Vec<(String, Vec<&'a ProcessSample>)> {
+    let mut children: HashMap<u32, Vec<&ProcessSample>> = HashMap::new();
+    for sample in samples {
+        children.entry(sample.ppid).or_default().push(sample);
+    }
+    let subtree = |pid: u32| {
+        let mut tree = Vec::new();
+        let mut pending = vec![pid];
+        let mut seen = HashSet::new();
+        while let Some(pid) = pending.pop() {
+            for child in children.get(&pid).into_iter().flatten() {
+                if seen.insert(child.pid) {
+                    tree.push(*child);
+                    pending.push(child.pid);
+                }
+            }
+        }
+        tree
+    };
+
+    let mut found = Vec::new();
+    let mut pending = vec![root_pid];
+    while let Some(pid) = pending.pop() {
+        for child in children.get(&pid).into_iter().flatten() {
+            let server = servers
+                .iter()
+                .find(|(_, command, args)| matches_server(&child.command_line, command, args));
+            match server {
+                Some((name, _, _)) => {
+                    let mut tree = vec![*child];
+                    tree.extend(subtree(child.pid));
+                    found.push((name.clone(), tree));
+                }
+                None => pending.push(child.pid),
+            }
+        }
+    }
+    found
+}
+
+#[cfg(unix)]
+fn kill_tree(tree: &[&ProcessSample]) -> bool {
+    let mut killed = true;
+    for process in tree {
+        let Ok(pid) = libc::pid_t::try_from(process.pid) else {
+            continue;
+        };
+        if unsafe { libc::kill(pid, libc::SIGKILL) } != 0 {
+            killed = false;
+        }
+    }
+    killed
+}
+
+#[cfg(not(unix))]
+fn kill_tree(_tree: &[&ProcessSample]) -> bool {
+    false
+}
+
+/// Command and arguments of a stdio server, as configured for `project_path`
+fn stdio_command(server: &str, project_path: &str) -> Option<(String, Vec<String>)> {
+    let config = find_server_config(server, Some(project_path))?;
+    let transport = config
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("stdio");
+    if transport != "stdio" {
+        return None;
+    }
+    let command = config.get("command")?.as_str()?.to_string();
+    let args = config
+        .get("args")
+        .and_then(Value::as_array)
+        .map(|args| {
+            args.iter()
+                .filter_map(|arg| arg.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((command, args))
+}
+
+/// Sample the servers of every running process and kill those over their limits
+fn enforce_limits(app: &AppHandle) -> Result<(), String> {
+    let limits = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_limits(&conn).map_err(|e| e.to_string())?
+    };
+    if limits.is_empty() {
+        return Ok(());
+    }
+    let processes = app
+        .state::<ProcessRegistryState>()
+        .0
+        .get_running_processes()?;
+    if processes.is_empty() {
+        return Ok(());
+    }
+    let samples = sample_processes()?;
+
+    for process in processes {
+        let servers: Vec<(String, String, Vec<String>)> = limits
+            .iter()
+            .filter_map(|limits| {
+                stdio_command(&limits.server, &process.project_path)
+                    .map(|(command, args)| (limits.server.clone(), command, args))
+            })
+            .collect();
+        for (server, tree) in find_server_trees(&samples, process.pid, &servers) {
+            let Some((limit, observed, max)) = limits
+                .iter()
+                .find(|limits| limits.server == server)
+                .and_then(|limits| limits.exceeded_by(&tree))
+            else {
+                continue;
+            };
+            let killed = kill_tree(&tree);
+            warn!(
+                "🛑 MCP server {} of run {} went over its {} limit ({} > {} {}), killed: {}",
+                server,
+                process.run_id,
+                limit.as_str(),
+                observed,
+                max,
+                limit.unit(),
+                killed
+            );
+            let mut violation = LimitViolation {
+                id: None,
+                server: server.clone(),
+                run_id: process.run_id,
+                pid: tree[0].pid,
+                limit,
+                observed,
+                max,
+                killed,
+                created_at: None,
+            };
+            if let Ok(conn) = app.state::<AgentDb>().0.lock() {
+                violation.id = record_violation(&conn, &violation).ok();
+            }
+            let _ = app.emit("mcp:limit-exceeded", &violation);
+            record_activity(
+                app,
+                NewActivity::new(
+                    ActivityKind::McpServerLimitExceeded,
+                    format!("MCP server {} killed", server),
+                )
+                .project(process.project_path.clone())
+                .run(process.run_id)
+                .detail(serde_json::json!({
+                    "limit": limit,
+                    "observed": observed,
+                    "max": max,
+                })),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Enforce the configured server limits in the background
+pub fn spawn_mcp_guardrails(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        info!("MCP server guardrails started");
+        loop {
+            tokio::time::sleep(GUARDRAIL_CHECK_INTERVAL).await;
+            let handle = app.clone();
+            match tauri::async_runtime::spawn_blocking(move || enforce_limits(&handle)).await {
+                Ok(Err(e)) => warn!("MCP guardrail check failed: {}", e),
+                Err(e) => warn!("MCP guardrail task failed: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_mcp_server_limits(db: State<'_, AgentDb>) -> Result<Vec<McpServerLimits>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_limits(&conn).map_err(|e| e.to_string())
+}
+
+/// Set a server's limits; leaving every limit empty removes them
+#[tauri::command]
+pub async fn set_mcp_server_limits(
+    db: State<'_, AgentDb>,
+    limits: McpServerLimits,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_limits(&conn, &limits)
+}
+
+/// Servers killed for going over a limit, newest first
+#[tauri::command]
+pub async fn list_mcp_limit_violations(
+    db: State<'_, AgentDb>,
+    limit: Option<i64>,
+) -> Result<Vec<LimitViolation>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_violations(&conn, limit.unwrap_or(DEFAULT_VIOLATION_LIMIT)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_trees_are_found_below_the_run_and_checked() {
+        let output = "\
+          100     1  90000    00:01:10    01:00:00 claude -p task
+          200   100  40000    00:00:05       10:00 node /usr/local/bin/npx -y @modelcontextprotocol/server-filesystem /work
+          201   200 900000 01:30:00.50       09:58 node /cache/server-filesystem/dist/index.js /work
+          300   100  20000    00:00:01       05:00 bash -c ls
+          400     1  50000    00:00:01 2-00:00:00 npx -y @modelcontextprotocol/server-filesystem /other
+        ";
+        let samples = parse_ps_output(output);
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[2].cpu_secs, 5400);
+        assert_eq!(samples[4].elapsed_secs, 172_800);
+
+        let servers = vec![(
+            "filesystem".to_string(),
+            "npx".to_string(),
+            vec![
+                "-y".to_string(),
+                "@modelcontextprotocol/server-filesystem".to_string(),
+            ],
+        )];
+        // The same server outside the run is left alone
+        let trees = find_server_trees(&samples, 100, &servers);
+        assert_eq!(trees.len(), 1);
+        let (name, tree) = &trees[0];
+        assert_eq!(name, "filesystem");
+        assert_eq!(
+            tree.iter().map(|process| process.pid).collect::<Vec<_>>(),
+            vec![200, 201]
+        );
+
+        let limits = McpServerLimits {
+            server: "filesystem".to_string(),
+            max_rss_mb: Some(2048),
+            max_cpu_secs: Some(3600),
+            max_runtime_secs: None,
+        };
+        assert_eq!(limits.exceeded_by(tree), Some((LimitKind::Cpu, 5405, 3600)));
+        let limits = McpServerLimits {
+            max_cpu_secs: None,
+            ..limits
+        };
+        assert_eq!(limits.exceeded_by(tree), None);
+    }
+
+    #[test]
+    fn test_limits_round_trip_and_clear() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_mcp_guardrail_tables(&conn).unwrap();
+        let limits = McpServerLimits {
+            server: "indexer".to_string(),
+            max_rss_mb: Some(512),
+            max_cpu_secs: None,
+            max_runtime_secs: Some(3600),
+        };
+        save_limits(&conn, &limits).unwrap();
+        assert_eq!(load_limits(&conn).unwrap(), vec![limits.clone()]);
+
+        assert!(save_limits(
+            &conn,
+            &McpServerLimits {
+                max_rss_mb: Some(0),
+                ..limits.clone()
+            }
+        )
+        .is_err());
+        save_limits(
+            &conn,
+            &McpServerLimits {
+                max_rss_mb: None,
+                max_runtime_secs: None,
+                ..limits
+            },
+        )
+        .unwrap();
+        assert!(load_limits(&conn).unwrap().is_empty());
+    }
+}
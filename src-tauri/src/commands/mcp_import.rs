@@ -0,0 +1,546 @@
+#![allow(dead_code)]
+
+//! Imports MCP servers configured in other tools (VS Code, Cursor, Windsurf, Claude
+//! Desktop). Each format is mapped onto `MCPServerConfig`; a preview lists what would be
+//! added and which fields Claude Code can't use before anything is written.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use super::mcp::{mcp_add_json, ImportResult, ImportServerResult, MCPServerConfig};
+
+/// Tool an import reads from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpImportSource {
+    VsCode,
+    Cursor,
+    Windsurf,
+    ClaudeDesktop,
+}
+
+impl McpImportSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            McpImportSource::VsCode => "VS Code",
+            McpImportSource::Cursor => "Cursor",
+            McpImportSource::Windsurf => "Windsurf",
+            McpImportSource::ClaudeDesktop => "Claude Desktop",
+        }
+    }
+
+    /// Keys each format understands besides the ones `MCPServerConfig` maps
+    fn ignored_keys(&self) -> &'static [&'static str] {
+        match self {
+            McpImportSource::VsCode => &["envFile", "dev", "gallery", "version"],
+            McpImportSource::Cursor => &[],
+            McpImportSource::Windsurf => &["disabledTools", "alwaysAllow"],
+            McpImportSource::ClaudeDesktop => &[],
+        }
+    }
+}
+
+/// A server found in another tool's config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpImportCandidate {
+    /// Name it would be added under (sanitized for Claude Code)
+    pub name: String,
+    /// Name in the source file
+    pub source_name: String,
+    /// `None` when the entry can't be mapped; see `warnings`
+    pub config: Option<MCPServerConfig>,
+    /// Fields present in the source that are dropped on import
+    pub unsupported_fields: Vec<String>,
+    pub warnings: Vec<String>,
+    /// A server with this name is already configured for Claude Code
+    pub conflict: bool,
+    /// Disabled in the source tool
+    pub disabled: bool,
+}
+
+/// Result of `mcp_import_preview`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpImportPreview {
+    pub source: McpImportSource,
+    pub path: String,
+    pub candidates: Vec<McpImportCandidate>,
+}
+
+/// Config files a source is usually read from, most specific first
+pub fn default_import_paths(source: McpImportSource, project_path: Option<&str>) -> Vec<PathBuf> {
+    let home = dirs::home_dir();
+    let config_dir = dirs::config_dir();
+    let project = project_path.map(PathBuf::from);
+    let mut paths = Vec::new();
+    match source {
+        McpImportSource::VsCode => {
+            if let Some(project) = &project {
+                paths.push(project.join(".vscode").join("mcp.json"));
+            }
+            if let Some(config) = &config_dir {
+                paths.push(config.join("Code").join("User").join("mcp.json"));
+                paths.push(config.join("Code").join("User").join("settings.json"));
+            }
+        }
+        McpImportSource::Cursor => {
+            if let Some(project) = &project {
+                paths.push(project.join(".cursor").join("mcp.json"));
+            }
+            if let Some(home) = &home {
+                paths.push(home.join(".cursor").join("mcp.json"));
+            }
+        }
+        McpImportSource::Windsurf => {
+            if let Some(home) = &home {
+                paths.push(home.join(".codeium").join("windsurf").join("mcp_config.json"));
+            }
+        }
+        McpImportSource::ClaudeDesktop => {
+            if let Some(config) = &config_dir {
+                paths.push(config.join("Claude").join("claude_desktop_config.json"));
+            }
+        }
+    }
+    paths
+}
+
+/// Claude Code only accepts letters, digits, `-` and `_` in server names
+fn sanitize_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    sanitized.trim_matches('-').to_string()
+}
+
+/// The server table of a config file. VS Code uses `servers` (or `mcp.servers` in
+/// settings.json); the others use `mcpServers`.
+fn server_entries(source: McpImportSource, config: &Value) -> Vec<(String, Value)> {
+    let servers = match source {
+        McpImportSource::VsCode => config
+            .get("servers")
+            .or_else(|| config.get("mcp").and_then(|mcp| mcp.get("servers")))
+            .or_else(|| config.get("mcpServers")),
+        _ => config.get("mcpServers"),
+    };
+    servers
+        .and_then(|servers| servers.as_object())
+        .map(|servers| servers.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default()
+}
+
+fn string_map(value: Option<&Value>, field: &str, warnings: &mut Vec<String>) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    if let Some(object) = value.and_then(|v| v.as_object()) {
+        for (key, value) in object {
+            match value {
+                Value::String(s) => {
+                    map.insert(key.clone(), s.clone());
+                }
+                Value::Number(_) | Value::Bool(_) => {
+                    map.insert(key.clone(), value.to_string());
+                }
+                _ => warnings.push(format!("{}.{} is not a string and was skipped", field, key)),
+            }
+        }
+    }
+    map
+}
+
+/// Expand `${workspaceFolder}` and flag variables that only the source tool can resolve
+fn resolve_variables(value: &str, project_path: Option<&str>, warnings: &mut Vec<String>) -> String {
+    let mut value = value.to_string();
+    if let Some(project) = project_path {
+        value = value
+            .replace("${workspaceFolder}", project)
+            .replace("${workspaceRoot}", project);
+    }
+    if let Some(start) = value.find("${") {
+        let end = value[start..].find('}').map(|i| start + i + 1).unwrap_or(value.len());
+        let variable = &value[start..end];
+        let warning = format!("{} must be replaced with a real value", variable);
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+    value
+}
+
+/// Map one server entry from `source` onto `MCPServerConfig`
+pub fn map_server(
+    source: McpImportSource,
+    source_name: &str,
+    entry: &Value,
+    project_path: Option<&str>,
+) -> McpImportCandidate {
+    let mut warnings = Vec::new();
+    let name = sanitize_name(source_name);
+    if name != source_name {
+        warnings.push(format!("Renamed from \"{}\"", source_name));
+    }
+
+    let known = [
+        "type", "transport", "command", "args", "env", "url", "serverUrl", "headers", "disabled",
+    ];
+    let mut unsupported_fields: Vec<String> = entry
+        .as_object()
+        .map(|object| {
+            object
+                .keys()
+                .filter(|key| !known.contains(&key.as_str()))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    unsupported_fields.sort();
+    for key in unsupported_fields
+        .iter()
+        .filter(|key| source.ignored_keys().contains(&key.as_str()))
+    {
+        warnings.push(format!("{} is specific to {} and is not imported", key, source.label()));
+    }
+
+    let disabled = entry.get("disabled").and_then(|v| v.as_bool()).unwrap_or(false);
+    let url = entry
+        .get("url")
+        .or_else(|| entry.get("serverUrl"))
+        .and_then(|v| v.as_str())
+        .map(|url| resolve_variables(url, project_path, &mut warnings));
+    let declared_type = entry
+        .get("type")
+        .or_else(|| entry.get("transport"))
+        .and_then(|v| v.as_str())
+        .map(|t| t.to_lowercase());
+    let command = entry
+        .get("command")
+        .and_then(|v| v.as_str())
+        .map(|command| resolve_variables(command, project_path, &mut warnings));
+
+    let transport_type = match (declared_type.as_deref(), &command, &url) {
+        (Some("stdio"), _, _) | (None, Some(_), _) => Some("stdio"),
+        (Some("sse"), _, _) => Some("sse"),
+        (Some("http") | Some("streamable-http") | Some("streamablehttp"), _, _) | (None, None, Some(_)) => {
+            Some("http")
+        }
+        (Some(other), _, _) => {
+            warnings.push(format!("Unsupported transport \"{}\"", other));
+            None
+        }
+        (None, None, None) => {
+            warnings.push("Entry has neither a command nor a URL".to_string());
+            None
+        }
+    };
+
+    let config = match (transport_type, command) {
+        (Some("stdio"), Some(command)) => {
+            let args = entry
+                .get("args")
+                .and_then(|v| v.as_array())
+                .map(|args| {
+                    args.iter()
+                        .filter_map(|arg| arg.as_str())
+                        .map(|arg| resolve_variables(arg, project_path, &mut warnings))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let env = string_map(entry.get("env"), "env", &mut warnings)
+                .into_iter()
+                .map(|(k, v)| {
+                    let v = resolve_variables(&v, project_path, &mut warnings);
+                    (k, v)
+                })
+                .collect();
+            Some(MCPServerConfig {
+                transport_type: "stdio".to_string(),
+                command,
+                args,
+                env,
+                url: None,
+                headers: None,
+            })
+        }
+        (Some("stdio"), None) => {
+            warnings.push("stdio server has no command".to_string());
+            None
+        }
+        (Some(transport_type), _) => {
+            let headers = string_map(entry.get("headers"), "headers", &mut warnings);
+            Some(MCPServerConfig {
+                transport_type: transport_type.to_string(),
+                command: String::new(),
+                args: Vec::new(),
+                env: HashMap::new(),
+                url,
+                headers: if headers.is_empty() { None } else { Some(headers) },
+            })
+        }
+        (None, _) => None,
+    };
+    if disabled {
+        warnings.push(format!("Disabled in {}", source.label()));
+    }
+
+    McpImportCandidate {
+        name,
+        source_name: source_name.to_string(),
+        config,
+        unsupported_fields,
+        warnings,
+        conflict: false,
+        disabled,
+    }
+}
+
+/// Read a config file and map every server in it
+pub fn read_import_file(
+    source: McpImportSource,
+    path: &Path,
+    project_path: Option<&str>,
+) -> Result<Vec<McpImportCandidate>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    // VS Code files allow comments and trailing commas
+    let config: Value = serde_json::from_str(&strip_jsonc(&content))
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(server_entries(source, &config)
+        .iter()
+        .map(|(name, entry)| map_server(source, name, entry, project_path))
+        .collect())
+}
+
+/// Remove `//` and `/* */` comments and trailing commas outside of strings
+pub(crate) fn strip_jsonc(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            (',', _) => {
+                let rest: String = chars.clone().collect();
+                let next = rest.trim_start().chars().next();
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The JSON `claude mcp add-json` expects for a config
+pub(crate) fn add_json_config(config: &MCPServerConfig) -> Value {
+    if config.transport_type == "stdio" {
+        json!({
+            "type": "stdio",
+            "command": config.command,
+            "args": config.args,
+            "env": config.env,
+        })
+    } else {
+        json!({
+            "type": config.transport_type,
+            "url": config.url,
+            "headers": config.headers.clone().unwrap_or_default(),
+        })
+    }
+}
+
+fn resolve_import_path(
+    source: McpImportSource,
+    path: Option<String>,
+    project_path: Option<&str>,
+) -> Result<PathBuf, String> {
+    match path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => default_import_paths(source, project_path)
+            .into_iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("No {} MCP configuration found", source.label())),
+    }
+}
+
+/// List the servers an import would add, without changing anything
+#[tauri::command]
+pub async fn mcp_import_preview(
+    source: McpImportSource,
+    path: Option<String>,
+    project_path: Option<String>,
+) -> Result<McpImportPreview, String> {
+    let path = resolve_import_path(source, path, project_path.as_deref())?;
+    let mut candidates = read_import_file(source, &path, project_path.as_deref())?;
+    for candidate in &mut candidates {
+        candidate.conflict =
+            super::mcp_capabilities::find_server_config(&candidate.name, project_path.as_deref())
+                .is_some();
+    }
+    Ok(McpImportPreview {
+        source,
+        path: path.to_string_lossy().to_string(),
+        candidates,
+    })
+}
+
+/// Import servers from another tool into `scope`. `names` limits the import to those
+/// servers; by default every mappable, enabled server without a name conflict is added.
+#[tauri::command]
+pub async fn mcp_import_servers(
+    app: AppHandle,
+    source: McpImportSource,
+    path: Option<String>,
+    project_path: Option<String>,
+    scope: String,
+    names: Option<Vec<String>>,
+) -> Result<ImportResult, String> {
+    let preview = mcp_import_preview(source, path, project_path).await?;
+    let mut result = ImportResult {
+        imported_count: 0,
+        failed_count: 0,
+        servers: Vec::new(),
+    };
+
+    for candidate in preview.candidates {
+        let selected = match &names {
+            Some(names) => names.contains(&candidate.name),
+            None => !candidate.conflict && !candidate.disabled,
+        };
+        if !selected {
+            continue;
+        }
+        let outcome = match &candidate.config {
+            Some(config) => {
+                mcp_add_json(
+                    app.clone(),
+                    candidate.name.clone(),
+                    add_json_config(config).to_string(),
+                    scope.clone(),
+                )
+                .await
+                .map_err(String::from)
+                .and_then(|added| if added.success { Ok(()) } else { Err(added.message) })
+            }
+            None => Err(candidate.warnings.join("; ")),
+        };
+        match outcome {
+            Ok(()) => result.imported_count += 1,
+            Err(_) => result.failed_count += 1,
+        }
+        result.servers.push(ImportServerResult {
+            name: candidate.name,
+            success: outcome.is_ok(),
+            error: outcome.err(),
+        });
+    }
+    log::info!(
+        "Imported {} MCP servers from {} ({} failed)",
+        result.imported_count,
+        source.label(),
+        result.failed_count
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vscode_mcp_json_with_comments_inputs_and_extra_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mcp.json");
+        fs::write(
+            &path,
+            r#"{
+                // Workspace servers
+                "inputs": [{"id": "token", "type": "promptString"}],
+                "servers": {
+                    "github.com/fs": {
+                        "type": "stdio",
+                        "command": "npx",
+                        "args": ["-y", "fs-server", "${workspaceFolder}"],
+                        "env": {"TOKEN": "${input:token}"},
+                        "envFile": "${workspaceFolder}/.env",
+                    },
+                    "remote": {"type": "http", "url": "https://mcp.example.com", "headers": {"X-Key": "k"}},
+                    "weird": {"type": "websocket", "url": "wss://x"}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let candidates = read_import_file(McpImportSource::VsCode, &path, Some("/work/app")).unwrap();
+        assert_eq!(candidates.len(), 3);
+
+        let fs_server = candidates.iter().find(|c| c.source_name == "github.com/fs").unwrap();
+        assert_eq!(fs_server.name, "github-com-fs");
+        let config = fs_server.config.as_ref().unwrap();
+        assert_eq!(config.args, vec!["-y", "fs-server", "/work/app"]);
+        assert_eq!(fs_server.unsupported_fields, vec!["envFile"]);
+        assert!(fs_server.warnings.iter().any(|w| w.contains("${input:token}")));
+
+        let remote = candidates.iter().find(|c| c.name == "remote").unwrap();
+        let config = remote.config.as_ref().unwrap();
+        assert_eq!(config.transport_type, "http");
+        assert_eq!(
+            add_json_config(config),
+            json!({"type": "http", "url": "https://mcp.example.com", "headers": {"X-Key": "k"}})
+        );
+
+        assert!(candidates.iter().find(|c| c.name == "weird").unwrap().config.is_none());
+    }
+
+    #[test]
+    fn test_cursor_and_windsurf_formats() {
+        let cursor = json!({"command": "uvx", "args": ["mcp-git"], "env": {"DEBUG": 1}});
+        let candidate = map_server(McpImportSource::Cursor, "git", &cursor, None);
+        let config = candidate.config.unwrap();
+        assert_eq!(config.transport_type, "stdio");
+        assert_eq!(config.env.get("DEBUG").map(String::as_str), Some("1"));
+        assert!(candidate.unsupported_fields.is_empty());
+
+        let windsurf = json!({"serverUrl": "https://mcp.example.com/sse", "disabled": true, "disabledTools": ["x"]});
+        let candidate = map_server(McpImportSource::Windsurf, "remote", &windsurf, None);
+        assert_eq!(candidate.config.unwrap().url.as_deref(), Some("https://mcp.example.com/sse"));
+        assert!(candidate.disabled);
+        assert_eq!(candidate.unsupported_fields, vec!["disabledTools"]);
+    }
+}
@@ -0,0 +1,432 @@
+#![allow(dead_code)]
+
+//! Lints the MCP config files for mistakes that only surface when a server fails to start:
+//! the same server name in several scopes, stdio commands that don't exist, `${VAR}`
+//! references to variables that aren't defined, SSE/HTTP hosts that don't resolve and
+//! headers without a value. Each warning carries a fix the UI can apply when there is one.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::mcp_config_watcher::{parse_servers, watched_configs};
+use super::mcp_prerequisites::find_executable;
+
+/// Longest wait for a server host to resolve
+const DNS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Scopes from highest to lowest precedence; a name in a later scope is shadowed
+const SCOPE_PRECEDENCE: &[&str] = &["local", "project", "user"];
+
+/// Where GUI launches often miss executables a terminal finds, as their PATH is shorter
+const EXTRA_BIN_DIRS: &[&str] = &[
+    "/opt/homebrew/bin",
+    "/usr/local/bin",
+    "~/.local/bin",
+    "~/.cargo/bin",
+    "~/.bun/bin",
+    "~/.volta/bin",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LintCode {
+    DuplicateServer,
+    CommandNotFound,
+    UndefinedVariable,
+    UnresolvableHost,
+    EmptyHeader,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LintSeverity {
+    /// The server will not start or connect
+    Error,
+    /// The server may work, but not as configured
+    Warning,
+}
+
+/// A change to the server's config that resolves a warning
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum LintFix {
+    /// Delete the server from this scope, leaving the definition that takes precedence
+    RemoveServer,
+    /// Use the absolute path of the command found outside PATH
+    ReplaceCommand { command: String },
+    /// Define the variable in the `env` of the Claude settings
+    DefineVariable { variable: String },
+    /// Delete the header
+    RemoveHeader { header: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LintWarning {
+    pub code: LintCode,
+    pub severity: LintSeverity,
+    /// `user`, `local` or `project`
+    pub scope: String,
+    pub server: String,
+    /// Config file the server is defined in
+    pub path: String,
+    pub message: String,
+    pub fix: Option<LintFix>,
+}
+
+/// A server as found in a config file
+#[derive(Debug, Clone)]
+pub struct ConfiguredServer {
+    pub scope: String,
+    pub path: String,
+    pub name: String,
+    pub config: Value,
+}
+
+impl ConfiguredServer {
+    fn warning(
+        &self,
+        code: LintCode,
+        severity: LintSeverity,
+        message: String,
+        fix: Option<LintFix>,
+    ) -> LintWarning {
+        LintWarning {
+            code,
+            severity,
+            scope: self.scope.clone(),
+            server: self.name.clone(),
+            path: self.path.clone(),
+            message,
+            fix,
+        }
+    }
+
+    fn transport(&self) -> &str {
+        match self.config.get("type").and_then(Value::as_str) {
+            Some(transport) => transport,
+            None if self.config.get("url").is_some() => "sse",
+            None => "stdio",
+        }
+    }
+
+    fn string_map(&self, key: &str) -> Vec<(String, String)> {
+        self.config
+            .get(key)
+            .and_then(Value::as_object)
+            .map(|map| {
+                map.iter()
+                    .map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// `${VAR}` references without a `:-default`
+fn variable_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(:-[^}]*)?\}").unwrap())
+}
+
+/// Every string in the config, where variables are expanded
+fn collect_strings<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => out.push(s),
+        Value::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        Value::Object(map) => map.values().for_each(|item| collect_strings(item, out)),
+        _ => {}
+    }
+}
+
+fn expand_home(dir: &str) -> Option<PathBuf> {
+    match dir.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+        None => Some(PathBuf::from(dir)),
+    }
+}
+
+/// Servers of every config file visible from `project_path`
+pub fn configured_servers(project_path: Option<&str>) -> Vec<ConfiguredServer> {
+    let mut servers = Vec::new();
+    for config in watched_configs(project_path) {
+        let Ok(content) = std::fs::read_to_string(&config.path) else {
+            continue;
+        };
+        let Ok(scopes) = parse_servers(&content, config.scope, project_path) else {
+            continue;
+        };
+        for (scope, map) in scopes {
+            for (name, value) in map {
+                servers.push(ConfiguredServer {
+                    scope: scope.clone(),
+                    path: config.path.to_string_lossy().to_string(),
+                    name,
+                    config: value,
+                });
+            }
+        }
+    }
+    servers
+}
+
+/// Checks that need no network; `lookup_var` resolves environment variables
+pub fn lint_servers(
+    servers: &[ConfiguredServer],
+    lookup_var: &dyn Fn(&str) -> Option<String>,
+    path_var: Option<&OsStr>,
+) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+
+    let mut by_name: BTreeMap<&str, Vec<&ConfiguredServer>> = BTreeMap::new();
+    for server in servers {
+        by_name.entry(&server.name).or_default().push(server);
+    }
+    for definitions in by_name.values().filter(|d| d.len() > 1) {
+        let rank = |server: &ConfiguredServer| {
+            SCOPE_PRECEDENCE
+                .iter()
+                .position(|scope| *scope == server.scope)
+                .unwrap_or(SCOPE_PRECEDENCE.len())
+        };
+        let winner = definitions
+            .iter()
+            .min_by_key(|server| rank(server))
+            .unwrap();
+        for server in definitions
+            .iter()
+            .filter(|server| rank(server) > rank(winner))
+        {
+            warnings.push(server.warning(
+                LintCode::DuplicateServer,
+                LintSeverity::Warning,
+                format!(
+                    "'{}' is also defined in the {} scope, which takes precedence; this definition is ignored",
+                    server.name, winner.scope
+                ),
+                Some(LintFix::RemoveServer),
+            ));
+        }
+    }
+
+    for server in servers {
+        if server.transport() == "stdio" {
+            let command = server
+                .config
+                .get("command")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            if !command.contains("${") && find_executable(command, path_var).is_none() {
+                let found = EXTRA_BIN_DIRS
+                    .iter()
+                    .filter_map(|dir| expand_home(dir))
+                    .find_map(|dir| find_executable(command, Some(dir.as_os_str())));
+                let message = match &found {
+                    Some(path) => format!(
+                        "'{}' is not on the PATH opcode sees, but exists at {}",
+                        command,
+                        path.display()
+                    ),
+                    None if command.is_empty() => "No command is configured".to_string(),
+                    None => format!("'{}' was not found or is not executable", command),
+                };
+                warnings.push(server.warning(
+                    LintCode::CommandNotFound,
+                    LintSeverity::Error,
+                    message,
+                    found.map(|path| LintFix::ReplaceCommand {
+                        command: path.to_string_lossy().to_string(),
+                    }),
+                ));
+            }
+        }
+
+        let mut strings = Vec::new();
+        collect_strings(&server.config, &mut strings);
+        let mut undefined: Vec<&str> = strings
+            .iter()
+            .flat_map(|s| variable_regex().captures_iter(s))
+            .filter(|captures| captures.get(2).is_none())
+            .map(|captures| captures.get(1).unwrap().as_str())
+            .filter(|name| lookup_var(name).is_none())
+            .collect();
+        undefined.sort_unstable();
+        undefined.dedup();
+        for variable in undefined {
+            warnings.push(server.warning(
+                LintCode::UndefinedVariable,
+                LintSeverity::Error,
+                format!("${{{}}} is referenced but not defined", variable),
+                Some(LintFix::DefineVariable {
+                    variable: variable.to_string(),
+                }),
+            ));
+        }
+
+        for (header, value) in server.string_map("headers") {
+            if value.trim().is_empty() {
+                warnings.push(server.warning(
+                    LintCode::EmptyHeader,
+                    LintSeverity::Warning,
+                    format!("Header '{}' has no value", header),
+                    Some(LintFix::RemoveHeader { header }),
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Host and port of a remote server's URL, skipping URLs still holding variables
+fn remote_host(server: &ConfiguredServer) -> Option<(String, u16)> {
+    if !matches!(server.transport(), "sse" | "http") {
+        return None;
+    }
+    let url = server.config.get("url")?.as_str()?;
+    if url.contains("${") {
+        return None;
+    }
+    let url = reqwest::Url::parse(url).ok()?;
+    Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+}
+
+async fn lint_hosts(servers: &[ConfiguredServer]) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    for server in servers {
+        let Some((host, port)) = remote_host(server) else {
+            continue;
+        };
+        let failure =
+            match tokio::time::timeout(DNS_TIMEOUT, tokio::net::lookup_host((host.as_str(), port)))
+                .await
+            {
+                Ok(Ok(addrs)) => match addrs.count() {
+                    0 => "has no addresses".to_string(),
+                    _ => continue,
+                },
+                Ok(Err(e)) => format!("does not resolve: {}", e),
+                Err(_) => "timed out resolving".to_string(),
+            };
+        warnings.push(server.warning(
+            LintCode::UnresolvableHost,
+            LintSeverity::Error,
+            format!("Host '{}' {}", host, failure),
+            None,
+        ));
+    }
+    warnings
+}
+
+/// Lint the MCP servers visible from `project_path`, reporting only those of `scope` when set
+#[tauri::command]
+pub async fn mcp_lint_config(
+    scope: Option<String>,
+    project_path: Option<String>,
+) -> Result<Vec<LintWarning>, String> {
+    let servers = configured_servers(project_path.as_deref());
+    let mut warnings = lint_servers(
+        &servers,
+        &|name| std::env::var(name).ok(),
+        std::env::var_os("PATH").as_deref(),
+    );
+    warnings.extend(lint_hosts(&servers).await);
+    if let Some(scope) = scope {
+        warnings.retain(|warning| warning.scope == scope);
+    }
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn server(scope: &str, name: &str, config: Value) -> ConfiguredServer {
+        ConfiguredServer {
+            scope: scope.to_string(),
+            path: format!("/{}.json", scope),
+            name: name.to_string(),
+            config,
+        }
+    }
+
+    fn lint(servers: &[ConfiguredServer]) -> Vec<LintWarning> {
+        let env = |name: &str| (name == "DEFINED").then(|| "1".to_string());
+        lint_servers(servers, &env, Some(OsStr::new("")))
+    }
+
+    #[test]
+    fn test_duplicates_flag_the_shadowed_definitions() {
+        let sh = if cfg!(windows) {
+            "C:\\Windows\\System32\\cmd.exe"
+        } else {
+            "/bin/sh"
+        };
+        let servers = [
+            server("user", "github", json!({ "command": sh })),
+            server("local", "github", json!({ "command": sh })),
+            server("project", "github", json!({ "command": sh })),
+        ];
+        let warnings = lint(&servers);
+        let shadowed: Vec<&str> = warnings.iter().map(|w| w.scope.as_str()).collect();
+        assert_eq!(shadowed, ["user", "project"]);
+        assert!(warnings.iter().all(|w| w.code == LintCode::DuplicateServer
+            && w.fix == Some(LintFix::RemoveServer)
+            && w.message.contains("local scope")));
+    }
+
+    #[test]
+    fn test_commands_variables_and_headers_are_checked() {
+        let servers = [
+            server(
+                "user",
+                "local-tool",
+                json!({
+                    "command": "definitely-not-an-installed-command",
+                    "env": { "TOKEN": "${MISSING}", "OTHER": "${DEFINED}", "LEVEL": "${LEVEL:-info}" }
+                }),
+            ),
+            server(
+                "project",
+                "remote",
+                json!({
+                    "type": "http",
+                    "url": "https://mcp.example.com/${MISSING}",
+                    "headers": { "Authorization": "", "X-Team": "core" }
+                }),
+            ),
+        ];
+        let warnings = lint(&servers);
+        let codes: Vec<(LintCode, &str)> = warnings
+            .iter()
+            .map(|w| (w.code, w.server.as_str()))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (LintCode::CommandNotFound, "local-tool"),
+                (LintCode::UndefinedVariable, "local-tool"),
+                (LintCode::UndefinedVariable, "remote"),
+                (LintCode::EmptyHeader, "remote"),
+            ]
+        );
+        assert_eq!(
+            warnings[1].fix,
+            Some(LintFix::DefineVariable {
+                variable: "MISSING".to_string()
+            })
+        );
+        assert_eq!(
+            warnings[3].fix,
+            Some(LintFix::RemoveHeader {
+                header: "Authorization".to_string()
+            })
+        );
+        assert_eq!(remote_host(&servers[1]), None);
+    }
+}
@@ -0,0 +1,341 @@
+#![allow(dead_code)]
+
+//! Runtime prerequisites of stdio MCP servers, checked before a server is added or started:
+//! the command resolves to an executable, the `npx`/`uvx` package it launches exists in its
+//! registry, and the env vars it is given are filled in.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::mcp::MCPServerConfig;
+
+/// How long a registry lookup may take before the package check is reported as unverified
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    /// Could not be verified; the server may still work
+    Warning,
+    /// The server will not start
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrerequisiteCheck {
+    /// `command`, `package` or `env:<NAME>`
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl PrerequisiteCheck {
+    fn new(name: impl Into<String>, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrerequisiteReport {
+    pub checks: Vec<PrerequisiteCheck>,
+    /// No check failed
+    pub ok: bool,
+}
+
+/// Package registry a launcher installs from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageRegistry {
+    Npm,
+    PyPi,
+}
+
+/// Path of `command` as it would be spawned, searching `path_var` for bare names
+pub fn find_executable(command: &str, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    let candidates = |base: PathBuf| {
+        let mut names = vec![base.clone()];
+        if cfg!(windows) && base.extension().is_none() {
+            for ext in ["exe", "cmd", "bat"] {
+                names.push(base.with_extension(ext));
+            }
+        }
+        names
+    };
+
+    if command.contains('/') || command.contains('\\') {
+        return candidates(PathBuf::from(command))
+            .into_iter()
+            .find(|path| is_executable(path));
+    }
+    std::env::split_paths(path_var?)
+        .flat_map(|dir| candidates(dir.join(command)))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    if !metadata.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        true
+    }
+}
+
+/// Package a launcher command runs, with any version pin stripped
+pub fn launched_package(command: &str, args: &[String]) -> Option<(PackageRegistry, String)> {
+    let launcher = Path::new(command)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(command);
+    let registry = match launcher {
+        "npx" | "pnpx" | "bunx" => PackageRegistry::Npm,
+        "uvx" | "pipx" => PackageRegistry::PyPi,
+        _ => return None,
+    };
+
+    let mut args = args.iter().map(String::as_str);
+    if launcher == "pipx" && args.next() != Some("run") {
+        return None;
+    }
+    let mut package = None;
+    while let Some(arg) = args.next() {
+        match arg {
+            // Options naming the package explicitly win over the command name
+            "-p" | "--package" | "--from" | "--spec" => {
+                package = args.next();
+                break;
+            }
+            _ if arg.starts_with("--package=") || arg.starts_with("--from=") => {
+                package = arg.split_once('=').map(|(_, value)| value);
+                break;
+            }
+            _ if arg.starts_with('-') => {}
+            _ => {
+                package = Some(arg);
+                break;
+            }
+        }
+    }
+    let package = package?;
+
+    // Local paths and git/URL specs aren't looked up in a registry
+    if package.is_empty()
+        || package.starts_with('.')
+        || package.starts_with('/')
+        || package.contains("://")
+        || package.starts_with("git+")
+    {
+        return None;
+    }
+    let name = match registry {
+        // `@scope/name@1.2.3`: the version separator is the last `@` after the first character
+        PackageRegistry::Npm => match package.rfind('@') {
+            Some(at) if at > 0 => &package[..at],
+            _ => package,
+        },
+        PackageRegistry::PyPi => package
+            .split(['=', '<', '>', '!', '~', '@', '[', ';', ' '])
+            .next()
+            .unwrap_or(package),
+    };
+    (!name.is_empty()).then(|| (registry, name.to_string()))
+}
+
+/// An env var value that is empty or still an unexpanded placeholder
+pub fn check_env(env: &HashMap<String, String>) -> Vec<PrerequisiteCheck> {
+    let mut names: Vec<&String> = env.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let value = env[name].trim();
+            let check = format!("env:{}", name);
+            if value.is_empty() {
+                PrerequisiteCheck::new(check, CheckStatus::Failed, format!("{} is empty", name))
+            } else if value.starts_with("${") || value.starts_with('<') || value.contains("YOUR_") {
+                PrerequisiteCheck::new(
+                    check,
+                    CheckStatus::Warning,
+                    format!("{} looks like a placeholder: {}", name, value),
+                )
+            } else {
+                PrerequisiteCheck::new(check, CheckStatus::Passed, format!("{} is set", name))
+            }
+        })
+        .collect()
+}
+
+fn check_command(command: &str) -> PrerequisiteCheck {
+    if command.trim().is_empty() {
+        return PrerequisiteCheck::new("command", CheckStatus::Failed, "No command configured");
+    }
+    match find_executable(command, std::env::var_os("PATH").as_deref()) {
+        Some(path) => PrerequisiteCheck::new(
+            "command",
+            CheckStatus::Passed,
+            format!("{} resolves to {}", command, path.display()),
+        ),
+        None => PrerequisiteCheck::new(
+            "command",
+            CheckStatus::Failed,
+            format!("{} was not found or is not executable", command),
+        ),
+    }
+}
+
+async fn check_package(registry: PackageRegistry, name: &str) -> PrerequisiteCheck {
+    let url = match registry {
+        PackageRegistry::Npm => format!("https://registry.npmjs.org/{}", name.replace('/', "%2F")),
+        PackageRegistry::PyPi => format!("https://pypi.org/pypi/{}/json", name),
+    };
+    let client = reqwest::Client::builder()
+        .timeout(REGISTRY_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+    match client.head(&url).send().await {
+        Ok(response) if response.status().is_success() => PrerequisiteCheck::new(
+            "package",
+            CheckStatus::Passed,
+            format!("{} found in the {:?} registry", name, registry),
+        ),
+        Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+            PrerequisiteCheck::new(
+                "package",
+                CheckStatus::Failed,
+                format!("{} does not exist in the {:?} registry", name, registry),
+            )
+        }
+        Ok(response) => PrerequisiteCheck::new(
+            "package",
+            CheckStatus::Warning,
+            format!(
+                "Could not verify {}: registry returned {}",
+                name,
+                response.status()
+            ),
+        ),
+        Err(e) => PrerequisiteCheck::new(
+            "package",
+            CheckStatus::Warning,
+            format!("Could not verify {}: {}", name, e),
+        ),
+    }
+}
+
+/// Check whether a stdio server's command, package and env vars are in place. Other
+/// transports have no local prerequisites and always pass.
+#[tauri::command]
+pub async fn mcp_check_prerequisites(
+    config: MCPServerConfig,
+) -> Result<PrerequisiteReport, String> {
+    let mut checks = Vec::new();
+    if config.transport_type == "stdio" {
+        let command = config.command.clone();
+        checks.push(
+            tauri::async_runtime::spawn_blocking(move || check_command(&command))
+                .await
+                .map_err(|e| e.to_string())?,
+        );
+        if let Some((registry, name)) = launched_package(&config.command, &config.args) {
+            checks.push(check_package(registry, &name).await);
+        }
+        checks.extend(check_env(&config.env));
+    }
+
+    let ok = checks
+        .iter()
+        .all(|check| check.status != CheckStatus::Failed);
+    Ok(PrerequisiteReport { checks, ok })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_launched_package_strips_flags_and_versions() {
+        assert_eq!(
+            launched_package(
+                "npx",
+                &args(&["-y", "@modelcontextprotocol/server-github@1.2.0"])
+            ),
+            Some((
+                PackageRegistry::Npm,
+                "@modelcontextprotocol/server-github".to_string()
+            ))
+        );
+        assert_eq!(
+            launched_package(
+                "/usr/local/bin/npx",
+                &args(&["--package=mcp-remote@latest", "mcp-remote"])
+            ),
+            Some((PackageRegistry::Npm, "mcp-remote".to_string()))
+        );
+        assert_eq!(
+            launched_package(
+                "uvx",
+                &args(&["mcp-server-fetch==0.6.2", "--ignore-robots-txt"])
+            ),
+            Some((PackageRegistry::PyPi, "mcp-server-fetch".to_string()))
+        );
+        assert_eq!(
+            launched_package("uvx", &args(&["--from", "git+https://github.com/x/y", "y"])),
+            None
+        );
+        assert_eq!(launched_package("node", &args(&["server.js"])), None);
+    }
+
+    #[test]
+    fn test_find_executable_and_env_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("my-server");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let path_var = std::env::join_paths([dir.path()]).unwrap();
+        assert_eq!(find_executable("my-server", Some(&path_var)), Some(script));
+        assert_eq!(find_executable("missing-server", Some(&path_var)), None);
+
+        let env: HashMap<String, String> = [
+            ("API_KEY", ""),
+            ("REGION", "eu-west-1"),
+            ("TOKEN", "${GITHUB_TOKEN}"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let statuses: Vec<CheckStatus> = check_env(&env).iter().map(|check| check.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                CheckStatus::Failed,
+                CheckStatus::Passed,
+                CheckStatus::Warning
+            ]
+        );
+    }
+}
@@ -0,0 +1,558 @@
+#![allow(dead_code)]
+
+//! Reachability probes for SSE/HTTP MCP servers: DNS resolution, TCP connect, the TLS
+//! handshake with the certificate's validity and expiry date, and the HTTP status of the
+//! endpoint. Servers are probed before they are added and periodically afterwards; the last
+//! probe of each server is kept in memory and reported in its `ServerStatus`, so a "failed
+//! to connect" comes with the stage that failed and why.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::mcp::ServerStatus;
+use super::mcp_lint::configured_servers;
+use super::tls_settings::{allows_invalid_certs, configure_trust};
+use crate::process::ProcessRegistryState;
+
+/// Longest wait for each of DNS, TCP and the HTTP request
+const STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the background monitor probes the configured remote servers
+const PROBE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Emitted with a [`ReachabilityProbe`] when a server becomes reachable or unreachable
+pub const REACHABILITY_CHANGED_EVENT: &str = "mcp:reachability-changed";
+
+/// Last probe of each server, by server name
+static PROBES: Mutex<BTreeMap<String, ReachabilityProbe>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStage {
+    Url,
+    Dns,
+    Tcp,
+    Tls,
+    Http,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CertificateCheck {
+    /// Whether the certificate chain verified against the system roots
+    pub valid: bool,
+    /// Why verification failed
+    pub error: Option<String>,
+    /// RFC 3339
+    pub not_after: Option<String>,
+    /// Negative once expired
+    pub days_remaining: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReachabilityProbe {
+    pub url: String,
+    /// Unix seconds
+    pub checked_at: u64,
+    pub elapsed_ms: u64,
+    /// Addresses the host resolved to
+    pub addresses: Vec<String>,
+    /// The address a TCP connection was made to
+    pub connected_to: Option<String>,
+    /// Only for https URLs that got as far as the handshake
+    pub certificate: Option<CertificateCheck>,
+    pub http_status: Option<u16>,
+    /// The first stage that failed; `None` when the server is reachable
+    pub failed_stage: Option<ProbeStage>,
+    pub cause: Option<String>,
+}
+
+impl ReachabilityProbe {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            checked_at: now_secs(),
+            elapsed_ms: 0,
+            addresses: Vec::new(),
+            connected_to: None,
+            certificate: None,
+            http_status: None,
+            failed_stage: None,
+            cause: None,
+        }
+    }
+
+    pub fn reachable(&self) -> bool {
+        self.failed_stage.is_none()
+    }
+
+    fn fail(mut self, stage: ProbeStage, cause: String) -> Self {
+        self.failed_stage = Some(stage);
+        self.cause = Some(cause);
+        self
+    }
+
+    /// One line for a status error, e.g. "TLS: certificate has expired"
+    pub fn summary(&self) -> Option<String> {
+        let stage = match self.failed_stage? {
+            ProbeStage::Url => "URL",
+            ProbeStage::Dns => "DNS",
+            ProbeStage::Tcp => "TCP",
+            ProbeStage::Tls => "TLS",
+            ProbeStage::Http => "HTTP",
+        };
+        Some(format!(
+            "{}: {}",
+            stage,
+            self.cause.as_deref().unwrap_or("failed")
+        ))
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// The error and the errors that caused it, as reqwest's own message rarely says why
+fn error_chain(error: &dyn Error) -> String {
+    let mut parts = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let text = cause.to_string();
+        if !parts.iter().any(|part| part.contains(&text)) {
+            parts.push(text);
+        }
+        source = cause.source();
+    }
+    parts.join(": ")
+}
+
+/// Tag, content and the bytes after one DER element
+fn der_element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, &byte| (len << 8) | byte as usize);
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// UTCTime (0x17) or GeneralizedTime (0x18) as UTC
+fn der_time(tag: u8, content: &[u8]) -> Option<chrono::DateTime<chrono::Utc>> {
+    let text = std::str::from_utf8(content).ok()?.strip_suffix('Z')?;
+    let full = match tag {
+        0x17 => {
+            let year: u32 = text.get(..2)?.parse().ok()?;
+            format!("{}{}", if year < 50 { "20" } else { "19" }, text)
+        }
+        0x18 => text.to_string(),
+        _ => return None,
+    };
+    chrono::NaiveDateTime::parse_from_str(full.get(..14)?, "%Y%m%d%H%M%S")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// The notAfter date of a DER-encoded X.509 certificate
+pub fn certificate_not_after(der: &[u8]) -> Option<chrono::DateTime<chrono::Utc>> {
+    let (_, certificate, _) = der_element(der)?;
+    let (_, tbs, _) = der_element(certificate)?;
+    let (tag, _, mut rest) = der_element(tbs)?;
+    // The version is an optional explicit [0] before the serial number
+    if tag == 0xa0 {
+        rest = der_element(rest)?.2;
+    }
+    // Signature algorithm, then issuer
+    rest = der_element(rest)?.2;
+    rest = der_element(rest)?.2;
+    let (_, validity, _) = der_element(rest)?;
+    let (_, _, after_not_before) = der_element(validity)?;
+    let (tag, not_after, _) = der_element(after_not_before)?;
+    der_time(tag, not_after)
+}
+
+fn certificate_check(der: Option<&[u8]>, error: Option<String>) -> CertificateCheck {
+    let not_after = der.and_then(certificate_not_after);
+    CertificateCheck {
+        valid: error.is_none(),
+        error,
+        not_after: not_after.map(|time| time.to_rfc3339()),
+        days_remaining: not_after.map(|time| (time - chrono::Utc::now()).num_days()),
+    }
+}
+
+async fn send(
+    url: &reqwest::Url,
+    headers: &HashMap<String, String>,
+    server: Option<&str>,
+    accept_invalid_certs: bool,
+) -> Result<reqwest::Response, reqwest::Error> {
+    // Whether to verify is decided here rather than by the settings, to report bad certificates
+    let client = configure_trust(reqwest::Client::builder(), server)
+        .timeout(STAGE_TIMEOUT)
+        .tls_info(true)
+        .danger_accept_invalid_certs(accept_invalid_certs)
+        .build()?;
+    let mut request = client
+        .get(url.clone())
+        .header("Accept", "application/json, text/event-stream");
+    for (name, value) in headers {
+        // Unexpanded variables would only earn a misleading 401
+        if !value.contains("${") {
+            request = request.header(name, value);
+        }
+    }
+    request.send().await
+}
+
+fn peer_certificate(response: &reqwest::Response) -> Option<Vec<u8>> {
+    response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .map(|der| der.to_vec())
+}
+
+/// Probe `url` stage by stage, stopping at the first that fails. Any HTTP answer other than
+/// 404 or a server error counts as reachable, since MCP endpoints often refuse a bare GET.
+/// An invalid certificate fails the probe unless `server` is allowed to skip verification.
+pub async fn probe(
+    url: &str,
+    headers: &HashMap<String, String>,
+    server: Option<&str>,
+) -> ReachabilityProbe {
+    let started = Instant::now();
+    let mut result = probe_stages(url, headers, server).await;
+    result.elapsed_ms = started.elapsed().as_millis() as u64;
+    result
+}
+
+async fn probe_stages(
+    url: &str,
+    headers: &HashMap<String, String>,
+    server: Option<&str>,
+) -> ReachabilityProbe {
+    let mut probe = ReachabilityProbe::new(url);
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => parsed,
+        Ok(parsed) => {
+            let cause = format!("unsupported scheme '{}'", parsed.scheme());
+            return probe.fail(ProbeStage::Url, cause);
+        }
+        Err(e) => return probe.fail(ProbeStage::Url, e.to_string()),
+    };
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return probe.fail(ProbeStage::Url, "no host".to_string());
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let addresses: Vec<SocketAddr> =
+        match tokio::time::timeout(STAGE_TIMEOUT, tokio::net::lookup_host((host, port))).await {
+            Ok(Ok(addresses)) => addresses.collect(),
+            Ok(Err(e)) => {
+                let cause = format!("'{}' does not resolve: {}", host, e);
+                return probe.fail(ProbeStage::Dns, cause);
+            }
+            Err(_) => {
+                let cause = format!("timed out resolving '{}'", host);
+                return probe.fail(ProbeStage::Dns, cause);
+            }
+        };
+    probe.addresses = addresses
+        .iter()
+        .map(|address| address.to_string())
+        .collect();
+    if addresses.is_empty() {
+        let cause = format!("'{}' has no addresses", host);
+        return probe.fail(ProbeStage::Dns, cause);
+    }
+
+    let mut connect_error = String::new();
+    for address in &addresses {
+        match tokio::time::timeout(STAGE_TIMEOUT, tokio::net::TcpStream::connect(address)).await {
+            Ok(Ok(_)) => {
+                probe.connected_to = Some(address.to_string());
+                break;
+            }
+            Ok(Err(e)) => connect_error = format!("{}: {}", address, e),
+            Err(_) => connect_error = format!("{}: timed out", address),
+        }
+    }
+    if probe.connected_to.is_none() {
+        return probe.fail(ProbeStage::Tcp, connect_error);
+    }
+
+    let https = parsed.scheme() == "https";
+    let response = match send(&parsed, headers, server, false).await {
+        Ok(response) => {
+            if https {
+                probe.certificate = Some(certificate_check(
+                    peer_certificate(&response).as_deref(),
+                    None,
+                ));
+            }
+            response
+        }
+        Err(e) if e.is_timeout() => {
+            let cause = format!("no response within {}s", STAGE_TIMEOUT.as_secs());
+            return probe.fail(ProbeStage::Http, cause);
+        }
+        Err(e) if https && e.is_connect() => {
+            // The TCP connect worked, so the handshake failed. Retry without verification to
+            // tell a bad certificate (and still read its dates) from a broken handshake.
+            let cause = error_chain(&e);
+            let Ok(response) = send(&parsed, headers, server, true).await else {
+                return probe.fail(ProbeStage::Tls, cause);
+            };
+            probe.certificate = Some(certificate_check(
+                peer_certificate(&response).as_deref(),
+                Some(cause.clone()),
+            ));
+            if !allows_invalid_certs(server) {
+                probe.http_status = Some(response.status().as_u16());
+                return probe.fail(ProbeStage::Tls, cause);
+            }
+            response
+        }
+        Err(e) => return probe.fail(ProbeStage::Http, error_chain(&e)),
+    };
+
+    let status = response.status();
+    probe.http_status = Some(status.as_u16());
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return probe.fail(ProbeStage::Http, "endpoint not found (404)".to_string());
+    }
+    if status.is_server_error() {
+        return probe.fail(ProbeStage::Http, format!("server error ({})", status));
+    }
+    probe
+}
+
+/// The last probe of `server`, if it has been probed
+pub fn cached_probe(server: &str) -> Option<ReachabilityProbe> {
+    PROBES.lock().ok()?.get(server).cloned()
+}
+
+/// Keep `probe` as the latest for `server`, returning the one it replaced
+pub fn store_probe(server: &str, probe: ReachabilityProbe) -> Option<ReachabilityProbe> {
+    PROBES.lock().ok()?.insert(server.to_string(), probe)
+}
+
+/// `error` with the cause found by the last probe of `server` appended
+pub fn explain_error(server: &str, error: Option<String>) -> Option<String> {
+    let cause = cached_probe(server).and_then(|probe| probe.summary());
+    match (error, cause) {
+        (Some(error), Some(cause)) => Some(format!("{} ({})", error, cause)),
+        (error, _) => error,
+    }
+}
+
+/// The URL and headers of a configured remote server, skipping URLs holding variables
+fn remote_endpoint(config: &serde_json::Value) -> Option<(String, HashMap<String, String>)> {
+    let url = config.get("url")?.as_str()?;
+    if url.contains("${") || config.get("command").is_some() {
+        return None;
+    }
+    let headers = config
+        .get("headers")
+        .and_then(|headers| headers.as_object())
+        .map(|headers| {
+            headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((url.to_string(), headers))
+}
+
+/// Remote servers visible globally and from the projects of running sessions, by name
+fn remote_servers(app: &AppHandle) -> BTreeMap<String, (String, HashMap<String, String>)> {
+    let mut projects: Vec<Option<String>> = vec![None];
+    if let Ok(processes) = app
+        .state::<ProcessRegistryState>()
+        .0
+        .get_running_processes()
+    {
+        for process in processes {
+            if !projects.contains(&Some(process.project_path.clone())) {
+                projects.push(Some(process.project_path));
+            }
+        }
+    }
+    let mut servers = BTreeMap::new();
+    for project in projects {
+        for server in configured_servers(project.as_deref()) {
+            if let Some(endpoint) = remote_endpoint(&server.config) {
+                servers.entry(server.name).or_insert(endpoint);
+            }
+        }
+    }
+    servers
+}
+
+/// Probe every configured remote server, emitting an event for each whose reachability changed
+pub async fn probe_all(app: &AppHandle) -> BTreeMap<String, ReachabilityProbe> {
+    let mut probes = BTreeMap::new();
+    for (name, (url, headers)) in remote_servers(app) {
+        let result = probe(&url, &headers, Some(&name)).await;
+        let previous = store_probe(&name, result.clone());
+        if previous.map(|previous| previous.reachable()) != Some(result.reachable()) {
+            if let Some(summary) = result.summary() {
+                warn!("MCP server {} is unreachable: {}", name, summary);
+            }
+            let _ = app.emit(
+                REACHABILITY_CHANGED_EVENT,
+                serde_json::json!({ "server": name, "probe": result }),
+            );
+        }
+        probes.insert(name, result);
+    }
+    probes
+}
+
+/// Re-probe the configured remote servers in the background, pausing while offline
+pub fn spawn_reachability_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        info!("MCP reachability monitor started");
+        loop {
+            if !super::offline::is_offline() {
+                probe_all(&app).await;
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+/// Status of each probed remote server, from its last probe
+pub fn probed_statuses() -> HashMap<String, ServerStatus> {
+    let Ok(probes) = PROBES.lock() else {
+        return HashMap::new();
+    };
+    probes
+        .iter()
+        .map(|(name, probe)| {
+            let status = ServerStatus {
+                running: probe.reachable(),
+                error: probe.summary(),
+                last_checked: Some(probe.checked_at),
+                reachability: Some(probe.clone()),
+            };
+            (name.clone(), status)
+        })
+        .collect()
+}
+
+/// Probe a URL before adding a server with it; `server` applies its TLS settings
+#[tauri::command]
+pub async fn mcp_check_reachability(
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    server: Option<String>,
+) -> Result<ReachabilityProbe, String> {
+    Ok(probe(&url, &headers.unwrap_or_default(), server.as_deref()).await)
+}
+
+/// Probe the configured remote servers now instead of waiting for the monitor
+#[tauri::command]
+pub async fn mcp_refresh_reachability(
+    app: AppHandle,
+) -> Result<BTreeMap<String, ReachabilityProbe>, String> {
+    Ok(probe_all(&app).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.extend([0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    #[test]
+    fn test_certificate_not_after_is_read_from_validity() {
+        let validity = [der(0x17, b"250101000000Z"), der(0x18, b"20300615123000Z")].concat();
+        let tbs = [
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[0x01, 0x23]),
+            der(0x30, &der(0x06, &[0x2a, 0x86, 0x48])),
+            der(0x30, &[0u8; 150]),
+            der(0x30, &validity),
+            der(0x30, &[]),
+        ]
+        .concat();
+        let certificate = der(0x30, &[der(0x30, &tbs), der(0x30, &[])].concat());
+
+        let not_after = certificate_not_after(&certificate).unwrap();
+        assert_eq!(not_after.to_rfc3339(), "2030-06-15T12:30:00+00:00");
+        assert_eq!(
+            der_time(0x17, b"491231235959Z").unwrap().to_rfc3339(),
+            "2049-12-31T23:59:59+00:00"
+        );
+        assert!(certificate_not_after(&certificate[..40]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_the_failing_stage() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n");
+            }
+        });
+
+        let url = format!("http://127.0.0.1:{}/mcp", port);
+        let found = probe(&url, &HashMap::new(), None).await;
+        assert_eq!(found.connected_to, Some(format!("127.0.0.1:{}", port)));
+        assert_eq!(found.http_status, Some(404));
+        assert_eq!(found.failed_stage, Some(ProbeStage::Http));
+        assert_eq!(
+            found.summary().as_deref(),
+            Some("HTTP: endpoint not found (404)")
+        );
+
+        // Bind and drop a listener to find a port nothing listens on
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_port = closed.local_addr().unwrap().port();
+        drop(closed);
+        let refused = probe(
+            &format!("http://127.0.0.1:{}/", closed_port),
+            &HashMap::new(),
+            None,
+        )
+        .await;
+        assert_eq!(refused.failed_stage, Some(ProbeStage::Tcp));
+        assert!(refused.certificate.is_none());
+
+        let bad = probe("ftp://example.com/", &HashMap::new(), None).await;
+        assert_eq!(bad.failed_stage, Some(ProbeStage::Url));
+    }
+}
@@ -0,0 +1,209 @@
+#![allow(dead_code)]
+
+//! Advisory scope for a server being added: servers carrying secrets belong in `local` so
+//! they stay out of shared files, servers tied to the repository's files belong in
+//! `project`, and generic tooling belongs in `user` so every project gets it.
+
+use serde::{Deserialize, Serialize};
+
+use super::mcp::NewMcpServer;
+
+/// Fragments of env var, header and option names that usually hold credentials
+const SECRET_MARKERS: &[&str] = &[
+    "KEY",
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "AUTH",
+    "CREDENTIAL",
+    "PRIVATE",
+    "COOKIE",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScopeRecommendation {
+    /// `local`, `project` or `user`
+    pub scope: String,
+    pub reasons: Vec<String>,
+    /// A team policy requires this scope
+    #[serde(default)]
+    pub enforced: bool,
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let upper = name.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// A literal value, as opposed to a `${VAR}` reference resolved from the user's environment
+fn is_literal(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty() && !value.starts_with('$')
+}
+
+fn secret_reasons(server: &NewMcpServer) -> Vec<String> {
+    let mut reasons = Vec::new();
+    let mut env: Vec<_> = server.env.iter().collect();
+    env.sort();
+    for (name, value) in env {
+        if is_secret_name(name) && is_literal(value) {
+            reasons.push(format!("env var {} holds a credential", name));
+        }
+    }
+    let mut headers: Vec<_> = server.headers.iter().collect();
+    headers.sort();
+    for (name, value) in headers {
+        if is_secret_name(name) && is_literal(value) {
+            reasons.push(format!("header {} holds a credential", name));
+        }
+    }
+
+    let mut args = server.args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let Some(option) = arg.strip_prefix("--") else {
+            continue;
+        };
+        let (name, inline_value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option, None),
+        };
+        if !is_secret_name(name) {
+            continue;
+        }
+        let value = inline_value.or_else(|| args.peek().map(|value| value.to_string()));
+        if value.as_deref().is_some_and(is_literal) {
+            reasons.push(format!("argument --{} holds a credential", name));
+        }
+    }
+
+    if let Some(url) = server
+        .url
+        .as_deref()
+        .and_then(|url| reqwest::Url::parse(url).ok())
+    {
+        if url.password().is_some() || !url.username().is_empty() {
+            reasons.push("URL embeds credentials".to_string());
+        }
+        if url.query_pairs().any(|(name, _)| is_secret_name(&name)) {
+            reasons.push("URL query string holds a credential".to_string());
+        }
+    }
+    reasons
+}
+
+fn is_relative_path(value: &str) -> bool {
+    value.starts_with("./") || value.starts_with("../") || value.starts_with(".\\")
+}
+
+fn project_reasons(server: &NewMcpServer, project_path: Option<&str>) -> Vec<String> {
+    let project_path = project_path.map(|path| path.trim_end_matches(['/', '\\']));
+    let mentions_project = |value: &str| {
+        project_path.is_some_and(|project| !project.is_empty() && value.contains(project))
+    };
+
+    let mut reasons = Vec::new();
+    let values = server
+        .command
+        .iter()
+        .chain(server.args.iter())
+        .chain(server.env.values());
+    for value in values {
+        if mentions_project(value) {
+            reasons.push(format!("{} points into the project", value));
+        } else if is_relative_path(value) {
+            reasons.push(format!("{} is relative to the project", value));
+        }
+    }
+    reasons.sort();
+    reasons.dedup();
+    reasons
+}
+
+/// Recommended scope for `server`; secrets take precedence over repository paths
+pub fn recommend_scope(server: &NewMcpServer) -> ScopeRecommendation {
+    let secrets = secret_reasons(server);
+    if !secrets.is_empty() {
+        return ScopeRecommendation {
+            scope: "local".to_string(),
+            reasons: secrets,
+            enforced: false,
+        };
+    }
+    let project = project_reasons(server, server.project_path.as_deref());
+    if !project.is_empty() {
+        return ScopeRecommendation {
+            scope: "project".to_string(),
+            reasons: project,
+            enforced: false,
+        };
+    }
+    ScopeRecommendation {
+        scope: "user".to_string(),
+        reasons: vec!["no secrets or project-specific paths; generic tooling".to_string()],
+        enforced: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn server(args: &[&str], env: &[(&str, &str)]) -> NewMcpServer {
+        NewMcpServer {
+            name: "srv".to_string(),
+            transport: "stdio".to_string(),
+            command: Some("npx".to_string()),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            scope: "local".to_string(),
+            project_path: Some("/work/app".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_secrets_recommend_local_scope() {
+        let recommendation = recommend_scope(&server(
+            &["-y", "server-github", "/work/app/docs"],
+            &[("GITHUB_TOKEN", "ghp_123")],
+        ));
+        assert_eq!(recommendation.scope, "local");
+        assert_eq!(
+            recommendation.reasons,
+            vec!["env var GITHUB_TOKEN holds a credential"]
+        );
+
+        let recommendation = recommend_scope(&server(&["--api-key", "sk-1"], &[]));
+        assert_eq!(recommendation.scope, "local");
+
+        // Referencing the user's environment keeps the secret out of the config
+        let recommendation = recommend_scope(&server(&[], &[("GITHUB_TOKEN", "${GITHUB_TOKEN}")]));
+        assert_eq!(recommendation.scope, "user");
+
+        let mut remote = server(&[], &[]);
+        remote.transport = "sse".to_string();
+        remote.url = Some("https://mcp.example.com/sse?access_token=abc".to_string());
+        assert_eq!(recommend_scope(&remote).scope, "local");
+    }
+
+    #[test]
+    fn test_repository_paths_recommend_project_scope() {
+        let recommendation = recommend_scope(&server(&["-y", "server-fs", "/work/app/src"], &[]));
+        assert_eq!(recommendation.scope, "project");
+        assert_eq!(
+            recommendation.reasons,
+            vec!["/work/app/src points into the project"]
+        );
+
+        let recommendation = recommend_scope(&server(&["./scripts/mcp.js"], &[]));
+        assert_eq!(recommendation.scope, "project");
+
+        let recommendation = recommend_scope(&server(&["-y", "server-fetch"], &[("REGION", "eu")]));
+        assert_eq!(recommendation.scope, "user");
+    }
+}
@@ -0,0 +1,553 @@
+#![allow(dead_code)]
+
+//! MCP stacks: named bundles of MCP servers (e.g. "web-dev", "data") that are added to a
+//! project in one action and removed again as a unit.
+//!
+//! Stacks are written straight into the config file of the chosen scope rather than through
+//! `claude mcp add`, which always targets the app's working directory. Each application
+//! records the servers it actually added, so removing a stack never touches servers that
+//! were already configured under the same name.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+
+use super::activity::{record_activity, ActivityKind, NewActivity};
+use super::agents::AgentDb;
+use super::config_snapshots::{snapshot_config_file, user_claude_json};
+use super::mcp::MCPServerConfig;
+use super::mcp_import::add_json_config;
+
+/// A named bundle of servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpStack {
+    pub id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub servers: BTreeMap<String, MCPServerConfig>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// A server name the stack shares with the target config
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StackConflict {
+    pub name: String,
+    /// The existing entry matches the stack's config exactly
+    pub identical: bool,
+    /// The existing entry was replaced
+    pub overwritten: bool,
+}
+
+/// Outcome of applying a stack
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApplyStackResult {
+    /// Servers the stack added or overwrote
+    pub applied: Vec<String>,
+    pub conflicts: Vec<StackConflict>,
+    /// Config file that was written
+    pub config_path: String,
+}
+
+/// Where a stack has been applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackApplication {
+    pub stack_id: i64,
+    pub project_path: String,
+    pub scope: String,
+    pub servers: Vec<String>,
+    pub applied_at: String,
+}
+
+pub fn init_mcp_stack_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_stacks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            servers TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_stack_applications (
+            stack_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            servers TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (stack_id, project_path, scope),
+            FOREIGN KEY (stack_id) REFERENCES mcp_stacks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn validate_stack(stack: &McpStack) -> Result<(), String> {
+    if stack.name.trim().is_empty() {
+        return Err("Stack name cannot be empty".to_string());
+    }
+    if stack.servers.is_empty() {
+        return Err("A stack needs at least one server".to_string());
+    }
+    if let Some(name) = stack.servers.keys().find(|name| name.trim().is_empty()) {
+        return Err(format!("Invalid server name '{}'", name));
+    }
+    Ok(())
+}
+
+fn row_to_stack(row: &rusqlite::Row) -> SqliteResult<McpStack> {
+    let servers: String = row.get(3)?;
+    Ok(McpStack {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        description: row.get(2)?,
+        servers: serde_json::from_str(&servers).unwrap_or_default(),
+        created_at: Some(row.get(4)?),
+        updated_at: Some(row.get(5)?),
+    })
+}
+
+const STACK_COLUMNS: &str = "id, name, description, servers, created_at, updated_at";
+
+fn load_stack(conn: &Connection, id: i64) -> Result<McpStack, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM mcp_stacks WHERE id = ?1", STACK_COLUMNS),
+        params![id],
+        row_to_stack,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("MCP stack {} not found", id))
+}
+
+fn load_application(
+    conn: &Connection,
+    stack_id: i64,
+    project_path: &str,
+    scope: &str,
+) -> Result<Option<StackApplication>, String> {
+    conn.query_row(
+        "SELECT stack_id, project_path, scope, servers, applied_at FROM mcp_stack_applications
+         WHERE stack_id = ?1 AND project_path = ?2 AND scope = ?3",
+        params![stack_id, project_path, scope],
+        |row| {
+            let servers: String = row.get(3)?;
+            Ok(StackApplication {
+                stack_id: row.get(0)?,
+                project_path: row.get(1)?,
+                scope: row.get(2)?,
+                servers: serde_json::from_str(&servers).unwrap_or_default(),
+                applied_at: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Config file that holds the servers of `scope`
+fn config_path(scope: &str, project_path: &str) -> Result<PathBuf, String> {
+    match scope {
+        "project" => Ok(Path::new(project_path).join(".mcp.json")),
+        "local" | "user" => {
+            user_claude_json().ok_or_else(|| "Failed to get home directory".to_string())
+        }
+        other => Err(format!(
+            "Invalid scope '{}': expected local, project or user",
+            other
+        )),
+    }
+}
+
+/// The `mcpServers` object of `scope` inside a parsed config file, created if missing
+pub fn scope_servers<'a>(
+    config: &'a mut Value,
+    scope: &str,
+    project_path: &str,
+) -> Result<&'a mut Map<String, Value>, String> {
+    let mut node = config;
+    if scope == "local" {
+        node = object_entry(node, "projects")?;
+        node = object_entry(node, project_path)?;
+    }
+    object_entry(node, "mcpServers")?
+        .as_object_mut()
+        .ok_or_else(|| "mcpServers is not an object".to_string())
+}
+
+fn object_entry<'a>(node: &'a mut Value, key: &str) -> Result<&'a mut Value, String> {
+    let object = node
+        .as_object_mut()
+        .ok_or_else(|| format!("Expected an object around '{}'", key))?;
+    Ok(object
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Object(Map::new())))
+}
+
+/// Add the stack's servers to `existing`, reporting names that are already taken. Conflicting
+/// servers are only replaced with `overwrite`; identical entries are left alone either way.
+pub fn merge_stack_servers(
+    existing: &mut Map<String, Value>,
+    servers: &BTreeMap<String, MCPServerConfig>,
+    overwrite: bool,
+) -> (Vec<String>, Vec<StackConflict>) {
+    let mut applied = Vec::new();
+    let mut conflicts = Vec::new();
+    for (name, config) in servers {
+        let config = add_json_config(config);
+        match existing.get(name) {
+            Some(current) => {
+                let identical = *current == config;
+                let overwritten = overwrite && !identical;
+                if overwritten {
+                    existing.insert(name.clone(), config);
+                    applied.push(name.clone());
+                }
+                conflicts.push(StackConflict {
+                    name: name.clone(),
+                    identical,
+                    overwritten,
+                });
+            }
+            None => {
+                existing.insert(name.clone(), config);
+                applied.push(name.clone());
+            }
+        }
+    }
+    (applied, conflicts)
+}
+
+fn read_config(path: &Path) -> Result<Value, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if !content.trim().is_empty() => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Ok(_) => Ok(Value::Object(Map::new())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Value::Object(Map::new())),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn write_config(path: &Path, config: &Value, reason: &str) -> Result<(), String> {
+    snapshot_config_file(path, reason)?;
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    crate::atomic_file::write_atomic(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Create a stack
+#[tauri::command]
+pub async fn create_mcp_stack(db: State<'_, AgentDb>, stack: McpStack) -> Result<McpStack, String> {
+    validate_stack(&stack)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let servers = serde_json::to_string(&stack.servers).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO mcp_stacks (name, description, servers) VALUES (?1, ?2, ?3)",
+        params![stack.name.trim(), stack.description, servers],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            format!("An MCP stack named '{}' already exists", stack.name.trim())
+        }
+        e => e.to_string(),
+    })?;
+    load_stack(&conn, conn.last_insert_rowid())
+}
+
+/// All stacks, by name
+#[tauri::command]
+pub async fn list_mcp_stacks(db: State<'_, AgentDb>) -> Result<Vec<McpStack>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM mcp_stacks ORDER BY name",
+            STACK_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let stacks = stmt
+        .query_map([], row_to_stack)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(stacks)
+}
+
+#[tauri::command]
+pub async fn get_mcp_stack(db: State<'_, AgentDb>, id: i64) -> Result<McpStack, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_stack(&conn, id)
+}
+
+/// Replace a stack's name, description and servers. Projects it was applied to keep the
+/// servers they were given until the stack is removed and applied again.
+#[tauri::command]
+pub async fn update_mcp_stack(
+    db: State<'_, AgentDb>,
+    id: i64,
+    stack: McpStack,
+) -> Result<McpStack, String> {
+    validate_stack(&stack)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let servers = serde_json::to_string(&stack.servers).map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE mcp_stacks SET name = ?1, description = ?2, servers = ?3,
+             updated_at = CURRENT_TIMESTAMP WHERE id = ?4",
+            params![stack.name.trim(), stack.description, servers, id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("MCP stack {} not found", id));
+    }
+    load_stack(&conn, id)
+}
+
+/// Delete a stack. Servers it added to projects stay configured.
+#[tauri::command]
+pub async fn delete_mcp_stack(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM mcp_stack_applications WHERE stack_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM mcp_stacks WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Projects and scopes a stack is currently applied to
+#[tauri::command]
+pub async fn list_stack_applications(
+    db: State<'_, AgentDb>,
+    stack_id: i64,
+) -> Result<Vec<StackApplication>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT project_path, scope FROM mcp_stack_applications
+             WHERE stack_id = ?1 ORDER BY applied_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let keys = stmt
+        .query_map(params![stack_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    let mut applications = Vec::new();
+    for (project_path, scope) in keys {
+        applications.extend(load_application(&conn, stack_id, &project_path, &scope)?);
+    }
+    Ok(applications)
+}
+
+/// Add every server of a stack to `project` in `scope` (`local`, `project` or `user`).
+/// Names that are already configured are reported as conflicts and kept unless `overwrite`.
+#[tauri::command]
+pub async fn apply_stack(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    stack_id: i64,
+    project: String,
+    scope: String,
+    overwrite: Option<bool>,
+) -> Result<ApplyStackResult, String> {
+    let path = config_path(&scope, &project)?;
+    let (name, result) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let stack = load_stack(&conn, stack_id)?;
+
+        let mut config = read_config(&path)?;
+        let servers = scope_servers(&mut config, &scope, &project)?;
+        let (applied, conflicts) =
+            merge_stack_servers(servers, &stack.servers, overwrite.unwrap_or(false));
+        if !applied.is_empty() {
+            write_config(&path, &config, &format!("apply MCP stack {}", stack.name))?;
+        }
+
+        // Re-applying keeps servers added by an earlier application attributed to the stack
+        let mut owned = load_application(&conn, stack_id, &project, &scope)?
+            .map(|application| application.servers)
+            .unwrap_or_default();
+        for name in &applied {
+            if !owned.contains(name) {
+                owned.push(name.clone());
+            }
+        }
+        conn.execute(
+            "INSERT INTO mcp_stack_applications (stack_id, project_path, scope, servers)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(stack_id, project_path, scope) DO UPDATE SET
+                servers = excluded.servers, applied_at = CURRENT_TIMESTAMP",
+            params![
+                stack_id,
+                project,
+                scope,
+                serde_json::to_string(&owned).map_err(|e| e.to_string())?
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+        (
+            stack.name,
+            ApplyStackResult {
+                applied,
+                conflicts,
+                config_path: path.to_string_lossy().to_string(),
+            },
+        )
+    };
+
+    record_activity(
+        &app,
+        NewActivity::new(
+            ActivityKind::McpServerAdded,
+            format!("MCP stack {} applied", name),
+        )
+        .project(project)
+        .detail(serde_json::json!({ "scope": scope, "servers": result.applied })),
+    );
+    Ok(result)
+}
+
+/// Remove the servers a stack added to `project` in `scope`
+#[tauri::command]
+pub async fn remove_stack(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    stack_id: i64,
+    project: String,
+    scope: String,
+) -> Result<Vec<String>, String> {
+    let path = config_path(&scope, &project)?;
+    let (name, removed) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let stack = load_stack(&conn, stack_id)?;
+        let application =
+            load_application(&conn, stack_id, &project, &scope)?.ok_or_else(|| {
+                format!(
+                    "MCP stack {} is not applied to {} ({})",
+                    stack.name, project, scope
+                )
+            })?;
+
+        let mut config = read_config(&path)?;
+        let servers = scope_servers(&mut config, &scope, &project)?;
+        let removed: Vec<String> = application
+            .servers
+            .into_iter()
+            .filter(|name| servers.remove(name).is_some())
+            .collect();
+        if !removed.is_empty() {
+            write_config(&path, &config, &format!("remove MCP stack {}", stack.name))?;
+        }
+        conn.execute(
+            "DELETE FROM mcp_stack_applications WHERE stack_id = ?1 AND project_path = ?2 AND scope = ?3",
+            params![stack_id, project, scope],
+        )
+        .map_err(|e| e.to_string())?;
+        (stack.name, removed)
+    };
+
+    record_activity(
+        &app,
+        NewActivity::new(
+            ActivityKind::McpServerRemoved,
+            format!("MCP stack {} removed", name),
+        )
+        .project(project)
+        .detail(serde_json::json!({ "scope": scope, "servers": removed })),
+    );
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn stdio(command: &str) -> MCPServerConfig {
+        MCPServerConfig {
+            transport_type: "stdio".to_string(),
+            command: command.to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            url: None,
+            headers: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_reports_conflicts_and_only_overwrites_when_asked() {
+        let servers: BTreeMap<String, MCPServerConfig> = [
+            ("fetch".to_string(), stdio("uvx")),
+            ("github".to_string(), stdio("npx")),
+            ("postgres".to_string(), stdio("npx")),
+        ]
+        .into_iter()
+        .collect();
+        let mut existing = Map::new();
+        existing.insert(
+            "github".to_string(),
+            json!({"type": "stdio", "command": "docker"}),
+        );
+        existing.insert("fetch".to_string(), add_json_config(&stdio("uvx")));
+
+        let (applied, conflicts) = merge_stack_servers(&mut existing.clone(), &servers, false);
+        assert_eq!(applied, vec!["postgres"]);
+        assert_eq!(
+            conflicts,
+            vec![
+                StackConflict {
+                    name: "fetch".to_string(),
+                    identical: true,
+                    overwritten: false
+                },
+                StackConflict {
+                    name: "github".to_string(),
+                    identical: false,
+                    overwritten: false
+                },
+            ]
+        );
+
+        let (applied, conflicts) = merge_stack_servers(&mut existing, &servers, true);
+        assert_eq!(applied, vec!["github", "postgres"]);
+        assert!(conflicts[1].overwritten);
+        assert_eq!(existing["github"]["command"], "npx");
+    }
+
+    #[test]
+    fn test_scope_servers_locates_the_scope_entry() {
+        let mut config = json!({
+            "mcpServers": {"global": {}},
+            "projects": {"/work/app": {"allowedTools": []}}
+        });
+
+        scope_servers(&mut config, "local", "/work/app")
+            .unwrap()
+            .insert("local-only".to_string(), json!({}));
+        assert!(config["projects"]["/work/app"]["mcpServers"]["local-only"].is_object());
+        assert!(config["projects"]["/work/app"]["allowedTools"].is_array());
+
+        let user = scope_servers(&mut config, "user", "/work/app").unwrap();
+        assert!(user.contains_key("global"));
+        assert!(!user.contains_key("local-only"));
+
+        let mut fresh = json!({});
+        scope_servers(&mut fresh, "local", "/other").unwrap();
+        assert!(fresh["projects"]["/other"]["mcpServers"].is_object());
+    }
+}
@@ -0,0 +1,288 @@
+#![allow(dead_code)]
+
+//! Catalog of user-facing backend messages. Each message has a stable code and named
+//! parameters, which are sent to the frontend alongside the text rendered in the current
+//! locale, so the UI can show its own translation or fall back to ours.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use tauri::State;
+
+use super::agents::AgentDb;
+
+/// app_settings key holding the locale backend messages are rendered in
+const LOCALE_KEY: &str = "locale";
+
+static CURRENT_LOCALE: RwLock<Locale> = RwLock::new(Locale::En);
+
+/// Locales with a translation in the catalog
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+}
+
+impl Locale {
+    /// Best match for a BCP 47 tag such as `zh-CN`, `zh_TW` or `en-US`
+    pub fn from_tag(tag: &str) -> Locale {
+        match tag.trim().to_lowercase().split(['-', '_']).next() {
+            Some("zh") => Locale::ZhCn,
+            _ => Locale::En,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::ZhCn => "zh-CN",
+        }
+    }
+}
+
+/// Locale messages are currently rendered in
+pub fn current_locale() -> Locale {
+    CURRENT_LOCALE.read().map(|locale| *locale).unwrap_or_default()
+}
+
+pub fn set_current_locale(locale: Locale) {
+    if let Ok(mut current) = CURRENT_LOCALE.write() {
+        *current = locale;
+    }
+}
+
+/// Stable identifiers of backend messages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MessageCode {
+    #[serde(rename = "validation.empty_field")]
+    ValidationEmptyField,
+    #[serde(rename = "validation.invalid_characters")]
+    ValidationInvalidCharacters,
+    #[serde(rename = "validation.invalid_length")]
+    ValidationInvalidLength,
+    #[serde(rename = "validation.invalid_format")]
+    ValidationInvalidFormat,
+    #[serde(rename = "validation.path_traversal")]
+    ValidationPathTraversal,
+    #[serde(rename = "validation.unauthorized_path")]
+    ValidationUnauthorizedPath,
+    #[serde(rename = "terminal.command_too_long")]
+    TerminalCommandTooLong,
+    #[serde(rename = "terminal.command_not_allowed")]
+    TerminalCommandNotAllowed,
+    #[serde(rename = "terminal.directory_not_allowed")]
+    TerminalDirectoryNotAllowed,
+    #[serde(rename = "terminal.streaming_unsupported")]
+    TerminalStreamingUnsupported,
+    #[serde(rename = "terminal.working_dir_missing")]
+    TerminalWorkingDirMissing,
+    #[serde(rename = "mcp.project_config_parse_failed")]
+    McpProjectConfigParseFailed,
+    #[serde(rename = "mcp.project_config_read_failed")]
+    McpProjectConfigReadFailed,
+    #[serde(rename = "operation.cancelled")]
+    OperationCancelled,
+}
+
+impl MessageCode {
+    pub const ALL: &'static [MessageCode] = &[
+        MessageCode::ValidationEmptyField,
+        MessageCode::ValidationInvalidCharacters,
+        MessageCode::ValidationInvalidLength,
+        MessageCode::ValidationInvalidFormat,
+        MessageCode::ValidationPathTraversal,
+        MessageCode::ValidationUnauthorizedPath,
+        MessageCode::TerminalCommandTooLong,
+        MessageCode::TerminalCommandNotAllowed,
+        MessageCode::TerminalDirectoryNotAllowed,
+        MessageCode::TerminalStreamingUnsupported,
+        MessageCode::TerminalWorkingDirMissing,
+        MessageCode::McpProjectConfigParseFailed,
+        MessageCode::McpProjectConfigReadFailed,
+        MessageCode::OperationCancelled,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageCode::ValidationEmptyField => "validation.empty_field",
+            MessageCode::ValidationInvalidCharacters => "validation.invalid_characters",
+            MessageCode::ValidationInvalidLength => "validation.invalid_length",
+            MessageCode::ValidationInvalidFormat => "validation.invalid_format",
+            MessageCode::ValidationPathTraversal => "validation.path_traversal",
+            MessageCode::ValidationUnauthorizedPath => "validation.unauthorized_path",
+            MessageCode::TerminalCommandTooLong => "terminal.command_too_long",
+            MessageCode::TerminalCommandNotAllowed => "terminal.command_not_allowed",
+            MessageCode::TerminalDirectoryNotAllowed => "terminal.directory_not_allowed",
+            MessageCode::TerminalStreamingUnsupported => "terminal.streaming_unsupported",
+            MessageCode::TerminalWorkingDirMissing => "terminal.working_dir_missing",
+            MessageCode::McpProjectConfigParseFailed => "mcp.project_config_parse_failed",
+            MessageCode::McpProjectConfigReadFailed => "mcp.project_config_read_failed",
+            MessageCode::OperationCancelled => "operation.cancelled",
+        }
+    }
+
+    /// Template for `locale`, with `{name}` placeholders for parameters
+    pub fn template(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (MessageCode::ValidationEmptyField, Locale::En) => "{field} cannot be empty",
+            (MessageCode::ValidationEmptyField, Locale::ZhCn) => "{field}不能为空",
+            (MessageCode::ValidationInvalidCharacters, Locale::En) => {
+                "{field} contains invalid characters: {characters}"
+            }
+            (MessageCode::ValidationInvalidCharacters, Locale::ZhCn) => "{field}包含无效字符：{characters}",
+            (MessageCode::ValidationInvalidLength, Locale::En) => {
+                "{field} length {length} exceeds maximum allowed"
+            }
+            (MessageCode::ValidationInvalidLength, Locale::ZhCn) => "{field}长度 {length} 超过允许的最大值",
+            (MessageCode::ValidationInvalidFormat, Locale::En) => "{field} has invalid format: {reason}",
+            (MessageCode::ValidationInvalidFormat, Locale::ZhCn) => "{field}格式无效：{reason}",
+            (MessageCode::ValidationPathTraversal, Locale::En) => "Path traversal detected: {path}",
+            (MessageCode::ValidationPathTraversal, Locale::ZhCn) => "检测到路径遍历：{path}",
+            (MessageCode::ValidationUnauthorizedPath, Locale::En) => "Unauthorized path: {path}",
+            (MessageCode::ValidationUnauthorizedPath, Locale::ZhCn) => "未授权的路径：{path}",
+            (MessageCode::TerminalCommandTooLong, Locale::En) => {
+                "Command exceeds maximum length of {max} characters"
+            }
+            (MessageCode::TerminalCommandTooLong, Locale::ZhCn) => "命令超过最大长度 {max} 个字符",
+            (MessageCode::TerminalCommandNotAllowed, Locale::En) => {
+                "Command not allowed: {command}. Allowed commands: {allowed}"
+            }
+            (MessageCode::TerminalCommandNotAllowed, Locale::ZhCn) => {
+                "不允许执行命令：{command}。允许的命令：{allowed}"
+            }
+            (MessageCode::TerminalDirectoryNotAllowed, Locale::En) => "Access to this directory is not allowed",
+            (MessageCode::TerminalDirectoryNotAllowed, Locale::ZhCn) => "不允许访问此目录",
+            (MessageCode::TerminalStreamingUnsupported, Locale::En) => "Streaming not yet implemented",
+            (MessageCode::TerminalStreamingUnsupported, Locale::ZhCn) => "尚未实现流式输出",
+            (MessageCode::TerminalWorkingDirMissing, Locale::En) => "Working directory does not exist: {path}",
+            (MessageCode::TerminalWorkingDirMissing, Locale::ZhCn) => "工作目录不存在：{path}",
+            (MessageCode::McpProjectConfigParseFailed, Locale::En) => "Failed to parse .mcp.json: {error}",
+            (MessageCode::McpProjectConfigParseFailed, Locale::ZhCn) => "解析 .mcp.json 失败：{error}",
+            (MessageCode::McpProjectConfigReadFailed, Locale::En) => "Failed to read .mcp.json: {error}",
+            (MessageCode::McpProjectConfigReadFailed, Locale::ZhCn) => "读取 .mcp.json 失败：{error}",
+            (MessageCode::OperationCancelled, Locale::En) => "Operation cancelled",
+            (MessageCode::OperationCancelled, Locale::ZhCn) => "操作已取消",
+        }
+    }
+}
+
+/// A message code with its parameters
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Message {
+    pub code: MessageCode,
+    pub params: BTreeMap<String, String>,
+}
+
+impl Message {
+    pub fn new(code: MessageCode) -> Self {
+        Self {
+            code,
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn param(mut self, name: &str, value: impl ToString) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Text in `locale`; unknown placeholders are left as they are
+    pub fn render(&self, locale: Locale) -> String {
+        let mut text = self.code.template(locale).to_string();
+        for (name, value) in &self.params {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+}
+
+impl std::fmt::Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(current_locale()))
+    }
+}
+
+fn load_locale(conn: &Connection) -> Option<Locale> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![LOCALE_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+    .map(|tag| Locale::from_tag(&tag))
+}
+
+/// Apply the saved locale; called once at startup
+pub fn apply_saved_locale(conn: &Connection) {
+    if let Some(locale) = load_locale(conn) {
+        set_current_locale(locale);
+    }
+}
+
+/// Locale backend messages are rendered in
+#[tauri::command]
+pub async fn get_locale() -> Result<Locale, String> {
+    Ok(current_locale())
+}
+
+/// Render backend messages in `locale` (a tag such as `zh-CN`) from now on
+#[tauri::command]
+pub async fn set_locale(db: State<'_, AgentDb>, locale: String) -> Result<Locale, String> {
+    let locale = Locale::from_tag(&locale);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![LOCALE_KEY, locale.as_str()],
+    )
+    .map_err(|e| format!("Failed to save locale: {}", e))?;
+    set_current_locale(locale);
+    Ok(locale)
+}
+
+/// Every message template for `locale` (the current one when omitted), by code
+#[tauri::command]
+pub async fn get_message_catalog(locale: Option<String>) -> Result<BTreeMap<String, String>, String> {
+    let locale = locale.map(|tag| Locale::from_tag(&tag)).unwrap_or_else(current_locale);
+    Ok(MessageCode::ALL
+        .iter()
+        .map(|code| (code.as_str().to_string(), code.template(locale).to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_in_each_locale() {
+        let message = Message::new(MessageCode::ValidationEmptyField).param("field", "Server name");
+        assert_eq!(message.render(Locale::En), "Server name cannot be empty");
+        assert_eq!(message.render(Locale::ZhCn), "Server name不能为空");
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({ "code": "validation.empty_field", "params": { "field": "Server name" } })
+        );
+
+        assert_eq!(Locale::from_tag("zh_TW"), Locale::ZhCn);
+        assert_eq!(Locale::from_tag("en-US"), Locale::En);
+        assert_eq!(Locale::from_tag("fr"), Locale::En);
+    }
+
+    #[test]
+    fn test_codes_are_unique_and_match_serde() {
+        let mut seen = std::collections::HashSet::new();
+        for code in MessageCode::ALL {
+            assert!(seen.insert(code.as_str()), "duplicate code {}", code.as_str());
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+            for locale in [Locale::En, Locale::ZhCn] {
+                assert!(!code.template(locale).is_empty());
+            }
+        }
+    }
+}
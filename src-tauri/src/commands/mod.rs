@@ -1,10 +1,96 @@
+pub mod activity;
+pub mod agent_retry;
 pub mod agents;
+pub mod app_config;
+pub mod app_lock;
+pub mod approval_mcp;
+pub mod artifacts;
+pub mod attachments;
+pub mod auth_status;
+pub mod background;
+pub mod batches;
+pub mod cache_savings;
+pub mod cancellation;
 pub mod claude;
+pub mod cli_invoker;
+pub mod config_backups;
+pub mod config_merge;
+pub mod config_snapshots;
+pub mod config_sync;
+pub mod containers;
+pub mod crash;
+pub mod db_maintenance;
+pub mod deep_link;
+pub mod digests;
+pub mod encryption;
+pub mod error;
+pub mod error_stats;
+pub mod event_broker;
+pub mod experiments;
+pub mod file_changes;
+pub mod file_hotspots;
+pub mod keychain;
+pub mod logs;
 pub mod mcp;
+pub mod mcp_capabilities;
+pub mod mcp_config_watcher;
+pub mod mcp_guardrails;
+pub mod mcp_import;
+pub mod mcp_lint;
+pub mod mcp_prerequisites;
+pub mod mcp_reachability;
+pub mod mcp_scope;
+pub mod mcp_stacks;
+pub mod messages;
+pub mod model_policy;
+pub mod notifications;
+pub mod offline;
+pub mod output_bench;
+pub mod permission_relay;
+pub mod project_init;
+pub mod project_stats;
+pub mod pricing;
+pub mod print_tasks;
+pub mod prompt_templates;
+pub mod providers;
+pub mod provider_profiles;
 pub mod proxy;
+pub mod quick_run;
+pub mod recent_projects;
+pub mod redaction;
+pub mod remote_hosts;
+pub mod replay;
+pub mod retention;
+pub mod rollback;
+pub mod run_bundle;
+pub mod run_guards;
+pub mod run_queue;
+pub mod run_watchdog;
+pub mod sandbox;
+pub mod session_diff;
+pub mod session_index;
+pub mod session_insights;
+pub mod session_merge;
+pub mod session_watcher;
+pub mod session_window;
+pub mod settings;
 pub mod slash_commands;
 pub mod skills;
+pub mod ssh_tunnels;
 pub mod storage;
+pub mod team_policy;
+pub mod telemetry;
 pub mod terminal;
+pub mod thinking;
+pub mod tls_settings;
+pub mod tokens;
+pub mod tool_rules;
+pub mod tool_usage;
+pub mod tray;
 pub mod usage;
+pub mod usage_backfill;
+pub mod verbosity;
 pub mod version;
+pub mod webhooks;
+pub mod workspaces;
+pub mod worktrees;
@@ -0,0 +1,199 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::agent_retry::FailureKind;
+use super::agents::AgentDb;
+
+/// Upper bound for fallbacks so a chain can't keep a run bouncing between models
+const MAX_FALLBACK_MODELS: usize = 5;
+
+/// Primary model plus ordered fallbacks used when the primary is unavailable
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPolicy {
+    pub primary: String,
+    pub fallbacks: Vec<String>,
+}
+
+impl ModelPolicy {
+    /// Validate the policy before persisting it
+    pub fn validate(&self) -> Result<(), String> {
+        if self.primary.trim().is_empty() {
+            return Err("Primary model cannot be empty".to_string());
+        }
+        if self.fallbacks.len() > MAX_FALLBACK_MODELS {
+            return Err(format!(
+                "At most {} fallback models are allowed",
+                MAX_FALLBACK_MODELS
+            ));
+        }
+        for (i, model) in self.fallbacks.iter().enumerate() {
+            if model.trim().is_empty() {
+                return Err("Fallback models cannot be empty".to_string());
+            }
+            if model == &self.primary || self.fallbacks[..i].contains(model) {
+                return Err(format!("Model '{}' appears more than once", model));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a failure should switch models rather than wait for a retry
+    pub fn fails_over_on(failure: FailureKind) -> bool {
+        matches!(failure, FailureKind::RateLimit | FailureKind::Overloaded)
+    }
+
+    /// Model to try after `current` failed; runs started outside the chain
+    /// (e.g. an explicit model override) fall back to the first fallback
+    pub fn next_after(&self, current: &str) -> Option<&str> {
+        if current == self.primary {
+            return self.fallbacks.first().map(|m| m.as_str());
+        }
+        match self.fallbacks.iter().position(|m| m == current) {
+            Some(i) => self.fallbacks.get(i + 1).map(|m| m.as_str()),
+            None => self.fallbacks.first().map(|m| m.as_str()),
+        }
+    }
+
+    /// The CLI's own `--fallback-model` only takes a single model, so sessions get the first
+    /// fallback that differs from the model being launched
+    pub fn cli_fallback_args(model: &str, fallbacks: &[String]) -> Vec<String> {
+        match fallbacks.iter().find(|m| m.as_str() != model) {
+            Some(fallback) => vec!["--fallback-model".to_string(), fallback.clone()],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Extract the model that served a run from a stream-json line
+pub fn served_model_from_line(line: &str) -> Option<String> {
+    let json = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    let model = match json.get("type").and_then(|t| t.as_str()) {
+        Some("system") => json.get("model"),
+        Some("assistant") => json.get("message").and_then(|m| m.get("model")),
+        _ => None,
+    }?;
+    model
+        .as_str()
+        .filter(|m| !m.is_empty() && *m != "<synthetic>")
+        .map(|m| m.to_string())
+}
+
+/// Create the model policy table
+pub fn init_model_policy_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_model_policies (
+            agent_id INTEGER PRIMARY KEY,
+            fallback_models TEXT NOT NULL DEFAULT '[]',
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Load the model policy for an agent; the agent's own model is the primary
+pub fn load_model_policy(conn: &Connection, agent_id: i64) -> SqliteResult<ModelPolicy> {
+    let primary: String = conn.query_row(
+        "SELECT model FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    )?;
+    let fallbacks: Option<String> = conn
+        .query_row(
+            "SELECT fallback_models FROM agent_model_policies WHERE agent_id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(ModelPolicy {
+        primary,
+        fallbacks: fallbacks
+            .and_then(|f| serde_json::from_str(&f).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// Get the model policy configured for an agent
+#[tauri::command]
+pub async fn get_agent_model_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<ModelPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_model_policy(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Set the primary model and fallbacks for an agent
+#[tauri::command]
+pub async fn set_agent_model_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    policy: ModelPolicy,
+) -> Result<ModelPolicy, String> {
+    policy.validate()?;
+
+    let fallbacks = serde_json::to_string(&policy.fallbacks)
+        .map_err(|e| format!("Failed to serialize fallback models: {}", e))?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agents SET model = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![policy.primary, agent_id],
+    )
+    .map_err(|e| format!("Failed to update agent model: {}", e))?;
+    conn.execute(
+        "INSERT INTO agent_model_policies (agent_id, fallback_models) VALUES (?1, ?2)
+         ON CONFLICT(agent_id) DO UPDATE SET fallback_models = ?2, updated_at = CURRENT_TIMESTAMP",
+        params![agent_id, fallbacks],
+    )
+    .map_err(|e| format!("Failed to save model policy: {}", e))?;
+
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ModelPolicy {
+        ModelPolicy {
+            primary: "opus".to_string(),
+            fallbacks: vec!["sonnet".to_string(), "haiku".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_next_after_walks_the_chain() {
+        let policy = policy();
+        assert_eq!(policy.next_after("opus"), Some("sonnet"));
+        assert_eq!(policy.next_after("sonnet"), Some("haiku"));
+        assert_eq!(policy.next_after("haiku"), None);
+        assert_eq!(policy.next_after("claude-opus-4-1"), Some("sonnet"));
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicates() {
+        let mut policy = policy();
+        assert!(policy.validate().is_ok());
+        policy.fallbacks.push("opus".to_string());
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn test_served_model_from_stream() {
+        assert_eq!(
+            served_model_from_line(r#"{"type":"system","subtype":"init","model":"claude-sonnet-4"}"#),
+            Some("claude-sonnet-4".to_string())
+        );
+        assert_eq!(
+            served_model_from_line(r#"{"type":"assistant","message":{"model":"claude-opus-4"}}"#),
+            Some("claude-opus-4".to_string())
+        );
+        assert_eq!(served_model_from_line(r#"{"type":"result"}"#), None);
+    }
+}
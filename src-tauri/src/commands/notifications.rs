@@ -0,0 +1,270 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::commands::agents::AgentDb;
+
+/// Kinds of lifecycle events that can raise a desktop notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    RunCompleted,
+    RunFailed,
+    BudgetThreshold,
+    PermissionRequired,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    pub on_run_completed: bool,
+    pub on_run_failed: bool,
+    pub on_budget_threshold: bool,
+    pub on_permission_request: bool,
+    /// Only notify when no app window has focus
+    pub only_when_unfocused: bool,
+    /// Interactive Claude sessions shorter than this don't notify on completion
+    pub min_session_duration_secs: u64,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_run_completed: true,
+            on_run_failed: true,
+            on_budget_threshold: true,
+            on_permission_request: true,
+            only_when_unfocused: true,
+            min_session_duration_secs: 60,
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// Whether notifications for the given event type are turned on
+    pub fn allows(&self, event: NotificationEvent) -> bool {
+        self.enabled
+            && match event {
+                NotificationEvent::RunCompleted => self.on_run_completed,
+                NotificationEvent::RunFailed => self.on_run_failed,
+                NotificationEvent::BudgetThreshold => self.on_budget_threshold,
+                NotificationEvent::PermissionRequired => self.on_permission_request,
+            }
+    }
+
+    /// Whether an event should raise a notification given the current window focus
+    pub fn should_show(&self, event: NotificationEvent, app_focused: bool) -> bool {
+        self.allows(event) && !(self.only_when_unfocused && app_focused)
+    }
+}
+
+/// Body of the notification sent when an agent run finishes, `failure` being the
+/// failure kind of a failed run
+pub fn run_outcome_body(run_id: i64, duration_ms: i64, failure: Option<&str>) -> String {
+    let duration = format!("{:.1}s", duration_ms as f64 / 1000.0);
+    match failure {
+        None => format!("Run {} completed in {}", run_id, duration),
+        Some(kind) => format!("Run {} failed after {} ({})", run_id, duration, kind),
+    }
+}
+
+/// Load notification settings from the app_settings table
+pub fn load_notification_settings(conn: &Connection) -> NotificationSettings {
+    let mut settings = NotificationSettings::default();
+
+    let keys = vec![
+        ("notify_enabled", "enabled"),
+        ("notify_run_completed", "on_run_completed"),
+        ("notify_run_failed", "on_run_failed"),
+        ("notify_budget_threshold", "on_budget_threshold"),
+        ("notify_permission_request", "on_permission_request"),
+        ("notify_only_when_unfocused", "only_when_unfocused"),
+        ("notify_min_session_duration_secs", "min_session_duration_secs"),
+    ];
+
+    for (db_key, field) in keys {
+        if let Ok(value) = conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![db_key],
+            |row| row.get::<_, String>(0),
+        ) {
+            match field {
+                "enabled" => settings.enabled = value == "true",
+                "on_run_completed" => settings.on_run_completed = value == "true",
+                "on_run_failed" => settings.on_run_failed = value == "true",
+                "on_budget_threshold" => settings.on_budget_threshold = value == "true",
+                "on_permission_request" => settings.on_permission_request = value == "true",
+                "only_when_unfocused" => settings.only_when_unfocused = value == "true",
+                "min_session_duration_secs" => {
+                    if let Ok(secs) = value.parse() {
+                        settings.min_session_duration_secs = secs;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    settings
+}
+
+/// Get notification settings from the database
+#[tauri::command]
+pub async fn get_notification_settings(
+    db: State<'_, AgentDb>,
+) -> Result<NotificationSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_notification_settings(&conn))
+}
+
+/// Save notification settings to the database
+#[tauri::command]
+pub async fn save_notification_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    settings: NotificationSettings,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let values = vec![
+        ("notify_enabled", settings.enabled.to_string()),
+        ("notify_run_completed", settings.on_run_completed.to_string()),
+        ("notify_run_failed", settings.on_run_failed.to_string()),
+        (
+            "notify_budget_threshold",
+            settings.on_budget_threshold.to_string(),
+        ),
+        (
+            "notify_permission_request",
+            settings.on_permission_request.to_string(),
+        ),
+        (
+            "notify_only_when_unfocused",
+            settings.only_when_unfocused.to_string(),
+        ),
+        (
+            "notify_min_session_duration_secs",
+            settings.min_session_duration_secs.to_string(),
+        ),
+    ];
+
+    let mut keys = Vec::with_capacity(values.len());
+    for (key, value) in values {
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+        keys.push(key);
+    }
+
+    super::settings::notify_settings_changed(&app, &conn, &keys);
+    Ok(())
+}
+
+/// Send a test notification, bypassing the per-event toggles
+#[tauri::command]
+pub async fn send_test_notification(app: AppHandle) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title("opcode")
+        .body("Notifications are working")
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+/// Whether any of the app's windows currently has focus
+fn app_has_focus(app: &AppHandle) -> bool {
+    app.webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Load the current notification settings through the managed database
+pub fn current_settings(app: &AppHandle) -> NotificationSettings {
+    match app.try_state::<AgentDb>() {
+        Some(db) => match db.0.lock() {
+            Ok(conn) => load_notification_settings(&conn),
+            Err(_) => NotificationSettings::default(),
+        },
+        None => NotificationSettings::default(),
+    }
+}
+
+/// Show a desktop notification for a lifecycle event if the user's settings allow it
+pub fn notify(app: &AppHandle, event: NotificationEvent, title: &str, body: &str) {
+    let settings = current_settings(app);
+    if !settings.allows(event) {
+        return;
+    }
+    if !settings.should_show(event, app_has_focus(app)) {
+        log::debug!("Skipping {:?} notification because the window is focused", event);
+        return;
+    }
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show {:?} notification: {}", event, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_event_toggles_and_focus_filter() {
+        let mut settings = NotificationSettings {
+            on_budget_threshold: false,
+            ..Default::default()
+        };
+        assert!(settings.should_show(NotificationEvent::PermissionRequired, false));
+        assert!(!settings.should_show(NotificationEvent::BudgetThreshold, false));
+        assert!(!settings.should_show(NotificationEvent::RunCompleted, true));
+
+        settings.only_when_unfocused = false;
+        assert!(settings.should_show(NotificationEvent::RunCompleted, true));
+
+        settings.enabled = false;
+        assert!(!settings.should_show(NotificationEvent::RunFailed, false));
+    }
+
+    #[test]
+    fn test_load_settings_from_app_settings() {
+        let conn = conn();
+        conn.execute_batch(
+            "INSERT INTO app_settings VALUES ('notify_permission_request', 'false');
+             INSERT INTO app_settings VALUES ('notify_min_session_duration_secs', '5');
+             INSERT INTO app_settings VALUES ('notify_run_failed', 'oops');",
+        )
+        .unwrap();
+
+        let settings = load_notification_settings(&conn);
+        assert!(!settings.allows(NotificationEvent::PermissionRequired));
+        assert!(!settings.allows(NotificationEvent::RunFailed));
+        assert!(settings.allows(NotificationEvent::RunCompleted));
+        assert_eq!(settings.min_session_duration_secs, 5);
+    }
+
+    #[test]
+    fn test_run_outcome_body() {
+        assert_eq!(run_outcome_body(4, 12_340, None), "Run 4 completed in 12.3s");
+        assert_eq!(
+            run_outcome_body(5, 900, Some("rate_limit")),
+            "Run 5 failed after 0.9s (rate_limit)"
+        );
+    }
+}
@@ -0,0 +1,621 @@
+#![allow(dead_code)]
+
+//! Offline mode. When the network or the Claude API is unreachable (or the user turns offline
+//! mode on), data that normally needs the network is served from the last good copy in the
+//! database and marked stale, and mutating MCP operations are queued and replayed in order
+//! once connectivity returns.
+//!
+//! Usage data, session transcripts and local agent definitions are read from `~/.claude` and
+//! the agents database and keep working offline as they are; what needs a cache is the
+//! `claude mcp list` output (which health-checks every server) and the GitHub agent catalog.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::{AgentDb, GitHubAgentFile};
+use super::error::OpcodeError;
+use super::event_broker::{publish, Channel};
+use super::mcp::{AddServerResult, MCPServer, NewMcpServer};
+use super::providers::{ClaudeCliRunner, SystemClaudeCli};
+
+/// app_settings key for offline mode turned on by the user
+pub const OFFLINE_MODE_KEY: &str = "offline_mode";
+
+/// Emitted with an [`OfflineStatus`] whenever the app goes offline or comes back
+pub const OFFLINE_STATUS_EVENT: &str = "offline-status-changed";
+
+/// Endpoint probed to tell whether the Claude API is reachable; any HTTP answer counts
+const PROBE_URL: &str = "https://api.anthropic.com";
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the background monitor probes connectivity
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+const MCP_SERVERS_CACHE_KEY: &str = "mcp_servers";
+const GITHUB_AGENTS_CACHE_KEY: &str = "github_agents";
+
+/// Offline mode turned on in settings
+static FORCED_OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// The last probe failed
+static NETWORK_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Whether network-bound data should come from the cache
+pub fn is_offline() -> bool {
+    FORCED_OFFLINE.load(Ordering::Relaxed) || NETWORK_DOWN.load(Ordering::Relaxed)
+}
+
+/// Apply the offline mode stored in settings
+pub fn apply_offline_mode(enabled: Option<bool>) {
+    FORCED_OFFLINE.store(enabled.unwrap_or(false), Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OfflineStatus {
+    pub offline: bool,
+    /// Offline because the user turned offline mode on, rather than a failed probe
+    pub forced: bool,
+    /// MCP operations waiting for connectivity
+    pub queued_operations: i64,
+}
+
+/// Data together with where it came from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Cached<T> {
+    pub data: T,
+    /// When the data was fetched; `None` for data fetched just now
+    pub cached_at: Option<String>,
+    /// Served from the cache because fetching fresh data wasn't possible
+    pub stale: bool,
+}
+
+/// A mutating MCP operation held back while offline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueuedMcpOperation {
+    Add {
+        server: NewMcpServer,
+    },
+    AddJson {
+        name: String,
+        json_config: String,
+        scope: String,
+    },
+    Remove {
+        name: String,
+    },
+}
+
+impl QueuedMcpOperation {
+    fn describe(&self) -> String {
+        match self {
+            QueuedMcpOperation::Add { server } => format!("add {}", server.name),
+            QueuedMcpOperation::AddJson { name, .. } => format!("add {}", name),
+            QueuedMcpOperation::Remove { name } => format!("remove {}", name),
+        }
+    }
+
+    /// Carry the operation out with the CLI
+    fn run(&self, cli: &dyn ClaudeCliRunner) -> Result<String, String> {
+        match self {
+            QueuedMcpOperation::Add { server } => {
+                let result = super::mcp::add_server(cli, server.clone());
+                if result.success {
+                    Ok(result.message)
+                } else {
+                    Err(result.message)
+                }
+            }
+            QueuedMcpOperation::AddJson {
+                name,
+                json_config,
+                scope,
+            } => cli.run_mcp(
+                &[
+                    "add-json".to_string(),
+                    name.clone(),
+                    json_config.clone(),
+                    "-s".to_string(),
+                    scope.clone(),
+                ],
+                &super::cancellation::CancellationToken::new(),
+            ),
+            QueuedMcpOperation::Remove { name } => {
+                super::mcp::remove_server(cli, name).map_err(|e| e.message)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueuedOperation {
+    pub id: i64,
+    pub operation: QueuedMcpOperation,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+/// Outcome of replaying the queue
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    /// Operations left in the queue, starting with the one that failed
+    pub remaining: usize,
+    pub error: Option<String>,
+}
+
+/// Create the cache and queue tables
+pub fn init_offline_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS offline_cache (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            cached_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS offline_mcp_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            operation TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Remember `data` as the last good copy under `key`
+pub fn store_cached<T: Serialize>(conn: &Connection, key: &str, data: &T) -> Result<(), String> {
+    let value = serde_json::to_string(data).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO offline_cache (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2, cached_at = CURRENT_TIMESTAMP",
+        params![key, value],
+    )
+    .map_err(|e| format!("Failed to cache {}: {}", key, e))?;
+    Ok(())
+}
+
+/// The last good copy under `key`, marked stale; `None` if there is none or it no longer parses
+pub fn load_cached<T: DeserializeOwned>(conn: &Connection, key: &str) -> Option<Cached<T>> {
+    let (value, cached_at): (String, String) = conn
+        .query_row(
+            "SELECT value, cached_at FROM offline_cache WHERE key = ?1",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .ok()??;
+    let data = serde_json::from_str(&value).ok()?;
+    Some(Cached {
+        data,
+        cached_at: Some(cached_at),
+        stale: true,
+    })
+}
+
+/// Cache a fresh result, or fall back to the cached copy when `fetched` failed. `fetched` is
+/// `None` when offline and nothing was attempted.
+pub fn resolve_cached<T: Serialize + DeserializeOwned>(
+    conn: &Connection,
+    key: &str,
+    fetched: Option<Result<T, String>>,
+) -> Result<Cached<T>, String> {
+    let error = match fetched {
+        Some(Ok(data)) => {
+            if let Err(e) = store_cached(conn, key, &data) {
+                log::warn!("{}", e);
+            }
+            return Ok(Cached {
+                data,
+                cached_at: None,
+                stale: false,
+            });
+        }
+        Some(Err(e)) => e,
+        None => "Offline and nothing cached yet".to_string(),
+    };
+    load_cached(conn, key).ok_or(error)
+}
+
+pub fn queue_operation(conn: &Connection, operation: &QueuedMcpOperation) -> Result<i64, String> {
+    let value = serde_json::to_string(operation).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO offline_mcp_queue (operation) VALUES (?1)",
+        params![value],
+    )
+    .map_err(|e| format!("Failed to queue MCP operation: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn list_queue(conn: &Connection) -> Result<Vec<QueuedOperation>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, operation, attempts, last_error, created_at
+             FROM offline_mcp_queue ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut queue = Vec::new();
+    for row in rows {
+        let (id, operation, attempts, last_error, created_at) = row.map_err(|e| e.to_string())?;
+        match serde_json::from_str(&operation) {
+            Ok(operation) => queue.push(QueuedOperation {
+                id,
+                operation,
+                attempts,
+                last_error,
+                created_at,
+            }),
+            Err(e) => log::warn!("Skipping unreadable queued MCP operation {}: {}", id, e),
+        }
+    }
+    Ok(queue)
+}
+
+fn queue_length(conn: &Connection) -> i64 {
+    conn.query_row("SELECT COUNT(*) FROM offline_mcp_queue", [], |row| {
+        row.get(0)
+    })
+    .unwrap_or(0)
+}
+
+/// Run queued operations oldest first, stopping at the first failure so later operations
+/// (such as the add half of a rename) never overtake earlier ones. The database is only
+/// locked between operations, not while the CLI runs.
+pub fn replay_queue(
+    db: &std::sync::Mutex<Connection>,
+    cli: &dyn ClaudeCliRunner,
+) -> Result<ReplayReport, String> {
+    let queue = list_queue(&*db.lock().map_err(|e| e.to_string())?)?;
+    let mut report = ReplayReport::default();
+    for (index, queued) in queue.iter().enumerate() {
+        let result = queued.operation.run(cli);
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        match result {
+            Ok(_) => {
+                log::info!(
+                    "Replayed queued MCP operation: {}",
+                    queued.operation.describe()
+                );
+                conn.execute(
+                    "DELETE FROM offline_mcp_queue WHERE id = ?1",
+                    params![queued.id],
+                )
+                .map_err(|e| e.to_string())?;
+                report.replayed += 1;
+            }
+            Err(e) => {
+                log::warn!(
+                    "Queued MCP operation {} failed: {}",
+                    queued.operation.describe(),
+                    e
+                );
+                conn.execute(
+                    "UPDATE offline_mcp_queue SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+                    params![queued.id, e],
+                )
+                .map_err(|e| e.to_string())?;
+                report.remaining = queue.len() - index;
+                report.error = Some(e);
+                break;
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn current_status(conn: &Connection) -> OfflineStatus {
+    OfflineStatus {
+        offline: is_offline(),
+        forced: FORCED_OFFLINE.load(Ordering::Relaxed),
+        queued_operations: queue_length(conn),
+    }
+}
+
+fn status_for_app(app: &AppHandle) -> Result<OfflineStatus, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(current_status(&conn))
+}
+
+/// Queue `operation` for when the app is back online
+pub fn queue_for_app(app: &AppHandle, operation: QueuedMcpOperation) -> Result<String, String> {
+    let description = operation.describe();
+    let status = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        queue_operation(&conn, &operation)?;
+        current_status(&conn)
+    };
+    let _ = publish(app, Channel::Connectivity, OFFLINE_STATUS_EVENT, &status);
+    log::info!("Offline: queued MCP operation {}", description);
+    Ok(format!(
+        "Offline: \"{}\" was queued and will run when connectivity returns",
+        description
+    ))
+}
+
+/// [`AddServerResult`] for an add that was queued instead of run
+pub fn queued_add_result(app: &AppHandle, operation: QueuedMcpOperation) -> AddServerResult {
+    let name = match &operation {
+        QueuedMcpOperation::Add { server } => Some(server.name.clone()),
+        QueuedMcpOperation::AddJson { name, .. } => Some(name.clone()),
+        QueuedMcpOperation::Remove { .. } => None,
+    };
+    match queue_for_app(app, operation) {
+        Ok(message) => AddServerResult {
+            success: true,
+            message,
+            server_name: name,
+            recommendation: None,
+        },
+        Err(message) => AddServerResult {
+            success: false,
+            message,
+            server_name: None,
+            recommendation: None,
+        },
+    }
+}
+
+/// Whether the Claude API answers at all
+async fn probe_connectivity() -> bool {
+    // Trust the custom CA bundle, as TLS-inspecting proxies re-sign the API's certificate
+    let client = super::tls_settings::configure_client(reqwest::Client::builder(), None)
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+    client.head(PROBE_URL).send().await.is_ok()
+}
+
+/// Probe connectivity and react to a change: announce it, and replay the queue on the way
+/// back online
+async fn refresh_connectivity(app: &AppHandle) -> Result<OfflineStatus, String> {
+    let was_offline = is_offline();
+    let reachable = probe_connectivity().await;
+    NETWORK_DOWN.store(!reachable, Ordering::Relaxed);
+    if was_offline && !is_offline() {
+        log::info!("Connectivity restored");
+        replay_for_app(app).await?;
+    } else if !was_offline && is_offline() {
+        log::warn!("Claude API unreachable; switching to offline mode");
+    }
+    let status = status_for_app(app)?;
+    if was_offline != status.offline {
+        let _ = publish(app, Channel::Connectivity, OFFLINE_STATUS_EVENT, &status);
+    }
+    Ok(status)
+}
+
+async fn replay_for_app(app: &AppHandle) -> Result<ReplayReport, String> {
+    let cli = SystemClaudeCli::for_app(app)?;
+    let handle = app.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        replay_queue(&handle.state::<AgentDb>().0, &cli)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    if report.replayed > 0 {
+        let _ = publish(app, Channel::Connectivity, OFFLINE_STATUS_EVENT, &status_for_app(app)?);
+    }
+    Ok(report)
+}
+
+/// Probe connectivity in the background so the app notices outages and recoveries
+pub fn spawn_connectivity_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if !FORCED_OFFLINE.load(Ordering::Relaxed) {
+                if let Err(e) = refresh_connectivity(&app).await {
+                    log::warn!("Connectivity check failed: {}", e);
+                }
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_offline_status(db: State<'_, AgentDb>) -> Result<OfflineStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(current_status(&conn))
+}
+
+/// Turn offline mode on or off. Turning it off probes connectivity and replays the queue.
+#[tauri::command]
+pub async fn set_offline_mode(app: AppHandle, enabled: bool) -> Result<OfflineStatus, String> {
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::settings::set_setting_as(&conn, OFFLINE_MODE_KEY, &enabled)?;
+    }
+    let was_offline = is_offline();
+    apply_offline_mode(Some(enabled));
+    if enabled {
+        let status = status_for_app(&app)?;
+        let _ = publish(&app, Channel::Connectivity, OFFLINE_STATUS_EVENT, &status);
+        return Ok(status);
+    }
+    // Replay even if the probe state didn't change, since the user was forcing offline
+    NETWORK_DOWN.store(was_offline, Ordering::Relaxed);
+    refresh_connectivity(&app).await
+}
+
+/// Probe connectivity now instead of waiting for the background monitor
+#[tauri::command]
+pub async fn check_connectivity(app: AppHandle) -> Result<OfflineStatus, String> {
+    refresh_connectivity(&app).await
+}
+
+/// MCP servers from `claude mcp list`, or the last good list when offline or the CLI fails
+#[tauri::command]
+pub async fn mcp_list_cached(app: AppHandle) -> Result<Cached<Vec<MCPServer>>, OpcodeError> {
+    let fetched = if is_offline() {
+        None
+    } else {
+        Some(
+            super::mcp::mcp_list(app.clone(), None)
+                .await
+                .map_err(|e| e.message),
+        )
+    };
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(resolve_cached(&conn, MCP_SERVERS_CACHE_KEY, fetched)?)
+}
+
+/// The GitHub agent catalog, or the last good copy when offline or GitHub is unreachable
+#[tauri::command]
+pub async fn fetch_github_agents_cached(
+    db: State<'_, AgentDb>,
+) -> Result<Cached<Vec<GitHubAgentFile>>, String> {
+    let fetched = if is_offline() {
+        None
+    } else {
+        Some(super::agents::fetch_github_agents().await)
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    resolve_cached(&conn, GITHUB_AGENTS_CACHE_KEY, fetched)
+}
+
+#[tauri::command]
+pub async fn list_queued_mcp_operations(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<QueuedOperation>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_queue(&conn)
+}
+
+#[tauri::command]
+pub async fn discard_queued_mcp_operation(app: AppHandle, id: i64) -> Result<(), String> {
+    let status = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM offline_mcp_queue WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        current_status(&conn)
+    };
+    let _ = publish(&app, Channel::Connectivity, OFFLINE_STATUS_EVENT, &status);
+    Ok(())
+}
+
+/// Replay queued MCP operations now
+#[tauri::command]
+pub async fn replay_queued_mcp_operations(app: AppHandle) -> Result<ReplayReport, String> {
+    if is_offline() {
+        return Err(
+            "Still offline; queued operations will replay when connectivity returns".to_string(),
+        );
+    }
+    replay_for_app(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::cancellation::CancellationToken;
+    use std::sync::Mutex;
+
+    /// Records the commands it's asked to run, failing the ones matching `fail_on`
+    struct FakeCli {
+        calls: Mutex<Vec<Vec<String>>>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl ClaudeCliRunner for FakeCli {
+        fn run_mcp(&self, args: &[String], _token: &CancellationToken) -> Result<String, String> {
+            self.calls.lock().unwrap().push(args.to_vec());
+            if self
+                .fail_on
+                .is_some_and(|name| args.iter().any(|arg| arg == name))
+            {
+                return Err("Command failed: network unreachable".to_string());
+            }
+            Ok("ok".to_string())
+        }
+    }
+
+    fn conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_offline_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_failed_fetch_serves_stale_cache() {
+        let conn = conn();
+        assert!(resolve_cached::<Vec<String>>(&conn, "servers", None).is_err());
+
+        let fresh = resolve_cached(&conn, "servers", Some(Ok(vec!["fs".to_string()]))).unwrap();
+        assert!(!fresh.stale);
+        assert_eq!(fresh.cached_at, None);
+
+        let stale = resolve_cached::<Vec<String>>(
+            &conn,
+            "servers",
+            Some(Err("Command timed out".to_string())),
+        )
+        .unwrap();
+        assert!(stale.stale);
+        assert!(stale.cached_at.is_some());
+        assert_eq!(stale.data, vec!["fs".to_string()]);
+    }
+
+    #[test]
+    fn test_replay_runs_in_order_and_stops_at_failure() {
+        let conn = conn();
+        let remove = |name: &str| QueuedMcpOperation::Remove {
+            name: name.to_string(),
+        };
+        queue_operation(&conn, &remove("old")).unwrap();
+        queue_operation(
+            &conn,
+            &QueuedMcpOperation::AddJson {
+                name: "new".to_string(),
+                json_config: "{}".to_string(),
+                scope: "user".to_string(),
+            },
+        )
+        .unwrap();
+        queue_operation(&conn, &remove("other")).unwrap();
+
+        let cli = FakeCli {
+            calls: Mutex::new(Vec::new()),
+            fail_on: Some("new"),
+        };
+        let db = Mutex::new(conn);
+        let report = replay_queue(&db, &cli).unwrap();
+        assert_eq!(report.replayed, 1);
+        assert_eq!(report.remaining, 2);
+        assert_eq!(cli.calls.lock().unwrap().len(), 2);
+
+        let conn = db.into_inner().unwrap();
+
+        let queue = list_queue(&conn).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue[0].attempts, 1);
+        assert!(queue[0].last_error.is_some());
+        assert_eq!(queue[1].operation, remove("other"));
+    }
+}
@@ -0,0 +1,204 @@
+#![allow(dead_code)]
+
+//! Throughput benchmark of the output pipeline every line of a run goes through: the live
+//! output buffer, the stream-json parsing for usage, and the serialization for the event
+//! streamer. Agents can produce tens of MB per minute, so regressions here show up as a
+//! laggy UI long before anything fails.
+//!
+//! `bench_output_pipeline` is a hidden command for the developer console, and
+//! `benches/output_pipeline.rs` runs the same stages from `cargo bench`. The latest result is
+//! included in the diagnostic report.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::hint::black_box;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::event_broker::{Channel, EventBroker};
+use crate::process::usage::RunUsageTracker;
+use crate::process::{CircularOutputBuffer, OutputStream, StreamLine};
+
+/// Largest amount of synthetic output a run may generate
+const MAX_TOTAL_BYTES: usize = 512 * 1024 * 1024;
+
+/// Content blocks per assistant message; the CLI repeats a message's usage on each
+const BLOCKS_PER_MESSAGE: usize = 4;
+
+static LAST_RESULT: Mutex<Option<OutputPipelineBench>> = Mutex::new(None);
+
+/// Throughput of one stage
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StageResult {
+    pub stage: String,
+    pub elapsed_ms: f64,
+    pub lines_per_sec: f64,
+    pub mb_per_sec: f64,
+}
+
+/// Result of a benchmark run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutputPipelineBench {
+    pub lines: usize,
+    pub line_size: usize,
+    pub total_bytes: usize,
+    pub stages: Vec<StageResult>,
+    pub ran_at: String,
+}
+
+impl OutputPipelineBench {
+    /// One-line summary for the diagnostic report
+    pub fn summary(&self) -> String {
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| format!("{} {:.1} MB/s", stage.stage, stage.mb_per_sec))
+            .collect();
+        format!(
+            "{} ({} lines × {} B, {})",
+            stages.join(", "),
+            self.lines,
+            self.line_size,
+            self.ran_at
+        )
+    }
+}
+
+/// Stream-json lines shaped like an agent's output, each padded to about `line_size` bytes
+pub fn synthetic_lines(lines: usize, line_size: usize) -> Vec<String> {
+    (0..lines)
+        .map(|i| {
+            let mut line = json!({
+                "type": "assistant",
+                "message": {
+                    "id": format!("msg_{:08}", i / BLOCKS_PER_MESSAGE),
+                    "model": "claude-sonnet-4-5",
+                    "content": [{ "type": "text", "text": "" }],
+                    "usage": {
+                        "input_tokens": 1200 + i % 7,
+                        "output_tokens": 300 + i % 11,
+                        "cache_read_input_tokens": 20_000,
+                    },
+                },
+                "session_id": "6f1c1e2a-bench",
+            });
+            let padding = line_size.saturating_sub(line.to_string().len());
+            line["message"]["content"][0]["text"] = Value::String("x".repeat(padding));
+            line.to_string()
+        })
+        .collect()
+}
+
+fn stage(name: &str, lines: &[String], mut run: impl FnMut(&str)) -> StageResult {
+    let bytes: usize = lines.iter().map(|line| line.len()).sum();
+    let start = Instant::now();
+    for line in lines {
+        run(line);
+    }
+    let elapsed = start.elapsed().as_secs_f64().max(f64::EPSILON);
+    StageResult {
+        stage: name.to_string(),
+        elapsed_ms: elapsed * 1000.0,
+        lines_per_sec: lines.len() as f64 / elapsed,
+        mb_per_sec: bytes as f64 / elapsed / (1024.0 * 1024.0),
+    }
+}
+
+/// Push `lines` synthetic lines of `line_size` bytes through each stage of the pipeline
+pub fn run_output_pipeline_bench(
+    lines: usize,
+    line_size: usize,
+) -> Result<OutputPipelineBench, String> {
+    if lines == 0 || line_size == 0 {
+        return Err("The number of lines and the line size must be positive".to_string());
+    }
+    if lines.saturating_mul(line_size) > MAX_TOTAL_BYTES {
+        return Err(format!(
+            "The benchmark is limited to {} MB of output",
+            MAX_TOTAL_BYTES / (1024 * 1024)
+        ));
+    }
+    let input = synthetic_lines(lines, line_size);
+
+    // Same limits as a run's live output buffer
+    let mut buffer = CircularOutputBuffer::new(1000, 10 * 1024 * 1024);
+    let buffered = stage("circular_buffer", &input, |line| {
+        buffer.append_from(OutputStream::Stdout, line)
+    });
+
+    let mut tracker = RunUsageTracker::new(0, "claude-sonnet-4-5");
+    let parsed = stage("jsonl_parser", &input, |line| {
+        black_box(serde_json::from_str::<Value>(line).ok());
+        tracker.observe_line(line);
+    });
+    black_box(tracker.snapshot());
+
+    let broker = EventBroker::default();
+    let streamed = stage("event_streamer", &input, |line| {
+        let event = StreamLine::new(OutputStream::Stdout, line.to_string());
+        let payload = serde_json::to_value(event).unwrap_or(Value::Null);
+        black_box(broker.prepare(Channel::AgentOutput, payload).to_string());
+    });
+
+    let result = OutputPipelineBench {
+        lines,
+        line_size,
+        total_bytes: input.iter().map(|line| line.len()).sum(),
+        stages: vec![buffered, parsed, streamed],
+        ran_at: chrono::Local::now().to_rfc3339(),
+    };
+    if let Ok(mut last) = LAST_RESULT.lock() {
+        *last = Some(result.clone());
+    }
+    Ok(result)
+}
+
+/// Latest benchmark result of this session
+pub fn last_result() -> Option<OutputPipelineBench> {
+    LAST_RESULT.lock().ok().and_then(|last| last.clone())
+}
+
+/// Benchmark the output pipeline; not exposed in the UI, call it from the developer console
+#[tauri::command]
+pub async fn bench_output_pipeline(
+    lines: Option<usize>,
+    line_size: Option<usize>,
+) -> Result<OutputPipelineBench, String> {
+    let lines = lines.unwrap_or(100_000);
+    let line_size = line_size.unwrap_or(1024);
+    tokio::task::spawn_blocking(move || run_output_pipeline_bench(lines, line_size))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_lines_are_sized_stream_json() {
+        let lines = synthetic_lines(8, 512);
+        assert_eq!(lines.len(), 8);
+        for line in &lines {
+            assert_eq!(line.len(), 512);
+            let json: Value = serde_json::from_str(line).unwrap();
+            assert_eq!(json["type"], "assistant");
+        }
+        assert!(synthetic_lines(1, 10)[0].len() > 10);
+    }
+
+    #[test]
+    fn test_bench_reports_every_stage_and_is_kept() {
+        let result = run_output_pipeline_bench(200, 256).unwrap();
+        let stages: Vec<&str> = result.stages.iter().map(|s| s.stage.as_str()).collect();
+        assert_eq!(
+            stages,
+            ["circular_buffer", "jsonl_parser", "event_streamer"]
+        );
+        assert!(result.stages.iter().all(|s| s.mb_per_sec > 0.0));
+        assert_eq!(result.total_bytes, 200 * 256);
+        assert!(last_result().is_some());
+        assert!(run_output_pipeline_bench(0, 256).is_err());
+        assert!(run_output_pipeline_bench(usize::MAX, 1024).is_err());
+    }
+}
@@ -0,0 +1,392 @@
+#![allow(dead_code)]
+
+//! Tool permission prompts relayed to the UI. Sessions started with `interactive_permissions`
+//! run without `--dangerously-skip-permissions`; instead the CLI is pointed at stdio with
+//! `--permission-prompt-tool stdio` and reads its prompt as a stream-json message. Each
+//! decision it needs arrives on stdout as a `can_use_tool` control request, is announced
+//! with a `run:permission-request` event and waits for `respond_permission`, whose answer
+//! is written back to the process's stdin.
+//!
+//! Stdin is closed once the run reports its result, which lets the CLI exit.
+//!
+//! Approval calls of the built-in approval MCP server wait here too, on a channel instead of
+//! stdin, so `respond_permission` answers both kinds.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::oneshot;
+
+/// Payload of the `run:permission-request` event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PermissionRequest {
+    pub run_id: i64,
+    /// Pass back to `respond_permission`
+    pub request_id: String,
+    pub tool_name: String,
+    pub input: Value,
+    pub tool_use_id: Option<String>,
+    /// Permission rules the CLI suggests for always allowing this tool
+    pub suggestions: Option<Value>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Parse a `can_use_tool` control request from a stdout line
+pub fn parse_permission_request(run_id: i64, line: &str) -> Option<PermissionRequest> {
+    // Cheap check first, as this runs on every line of output
+    if !line.contains("can_use_tool") {
+        return None;
+    }
+    let json: Value = serde_json::from_str(line).ok()?;
+    if json.get("type").and_then(Value::as_str) != Some("control_request") {
+        return None;
+    }
+    let request = json.get("request")?;
+    if request.get("subtype").and_then(Value::as_str) != Some("can_use_tool") {
+        return None;
+    }
+    Some(PermissionRequest {
+        run_id,
+        request_id: json.get("request_id")?.as_str()?.to_string(),
+        tool_name: request.get("tool_name")?.as_str()?.to_string(),
+        input: request.get("input").cloned().unwrap_or_else(|| json!({})),
+        tool_use_id: request
+            .get("tool_use_id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        suggestions: request.get("permission_suggestions").cloned(),
+        received_at: Utc::now(),
+    })
+}
+
+/// Whether a stdout line is the run's final result, after which no more prompts come
+pub fn is_result_line(line: &str) -> bool {
+    line.contains("\"result\"")
+        && serde_json::from_str::<Value>(line)
+            .is_ok_and(|json| json.get("type").and_then(Value::as_str) == Some("result"))
+}
+
+/// The decision the CLI expects from a permission prompt tool
+pub fn permission_decision(input: &Value, allow: bool, message: Option<&str>) -> Value {
+    if allow {
+        json!({ "behavior": "allow", "updatedInput": input })
+    } else {
+        json!({
+            "behavior": "deny",
+            "message": message.unwrap_or("The user denied this tool use"),
+        })
+    }
+}
+
+/// The control response answering a permission request
+pub fn permission_response_line(
+    request: &PermissionRequest,
+    allow: bool,
+    message: Option<&str>,
+) -> String {
+    json!({
+        "type": "control_response",
+        "response": {
+            "subtype": "success",
+            "request_id": request.request_id,
+            "response": permission_decision(&request.input, allow, message),
+        },
+    })
+    .to_string()
+}
+
+/// The user message carrying the prompt on stdin
+pub fn prompt_message_line(prompt: &str) -> String {
+    json!({
+        "type": "user",
+        "message": { "role": "user", "content": prompt },
+    })
+    .to_string()
+}
+
+/// Turn print-mode arguments into relayed ones: the prompt after `-p` is taken out to be
+/// sent on stdin, and permissions are asked over stdio instead of being skipped.
+/// Returns the new arguments and the prompt.
+pub fn relay_args(args: Vec<String>) -> (Vec<String>, String) {
+    let mut relayed = Vec::with_capacity(args.len() + 3);
+    let mut prompt = String::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" => {
+                prompt = args.next().unwrap_or_default();
+                relayed.push(arg);
+                relayed.push("--input-format".to_string());
+                relayed.push("stream-json".to_string());
+            }
+            "--dangerously-skip-permissions" => {}
+            _ => relayed.push(arg),
+        }
+    }
+    relayed.push("--permission-prompt-tool".to_string());
+    relayed.push("stdio".to_string());
+    (relayed, prompt)
+}
+
+/// [`relay_args`] for runs started with interactive permissions; other runs keep theirs
+pub fn relay_args_if(enabled: Option<bool>, args: Vec<String>) -> (Vec<String>, Option<String>) {
+    if enabled.unwrap_or(false) {
+        let (args, prompt) = relay_args(args);
+        (args, Some(prompt))
+    } else {
+        (args, None)
+    }
+}
+
+/// Where the answer to a request goes
+enum Reply {
+    /// A control response on the run's stdin
+    Stdin(Arc<tokio::sync::Mutex<ChildStdin>>),
+    /// The decision, to a caller waiting for it
+    Channel(oneshot::Sender<Value>),
+}
+
+struct PendingRequest {
+    request: PermissionRequest,
+    reply: Reply,
+}
+
+/// Stdin handles of relayed runs and the requests waiting for an answer
+#[derive(Default)]
+pub struct PermissionRelayState {
+    stdins: Mutex<HashMap<i64, Arc<tokio::sync::Mutex<ChildStdin>>>>,
+    pending: Mutex<HashMap<(i64, String), PendingRequest>>,
+}
+
+impl PermissionRelayState {
+    /// Start relaying for a run, taking over its stdin
+    pub fn attach(&self, run_id: i64, stdin: ChildStdin) {
+        self.stdins
+            .lock()
+            .unwrap()
+            .insert(run_id, Arc::new(tokio::sync::Mutex::new(stdin)));
+    }
+
+    /// Stop relaying for a run: its stdin is closed and requests still waiting are dropped,
+    /// which denies them
+    pub fn detach(&self, run_id: i64) {
+        self.stdins.lock().unwrap().remove(&run_id);
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|(id, _), _| *id != run_id);
+    }
+
+    /// Remember a request seen on a run's stdout; false when the run isn't relayed
+    pub fn record(&self, request: PermissionRequest) -> bool {
+        let Some(stdin) = self.stdins.lock().unwrap().get(&request.run_id).cloned() else {
+            return false;
+        };
+        self.insert(request, Reply::Stdin(stdin));
+        true
+    }
+
+    /// Remember a request whose decision is delivered on the returned channel
+    pub fn wait_for(&self, request: PermissionRequest) -> oneshot::Receiver<Value> {
+        let (sender, receiver) = oneshot::channel();
+        self.insert(request, Reply::Channel(sender));
+        receiver
+    }
+
+    fn insert(&self, request: PermissionRequest, reply: Reply) {
+        self.pending.lock().unwrap().insert(
+            (request.run_id, request.request_id.clone()),
+            PendingRequest { request, reply },
+        );
+    }
+
+    pub fn pending(&self, run_id: Option<i64>) -> Vec<PermissionRequest> {
+        let mut pending: Vec<PermissionRequest> = self
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|pending| run_id.is_none_or(|run_id| pending.request.run_id == run_id))
+            .map(|pending| pending.request.clone())
+            .collect();
+        pending.sort_by_key(|request| request.received_at);
+        pending
+    }
+
+    fn take(&self, run_id: i64, request_id: &str) -> Result<PendingRequest, String> {
+        self.pending
+            .lock()
+            .unwrap()
+            .remove(&(run_id, request_id.to_string()))
+            .ok_or_else(|| {
+                format!(
+                    "Permission request {} of run {} was already answered or does not exist",
+                    request_id, run_id
+                )
+            })
+    }
+}
+
+/// Announce a recorded permission request to the frontend
+pub fn announce(app: &AppHandle, request: &PermissionRequest) {
+    log::info!(
+        "Run {} asks permission for {} ({})",
+        request.run_id,
+        request.tool_name,
+        request.request_id
+    );
+    let _ = app.emit("run:permission-request", request);
+    let _ = app.emit(
+        &format!("run:permission-request:{}", request.run_id),
+        request,
+    );
+    super::notifications::notify(
+        app,
+        super::notifications::NotificationEvent::PermissionRequired,
+        "Permission required",
+        &format!(
+            "Run {} is waiting for permission to use {}",
+            request.run_id, request.tool_name
+        ),
+    );
+}
+
+/// Record a permission request seen on a run's stdout and announce it to the frontend
+pub fn relay_request(app: &AppHandle, relay: &PermissionRelayState, request: PermissionRequest) {
+    if !relay.record(request.clone()) {
+        log::warn!(
+            "Permission request {} for run {} without a relayed stdin",
+            request.request_id,
+            request.run_id
+        );
+        return;
+    }
+    announce(app, &request);
+}
+
+/// Answer a pending permission request of a run
+#[tauri::command]
+pub async fn respond_permission(
+    relay: State<'_, PermissionRelayState>,
+    run_id: i64,
+    request_id: String,
+    allow: bool,
+    message: Option<String>,
+) -> Result<(), String> {
+    let PendingRequest { request, reply } = relay.take(run_id, &request_id)?;
+    match reply {
+        Reply::Stdin(stdin) => {
+            let mut line = permission_response_line(&request, allow, message.as_deref());
+            line.push('\n');
+
+            let mut stdin = stdin.lock().await;
+            stdin
+                .write_all(line.as_bytes())
+                .await
+                .map_err(|e| format!("Failed to answer permission request: {}", e))?;
+            stdin
+                .flush()
+                .await
+                .map_err(|e| format!("Failed to answer permission request: {}", e))?;
+        }
+        Reply::Channel(sender) => {
+            let decision = permission_decision(&request.input, allow, message.as_deref());
+            sender
+                .send(decision)
+                .map_err(|_| format!("Run {} no longer waits for this answer", run_id))?;
+        }
+    }
+    log::info!(
+        "{} {} for run {}",
+        if allow { "Allowed" } else { "Denied" },
+        request.tool_name,
+        run_id
+    );
+    Ok(())
+}
+
+/// Permission requests still waiting for an answer, for one run or all of them
+#[tauri::command]
+pub async fn list_pending_permissions(
+    relay: State<'_, PermissionRelayState>,
+    run_id: Option<i64>,
+) -> Result<Vec<PermissionRequest>, String> {
+    Ok(relay.pending(run_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_request_round_trip() {
+        let line = r#"{"type":"control_request","request_id":"req-1","request":{"subtype":"can_use_tool","tool_name":"Bash","input":{"command":"rm -rf build"},"tool_use_id":"toolu_1"}}"#;
+        let request = parse_permission_request(7, line).unwrap();
+        assert_eq!(request.run_id, 7);
+        assert_eq!(request.request_id, "req-1");
+        assert_eq!(request.tool_name, "Bash");
+        assert_eq!(request.tool_use_id.as_deref(), Some("toolu_1"));
+
+        let allow: Value =
+            serde_json::from_str(&permission_response_line(&request, true, None)).unwrap();
+        assert_eq!(allow["type"], "control_response");
+        assert_eq!(allow["response"]["request_id"], "req-1");
+        assert_eq!(allow["response"]["response"]["behavior"], "allow");
+        assert_eq!(
+            allow["response"]["response"]["updatedInput"]["command"],
+            "rm -rf build"
+        );
+
+        let deny: Value =
+            serde_json::from_str(&permission_response_line(&request, false, Some("Not now")))
+                .unwrap();
+        assert_eq!(deny["response"]["response"]["behavior"], "deny");
+        assert_eq!(deny["response"]["response"]["message"], "Not now");
+
+        assert!(parse_permission_request(
+            7,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"can_use_tool"}]}}"#
+        )
+        .is_none());
+        assert!(is_result_line(
+            r#"{"type":"result","subtype":"success","result":"done"}"#
+        ));
+        assert!(!is_result_line(
+            r#"{"type":"assistant","message":{"content":"result"}}"#
+        ));
+    }
+
+    #[test]
+    fn test_relay_args_move_the_prompt_to_stdin() {
+        let args = vec![
+            "--resume".to_string(),
+            "abc".to_string(),
+            "-p".to_string(),
+            "Fix the bug".to_string(),
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--dangerously-skip-permissions".to_string(),
+        ];
+        let (args, prompt) = relay_args(args);
+        assert_eq!(prompt, "Fix the bug");
+        assert_eq!(
+            args,
+            vec![
+                "--resume",
+                "abc",
+                "-p",
+                "--input-format",
+                "stream-json",
+                "--output-format",
+                "stream-json",
+                "--permission-prompt-tool",
+                "stdio",
+            ]
+        );
+    }
+}
@@ -0,0 +1,525 @@
+#![allow(dead_code)]
+
+//! Model prices used for cost estimates. A default table ships with the app; newer tables
+//! can be fetched from a configurable URL with `update_price_table`. Every table is stored
+//! under its version and never changed, so costs can be recomputed with the prices that
+//! applied at the time. Per-model overrides (e.g. negotiated enterprise pricing) take
+//! precedence over whichever table is active.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the `PricingSettings`
+pub const PRICING_SETTINGS_KEY: &str = "pricing";
+
+/// Version of the table compiled into the app
+pub const BUNDLED_PRICE_VERSION: &str = "bundled-1";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Prices in USD per million tokens for models whose id contains `model`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub model: String,
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceTable {
+    pub version: String,
+    pub models: Vec<ModelPrice>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceTableInfo {
+    pub version: String,
+    /// `bundled` or the URL the table was fetched from
+    pub source: String,
+    pub fetched_at: Option<String>,
+    pub model_count: usize,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PricingSettings {
+    /// Where `update_price_table` fetches tables from
+    pub url: Option<String>,
+    /// Table used for new estimates; the newest fetched table when unset
+    pub active_version: Option<String>,
+}
+
+/// The table and overrides new estimates use
+#[derive(Debug, Clone)]
+struct ActivePricing {
+    table: PriceTable,
+    overrides: Vec<ModelPrice>,
+}
+
+/// Kept in sync with the `pricing` setting and the override table
+static ACTIVE_PRICING: RwLock<Option<ActivePricing>> = RwLock::new(None);
+
+fn price(model: &str, input: f64, output: f64, cache_write: f64, cache_read: f64) -> ModelPrice {
+    ModelPrice {
+        model: model.to_string(),
+        input,
+        output,
+        cache_write,
+        cache_read,
+    }
+}
+
+pub fn bundled_price_table() -> PriceTable {
+    PriceTable {
+        version: BUNDLED_PRICE_VERSION.to_string(),
+        models: vec![
+            price("opus-4", 15.0, 75.0, 18.75, 1.50),
+            price("sonnet-4", 3.0, 15.0, 3.75, 0.30),
+        ],
+    }
+}
+
+/// The price whose pattern is the longest match for `model`, overrides first
+pub fn resolve_price<'a>(
+    model: &str,
+    table: &'a PriceTable,
+    overrides: &'a [ModelPrice],
+) -> Option<&'a ModelPrice> {
+    let longest = |prices: &'a [ModelPrice]| {
+        prices
+            .iter()
+            .filter(|price| !price.model.is_empty() && model.contains(price.model.as_str()))
+            .max_by_key(|price| price.model.len())
+    };
+    longest(overrides).or_else(|| longest(&table.models))
+}
+
+/// Cost in USD of a token count at `price`
+pub fn cost_at(
+    price: &ModelPrice,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+) -> f64 {
+    (input_tokens as f64 * price.input
+        + output_tokens as f64 * price.output
+        + cache_creation_tokens as f64 * price.cache_write
+        + cache_read_tokens as f64 * price.cache_read)
+        / 1_000_000.0
+}
+
+/// Price of `model` under the active table and overrides
+pub fn active_price(model: &str) -> Option<ModelPrice> {
+    let active = ACTIVE_PRICING.read().ok()?;
+    match active.as_ref() {
+        Some(active) => resolve_price(model, &active.table, &active.overrides).cloned(),
+        None => resolve_price(model, &bundled_price_table(), &[]).cloned(),
+    }
+}
+
+/// Version of the table new estimates use
+pub fn active_price_version() -> String {
+    ACTIVE_PRICING
+        .read()
+        .ok()
+        .and_then(|active| active.as_ref().map(|active| active.table.version.clone()))
+        .unwrap_or_else(|| BUNDLED_PRICE_VERSION.to_string())
+}
+
+/// Check a table before storing it
+pub fn validate_price_table(table: &PriceTable) -> Result<(), String> {
+    if table.version.trim().is_empty() {
+        return Err("Price table has no version".to_string());
+    }
+    if table.models.is_empty() {
+        return Err(format!("Price table {} lists no models", table.version));
+    }
+    for price in &table.models {
+        validate_price(price)?;
+    }
+    Ok(())
+}
+
+fn validate_price(price: &ModelPrice) -> Result<(), String> {
+    if price.model.trim().is_empty() {
+        return Err("Model name cannot be empty".to_string());
+    }
+    let values = [
+        price.input,
+        price.output,
+        price.cache_write,
+        price.cache_read,
+    ];
+    if values
+        .iter()
+        .any(|value| !value.is_finite() || *value < 0.0)
+    {
+        return Err(format!(
+            "Prices for {} must be non-negative numbers",
+            price.model
+        ));
+    }
+    Ok(())
+}
+
+pub fn init_pricing_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS price_tables (
+            version TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            models TEXT NOT NULL,
+            fetched_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS price_overrides (
+            model TEXT PRIMARY KEY,
+            input REAL NOT NULL,
+            output REAL NOT NULL,
+            cache_write REAL NOT NULL,
+            cache_read REAL NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn load_pricing_settings(conn: &Connection) -> PricingSettings {
+    get_setting_as(conn, PRICING_SETTINGS_KEY).unwrap_or_default()
+}
+
+/// A stored table by version; the bundled table is always available
+pub fn load_price_table(conn: &Connection, version: &str) -> Result<PriceTable, String> {
+    if version == BUNDLED_PRICE_VERSION {
+        return Ok(bundled_price_table());
+    }
+    let models: String = conn
+        .query_row(
+            "SELECT models FROM price_tables WHERE version = ?1",
+            params![version],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Price table {} not found", version))?;
+    Ok(PriceTable {
+        version: version.to_string(),
+        models: serde_json::from_str(&models).map_err(|e| e.to_string())?,
+    })
+}
+
+fn newest_fetched_version(conn: &Connection) -> Option<String> {
+    conn.query_row(
+        "SELECT version FROM price_tables ORDER BY fetched_at DESC, rowid DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+fn active_version(conn: &Connection) -> String {
+    load_pricing_settings(conn)
+        .active_version
+        .or_else(|| newest_fetched_version(conn))
+        .unwrap_or_else(|| BUNDLED_PRICE_VERSION.to_string())
+}
+
+fn load_overrides(conn: &Connection) -> SqliteResult<Vec<ModelPrice>> {
+    let mut stmt = conn.prepare(
+        "SELECT model, input, output, cache_write, cache_read FROM price_overrides ORDER BY model",
+    )?;
+    let overrides = stmt
+        .query_map([], |row| {
+            Ok(ModelPrice {
+                model: row.get(0)?,
+                input: row.get(1)?,
+                output: row.get(2)?,
+                cache_write: row.get(3)?,
+                cache_read: row.get(4)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>();
+    overrides
+}
+
+/// Load the active table and overrides into the cache used by cost estimates
+pub fn apply_pricing(conn: &Connection) {
+    let version = active_version(conn);
+    let table = load_price_table(conn, &version).unwrap_or_else(|e| {
+        log::warn!("Falling back to bundled prices: {}", e);
+        bundled_price_table()
+    });
+    let overrides = load_overrides(conn).unwrap_or_default();
+    if let Ok(mut active) = ACTIVE_PRICING.write() {
+        *active = Some(ActivePricing { table, overrides });
+    }
+}
+
+/// Store a fetched table; a version that is already stored must not change
+pub fn store_price_table(
+    conn: &Connection,
+    table: &PriceTable,
+    source: &str,
+) -> Result<bool, String> {
+    validate_price_table(table)?;
+    if table.version == BUNDLED_PRICE_VERSION {
+        return Err(format!(
+            "Version {} is reserved for the bundled table",
+            table.version
+        ));
+    }
+    if let Ok(existing) = load_price_table(conn, &table.version) {
+        if existing.models != table.models {
+            return Err(format!(
+                "Price table {} changed without a new version; refusing to overwrite it",
+                table.version
+            ));
+        }
+        return Ok(false);
+    }
+    let models = serde_json::to_string(&table.models).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO price_tables (version, source, models) VALUES (?1, ?2, ?3)",
+        params![table.version, source, models],
+    )
+    .map_err(|e| format!("Failed to store price table: {}", e))?;
+    Ok(true)
+}
+
+/// A price table; the active one when no version is given
+#[tauri::command]
+pub async fn get_price_table(
+    db: State<'_, AgentDb>,
+    version: Option<String>,
+) -> Result<PriceTable, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let version = version.unwrap_or_else(|| active_version(&conn));
+    load_price_table(&conn, &version)
+}
+
+/// Every stored table, newest first, then the bundled one
+#[tauri::command]
+pub async fn list_price_tables(db: State<'_, AgentDb>) -> Result<Vec<PriceTableInfo>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let active = active_version(&conn);
+    let mut stmt = conn
+        .prepare("SELECT version, source, models, fetched_at FROM price_tables ORDER BY fetched_at DESC, rowid DESC")
+        .map_err(|e| e.to_string())?;
+    let mut tables = stmt
+        .query_map([], |row| {
+            let version: String = row.get(0)?;
+            let models: String = row.get(2)?;
+            Ok(PriceTableInfo {
+                active: version == active,
+                version,
+                source: row.get(1)?,
+                fetched_at: row.get(3)?,
+                model_count: serde_json::from_str::<Vec<ModelPrice>>(&models)
+                    .map(|models| models.len())
+                    .unwrap_or(0),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    tables.push(PriceTableInfo {
+        version: BUNDLED_PRICE_VERSION.to_string(),
+        source: "bundled".to_string(),
+        fetched_at: None,
+        model_count: bundled_price_table().models.len(),
+        active: active == BUNDLED_PRICE_VERSION,
+    });
+    Ok(tables)
+}
+
+/// Fetch the maintained price table from `url` (or the configured URL) and make it active
+#[tauri::command]
+pub async fn update_price_table(
+    db: State<'_, AgentDb>,
+    url: Option<String>,
+) -> Result<PriceTable, String> {
+    let url = match url {
+        Some(url) => url,
+        None => {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            load_pricing_settings(&conn)
+                .url
+                .ok_or("No price table URL configured")?
+        }
+    };
+    reqwest::Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+    let table: PriceTable = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch price table: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid price table: {}", e))?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if store_price_table(&conn, &table, &url)? {
+        log::info!("Stored price table {} from {}", table.version, url);
+    }
+    let mut settings = load_pricing_settings(&conn);
+    settings.active_version = Some(table.version.clone());
+    set_setting_as(&conn, PRICING_SETTINGS_KEY, &settings)?;
+    apply_pricing(&conn);
+    Ok(table)
+}
+
+#[tauri::command]
+pub async fn get_pricing_settings(db: State<'_, AgentDb>) -> Result<PricingSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_pricing_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_pricing_settings(
+    db: State<'_, AgentDb>,
+    settings: PricingSettings,
+) -> Result<PricingSettings, String> {
+    if let Some(url) = &settings.url {
+        reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(version) = &settings.active_version {
+        load_price_table(&conn, version)?;
+    }
+    set_setting_as(&conn, PRICING_SETTINGS_KEY, &settings)?;
+    apply_pricing(&conn);
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn list_price_overrides(db: State<'_, AgentDb>) -> Result<Vec<ModelPrice>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_overrides(&conn).map_err(|e| e.to_string())
+}
+
+/// Override the price of models matching `price.model`
+#[tauri::command]
+pub async fn set_price_override(
+    db: State<'_, AgentDb>,
+    price: ModelPrice,
+) -> Result<ModelPrice, String> {
+    validate_price(&price)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO price_overrides (model, input, output, cache_write, cache_read) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(model) DO UPDATE SET input = excluded.input, output = excluded.output, cache_write = excluded.cache_write, cache_read = excluded.cache_read, updated_at = CURRENT_TIMESTAMP",
+        params![price.model, price.input, price.output, price.cache_write, price.cache_read],
+    )
+    .map_err(|e| format!("Failed to save price override: {}", e))?;
+    apply_pricing(&conn);
+    Ok(price)
+}
+
+#[tauri::command]
+pub async fn remove_price_override(db: State<'_, AgentDb>, model: String) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let removed = conn
+        .execute(
+            "DELETE FROM price_overrides WHERE model = ?1",
+            params![model],
+        )
+        .map_err(|e| e.to_string())?;
+    apply_pricing(&conn);
+    Ok(removed > 0)
+}
+
+/// Cost of a token count with a given table version (the active one by default), including
+/// overrides. Used to recompute historical costs with the prices recorded alongside them.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn estimate_cost(
+    db: State<'_, AgentDb>,
+    model: String,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    version: Option<String>,
+) -> Result<f64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let version = version.unwrap_or_else(|| active_version(&conn));
+    let table = load_price_table(&conn, &version)?;
+    let overrides = load_overrides(&conn).map_err(|e| e.to_string())?;
+    Ok(resolve_price(&model, &table, &overrides)
+        .map(|price| {
+            cost_at(
+                price,
+                input_tokens,
+                output_tokens,
+                cache_creation_tokens,
+                cache_read_tokens,
+            )
+        })
+        .unwrap_or(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_price_prefers_overrides_and_longest_match() {
+        let mut table = bundled_price_table();
+        table.models.push(price("opus-4-1", 20.0, 100.0, 25.0, 2.0));
+        let overrides = vec![price("sonnet-4", 2.0, 10.0, 2.5, 0.2)];
+
+        let opus = resolve_price("claude-opus-4-1-20250805", &table, &overrides).unwrap();
+        assert_eq!(opus.model, "opus-4-1");
+        let opus = resolve_price("claude-opus-4-20250514", &table, &overrides).unwrap();
+        assert_eq!(opus.input, 15.0);
+        let sonnet = resolve_price("claude-sonnet-4-20250514", &table, &overrides).unwrap();
+        assert_eq!(sonnet.input, 2.0);
+        assert!(resolve_price("gpt-4o", &table, &overrides).is_none());
+
+        let cost = cost_at(opus, 1_000_000, 100_000, 0, 2_000_000);
+        assert!((cost - (15.0 + 7.5 + 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stored_versions_are_immutable() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_pricing_tables(&conn).unwrap();
+        let table: PriceTable = serde_json::from_str(
+            r#"{"version":"2025-08","models":[{"model":"opus-4","input":15,"output":75,"cache_write":18.75,"cache_read":1.5}]}"#,
+        )
+        .unwrap();
+
+        assert!(store_price_table(&conn, &table, "https://example.com/prices.json").unwrap());
+        assert!(!store_price_table(&conn, &table, "https://example.com/prices.json").unwrap());
+        assert_eq!(load_price_table(&conn, "2025-08").unwrap(), table);
+
+        let mut changed = table.clone();
+        changed.models[0].input = 10.0;
+        assert!(store_price_table(&conn, &changed, "https://example.com/prices.json").is_err());
+
+        let mut invalid = table.clone();
+        invalid.version = "2025-09".to_string();
+        invalid.models[0].output = -1.0;
+        assert!(store_price_table(&conn, &invalid, "https://example.com/prices.json").is_err());
+        assert_eq!(active_version(&conn), "2025-08");
+    }
+}
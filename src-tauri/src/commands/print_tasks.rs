@@ -0,0 +1,588 @@
+#![allow(dead_code)]
+
+//! One-shot text tasks (session summaries, commit messages, transcript analysis) that need a
+//! single model answer rather than an agent loop. They run through `claude -p` when a
+//! recent enough CLI is installed, and otherwise call the Messages API directly, so they keep
+//! working without the CLI. The `print_task_backend` setting can pin either one.
+//!
+//! Both backends authenticate the same way: with a provider profile when one is given or
+//! assigned to the project, else with the credentials opcode was launched with. The API
+//! backend speaks the Anthropic API only, so Bedrock and Vertex profiles need the CLI.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::provider_profiles::{profile_for_run, ProviderKind, ProviderProfile};
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the `PrintBackendPreference`
+pub const PRINT_BACKEND_KEY: &str = "print_task_backend";
+
+/// Oldest CLI whose print mode reports JSON results with usage
+const MIN_CLI_VERSION: &str = "1.0.0";
+
+/// Model for API calls when the task names none; print tasks are short and cheap
+const DEFAULT_API_MODEL: &str = "claude-3-5-haiku-latest";
+const DEFAULT_CLI_MODEL: &str = "haiku";
+
+const MAX_OUTPUT_TOKENS: u32 = 1024;
+const TASK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Inputs are cut beyond this many characters: transcripts to their most recent part, diffs
+/// to their first files
+const MAX_INPUT_CHARS: usize = 120_000;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintBackendPreference {
+    /// The CLI when it's installed and recent enough, else the API
+    #[default]
+    Auto,
+    Cli,
+    Api,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintBackend {
+    Cli,
+    Api,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintTaskKind {
+    /// A short summary of a session transcript
+    Summarize,
+    /// A commit message for a diff, the project's staged changes by default
+    CommitMessage,
+    /// What went well and badly in a session transcript
+    TranscriptAnalysis,
+}
+
+impl PrintTaskKind {
+    fn system_prompt(self) -> &'static str {
+        match self {
+            PrintTaskKind::Summarize => {
+                "Summarize this coding session transcript in 3-5 sentences: the goal, what was \
+                 changed, and anything left unfinished. Reply with the summary only."
+            }
+            PrintTaskKind::CommitMessage => {
+                "Write a git commit message for this diff: an imperative subject line of at most \
+                 72 characters, a blank line, then a short body explaining what changed and why \
+                 if the subject doesn't say it all. Reply with the message only, without code \
+                 fences."
+            }
+            PrintTaskKind::TranscriptAnalysis => {
+                "Analyze this coding session transcript. List, as short markdown bullets under \
+                 the headings Outcome, Problems and Suggestions: whether the task was achieved, \
+                 where the assistant struggled (failed tools, repeated attempts, \
+                 misunderstandings), and how the prompt or setup could be improved."
+            }
+        }
+    }
+}
+
+/// A one-shot task; the input is given directly or read from a session or the project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintTaskRequest {
+    pub kind: PrintTaskKind,
+    pub input: Option<String>,
+    /// Session whose transcript is the input of summaries and analyses
+    pub session_id: Option<String>,
+    /// Project whose staged diff is the input of commit messages; also picks its profile
+    pub project_path: Option<String>,
+    pub profile_id: Option<i64>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrintTaskResult {
+    pub text: String,
+    pub backend: PrintBackend,
+    pub model: Option<String>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+/// Which backend print tasks use and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintBackendStatus {
+    pub preference: PrintBackendPreference,
+    pub cli_path: Option<String>,
+    pub cli_version: Option<String>,
+    /// Installed and at least `MIN_CLI_VERSION`
+    pub cli_usable: bool,
+    pub selected: PrintBackend,
+}
+
+/// The user and assistant text of a session's JSONL transcript, tool calls as markers
+pub fn transcript_text(jsonl: &str) -> String {
+    let mut text = String::new();
+    for line in jsonl.lines() {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let speaker = match entry.get("type").and_then(Value::as_str) {
+            Some("user") => "User",
+            Some("assistant") => "Assistant",
+            _ => continue,
+        };
+        let content = entry.pointer("/message/content");
+        let parts: Vec<String> = match content {
+            Some(Value::String(content)) => vec![content.clone()],
+            Some(Value::Array(blocks)) => blocks
+                .iter()
+                .filter_map(|block| match block.get("type").and_then(Value::as_str) {
+                    Some("text") => block
+                        .get("text")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    Some("tool_use") => Some(format!(
+                        "[tool: {}]",
+                        block
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or("unknown")
+                    )),
+                    Some("tool_result") if block.get("is_error") == Some(&Value::Bool(true)) => {
+                        Some("[tool failed]".to_string())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let message = parts.join("\n");
+        if !message.trim().is_empty() {
+            text.push_str(&format!("{}: {}\n\n", speaker, message.trim()));
+        }
+    }
+    truncate_front(text, MAX_INPUT_CHARS)
+}
+
+/// Keep the last `max_chars` characters, where the outcome of a session is
+fn truncate_front(text: String, max_chars: usize) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text;
+    }
+    let kept: String = text.chars().skip(count - max_chars).collect();
+    format!("[earlier part omitted]\n\n{}", kept)
+}
+
+/// The project's staged diff, or its uncommitted changes when nothing is staged
+fn project_diff(project_path: &str) -> Result<String, String> {
+    let git_diff = |args: &[&str]| -> Result<String, String> {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(project_path)
+            .output()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    let staged = git_diff(&["diff", "--cached"])?;
+    let diff = if staged.trim().is_empty() {
+        git_diff(&["diff", "HEAD"])?
+    } else {
+        staged
+    };
+    if diff.trim().is_empty() {
+        return Err("There are no changes to describe".to_string());
+    }
+    if diff.chars().count() > MAX_INPUT_CHARS {
+        let kept: String = diff.chars().take(MAX_INPUT_CHARS).collect();
+        return Ok(format!("{}\n[rest of the diff omitted]", kept));
+    }
+    Ok(diff)
+}
+
+fn task_input(request: &PrintTaskRequest) -> Result<String, String> {
+    if let Some(input) = request
+        .input
+        .as_ref()
+        .filter(|input| !input.trim().is_empty())
+    {
+        return Ok(input.clone());
+    }
+    match (request.kind, &request.session_id, &request.project_path) {
+        (PrintTaskKind::CommitMessage, _, Some(project_path)) => project_diff(project_path),
+        (PrintTaskKind::Summarize | PrintTaskKind::TranscriptAnalysis, Some(session_id), _) => {
+            let path = super::session_window::find_session_file(session_id)?;
+            let jsonl = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read session file: {}", e))?;
+            Ok(transcript_text(&jsonl))
+        }
+        (PrintTaskKind::CommitMessage, _, None) => {
+            Err("A diff or a project is required".to_string())
+        }
+        _ => Err("A transcript or a session is required".to_string()),
+    }
+}
+
+/// The installed CLI and its version, if it's recent enough for print tasks
+fn usable_cli(app: &AppHandle) -> (Option<String>, Option<String>, bool) {
+    let Ok(path) = crate::claude_binary::find_claude_binary(app) else {
+        return (None, None, false);
+    };
+    let version = crate::claude_binary::get_claude_version(&path)
+        .ok()
+        .flatten();
+    let usable = version.as_deref().is_some_and(|version| {
+        crate::claude_binary::compare_versions(version, MIN_CLI_VERSION) != std::cmp::Ordering::Less
+    });
+    (Some(path), version, usable)
+}
+
+fn backend_status(app: &AppHandle) -> Result<PrintBackendStatus, String> {
+    let preference = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        get_setting_as(&conn, PRINT_BACKEND_KEY).unwrap_or_default()
+    };
+    let (cli_path, cli_version, cli_usable) = usable_cli(app);
+    let selected = match preference {
+        PrintBackendPreference::Cli => PrintBackend::Cli,
+        PrintBackendPreference::Api => PrintBackend::Api,
+        PrintBackendPreference::Auto if cli_usable => PrintBackend::Cli,
+        PrintBackendPreference::Auto => PrintBackend::Api,
+    };
+    Ok(PrintBackendStatus {
+        preference,
+        cli_path,
+        cli_version,
+        cli_usable,
+        selected,
+    })
+}
+
+/// The result of `claude -p --output-format json`
+pub fn parse_cli_result(stdout: &str) -> Result<PrintTaskResult, String> {
+    let result: Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Unexpected output from claude: {}", e))?;
+    if result.get("is_error").and_then(Value::as_bool) == Some(true) {
+        return Err(result
+            .get("result")
+            .and_then(Value::as_str)
+            .unwrap_or("claude reported an error")
+            .to_string());
+    }
+    let text = result
+        .get("result")
+        .and_then(Value::as_str)
+        .ok_or("claude returned no result")?;
+    let usage = |name: &str| {
+        result
+            .pointer(&format!("/usage/{}", name))
+            .and_then(Value::as_u64)
+    };
+    Ok(PrintTaskResult {
+        text: text.trim().to_string(),
+        backend: PrintBackend::Cli,
+        model: result
+            .get("modelUsage")
+            .and_then(Value::as_object)
+            .and_then(|models| models.keys().next().cloned()),
+        input_tokens: usage("input_tokens"),
+        output_tokens: usage("output_tokens"),
+    })
+}
+
+async fn run_cli(
+    cli_path: &str,
+    kind: PrintTaskKind,
+    input: String,
+    model: Option<&str>,
+    env: &[(String, String)],
+) -> Result<PrintTaskResult, String> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut cmd = crate::claude_binary::create_command_with_env(cli_path);
+    cmd.args([
+        "-p",
+        kind.system_prompt(),
+        "--output-format",
+        "json",
+        "--model",
+        model.unwrap_or(DEFAULT_CLI_MODEL),
+    ]);
+    let mut cmd = tokio::process::Command::from(cmd);
+    super::provider_profiles::apply_env(&mut cmd, env);
+    // The input goes through stdin, as diffs and transcripts can exceed argument limits
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run claude: {}", e))?;
+    let mut stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+    stdin
+        .write_all(input.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to claude: {}", e))?;
+    drop(stdin);
+    let output = tokio::time::timeout(TASK_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| "claude did not answer in time".to_string())?
+        .map_err(|e| format!("Failed to run claude: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() && stdout.trim().is_empty() {
+        return Err(format!(
+            "claude failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_cli_result(&stdout)
+}
+
+/// The text and usage of a Messages API response
+pub fn parse_api_response(response: &Value) -> Result<PrintTaskResult, String> {
+    if let Some(message) = response.pointer("/error/message").and_then(Value::as_str) {
+        return Err(format!("The API returned an error: {}", message));
+    }
+    let text: String = response
+        .get("content")
+        .and_then(Value::as_array)
+        .ok_or("The API response has no content")?
+        .iter()
+        .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+        .filter_map(|block| block.get("text").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join("");
+    let usage = |name: &str| {
+        response
+            .pointer(&format!("/usage/{}", name))
+            .and_then(Value::as_u64)
+    };
+    Ok(PrintTaskResult {
+        text: text.trim().to_string(),
+        backend: PrintBackend::Api,
+        model: response
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        input_tokens: usage("input_tokens"),
+        output_tokens: usage("output_tokens"),
+    })
+}
+
+/// Endpoint and credentials for the API backend, from the profile or opcode's environment
+fn api_credentials(
+    profile: Option<&ProviderProfile>,
+    env: &[(String, String)],
+) -> Result<(String, BTreeMap<String, String>), String> {
+    let vars: BTreeMap<String, String> = match profile {
+        Some(profile) => {
+            if matches!(
+                profile.provider,
+                ProviderKind::Bedrock | ProviderKind::Vertex
+            ) {
+                return Err(format!(
+                    "Provider profile '{}' uses a cloud backend, which needs the claude CLI",
+                    profile.name
+                ));
+            }
+            env.iter().cloned().collect()
+        }
+        None => [
+            "ANTHROPIC_API_KEY",
+            "ANTHROPIC_AUTH_TOKEN",
+            "ANTHROPIC_BASE_URL",
+        ]
+        .iter()
+        .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+        .collect(),
+    };
+    if !vars.contains_key("ANTHROPIC_API_KEY") && !vars.contains_key("ANTHROPIC_AUTH_TOKEN") {
+        return Err(
+            "No API key: add one to a provider profile, or install the claude CLI".to_string(),
+        );
+    }
+    let base_url = vars
+        .get("ANTHROPIC_BASE_URL")
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .unwrap_or_else(|| "https://api.anthropic.com".to_string());
+    Ok((format!("{}/v1/messages", base_url), vars))
+}
+
+async fn run_api(
+    kind: PrintTaskKind,
+    input: String,
+    model: Option<&str>,
+    profile: Option<&ProviderProfile>,
+    env: &[(String, String)],
+) -> Result<PrintTaskResult, String> {
+    let (endpoint, vars) = api_credentials(profile, env)?;
+    let model = model
+        .or_else(|| vars.get("ANTHROPIC_SMALL_FAST_MODEL").map(String::as_str))
+        .unwrap_or(DEFAULT_API_MODEL);
+    let client = super::tls_settings::configure_client(
+        reqwest::Client::builder().timeout(TASK_TIMEOUT),
+        None,
+    )
+    .build()
+    .map_err(|e| e.to_string())?;
+    let mut request = client
+        .post(&endpoint)
+        .header("anthropic-version", "2023-06-01")
+        .json(&json!({
+            "model": model,
+            "max_tokens": MAX_OUTPUT_TOKENS,
+            "system": kind.system_prompt(),
+            "messages": [{ "role": "user", "content": input }],
+        }));
+    if let Some(key) = vars.get("ANTHROPIC_API_KEY") {
+        request = request.header("x-api-key", key);
+    }
+    if let Some(token) = vars.get("ANTHROPIC_AUTH_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach {}: {}", endpoint, e))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected response (HTTP {}): {}", status.as_u16(), e))?;
+    parse_api_response(&body)
+}
+
+/// Run a one-shot text task on the configured backend
+#[tauri::command]
+pub async fn run_print_task(
+    app: AppHandle,
+    request: PrintTaskRequest,
+) -> Result<PrintTaskResult, String> {
+    let status = backend_status(&app)?;
+    let profile = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        profile_for_run(
+            &conn,
+            request.project_path.as_deref().unwrap_or_default(),
+            request.profile_id,
+        )?
+    };
+    let task_request = request.clone();
+    // Reading keychain secrets, transcripts and diffs blocks
+    let (input, env, profile) = tauri::async_runtime::spawn_blocking(move || {
+        let env = match &profile {
+            Some(profile) => profile.env_vars()?,
+            None => Vec::new(),
+        };
+        Ok::<_, String>((task_input(&task_request)?, env, profile))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let model = request.model.as_deref();
+
+    info!(
+        "Running {:?} print task with the {:?} backend",
+        request.kind, status.selected
+    );
+    match (status.selected, &status.cli_path) {
+        (PrintBackend::Cli, Some(cli_path)) => {
+            if !status.cli_usable {
+                warn!(
+                    "claude {} is older than {}; print tasks may fail",
+                    status.cli_version.as_deref().unwrap_or("(unknown version)"),
+                    MIN_CLI_VERSION
+                );
+            }
+            run_cli(cli_path, request.kind, input, model, &env).await
+        }
+        (PrintBackend::Cli, None) => {
+            Err("The claude CLI is not installed; switch print tasks to the API".to_string())
+        }
+        (PrintBackend::Api, _) => run_api(request.kind, input, model, profile.as_ref(), &env).await,
+    }
+}
+
+/// Which backend print tasks use
+#[tauri::command]
+pub async fn get_print_task_backend(app: AppHandle) -> Result<PrintBackendStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || backend_status(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Pin print tasks to the CLI or the API, or pick automatically
+#[tauri::command]
+pub async fn set_print_task_backend(
+    app: AppHandle,
+    preference: PrintBackendPreference,
+) -> Result<PrintBackendStatus, String> {
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        set_setting_as(&conn, PRINT_BACKEND_KEY, &preference)?;
+    }
+    get_print_task_backend(app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_keeps_text_and_marks_tools() {
+        let jsonl = [
+            r#"{"type":"summary","summary":"ignored"}"#,
+            r#"{"type":"user","message":{"role":"user","content":"Fix the build"}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Looking."},{"type":"tool_use","name":"Bash","input":{}}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","is_error":true,"content":"boom"}]}}"#,
+            "not json",
+        ]
+        .join("\n");
+        assert_eq!(
+            transcript_text(&jsonl),
+            "User: Fix the build\n\nAssistant: Looking.\n[tool: Bash]\n\nUser: [tool failed]\n\n"
+        );
+        let long = truncate_front("abcdef".to_string(), 2);
+        assert!(long.ends_with("\n\nef"));
+    }
+
+    #[test]
+    fn test_cli_and_api_results_are_parsed() {
+        let cli = parse_cli_result(
+            r#"{"type":"result","is_error":false,"result":" Add retries \n","usage":{"input_tokens":12,"output_tokens":3},"modelUsage":{"claude-haiku":{}}}"#,
+        )
+        .unwrap();
+        assert_eq!(cli.text, "Add retries");
+        assert_eq!(cli.backend, PrintBackend::Cli);
+        assert_eq!(cli.model.as_deref(), Some("claude-haiku"));
+        assert_eq!((cli.input_tokens, cli.output_tokens), (Some(12), Some(3)));
+        assert!(
+            parse_cli_result(r#"{"is_error":true,"result":"Credit balance is too low"}"#)
+                .unwrap_err()
+                .contains("Credit balance")
+        );
+
+        let api = parse_api_response(&json!({
+            "model": "claude-3-5-haiku-latest",
+            "content": [{ "type": "text", "text": "Fix flaky test" }],
+            "usage": { "input_tokens": 40, "output_tokens": 5 }
+        }))
+        .unwrap();
+        assert_eq!(api.text, "Fix flaky test");
+        assert_eq!(api.backend, PrintBackend::Api);
+        assert_eq!(api.output_tokens, Some(5));
+        assert!(parse_api_response(&json!({
+            "type": "error",
+            "error": { "type": "authentication_error", "message": "invalid x-api-key" }
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,523 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// Entries added to `.gitignore` for files that hold personal Claude settings
+const GITIGNORE_ENTRIES: &[&str] = &[".claude/settings.local.json", "CLAUDE.local.md"];
+
+/// What `init_project` should create
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InitProjectOptions {
+    /// Write `.claude/settings.json` with permissions for the detected stack
+    pub settings: bool,
+    /// Write a starter `CLAUDE.md`
+    pub claude_md: bool,
+    /// Built-in MCP templates to put in `.mcp.json`
+    pub mcp_templates: Vec<String>,
+    /// Add personal Claude files to `.gitignore`
+    pub gitignore: bool,
+    /// Replace files that already exist instead of leaving them alone
+    pub overwrite: bool,
+}
+
+impl Default for InitProjectOptions {
+    fn default() -> Self {
+        Self {
+            settings: true,
+            claude_md: true,
+            mcp_templates: Vec::new(),
+            gitignore: true,
+            overwrite: false,
+        }
+    }
+}
+
+/// Languages, frameworks and commands detected from a project's manifests
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DetectedStack {
+    pub name: Option<String>,
+    pub languages: Vec<String>,
+    pub frameworks: Vec<String>,
+    pub package_manager: Option<String>,
+    /// Common commands as (purpose, command)
+    pub commands: Vec<(String, String)>,
+}
+
+/// A file `init_project` touched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitFileResult {
+    pub path: String,
+    /// `created`, `updated` or `skipped`
+    pub action: String,
+}
+
+/// Summary of an `init_project` call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitProjectSummary {
+    pub stack: DetectedStack,
+    pub files: Vec<InitFileResult>,
+}
+
+/// A built-in MCP server template for `.mcp.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTemplate {
+    pub id: String,
+    pub description: String,
+    pub config: Value,
+}
+
+/// Built-in MCP server templates offered during onboarding
+pub fn builtin_mcp_templates() -> Vec<McpTemplate> {
+    vec![
+        McpTemplate {
+            id: "filesystem".to_string(),
+            description: "Read and write files in the project".to_string(),
+            config: json!({
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-filesystem", "."]
+            }),
+        },
+        McpTemplate {
+            id: "github".to_string(),
+            description: "Issues, pull requests and repository access".to_string(),
+            config: json!({
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-github"],
+                "env": { "GITHUB_PERSONAL_ACCESS_TOKEN": "${GITHUB_PERSONAL_ACCESS_TOKEN}" }
+            }),
+        },
+        McpTemplate {
+            id: "fetch".to_string(),
+            description: "Fetch web pages as markdown".to_string(),
+            config: json!({ "command": "uvx", "args": ["mcp-server-fetch"] }),
+        },
+        McpTemplate {
+            id: "memory".to_string(),
+            description: "Persistent knowledge graph memory".to_string(),
+            config: json!({
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-memory"]
+            }),
+        },
+    ]
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+/// Detect the project's stack from package.json, Cargo.toml, pyproject.toml and go.mod
+pub fn detect_stack(project: &Path) -> DetectedStack {
+    let mut stack = DetectedStack::default();
+
+    if let Some(package) = fs::read_to_string(project.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    {
+        stack.name = package["name"].as_str().map(|s| s.to_string());
+        let manager = if project.join("bun.lockb").exists() || project.join("bun.lock").exists() {
+            "bun"
+        } else if project.join("pnpm-lock.yaml").exists() {
+            "pnpm"
+        } else if project.join("yarn.lock").exists() {
+            "yarn"
+        } else {
+            "npm"
+        };
+        stack.package_manager = Some(manager.to_string());
+
+        let has_dep = |name: &str| {
+            package["dependencies"].get(name).is_some() || package["devDependencies"].get(name).is_some()
+        };
+        push_unique(
+            &mut stack.languages,
+            if has_dep("typescript") || project.join("tsconfig.json").exists() {
+                "TypeScript"
+            } else {
+                "JavaScript"
+            },
+        );
+        for (dep, framework) in [
+            ("react", "React"),
+            ("next", "Next.js"),
+            ("vue", "Vue"),
+            ("svelte", "Svelte"),
+            ("@tauri-apps/api", "Tauri"),
+            ("express", "Express"),
+            ("vite", "Vite"),
+        ] {
+            if has_dep(dep) {
+                push_unique(&mut stack.frameworks, framework);
+            }
+        }
+        if let Some(scripts) = package["scripts"].as_object() {
+            for script in ["dev", "build", "test", "lint"] {
+                if scripts.contains_key(script) {
+                    stack
+                        .commands
+                        .push((script.to_string(), format!("{} run {}", manager, script)));
+                }
+            }
+        }
+    }
+
+    // Tauri apps keep their Cargo.toml in src-tauri
+    for dir in [project.to_path_buf(), project.join("src-tauri")] {
+        let Ok(cargo) = fs::read_to_string(dir.join("Cargo.toml")) else {
+            continue;
+        };
+        push_unique(&mut stack.languages, "Rust");
+        if stack.name.is_none() {
+            stack.name = cargo_package_name(&cargo);
+        }
+        let prefix = if dir == project {
+            String::new()
+        } else {
+            "cd src-tauri && ".to_string()
+        };
+        for (purpose, command) in [("build", "cargo build"), ("test", "cargo test"), ("lint", "cargo clippy")] {
+            stack
+                .commands
+                .push((purpose.to_string(), format!("{}{}", prefix, command)));
+        }
+        break;
+    }
+
+    if project.join("pyproject.toml").exists() || project.join("requirements.txt").exists() {
+        push_unique(&mut stack.languages, "Python");
+        stack.commands.push(("test".to_string(), "pytest".to_string()));
+    }
+    if project.join("go.mod").exists() {
+        push_unique(&mut stack.languages, "Go");
+        stack.commands.push(("build".to_string(), "go build ./...".to_string()));
+        stack.commands.push(("test".to_string(), "go test ./...".to_string()));
+    }
+
+    if stack.name.is_none() {
+        stack.name = project
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+    }
+    stack
+}
+
+/// `name` from the `[package]` table of a Cargo.toml
+fn cargo_package_name(cargo: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in cargo.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        if in_package {
+            if let Some(value) = line.strip_prefix("name").map(str::trim_start) {
+                if let Some(value) = value.strip_prefix('=') {
+                    return Some(value.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Starter CLAUDE.md for a detected stack
+pub fn render_claude_md(stack: &DetectedStack) -> String {
+    let mut md = format!(
+        "# {}\n\nThis file gives Claude Code guidance for working in this repository.\n\n",
+        stack.name.as_deref().unwrap_or("Project")
+    );
+
+    md.push_str("## Stack\n\n");
+    if stack.languages.is_empty() {
+        md.push_str("- Not detected yet; describe the languages and frameworks used here\n");
+    }
+    for language in &stack.languages {
+        md.push_str(&format!("- {}\n", language));
+    }
+    for framework in &stack.frameworks {
+        md.push_str(&format!("- {}\n", framework));
+    }
+    if let Some(manager) = &stack.package_manager {
+        md.push_str(&format!("- Package manager: {}\n", manager));
+    }
+
+    md.push_str("\n## Commands\n\n");
+    if stack.commands.is_empty() {
+        md.push_str("- Add the commands used to build, test and lint the project\n");
+    } else {
+        md.push_str("```bash\n");
+        for (purpose, command) in &stack.commands {
+            md.push_str(&format!("{}  # {}\n", command, purpose));
+        }
+        md.push_str("```\n");
+    }
+
+    md.push_str(
+        "\n## Conventions\n\n- Follow the existing code style and structure\n- Run the tests before finishing a change\n",
+    );
+    md
+}
+
+/// Project settings allowing the detected build and test commands
+fn render_settings(stack: &DetectedStack) -> Value {
+    let mut allow: Vec<String> = Vec::new();
+    for (_, command) in &stack.commands {
+        push_unique(&mut allow, &format!("Bash({}:*)", command));
+    }
+    json!({ "permissions": { "allow": allow, "deny": [] } })
+}
+
+/// Write `content` to `path` unless it exists and `overwrite` is off
+fn write_file(
+    project: &Path,
+    relative: &str,
+    content: &str,
+    overwrite: bool,
+    files: &mut Vec<InitFileResult>,
+) -> Result<(), String> {
+    let path = project.join(relative);
+    let exists = path.exists();
+    let action = if exists && !overwrite {
+        "skipped"
+    } else {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        if exists && relative.ends_with(".json") {
+            super::config_snapshots::snapshot_config_file(&path, "project init")?;
+        }
+        crate::atomic_file::write_atomic(&path, content).map_err(|e| format!("Failed to write {}: {}", relative, e))?;
+        if exists {
+            "updated"
+        } else {
+            "created"
+        }
+    };
+    files.push(InitFileResult {
+        path: relative.to_string(),
+        action: action.to_string(),
+    });
+    Ok(())
+}
+
+/// Merge the selected templates into `.mcp.json`, keeping servers already configured
+fn write_mcp_json(
+    project: &Path,
+    template_ids: &[String],
+    overwrite: bool,
+    files: &mut Vec<InitFileResult>,
+) -> Result<(), String> {
+    let templates = builtin_mcp_templates();
+    let path = project.join(".mcp.json");
+    let existing = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+    let mut servers = existing
+        .as_ref()
+        .and_then(|config| config["mcpServers"].as_object().cloned())
+        .unwrap_or_default();
+
+    let mut changed = false;
+    for id in template_ids {
+        let template = templates
+            .iter()
+            .find(|template| &template.id == id)
+            .ok_or_else(|| format!("Unknown MCP template: {}", id))?;
+        if overwrite || !servers.contains_key(id) {
+            servers.insert(id.clone(), template.config.clone());
+            changed = true;
+        }
+    }
+
+    let action = match (existing.is_some(), changed) {
+        (_, false) => "skipped",
+        (true, true) => "updated",
+        (false, true) => "created",
+    };
+    if changed {
+        let content = serde_json::to_string_pretty(&json!({ "mcpServers": servers }))
+            .map_err(|e| e.to_string())?;
+        super::config_snapshots::snapshot_config_file(&path, "project init")?;
+        crate::atomic_file::write_atomic(&path, content + "\n").map_err(|e| format!("Failed to write .mcp.json: {}", e))?;
+    }
+    files.push(InitFileResult {
+        path: ".mcp.json".to_string(),
+        action: action.to_string(),
+    });
+    Ok(())
+}
+
+/// Append the personal Claude files to `.gitignore`
+fn update_gitignore(project: &Path, files: &mut Vec<InitFileResult>) -> Result<(), String> {
+    let path = project.join(".gitignore");
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let missing: Vec<&str> = GITIGNORE_ENTRIES
+        .iter()
+        .copied()
+        .filter(|entry| !existing.lines().any(|line| line.trim() == *entry))
+        .collect();
+
+    let action = if missing.is_empty() {
+        "skipped"
+    } else {
+        let mut content = existing.clone();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str("\n# Claude Code personal settings\n");
+        for entry in &missing {
+            content.push_str(entry);
+            content.push('\n');
+        }
+        crate::atomic_file::write_atomic(&path, content).map_err(|e| format!("Failed to update .gitignore: {}", e))?;
+        if existing.is_empty() {
+            "created"
+        } else {
+            "updated"
+        }
+    };
+    files.push(InitFileResult {
+        path: ".gitignore".to_string(),
+        action: action.to_string(),
+    });
+    Ok(())
+}
+
+/// Scaffold a project for Claude Code
+pub fn init_project_at(project: &Path, options: &InitProjectOptions) -> Result<InitProjectSummary, String> {
+    if !project.is_dir() {
+        return Err(format!("Project directory does not exist: {}", project.display()));
+    }
+
+    let templates = builtin_mcp_templates();
+    if let Some(unknown) = options
+        .mcp_templates
+        .iter()
+        .find(|id| !templates.iter().any(|template| &template.id == *id))
+    {
+        return Err(format!("Unknown MCP template: {}", unknown));
+    }
+
+    let stack = detect_stack(project);
+    let mut files = Vec::new();
+
+    if options.settings {
+        let settings = serde_json::to_string_pretty(&render_settings(&stack)).map_err(|e| e.to_string())?;
+        write_file(project, ".claude/settings.json", &(settings + "\n"), options.overwrite, &mut files)?;
+    }
+    if options.claude_md {
+        write_file(project, "CLAUDE.md", &render_claude_md(&stack), options.overwrite, &mut files)?;
+    }
+    if !options.mcp_templates.is_empty() {
+        write_mcp_json(project, &options.mcp_templates, options.overwrite, &mut files)?;
+    }
+    if options.gitignore {
+        update_gitignore(project, &mut files)?;
+    }
+
+    Ok(InitProjectSummary { stack, files })
+}
+
+/// Set up `.claude/`, CLAUDE.md, `.mcp.json` and `.gitignore` for a project
+#[tauri::command]
+pub async fn init_project(
+    path: String,
+    options: Option<InitProjectOptions>,
+) -> Result<InitProjectSummary, String> {
+    log::info!("Initializing project for Claude Code: {}", path);
+    init_project_at(Path::new(&path), &options.unwrap_or_default())
+}
+
+/// List the MCP templates offered by `init_project`
+#[tauri::command]
+pub async fn list_project_mcp_templates() -> Result<Vec<McpTemplate>, String> {
+    Ok(builtin_mcp_templates())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_stack_from_package_json_and_cargo() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name":"web-app","scripts":{"build":"vite build","test":"vitest"},"devDependencies":{"typescript":"5","vite":"5"},"dependencies":{"react":"18"}}"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("bun.lockb"), "").unwrap();
+        fs::create_dir(dir.path().join("src-tauri")).unwrap();
+        fs::write(
+            dir.path().join("src-tauri/Cargo.toml"),
+            "[package]\nname = \"web-app-backend\"\n\n[dependencies]\nname = \"ignored\"\n",
+        )
+        .unwrap();
+
+        let stack = detect_stack(dir.path());
+        assert_eq!(stack.name.as_deref(), Some("web-app"));
+        assert_eq!(stack.languages, vec!["TypeScript", "Rust"]);
+        assert_eq!(stack.frameworks, vec!["React", "Vite"]);
+        assert_eq!(stack.package_manager.as_deref(), Some("bun"));
+        assert!(stack.commands.contains(&("test".to_string(), "bun run test".to_string())));
+        assert!(stack
+            .commands
+            .contains(&("test".to_string(), "cd src-tauri && cargo test".to_string())));
+        assert_eq!(cargo_package_name("[package]\nname = \"x\"\n").as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn test_init_project_keeps_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("CLAUDE.md"), "# Mine\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "node_modules").unwrap();
+        fs::write(
+            dir.path().join(".mcp.json"),
+            r#"{"mcpServers":{"custom":{"command":"my-server"}}}"#,
+        )
+        .unwrap();
+
+        let options = InitProjectOptions {
+            mcp_templates: vec!["fetch".to_string()],
+            ..Default::default()
+        };
+        let summary = init_project_at(dir.path(), &options).unwrap();
+        let action = |path: &str| {
+            summary
+                .files
+                .iter()
+                .find(|file| file.path == path)
+                .map(|file| file.action.clone())
+                .unwrap()
+        };
+        assert_eq!(action(".claude/settings.json"), "created");
+        assert_eq!(action("CLAUDE.md"), "skipped");
+        assert_eq!(action(".mcp.json"), "updated");
+        assert_eq!(action(".gitignore"), "updated");
+
+        assert_eq!(fs::read_to_string(dir.path().join("CLAUDE.md")).unwrap(), "# Mine\n");
+        let mcp: Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join(".mcp.json")).unwrap()).unwrap();
+        assert!(mcp["mcpServers"]["custom"].is_object());
+        assert!(mcp["mcpServers"]["fetch"].is_object());
+        let gitignore = fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        assert!(gitignore.starts_with("node_modules\n"));
+        assert!(gitignore.contains(".claude/settings.local.json"));
+
+        // Running again changes nothing
+        let summary = init_project_at(dir.path(), &options).unwrap();
+        assert!(summary.files.iter().all(|file| file.action == "skipped"));
+        assert!(init_project_at(dir.path(), &InitProjectOptions {
+            mcp_templates: vec!["nope".to_string()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,338 @@
+#![allow(dead_code)]
+
+//! Per-project insights: sessions, messages, tokens and cost, the tools used most, the files
+//! edited most and when work happens during the week. Everything is read from the session
+//! index (see [`super::session_index`]), which only parses what was appended to transcripts
+//! since the last update, so the stats stay cheap to refresh.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::session_index::{canonical, index_all, open_store, project_usage_entries, projects_dir};
+
+/// Tools and files listed per ranking
+const TOP_LIMIT: i64 = 10;
+
+/// Tools that change the file they name
+pub(crate) const EDIT_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCount {
+    pub name: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEditCount {
+    pub path: String,
+    pub edits: u64,
+}
+
+/// Messages per hour of one weekday, in local time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeekdayActivity {
+    /// 0 is Sunday
+    pub weekday: u32,
+    /// 24 entries, one per hour
+    pub hours: Vec<u64>,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub project_id: String,
+    pub session_count: u64,
+    pub message_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    pub first_activity: Option<String>,
+    pub last_activity: Option<String>,
+    pub top_tools: Vec<ToolCount>,
+    pub top_files: Vec<FileEditCount>,
+    /// Seven entries, Sunday first
+    pub activity_by_weekday: Vec<WeekdayActivity>,
+}
+
+/// Stats of the project directory `project_id` under `projects_dir`, from the index as it is
+pub fn project_stats(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_id: &str,
+) -> Result<ProjectStats, String> {
+    let prefix = canonical(projects_dir).to_string_lossy().to_string();
+    let mut stats = ProjectStats {
+        project_id: project_id.to_string(),
+        ..ProjectStats::default()
+    };
+
+    let (session_count, message_count): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(message_count), 0) FROM session_index_files
+             WHERE substr(path, 1, length(?1)) = ?1 AND project_dir = ?2",
+            params![prefix, project_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    stats.session_count = session_count as u64;
+    stats.message_count = message_count as u64;
+
+    (stats.first_activity, stats.last_activity) = conn
+        .query_row(
+            "SELECT MIN(m.timestamp), MAX(m.timestamp) FROM session_index_messages m
+             JOIN session_index_files f ON f.path = m.path
+             WHERE substr(f.path, 1, length(?1)) = ?1 AND f.project_dir = ?2",
+            params![prefix, project_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    for entry in project_usage_entries(conn, projects_dir, project_id)? {
+        stats.input_tokens += entry.input_tokens;
+        stats.output_tokens += entry.output_tokens;
+        stats.cache_creation_tokens += entry.cache_creation_tokens;
+        stats.cache_read_tokens += entry.cache_read_tokens;
+        stats.cost_usd += entry.cost;
+    }
+    stats.total_tokens = stats.input_tokens
+        + stats.output_tokens
+        + stats.cache_creation_tokens
+        + stats.cache_read_tokens;
+
+    stats.top_tools = ranked(
+        conn,
+        "SELECT t.tool_name, COUNT(*) FROM session_index_tool_uses t
+         JOIN session_index_files f ON f.path = t.path
+         WHERE substr(f.path, 1, length(?1)) = ?1 AND f.project_dir = ?2
+         GROUP BY t.tool_name ORDER BY COUNT(*) DESC, t.tool_name LIMIT ?3",
+        &prefix,
+        project_id,
+    )?
+    .into_iter()
+    .map(|(name, count)| ToolCount { name, count })
+    .collect();
+
+    stats.top_files = ranked(
+        conn,
+        &format!(
+            "SELECT t.file_path, COUNT(*) FROM session_index_tool_uses t
+             JOIN session_index_files f ON f.path = t.path
+             WHERE substr(f.path, 1, length(?1)) = ?1 AND f.project_dir = ?2
+               AND t.file_path IS NOT NULL AND t.tool_name IN ({})
+             GROUP BY t.file_path ORDER BY COUNT(*) DESC, t.file_path LIMIT ?3",
+            edit_tools_sql()
+        ),
+        &prefix,
+        project_id,
+    )?
+    .into_iter()
+    .map(|(path, edits)| FileEditCount { path, edits })
+    .collect();
+
+    stats.activity_by_weekday = weekday_activity(conn, &prefix, project_id)?;
+    Ok(stats)
+}
+
+/// [`EDIT_TOOLS`] as an SQL list for `tool_name IN (...)`
+pub(crate) fn edit_tools_sql() -> String {
+    EDIT_TOOLS
+        .iter()
+        .map(|tool| format!("'{}'", tool))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Rows of a `SELECT name, COUNT(*) ... LIMIT ?3` query
+fn ranked(
+    conn: &Connection,
+    sql: &str,
+    prefix: &str,
+    project_id: &str,
+) -> Result<Vec<(String, u64)>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![prefix, project_id, TOP_LIMIT], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+fn weekday_activity(
+    conn: &Connection,
+    prefix: &str,
+    project_id: &str,
+) -> Result<Vec<WeekdayActivity>, String> {
+    let mut days: Vec<WeekdayActivity> = (0..7)
+        .map(|weekday| WeekdayActivity {
+            weekday,
+            hours: vec![0; 24],
+            total: 0,
+        })
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT CAST(strftime('%w', m.timestamp, 'localtime') AS INTEGER),
+                    CAST(strftime('%H', m.timestamp, 'localtime') AS INTEGER), COUNT(*)
+             FROM session_index_messages m
+             JOIN session_index_files f ON f.path = m.path
+             WHERE substr(f.path, 1, length(?1)) = ?1 AND f.project_dir = ?2
+               AND m.timestamp IS NOT NULL
+             GROUP BY 1, 2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![prefix, project_id], |row| {
+            Ok((
+                row.get::<_, Option<i64>>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        // Timestamps SQLite can't parse have no weekday
+        let (Some(weekday), Some(hour), count) = row.map_err(|e| e.to_string())? else {
+            continue;
+        };
+        if let Some(day) = days.get_mut(weekday as usize) {
+            if let Some(slot) = day.hours.get_mut(hour as usize) {
+                *slot += count as u64;
+                day.total += count as u64;
+            }
+        }
+    }
+    Ok(days)
+}
+
+/// Sessions, messages, usage, top tools and files, and weekly activity of a project
+#[tauri::command]
+pub async fn get_project_stats(project_id: String) -> Result<ProjectStats, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = open_store().ok_or("The session index is not available")?;
+        let projects_dir = projects_dir()?;
+        index_all(&conn, &projects_dir)?;
+        project_stats(&conn, &projects_dir, &project_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::session_index::init_session_index_tables;
+    use super::*;
+    use std::io::Write;
+
+    fn assistant(id: &str, timestamp: &str, tools: serde_json::Value) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "uuid": format!("u-{}", id),
+            "timestamp": timestamp,
+            "sessionId": "s1",
+            "requestId": format!("req-{}", id),
+            "message": {
+                "id": id,
+                "model": "claude-sonnet-4-5",
+                "content": tools,
+                "usage": { "input_tokens": 100, "output_tokens": 20 },
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_stats_count_tools_files_and_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let mut file = std::fs::File::create(project.join("s1.jsonl")).unwrap();
+        let edit = |path: &str| serde_json::json!({ "type": "tool_use", "name": "Edit", "input": { "file_path": path } });
+        let lines = [
+            assistant(
+                "m1",
+                "2025-01-06T10:00:00Z",
+                serde_json::json!([edit("src/lib.rs"), { "type": "tool_use", "name": "Bash", "input": { "command": "ls" } }]),
+            ),
+            assistant(
+                "m2",
+                "2025-01-06T10:05:00Z",
+                serde_json::json!([edit("src/lib.rs"), { "type": "tool_use", "name": "Read", "input": { "file_path": "README.md" } }]),
+            ),
+            assistant(
+                "m3",
+                "2025-01-07T18:00:00Z",
+                serde_json::json!([edit("src/main.rs")]),
+            ),
+        ];
+        for line in &lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+        let other = dir.path().join("-work-other");
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(
+            other.join("s2.jsonl"),
+            format!(
+                "{}\n",
+                assistant(
+                    "m9",
+                    "2025-01-06T10:00:00Z",
+                    serde_json::json!([edit("x.rs")])
+                )
+            ),
+        )
+        .unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_session_index_tables(&conn).unwrap();
+        index_all(&conn, dir.path()).unwrap();
+        let stats = project_stats(&conn, dir.path(), "-work-app").unwrap();
+
+        assert_eq!(stats.session_count, 1);
+        assert_eq!(stats.message_count, 3);
+        assert_eq!(stats.input_tokens, 300);
+        assert_eq!(stats.total_tokens, 360);
+        assert!(stats.cost_usd > 0.0);
+        assert_eq!(
+            stats.first_activity.as_deref(),
+            Some("2025-01-06T10:00:00Z")
+        );
+        assert_eq!(
+            stats.top_tools[0],
+            ToolCount {
+                name: "Edit".to_string(),
+                count: 3
+            }
+        );
+        assert_eq!(stats.top_tools.len(), 3);
+        // Reads don't count as edits
+        assert_eq!(
+            stats.top_files,
+            vec![
+                FileEditCount {
+                    path: "src/lib.rs".to_string(),
+                    edits: 2
+                },
+                FileEditCount {
+                    path: "src/main.rs".to_string(),
+                    edits: 1
+                },
+            ]
+        );
+        assert_eq!(stats.activity_by_weekday.len(), 7);
+        assert_eq!(
+            stats
+                .activity_by_weekday
+                .iter()
+                .map(|day| day.total)
+                .sum::<u64>(),
+            3
+        );
+    }
+}
@@ -0,0 +1,326 @@
+#![allow(dead_code)]
+
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tauri::State;
+
+use super::agents::AgentDb;
+
+/// A reusable prompt; `body` may contain `{{variable}}` or `{{variable|default}}` placeholders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: Option<i64>,
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Variables used in `body`, in order of first appearance (filled in by the backend)
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    #[serde(default)]
+    pub use_count: i64,
+    pub last_used_at: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// A placeholder in a template body
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateVariable {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+/// Result of rendering a template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedPrompt {
+    pub prompt: String,
+    /// Variables that had neither a value nor a default; their placeholders are left as-is
+    pub missing: Vec<String>,
+}
+
+pub fn init_prompt_template_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prompt_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL UNIQUE,
+            body TEXT NOT NULL,
+            tags TEXT NOT NULL DEFAULT '[]',
+            use_count INTEGER NOT NULL DEFAULT 0,
+            last_used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_\-]*)\s*(?:\|([^}]*))?\}\}").unwrap()
+    })
+}
+
+/// Variables used in a template body, in order of first appearance
+pub fn template_variables(body: &str) -> Vec<TemplateVariable> {
+    let mut variables: Vec<TemplateVariable> = Vec::new();
+    for caps in placeholder_regex().captures_iter(body) {
+        let name = caps[1].to_string();
+        let default = caps.get(2).map(|m| m.as_str().trim().to_string());
+        match variables.iter_mut().find(|v| v.name == name) {
+            // A later placeholder may supply the default the first one lacked
+            Some(existing) => {
+                if existing.default.is_none() {
+                    existing.default = default;
+                }
+            }
+            None => variables.push(TemplateVariable { name, default }),
+        }
+    }
+    variables
+}
+
+/// Substitute variables into a template body
+pub fn render_template(body: &str, vars: &HashMap<String, String>) -> RenderedPrompt {
+    let variables = template_variables(body);
+    let mut missing = Vec::new();
+    let prompt = placeholder_regex()
+        .replace_all(body, |caps: &regex::Captures| {
+            let name = &caps[1];
+            if let Some(value) = vars.get(name) {
+                return value.clone();
+            }
+            let default = variables
+                .iter()
+                .find(|v| v.name == name)
+                .and_then(|v| v.default.clone());
+            match default {
+                Some(default) => default,
+                None => {
+                    if !missing.iter().any(|m| m == name) {
+                        missing.push(name.to_string());
+                    }
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned();
+    RenderedPrompt { prompt, missing }
+}
+
+fn template_from_row(row: &rusqlite::Row) -> SqliteResult<PromptTemplate> {
+    let body: String = row.get(2)?;
+    let tags: String = row.get(3)?;
+    Ok(PromptTemplate {
+        id: Some(row.get(0)?),
+        title: row.get(1)?,
+        variables: template_variables(&body),
+        body,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        use_count: row.get(4)?,
+        last_used_at: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+const TEMPLATE_COLUMNS: &str =
+    "id, title, body, tags, use_count, last_used_at, created_at, updated_at";
+
+fn load_template(conn: &Connection, id: i64) -> SqliteResult<Option<PromptTemplate>> {
+    conn.query_row(
+        &format!("SELECT {} FROM prompt_templates WHERE id = ?1", TEMPLATE_COLUMNS),
+        params![id],
+        template_from_row,
+    )
+    .optional()
+}
+
+/// Trimmed, de-duplicated, lowercase tags
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+fn validate_template(template: &PromptTemplate) -> Result<(), String> {
+    if template.title.trim().is_empty() {
+        return Err("Template title cannot be empty".to_string());
+    }
+    if template.body.trim().is_empty() {
+        return Err("Template body cannot be empty".to_string());
+    }
+    Ok(())
+}
+
+/// List prompt templates, optionally only those with `tag`; most used first
+#[tauri::command]
+pub async fn list_prompt_templates(
+    db: State<'_, AgentDb>,
+    tag: Option<String>,
+) -> Result<Vec<PromptTemplate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM prompt_templates ORDER BY use_count DESC, title ASC",
+            TEMPLATE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let templates = stmt
+        .query_map([], template_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let tag = tag.map(|tag| tag.trim().to_lowercase());
+    Ok(templates
+        .into_iter()
+        .filter(|template| tag.as_ref().is_none_or(|tag| template.tags.contains(tag)))
+        .collect())
+}
+
+/// Get a prompt template by id
+#[tauri::command]
+pub async fn get_prompt_template(
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<PromptTemplate, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_template(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Prompt template {} not found", id))
+}
+
+/// Create a prompt template
+#[tauri::command]
+pub async fn create_prompt_template(
+    db: State<'_, AgentDb>,
+    template: PromptTemplate,
+) -> Result<PromptTemplate, String> {
+    validate_template(&template)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tags = serde_json::to_string(&normalize_tags(&template.tags)).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO prompt_templates (title, body, tags) VALUES (?1, ?2, ?3)",
+        params![template.title.trim(), template.body, tags],
+    )
+    .map_err(|e| format!("Failed to create prompt template: {}", e))?;
+    load_template(&conn, conn.last_insert_rowid())
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Prompt template disappeared after creation".to_string())
+}
+
+/// Update a prompt template's title, body and tags
+#[tauri::command]
+pub async fn update_prompt_template(
+    db: State<'_, AgentDb>,
+    template: PromptTemplate,
+) -> Result<PromptTemplate, String> {
+    let id = template.id.ok_or("Prompt template id is required")?;
+    validate_template(&template)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tags = serde_json::to_string(&normalize_tags(&template.tags)).map_err(|e| e.to_string())?;
+    let rows = conn
+        .execute(
+            "UPDATE prompt_templates SET title = ?1, body = ?2, tags = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?4",
+            params![template.title.trim(), template.body, tags, id],
+        )
+        .map_err(|e| format!("Failed to update prompt template: {}", e))?;
+    if rows == 0 {
+        return Err(format!("Prompt template {} not found", id));
+    }
+    load_template(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Prompt template {} not found", id))
+}
+
+/// Delete a prompt template
+#[tauri::command]
+pub async fn delete_prompt_template(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM prompt_templates WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete prompt template: {}", e))?;
+    Ok(())
+}
+
+/// Render a template with the given variables for a session prompt or agent task.
+/// Fails if a variable without a default is missing, unless `allow_missing` is set.
+#[tauri::command]
+pub async fn render_prompt(
+    db: State<'_, AgentDb>,
+    template_id: i64,
+    vars: Option<HashMap<String, String>>,
+    allow_missing: Option<bool>,
+) -> Result<RenderedPrompt, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let template = load_template(&conn, template_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Prompt template {} not found", template_id))?;
+
+    let rendered = render_template(&template.body, &vars.unwrap_or_default());
+    if !rendered.missing.is_empty() && !allow_missing.unwrap_or(false) {
+        return Err(format!(
+            "Missing values for: {}",
+            rendered.missing.join(", ")
+        ));
+    }
+
+    conn.execute(
+        "UPDATE prompt_templates SET use_count = use_count + 1, last_used_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![template_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "Triage {{ issue }} in {{area|the backend}}.\nSeverity: {{severity}}. Re: {{issue}}";
+
+    #[test]
+    fn test_template_variables_in_order_with_defaults() {
+        assert_eq!(
+            template_variables(BODY),
+            vec![
+                TemplateVariable { name: "issue".to_string(), default: None },
+                TemplateVariable { name: "area".to_string(), default: Some("the backend".to_string()) },
+                TemplateVariable { name: "severity".to_string(), default: None },
+            ]
+        );
+        assert!(template_variables("no {placeholders} here").is_empty());
+    }
+
+    #[test]
+    fn test_render_template_uses_values_then_defaults() {
+        let vars = HashMap::from([("issue".to_string(), "#42".to_string())]);
+        let rendered = render_template(BODY, &vars);
+        assert_eq!(
+            rendered.prompt,
+            "Triage #42 in the backend.\nSeverity: {{severity}}. Re: #42"
+        );
+        assert_eq!(rendered.missing, vec!["severity"]);
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_prompt_template_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO prompt_templates (title, body, tags) VALUES ('Triage', ?1, '[\"bugs\"]')",
+            params![BODY],
+        )
+        .unwrap();
+        let template = load_template(&conn, 1).unwrap().unwrap();
+        assert_eq!(template.tags, vec!["bugs"]);
+        assert_eq!(template.variables.len(), 3);
+    }
+}
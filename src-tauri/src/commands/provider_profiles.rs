@@ -0,0 +1,651 @@
+#![allow(dead_code)]
+
+//! Provider profiles: named sets of environment variables that route the CLI to a backend
+//! (the Anthropic API, Bedrock, Vertex or an API-compatible proxy). A profile can be assigned
+//! to a project and picked per run; the run's process gets the profile's variables, with the
+//! authentication variables it inherited from opcode cleared so backends never mix.
+//!
+//! Credentials are never stored in the database: variables given as secrets go to the OS
+//! keychain and are read back when a run starts.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::auth_status::AUTH_ENV_VARS;
+use super::keychain;
+
+/// Longest wait for a connectivity test
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Variable names that must be given as secrets rather than stored in plain text
+const SECRET_NAME_PARTS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Anthropic,
+    Bedrock,
+    Vertex,
+    /// An Anthropic-compatible gateway at `ANTHROPIC_BASE_URL`
+    Proxy,
+}
+
+impl ProviderKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::Bedrock => "bedrock",
+            ProviderKind::Vertex => "vertex",
+            ProviderKind::Proxy => "proxy",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "bedrock" => ProviderKind::Bedrock,
+            "vertex" => ProviderKind::Vertex,
+            "proxy" => ProviderKind::Proxy,
+            _ => ProviderKind::Anthropic,
+        }
+    }
+
+    /// Variables a profile of this kind can't work without
+    fn required_vars(self) -> &'static [&'static str] {
+        match self {
+            ProviderKind::Anthropic => &[],
+            ProviderKind::Bedrock => &["AWS_REGION"],
+            ProviderKind::Vertex => &["CLOUD_ML_REGION", "ANTHROPIC_VERTEX_PROJECT_ID"],
+            ProviderKind::Proxy => &["ANTHROPIC_BASE_URL"],
+        }
+    }
+
+    /// Switch the CLI to this backend
+    fn switch_var(self) -> Option<&'static str> {
+        match self {
+            ProviderKind::Bedrock => Some("CLAUDE_CODE_USE_BEDROCK"),
+            ProviderKind::Vertex => Some("CLAUDE_CODE_USE_VERTEX"),
+            ProviderKind::Anthropic | ProviderKind::Proxy => None,
+        }
+    }
+}
+
+/// A provider profile; secret values live in the keychain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderProfile {
+    pub id: Option<i64>,
+    pub name: String,
+    pub provider: ProviderKind,
+    /// Variables stored in plain text, e.g. `ANTHROPIC_BASE_URL` or `AWS_REGION`
+    pub env: BTreeMap<String, String>,
+    /// Names of the variables kept in the keychain
+    #[serde(default)]
+    pub secret_vars: Vec<String>,
+    pub created_at: Option<String>,
+}
+
+/// Outcome of `test_provider_profile`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderConnectivity {
+    pub ok: bool,
+    /// HTTP status of the probe, when the endpoint answered
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub endpoint: String,
+    pub message: String,
+}
+
+pub fn init_provider_profile_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS provider_profiles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            provider TEXT NOT NULL,
+            env TEXT NOT NULL DEFAULT '{}',
+            secret_vars TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_provider_profiles (
+            project_path TEXT PRIMARY KEY,
+            profile_id INTEGER NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES provider_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const PROFILE_COLUMNS: &str = "id, name, provider, env, secret_vars, created_at";
+
+fn profile_from_row(row: &rusqlite::Row) -> rusqlite::Result<ProviderProfile> {
+    let provider: String = row.get(2)?;
+    let env: String = row.get(3)?;
+    let secret_vars: String = row.get(4)?;
+    Ok(ProviderProfile {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        provider: ProviderKind::parse(&provider),
+        env: serde_json::from_str(&env).unwrap_or_default(),
+        secret_vars: serde_json::from_str(&secret_vars).unwrap_or_default(),
+        created_at: row.get(5)?,
+    })
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let upper = name.to_uppercase();
+    SECRET_NAME_PARTS.iter().any(|part| upper.contains(part))
+}
+
+fn validate_profile(profile: &ProviderProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    for name in profile.env.keys().chain(&profile.secret_vars) {
+        if !is_var_name(name) {
+            return Err(format!("Invalid environment variable name: {}", name));
+        }
+    }
+    if let Some(name) = profile.env.keys().find(|name| is_secret_name(name)) {
+        return Err(format!("{} must be given as a secret", name));
+    }
+    for required in profile.provider.required_vars() {
+        if profile
+            .env
+            .get(*required)
+            .is_none_or(|value| value.trim().is_empty())
+        {
+            return Err(format!(
+                "{} profiles need {}",
+                profile.provider.as_str(),
+                required
+            ));
+        }
+    }
+    if let Some(url) = profile.env.get("ANTHROPIC_BASE_URL") {
+        reqwest::Url::parse(url).map_err(|e| format!("Invalid ANTHROPIC_BASE_URL: {}", e))?;
+    }
+    Ok(())
+}
+
+fn secret_account(profile_id: i64, name: &str) -> String {
+    format!("provider-profile:{}:{}", profile_id, name)
+}
+
+/// Store the given secrets in the keychain and record their names on the profile. Empty
+/// values keep what is already stored.
+fn store_secrets(
+    conn: &Connection,
+    profile: &mut ProviderProfile,
+    secrets: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let id = profile.id.ok_or("Profile id is required")?;
+    for (name, value) in secrets {
+        if !is_var_name(name) {
+            return Err(format!("Invalid environment variable name: {}", name));
+        }
+        if !value.is_empty() {
+            keychain::store_secret(&secret_account(id, name), value)?;
+        }
+        if !profile.secret_vars.contains(name) {
+            profile.secret_vars.push(name.clone());
+        }
+    }
+    profile.secret_vars.sort();
+    conn.execute(
+        "UPDATE provider_profiles SET secret_vars = ?1 WHERE id = ?2",
+        params![
+            serde_json::to_string(&profile.secret_vars).map_err(|e| e.to_string())?,
+            id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+impl ProviderProfile {
+    /// Every variable the profile sets, with secrets read from the keychain
+    pub fn env_vars(&self) -> Result<Vec<(String, String)>, String> {
+        let id = self.id.ok_or("Profile id is required")?;
+        let mut vars: Vec<(String, String)> = self
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for name in &self.secret_vars {
+            let value = keychain::load_secret(&secret_account(id, name)).ok_or_else(|| {
+                format!(
+                    "{} of provider profile '{}' is missing from the keychain",
+                    name, self.name
+                )
+            })?;
+            vars.push((name.clone(), value));
+        }
+        if let Some(switch) = self.provider.switch_var() {
+            vars.push((switch.to_string(), "1".to_string()));
+        }
+        Ok(vars)
+    }
+}
+
+pub fn load_profile(conn: &Connection, id: i64) -> SqliteResult<Option<ProviderProfile>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM provider_profiles WHERE id = ?1",
+            PROFILE_COLUMNS
+        ),
+        params![id],
+        profile_from_row,
+    )
+    .optional()
+}
+
+pub fn load_project_profile(
+    conn: &Connection,
+    project_path: &str,
+) -> SqliteResult<Option<ProviderProfile>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM provider_profiles WHERE id = (SELECT profile_id FROM project_provider_profiles WHERE project_path = ?1)",
+            PROFILE_COLUMNS
+        ),
+        params![project_path],
+        profile_from_row,
+    )
+    .optional()
+}
+
+/// Profile a run uses: the one picked for the run, otherwise the project's
+pub fn profile_for_run(
+    conn: &Connection,
+    project_path: &str,
+    profile_id: Option<i64>,
+) -> Result<Option<ProviderProfile>, String> {
+    match profile_id {
+        Some(id) => load_profile(conn, id)
+            .map_err(|e| e.to_string())?
+            .map(Some)
+            .ok_or_else(|| format!("Provider profile {} not found", id)),
+        None => load_project_profile(conn, project_path).map_err(|e| e.to_string()),
+    }
+}
+
+/// Variables for a run's process, resolved through the app's database
+pub fn run_env(
+    app: &tauri::AppHandle,
+    project_path: &str,
+    profile_id: Option<i64>,
+) -> Result<Vec<(String, String)>, String> {
+    use tauri::Manager;
+    let profile = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        profile_for_run(&conn, project_path, profile_id)?
+    };
+    match profile {
+        Some(profile) => {
+            log::info!("Using provider profile '{}'", profile.name);
+            profile.env_vars()
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Give `cmd` a profile's variables; inherited authentication variables are cleared first
+pub fn apply_env(cmd: &mut tokio::process::Command, vars: &[(String, String)]) {
+    if vars.is_empty() {
+        return;
+    }
+    for name in AUTH_ENV_VARS {
+        cmd.env_remove(name);
+    }
+    for (name, value) in vars {
+        cmd.env(name, value);
+    }
+}
+
+/// Endpoint probed by the connectivity test
+fn probe_endpoint(profile: &ProviderProfile) -> String {
+    let env = |name: &str| profile.env.get(name).map(|value| value.trim());
+    match profile.provider {
+        ProviderKind::Anthropic | ProviderKind::Proxy => format!(
+            "{}/v1/models",
+            env("ANTHROPIC_BASE_URL")
+                .unwrap_or("https://api.anthropic.com")
+                .trim_end_matches('/')
+        ),
+        ProviderKind::Bedrock => format!(
+            "https://bedrock-runtime.{}.amazonaws.com/",
+            env("AWS_REGION").unwrap_or("us-east-1")
+        ),
+        ProviderKind::Vertex => match env("CLOUD_ML_REGION").unwrap_or("global") {
+            "global" => "https://aiplatform.googleapis.com/".to_string(),
+            region => format!("https://{}-aiplatform.googleapis.com/", region),
+        },
+    }
+}
+
+/// Interpret the probe's answer; only Anthropic-style endpoints can check credentials
+fn connectivity_result(
+    profile: &ProviderProfile,
+    has_credentials: bool,
+    status: u16,
+) -> (bool, String) {
+    match profile.provider {
+        ProviderKind::Anthropic | ProviderKind::Proxy => match status {
+            200..=299 if has_credentials => {
+                (true, "Connected and credentials accepted".to_string())
+            }
+            200..=299 => (true, "Connected".to_string()),
+            401 | 403 if has_credentials => (false, "Credentials were rejected".to_string()),
+            401 | 403 => (
+                true,
+                "Reachable; the profile has no credentials, so the CLI's own login will be used"
+                    .to_string(),
+            ),
+            404 => (
+                false,
+                "The endpoint does not serve the Anthropic API".to_string(),
+            ),
+            _ => (false, format!("Unexpected response: HTTP {}", status)),
+        },
+        ProviderKind::Bedrock | ProviderKind::Vertex => (
+            true,
+            "Reachable; cloud credentials are checked by the CLI when a run starts".to_string(),
+        ),
+    }
+}
+
+/// List all provider profiles
+#[tauri::command]
+pub async fn list_provider_profiles(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<ProviderProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM provider_profiles ORDER BY name ASC",
+            PROFILE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let profiles = stmt
+        .query_map([], profile_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(profiles)
+}
+
+/// Create a provider profile; `secrets` are stored in the keychain
+#[tauri::command]
+pub async fn create_provider_profile(
+    db: State<'_, AgentDb>,
+    profile: ProviderProfile,
+    secrets: Option<BTreeMap<String, String>>,
+) -> Result<ProviderProfile, String> {
+    validate_profile(&profile)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO provider_profiles (name, provider, env) VALUES (?1, ?2, ?3)",
+        params![
+            profile.name,
+            profile.provider.as_str(),
+            serde_json::to_string(&profile.env).map_err(|e| e.to_string())?
+        ],
+    )
+    .map_err(|e| format!("Failed to create provider profile: {}", e))?;
+
+    let mut created = load_profile(&conn, conn.last_insert_rowid())
+        .map_err(|e| e.to_string())?
+        .ok_or("Provider profile was not created")?;
+    store_secrets(&conn, &mut created, &secrets.unwrap_or_default())?;
+    Ok(created)
+}
+
+/// Update a provider profile. Secrets with an empty value keep their stored value; secret
+/// variables missing from `profile.secret_vars` are dropped.
+#[tauri::command]
+pub async fn update_provider_profile(
+    db: State<'_, AgentDb>,
+    profile: ProviderProfile,
+    secrets: Option<BTreeMap<String, String>>,
+) -> Result<ProviderProfile, String> {
+    let id = profile.id.ok_or("Profile id is required")?;
+    validate_profile(&profile)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let rows = conn
+        .execute(
+            "UPDATE provider_profiles SET name = ?1, provider = ?2, env = ?3, secret_vars = ?4 WHERE id = ?5",
+            params![
+                profile.name,
+                profile.provider.as_str(),
+                serde_json::to_string(&profile.env).map_err(|e| e.to_string())?,
+                serde_json::to_string(&profile.secret_vars).map_err(|e| e.to_string())?,
+                id
+            ],
+        )
+        .map_err(|e| format!("Failed to update provider profile: {}", e))?;
+    if rows == 0 {
+        return Err(format!("Provider profile {} not found", id));
+    }
+
+    let mut updated = load_profile(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Provider profile {} not found", id))?;
+    store_secrets(&conn, &mut updated, &secrets.unwrap_or_default())?;
+    Ok(updated)
+}
+
+/// Delete a provider profile and unassign it from projects
+#[tauri::command]
+pub async fn delete_provider_profile(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM project_provider_profiles WHERE profile_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM provider_profiles WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete provider profile: {}", e))?;
+    Ok(())
+}
+
+/// Get the provider profile assigned to a project
+#[tauri::command]
+pub async fn get_project_provider_profile(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<Option<ProviderProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_project_profile(&conn, &project_path).map_err(|e| e.to_string())
+}
+
+/// Assign a provider profile to a project, or go back to the default with `None`
+#[tauri::command]
+pub async fn set_project_provider_profile(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    profile_id: Option<i64>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match profile_id {
+        Some(profile_id) => conn.execute(
+            "INSERT OR REPLACE INTO project_provider_profiles (project_path, profile_id) VALUES (?1, ?2)",
+            params![project_path, profile_id],
+        ),
+        None => conn.execute(
+            "DELETE FROM project_provider_profiles WHERE project_path = ?1",
+            params![project_path],
+        ),
+    }
+    .map_err(|e| format!("Failed to set project provider profile: {}", e))?;
+    Ok(())
+}
+
+/// Check that a profile's backend is reachable and, where the API allows it without
+/// spending tokens, that its credentials are accepted
+#[tauri::command]
+pub async fn test_provider_profile(
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<ProviderConnectivity, String> {
+    let profile = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_profile(&conn, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Provider profile {} not found", id))?
+    };
+    let vars: BTreeMap<String, String> = tauri::async_runtime::spawn_blocking({
+        let profile = profile.clone();
+        move || profile.env_vars()
+    })
+    .await
+    .map_err(|e| e.to_string())??
+    .into_iter()
+    .collect();
+
+    let endpoint = probe_endpoint(&profile);
+    let client = reqwest::Client::builder()
+        .timeout(TEST_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+    let mut request = client.get(&endpoint);
+    let mut has_credentials = false;
+    if matches!(
+        profile.provider,
+        ProviderKind::Anthropic | ProviderKind::Proxy
+    ) {
+        request = request.header("anthropic-version", "2023-06-01");
+        if let Some(key) = vars.get("ANTHROPIC_API_KEY") {
+            request = request.header("x-api-key", key);
+            has_credentials = true;
+        }
+        if let Some(token) = vars.get("ANTHROPIC_AUTH_TOKEN") {
+            request = request.bearer_auth(token);
+            has_credentials = true;
+        }
+    }
+
+    let started = Instant::now();
+    let response = request.send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    Ok(match response {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let (ok, message) = connectivity_result(&profile, has_credentials, status);
+            ProviderConnectivity {
+                ok,
+                status: Some(status),
+                latency_ms,
+                endpoint,
+                message,
+            }
+        }
+        Err(e) => ProviderConnectivity {
+            ok: false,
+            status: None,
+            latency_ms,
+            endpoint,
+            message: format!("Could not reach the endpoint: {}", e),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(provider: ProviderKind, env: &[(&str, &str)]) -> ProviderProfile {
+        ProviderProfile {
+            id: None,
+            name: "work".to_string(),
+            provider,
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            secret_vars: Vec::new(),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_profiles_are_validated_per_provider() {
+        assert!(validate_profile(&profile(ProviderKind::Anthropic, &[])).is_ok());
+        assert!(validate_profile(&profile(ProviderKind::Bedrock, &[]))
+            .unwrap_err()
+            .contains("AWS_REGION"));
+        assert!(validate_profile(&profile(
+            ProviderKind::Bedrock,
+            &[("AWS_REGION", "eu-west-1")]
+        ))
+        .is_ok());
+        assert!(validate_profile(&profile(
+            ProviderKind::Proxy,
+            &[("ANTHROPIC_BASE_URL", "not a url")]
+        ))
+        .is_err());
+        let error = validate_profile(&profile(
+            ProviderKind::Anthropic,
+            &[("ANTHROPIC_API_KEY", "sk-ant-plain")],
+        ))
+        .unwrap_err();
+        assert!(error.contains("secret"));
+        assert!(validate_profile(&profile(ProviderKind::Anthropic, &[("1BAD", "x")])).is_err());
+    }
+
+    #[test]
+    fn test_run_profile_falls_back_to_the_project_and_probes_its_backend() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_provider_profile_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO provider_profiles (name, provider, env) VALUES
+             ('gateway', 'proxy', '{\"ANTHROPIC_BASE_URL\":\"https://llm.internal/\"}'),
+             ('vertex', 'vertex', '{\"CLOUD_ML_REGION\":\"us-east5\"}')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO project_provider_profiles (project_path, profile_id) VALUES ('/work/app', 1)",
+            [],
+        )
+        .unwrap();
+
+        let project = profile_for_run(&conn, "/work/app", None).unwrap().unwrap();
+        assert_eq!(project.name, "gateway");
+        assert_eq!(probe_endpoint(&project), "https://llm.internal/v1/models");
+        assert_eq!(
+            connectivity_result(&project, true, 401),
+            (false, "Credentials were rejected".to_string())
+        );
+        assert!(profile_for_run(&conn, "/elsewhere", None)
+            .unwrap()
+            .is_none());
+        assert!(profile_for_run(&conn, "/work/app", Some(9)).is_err());
+
+        let vertex = profile_for_run(&conn, "/work/app", Some(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            probe_endpoint(&vertex),
+            "https://us-east5-aiplatform.googleapis.com/"
+        );
+        assert_eq!(
+            vertex.env_vars().unwrap().last(),
+            Some(&("CLAUDE_CODE_USE_VERTEX".to_string(), "1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,222 @@
+#![allow(dead_code)]
+
+//! Seams between command logic and the outside world. Commands resolve the system
+//! implementations from their `AppHandle`; the logic underneath takes the traits, so it can
+//! be driven with the in-memory implementations in `mock` without Tauri or a `claude` binary.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use super::cancellation::CancellationToken;
+
+/// Runs `claude` CLI subcommands
+pub trait ClaudeCliRunner: Send + Sync {
+    /// Run `claude mcp <args>` and return its stdout
+    fn run_mcp(&self, args: &[String], token: &CancellationToken) -> Result<String, String>;
+}
+
+/// File system access used by command logic
+pub trait FsProvider: Send + Sync {
+    fn exists(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+}
+
+/// The installed `claude` binary, through the shared CLI invoker
+pub struct SystemClaudeCli {
+    claude_path: String,
+}
+
+impl SystemClaudeCli {
+    pub fn new(claude_path: impl Into<String>) -> Self {
+        Self {
+            claude_path: claude_path.into(),
+        }
+    }
+
+    /// Use the binary selected for this app
+    pub fn for_app(app: &AppHandle) -> Result<Self, String> {
+        crate::claude_binary::find_claude_binary(app).map(Self::new)
+    }
+}
+
+impl ClaudeCliRunner for SystemClaudeCli {
+    fn run_mcp(&self, args: &[String], token: &CancellationToken) -> Result<String, String> {
+        super::mcp::run_claude_mcp_command_cancellable(&self.claude_path, args.to_vec(), token)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The real file system
+pub struct SystemFs;
+
+impl FsProvider for SystemFs {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        crate::atomic_file::write_atomic(path, contents)
+    }
+}
+
+/// In-memory implementations for tests
+pub mod mock {
+    use super::*;
+
+    /// Answers `claude mcp` calls from canned responses and records every call
+    #[derive(Default)]
+    pub struct MockClaudeCli {
+        responses: Mutex<HashMap<Vec<String>, Result<String, String>>>,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl MockClaudeCli {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Answer `claude mcp <args>` with `output`
+        pub fn respond(self, args: &[&str], output: &str) -> Self {
+            self.set(args, Ok(output.to_string()));
+            self
+        }
+
+        /// Fail `claude mcp <args>` with `error`, as the real runner reports a non-zero exit
+        pub fn fail(self, args: &[&str], error: &str) -> Self {
+            self.set(args, Err(format!("Command failed: {}", error)));
+            self
+        }
+
+        fn set(&self, args: &[&str], result: Result<String, String>) {
+            let key = args.iter().map(|arg| arg.to_string()).collect();
+            self.responses.lock().unwrap().insert(key, result);
+        }
+
+        /// Every call made so far, in order
+        pub fn calls(&self) -> Vec<Vec<String>> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl ClaudeCliRunner for MockClaudeCli {
+        fn run_mcp(&self, args: &[String], token: &CancellationToken) -> Result<String, String> {
+            token.check()?;
+            self.calls.lock().unwrap().push(args.to_vec());
+            self.responses
+                .lock()
+                .unwrap()
+                .get(args)
+                .cloned()
+                .unwrap_or_else(|| Err(format!("Command failed: unexpected call: claude mcp {}", args.join(" "))))
+        }
+    }
+
+    /// File system held in memory
+    #[derive(Default)]
+    pub struct MemoryFs {
+        files: Mutex<BTreeMap<PathBuf, String>>,
+        dirs: Mutex<BTreeSet<PathBuf>>,
+    }
+
+    impl MemoryFs {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn with_dir(self, path: impl Into<PathBuf>) -> Self {
+            self.dirs.lock().unwrap().insert(path.into());
+            self
+        }
+
+        /// Add a file; its parent directory is created too
+        pub fn with_file(self, path: impl Into<PathBuf>, contents: &str) -> Self {
+            let path = path.into();
+            if let Some(parent) = path.parent() {
+                self.dirs.lock().unwrap().insert(parent.to_path_buf());
+            }
+            self.files.lock().unwrap().insert(path, contents.to_string());
+            self
+        }
+
+        pub fn file(&self, path: impl AsRef<Path>) -> Option<String> {
+            self.files.lock().unwrap().get(path.as_ref()).cloned()
+        }
+    }
+
+    impl FsProvider for MemoryFs {
+        fn exists(&self, path: &Path) -> bool {
+            self.is_dir(path) || self.files.lock().unwrap().contains_key(path)
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.dirs.lock().unwrap().contains(path)
+        }
+
+        fn read_to_string(&self, path: &Path) -> io::Result<String> {
+            self.file(path)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display())))
+        }
+
+        fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+            if let Some(parent) = path.parent() {
+                if !parent.as_os_str().is_empty() && !self.is_dir(parent) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} not found", parent.display()),
+                    ));
+                }
+            }
+            self.files.lock().unwrap().insert(path.to_path_buf(), contents.to_string());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::*;
+    use super::*;
+
+    #[test]
+    fn test_mock_cli_answers_canned_calls_and_records_them() {
+        let cli = MockClaudeCli::new()
+            .respond(&["list"], "fs: npx fs - ✓ Connected")
+            .fail(&["get", "gone"], "No MCP server found with name: gone");
+        let token = CancellationToken::new();
+        let list = vec!["list".to_string()];
+
+        assert_eq!(cli.run_mcp(&list, &token).unwrap(), "fs: npx fs - ✓ Connected");
+        assert!(cli
+            .run_mcp(&["get".to_string(), "gone".to_string()], &token)
+            .unwrap_err()
+            .starts_with("Command failed"));
+        assert_eq!(cli.calls().len(), 2);
+
+        token.cancel();
+        assert!(cli.run_mcp(&list, &token).is_err());
+        assert_eq!(cli.calls().len(), 2);
+    }
+
+    #[test]
+    fn test_memory_fs_requires_parent_directory() {
+        let fs = MemoryFs::new().with_dir("/work");
+        fs.write(Path::new("/work/.mcp.json"), "{}").unwrap();
+        assert_eq!(fs.read_to_string(Path::new("/work/.mcp.json")).unwrap(), "{}");
+        assert!(fs.exists(Path::new("/work/.mcp.json")));
+        assert!(!fs.is_dir(Path::new("/work/.mcp.json")));
+        assert!(fs.write(Path::new("/missing/.mcp.json"), "{}").is_err());
+    }
+}
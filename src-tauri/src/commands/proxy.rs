@@ -1,10 +1,12 @@
 #![allow(dead_code)]
 
-use rusqlite::params;
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use serde_json::Value;
+use tauri::{AppHandle, State};
 
 use crate::commands::agents::AgentDb;
+use crate::commands::settings::{notify_settings_changed, set_setting_value};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProxySettings {
@@ -27,11 +29,8 @@ impl Default for ProxySettings {
     }
 }
 
-/// Get proxy settings from the database
-#[tauri::command]
-pub async fn get_proxy_settings(db: State<'_, AgentDb>) -> Result<ProxySettings, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
+/// Load proxy settings from the app_settings table
+pub fn load_proxy_settings(conn: &Connection) -> ProxySettings {
     let mut settings = ProxySettings::default();
 
     // Query each proxy setting
@@ -60,42 +59,43 @@ pub async fn get_proxy_settings(db: State<'_, AgentDb>) -> Result<ProxySettings,
         }
     }
 
-    Ok(settings)
+    settings
+}
+
+/// Get proxy settings from the database
+#[tauri::command]
+pub async fn get_proxy_settings(db: State<'_, AgentDb>) -> Result<ProxySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_proxy_settings(&conn))
 }
 
 /// Save proxy settings to the database
 #[tauri::command]
 pub async fn save_proxy_settings(
+    app: AppHandle,
     db: State<'_, AgentDb>,
     settings: ProxySettings,
 ) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    // Save each setting
+    // Save each setting; unset fields are removed rather than stored empty
     let values = vec![
-        ("proxy_enabled", settings.enabled.to_string()),
-        (
-            "proxy_http",
-            settings.http_proxy.clone().unwrap_or_default(),
-        ),
-        (
-            "proxy_https",
-            settings.https_proxy.clone().unwrap_or_default(),
-        ),
-        ("proxy_no", settings.no_proxy.clone().unwrap_or_default()),
-        ("proxy_all", settings.all_proxy.clone().unwrap_or_default()),
+        ("proxy_enabled", Some(settings.enabled.to_string())),
+        ("proxy_http", settings.http_proxy.clone()),
+        ("proxy_https", settings.https_proxy.clone()),
+        ("proxy_no", settings.no_proxy.clone()),
+        ("proxy_all", settings.all_proxy.clone()),
     ];
 
+    let mut keys = Vec::with_capacity(values.len());
     for (key, value) in values {
-        conn.execute(
-            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
-            params![key, value],
-        )
-        .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+        let value = value.filter(|v| !v.is_empty()).map(Value::String);
+        set_setting_value(&conn, key, value.as_ref())?;
+        keys.push(key);
     }
 
-    // Apply the proxy settings immediately to the current process
-    apply_proxy_settings(&settings);
+    // Applies the proxy settings to the current process and notifies listeners
+    notify_settings_changed(&app, &conn, &keys);
 
     Ok(())
 }
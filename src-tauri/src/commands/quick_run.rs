@@ -0,0 +1,400 @@
+#![allow(dead_code)]
+
+//! Quick-run command palette. `quick_run_search` fuzzy-matches agents, slash commands and
+//! recently run tasks; `quick_run` dispatches the chosen entry. A global OS shortcut opens
+//! the palette from anywhere, including when the window is hidden in the tray.
+//!
+//! Recent tasks run straight away. Agents and slash commands need more input (a task,
+//! arguments), so they show the window and hand the entry to the UI through an event.
+
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use super::agents::AgentDb;
+use super::app_lock::is_locked;
+use super::background::show_main_window;
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the palette shortcut; an empty string disables it
+pub const QUICK_RUN_SHORTCUT_KEY: &str = "quick_run_shortcut";
+pub const DEFAULT_QUICK_RUN_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+/// Emitted when the global shortcut asks the UI to open the palette
+pub const QUICK_RUN_OPEN_EVENT: &str = "quick-run:open";
+/// Emitted with an agent or slash command entry the UI should finish dispatching
+pub const QUICK_RUN_SELECTED_EVENT: &str = "quick-run:selected";
+
+const DEFAULT_LIMIT: usize = 20;
+/// Distinct recent tasks considered as candidates
+const RECENT_TASKS: usize = 50;
+
+/// The shortcut currently registered with the OS
+static REGISTERED_SHORTCUT: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickRunKind {
+    Agent,
+    SlashCommand,
+    RecentTask,
+}
+
+/// A palette entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuickRunItem {
+    pub kind: QuickRunKind,
+    /// Stable id to pass back to `quick_run`, e.g. `agent:3`
+    pub id: String,
+    pub label: String,
+    pub detail: Option<String>,
+    /// Higher is a better match
+    pub score: i64,
+    pub agent_id: Option<i64>,
+    pub project_path: Option<String>,
+    pub task: Option<String>,
+}
+
+/// What `quick_run` did with the chosen entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickRunOutcome {
+    pub item: QuickRunItem,
+    /// Run started for a recent task
+    pub run_id: Option<i64>,
+}
+
+/// Score `candidate` against `query` as a case-insensitive subsequence match, rewarding
+/// consecutive characters, word starts and a matching prefix. `None` when not all query
+/// characters appear in order.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous_match: Option<usize> = None;
+    for (index, c) in candidate.iter().enumerate() {
+        if next == query.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(std::iter::once(query[next])) {
+            continue;
+        }
+        score += 1;
+        if previous_match == Some(index.wrapping_sub(1)) {
+            score += 5;
+        }
+        let word_start = index == 0 || !candidate[index - 1].is_alphanumeric();
+        if word_start {
+            score += 8;
+        }
+        // Every character so far matched at the very start
+        if index == next {
+            score += 3;
+        }
+        previous_match = Some(index);
+        next += 1;
+    }
+    (next == query.len()).then(|| score * 100 / (candidate.len() as i64 + 10))
+}
+
+fn agents(conn: &Connection) -> Result<Vec<QuickRunItem>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, default_task, model FROM agents ORDER BY name ASC")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            Ok(QuickRunItem {
+                kind: QuickRunKind::Agent,
+                id: format!("agent:{}", id),
+                label: row.get(1)?,
+                detail: Some(row.get::<_, String>(3)?),
+                score: 0,
+                agent_id: Some(id),
+                project_path: None,
+                task: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn recent_tasks(conn: &Connection) -> Result<Vec<QuickRunItem>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.agent_id, a.name, r.task, r.project_path, MAX(r.id) AS last_run
+             FROM agent_runs r JOIN agents a ON a.id = r.agent_id
+             WHERE TRIM(r.task) != ''
+             GROUP BY r.agent_id, r.task, r.project_path
+             ORDER BY last_run DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![RECENT_TASKS as i64], |row| {
+            let agent_id: i64 = row.get(0)?;
+            let agent_name: String = row.get(1)?;
+            let task: String = row.get(2)?;
+            let project_path: String = row.get(3)?;
+            let last_run: i64 = row.get(4)?;
+            Ok(QuickRunItem {
+                kind: QuickRunKind::RecentTask,
+                id: format!("run:{}", last_run),
+                label: task.lines().next().unwrap_or_default().to_string(),
+                detail: Some(format!("{} in {}", agent_name, project_path)),
+                score: 0,
+                agent_id: Some(agent_id),
+                project_path: Some(project_path),
+                task: Some(task),
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+async fn slash_commands(project_path: Option<String>) -> Vec<QuickRunItem> {
+    let project = project_path.clone();
+    match super::slash_commands::slash_commands_list(project_path).await {
+        Ok(commands) => commands
+            .into_iter()
+            .map(|command| QuickRunItem {
+                kind: QuickRunKind::SlashCommand,
+                id: format!("command:{}", command.id),
+                label: command.full_command,
+                detail: command.description,
+                score: 0,
+                agent_id: None,
+                project_path: project.clone(),
+                task: None,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Quick run without slash commands: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Keep the candidates matching `query`, best first. Recent tasks win ties, since rerunning
+/// is what the palette is mostly used for.
+pub fn rank(query: &str, candidates: Vec<QuickRunItem>, limit: usize) -> Vec<QuickRunItem> {
+    let mut matches: Vec<QuickRunItem> = candidates
+        .into_iter()
+        .filter_map(|mut item| {
+            let label = fuzzy_score(query, &item.label);
+            let detail = item
+                .detail
+                .as_deref()
+                .and_then(|detail| fuzzy_score(query, detail))
+                .map(|score| score / 2);
+            item.score = label.into_iter().chain(detail).max()?;
+            Some(item)
+        })
+        .collect();
+    let kind_order = |kind: QuickRunKind| match kind {
+        QuickRunKind::RecentTask => 0,
+        QuickRunKind::Agent => 1,
+        QuickRunKind::SlashCommand => 2,
+    };
+    // Stable, so recent tasks keep their most-recent-first order among equal scores
+    matches.sort_by_key(|item| (-item.score, kind_order(item.kind)));
+    matches.truncate(limit);
+    matches
+}
+
+async fn search(
+    db: &AgentDb,
+    query: &str,
+    project_path: Option<String>,
+    limit: usize,
+) -> Result<Vec<QuickRunItem>, String> {
+    let mut candidates = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut candidates = recent_tasks(&conn)?;
+        candidates.extend(agents(&conn)?);
+        candidates
+    };
+    candidates.extend(slash_commands(project_path).await);
+    Ok(rank(query, candidates, limit))
+}
+
+/// Palette entries matching `query`, best first
+#[tauri::command]
+pub async fn quick_run_search(
+    db: State<'_, AgentDb>,
+    query: String,
+    project_path: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<QuickRunItem>, String> {
+    search(&db, &query, project_path, limit.unwrap_or(DEFAULT_LIMIT)).await
+}
+
+/// Dispatch the palette entry `item_id`, or the best match for `query` when none was picked
+#[tauri::command]
+pub async fn quick_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    query: String,
+    project_path: Option<String>,
+    item_id: Option<String>,
+) -> Result<QuickRunOutcome, String> {
+    let matches = search(&db, &query, project_path, usize::MAX).await?;
+    let item = match &item_id {
+        Some(id) => matches.into_iter().find(|item| &item.id == id),
+        None => matches.into_iter().next(),
+    }
+    .ok_or_else(|| format!("Nothing matches \"{}\"", query))?;
+
+    let run_id = match (&item.kind, item.agent_id, &item.project_path, &item.task) {
+        (QuickRunKind::RecentTask, Some(agent_id), Some(project_path), Some(task)) => {
+            let run_id = super::agents::execute_agent(
+                app.clone(),
+                agent_id,
+                project_path.clone(),
+                task.clone(),
+                None,
+                None,
+                None,
+                None,
+                db,
+                registry,
+            )
+            .await?;
+            info!("Quick run started run {} from {}", run_id, item.id);
+            Some(run_id)
+        }
+        _ => {
+            show_main_window(&app);
+            let _ = app.emit(QUICK_RUN_SELECTED_EVENT, &item);
+            None
+        }
+    };
+    Ok(QuickRunOutcome { item, run_id })
+}
+
+/// Replace the registered palette shortcut with `shortcut`; empty unregisters it
+fn register_shortcut(app: &AppHandle, shortcut: &str) -> Result<(), String> {
+    let mut registered = REGISTERED_SHORTCUT.lock().map_err(|e| e.to_string())?;
+    let global = app.global_shortcut();
+    if let Some(previous) = registered.take() {
+        if let Err(e) = global.unregister(previous.as_str()) {
+            warn!(
+                "Failed to unregister quick run shortcut {}: {}",
+                previous, e
+            );
+        }
+    }
+    if shortcut.trim().is_empty() {
+        return Ok(());
+    }
+    global
+        .on_shortcut(shortcut, |app, _, event| {
+            if event.state == ShortcutState::Pressed {
+                show_main_window(app);
+                // While locked the window only shows the unlock screen
+                if !is_locked() {
+                    let _ = app.emit(QUICK_RUN_OPEN_EVENT, ());
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to register shortcut {}: {}", shortcut, e))?;
+    *registered = Some(shortcut.to_string());
+    Ok(())
+}
+
+fn saved_shortcut(app: &AppHandle) -> String {
+    app.try_state::<AgentDb>()
+        .and_then(|db| {
+            db.0.lock()
+                .ok()
+                .and_then(|conn| get_setting_as(&conn, QUICK_RUN_SHORTCUT_KEY))
+        })
+        .unwrap_or_else(|| DEFAULT_QUICK_RUN_SHORTCUT.to_string())
+}
+
+/// Register the saved palette shortcut at startup
+pub fn init_quick_run_shortcut(app: &AppHandle) {
+    let shortcut = saved_shortcut(app);
+    match register_shortcut(app, &shortcut) {
+        Ok(()) if !shortcut.is_empty() => info!("Quick run shortcut: {}", shortcut),
+        Ok(()) => {}
+        Err(e) => warn!("{}", e),
+    }
+}
+
+/// The palette shortcut; empty when disabled
+#[tauri::command]
+pub async fn get_quick_run_shortcut(app: AppHandle) -> Result<String, String> {
+    Ok(saved_shortcut(&app))
+}
+
+/// Change the palette shortcut, e.g. `Alt+Space`; empty disables it. The old shortcut stays
+/// registered when the new one is rejected.
+#[tauri::command]
+pub async fn set_quick_run_shortcut(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    shortcut: String,
+) -> Result<(), String> {
+    let previous = saved_shortcut(&app);
+    if let Err(e) = register_shortcut(&app, &shortcut) {
+        let _ = register_shortcut(&app, &previous);
+        return Err(e);
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, QUICK_RUN_SHORTCUT_KEY, &shortcut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(kind: QuickRunKind, id: &str, label: &str) -> QuickRunItem {
+        QuickRunItem {
+            kind,
+            id: id.to_string(),
+            label: label.to_string(),
+            detail: None,
+            score: 0,
+            agent_id: None,
+            project_path: None,
+            task: None,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts_and_runs() {
+        assert_eq!(fuzzy_score("xyz", "Code Reviewer"), None);
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        let initials = fuzzy_score("cr", "Code Reviewer").unwrap();
+        let scattered = fuzzy_score("cr", "Security Scanner").unwrap();
+        assert!(initials > scattered);
+        let prefix = fuzzy_score("test", "Tests").unwrap();
+        let inside = fuzzy_score("test", "Latest notes").unwrap();
+        assert!(prefix > inside);
+    }
+
+    #[test]
+    fn test_rank_filters_orders_and_limits() {
+        let candidates = vec![
+            item(QuickRunKind::SlashCommand, "command:1", "/project:review"),
+            item(QuickRunKind::Agent, "agent:1", "Reviewer"),
+            item(QuickRunKind::RecentTask, "run:9", "Reviewer"),
+            item(QuickRunKind::Agent, "agent:2", "Deployer"),
+        ];
+        let ranked = rank("rev", candidates.clone(), 10);
+        let ids: Vec<&str> = ranked.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, vec!["run:9", "agent:1", "command:1"]);
+        assert_eq!(rank("rev", candidates, 1).len(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+
+/// Entries returned when no limit is given
+const DEFAULT_RECENT_LIMIT: usize = 20;
+
+/// Unpinned entries kept; older ones are dropped when a project is recorded
+const MAX_RECENT_PROJECTS: i64 = 200;
+
+/// A project the app has recently run something in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentProject {
+    pub path: String,
+    /// Last path component, for display
+    pub name: String,
+    pub pinned: bool,
+    pub open_count: i64,
+    pub last_opened_at: String,
+    /// What last targeted the project, e.g. `session` or `agent`
+    pub last_source: Option<String>,
+    /// False when the directory was moved or deleted
+    pub exists: bool,
+    pub git: Option<GitStatus>,
+}
+
+/// Git state of a project's working tree
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GitStatus {
+    /// Current branch, or `None` on a detached HEAD
+    pub branch: Option<String>,
+    /// Whether there are uncommitted changes
+    pub dirty: bool,
+}
+
+pub fn init_recent_project_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recent_projects (
+            path TEXT PRIMARY KEY,
+            pinned BOOLEAN NOT NULL DEFAULT 0,
+            open_count INTEGER NOT NULL DEFAULT 0,
+            last_source TEXT,
+            last_opened_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Record that `path` was targeted by a session or agent run
+pub fn record_recent_project(conn: &Connection, path: &str, source: &str) -> SqliteResult<()> {
+    let path = path.trim_end_matches(['/', '\\']);
+    if path.is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO recent_projects (path, open_count, last_source, last_opened_at)
+         VALUES (?1, 1, ?2, strftime('%Y-%m-%d %H:%M:%f', 'now'))
+         ON CONFLICT(path) DO UPDATE SET
+            open_count = open_count + 1,
+            last_source = excluded.last_source,
+            last_opened_at = excluded.last_opened_at",
+        params![path, source],
+    )?;
+    conn.execute(
+        "DELETE FROM recent_projects WHERE pinned = 0 AND path NOT IN (
+            SELECT path FROM recent_projects WHERE pinned = 0
+            ORDER BY last_opened_at DESC LIMIT ?1
+        )",
+        params![MAX_RECENT_PROJECTS],
+    )?;
+    Ok(())
+}
+
+/// Record a recent project from a command that only has the app handle;
+/// failures are logged, never surfaced to the run
+pub fn touch_recent_project(app: &AppHandle, path: &str, source: &str) {
+    let Some(db) = app.try_state::<AgentDb>() else {
+        return;
+    };
+    let result = match db.0.lock() {
+        Ok(conn) => record_recent_project(&conn, path, source).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to record recent project {}: {}", path, e);
+    }
+}
+
+fn run_git(path: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| crate::decoding::decode_command_output(&output.stdout).trim().to_string())
+}
+
+/// Git branch and dirty state, or `None` if `path` isn't in a git work tree
+pub fn git_status(path: &str) -> Option<GitStatus> {
+    if run_git(path, &["rev-parse", "--is-inside-work-tree"])?.as_str() != "true" {
+        return None;
+    }
+    let branch = run_git(path, &["symbolic-ref", "--short", "-q", "HEAD"])
+        .filter(|branch| !branch.is_empty());
+    let dirty = run_git(path, &["status", "--porcelain", "--untracked-files=normal"])
+        .is_some_and(|status| !status.is_empty());
+    Some(GitStatus { branch, dirty })
+}
+
+fn load_recent_projects(conn: &Connection, limit: usize) -> SqliteResult<Vec<RecentProject>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, pinned, open_count, last_source, last_opened_at FROM recent_projects
+         ORDER BY pinned DESC, last_opened_at DESC LIMIT ?1",
+    )?;
+    let projects = stmt
+        .query_map(params![limit as i64], |row| {
+            let path: String = row.get(0)?;
+            Ok(RecentProject {
+                name: Path::new(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone()),
+                path,
+                pinned: row.get(1)?,
+                open_count: row.get(2)?,
+                last_source: row.get(3)?,
+                last_opened_at: row.get(4)?,
+                exists: false,
+                git: None,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(projects)
+}
+
+/// Recently used projects, pinned ones first, with existence and git status
+#[tauri::command]
+pub async fn get_recent_projects(
+    db: State<'_, AgentDb>,
+    limit: Option<usize>,
+) -> Result<Vec<RecentProject>, String> {
+    let projects = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_recent_projects(&conn, limit.unwrap_or(DEFAULT_RECENT_LIMIT))
+            .map_err(|e| e.to_string())?
+    };
+
+    // git is slow on large trees; keep it off the async runtime
+    tokio::task::spawn_blocking(move || {
+        projects
+            .into_iter()
+            .map(|mut project| {
+                project.exists = Path::new(&project.path).is_dir();
+                if project.exists {
+                    project.git = git_status(&project.path);
+                }
+                project
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Add a project to the recent list by hand, e.g. from the file picker
+#[tauri::command]
+pub async fn add_recent_project(db: State<'_, AgentDb>, path: String) -> Result<(), String> {
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_recent_project(&conn, &path, "manual").map_err(|e| e.to_string())
+}
+
+/// Pin or unpin a recent project; pinned projects are listed first and never dropped
+#[tauri::command]
+pub async fn set_recent_project_pinned(
+    db: State<'_, AgentDb>,
+    path: String,
+    pinned: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let rows = conn
+        .execute(
+            "UPDATE recent_projects SET pinned = ?1 WHERE path = ?2",
+            params![pinned, path],
+        )
+        .map_err(|e| e.to_string())?;
+    if rows == 0 {
+        return Err(format!("Not a recent project: {}", path));
+    }
+    Ok(())
+}
+
+/// Remove a project from the recent list
+#[tauri::command]
+pub async fn remove_recent_project(db: State<'_, AgentDb>, path: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM recent_projects WHERE path = ?1", params![path])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_orders_pinned_first_then_most_recent() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_recent_project_tables(&conn).unwrap();
+
+        record_recent_project(&conn, "/work/a", "session").unwrap();
+        record_recent_project(&conn, "/work/b/", "agent").unwrap();
+        record_recent_project(&conn, "/work/a", "agent").unwrap();
+        conn.execute("UPDATE recent_projects SET pinned = 1 WHERE path = '/work/b'", [])
+            .unwrap();
+        record_recent_project(&conn, "/work/c", "session").unwrap();
+        conn.execute(
+            "UPDATE recent_projects SET last_opened_at = '2999-01-01 00:00:00.000' WHERE path = '/work/c'",
+            [],
+        )
+        .unwrap();
+
+        let projects = load_recent_projects(&conn, 10).unwrap();
+        let paths: Vec<_> = projects.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(paths, vec!["/work/b", "/work/c", "/work/a"]);
+        assert_eq!(projects[2].open_count, 2);
+        assert_eq!(projects[2].last_source.as_deref(), Some("agent"));
+        assert_eq!(projects[0].name, "b");
+    }
+
+    #[test]
+    fn test_git_status_outside_a_repo_is_none() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(git_status(&dir.path().to_string_lossy()), None);
+    }
+}
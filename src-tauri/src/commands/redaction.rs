@@ -0,0 +1,385 @@
+#![allow(dead_code)]
+
+//! Scrubbing of transcripts before they leave the machine. Exports and clipboard copies
+//! run every string through a `Redactor` built from the `redaction_config` setting and
+//! report how many replacements each category made.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, State};
+
+use super::agents::AgentDb;
+use super::claude::load_session_history;
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the `RedactionConfig`
+pub const REDACTION_CONFIG_KEY: &str = "redaction_config";
+
+/// Known API key and token shapes
+pub(crate) const API_KEY_PATTERNS: &[&str] = &[
+    r"sk-(?:ant-)?[A-Za-z0-9_\-]{20,}",
+    r"gh[pousr]_[A-Za-z0-9]{36,}",
+    r"github_pat_[A-Za-z0-9_]{22,}",
+    r"AKIA[0-9A-Z]{16}",
+    r"xox[abprs]-[A-Za-z0-9\-]{10,}",
+    r"AIza[0-9A-Za-z_\-]{35}",
+    r"(?i)bearer\s+[A-Za-z0-9._\-]{20,}",
+];
+
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}";
+
+/// Home directories of any user, on macOS, Linux and Windows
+const HOME_PATH_PATTERN: &str = r"(?:/Users|/home|[A-Za-z]:\\{1,2}Users)[/\\]{1,2}[^/\\\s\x22',;:)\]]+";
+
+/// A user-defined pattern
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomRedactionPattern {
+    /// Category name used in the report
+    pub name: String,
+    pub pattern: String,
+    /// Replacement text; defaults to `[REDACTED:<name>]`
+    pub replacement: Option<String>,
+}
+
+/// Which categories to scrub
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub api_keys: bool,
+    pub emails: bool,
+    pub home_paths: bool,
+    pub custom_patterns: Vec<CustomRedactionPattern>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            api_keys: true,
+            emails: true,
+            home_paths: true,
+            custom_patterns: Vec::new(),
+        }
+    }
+}
+
+/// Replacements made per category
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RedactionReport {
+    pub counts: BTreeMap<String, usize>,
+    pub total: usize,
+}
+
+impl RedactionReport {
+    fn add(&mut self, category: &str, count: usize) {
+        if count > 0 {
+            *self.counts.entry(category.to_string()).or_default() += count;
+            self.total += count;
+        }
+    }
+}
+
+/// Text with redactions applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactedText {
+    pub text: String,
+    pub report: RedactionReport,
+}
+
+/// Output format for session exports
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionExportFormat {
+    Jsonl,
+    Markdown,
+}
+
+struct Rule {
+    category: String,
+    regex: Regex,
+    replacement: String,
+}
+
+/// Compiled redaction rules
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        let mut push = |category: &str, pattern: &str, replacement: &str| -> Result<(), String> {
+            let regex = Regex::new(pattern)
+                .map_err(|e| format!("Invalid redaction pattern for {}: {}", category, e))?;
+            rules.push(Rule {
+                category: category.to_string(),
+                regex,
+                replacement: replacement.to_string(),
+            });
+            Ok(())
+        };
+
+        // Custom patterns go first so they can target things the built-ins would mangle
+        for custom in &config.custom_patterns {
+            if custom.name.trim().is_empty() {
+                return Err("Custom redaction patterns need a name".to_string());
+            }
+            let replacement = custom
+                .replacement
+                .clone()
+                .unwrap_or_else(|| format!("[REDACTED:{}]", custom.name));
+            push(&custom.name, &custom.pattern, &replacement)?;
+        }
+        if config.api_keys {
+            push("api_keys", &API_KEY_PATTERNS.join("|"), "[REDACTED_API_KEY]")?;
+        }
+        if config.emails {
+            push("emails", EMAIL_PATTERN, "[REDACTED_EMAIL]")?;
+        }
+        if config.home_paths {
+            push("home_paths", HOME_PATH_PATTERN, "~")?;
+        }
+        Ok(Self { rules })
+    }
+
+    /// Redact a string, adding the replacements made to `report`
+    pub fn redact(&self, text: &str, report: &mut RedactionReport) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let count = rule.regex.find_iter(&text).count();
+            if count > 0 {
+                text = rule
+                    .regex
+                    .replace_all(&text, regex::NoExpand(&rule.replacement))
+                    .into_owned();
+                report.add(&rule.category, count);
+            }
+        }
+        text
+    }
+
+    /// Redact every string (and object key) in a JSON value, keeping it valid JSON
+    pub fn redact_json(&self, value: &Value, report: &mut RedactionReport) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.redact(s, report)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|item| self.redact_json(item, report)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (self.redact(key, report), self.redact_json(value, report)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+fn load_config(db: &AgentDb) -> Result<RedactionConfig, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(get_setting_as(&conn, REDACTION_CONFIG_KEY).unwrap_or_default())
+}
+
+/// Text of a message's content, whether it's a string or a list of blocks
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.as_str()),
+                _ if item.get("type").and_then(Value::as_str) == Some("text") => {
+                    item.get("text").and_then(Value::as_str)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
+
+/// Render user and assistant messages as Markdown, matching the session view's copy
+pub fn render_session_markdown(messages: &[Value]) -> String {
+    messages
+        .iter()
+        .filter_map(|message| {
+            let role = match message.get("type").and_then(Value::as_str)? {
+                "user" => "User",
+                "assistant" => "Assistant",
+                _ => return None,
+            };
+            let text = message
+                .get("message")
+                .and_then(|m| m.get("content"))
+                .map(content_text)
+                .unwrap_or_default();
+            (!text.trim().is_empty()).then(|| format!("## {}\n\n{}", role, text))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n")
+}
+
+/// Load a session and render it, redacting unless `redact` is false
+async fn render_session(
+    db: &AgentDb,
+    session_id: String,
+    project_id: String,
+    format: SessionExportFormat,
+    redact: bool,
+) -> Result<RedactedText, String> {
+    let mut messages = load_session_history(session_id, project_id).await?;
+    let mut report = RedactionReport::default();
+    if redact {
+        let redactor = Redactor::new(&load_config(db)?)?;
+        messages = messages
+            .iter()
+            .map(|message| redactor.redact_json(message, &mut report))
+            .collect();
+    }
+
+    let text = match format {
+        SessionExportFormat::Jsonl => messages
+            .iter()
+            .map(|message| message.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        SessionExportFormat::Markdown => render_session_markdown(&messages),
+    };
+    Ok(RedactedText { text, report })
+}
+
+/// Get the redaction settings
+#[tauri::command]
+pub async fn get_redaction_config(db: State<'_, AgentDb>) -> Result<RedactionConfig, String> {
+    load_config(&db)
+}
+
+/// Save the redaction settings; custom patterns must compile
+#[tauri::command]
+pub async fn set_redaction_config(
+    db: State<'_, AgentDb>,
+    config: RedactionConfig,
+) -> Result<(), String> {
+    Redactor::new(&config)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, REDACTION_CONFIG_KEY, &config)
+}
+
+/// Redact arbitrary text with the saved settings
+#[tauri::command]
+pub async fn redact_text(db: State<'_, AgentDb>, text: String) -> Result<RedactedText, String> {
+    let redactor = Redactor::new(&load_config(&db)?)?;
+    let mut report = RedactionReport::default();
+    let text = redactor.redact(&text, &mut report);
+    Ok(RedactedText { text, report })
+}
+
+/// Export a session transcript to a file, redacted unless `redact` is false
+#[tauri::command]
+pub async fn export_session(
+    db: State<'_, AgentDb>,
+    session_id: String,
+    project_id: String,
+    output_path: String,
+    format: SessionExportFormat,
+    redact: Option<bool>,
+) -> Result<RedactionReport, String> {
+    let rendered =
+        render_session(&db, session_id, project_id, format, redact.unwrap_or(true)).await?;
+    std::fs::write(&output_path, rendered.text)
+        .map_err(|e| format!("Failed to write export: {}", e))?;
+    log::info!(
+        "Exported session to {} with {} redaction(s)",
+        output_path,
+        rendered.report.total
+    );
+    Ok(rendered.report)
+}
+
+/// Copy a session transcript to the clipboard, redacted unless `redact` is false
+#[tauri::command]
+pub async fn copy_session_to_clipboard(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    session_id: String,
+    project_id: String,
+    format: SessionExportFormat,
+    redact: Option<bool>,
+) -> Result<RedactionReport, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let rendered =
+        render_session(&db, session_id, project_id, format, redact.unwrap_or(true)).await?;
+    app.clipboard()
+        .write_text(rendered.text)
+        .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+    Ok(rendered.report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_counts_per_category() {
+        let config = RedactionConfig {
+            custom_patterns: vec![CustomRedactionPattern {
+                name: "ticket".to_string(),
+                pattern: r"ACME-\d+".to_string(),
+                replacement: None,
+            }],
+            ..Default::default()
+        };
+        let redactor = Redactor::new(&config).unwrap();
+        let mut report = RedactionReport::default();
+        let text = redactor.redact(
+            "key sk-ant-REDACTED for dev@example.com in /Users/alice/src and /home/bob, see ACME-12",
+            &mut report,
+        );
+
+        assert_eq!(
+            text,
+            "key [REDACTED_API_KEY] for [REDACTED_EMAIL] in ~/src and ~, see [REDACTED:ticket]"
+        );
+        assert_eq!(report.counts["api_keys"], 1);
+        assert_eq!(report.counts["emails"], 1);
+        assert_eq!(report.counts["home_paths"], 2);
+        assert_eq!(report.counts["ticket"], 1);
+        assert_eq!(report.total, 5);
+
+        let bad = RedactionConfig {
+            custom_patterns: vec![CustomRedactionPattern {
+                name: "broken".to_string(),
+                pattern: "(".to_string(),
+                replacement: None,
+            }],
+            ..Default::default()
+        };
+        assert!(Redactor::new(&bad).is_err());
+    }
+
+    #[test]
+    fn test_redact_json_keeps_structure_and_markdown_renders() {
+        let redactor = Redactor::new(&RedactionConfig::default()).unwrap();
+        let mut report = RedactionReport::default();
+        let messages = vec![
+            json!({"type": "user", "cwd": "C:\\Users\\alice\\app", "message": {"content": "mail me at a@b.io"}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "Done"}, {"type": "tool_use"}]}}),
+            json!({"type": "system"}),
+        ];
+        let redacted: Vec<Value> = messages
+            .iter()
+            .map(|message| redactor.redact_json(message, &mut report))
+            .collect();
+
+        assert_eq!(redacted[0]["cwd"], "~\\app");
+        assert_eq!(report.total, 2);
+        assert_eq!(
+            render_session_markdown(&redacted),
+            "## User\n\nmail me at [REDACTED_EMAIL]\n\n---\n\n## Assistant\n\nDone"
+        );
+    }
+}
@@ -0,0 +1,707 @@
+#![allow(dead_code)]
+
+//! Remote execution over SSH. A project assigned to a host runs its agents there: the run's
+//! command becomes `ssh host 'sh -c "<read env> cd <remote path> && exec claude -p ..."'`,
+//! so its output streams through the same pipeline as a local run. The environment is read
+//! from ssh's stdin, keeping API keys out of process listings. Local project paths are
+//! translated with the host's path mappings, and the claude binary is detected per host, as
+//! non-interactive SSH sessions don't load the shell profile that puts it on PATH.
+//!
+//! Keys come from the SSH agent (and `~/.ssh/config`) or, for hosts set up with a private
+//! key, from the OS keychain; that key is written to a private file for the `ssh` call.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::error::{ErrorKind, OpcodeError};
+use super::keychain;
+use super::terminal::{check_command, CommandOutput};
+
+/// Longest wait for a command run on a host outside an agent run
+const REMOTE_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where the claude installer and package managers put the binary, tried when it's not on
+/// the non-interactive PATH
+const REMOTE_CLAUDE_CANDIDATES: &[&str] = &[
+    "$HOME/.claude/local/claude",
+    "$HOME/.local/bin/claude",
+    "$HOME/.npm-global/bin/claude",
+    "$HOME/.bun/bin/claude",
+    "/usr/local/bin/claude",
+    "/opt/homebrew/bin/claude",
+];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// The SSH agent and `~/.ssh/config`
+    #[default]
+    Agent,
+    /// A private key stored in the OS keychain
+    Keychain,
+}
+
+impl KeySource {
+    fn as_str(self) -> &'static str {
+        match self {
+            KeySource::Agent => "agent",
+            KeySource::Keychain => "keychain",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "keychain" => KeySource::Keychain,
+            _ => KeySource::Agent,
+        }
+    }
+}
+
+/// A local directory and where the same checkout lives on the host
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathMapping {
+    pub local: String,
+    pub remote: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteHost {
+    pub id: Option<i64>,
+    pub name: String,
+    /// Host name or `~/.ssh/config` alias
+    pub host: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub key_source: KeySource,
+    #[serde(default)]
+    pub path_mappings: Vec<PathMapping>,
+    /// Set by detection
+    pub claude_path: Option<String>,
+    pub claude_version: Option<String>,
+    pub detected_at: Option<String>,
+}
+
+pub fn init_remote_host_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS remote_hosts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            host TEXT NOT NULL,
+            user TEXT,
+            port INTEGER,
+            key_source TEXT NOT NULL DEFAULT 'agent',
+            path_mappings TEXT NOT NULL DEFAULT '[]',
+            claude_path TEXT,
+            claude_version TEXT,
+            detected_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_remote_hosts (
+            project_path TEXT PRIMARY KEY,
+            host_id INTEGER NOT NULL,
+            FOREIGN KEY (host_id) REFERENCES remote_hosts(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const HOST_COLUMNS: &str =
+    "id, name, host, user, port, key_source, path_mappings, claude_path, claude_version, detected_at";
+
+fn host_from_row(row: &rusqlite::Row) -> rusqlite::Result<RemoteHost> {
+    let key_source: String = row.get(5)?;
+    let path_mappings: String = row.get(6)?;
+    Ok(RemoteHost {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        host: row.get(2)?,
+        user: row.get(3)?,
+        port: row.get(4)?,
+        key_source: KeySource::parse(&key_source),
+        path_mappings: serde_json::from_str(&path_mappings).unwrap_or_default(),
+        claude_path: row.get(7)?,
+        claude_version: row.get(8)?,
+        detected_at: row.get(9)?,
+    })
+}
+
+pub fn load_host(conn: &Connection, id: i64) -> SqliteResult<Option<RemoteHost>> {
+    conn.query_row(
+        &format!("SELECT {} FROM remote_hosts WHERE id = ?1", HOST_COLUMNS),
+        params![id],
+        host_from_row,
+    )
+    .optional()
+}
+
+/// The host `project_path` runs on, if it was assigned one
+pub fn host_for_project(conn: &Connection, project_path: &str) -> SqliteResult<Option<RemoteHost>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM remote_hosts WHERE id = (SELECT host_id FROM project_remote_hosts WHERE project_path = ?1)",
+            HOST_COLUMNS
+        ),
+        params![project_path],
+        host_from_row,
+    )
+    .optional()
+}
+
+/// `value` quoted for a POSIX shell
+pub fn shell_quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c))
+    {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn validate_host(host: &RemoteHost) -> Result<(), String> {
+    if host.name.trim().is_empty() {
+        return Err("Host name is required".to_string());
+    }
+    // Anything starting with '-' would be read by ssh as an option
+    let invalid = |value: &str| {
+        value.is_empty() || value.starts_with('-') || value.chars().any(char::is_whitespace)
+    };
+    if invalid(&host.host) {
+        return Err(format!("Invalid host: '{}'", host.host));
+    }
+    if host.user.as_deref().is_some_and(invalid) {
+        return Err("Invalid user name".to_string());
+    }
+    for mapping in &host.path_mappings {
+        if !Path::new(&mapping.local).is_absolute() || !mapping.remote.starts_with('/') {
+            return Err(format!(
+                "Path mappings need absolute paths: {} -> {}",
+                mapping.local, mapping.remote
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl RemoteHost {
+    /// Where `local` is on the host, from the longest matching mapping
+    pub fn map_path(&self, local: &str) -> Result<String, String> {
+        let local_path = Path::new(local);
+        self.path_mappings
+            .iter()
+            .filter_map(|mapping| {
+                let rest = local_path.strip_prefix(&mapping.local).ok()?;
+                Some((mapping.local.len(), mapping, rest))
+            })
+            .max_by_key(|(len, _, _)| *len)
+            .map(|(_, mapping, rest)| {
+                let remote = mapping.remote.trim_end_matches('/');
+                let rest: Vec<String> = rest
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                if rest.is_empty() && remote.is_empty() {
+                    "/".to_string()
+                } else if rest.is_empty() {
+                    remote.to_string()
+                } else {
+                    format!("{}/{}", remote, rest.join("/"))
+                }
+            })
+            .ok_or_else(|| format!("No path mapping of {} covers {}", self.name, local))
+    }
+
+    fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Options and destination for `ssh`, never prompting so a run can't hang on a password
+    pub fn ssh_args(&self, key_file: Option<&Path>) -> Vec<String> {
+        let mut args: Vec<String> = ["-T", "-o", "BatchMode=yes", "-o", "ServerAliveInterval=30"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        if let Some(key_file) = key_file {
+            args.extend([
+                "-i".to_string(),
+                key_file.to_string_lossy().to_string(),
+                "-o".to_string(),
+                "IdentitiesOnly=yes".to_string(),
+            ]);
+        }
+        args.push(self.destination());
+        args
+    }
+
+    /// The `ssh` program and arguments running the host's claude with `args` in the remote
+    /// counterpart of `project_path`. The remote side first reads the run's environment from
+    /// stdin (see `env_input`), so values like API keys never appear on a command line, then
+    /// prints `READY_MARKER` on stderr.
+    /// `key_file` comes from `key_file()` and must outlive the ssh process.
+    pub fn wrap_command(
+        &self,
+        args: Vec<String>,
+        project_path: &str,
+        key_file: Option<&Path>,
+    ) -> Result<(String, Vec<String>), String> {
+        let remote_dir = self.map_path(project_path)?;
+        let claude = self.claude_path.as_deref().unwrap_or("claude");
+        let mut script = format!(
+            "while IFS= read -r line && [ -n \"$line\" ]; do export \"$line\"; done; echo {} >&2; cd {} && exec {}",
+            READY_MARKER,
+            shell_quote(&remote_dir),
+            shell_quote(claude)
+        );
+        for arg in &args {
+            script.push(' ');
+            script.push_str(&shell_quote(arg));
+        }
+        let mut ssh_args = self.ssh_args(key_file);
+        ssh_args.push(format!("sh -c {}", shell_quote(&script)));
+        Ok(("ssh".to_string(), ssh_args))
+    }
+}
+
+/// What to write to the stdin of a `wrap_command` process: one `NAME=value` line per
+/// variable and an empty line ending the list
+pub fn env_input(env: &[(String, String)]) -> Result<String, String> {
+    let mut input = String::new();
+    for (name, value) in env {
+        let valid_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!("{} is not a valid environment variable name", name));
+        }
+        if value.contains(['\n', '\r']) {
+            return Err(format!("The value of {} can't span several lines", name));
+        }
+        input.push_str(name);
+        input.push('=');
+        input.push_str(value);
+        input.push('\n');
+    }
+    input.push('\n');
+    Ok(input)
+}
+
+/// Line a `wrap_command` process prints on stderr once ssh has connected and the remote
+/// shell has started; the run's first-output timeout starts from there
+pub const READY_MARKER: &str = "opcode-remote-ready";
+
+fn key_account(host_id: i64) -> String {
+    format!("remote-host:{}:private-key", host_id)
+}
+
+/// A private key written out for `ssh -i`; the file is deleted when this is dropped, so keep
+/// it alive until ssh has exited
+#[derive(Debug)]
+pub(crate) struct KeyFile {
+    path: PathBuf,
+}
+
+impl KeyFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for KeyFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Failed to delete key file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// The keychain key of `host` written to a fresh file only the user can read, for `ssh -i`
+pub(crate) fn key_file(host: &RemoteHost) -> Result<Option<KeyFile>, String> {
+    if host.key_source != KeySource::Keychain {
+        return Ok(None);
+    }
+    let id = host.id.ok_or("Host id is required")?;
+    let key = keychain::load_secret(&key_account(id))
+        .ok_or_else(|| format!("The private key of {} is not in the keychain", host.name))?;
+    let log_dir = crate::logger::log_dir();
+    let dir = log_dir
+        .parent()
+        .map(|dir| dir.join("ssh-keys"))
+        .unwrap_or_else(|| log_dir.join("ssh-keys"));
+
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700);
+        builder.create(&dir).map_err(|e| e.to_string())?;
+        // The directory may predate this mode
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(not(unix))]
+    builder.create(&dir).map_err(|e| e.to_string())?;
+
+    // A new file per connection, never readable by others even briefly
+    let path = dir.join(format!("{}-{}", id, uuid::Uuid::new_v4()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path).map_err(|e| e.to_string())?;
+    let key_file = KeyFile { path };
+    // ssh needs the trailing newline
+    file.write_all(format!("{}\n", key.trim_end()).as_bytes())
+        .map_err(|e| e.to_string())?;
+    Ok(Some(key_file))
+}
+
+/// Run `command` through the host's shell, outside of any agent run
+async fn run_remote(host: &RemoteHost, command: String) -> Result<CommandOutput, String> {
+    let key = key_file(host)?;
+    let mut args = host.ssh_args(key.as_ref().map(KeyFile::path));
+    args.push(command);
+    let output = tokio::time::timeout(
+        REMOTE_COMMAND_TIMEOUT,
+        tokio::process::Command::new("ssh")
+            .args(&args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| {
+        format!(
+            "{} did not answer within {}s",
+            host.name,
+            REMOTE_COMMAND_TIMEOUT.as_secs()
+        )
+    })?
+    .map_err(|e| format!("Failed to run ssh: {}", e))?;
+    Ok(CommandOutput {
+        stdout: crate::decoding::decode_command_output(&output.stdout),
+        stderr: crate::decoding::decode_command_output(&output.stderr),
+        exit_code: output.status.code().unwrap_or(-1),
+    })
+}
+
+/// Shell script printing the claude binary's path, then its version
+fn detect_script() -> String {
+    let candidates = REMOTE_CLAUDE_CANDIDATES
+        .iter()
+        .map(|candidate| format!("\"{}\"", candidate))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "p=$(command -v claude); if [ -z \"$p\" ]; then for c in {}; do if [ -x \"$c\" ]; then p=$c; break; fi; done; fi; \
+         [ -n \"$p\" ] && echo \"$p\" && \"$p\" --version",
+        candidates
+    )
+}
+
+/// Path and version from the output of [`detect_script`]
+fn parse_detection(stdout: &str) -> Option<(String, Option<String>)> {
+    let mut lines = stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let path = lines.next().filter(|path| path.starts_with('/'))?;
+    Some((path.to_string(), lines.next().map(|line| line.to_string())))
+}
+
+#[tauri::command]
+pub async fn list_remote_hosts(db: State<'_, AgentDb>) -> Result<Vec<RemoteHost>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM remote_hosts ORDER BY name ASC",
+            HOST_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let hosts = stmt
+        .query_map([], host_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(hosts)
+}
+
+/// Create or update a host. A `private_key` is stored in the keychain and makes the host use
+/// it; an empty one keeps the stored key.
+#[tauri::command]
+pub async fn save_remote_host(
+    db: State<'_, AgentDb>,
+    mut host: RemoteHost,
+    private_key: Option<String>,
+) -> Result<RemoteHost, String> {
+    let private_key = private_key.filter(|key| !key.trim().is_empty());
+    if private_key.is_some() {
+        host.key_source = KeySource::Keychain;
+    }
+    validate_host(&host)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mappings = serde_json::to_string(&host.path_mappings).map_err(|e| e.to_string())?;
+    let id = match host.id {
+        Some(id) => {
+            let rows = conn
+                .execute(
+                    "UPDATE remote_hosts SET name = ?1, host = ?2, user = ?3, port = ?4, key_source = ?5, path_mappings = ?6 WHERE id = ?7",
+                    params![host.name, host.host, host.user, host.port, host.key_source.as_str(), mappings, id],
+                )
+                .map_err(|e| format!("Failed to update remote host: {}", e))?;
+            if rows == 0 {
+                return Err(format!("Remote host {} not found", id));
+            }
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO remote_hosts (name, host, user, port, key_source, path_mappings) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![host.name, host.host, host.user, host.port, host.key_source.as_str(), mappings],
+            )
+            .map_err(|e| format!("Failed to create remote host: {}", e))?;
+            conn.last_insert_rowid()
+        }
+    };
+    if let Some(private_key) = private_key {
+        keychain::store_secret(&key_account(id), &private_key)?;
+    }
+    load_host(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Remote host {} not found", id))
+}
+
+/// Delete a host and unassign it from projects
+#[tauri::command]
+pub async fn delete_remote_host(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM project_remote_hosts WHERE host_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM remote_hosts WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete remote host: {}", e))?;
+    // The keychain helpers can't delete; an empty secret reads back as missing
+    let _ = keychain::store_secret(&key_account(id), "");
+    Ok(())
+}
+
+/// Find the claude binary on the host and record its path and version
+#[tauri::command]
+pub async fn detect_remote_claude(db: State<'_, AgentDb>, id: i64) -> Result<RemoteHost, String> {
+    let host = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_host(&conn, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Remote host {} not found", id))?
+    };
+    let output = run_remote(&host, detect_script()).await?;
+    let (path, version) =
+        parse_detection(&output.stdout).ok_or_else(|| match output.stderr.trim() {
+            "" => format!("claude was not found on {}", host.name),
+            stderr => format!("claude was not found on {}: {}", host.name, stderr),
+        })?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE remote_hosts SET claude_path = ?1, claude_version = ?2, detected_at = ?3 WHERE id = ?4",
+        params![path, version, chrono::Utc::now().to_rfc3339(), id],
+    )
+    .map_err(|e| e.to_string())?;
+    load_host(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Remote host {} not found", id))
+}
+
+/// Get the host a project's agents run on
+#[tauri::command]
+pub async fn get_project_remote_host(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<Option<RemoteHost>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    host_for_project(&conn, &project_path).map_err(|e| e.to_string())
+}
+
+/// Run a project's agents on a host, or locally again with `None`. The host must map the
+/// project's path.
+#[tauri::command]
+pub async fn set_project_remote_host(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    host_id: Option<i64>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match host_id {
+        Some(host_id) => {
+            let host = load_host(&conn, host_id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Remote host {} not found", host_id))?;
+            host.map_path(&project_path)?;
+            conn.execute(
+                "INSERT OR REPLACE INTO project_remote_hosts (project_path, host_id) VALUES (?1, ?2)",
+                params![project_path, host_id],
+            )
+        }
+        None => conn.execute(
+            "DELETE FROM project_remote_hosts WHERE project_path = ?1",
+            params![project_path],
+        ),
+    }
+    .map_err(|e| format!("Failed to set project remote host: {}", e))?;
+    Ok(())
+}
+
+/// Run a terminal command on a host, in the remote counterpart of `working_dir`. The same
+/// command allowlist as local terminal commands applies.
+#[tauri::command]
+pub async fn execute_remote_terminal_command(
+    db: State<'_, AgentDb>,
+    host_id: i64,
+    command: String,
+    working_dir: Option<String>,
+) -> Result<CommandOutput, OpcodeError> {
+    check_command(&command, None)?;
+    let host = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_host(&conn, host_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| {
+                OpcodeError::new(
+                    ErrorKind::NotFound,
+                    format!("Remote host {} not found", host_id),
+                )
+            })?
+    };
+    let remote_command = match working_dir {
+        Some(dir) => format!("cd {} && {}", shell_quote(&host.map_path(&dir)?), command),
+        None => command,
+    };
+    Ok(run_remote(&host, remote_command).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host() -> RemoteHost {
+        RemoteHost {
+            id: Some(1),
+            name: "gpu".to_string(),
+            host: "gpu.internal".to_string(),
+            user: Some("dev".to_string()),
+            port: Some(2222),
+            key_source: KeySource::Agent,
+            path_mappings: vec![
+                PathMapping {
+                    local: "/Users/me/src".to_string(),
+                    remote: "/home/dev/src".to_string(),
+                },
+                PathMapping {
+                    local: "/Users/me/src/big-model".to_string(),
+                    remote: "/data/big-model/".to_string(),
+                },
+            ],
+            claude_path: Some("/home/dev/.claude/local/claude".to_string()),
+            claude_version: None,
+            detected_at: None,
+        }
+    }
+
+    #[test]
+    fn test_paths_map_to_the_longest_matching_mapping() {
+        let host = host();
+        assert_eq!(
+            host.map_path("/Users/me/src/app").unwrap(),
+            "/home/dev/src/app"
+        );
+        assert_eq!(
+            host.map_path("/Users/me/src/big-model/train").unwrap(),
+            "/data/big-model/train"
+        );
+        assert_eq!(host.map_path("/Users/me/src").unwrap(), "/home/dev/src");
+        // Prefixes only match whole components
+        assert!(host.map_path("/Users/me/srcs/app").is_err());
+        assert!(validate_host(&RemoteHost {
+            host: "-oProxyCommand=evil".to_string(),
+            ..host.clone()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_wrapped_command_runs_claude_in_the_remote_directory() {
+        let (program, args) = host()
+            .wrap_command(
+                vec!["-p".to_string(), "fix the user's bug".to_string()],
+                "/Users/me/src/app",
+                None,
+            )
+            .unwrap();
+        assert_eq!(program, "ssh");
+        assert_eq!(
+            &args[..8],
+            [
+                "-T",
+                "-o",
+                "BatchMode=yes",
+                "-o",
+                "ServerAliveInterval=30",
+                "-p",
+                "2222",
+                "dev@gpu.internal"
+            ]
+        );
+        assert_eq!(args.len(), 9);
+        assert_eq!(
+            args[8],
+            format!(
+                "sh -c {}",
+                shell_quote(
+                    "while IFS= read -r line && [ -n \"$line\" ]; do export \"$line\"; done; echo opcode-remote-ready >&2; cd /home/dev/src/app && exec /home/dev/.claude/local/claude -p 'fix the user'\\''s bug'"
+                )
+            )
+        );
+
+        // Secrets go over stdin, not argv
+        let env = [("ANTHROPIC_API_KEY".to_string(), "sk-ant-secret".to_string())];
+        assert_eq!(
+            env_input(&env).unwrap(),
+            "ANTHROPIC_API_KEY=sk-ant-secret\n\n"
+        );
+        assert!(env_input(&[("BAD NAME".to_string(), "x".to_string())]).is_err());
+        assert!(env_input(&[("KEY".to_string(), "a\nb".to_string())]).is_err());
+
+        assert_eq!(
+            parse_detection("/usr/local/bin/claude\n1.0.80 (Claude Code)\n"),
+            Some((
+                "/usr/local/bin/claude".to_string(),
+                Some("1.0.80 (Claude Code)".to_string())
+            ))
+        );
+        assert_eq!(parse_detection("sh: claude: not found\n"), None);
+    }
+}
@@ -0,0 +1,487 @@
+#![allow(dead_code)]
+
+//! Conversation replay. The user prompts of a saved transcript are sent again, optionally to
+//! a different model, while every tool call is answered from the recorded run instead of
+//! being executed. The new transcript is then diffed against the original, which makes prompt
+//! and agent changes regression-testable without re-running expensive or side-effecting tools.
+//!
+//! Stubbing goes through a `PreToolUse` hook: the app binary run as `opcode replay-stub
+//! <stubs.json>` (or `opcode-cli replay-stub`, for the CLI) looks the call up among the
+//! recorded results and denies it with the recorded output as the reason,
+//! so the model sees the old result and nothing runs. Each answer is appended to a hit log
+//! next to the stubs, which both marks a stub as used and feeds the replay report.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use super::claude::is_valid_uuid;
+use super::session_diff::{diff_transcripts, read_transcript, SessionDiff};
+use super::session_merge::project_dir;
+
+/// First argument that starts the app binary as the replay stub hook instead of the GUI
+pub const REPLAY_STUB_COMMAND: &str = "replay-stub";
+
+/// Emitted with a [`ReplayProgress`] before each prompt is sent
+pub const REPLAY_PROGRESS_EVENT: &str = "replay-progress";
+
+/// Prefix of the text the model sees instead of running a tool
+const STUB_REASON_PREFIX: &str =
+    "Replayed result from the recorded run (the tool was not executed):";
+
+/// A recorded tool call and what it returned
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolStub {
+    pub name: String,
+    pub input: Value,
+    pub result: String,
+    pub is_error: bool,
+}
+
+/// What a replay sends and answers with, taken from a transcript
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayPlan {
+    pub prompts: Vec<String>,
+    pub stubs: Vec<ToolStub>,
+    /// Model of the recorded run
+    pub model: Option<String>,
+    /// Directory the recorded run worked in
+    pub cwd: Option<String>,
+}
+
+/// One line of the hit log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct StubHit {
+    tool_name: String,
+    /// Index of the stub that answered, `None` if nothing was recorded for the call
+    stub: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayProgress {
+    pub session_id: String,
+    /// Zero-based index of the prompt about to be sent
+    pub prompt: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub original_session_id: String,
+    pub replay_session_id: String,
+    pub model: Option<String>,
+    pub prompts_replayed: usize,
+    /// Tool calls answered from the recording
+    pub stubbed_tool_calls: usize,
+    /// Tool calls the recording had no result for; they were denied
+    pub unmatched_tool_calls: Vec<String>,
+    pub diff: SessionDiff,
+}
+
+/// Text of a `tool_result` block, whose content is a string or a list of text blocks
+fn tool_result_text(block: &Value) -> String {
+    match block.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Slash command bookkeeping the CLI records as user messages
+fn is_command_echo(text: &str) -> bool {
+    let text = text.trim_start();
+    text.starts_with("<command-") || text.starts_with("<local-command-")
+}
+
+/// Collect the prompts and tool results of a transcript
+pub fn plan_from_transcript(content: &str) -> ReplayPlan {
+    let mut plan = ReplayPlan::default();
+    // tool_use id -> index into plan.stubs
+    let mut pending: HashMap<String, usize> = HashMap::new();
+    for value in content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+    {
+        if value.get("isMeta").and_then(Value::as_bool) == Some(true)
+            || value.get("isSidechain").and_then(Value::as_bool) == Some(true)
+        {
+            continue;
+        }
+        if plan.cwd.is_none() {
+            plan.cwd = value.get("cwd").and_then(Value::as_str).map(str::to_string);
+        }
+        let Some(message) = value.get("message") else {
+            continue;
+        };
+        match value.get("type").and_then(Value::as_str) {
+            Some("user") => match message.get("content") {
+                Some(Value::String(text)) if !is_command_echo(text) => {
+                    plan.prompts.push(text.clone())
+                }
+                Some(Value::Array(blocks)) => {
+                    let mut text = Vec::new();
+                    for block in blocks {
+                        match block.get("type").and_then(Value::as_str) {
+                            Some("text") => text.extend(block.get("text").and_then(Value::as_str)),
+                            Some("tool_result") => {
+                                let id = block.get("tool_use_id").and_then(Value::as_str);
+                                if let Some(index) = id.and_then(|id| pending.remove(id)) {
+                                    plan.stubs[index].result = tool_result_text(block);
+                                    plan.stubs[index].is_error =
+                                        block.get("is_error").and_then(Value::as_bool)
+                                            == Some(true);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    let text = text.join("\n");
+                    if !text.trim().is_empty() && !is_command_echo(&text) {
+                        plan.prompts.push(text);
+                    }
+                }
+                _ => {}
+            },
+            Some("assistant") => {
+                if plan.model.is_none() {
+                    plan.model = message
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .filter(|model| !model.starts_with('<'))
+                        .map(str::to_string);
+                }
+                for block in message
+                    .get("content")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter(|block| block.get("type").and_then(Value::as_str) == Some("tool_use"))
+                {
+                    let name = block
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    if let Some(id) = block.get("id").and_then(Value::as_str) {
+                        pending.insert(id.to_string(), plan.stubs.len());
+                    }
+                    plan.stubs.push(ToolStub {
+                        name: name.to_string(),
+                        input: block.get("input").cloned().unwrap_or(Value::Null),
+                        result: String::new(),
+                        is_error: false,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    plan
+}
+
+/// Pick the recorded result for a call: an unused stub with the same input, then an unused
+/// stub of the same tool in recorded order, then a used stub with the same input (the replay
+/// repeated a call)
+fn find_stub(stubs: &[ToolStub], used: &[usize], name: &str, input: &Value) -> Option<usize> {
+    let unused = |index: &usize| !used.contains(index);
+    let indices = || (0..stubs.len()).filter(|&index| stubs[index].name == name);
+    indices()
+        .filter(unused)
+        .find(|&index| stubs[index].input == *input)
+        .or_else(|| indices().find(unused))
+        .or_else(|| indices().find(|&index| stubs[index].input == *input))
+}
+
+fn hit_log_path(stubs_path: &Path) -> PathBuf {
+    stubs_path.with_extension("hits.jsonl")
+}
+
+fn read_hits(stubs_path: &Path) -> Vec<StubHit> {
+    fs::read_to_string(hit_log_path(stubs_path))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Answer the hook call on stdin for `<stubs.json>`, the argument after `REPLAY_STUB_COMMAND`,
+/// printing the hook's output
+pub fn stub_hook_from_args(args: &[String]) -> Result<(), String> {
+    let [stubs_path] = args else {
+        return Err(format!("Usage: {} <stubs.json>", REPLAY_STUB_COMMAND));
+    };
+    let mut input = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut input)
+        .map_err(|e| e.to_string())?;
+    println!("{}", answer_stub_hook(Path::new(stubs_path), &input)?);
+    Ok(())
+}
+
+/// Answer a `PreToolUse` hook call (its JSON on stdin) from the stubs at `stubs_path`. Runs
+/// inside `opcode replay-stub` or `opcode-cli replay-stub`; returns the hook's JSON output.
+pub fn answer_stub_hook(stubs_path: &Path, hook_input: &str) -> Result<String, String> {
+    let stubs: Vec<ToolStub> = serde_json::from_str(
+        &fs::read_to_string(stubs_path).map_err(|e| format!("Failed to read stubs: {}", e))?,
+    )
+    .map_err(|e| format!("Failed to parse stubs: {}", e))?;
+    let input: Value = serde_json::from_str(hook_input)
+        .map_err(|e| format!("Failed to parse hook input: {}", e))?;
+    let tool_name = input
+        .get("tool_name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let tool_input = input.get("tool_input").cloned().unwrap_or(Value::Null);
+
+    let used: Vec<usize> = read_hits(stubs_path)
+        .iter()
+        .filter_map(|hit| hit.stub)
+        .collect();
+    let stub = find_stub(&stubs, &used, tool_name, &tool_input);
+    let hit = StubHit {
+        tool_name: tool_name.to_string(),
+        stub,
+    };
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(hit_log_path(stubs_path))
+        .map_err(|e| format!("Failed to open hit log: {}", e))?;
+    writeln!(
+        log,
+        "{}",
+        serde_json::to_string(&hit).map_err(|e| e.to_string())?
+    )
+    .map_err(|e| format!("Failed to write hit log: {}", e))?;
+
+    let reason = match stub.map(|index| &stubs[index]) {
+        Some(stub) if stub.is_error => format!("{} the tool failed with:\n{}", STUB_REASON_PREFIX, stub.result),
+        Some(stub) => format!("{}\n{}", STUB_REASON_PREFIX, stub.result),
+        None => "The recorded run has no result for this tool call, so it was not executed during the replay".to_string(),
+    };
+    Ok(json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "permissionDecision": "deny",
+            "permissionDecisionReason": reason,
+        }
+    })
+    .to_string())
+}
+
+/// Settings passed with `--settings` that route every tool call through the stub hook
+fn stub_settings(cli_path: &Path, stubs_path: &Path) -> Value {
+    let command = format!(
+        "\"{}\" {} \"{}\"",
+        cli_path.display(),
+        REPLAY_STUB_COMMAND,
+        stubs_path.display()
+    );
+    json!({
+        "hooks": {
+            "PreToolUse": [{
+                "matcher": "*",
+                "hooks": [{ "type": "command", "command": command }]
+            }]
+        }
+    })
+}
+
+/// `session_id` from the `system/init` line of stream-json output
+fn session_id_from_output(stdout: &str) -> Option<String> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|value| value.get("type").and_then(Value::as_str) == Some("system"))
+        .and_then(|value| {
+            value
+                .get("session_id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+}
+
+/// Replay a session's prompts with recorded tool results, optionally against another model,
+/// and diff the new transcript against the original
+#[tauri::command]
+pub async fn replay_session(
+    app: AppHandle,
+    project_id: String,
+    session_id: String,
+    model: Option<String>,
+) -> Result<ReplayResult, String> {
+    let (dir, _) = project_dir(&project_id)?;
+    if !is_valid_uuid(&session_id) {
+        return Err(format!("Invalid session id: {}", session_id));
+    }
+    let original_path = dir.join(format!("{}.jsonl", session_id));
+    let content = fs::read_to_string(&original_path)
+        .map_err(|e| format!("Failed to read session {}: {}", session_id, e))?;
+    let plan = plan_from_transcript(&content);
+    if plan.prompts.is_empty() {
+        return Err("The session has no prompts to replay".to_string());
+    }
+    let cwd = plan
+        .cwd
+        .clone()
+        .filter(|cwd| Path::new(cwd).is_dir())
+        .ok_or("The session's working directory no longer exists")?;
+    let model = model.or_else(|| plan.model.clone());
+
+    let claude_path = crate::claude_binary::find_claude_binary(&app)?;
+    let workdir = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let stubs_path = workdir.path().join("stubs.json");
+    fs::write(
+        &stubs_path,
+        serde_json::to_string(&plan.stubs).map_err(|e| e.to_string())?,
+    )
+    .map_err(|e| format!("Failed to write stubs: {}", e))?;
+    let settings =
+        stub_settings(&super::approval_mcp::app_binary_path()?, &stubs_path).to_string();
+
+    let mut replay_session_id: Option<String> = None;
+    for (index, prompt) in plan.prompts.iter().enumerate() {
+        let _ = app.emit(
+            REPLAY_PROGRESS_EVENT,
+            ReplayProgress {
+                session_id: session_id.clone(),
+                prompt: index,
+                total: plan.prompts.len(),
+            },
+        );
+        let mut args = vec![
+            "-p".to_string(),
+            prompt.clone(),
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--verbose".to_string(),
+            "--settings".to_string(),
+            settings.clone(),
+        ];
+        if let Some(model) = &model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        if let Some(id) = &replay_session_id {
+            args.extend(["--resume".to_string(), id.clone()]);
+        }
+        let output = super::agents::create_agent_system_command(&claude_path, args, &cwd)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run claude: {}", e))?;
+        let stdout = crate::claude_binary::decode_command_output(&output.stdout);
+        if !output.status.success() {
+            return Err(format!(
+                "Replay failed at prompt {}: {}",
+                index + 1,
+                crate::claude_binary::decode_command_output(&output.stderr).trim()
+            ));
+        }
+        if let Some(id) = session_id_from_output(&stdout) {
+            replay_session_id = Some(id);
+        }
+    }
+    let replay_session_id = replay_session_id.ok_or("The replay produced no session")?;
+
+    let hits = read_hits(&stubs_path);
+    let original = read_transcript(&original_path)?;
+    let replayed = read_transcript(&dir.join(format!("{}.jsonl", replay_session_id)))?;
+    log::info!(
+        "Replayed session {} as {} ({} prompts, {} tool calls stubbed)",
+        session_id,
+        replay_session_id,
+        plan.prompts.len(),
+        hits.iter().filter(|hit| hit.stub.is_some()).count()
+    );
+    Ok(ReplayResult {
+        diff: diff_transcripts(&session_id, &original, &replay_session_id, &replayed),
+        original_session_id: session_id,
+        replay_session_id,
+        model,
+        prompts_replayed: plan.prompts.len(),
+        stubbed_tool_calls: hits.iter().filter(|hit| hit.stub.is_some()).count(),
+        unmatched_tool_calls: hits
+            .into_iter()
+            .filter(|hit| hit.stub.is_none())
+            .map(|hit| hit.tool_name)
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript() -> String {
+        [
+            json!({"type": "user", "cwd": "/work", "message": {"role": "user", "content": "List the files"}}),
+            json!({"type": "assistant", "message": {"model": "claude-sonnet-4-5", "content": [
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}
+            ]}}),
+            json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "Cargo.toml\nsrc"}
+            ]}}),
+            json!({"type": "user", "isMeta": true, "message": {"content": "Caveat: ignore"}}),
+            json!({"type": "user", "message": {"content": [{"type": "text", "text": "Now read Cargo.toml"}]}}),
+            json!({"type": "assistant", "message": {"content": [
+                {"type": "tool_use", "id": "t2", "name": "Read", "input": {"file_path": "Cargo.toml"}}
+            ]}}),
+            json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t2", "is_error": true, "content": [{"type": "text", "text": "denied"}]}
+            ]}}),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
+    #[test]
+    fn test_plan_collects_prompts_and_tool_results() {
+        let plan = plan_from_transcript(&transcript());
+        assert_eq!(plan.prompts, vec!["List the files", "Now read Cargo.toml"]);
+        assert_eq!(plan.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(plan.cwd.as_deref(), Some("/work"));
+        assert_eq!(plan.stubs.len(), 2);
+        assert_eq!(plan.stubs[0].result, "Cargo.toml\nsrc");
+        assert!(plan.stubs[1].is_error);
+        assert_eq!(plan.stubs[1].result, "denied");
+    }
+
+    #[test]
+    fn test_hook_answers_from_recording_and_logs_hits() {
+        let dir = tempfile::tempdir().unwrap();
+        let stubs_path = dir.path().join("stubs.json");
+        let plan = plan_from_transcript(&transcript());
+        fs::write(&stubs_path, serde_json::to_string(&plan.stubs).unwrap()).unwrap();
+
+        // Different input, same tool: falls back to the recorded Bash call
+        let answer = answer_stub_hook(
+            &stubs_path,
+            &json!({"tool_name": "Bash", "tool_input": {"command": "ls -la"}}).to_string(),
+        )
+        .unwrap();
+        let answer: Value = serde_json::from_str(&answer).unwrap();
+        assert_eq!(answer["hookSpecificOutput"]["permissionDecision"], "deny");
+        assert!(answer["hookSpecificOutput"]["permissionDecisionReason"]
+            .as_str()
+            .unwrap()
+            .ends_with("Cargo.toml\nsrc"));
+
+        // The only Bash stub is used up and the input differs, so nothing matches
+        answer_stub_hook(
+            &stubs_path,
+            &json!({"tool_name": "Bash", "tool_input": {"command": "pwd"}}).to_string(),
+        )
+        .unwrap();
+        let hits = read_hits(&stubs_path);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].stub, Some(0));
+        assert_eq!(hits[1].stub, None);
+    }
+}
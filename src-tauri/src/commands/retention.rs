@@ -0,0 +1,635 @@
+#![allow(dead_code)]
+
+//! Retention for what opcode and Claude Code leave on disk. Each storage category (sessions,
+//! agent run logs, run artifacts and checkpoints) can keep items for N days, the newest N
+//! items, or up to N GB. A background pruner applies the policies when auto-pruning is on,
+//! and `prune_storage` produces the same report as a dry run.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, State};
+use walkdir::WalkDir;
+
+use super::agents::AgentDb;
+use super::settings::{get_setting_as, set_setting_as};
+
+/// app_settings key holding the `RetentionSettings`
+const RETENTION_SETTINGS_KEY: &str = "retention";
+
+/// app_settings key holding the report of the last prune that deleted anything
+const LAST_REPORT_KEY: &str = "retention_last_report";
+
+/// How often the background pruner runs
+const PRUNE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Delay before the first background prune, so it doesn't compete with startup
+const FIRST_PRUNE_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Items modified this recently may still be in use and are never pruned
+const ACTIVE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    /// Interactive Claude Code session transcripts
+    Sessions,
+    /// Transcripts of agent runs
+    RunLogs,
+    /// Files collected from agent runs
+    Artifacts,
+    /// Session checkpoint timelines
+    Checkpoints,
+}
+
+impl StorageCategory {
+    pub const ALL: [StorageCategory; 4] = [
+        StorageCategory::Sessions,
+        StorageCategory::RunLogs,
+        StorageCategory::Artifacts,
+        StorageCategory::Checkpoints,
+    ];
+}
+
+/// Limits for one category; an item is pruned once it breaks any of them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u32>,
+    pub max_items: Option<usize>,
+    pub max_gb: Option<f64>,
+}
+
+impl RetentionPolicy {
+    fn is_unlimited(&self) -> bool {
+        self.max_age_days.is_none() && self.max_items.is_none() && self.max_gb.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    /// Prune in the background every few hours
+    pub auto_prune: bool,
+    pub sessions: RetentionPolicy,
+    pub run_logs: RetentionPolicy,
+    pub artifacts: RetentionPolicy,
+    pub checkpoints: RetentionPolicy,
+}
+
+impl RetentionSettings {
+    fn policy(&self, category: StorageCategory) -> &RetentionPolicy {
+        match category {
+            StorageCategory::Sessions => &self.sessions,
+            StorageCategory::RunLogs => &self.run_logs,
+            StorageCategory::Artifacts => &self.artifacts,
+            StorageCategory::Checkpoints => &self.checkpoints,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub items: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub categories: Vec<CategoryUsage>,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryPruneReport {
+    pub category: StorageCategory,
+    pub items: usize,
+    pub bytes: u64,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub ran_at: String,
+    pub categories: Vec<CategoryPruneReport>,
+    pub bytes_freed: u64,
+    pub errors: Vec<String>,
+}
+
+/// Something on disk that retention can account for and delete
+#[derive(Debug, Clone)]
+struct StorageItem {
+    category: StorageCategory,
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+    /// Artifact directories are named after their run
+    run_id: Option<i64>,
+    /// In use, so counted but never pruned
+    protected: bool,
+}
+
+/// Indices of the items a policy prunes. Items are considered newest first; protected items
+/// are kept and count toward the item and size limits.
+pub fn select_prunable(
+    items: &[(SystemTime, u64, bool)],
+    policy: &RetentionPolicy,
+    now: SystemTime,
+) -> Vec<usize> {
+    if policy.is_unlimited() {
+        return Vec::new();
+    }
+    let max_age = policy
+        .max_age_days
+        .map(|days| Duration::from_secs(u64::from(days) * 24 * 60 * 60));
+    let max_bytes = policy.max_gb.map(|gb| (gb * BYTES_PER_GB) as u64);
+
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|a, b| items[*b].0.cmp(&items[*a].0));
+
+    let mut kept_items = 0usize;
+    let mut kept_bytes = 0u64;
+    let mut prunable = Vec::new();
+    for index in order {
+        let (modified, bytes, protected) = items[index];
+        let age = now.duration_since(modified).unwrap_or_default();
+        let expired = max_age.is_some_and(|max| age > max);
+        let over_count = policy.max_items.is_some_and(|max| kept_items >= max);
+        let over_size = max_bytes.is_some_and(|max| kept_bytes + bytes > max);
+        if !protected && (expired || over_count || over_size) {
+            prunable.push(index);
+        } else {
+            kept_items += 1;
+            kept_bytes += bytes;
+        }
+    }
+    prunable.sort_unstable();
+    prunable
+}
+
+pub fn load_retention_settings(conn: &Connection) -> RetentionSettings {
+    get_setting_as(conn, RETENTION_SETTINGS_KEY).unwrap_or_default()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Latest modification time of anything under `path`
+fn dir_modified(path: &Path) -> SystemTime {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Agent run transcripts by session id, and the session ids of runs still going
+fn load_run_sessions(conn: &Connection) -> Result<(HashMap<String, i64>, HashSet<String>), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, session_id, COALESCE(status, '') FROM agent_runs WHERE session_id != ''",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut sessions = HashMap::new();
+    let mut active = HashSet::new();
+    for (run_id, session_id, status) in rows {
+        if status == "pending" || status == "running" {
+            active.insert(session_id.clone());
+        }
+        sessions.insert(session_id, run_id);
+    }
+    Ok((sessions, active))
+}
+
+/// Walk `~/.claude/projects` and the artifacts directory
+fn collect_items(
+    claude_dir: Option<&Path>,
+    artifacts_root: &Path,
+    run_sessions: &HashMap<String, i64>,
+    active_sessions: &HashSet<String>,
+    active_runs: &HashSet<i64>,
+    now: SystemTime,
+) -> Vec<StorageItem> {
+    let recent =
+        |modified: SystemTime| now.duration_since(modified).unwrap_or_default() < ACTIVE_WINDOW;
+    let mut items = Vec::new();
+
+    let projects = claude_dir.map(|dir| dir.join("projects"));
+    let project_dirs = projects
+        .as_deref()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir());
+
+    for project_dir in project_dirs {
+        for entry in std::fs::read_dir(&project_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let session_id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let category = if run_sessions.contains_key(&session_id) {
+                StorageCategory::RunLogs
+            } else {
+                StorageCategory::Sessions
+            };
+            items.push(StorageItem {
+                category,
+                path,
+                bytes: metadata.len(),
+                modified,
+                run_id: run_sessions.get(&session_id).copied(),
+                protected: active_sessions.contains(&session_id) || recent(modified),
+            });
+        }
+
+        let timelines = project_dir.join(".timelines");
+        for entry in std::fs::read_dir(&timelines)
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let session_id = entry.file_name().to_string_lossy().to_string();
+            let modified = dir_modified(&path);
+            items.push(StorageItem {
+                category: StorageCategory::Checkpoints,
+                bytes: dir_size(&path),
+                path,
+                modified,
+                run_id: None,
+                protected: active_sessions.contains(&session_id) || recent(modified),
+            });
+        }
+    }
+
+    for entry in std::fs::read_dir(artifacts_root)
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let run_id = entry.file_name().to_string_lossy().parse::<i64>().ok();
+        let modified = dir_modified(&path);
+        items.push(StorageItem {
+            category: StorageCategory::Artifacts,
+            bytes: dir_size(&path),
+            path,
+            modified,
+            protected: run_id.is_some_and(|id| active_runs.contains(&id)) || recent(modified),
+            run_id,
+        });
+    }
+
+    items
+}
+
+/// Everything retention looks at, with the database lock released before walking the disk
+fn scan_storage(app: &AppHandle) -> Result<(Vec<StorageItem>, RetentionSettings), String> {
+    let (run_sessions, active_sessions, settings) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let (run_sessions, active_sessions) = load_run_sessions(&conn)?;
+        (
+            run_sessions,
+            active_sessions,
+            load_retention_settings(&conn),
+        )
+    };
+    let active_runs: HashSet<i64> = active_sessions
+        .iter()
+        .filter_map(|session_id| run_sessions.get(session_id).copied())
+        .collect();
+
+    let claude_dir = super::claude::get_claude_dir().ok();
+    let artifacts_root = app
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join("artifacts"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let items = collect_items(
+        claude_dir.as_deref(),
+        &artifacts_root,
+        &run_sessions,
+        &active_sessions,
+        &active_runs,
+        SystemTime::now(),
+    );
+    Ok((items, settings))
+}
+
+fn remove_item(item: &StorageItem) -> std::io::Result<()> {
+    if item.path.is_dir() {
+        std::fs::remove_dir_all(&item.path)
+    } else {
+        std::fs::remove_file(&item.path)
+    }
+}
+
+/// Apply the retention policies, or report what they would delete
+fn prune(app: &AppHandle, dry_run: bool) -> Result<RetentionReport, String> {
+    let (items, settings) = scan_storage(app)?;
+    let now = SystemTime::now();
+    let mut report = RetentionReport {
+        dry_run,
+        ran_at: chrono::Utc::now().to_rfc3339(),
+        categories: Vec::new(),
+        bytes_freed: 0,
+        errors: Vec::new(),
+    };
+    let mut pruned_artifact_runs = Vec::new();
+
+    for category in StorageCategory::ALL {
+        let in_category: Vec<&StorageItem> = items
+            .iter()
+            .filter(|item| item.category == category)
+            .collect();
+        let facts: Vec<(SystemTime, u64, bool)> = in_category
+            .iter()
+            .map(|item| (item.modified, item.bytes, item.protected))
+            .collect();
+
+        let mut category_report = CategoryPruneReport {
+            category,
+            items: 0,
+            bytes: 0,
+            paths: Vec::new(),
+        };
+        for index in select_prunable(&facts, settings.policy(category), now) {
+            let item = in_category[index];
+            if !dry_run {
+                if let Err(e) = remove_item(item) {
+                    report
+                        .errors
+                        .push(format!("Failed to remove {}: {}", item.path.display(), e));
+                    continue;
+                }
+                if let (StorageCategory::Artifacts, Some(run_id)) = (category, item.run_id) {
+                    pruned_artifact_runs.push(run_id);
+                }
+            }
+            category_report.items += 1;
+            category_report.bytes += item.bytes;
+            category_report
+                .paths
+                .push(item.path.to_string_lossy().to_string());
+        }
+        report.bytes_freed += category_report.bytes;
+        report.categories.push(category_report);
+    }
+
+    if !dry_run {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        for run_id in pruned_artifact_runs {
+            if let Err(e) = conn.execute(
+                "DELETE FROM run_artifacts WHERE run_id = ?1",
+                rusqlite::params![run_id],
+            ) {
+                report.errors.push(format!(
+                    "Failed to forget artifacts of run {}: {}",
+                    run_id, e
+                ));
+            }
+        }
+        if report.bytes_freed > 0 || !report.errors.is_empty() {
+            set_setting_as(&conn, LAST_REPORT_KEY, &report)?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Start the background task that prunes storage when auto-pruning is on
+pub fn spawn_retention_pruner(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_PRUNE_DELAY).await;
+        loop {
+            let auto_prune = {
+                let db = app.state::<AgentDb>();
+                let auto_prune =
+                    db.0.lock()
+                        .map(|conn| load_retention_settings(&conn).auto_prune)
+                        .unwrap_or(false);
+                auto_prune
+            };
+            if auto_prune {
+                let handle = app.clone();
+                match tauri::async_runtime::spawn_blocking(move || prune(&handle, false)).await {
+                    Ok(Ok(report)) => {
+                        if report.bytes_freed > 0 {
+                            log::info!("🧹 Retention freed {} bytes", report.bytes_freed);
+                            let _ = app.emit("storage-pruned", &report);
+                        }
+                        for error in &report.errors {
+                            log::warn!("Retention: {}", error);
+                        }
+                    }
+                    Ok(Err(e)) => log::warn!("Retention prune failed: {}", e),
+                    Err(e) => log::warn!("Retention prune task failed: {}", e),
+                }
+            }
+            tokio::time::sleep(PRUNE_INTERVAL).await;
+        }
+    });
+}
+
+/// Disk used by sessions, run logs, artifacts and checkpoints
+#[tauri::command]
+pub async fn get_storage_usage(app: AppHandle) -> Result<StorageUsage, String> {
+    let (items, _) = tauri::async_runtime::spawn_blocking(move || scan_storage(&app))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let categories: Vec<CategoryUsage> = StorageCategory::ALL
+        .iter()
+        .map(|category| {
+            let in_category = items.iter().filter(|item| item.category == *category);
+            CategoryUsage {
+                category: *category,
+                items: in_category.clone().count(),
+                bytes: in_category.map(|item| item.bytes).sum(),
+            }
+        })
+        .collect();
+    let total_bytes = categories.iter().map(|usage| usage.bytes).sum();
+    Ok(StorageUsage {
+        categories,
+        total_bytes,
+    })
+}
+
+#[tauri::command]
+pub async fn get_retention_settings(db: State<'_, AgentDb>) -> Result<RetentionSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_retention_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_retention_settings(
+    db: State<'_, AgentDb>,
+    settings: RetentionSettings,
+) -> Result<RetentionSettings, String> {
+    for category in StorageCategory::ALL {
+        let policy = settings.policy(category);
+        if policy.max_age_days == Some(0) || policy.max_items == Some(0) {
+            return Err(format!("{:?} limits must be at least 1", category));
+        }
+        if policy.max_gb.is_some_and(|gb| !gb.is_finite() || gb <= 0.0) {
+            return Err(format!(
+                "{:?} size limit must be a positive number of GB",
+                category
+            ));
+        }
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, RETENTION_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+/// Apply the retention policies now; with `dry_run` only report what would be deleted
+#[tauri::command]
+pub async fn prune_storage(app: AppHandle, dry_run: bool) -> Result<RetentionReport, String> {
+    tauri::async_runtime::spawn_blocking(move || prune(&app, dry_run))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// The last prune that deleted anything
+#[tauri::command]
+pub async fn get_last_retention_report(
+    db: State<'_, AgentDb>,
+) -> Result<Option<RetentionReport>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(get_setting_as(&conn, LAST_REPORT_KEY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn test_select_prunable_applies_every_limit() {
+        let now = SystemTime::now();
+        let gb = BYTES_PER_GB as u64;
+        // Index order deliberately differs from age order
+        let items = [
+            (now - DAY * 10, gb, false),
+            (now - DAY, gb, false),
+            (now - DAY * 3, gb, false),
+            (now - DAY * 40, gb, false),
+        ];
+
+        let by_age = RetentionPolicy {
+            max_age_days: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(select_prunable(&items, &by_age, now), vec![3]);
+
+        let by_count = RetentionPolicy {
+            max_items: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(select_prunable(&items, &by_count, now), vec![0, 3]);
+
+        let by_size = RetentionPolicy {
+            max_gb: Some(1.5),
+            ..Default::default()
+        };
+        assert_eq!(select_prunable(&items, &by_size, now), vec![0, 2, 3]);
+
+        assert!(select_prunable(&items, &RetentionPolicy::default(), now).is_empty());
+    }
+
+    #[test]
+    fn test_collect_items_categorizes_and_protects() {
+        let root = tempfile::tempdir().unwrap();
+        let claude_dir = root.path().join(".claude");
+        let project = claude_dir.join("projects").join("-work-app");
+        std::fs::create_dir_all(project.join(".timelines").join("s1").join("checkpoints")).unwrap();
+        std::fs::write(project.join("s1.jsonl"), "{}\n").unwrap();
+        std::fs::write(project.join("run-session.jsonl"), "{}\n{}\n").unwrap();
+        std::fs::write(
+            project.join(".timelines").join("s1").join("timeline.json"),
+            "{}",
+        )
+        .unwrap();
+        let artifacts = root.path().join("artifacts");
+        std::fs::create_dir_all(artifacts.join("7")).unwrap();
+        std::fs::write(artifacts.join("7").join("report.md"), "done").unwrap();
+
+        let run_sessions = HashMap::from([("run-session".to_string(), 7)]);
+        let active_sessions = HashSet::from(["run-session".to_string()]);
+        let active_runs = HashSet::from([7]);
+        let later = SystemTime::now() + DAY;
+        let items = collect_items(
+            Some(&claude_dir),
+            &artifacts,
+            &run_sessions,
+            &active_sessions,
+            &active_runs,
+            later,
+        );
+
+        let find = |category| items.iter().find(|item| item.category == category).unwrap();
+        assert_eq!(items.len(), 4);
+        assert!(!find(StorageCategory::Sessions).protected);
+        assert!(find(StorageCategory::RunLogs).protected);
+        assert_eq!(find(StorageCategory::RunLogs).run_id, Some(7));
+        assert_eq!(find(StorageCategory::Checkpoints).bytes, 2);
+        let artifact = find(StorageCategory::Artifacts);
+        assert_eq!(
+            (artifact.run_id, artifact.bytes, artifact.protected),
+            (Some(7), 4, true)
+        );
+    }
+}
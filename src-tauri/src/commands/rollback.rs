@@ -0,0 +1,543 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::checkpoint::state::CheckpointState;
+use crate::checkpoint::FileSnapshot;
+use crate::commands::agents::AgentDb;
+use crate::commands::file_changes::FileChangeTracker;
+
+/// What `abort_and_rollback` reverted
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RollbackReport {
+    pub run_id: i64,
+    pub checkpoint_id: String,
+    /// Files whose pre-run content was written back (modified or deleted by the run)
+    pub restored_files: Vec<String>,
+    /// Files the run created, removed by the rollback
+    pub removed_files: Vec<String>,
+    /// Files changed since the run touched them, left as they are
+    pub conflicts: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// A file from a run's change manifest, with the state the run left it in
+#[derive(Debug, Clone, PartialEq)]
+struct ManifestEntry {
+    path: PathBuf,
+    /// Hash of the file as the run left it, `None` when the run left it missing
+    final_hash: Option<String>,
+}
+
+/// Create the run checkpoint and change manifest tables
+pub fn init_rollback_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_checkpoints (
+            run_id INTEGER PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            checkpoint_id TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_rollback_files (
+            run_id INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            final_hash TEXT,
+            PRIMARY KEY (run_id, path),
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Checkpoint session used for an agent run's pre-run snapshot
+fn run_checkpoint_session_id(run_id: i64) -> String {
+    format!("agent-run-{}", run_id)
+}
+
+/// Snapshot the project before an agent run starts so it can be rolled back later
+pub(crate) async fn create_pre_run_checkpoint(
+    app: &AppHandle,
+    run_id: i64,
+    project_path: &str,
+) -> Result<String, String> {
+    let session_id = run_checkpoint_session_id(run_id);
+    let (project_id, checkpoint_id) = snapshot_project(
+        app,
+        &session_id,
+        project_path,
+        format!("Before agent run {}", run_id),
+    )
+    .await?;
+
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO agent_run_checkpoints (run_id, project_id, session_id, checkpoint_id) VALUES (?1, ?2, ?3, ?4)",
+            params![run_id, project_id, session_id, checkpoint_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    log::info!(
+        "📸 Created pre-run checkpoint {} for run {}",
+        checkpoint_id,
+        run_id
+    );
+    Ok(checkpoint_id)
+}
+
+/// File change trackers of runs still in progress, by run id
+fn live_trackers() -> &'static Mutex<HashMap<i64, Arc<Mutex<FileChangeTracker>>>> {
+    static TRACKERS: OnceLock<Mutex<HashMap<i64, Arc<Mutex<FileChangeTracker>>>>> = OnceLock::new();
+    TRACKERS.get_or_init(Default::default)
+}
+
+/// Make a running agent's file change tracker available to `abort_and_rollback`
+pub(crate) fn track_run(run_id: i64, tracker: Arc<Mutex<FileChangeTracker>>) {
+    if let Ok(mut trackers) = live_trackers().lock() {
+        trackers.insert(run_id, tracker);
+    }
+}
+
+/// Persist the change manifest of a finished run and forget its tracker
+pub(crate) fn finish_run(conn: &Connection, run_id: i64) -> SqliteResult<()> {
+    let tracker = live_trackers()
+        .lock()
+        .ok()
+        .and_then(|mut trackers| trackers.remove(&run_id));
+    let Some(entries) = tracker
+        .as_ref()
+        .and_then(|tracker| tracker.lock().ok().map(|t| entries_from_tracker(&t)))
+    else {
+        return Ok(());
+    };
+
+    conn.execute(
+        "DELETE FROM agent_run_rollback_files WHERE run_id = ?1",
+        params![run_id],
+    )?;
+    for entry in entries {
+        conn.execute(
+            "INSERT INTO agent_run_rollback_files (run_id, path, final_hash) VALUES (?1, ?2, ?3)",
+            params![run_id, entry.path.to_string_lossy(), entry.final_hash],
+        )?;
+    }
+    Ok(())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn current_hash(path: &Path) -> Option<String> {
+    std::fs::read(path).ok().map(|data| sha256_hex(&data))
+}
+
+/// Build the manifest from a tracker, taking the files' current state as the run's final state
+fn entries_from_tracker(tracker: &FileChangeTracker) -> Vec<ManifestEntry> {
+    tracker
+        .originals()
+        .map(|(path, _)| ManifestEntry {
+            path: path.to_path_buf(),
+            final_hash: current_hash(path),
+        })
+        .collect()
+}
+
+fn load_entries(conn: &Connection, run_id: i64) -> SqliteResult<Vec<ManifestEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, final_hash FROM agent_run_rollback_files WHERE run_id = ?1 ORDER BY path ASC",
+    )?;
+    let entries: SqliteResult<Vec<_>> = stmt
+        .query_map(params![run_id], |row| {
+            Ok(ManifestEntry {
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                final_hash: row.get(1)?,
+            })
+        })?
+        .collect();
+    entries
+}
+
+/// Collect non-hidden project files, relative to the project root. Hidden directories are
+/// skipped the same way the checkpoint manager skips them.
+fn collect_project_files(project_path: &Path) -> BTreeSet<PathBuf> {
+    walkdir::WalkDir::new(project_path)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !entry
+                    .file_name()
+                    .to_str()
+                    .map(|name| name.starts_with('.'))
+                    .unwrap_or(false)
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .strip_prefix(project_path)
+                .ok()
+                .map(|p| p.to_path_buf())
+        })
+        .collect()
+}
+
+/// Whether a file differs from the state the run left it in. Only files in the run's
+/// manifest can be checked; anything else is taken as the run's doing.
+fn changed_since_run(path: &Path, final_hashes: &HashMap<PathBuf, Option<String>>) -> bool {
+    final_hashes
+        .get(path)
+        .map(|final_hash| current_hash(path) != *final_hash)
+        .unwrap_or(false)
+}
+
+/// Put the project back to its pre-run snapshot. Files that were changed again after the
+/// run touched them are reported as conflicts and left alone.
+fn roll_back_to_snapshot(
+    report: &mut RollbackReport,
+    project_root: &Path,
+    snapshots: &[FileSnapshot],
+    final_hashes: &HashMap<PathBuf, Option<String>>,
+) {
+    let mut snapshot_paths = BTreeSet::new();
+    for snapshot in snapshots.iter().filter(|snapshot| !snapshot.is_deleted) {
+        snapshot_paths.insert(snapshot.file_path.clone());
+        let full_path = project_root.join(&snapshot.file_path);
+        let display = snapshot.file_path.to_string_lossy().to_string();
+
+        // Binary files are checkpointed without content
+        if snapshot.content.is_empty() && snapshot.size > 0 {
+            let unchanged = std::fs::metadata(&full_path)
+                .map(|metadata| metadata.len() == snapshot.size)
+                .unwrap_or(false);
+            if !unchanged {
+                report
+                    .warnings
+                    .push(format!("{} is binary and can't be restored", display));
+            }
+            continue;
+        }
+
+        let current = std::fs::read_to_string(&full_path).ok();
+        if current.as_deref() == Some(snapshot.content.as_str()) {
+            continue;
+        }
+        if changed_since_run(&full_path, final_hashes) {
+            report.conflicts.push(display);
+            continue;
+        }
+
+        if let Some(parent) = full_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = crate::atomic_file::write_atomic(&full_path, &snapshot.content) {
+            report
+                .warnings
+                .push(format!("Failed to restore {}: {}", display, e));
+            continue;
+        }
+        #[cfg(unix)]
+        if let Some(mode) = snapshot.permissions {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&full_path, std::fs::Permissions::from_mode(mode));
+        }
+        report.restored_files.push(display);
+    }
+
+    // Whatever is in the project now but wasn't before the run was created by it
+    for path in collect_project_files(project_root) {
+        if snapshot_paths.contains(&path) {
+            continue;
+        }
+        let full_path = project_root.join(&path);
+        let display = path.to_string_lossy().to_string();
+        if changed_since_run(&full_path, final_hashes) {
+            report.conflicts.push(display);
+            continue;
+        }
+        match std::fs::remove_file(&full_path) {
+            Ok(()) => report.removed_files.push(display),
+            Err(e) => report
+                .warnings
+                .push(format!("Failed to remove {}: {}", display, e)),
+        }
+    }
+}
+
+/// Project id, session id and checkpoint id of a run's pre-run checkpoint
+fn run_checkpoint(
+    conn: &Connection,
+    run_id: i64,
+) -> Result<Option<(String, String, String)>, String> {
+    conn.query_row(
+        "SELECT project_id, session_id, checkpoint_id FROM agent_run_checkpoints WHERE run_id = ?1",
+        params![run_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn load_snapshots(
+    project_id: &str,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Result<Vec<FileSnapshot>, String> {
+    let claude_dir = super::claude::get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+    storage
+        .load_checkpoint(project_id, session_id, checkpoint_id)
+        .map(|(_, files, _)| files)
+        .map_err(|e| e.to_string())
+}
+
+/// Project id for the checkpoint storage, matching Claude's project directory naming
+fn project_id_for_path(project_path: &str) -> String {
+    project_path.replace(['/', '\\', ':'], "-")
+}
+
+/// Checkpoint the project files under a checkpoint session of its own.
+/// Returns the project id and checkpoint id needed to restore it.
+pub(crate) async fn snapshot_project(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+    description: String,
+) -> Result<(String, String), String> {
+    let checkpoint_state = app.state::<CheckpointState>();
+    let project_id = project_id_for_path(project_path);
+
+    let manager = checkpoint_state
+        .get_or_create_manager(
+            session_id.to_string(),
+            project_id.clone(),
+            PathBuf::from(project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to create checkpoint manager: {}", e))?;
+
+    let result = manager
+        .create_checkpoint(Some(description), None)
+        .await
+        .map_err(|e| e.to_string());
+
+    // The manager is recreated from disk on restore; don't keep it in memory meanwhile
+    checkpoint_state.remove_manager(session_id).await;
+    let result = result?;
+
+    log::debug!(
+        "Checkpointed {} files of {} as {}",
+        result.files_processed,
+        project_path,
+        result.checkpoint.id
+    );
+
+    Ok((project_id, result.checkpoint.id))
+}
+
+/// Put the project files back to a checkpoint taken with `snapshot_project`.
+/// Returns the restore warnings.
+pub(crate) async fn restore_project_snapshot(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+    checkpoint_id: &str,
+) -> Result<Vec<String>, String> {
+    let checkpoint_state = app.state::<CheckpointState>();
+    let manager = checkpoint_state
+        .get_or_create_manager(
+            session_id.to_string(),
+            project_id_for_path(project_path),
+            PathBuf::from(project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to load checkpoint manager: {}", e))?;
+
+    let result = manager
+        .restore_checkpoint(checkpoint_id)
+        .await
+        .map_err(|e| format!("Failed to restore checkpoint: {}", e));
+    checkpoint_state.remove_manager(session_id).await;
+
+    Ok(result?.warnings)
+}
+
+/// Kill an agent run (with its child processes) and put the project back to its pre-run
+/// checkpoint. The run's change manifest is used to spot files edited after the run.
+#[tauri::command]
+pub async fn abort_and_rollback(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+) -> Result<RollbackReport, String> {
+    log::info!("Aborting and rolling back agent run {}", run_id);
+
+    let (project_path, checkpoint) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let project_path: String = conn
+            .query_row(
+                "SELECT project_path FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Agent run {} not found: {}", run_id, e))?;
+
+        // Mark as cancelled before killing so the monitor doesn't record a failure or retry
+        conn.execute(
+            "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('pending', 'running')",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        (project_path, run_checkpoint(&conn, run_id)?)
+    };
+
+    // Stop the run and everything it spawned before touching files
+    match registry.0.kill_process_tree(run_id).await {
+        Ok(true) => log::info!("Killed process tree for run {}", run_id),
+        Ok(false) => log::info!("Run {} was not running", run_id),
+        Err(e) => log::warn!("Failed to kill process tree for run {}: {}", run_id, e),
+    }
+    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+
+    let (project_id, session_id, checkpoint_id) = checkpoint
+        .ok_or_else(|| format!("Run {} has no pre-run checkpoint to roll back to", run_id))?;
+    let snapshots = load_snapshots(&project_id, &session_id, &checkpoint_id)
+        .map_err(|e| format!("Failed to load pre-run checkpoint: {}", e))?;
+
+    // A run still being monitored hasn't persisted its manifest yet
+    let live = live_trackers()
+        .lock()
+        .ok()
+        .and_then(|trackers| trackers.get(&run_id).cloned());
+    let entries = match live {
+        Some(tracker) => tracker
+            .lock()
+            .map(|tracker| entries_from_tracker(&tracker))
+            .map_err(|e| e.to_string())?,
+        None => {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            load_entries(&conn, run_id).map_err(|e| e.to_string())?
+        }
+    };
+    let final_hashes: HashMap<PathBuf, Option<String>> = entries
+        .into_iter()
+        .map(|entry| (entry.path, entry.final_hash))
+        .collect();
+
+    let mut report = RollbackReport {
+        run_id,
+        checkpoint_id,
+        ..Default::default()
+    };
+    roll_back_to_snapshot(
+        &mut report,
+        Path::new(&project_path),
+        &snapshots,
+        &final_hashes,
+    );
+
+    log::info!(
+        "Rolled back run {}: {} restored, {} removed, {} conflict(s), {} warning(s)",
+        run_id,
+        report.restored_files.len(),
+        report.removed_files.len(),
+        report.conflicts.len(),
+        report.warnings.len()
+    );
+
+    let _ = app.emit(&format!("agent-rolled-back:{}", run_id), &report);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(path: &str, content: &str) -> FileSnapshot {
+        FileSnapshot {
+            checkpoint_id: "pre-run".to_string(),
+            file_path: PathBuf::from(path),
+            content: content.to_string(),
+            hash: String::new(),
+            is_deleted: false,
+            permissions: None,
+            size: content.len() as u64,
+        }
+    }
+
+    #[test]
+    fn rollback_restores_snapshot_and_reports_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let snapshots = vec![
+            snapshot("edited.rs", "before"),
+            snapshot("deleted.rs", "gone"),
+            snapshot("conflict.rs", "before"),
+            snapshot("untouched.rs", "same"),
+        ];
+
+        // State the run left behind: an edit the tracker saw, a delete through Bash
+        // (not in the manifest), and a new file
+        std::fs::write(root.join("edited.rs"), "after").unwrap();
+        std::fs::write(root.join("conflict.rs"), "after").unwrap();
+        std::fs::write(root.join("untouched.rs"), "same").unwrap();
+        std::fs::write(root.join("created.rs"), "new").unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git/index"), "git internals").unwrap();
+        let final_hashes: HashMap<PathBuf, Option<String>> = ["edited.rs", "conflict.rs"]
+            .iter()
+            .map(|path| (root.join(path), current_hash(&root.join(path))))
+            .collect();
+
+        // The user kept working on this file after the run
+        std::fs::write(root.join("conflict.rs"), "after, then edited").unwrap();
+
+        let mut report = RollbackReport::default();
+        roll_back_to_snapshot(&mut report, root, &snapshots, &final_hashes);
+
+        assert_eq!(report.restored_files, vec!["edited.rs", "deleted.rs"]);
+        assert_eq!(report.removed_files, vec!["created.rs"]);
+        assert_eq!(report.conflicts, vec!["conflict.rs"]);
+        assert_eq!(
+            std::fs::read_to_string(root.join("edited.rs")).unwrap(),
+            "before"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("deleted.rs")).unwrap(),
+            "gone"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("conflict.rs")).unwrap(),
+            "after, then edited"
+        );
+        assert!(!root.join("created.rs").exists());
+        assert!(root.join(".git/index").exists());
+    }
+
+    #[test]
+    fn project_id_matches_claude_naming() {
+        assert_eq!(project_id_for_path("/home/me/app"), "-home-me-app");
+    }
+}
@@ -0,0 +1,585 @@
+#![allow(dead_code)]
+
+//! Reproducible bundles of agent runs, for sharing repro cases of agent misbehavior. A bundle
+//! holds the agent definition, task, model, a redacted view of the environment, the files the
+//! run changed (with their content before and after, when the pre-run checkpoint has it) and
+//! the transcript, as zstd-compressed JSON. Everything text-like goes through the redaction
+//! settings before it is written.
+//!
+//! Importing recreates the agent, can put the changed files back to their pre-run content in
+//! a project of the importer's choosing, and can start the same task there again.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, State};
+
+use super::agents::{
+    agent_from_row, agent_run_from_row, read_session_jsonl, Agent, AgentDb, AGENT_COLUMNS,
+    AGENT_RUN_COLUMNS,
+};
+use super::file_changes::{FileChange, FileChangeType};
+use super::redaction::{RedactionConfig, RedactionReport, Redactor, REDACTION_CONFIG_KEY};
+use super::settings::get_setting_as;
+use super::thinking::RunThinking;
+use super::tool_rules::ToolRules;
+use super::verbosity::RunVerbosity;
+
+/// Bumped when the bundle layout changes incompatibly
+pub const RUN_BUNDLE_VERSION: u32 = 1;
+
+/// Environment variables that shape how the CLI behaves; values are redacted
+const ENV_PREFIXES: &[&str] = &["ANTHROPIC_", "CLAUDE_", "MCP_", "DISABLE_", "OTEL_"];
+const ENV_NAMES: &[&str] = &[
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "NODE_OPTIONS",
+    "SHELL",
+];
+
+/// Variable names whose values are dropped entirely rather than pattern-redacted
+const SECRET_NAME_PARTS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "AUTH"];
+
+/// The agent as it was when the run was exported
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundledAgent {
+    pub name: String,
+    pub icon: String,
+    pub system_prompt: String,
+    pub default_task: Option<String>,
+    pub model: String,
+    pub enable_file_read: bool,
+    pub enable_file_write: bool,
+    pub enable_network: bool,
+    pub hooks: Option<String>,
+    #[serde(default)]
+    pub thinking: Option<RunThinking>,
+    #[serde(default)]
+    pub tool_rules: Option<ToolRules>,
+}
+
+impl From<&Agent> for BundledAgent {
+    fn from(agent: &Agent) -> Self {
+        Self {
+            name: agent.name.clone(),
+            icon: agent.icon.clone(),
+            system_prompt: agent.system_prompt.clone(),
+            default_task: agent.default_task.clone(),
+            model: agent.model.clone(),
+            enable_file_read: agent.enable_file_read,
+            enable_file_write: agent.enable_file_write,
+            enable_network: agent.enable_network,
+            hooks: agent.hooks.clone(),
+            thinking: agent.thinking.clone(),
+            tool_rules: agent.tool_rules.clone(),
+        }
+    }
+}
+
+/// A file the run changed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundledFileChange {
+    #[serde(flatten)]
+    pub change: FileChange,
+    /// Content before the run, from the pre-run checkpoint
+    pub before: Option<String>,
+    /// Content at export time
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunBundle {
+    pub version: u32,
+    pub exported_at: String,
+    pub app_version: String,
+    pub agent: BundledAgent,
+    pub task: String,
+    pub model: String,
+    pub served_model: Option<String>,
+    pub verbosity: Option<RunVerbosity>,
+    pub status: String,
+    /// Name of the project directory; the full path stays on the exporting machine
+    pub project_name: String,
+    pub environment: BTreeMap<String, String>,
+    pub file_changes: Vec<BundledFileChange>,
+    /// Transcript lines of the run's session
+    pub transcript: Vec<Value>,
+    /// What the redaction replaced across the whole bundle
+    pub redactions: RedactionReport,
+}
+
+/// What `import_run_bundle` did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedRunBundle {
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub task: String,
+    pub model: String,
+    /// Files written back to (or, for files the run created, removed from) their pre-run state
+    pub restored_files: Vec<String>,
+    /// The re-executed run, when execution was requested
+    pub run_id: Option<i64>,
+}
+
+/// OS, architecture and the CLI-related environment, with values redacted
+fn collect_environment(
+    vars: impl Iterator<Item = (String, String)>,
+    redactor: &Redactor,
+    report: &mut RedactionReport,
+) -> BTreeMap<String, String> {
+    let mut environment = BTreeMap::new();
+    environment.insert("os".to_string(), std::env::consts::OS.to_string());
+    environment.insert("arch".to_string(), std::env::consts::ARCH.to_string());
+    for (name, value) in vars {
+        let upper = name.to_ascii_uppercase();
+        let relevant = ENV_NAMES.contains(&upper.as_str())
+            || ENV_PREFIXES.iter().any(|prefix| upper.starts_with(prefix));
+        if !relevant {
+            continue;
+        }
+        let value = if SECRET_NAME_PARTS.iter().any(|part| upper.contains(part)) {
+            "[REDACTED]".to_string()
+        } else {
+            redactor.redact(&value, report)
+        };
+        environment.insert(format!("env.{}", name), value);
+    }
+    environment
+}
+
+/// Relative path inside a project, refusing anything that could escape it
+fn safe_relative_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| path.to_path_buf())
+}
+
+/// Pre-run content of the files in the run's checkpoint, keyed by relative path
+fn pre_run_contents(
+    conn: &rusqlite::Connection,
+    run_id: i64,
+) -> Result<BTreeMap<String, String>, String> {
+    let checkpoint: Option<(String, String, String)> = conn
+        .query_row(
+            "SELECT project_id, session_id, checkpoint_id FROM agent_run_checkpoints WHERE run_id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((project_id, session_id, checkpoint_id)) = checkpoint else {
+        return Ok(BTreeMap::new());
+    };
+    let claude_dir = super::claude::get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+    match storage.load_checkpoint(&project_id, &session_id, &checkpoint_id) {
+        Ok((_, files, _)) => Ok(files
+            .into_iter()
+            .filter(|file| !file.is_deleted)
+            .map(|file| (file.file_path.to_string_lossy().to_string(), file.content))
+            .collect()),
+        Err(e) => {
+            log::warn!("Pre-run checkpoint of run {} is unreadable: {}", run_id, e);
+            Ok(BTreeMap::new())
+        }
+    }
+}
+
+/// Gather and redact everything the bundle of `run_id` holds
+async fn build_bundle(db: &AgentDb, run_id: i64) -> Result<RunBundle, String> {
+    let (run, agent, changes, before, config) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let run = conn
+            .query_row(
+                &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
+                params![run_id],
+                agent_run_from_row,
+            )
+            .map_err(|e| format!("Agent run {} not found: {}", run_id, e))?;
+        let agent = conn
+            .query_row(
+                &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+                params![run.agent_id],
+                agent_from_row,
+            )
+            .map_err(|e| format!("Agent of run {} not found: {}", run_id, e))?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT path, change_type, additions, deletions FROM agent_run_file_changes
+                 WHERE run_id = ?1 ORDER BY path ASC",
+            )
+            .map_err(|e| e.to_string())?;
+        let changes = stmt
+            .query_map(params![run_id], |row| {
+                let change_type: String = row.get(1)?;
+                Ok(FileChange {
+                    path: row.get(0)?,
+                    change_type: serde_json::from_value(Value::String(change_type))
+                        .unwrap_or(FileChangeType::Modified),
+                    additions: row.get(2)?,
+                    deletions: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let before = pre_run_contents(&conn, run_id)?;
+        let config: RedactionConfig =
+            get_setting_as(&conn, REDACTION_CONFIG_KEY).unwrap_or_default();
+        (run, agent, changes, before, config)
+    };
+
+    let redactor = Redactor::new(&config)?;
+    let mut report = RedactionReport::default();
+    let transcript = match read_session_jsonl(&run.session_id, &run.project_path).await {
+        Ok(content) => content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .map(|value| redactor.redact_json(&value, &mut report))
+            .collect(),
+        Err(e) => {
+            log::warn!("Bundling run {} without a transcript: {}", run_id, e);
+            Vec::new()
+        }
+    };
+
+    let project = Path::new(&run.project_path);
+    let file_changes = changes
+        .into_iter()
+        .map(|change| {
+            let before = before
+                .get(&change.path)
+                .map(|content| redactor.redact(content, &mut report));
+            let after = (change.change_type != FileChangeType::Deleted)
+                .then(|| fs::read_to_string(project.join(&change.path)).ok())
+                .flatten()
+                .map(|content| redactor.redact(&content, &mut report));
+            BundledFileChange {
+                change,
+                before,
+                after,
+            }
+        })
+        .collect();
+
+    let mut agent = BundledAgent::from(&agent);
+    agent.system_prompt = redactor.redact(&agent.system_prompt, &mut report);
+    Ok(RunBundle {
+        version: RUN_BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        agent,
+        task: redactor.redact(&run.task, &mut report),
+        model: run.model,
+        served_model: run.served_model,
+        verbosity: run.verbosity,
+        status: run.status,
+        project_name: project
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        environment: collect_environment(std::env::vars(), &redactor, &mut report),
+        file_changes,
+        transcript,
+        redactions: report,
+    })
+}
+
+pub fn write_bundle(bundle: &RunBundle, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_vec(bundle).map_err(|e| e.to_string())?;
+    let compressed = zstd::stream::encode_all(json.as_slice(), 3)
+        .map_err(|e| format!("Failed to compress bundle: {}", e))?;
+    crate::atomic_file::write_atomic(path, compressed)
+        .map_err(|e| format!("Failed to write bundle: {}", e))
+}
+
+pub fn read_bundle(path: &Path) -> Result<RunBundle, String> {
+    let compressed = fs::read(path).map_err(|e| format!("Failed to read bundle: {}", e))?;
+    let json = zstd::stream::decode_all(compressed.as_slice())
+        .map_err(|e| format!("Not a run bundle: {}", e))?;
+    let bundle: RunBundle =
+        serde_json::from_slice(&json).map_err(|e| format!("Failed to parse bundle: {}", e))?;
+    if bundle.version != RUN_BUNDLE_VERSION {
+        return Err(format!(
+            "Unsupported bundle version {}; this version of the app reads version {}",
+            bundle.version, RUN_BUNDLE_VERSION
+        ));
+    }
+    Ok(bundle)
+}
+
+/// Put the bundled files back to their pre-run state under `project`
+fn restore_pre_run_files(bundle: &RunBundle, project: &Path) -> Result<Vec<String>, String> {
+    let mut restored = Vec::new();
+    for file in &bundle.file_changes {
+        let Some(relative) = safe_relative_path(&file.change.path) else {
+            log::warn!(
+                "Skipping bundled file outside the project: {}",
+                file.change.path
+            );
+            continue;
+        };
+        let target = project.join(relative);
+        match (&file.before, file.change.change_type) {
+            (Some(content), _) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                crate::atomic_file::write_atomic(&target, content)
+                    .map_err(|e| format!("Failed to restore {}: {}", file.change.path, e))?;
+            }
+            // The run created the file, so it didn't exist beforehand
+            (None, FileChangeType::Created) => {
+                if !target.exists() {
+                    continue;
+                }
+                fs::remove_file(&target)
+                    .map_err(|e| format!("Failed to remove {}: {}", file.change.path, e))?;
+            }
+            (None, _) => continue,
+        }
+        restored.push(file.change.path.clone());
+    }
+    Ok(restored)
+}
+
+/// Export an agent run as a bundle at `path`
+#[tauri::command]
+pub async fn export_run_bundle(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+    path: String,
+) -> Result<RedactionReport, String> {
+    let bundle = build_bundle(&db, run_id).await?;
+    write_bundle(&bundle, Path::new(&path))?;
+    log::info!(
+        "Exported run {} to {} with {} redaction(s)",
+        run_id,
+        path,
+        bundle.redactions.total
+    );
+    Ok(bundle.redactions)
+}
+
+/// Read a bundle without importing it
+#[tauri::command]
+pub async fn inspect_run_bundle(path: String) -> Result<RunBundle, String> {
+    read_bundle(Path::new(&path))
+}
+
+/// Import a bundle: recreate its agent and, given a project, optionally restore the changed
+/// files to their pre-run content and run the same task again
+#[tauri::command]
+pub async fn import_run_bundle(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    path: String,
+    project_path: Option<String>,
+    restore_files: Option<bool>,
+    execute: Option<bool>,
+) -> Result<ImportedRunBundle, String> {
+    let bundle = read_bundle(Path::new(&path))?;
+    let project = project_path
+        .as_deref()
+        .map(|path| {
+            Path::new(path)
+                .canonicalize()
+                .map_err(|e| format!("Invalid project directory: {}", e))
+        })
+        .transpose()?;
+    if project.is_none() && (restore_files.unwrap_or(false) || execute.unwrap_or(false)) {
+        return Err("Restoring files or re-running needs a project directory".to_string());
+    }
+
+    let (agent_id, agent_name) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let agent = &bundle.agent;
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM agents WHERE name = ?1)",
+                params![agent.name],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let name = if exists {
+            format!("{} (Imported)", agent.name)
+        } else {
+            agent.name.clone()
+        };
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, thinking, tool_rules)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                name,
+                agent.icon,
+                agent.system_prompt,
+                agent.default_task,
+                agent.model,
+                agent.enable_file_read,
+                agent.enable_file_write,
+                agent.enable_network,
+                agent.hooks,
+                agent.thinking.as_ref().and_then(RunThinking::to_column),
+                agent.tool_rules.as_ref().and_then(ToolRules::to_column)
+            ],
+        )
+        .map_err(|e| format!("Failed to create agent: {}", e))?;
+        (conn.last_insert_rowid(), name)
+    };
+
+    let restored_files = match (&project, restore_files.unwrap_or(false)) {
+        (Some(project), true) => restore_pre_run_files(&bundle, project)?,
+        _ => Vec::new(),
+    };
+
+    let run_id = match (&project, execute.unwrap_or(false)) {
+        (Some(project), true) => Some(
+            super::agents::start_agent_run(
+                app,
+                agent_id,
+                project.to_string_lossy().to_string(),
+                bundle.task.clone(),
+                Some(bundle.model.clone()),
+                false,
+                bundle.verbosity.clone(),
+                None,
+                None,
+                db,
+                registry,
+            )
+            .await?,
+        ),
+        _ => None,
+    };
+
+    Ok(ImportedRunBundle {
+        agent_id,
+        agent_name,
+        task: bundle.task,
+        model: bundle.model,
+        restored_files,
+        run_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> RunBundle {
+        RunBundle {
+            version: RUN_BUNDLE_VERSION,
+            exported_at: "2024-05-01T10:00:00Z".to_string(),
+            app_version: "0.0.0".to_string(),
+            agent: BundledAgent {
+                name: "Fixer".to_string(),
+                icon: "bot".to_string(),
+                system_prompt: "Fix bugs".to_string(),
+                default_task: None,
+                model: "sonnet".to_string(),
+                enable_file_read: true,
+                enable_file_write: true,
+                enable_network: false,
+                hooks: None,
+                thinking: None,
+                tool_rules: None,
+            },
+            task: "Fix the test".to_string(),
+            model: "sonnet".to_string(),
+            served_model: None,
+            verbosity: None,
+            status: "failed".to_string(),
+            project_name: "demo".to_string(),
+            environment: BTreeMap::new(),
+            file_changes: vec![
+                BundledFileChange {
+                    change: FileChange {
+                        path: "src/lib.rs".to_string(),
+                        change_type: FileChangeType::Modified,
+                        additions: Some(1),
+                        deletions: Some(1),
+                    },
+                    before: Some("old".to_string()),
+                    after: Some("new".to_string()),
+                },
+                BundledFileChange {
+                    change: FileChange {
+                        path: "notes.md".to_string(),
+                        change_type: FileChangeType::Created,
+                        additions: Some(1),
+                        deletions: Some(0),
+                    },
+                    before: None,
+                    after: Some("draft".to_string()),
+                },
+                BundledFileChange {
+                    change: FileChange {
+                        path: "../outside.txt".to_string(),
+                        change_type: FileChangeType::Modified,
+                        additions: None,
+                        deletions: None,
+                    },
+                    before: Some("nope".to_string()),
+                    after: None,
+                },
+            ],
+            transcript: vec![serde_json::json!({"type": "user"})],
+            redactions: RedactionReport::default(),
+        }
+    }
+
+    #[test]
+    fn test_bundle_round_trips_and_restores_pre_run_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.opcode-run");
+        write_bundle(&bundle(), &path).unwrap();
+        assert_eq!(read_bundle(&path).unwrap(), bundle());
+
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src/lib.rs"), "new").unwrap();
+        fs::write(project.join("notes.md"), "draft").unwrap();
+
+        let restored = restore_pre_run_files(&bundle(), &project).unwrap();
+        assert_eq!(restored, vec!["src/lib.rs", "notes.md"]);
+        assert_eq!(
+            fs::read_to_string(project.join("src/lib.rs")).unwrap(),
+            "old"
+        );
+        assert!(!project.join("notes.md").exists());
+        assert!(!dir.path().join("outside.txt").exists());
+    }
+
+    #[test]
+    fn test_environment_is_filtered_and_redacted() {
+        let redactor = Redactor::new(&RedactionConfig::default()).unwrap();
+        let mut report = RedactionReport::default();
+        let vars = [
+            (
+                "ANTHROPIC_API_KEY",
+                "sk-ant-REDACTED",
+            ),
+            ("ANTHROPIC_BASE_URL", "https://proxy.example.com"),
+            ("HTTPS_PROXY", "http://user@example.com:8080"),
+            ("PATH", "/usr/bin"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let environment = collect_environment(vars, &redactor, &mut report);
+        assert_eq!(environment["env.ANTHROPIC_API_KEY"], "[REDACTED]");
+        assert_eq!(
+            environment["env.ANTHROPIC_BASE_URL"],
+            "https://proxy.example.com"
+        );
+        assert!(!environment["env.HTTPS_PROXY"].contains("user@example.com"));
+        assert!(!environment.contains_key("env.PATH"));
+        assert_eq!(environment["os"], std::env::consts::OS);
+    }
+}
@@ -0,0 +1,306 @@
+#![allow(dead_code)]
+
+//! Per-run budget guards. `max_turns` is passed to the CLI as `--max-turns`, which ends the
+//! run itself; token and cost limits have no CLI flag, so a monitor watches the run's
+//! streamed usage and interrupts it once a limit is crossed. Either way the run ends with a
+//! `budget_exceeded` status and a `run:budget-exceeded` event carrying a [`BudgetExceeded`].
+
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::process::usage::RunUsage;
+use crate::process::{InterruptMode, ProcessRegistry};
+
+/// Run status recorded when a guard stops a run
+pub const BUDGET_EXCEEDED_STATUS: &str = "budget_exceeded";
+
+/// How often the monitor looks at a run's usage
+const GUARD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time a run gets to wind down after the interrupt before it is killed
+const GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Limits for a single run; unset limits aren't enforced
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunGuards {
+    pub max_turns: Option<u32>,
+    /// Input, output and cache tokens together
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+}
+
+/// The limit a run crossed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardLimit {
+    Turns,
+    Tokens,
+    Cost,
+}
+
+/// Payload of the `run:budget-exceeded` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetExceeded {
+    pub run_id: i64,
+    pub limit: GuardLimit,
+    pub limit_value: f64,
+    pub observed: f64,
+}
+
+impl RunGuards {
+    pub fn is_empty(&self) -> bool {
+        self.max_turns.is_none() && self.max_tokens.is_none() && self.max_cost_usd.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_turns == Some(0) {
+            return Err("max_turns must be at least 1".to_string());
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be at least 1".to_string());
+        }
+        if let Some(cost) = self.max_cost_usd {
+            if !cost.is_finite() || cost <= 0.0 {
+                return Err("max_cost_usd must be a positive amount".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Limits the CLI enforces on its own
+    pub fn claude_args(&self) -> Vec<String> {
+        match self.max_turns {
+            Some(turns) => vec!["--max-turns".to_string(), turns.to_string()],
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether token or cost limits need the usage monitor
+    pub fn needs_monitor(&self) -> bool {
+        self.max_tokens.is_some() || self.max_cost_usd.is_some()
+    }
+
+    /// The first streamed limit the usage has crossed, if any
+    pub fn exceeded(&self, usage: &RunUsage) -> Option<BudgetExceeded> {
+        if let Some(max_tokens) = self.max_tokens {
+            if usage.total_tokens > max_tokens {
+                return Some(BudgetExceeded {
+                    run_id: usage.run_id,
+                    limit: GuardLimit::Tokens,
+                    limit_value: max_tokens as f64,
+                    observed: usage.total_tokens as f64,
+                });
+            }
+        }
+        if let Some(max_cost) = self.max_cost_usd {
+            if usage.cost_usd > max_cost {
+                return Some(BudgetExceeded {
+                    run_id: usage.run_id,
+                    limit: GuardLimit::Cost,
+                    limit_value: max_cost,
+                    observed: usage.cost_usd,
+                });
+            }
+        }
+        None
+    }
+
+    /// The turn limit as reported by the CLI's `error_max_turns` result line
+    pub fn max_turns_reached(&self, run_id: i64, line: &str) -> Option<BudgetExceeded> {
+        let max_turns = self.max_turns?;
+        let json: serde_json::Value = serde_json::from_str(line).ok()?;
+        if json["type"] != "result" || json["subtype"] != "error_max_turns" {
+            return None;
+        }
+        Some(BudgetExceeded {
+            run_id,
+            limit: GuardLimit::Turns,
+            limit_value: max_turns as f64,
+            observed: json["num_turns"].as_u64().unwrap_or(max_turns as u64) as f64,
+        })
+    }
+
+    /// Stored form for the `agent_runs.guards` column; `None` when no limit is set
+    pub fn to_column(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn from_column(value: Option<String>) -> Option<Self> {
+        value.and_then(|value| serde_json::from_str(&value).ok())
+    }
+}
+
+/// Mark the run as stopped by its budget and tell the frontend. Agent runs pass their
+/// database so the status is recorded before the process exits; marking it first keeps the
+/// monitor from treating the exit as a failure and retrying it.
+pub fn record_budget_exceeded(app: &AppHandle, db_path: Option<&Path>, exceeded: &BudgetExceeded) {
+    warn!(
+        "💸 Run {} exceeded its {:?} limit ({} > {})",
+        exceeded.run_id, exceeded.limit, exceeded.observed, exceeded.limit_value
+    );
+    if let Some(db_path) = db_path {
+        if let Err(e) = Connection::open(db_path).and_then(|conn| {
+            conn.execute(
+                "UPDATE agent_runs SET status = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params![BUDGET_EXCEEDED_STATUS, exceeded.run_id],
+            )
+        }) {
+            warn!("Failed to mark run {} as over budget: {}", exceeded.run_id, e);
+        }
+    }
+
+    let _ = app.emit("run:budget-exceeded", exceeded);
+    let _ = app.emit(
+        &format!("run:budget-exceeded:{}", exceeded.run_id),
+        exceeded,
+    );
+    super::activity::record_activity(
+        app,
+        super::activity::NewActivity::new(
+            super::activity::ActivityKind::RunBudgetExceeded,
+            format!("Run {} exceeded its budget", exceeded.run_id),
+        )
+        .run(exceeded.run_id)
+        .detail(serde_json::json!({
+            "limit": exceeded.limit,
+            "limit_value": exceeded.limit_value,
+            "observed": exceeded.observed,
+        })),
+    );
+    super::notifications::notify(
+        app,
+        super::notifications::NotificationEvent::BudgetThreshold,
+        "Run budget exceeded",
+        &format!(
+            "Run {} was stopped after crossing its {:?} limit",
+            exceeded.run_id, exceeded.limit
+        ),
+    );
+}
+
+/// Watch a run's streamed usage until it leaves the registry, stopping it once a token or
+/// cost limit is crossed
+pub fn spawn_guard_monitor(
+    app: AppHandle,
+    registry: Arc<ProcessRegistry>,
+    run_id: i64,
+    guards: RunGuards,
+    db_path: Option<PathBuf>,
+) {
+    if !guards.needs_monitor() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(GUARD_CHECK_INTERVAL).await;
+            let usage = match registry.get_run_usage(run_id) {
+                Ok(Some(usage)) => usage,
+                // The run finished and was unregistered
+                _ => return,
+            };
+            let Some(exceeded) = guards.exceeded(&usage) else {
+                continue;
+            };
+
+            record_budget_exceeded(&app, db_path.as_deref(), &exceeded);
+            stop_run(&registry, run_id).await;
+            return;
+        }
+    });
+}
+
+/// Interrupt the run so it can finish its current write, and kill it if it doesn't exit
+async fn stop_run(registry: &ProcessRegistry, run_id: i64) {
+    if let Err(e) = registry
+        .interrupt_process(run_id, InterruptMode::Graceful)
+        .await
+    {
+        warn!("Failed to interrupt run {}: {}", run_id, e);
+    }
+    tokio::time::sleep(GRACEFUL_STOP_TIMEOUT).await;
+    if registry.is_process_running(run_id).await.unwrap_or(false) {
+        info!("Run {} ignored the interrupt, killing it", run_id);
+        if let Err(e) = registry
+            .interrupt_process(run_id, InterruptMode::Hard)
+            .await
+        {
+            warn!("Failed to kill run {}: {}", run_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded_reports_the_crossed_limit() {
+        let guards = RunGuards {
+            max_turns: Some(5),
+            max_tokens: Some(10_000),
+            max_cost_usd: Some(0.50),
+        };
+        assert!(guards.validate().is_ok());
+        assert_eq!(guards.claude_args(), vec!["--max-turns", "5"]);
+
+        let mut usage = RunUsage {
+            run_id: 7,
+            total_tokens: 10_000,
+            cost_usd: 0.50,
+            ..RunUsage::default()
+        };
+        assert_eq!(guards.exceeded(&usage), None);
+
+        usage.cost_usd = 0.51;
+        let exceeded = guards.exceeded(&usage).unwrap();
+        assert_eq!(exceeded.limit, GuardLimit::Cost);
+        assert_eq!(exceeded.run_id, 7);
+
+        usage.total_tokens = 12_000;
+        assert_eq!(guards.exceeded(&usage).unwrap().limit, GuardLimit::Tokens);
+
+        assert!(RunGuards {
+            max_turns: Some(0),
+            ..RunGuards::default()
+        }
+        .validate()
+        .is_err());
+        assert!(RunGuards {
+            max_cost_usd: Some(-1.0),
+            ..RunGuards::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_max_turns_result_and_column_round_trip() {
+        let guards = RunGuards {
+            max_turns: Some(3),
+            ..RunGuards::default()
+        };
+        assert!(!guards.needs_monitor());
+
+        let line = r#"{"type":"result","subtype":"error_max_turns","num_turns":4}"#;
+        let exceeded = guards.max_turns_reached(1, line).unwrap();
+        assert_eq!(exceeded.limit, GuardLimit::Turns);
+        assert_eq!(exceeded.observed, 4.0);
+        assert!(guards
+            .max_turns_reached(1, r#"{"type":"result","subtype":"success"}"#)
+            .is_none());
+        assert!(RunGuards::default().max_turns_reached(1, line).is_none());
+
+        assert_eq!(RunGuards::default().to_column(), None);
+        assert_eq!(RunGuards::from_column(guards.to_column()), Some(guards));
+    }
+}
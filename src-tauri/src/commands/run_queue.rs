@@ -0,0 +1,698 @@
+#![allow(dead_code)]
+
+//! Priority queue for agent runs. Queued runs start when fewer than `max_concurrent` runs
+//! are active, highest priority class first (interactive > scheduled > batch), oldest first
+//! within a class. With the `pause_lowest` preemption policy a waiting run of a higher class
+//! also pauses the lowest-priority active run to take its slot; paused runs resume once a
+//! slot frees up. Runs started outside the queue count as interactive.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use super::agents::AgentDb;
+use super::settings::{get_setting_as, set_setting_as};
+use crate::process::ProcessRegistryState;
+
+/// app_settings key holding the `RunQueueSettings`
+const RUN_QUEUE_SETTINGS_KEY: &str = "run_queue";
+
+/// Event emitted when queued runs start or active runs are paused or resumed
+const QUEUE_CHANGED_EVENT: &str = "run-queue-changed";
+
+/// How often the dispatcher looks for free slots without being woken
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Priority class of a run; later variants win
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPriority {
+    Batch,
+    Scheduled,
+    Interactive,
+}
+
+impl RunPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunPriority::Batch => "batch",
+            RunPriority::Scheduled => "scheduled",
+            RunPriority::Interactive => "interactive",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "batch" => RunPriority::Batch,
+            "scheduled" => RunPriority::Scheduled,
+            _ => RunPriority::Interactive,
+        }
+    }
+}
+
+/// What a waiting run of a higher class may do to active runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreemptionPolicy {
+    /// Wait for a slot to free up
+    #[default]
+    Never,
+    /// Pause the lowest-priority active run and take its slot
+    PauseLowest,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunQueueSettings {
+    pub max_concurrent: usize,
+    pub preemption: PreemptionPolicy,
+}
+
+impl Default for RunQueueSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 4,
+            preemption: PreemptionPolicy::Never,
+        }
+    }
+}
+
+/// A run waiting in (or started from) the queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedRun {
+    pub id: i64,
+    pub agent_id: i64,
+    pub project_path: String,
+    pub task: String,
+    pub model: Option<String>,
+    pub priority: RunPriority,
+    /// `queued`, `started`, `failed` or `cancelled`
+    pub status: String,
+    pub run_id: Option<i64>,
+    pub error: Option<String>,
+    pub enqueued_at: String,
+    pub started_at: Option<String>,
+}
+
+/// Queue state kept in memory
+#[derive(Default)]
+pub struct RunQueueState {
+    /// Runs the dispatcher paused
+    paused: Mutex<HashSet<i64>>,
+    wake: Notify,
+}
+
+impl RunQueueState {
+    /// Ask the dispatcher to look at the queue now
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn paused_runs(&self) -> Vec<i64> {
+        self.paused
+            .lock()
+            .map(|paused| paused.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// A queued entry as the planner sees it, in queue order
+#[derive(Debug, Clone, Copy)]
+pub struct WaitingEntry {
+    pub id: i64,
+    pub priority: RunPriority,
+}
+
+/// An agent run with a process, as the planner sees it
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveRun {
+    pub run_id: i64,
+    pub priority: RunPriority,
+    pub paused: bool,
+    /// Runs that haven't produced output yet are never paused, so the no-output
+    /// timeout can't fire while they're stopped
+    pub pausable: bool,
+}
+
+/// What the dispatcher should do next
+#[derive(Debug, Default, PartialEq)]
+pub struct DispatchPlan {
+    /// Queue entries to start
+    pub start: Vec<i64>,
+    /// Runs to pause
+    pub pause: Vec<i64>,
+    /// Paused runs to resume
+    pub resume: Vec<i64>,
+}
+
+/// Decide which queued runs to start and which runs to pause or resume
+pub fn plan_dispatch(
+    queued: &[WaitingEntry],
+    running: &[ActiveRun],
+    settings: &RunQueueSettings,
+) -> DispatchPlan {
+    let max = settings.max_concurrent.max(1);
+    let preempt = settings.preemption == PreemptionPolicy::PauseLowest;
+    let mut plan = DispatchPlan::default();
+    let mut active: Vec<ActiveRun> = running.iter().filter(|run| !run.paused).copied().collect();
+
+    // Lowest-priority pausable active run below `priority`
+    let lowest_below = |active: &[ActiveRun], priority: RunPriority| {
+        active
+            .iter()
+            .enumerate()
+            .filter(|(_, run)| run.pausable && run.priority < priority)
+            .min_by_key(|(_, run)| run.priority)
+            .map(|(index, _)| index)
+    };
+
+    // Runs started outside the queue can leave it over capacity
+    if preempt {
+        while active.len() > max {
+            let Some(highest) = active.iter().map(|run| run.priority).max() else {
+                break;
+            };
+            let Some(index) = lowest_below(&active, highest) else {
+                break;
+            };
+            plan.pause.push(active.remove(index).run_id);
+        }
+    }
+
+    // Waiting work, best first; paused runs resume before queued runs of the same class
+    let mut waiting: Vec<(RunPriority, bool, usize, i64)> = running
+        .iter()
+        .filter(|run| run.paused)
+        .map(|run| (run.priority, false, 0, run.run_id))
+        .chain(
+            queued
+                .iter()
+                .enumerate()
+                .map(|(order, entry)| (entry.priority, true, order, entry.id)),
+        )
+        .collect();
+    waiting.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    for (priority, is_queued, _, id) in waiting {
+        if active.len() >= max {
+            let Some(index) = preempt.then(|| lowest_below(&active, priority)).flatten() else {
+                break;
+            };
+            plan.pause.push(active.remove(index).run_id);
+        }
+        if is_queued {
+            plan.start.push(id);
+        } else {
+            plan.resume.push(id);
+        }
+        active.push(ActiveRun {
+            run_id: id,
+            priority,
+            paused: false,
+            pausable: false,
+        });
+    }
+
+    plan
+}
+
+pub fn init_run_queue_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            task TEXT NOT NULL,
+            model TEXT,
+            priority TEXT NOT NULL DEFAULT 'interactive',
+            status TEXT NOT NULL DEFAULT 'queued',
+            run_id INTEGER,
+            error TEXT,
+            enqueued_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            started_at TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_priorities (
+            run_id INTEGER PRIMARY KEY,
+            priority TEXT NOT NULL,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const QUEUE_COLUMNS: &str =
+    "id, agent_id, project_path, task, model, priority, status, run_id, error, enqueued_at, started_at";
+
+fn queued_run_from_row(row: &rusqlite::Row) -> SqliteResult<QueuedRun> {
+    Ok(QueuedRun {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        project_path: row.get(2)?,
+        task: row.get(3)?,
+        model: row.get(4)?,
+        priority: RunPriority::from_str(&row.get::<_, String>(5)?),
+        status: row.get(6)?,
+        run_id: row.get(7)?,
+        error: row.get(8)?,
+        enqueued_at: row.get(9)?,
+        started_at: row.get(10)?,
+    })
+}
+
+fn load_queued_run(conn: &Connection, id: i64) -> Result<QueuedRun, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM run_queue WHERE id = ?1", QUEUE_COLUMNS),
+        params![id],
+        queued_run_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Queued run {} not found", id))
+}
+
+/// Queue entries still waiting, in queue order
+fn load_waiting(conn: &Connection) -> SqliteResult<Vec<QueuedRun>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM run_queue WHERE status = 'queued' ORDER BY id",
+        QUEUE_COLUMNS
+    ))?;
+    let entries = stmt
+        .query_map([], queued_run_from_row)?
+        .collect::<SqliteResult<Vec<_>>>();
+    entries
+}
+
+/// Priority class of a run; runs started outside the queue are interactive
+pub fn run_priority(conn: &Connection, run_id: i64) -> RunPriority {
+    conn.query_row(
+        "SELECT priority FROM run_priorities WHERE run_id = ?1",
+        params![run_id],
+        |row| row.get::<_, String>(0),
+    )
+    .map(|priority| RunPriority::from_str(&priority))
+    .unwrap_or(RunPriority::Interactive)
+}
+
+/// Record the priority class of a run
+pub fn record_run_priority(
+    conn: &Connection,
+    run_id: i64,
+    priority: RunPriority,
+) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO run_priorities (run_id, priority) VALUES (?1, ?2)",
+        params![run_id, priority.as_str()],
+    )?;
+    Ok(())
+}
+
+pub fn load_run_queue_settings(conn: &Connection) -> RunQueueSettings {
+    get_setting_as(conn, RUN_QUEUE_SETTINGS_KEY).unwrap_or_default()
+}
+
+/// Stop or continue a run's process
+fn signal_run(pid: u32, pause: bool) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        let signal = if pause { "-STOP" } else { "-CONT" };
+        let output = std::process::Command::new("kill")
+            .arg(signal)
+            .arg(pid.to_string())
+            .output()
+            .map_err(|e| format!("Failed to signal process {}: {}", pid, e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Failed to signal process {}: {}",
+                pid,
+                crate::decoding::decode_command_output(&output.stderr).trim()
+            ))
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, pause);
+        Err("Pausing runs is not supported on this platform".to_string())
+    }
+}
+
+/// Start queued runs and pause or resume active ones as the settings allow
+async fn dispatch(app: &AppHandle) {
+    let queue = app.state::<RunQueueState>();
+    let registry = app.state::<ProcessRegistryState>();
+    let processes = registry.0.get_running_agent_processes().unwrap_or_default();
+
+    // Forget paused runs that have ended
+    if let Ok(mut paused) = queue.paused.lock() {
+        paused.retain(|run_id| processes.iter().any(|p| p.run_id == *run_id));
+    }
+    let paused = queue.paused_runs();
+
+    let (waiting, running, settings) = {
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            return;
+        };
+        let waiting = load_waiting(&conn).unwrap_or_default();
+        let running: Vec<ActiveRun> = processes
+            .iter()
+            .map(|process| ActiveRun {
+                run_id: process.run_id,
+                priority: run_priority(&conn, process.run_id),
+                paused: paused.contains(&process.run_id),
+                pausable: registry
+                    .0
+                    .get_recent_live_output(process.run_id, 1)
+                    .is_ok_and(|output| !output.is_empty()),
+            })
+            .collect();
+        (waiting, running, load_run_queue_settings(&conn))
+    };
+    if waiting.is_empty() && paused.is_empty() && running.len() <= settings.max_concurrent {
+        return;
+    }
+
+    let entries: Vec<WaitingEntry> = waiting
+        .iter()
+        .map(|entry| WaitingEntry {
+            id: entry.id,
+            priority: entry.priority,
+        })
+        .collect();
+    let plan = plan_dispatch(&entries, &running, &settings);
+    if plan == DispatchPlan::default() {
+        return;
+    }
+
+    for run_id in &plan.pause {
+        let Some(process) = processes.iter().find(|p| p.run_id == *run_id) else {
+            continue;
+        };
+        match signal_run(process.pid, true) {
+            Ok(()) => {
+                log::info!("⏸️ Paused run {} for a higher-priority run", run_id);
+                if let Ok(mut paused) = queue.paused.lock() {
+                    paused.insert(*run_id);
+                }
+                let _ = app.emit(&format!("agent-paused:{}", run_id), true);
+            }
+            Err(e) => log::warn!("Failed to pause run {}: {}", run_id, e),
+        }
+    }
+
+    for run_id in &plan.resume {
+        let Some(process) = processes.iter().find(|p| p.run_id == *run_id) else {
+            continue;
+        };
+        match signal_run(process.pid, false) {
+            Ok(()) => {
+                log::info!("▶️ Resumed run {}", run_id);
+                if let Ok(mut paused) = queue.paused.lock() {
+                    paused.remove(run_id);
+                }
+                let _ = app.emit(&format!("agent-resumed:{}", run_id), true);
+            }
+            Err(e) => log::warn!("Failed to resume run {}: {}", run_id, e),
+        }
+    }
+
+    for id in &plan.start {
+        let Some(entry) = waiting.iter().find(|entry| entry.id == *id) else {
+            continue;
+        };
+        let result = super::agents::execute_agent(
+            app.clone(),
+            entry.agent_id,
+            entry.project_path.clone(),
+            entry.task.clone(),
+            entry.model.clone(),
+            None,
+            None,
+            None,
+            app.state::<AgentDb>(),
+            app.state::<ProcessRegistryState>(),
+        )
+        .await;
+
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            continue;
+        };
+        match result {
+            Ok(run_id) => {
+                log::info!(
+                    "Started queued run {} ({}) as run {}",
+                    entry.id,
+                    entry.priority.as_str(),
+                    run_id
+                );
+                let _ = record_run_priority(&conn, run_id, entry.priority);
+                let _ = conn.execute(
+                    "UPDATE run_queue SET status = 'started', run_id = ?2, started_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                    params![entry.id, run_id],
+                );
+            }
+            Err(e) => {
+                log::warn!("Queued run {} failed to start: {}", entry.id, e);
+                let _ = conn.execute(
+                    "UPDATE run_queue SET status = 'failed', error = ?2 WHERE id = ?1",
+                    params![entry.id, e],
+                );
+            }
+        }
+    }
+
+    let _ = app.emit(QUEUE_CHANGED_EVENT, true);
+}
+
+/// Start the background task that drains the queue
+pub fn spawn_queue_dispatcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            dispatch(&app).await;
+            let queue = app.state::<RunQueueState>();
+            tokio::select! {
+                _ = queue.wake.notified() => {}
+                _ = tokio::time::sleep(DISPATCH_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+/// Add a run to the queue; it starts as soon as the queue allows
+#[tauri::command]
+pub async fn enqueue_agent_run(
+    db: State<'_, AgentDb>,
+    queue: State<'_, RunQueueState>,
+    agent_id: i64,
+    project_path: String,
+    task: String,
+    model: Option<String>,
+    priority: Option<RunPriority>,
+) -> Result<QueuedRun, String> {
+    if task.trim().is_empty() {
+        return Err("Task cannot be empty".to_string());
+    }
+    let priority = priority.unwrap_or(RunPriority::Interactive);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO run_queue (agent_id, project_path, task, model, priority) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![agent_id, project_path, task, model, priority.as_str()],
+    )
+    .map_err(|e| format!("Failed to queue run: {}", e))?;
+    let entry = load_queued_run(&conn, conn.last_insert_rowid())?;
+    queue.wake();
+    Ok(entry)
+}
+
+/// List the queue: waiting runs in the order they will start, then recently started ones
+#[tauri::command]
+pub async fn list_run_queue(
+    db: State<'_, AgentDb>,
+    include_finished: Option<bool>,
+) -> Result<Vec<QueuedRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut waiting = load_waiting(&conn).map_err(|e| e.to_string())?;
+    waiting.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.id.cmp(&b.id)));
+
+    if include_finished.unwrap_or(false) {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM run_queue WHERE status != 'queued' ORDER BY id DESC LIMIT 50",
+                QUEUE_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let finished = stmt
+            .query_map([], queued_run_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqliteResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        waiting.extend(finished);
+    }
+    Ok(waiting)
+}
+
+/// Remove a run from the queue before it starts
+#[tauri::command]
+pub async fn cancel_queued_run(db: State<'_, AgentDb>, id: i64) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE run_queue SET status = 'cancelled' WHERE id = ?1 AND status = 'queued'",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(updated > 0)
+}
+
+/// Move a waiting run to another priority class
+#[tauri::command]
+pub async fn reprioritize_queued_run(
+    db: State<'_, AgentDb>,
+    queue: State<'_, RunQueueState>,
+    id: i64,
+    priority: RunPriority,
+) -> Result<QueuedRun, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE run_queue SET priority = ?2 WHERE id = ?1 AND status = 'queued'",
+            params![id, priority.as_str()],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Queued run {} has already left the queue", id));
+    }
+    queue.wake();
+    load_queued_run(&conn, id)
+}
+
+/// Change the priority class of a running agent run, which decides what preemption pauses
+#[tauri::command]
+pub async fn reprioritize_running_run(
+    db: State<'_, AgentDb>,
+    queue: State<'_, RunQueueState>,
+    run_id: i64,
+    priority: RunPriority,
+) -> Result<RunPriority, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let status: Option<String> = conn
+        .query_row(
+            "SELECT status FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match status.as_deref() {
+        Some("pending") | Some("running") => {}
+        Some(_) => return Err(format!("Run {} is no longer running", run_id)),
+        None => return Err(format!("Run {} not found", run_id)),
+    }
+    record_run_priority(&conn, run_id, priority).map_err(|e| e.to_string())?;
+    queue.wake();
+    Ok(priority)
+}
+
+#[tauri::command]
+pub async fn get_run_queue_settings(db: State<'_, AgentDb>) -> Result<RunQueueSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_run_queue_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_run_queue_settings(
+    db: State<'_, AgentDb>,
+    queue: State<'_, RunQueueState>,
+    settings: RunQueueSettings,
+) -> Result<RunQueueSettings, String> {
+    if settings.max_concurrent == 0 || settings.max_concurrent > 32 {
+        return Err("Concurrent runs must be between 1 and 32".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, RUN_QUEUE_SETTINGS_KEY, &settings)?;
+    queue.wake();
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn active(run_id: i64, priority: RunPriority) -> ActiveRun {
+        ActiveRun {
+            run_id,
+            priority,
+            paused: false,
+            pausable: true,
+        }
+    }
+
+    fn waiting(id: i64, priority: RunPriority) -> WaitingEntry {
+        WaitingEntry { id, priority }
+    }
+
+    #[test]
+    fn test_interactive_runs_jump_the_queue() {
+        let settings = RunQueueSettings {
+            max_concurrent: 2,
+            preemption: PreemptionPolicy::Never,
+        };
+        let queued = [
+            waiting(1, RunPriority::Scheduled),
+            waiting(2, RunPriority::Scheduled),
+            waiting(3, RunPriority::Interactive),
+        ];
+        let plan = plan_dispatch(&queued, &[active(10, RunPriority::Scheduled)], &settings);
+        assert_eq!(plan.start, vec![3]);
+        assert!(plan.pause.is_empty());
+
+        // Full: nothing starts and nothing is paused without preemption
+        let running = [
+            active(10, RunPriority::Scheduled),
+            active(11, RunPriority::Batch),
+        ];
+        assert_eq!(
+            plan_dispatch(&queued, &running, &settings),
+            DispatchPlan::default()
+        );
+    }
+
+    #[test]
+    fn test_pause_lowest_preempts_and_resumes() {
+        let settings = RunQueueSettings {
+            max_concurrent: 2,
+            preemption: PreemptionPolicy::PauseLowest,
+        };
+        let running = [
+            active(10, RunPriority::Scheduled),
+            active(11, RunPriority::Batch),
+        ];
+        let plan = plan_dispatch(&[waiting(3, RunPriority::Interactive)], &running, &settings);
+        assert_eq!(plan.pause, vec![11]);
+        assert_eq!(plan.start, vec![3]);
+
+        // Equal classes never preempt each other
+        let plan = plan_dispatch(&[waiting(4, RunPriority::Batch)], &running, &settings);
+        assert!(plan.pause.is_empty() && plan.start.is_empty());
+
+        // A paused run resumes ahead of queued runs of its class once a slot frees up
+        let mut paused = active(11, RunPriority::Batch);
+        paused.paused = true;
+        let plan = plan_dispatch(
+            &[waiting(5, RunPriority::Batch)],
+            &[active(10, RunPriority::Scheduled), paused],
+            &settings,
+        );
+        assert_eq!(plan.resume, vec![11]);
+        assert!(plan.start.is_empty());
+    }
+}
@@ -0,0 +1,402 @@
+#![allow(dead_code)]
+
+//! Heartbeat-based hang detection. Every line a run writes to stdout or stderr counts as a
+//! heartbeat; a run that stays silent for longer than its agent's `stall_after_secs` is
+//! flagged as stalled, announced with a `run:stalled` event, and then handled according to
+//! the agent's [`StallAction`].
+
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+use super::agents::AgentDb;
+use crate::process::ProcessRegistry;
+
+/// Shortest silence that can count as a stall; the first output may take this long anyway
+const MIN_STALL_AFTER_SECS: u64 = 30;
+
+/// Longest configurable silence (1 day)
+const MAX_STALL_AFTER_SECS: u64 = 24 * 60 * 60;
+
+/// Upper bound for automatic retries of stalled runs
+const MAX_STALL_RETRIES: u32 = 10;
+
+/// What to do once a run is flagged as stalled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StallAction {
+    /// Emit the event and a notification, and let the run continue
+    Notify,
+    /// Kill the run and mark it as failed
+    AutoKill,
+    /// Kill the run and start it again, up to `max_retries` times
+    AutoKillAndRetry,
+}
+
+impl StallAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StallAction::Notify => "notify",
+            StallAction::AutoKill => "auto_kill",
+            StallAction::AutoKillAndRetry => "auto_kill_and_retry",
+        }
+    }
+}
+
+/// Per-agent hang detection policy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchdogPolicy {
+    pub enabled: bool,
+    /// Seconds without any output after which the run counts as stalled
+    pub stall_after_secs: u64,
+    pub action: StallAction,
+    /// Attempts started by `auto_kill_and_retry` before giving up
+    pub max_retries: u32,
+}
+
+impl Default for WatchdogPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stall_after_secs: 600,
+            action: StallAction::Notify,
+            max_retries: 1,
+        }
+    }
+}
+
+impl WatchdogPolicy {
+    /// Validate the policy before persisting it
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_STALL_AFTER_SECS..=MAX_STALL_AFTER_SECS).contains(&self.stall_after_secs) {
+            return Err(format!(
+                "stall_after_secs must be between {} and {}",
+                MIN_STALL_AFTER_SECS, MAX_STALL_AFTER_SECS
+            ));
+        }
+        if self.max_retries > MAX_STALL_RETRIES {
+            return Err(format!("max_retries cannot exceed {}", MAX_STALL_RETRIES));
+        }
+        Ok(())
+    }
+
+    pub fn stall_after(&self) -> Duration {
+        Duration::from_secs(self.stall_after_secs)
+    }
+
+    /// How often the watchdog looks at the heartbeat
+    pub fn check_interval(&self) -> Duration {
+        (self.stall_after() / 4).clamp(Duration::from_secs(1), Duration::from_secs(15))
+    }
+}
+
+/// Payload of the `run:stalled` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStalled {
+    pub run_id: i64,
+    pub agent_id: i64,
+    pub silent_for_ms: u64,
+    pub action: StallAction,
+}
+
+/// Flags a stall once per silent period and re-arms when output resumes
+#[derive(Debug)]
+pub struct StallDetector {
+    stall_after: Duration,
+    stalled: bool,
+}
+
+impl StallDetector {
+    pub fn new(stall_after: Duration) -> Self {
+        Self {
+            stall_after,
+            stalled: false,
+        }
+    }
+
+    /// Whether a run silent for `silent_for` has just become stalled
+    pub fn observe(&mut self, silent_for: Duration) -> bool {
+        if silent_for < self.stall_after {
+            self.stalled = false;
+            return false;
+        }
+        let newly_stalled = !self.stalled;
+        self.stalled = true;
+        newly_stalled
+    }
+}
+
+/// Create the watchdog policy table
+pub fn init_watchdog_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_watchdog_policies (
+            agent_id INTEGER PRIMARY KEY,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            stall_after_secs INTEGER NOT NULL DEFAULT 600,
+            action TEXT NOT NULL DEFAULT 'notify',
+            max_retries INTEGER NOT NULL DEFAULT 1,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Load the watchdog policy for an agent, falling back to the default
+pub fn load_watchdog_policy(conn: &Connection, agent_id: i64) -> SqliteResult<WatchdogPolicy> {
+    let policy = conn
+        .query_row(
+            "SELECT enabled, stall_after_secs, action, max_retries
+             FROM agent_watchdog_policies WHERE agent_id = ?1",
+            params![agent_id],
+            |row| {
+                let action: String = row.get(2)?;
+                Ok(WatchdogPolicy {
+                    enabled: row.get(0)?,
+                    stall_after_secs: row.get::<_, i64>(1)? as u64,
+                    action: serde_json::from_value(serde_json::Value::String(action))
+                        .unwrap_or(StallAction::Notify),
+                    max_retries: row.get(3)?,
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(policy.unwrap_or_default())
+}
+
+/// Persist an agent's watchdog policy
+pub fn save_watchdog_policy(
+    conn: &Connection,
+    agent_id: i64,
+    policy: &WatchdogPolicy,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO agent_watchdog_policies (agent_id, enabled, stall_after_secs, action, max_retries)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(agent_id) DO UPDATE SET
+            enabled = ?2, stall_after_secs = ?3, action = ?4, max_retries = ?5,
+            updated_at = CURRENT_TIMESTAMP",
+        params![
+            agent_id,
+            policy.enabled,
+            policy.stall_after_secs as i64,
+            policy.action.as_str(),
+            policy.max_retries
+        ],
+    )
+    .map_err(|e| format!("Failed to save watchdog policy: {}", e))?;
+    Ok(())
+}
+
+/// Watch a run's heartbeat until it leaves the registry, applying the agent's policy when it
+/// stalls
+pub fn spawn_run_watchdog(
+    app: AppHandle,
+    registry: Arc<ProcessRegistry>,
+    db_path: PathBuf,
+    run_id: i64,
+    agent_id: i64,
+) {
+    let policy =
+        match Connection::open(&db_path).and_then(|conn| load_watchdog_policy(&conn, agent_id)) {
+            Ok(policy) => policy,
+            Err(e) => {
+                warn!("Failed to load watchdog policy for run {}: {}", run_id, e);
+                return;
+            }
+        };
+    if !policy.enabled {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut detector = StallDetector::new(policy.stall_after());
+        loop {
+            tokio::time::sleep(policy.check_interval()).await;
+            let silent_for = match registry.silent_for(run_id) {
+                Ok(Some(silent_for)) => silent_for,
+                // The run finished and was unregistered
+                _ => return,
+            };
+            if !detector.observe(silent_for) {
+                continue;
+            }
+
+            handle_stall(
+                &app, &registry, &db_path, &policy, run_id, agent_id, silent_for,
+            )
+            .await;
+            if policy.action != StallAction::Notify {
+                return;
+            }
+        }
+    });
+}
+
+async fn handle_stall(
+    app: &AppHandle,
+    registry: &ProcessRegistry,
+    db_path: &std::path::Path,
+    policy: &WatchdogPolicy,
+    run_id: i64,
+    agent_id: i64,
+    silent_for: Duration,
+) {
+    let silent_secs = silent_for.as_secs();
+    warn!(
+        "⏳ Run {} has been silent for {}s, applying '{}'",
+        run_id,
+        silent_secs,
+        policy.action.as_str()
+    );
+    let stalled = RunStalled {
+        run_id,
+        agent_id,
+        silent_for_ms: silent_for.as_millis() as u64,
+        action: policy.action,
+    };
+    let _ = app.emit("run:stalled", &stalled);
+    let _ = app.emit(&format!("run:stalled:{}", run_id), &stalled);
+    super::activity::record_activity(
+        app,
+        super::activity::NewActivity::new(
+            super::activity::ActivityKind::RunStalled,
+            format!("Run {} stalled", run_id),
+        )
+        .run(run_id)
+        .detail(serde_json::json!({
+            "silent_for_ms": stalled.silent_for_ms,
+            "action": policy.action,
+        })),
+    );
+    super::notifications::notify(
+        app,
+        super::notifications::NotificationEvent::RunFailed,
+        "Run stalled",
+        &format!("Run {} has produced no output for {}s", run_id, silent_secs),
+    );
+
+    if policy.action == StallAction::Notify {
+        return;
+    }
+
+    // Mark the run failed first so the monitor doesn't treat the kill as an ordinary failure
+    // and apply the retry policy on top of ours
+    let attempt = match Connection::open(db_path).and_then(|conn| {
+        conn.execute(
+            "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+            params![run_id],
+        )?;
+        conn.query_row(
+            "SELECT attempt FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+    }) {
+        Ok(attempt) => attempt.unwrap_or(1),
+        Err(e) => {
+            warn!("Failed to mark stalled run {} as failed: {}", run_id, e);
+            1
+        }
+    };
+
+    if let Err(e) = registry.kill_process_tree(run_id).await {
+        warn!("Failed to kill stalled run {}: {}", run_id, e);
+    }
+
+    if policy.action == StallAction::AutoKillAndRetry {
+        // `attempt` counts the first run, so retries so far are one less
+        if attempt as u32 > policy.max_retries {
+            info!(
+                "Stalled run {} used up its {} retries",
+                run_id, policy.max_retries
+            );
+            return;
+        }
+        match super::agents::retry_agent_run(app.clone(), run_id, None, None).await {
+            Ok(new_run_id) => {
+                info!("🔁 Restarted stalled run {} as run {}", run_id, new_run_id);
+                let _ = app.emit(&format!("agent-retry:{}", run_id), new_run_id);
+            }
+            Err(e) => warn!("Failed to restart stalled run {}: {}", run_id, e),
+        }
+    }
+}
+
+/// Get the watchdog policy configured for an agent
+#[tauri::command]
+pub async fn get_agent_watchdog_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<WatchdogPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_watchdog_policy(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Set the watchdog policy for an agent
+#[tauri::command]
+pub async fn set_agent_watchdog_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    policy: WatchdogPolicy,
+) -> Result<WatchdogPolicy, String> {
+    policy.validate()?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_watchdog_policy(&conn, agent_id, &policy)?;
+
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detector_flags_each_silent_period_once() {
+        let mut detector = StallDetector::new(Duration::from_secs(60));
+        assert!(!detector.observe(Duration::from_secs(59)));
+        assert!(detector.observe(Duration::from_secs(60)));
+        assert!(!detector.observe(Duration::from_secs(120)));
+        // Output resumed, so the next silence is a new stall
+        assert!(!detector.observe(Duration::from_secs(1)));
+        assert!(detector.observe(Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_policy_round_trips_and_validates() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE agents (id INTEGER PRIMARY KEY)", [])
+            .unwrap();
+        conn.execute("INSERT INTO agents (id) VALUES (1)", [])
+            .unwrap();
+        init_watchdog_tables(&conn).unwrap();
+        assert_eq!(
+            load_watchdog_policy(&conn, 1).unwrap(),
+            WatchdogPolicy::default()
+        );
+
+        let policy = WatchdogPolicy {
+            enabled: true,
+            stall_after_secs: 120,
+            action: StallAction::AutoKillAndRetry,
+            max_retries: 2,
+        };
+        save_watchdog_policy(&conn, 1, &policy).unwrap();
+        assert_eq!(load_watchdog_policy(&conn, 1).unwrap(), policy);
+        assert_eq!(policy.check_interval(), Duration::from_secs(15));
+
+        let mut invalid = policy.clone();
+        invalid.stall_after_secs = 5;
+        assert!(invalid.validate().is_err());
+        invalid.stall_after_secs = 120;
+        invalid.max_retries = 50;
+        assert!(invalid.validate().is_err());
+    }
+}
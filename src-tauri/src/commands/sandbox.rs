@@ -0,0 +1,656 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Files of Claude's global config copied into a sandboxed run's scratch config directory
+const SCRATCH_CONFIG_FILES: &[&str] = &[
+    "settings.json",
+    "settings.local.json",
+    ".credentials.json",
+    "CLAUDE.md",
+];
+
+/// Tools that reach the network directly, disabled when a profile turns network off
+const NETWORK_TOOLS: &[&str] = &["WebFetch", "WebSearch"];
+
+/// Shell commands that reach the network, disabled when a profile turns network off
+const NETWORK_COMMANDS: &[&str] = &["curl", "wget", "nc", "ssh", "scp", "rsync", "ftp", "telnet"];
+
+/// A sandbox profile that can be attached to agents.
+///
+/// File access is enforced by the OS sandbox (`sandbox-exec` on macOS, `bwrap` on Linux), and
+/// a run with a profile fails where neither is available: the run can read everywhere but
+/// only write inside the project (minus its `.claude` directory), the allowed directories,
+/// the temp directory, Claude's session transcripts and a scratch config directory of its
+/// own, so it can't plant hooks or MCP servers that later run outside the sandbox.
+///
+/// Turning network off is not network isolation: the Claude CLI itself needs the network to
+/// reach the API, so it only removes the web tools and the common network commands through
+/// Claude's `--disallowedTools` rules. Denied commands are enforced the same way.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SandboxProfile {
+    pub id: Option<i64>,
+    pub name: String,
+    /// Directories (besides the project) the agent may write to
+    pub allowed_directories: Vec<String>,
+    /// When false, the web tools and common network commands are disallowed. Programs the
+    /// agent runs can still reach the network.
+    pub network_enabled: bool,
+    /// Command prefixes the agent may not run through Bash (e.g. `rm`, `git push`)
+    pub denied_commands: Vec<String>,
+    pub created_at: Option<String>,
+}
+
+/// A recorded sandbox violation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SandboxViolation {
+    pub id: i64,
+    pub run_id: i64,
+    pub source: String,
+    pub detail: String,
+    pub created_at: String,
+}
+
+impl SandboxProfile {
+    /// Claude CLI flags implementing this profile
+    pub fn claude_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        for dir in &self.allowed_directories {
+            args.push("--add-dir".to_string());
+            args.push(dir.clone());
+        }
+
+        let mut disallowed: Vec<String> = self
+            .denied_commands
+            .iter()
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .map(|c| format!("Bash({}:*)", c))
+            .collect();
+        if !self.network_enabled {
+            disallowed.extend(NETWORK_TOOLS.iter().map(|t| t.to_string()));
+            disallowed.extend(NETWORK_COMMANDS.iter().map(|c| format!("Bash({}:*)", c)));
+        }
+        if !disallowed.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.push(disallowed.join(","));
+        }
+
+        args
+    }
+
+    /// Directories the sandboxed process may write to
+    fn writable_paths(&self, project_path: &str, scratch: &SandboxScratch) -> Vec<String> {
+        let mut paths = vec![project_path.to_string()];
+        paths.extend(self.allowed_directories.iter().cloned());
+        // Session transcripts, which opcode reads back; no config lives there
+        if let Some(projects) = claude_projects_dir() {
+            paths.push(projects.to_string_lossy().to_string());
+        }
+        paths.push(scratch.dir.to_string_lossy().to_string());
+        paths.push(std::env::temp_dir().to_string_lossy().to_string());
+        paths
+    }
+
+    /// Paths inside the writable ones that stay read-only, as they configure what runs later
+    fn protected_paths(&self, project_path: &str) -> Vec<String> {
+        std::iter::once(project_path)
+            .chain(self.allowed_directories.iter().map(String::as_str))
+            .map(|dir| Path::new(dir).join(".claude").to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Generate a macOS Seatbelt profile restricting writes to the writable paths
+    pub fn seatbelt_profile(&self, project_path: &str, scratch: &SandboxScratch) -> String {
+        let escape = |path: &str| path.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut profile = String::from("(version 1)\n(allow default)\n(deny file-write*)\n");
+        profile.push_str("(allow file-write*\n");
+        profile.push_str("    (subpath \"/dev\")\n");
+        profile.push_str("    (subpath \"/private/tmp\")\n");
+        profile.push_str("    (subpath \"/private/var/folders\")\n");
+        for path in self.writable_paths(project_path, scratch) {
+            profile.push_str(&format!("    (subpath \"{}\")\n", escape(&path)));
+        }
+        profile.push_str(")\n");
+        // Later rules win, so these carve the protected paths back out
+        profile.push_str("(deny file-write*\n");
+        for path in self.protected_paths(project_path) {
+            profile.push_str(&format!("    (subpath \"{}\")\n", escape(&path)));
+        }
+        profile.push_str(")\n");
+        profile
+    }
+
+    /// Wrap the Claude invocation in the OS sandbox. Returns the program to run and its
+    /// arguments, or an error when no OS sandbox is available, as running the agent
+    /// unconfined would silently drop the profile's file restrictions.
+    pub fn wrap_command(
+        &self,
+        claude_path: &str,
+        args: Vec<String>,
+        project_path: &str,
+        scratch: &SandboxScratch,
+    ) -> Result<(String, Vec<String>), String> {
+        if cfg!(target_os = "macos") {
+            if !Path::new("/usr/bin/sandbox-exec").exists() {
+                return Err(
+                    "sandbox-exec is not available; refusing to run without the sandbox"
+                        .to_string(),
+                );
+            }
+            let mut wrapped = vec![
+                "-p".to_string(),
+                self.seatbelt_profile(project_path, scratch),
+                claude_path.to_string(),
+            ];
+            wrapped.extend(args);
+            return Ok(("/usr/bin/sandbox-exec".to_string(), wrapped));
+        }
+
+        if cfg!(target_os = "linux") {
+            if let Ok(bwrap) = which::which("bwrap") {
+                let mut wrapped = vec![
+                    "--ro-bind".to_string(),
+                    "/".to_string(),
+                    "/".to_string(),
+                    "--dev".to_string(),
+                    "/dev".to_string(),
+                    "--proc".to_string(),
+                    "/proc".to_string(),
+                    "--die-with-parent".to_string(),
+                ];
+                for path in self.writable_paths(project_path, scratch) {
+                    if Path::new(&path).exists() {
+                        wrapped.push("--bind".to_string());
+                        wrapped.push(path.clone());
+                        wrapped.push(path);
+                    }
+                }
+                // Later mounts win, so these carve the protected paths back out
+                for path in self.protected_paths(project_path) {
+                    if Path::new(&path).exists() {
+                        wrapped.push("--ro-bind".to_string());
+                        wrapped.push(path.clone());
+                        wrapped.push(path);
+                    }
+                }
+                wrapped.push("--".to_string());
+                wrapped.push(claude_path.to_string());
+                wrapped.extend(args);
+                return Ok((bwrap.to_string_lossy().to_string(), wrapped));
+            }
+            return Err("bwrap is not installed; refusing to run without the sandbox".to_string());
+        }
+
+        Err("Sandbox profiles are only supported on macOS and Linux".to_string())
+    }
+}
+
+fn claude_projects_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join("projects"))
+}
+
+/// A sandboxed run's own Claude config directory, seeded with a copy of the global config
+/// and deleted when dropped. Keep it alive until the run has exited.
+#[derive(Debug)]
+pub struct SandboxScratch {
+    dir: PathBuf,
+}
+
+impl SandboxScratch {
+    pub fn create(run_id: i64) -> Result<Self, String> {
+        let dir = std::env::temp_dir()
+            .join("opcode-sandbox")
+            .join(format!("run-{}-{}", run_id, uuid::Uuid::new_v4()));
+        let scratch = Self { dir };
+        // Private to the user, as the copied config includes credentials
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        for sub in ["config", "cache", "npm"] {
+            builder
+                .create(scratch.dir.join(sub))
+                .map_err(|e| format!("Failed to create sandbox scratch directory: {}", e))?;
+        }
+
+        if let Some(home) = dirs::home_dir() {
+            let config = scratch.config_dir();
+            for name in SCRATCH_CONFIG_FILES {
+                let source = home.join(".claude").join(name);
+                if source.is_file() {
+                    std::fs::copy(&source, config.join(name))
+                        .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+                }
+            }
+            let global = home.join(".claude.json");
+            if global.is_file() {
+                std::fs::copy(&global, config.join(".claude.json"))
+                    .map_err(|e| format!("Failed to copy {}: {}", global.display(), e))?;
+            }
+            // Transcripts still land where opcode reads them
+            #[cfg(unix)]
+            if let Some(projects) = claude_projects_dir().filter(|dir| dir.is_dir()) {
+                std::os::unix::fs::symlink(&projects, config.join("projects"))
+                    .map_err(|e| format!("Failed to link session transcripts: {}", e))?;
+            }
+        }
+
+        Ok(scratch)
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.dir.join("config")
+    }
+
+    /// Environment pointing Claude and common tool caches at the scratch directory
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("CLAUDE_CONFIG_DIR", self.config_dir().to_string_lossy().to_string()),
+            ("XDG_CACHE_HOME", self.dir.join("cache").to_string_lossy().to_string()),
+            ("npm_config_cache", self.dir.join("npm").to_string_lossy().to_string()),
+        ]
+    }
+}
+
+impl Drop for SandboxScratch {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            log::warn!("Failed to remove sandbox scratch {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+/// Detects sandbox violations in a run's output
+#[derive(Debug, Default)]
+pub struct SandboxViolationDetector;
+
+impl SandboxViolationDetector {
+    /// Check a stream-json stdout line for a tool call rejected by the profile
+    pub fn check_stdout(&self, line: &str) -> Option<String> {
+        let msg = serde_json::from_str::<serde_json::Value>(line).ok()?;
+        let content = msg.get("message")?.get("content")?.as_array()?;
+
+        for item in content {
+            if item.get("type").and_then(|t| t.as_str()) != Some("tool_result")
+                || item.get("is_error").and_then(|e| e.as_bool()) != Some(true)
+            {
+                continue;
+            }
+            let text = match item.get("content") {
+                Some(serde_json::Value::String(text)) => text.clone(),
+                Some(other) => other.to_string(),
+                None => continue,
+            };
+            if is_violation_text(&text) {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    /// Check a stderr line for an OS sandbox denial
+    pub fn check_stderr(&self, line: &str) -> Option<String> {
+        if is_violation_text(line) {
+            Some(line.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+/// Match only real denials: EPERM/EROFS from bwrap, `deny(1)` reports from
+/// sandbox-exec, and the CLI's result for a disallowed tool
+fn is_violation_text(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    lower.contains("operation not permitted")
+        || lower.contains("read-only file system")
+        || lower.contains(" deny(1) ")
+        || (lower.contains("permission to use") && lower.contains("has been denied"))
+}
+
+/// Create sandbox tables
+pub fn init_sandbox_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sandbox_profiles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            allowed_directories TEXT NOT NULL DEFAULT '[]',
+            network_enabled BOOLEAN NOT NULL DEFAULT 1,
+            denied_commands TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_sandbox_profiles (
+            agent_id INTEGER PRIMARY KEY,
+            profile_id INTEGER NOT NULL,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+            FOREIGN KEY (profile_id) REFERENCES sandbox_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_sandbox_violations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            detail TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) const PROFILE_COLUMNS: &str =
+    "id, name, allowed_directories, network_enabled, denied_commands, created_at";
+
+pub(crate) fn profile_from_row(row: &rusqlite::Row) -> rusqlite::Result<SandboxProfile> {
+    let allowed: String = row.get(2)?;
+    let denied: String = row.get(4)?;
+    Ok(SandboxProfile {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        allowed_directories: serde_json::from_str(&allowed).unwrap_or_default(),
+        network_enabled: row.get(3)?,
+        denied_commands: serde_json::from_str(&denied).unwrap_or_default(),
+        created_at: row.get(5)?,
+    })
+}
+
+fn validate_profile(profile: &SandboxProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    for dir in &profile.allowed_directories {
+        if !std::path::Path::new(dir).is_absolute() {
+            return Err(format!("Allowed directory must be an absolute path: {}", dir));
+        }
+    }
+    Ok(())
+}
+
+/// Load the sandbox profile attached to an agent, if any
+pub fn load_agent_sandbox_profile(
+    conn: &Connection,
+    agent_id: i64,
+) -> SqliteResult<Option<SandboxProfile>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM sandbox_profiles WHERE id = (SELECT profile_id FROM agent_sandbox_profiles WHERE agent_id = ?1)",
+            PROFILE_COLUMNS
+        ),
+        params![agent_id],
+        profile_from_row,
+    )
+    .optional()
+}
+
+/// Record a sandbox violation for a run
+pub fn record_violation(conn: &Connection, run_id: i64, source: &str, detail: &str) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO agent_run_sandbox_violations (run_id, source, detail) VALUES (?1, ?2, ?3)",
+        params![run_id, source, detail],
+    )?;
+    Ok(())
+}
+
+/// List all sandbox profiles
+#[tauri::command]
+pub async fn list_sandbox_profiles(db: State<'_, AgentDb>) -> Result<Vec<SandboxProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM sandbox_profiles ORDER BY name ASC",
+            PROFILE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let profiles = stmt
+        .query_map([], profile_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(profiles)
+}
+
+/// Create a sandbox profile
+#[tauri::command]
+pub async fn create_sandbox_profile(
+    db: State<'_, AgentDb>,
+    profile: SandboxProfile,
+) -> Result<SandboxProfile, String> {
+    validate_profile(&profile)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO sandbox_profiles (name, allowed_directories, network_enabled, denied_commands) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            profile.name,
+            serde_json::to_string(&profile.allowed_directories).map_err(|e| e.to_string())?,
+            profile.network_enabled,
+            serde_json::to_string(&profile.denied_commands).map_err(|e| e.to_string())?
+        ],
+    )
+    .map_err(|e| format!("Failed to create sandbox profile: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        &format!("SELECT {} FROM sandbox_profiles WHERE id = ?1", PROFILE_COLUMNS),
+        params![id],
+        profile_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Update a sandbox profile
+#[tauri::command]
+pub async fn update_sandbox_profile(
+    db: State<'_, AgentDb>,
+    profile: SandboxProfile,
+) -> Result<SandboxProfile, String> {
+    let id = profile.id.ok_or("Profile id is required")?;
+    validate_profile(&profile)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let rows = conn
+        .execute(
+            "UPDATE sandbox_profiles SET name = ?1, allowed_directories = ?2, network_enabled = ?3, denied_commands = ?4 WHERE id = ?5",
+            params![
+                profile.name,
+                serde_json::to_string(&profile.allowed_directories).map_err(|e| e.to_string())?,
+                profile.network_enabled,
+                serde_json::to_string(&profile.denied_commands).map_err(|e| e.to_string())?,
+                id
+            ],
+        )
+        .map_err(|e| format!("Failed to update sandbox profile: {}", e))?;
+    if rows == 0 {
+        return Err(format!("Sandbox profile {} not found", id));
+    }
+
+    conn.query_row(
+        &format!("SELECT {} FROM sandbox_profiles WHERE id = ?1", PROFILE_COLUMNS),
+        params![id],
+        profile_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Delete a sandbox profile and detach it from agents
+#[tauri::command]
+pub async fn delete_sandbox_profile(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM agent_sandbox_profiles WHERE profile_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM sandbox_profiles WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete sandbox profile: {}", e))?;
+    Ok(())
+}
+
+/// Get the sandbox profile attached to an agent
+#[tauri::command]
+pub async fn get_agent_sandbox_profile(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Option<SandboxProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_agent_sandbox_profile(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Attach a sandbox profile to an agent, or detach it with `None`
+#[tauri::command]
+pub async fn set_agent_sandbox_profile(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    profile_id: Option<i64>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match profile_id {
+        Some(profile_id) => conn.execute(
+            "INSERT OR REPLACE INTO agent_sandbox_profiles (agent_id, profile_id) VALUES (?1, ?2)",
+            params![agent_id, profile_id],
+        ),
+        None => conn.execute(
+            "DELETE FROM agent_sandbox_profiles WHERE agent_id = ?1",
+            params![agent_id],
+        ),
+    }
+    .map_err(|e| format!("Failed to set agent sandbox profile: {}", e))?;
+    Ok(())
+}
+
+/// List sandbox violations recorded for a run
+#[tauri::command]
+pub async fn get_run_sandbox_violations(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<SandboxViolation>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, run_id, source, detail, created_at FROM agent_run_sandbox_violations
+             WHERE run_id = ?1 ORDER BY id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let violations = stmt
+        .query_map(params![run_id], |row| {
+            Ok(SandboxViolation {
+                id: row.get(0)?,
+                run_id: row.get(1)?,
+                source: row.get(2)?,
+                detail: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> SandboxProfile {
+        SandboxProfile {
+            id: None,
+            name: "strict".to_string(),
+            allowed_directories: vec!["/data/shared".to_string()],
+            network_enabled: false,
+            denied_commands: vec!["rm".to_string(), "git push".to_string()],
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_claude_args() {
+        let args = profile().claude_args();
+        assert_eq!(args[0..2], ["--add-dir", "/data/shared"]);
+        assert_eq!(args[2], "--disallowedTools");
+        let disallowed: Vec<&str> = args[3].split(',').collect();
+        assert!(disallowed.contains(&"Bash(rm:*)"));
+        assert!(disallowed.contains(&"Bash(git push:*)"));
+        assert!(disallowed.contains(&"WebFetch"));
+        assert!(disallowed.contains(&"Bash(curl:*)"));
+    }
+
+    #[test]
+    fn test_network_enabled_only_denies_commands() {
+        let mut profile = profile();
+        profile.network_enabled = true;
+        profile.allowed_directories.clear();
+        assert_eq!(
+            profile.claude_args(),
+            vec!["--disallowedTools", "Bash(rm:*),Bash(git push:*)"]
+        );
+    }
+
+    #[test]
+    fn test_seatbelt_profile_allows_project_writes() {
+        let scratch = SandboxScratch::create(1).unwrap();
+        let sbpl = profile().seatbelt_profile("/work/project", &scratch);
+        assert!(sbpl.contains("(deny file-write*)"));
+        assert!(sbpl.contains("(subpath \"/work/project\")"));
+        assert!(sbpl.contains("(subpath \"/data/shared\")"));
+        // Claude's global config and the project's hooks stay read-only
+        let allowed = sbpl.split("(deny file-write*\n").next().unwrap();
+        assert!(!allowed.contains("/.claude\""));
+        assert!(!allowed.contains(".claude.json"));
+        assert!(sbpl.ends_with("(subpath \"/data/shared/.claude\")\n)\n"));
+    }
+
+    #[test]
+    fn test_scratch_config_is_removed_on_drop() {
+        let scratch = SandboxScratch::create(2).unwrap();
+        let dir = scratch.config_dir();
+        assert!(dir.is_dir());
+        assert!(scratch
+            .env()
+            .contains(&("CLAUDE_CONFIG_DIR", dir.to_string_lossy().to_string())));
+        drop(scratch);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_detects_denied_tool_result() {
+        let detector = SandboxViolationDetector;
+        let line = serde_json::json!({
+            "type": "user",
+            "message": {"content": [{
+                "type": "tool_result",
+                "is_error": true,
+                "content": "Permission to use Bash with command rm -rf build has been denied."
+            }]}
+        })
+        .to_string();
+        assert!(detector.check_stdout(&line).is_some());
+        assert!(detector
+            .check_stderr("touch: /etc/x: Operation not permitted")
+            .is_some());
+        assert!(detector.check_stderr("compiling...").is_none());
+    }
+
+    #[test]
+    fn test_ordinary_text_is_not_a_violation() {
+        assert!(is_violation_text(
+            "Sandbox: bash(4121) deny(1) file-write-create /etc/hosts"
+        ));
+        assert!(!is_violation_text("Updated the sandbox profile docs"));
+        assert!(!is_violation_text("Ask the user for permission to use the API key"));
+        assert!(!is_violation_text("Access has been denied by the remote server"));
+    }
+}
@@ -0,0 +1,348 @@
+#![allow(dead_code)]
+
+//! Diffing of two session transcripts that share history, such as two forks of one
+//! conversation. The shared prefix is aligned first, then each branch after the divergence
+//! point is summarised side by side (assistant responses, tool calls, tool errors, tokens) so
+//! the user can judge which fork went better.
+//!
+//! Messages are matched by `uuid` or, when a fork rewrote the ids, by role and content; tool
+//! calls compare by name and input since their ids differ between forks.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use super::claude::is_valid_uuid;
+use super::session_merge::project_dir;
+
+/// How two transcripts relate after alignment
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffRelation {
+    Identical,
+    /// Session A stops where session B carries on
+    APrefixOfB,
+    /// Session B stops where session A carries on
+    BPrefixOfA,
+    /// Both carry on differently after a shared prefix
+    Diverged,
+    /// Not even the first message matches
+    Unrelated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolCall {
+    pub name: String,
+    pub input: Value,
+}
+
+/// A user or assistant message of a transcript
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranscriptEntry {
+    pub uuid: Option<String>,
+    /// `user` or `assistant`
+    pub role: String,
+    pub timestamp: Option<String>,
+    /// Text blocks joined by newlines
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+    /// Tool results in this message that reported an error
+    pub tool_errors: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TranscriptEntry {
+    fn from_line(value: &Value) -> Option<Self> {
+        let role = value.get("type").and_then(Value::as_str)?;
+        if role != "user" && role != "assistant" {
+            return None;
+        }
+        if value.get("isMeta").and_then(Value::as_bool) == Some(true) {
+            return None;
+        }
+        let message = value.get("message")?;
+        let mut text = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut tool_errors = 0;
+        match message.get("content") {
+            Some(Value::String(content)) => text.push(content.clone()),
+            Some(Value::Array(blocks)) => {
+                for block in blocks {
+                    match block.get("type").and_then(Value::as_str) {
+                        Some("text") => {
+                            if let Some(content) = block.get("text").and_then(Value::as_str) {
+                                text.push(content.to_string());
+                            }
+                        }
+                        Some("tool_use") => tool_calls.push(ToolCall {
+                            name: block
+                                .get("name")
+                                .and_then(Value::as_str)
+                                .unwrap_or_default()
+                                .to_string(),
+                            input: block.get("input").cloned().unwrap_or(Value::Null),
+                        }),
+                        Some("tool_result")
+                            if block.get("is_error").and_then(Value::as_bool) == Some(true) =>
+                        {
+                            tool_errors += 1;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        let usage = message.get("usage");
+        let tokens = |key: &str| {
+            usage
+                .and_then(|usage| usage.get(key))
+                .and_then(Value::as_u64)
+                .unwrap_or(0)
+        };
+        let string = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+        Some(Self {
+            uuid: string("uuid"),
+            role: role.to_string(),
+            timestamp: string("timestamp"),
+            text: text.join("\n"),
+            tool_calls,
+            tool_errors,
+            input_tokens: tokens("input_tokens"),
+            output_tokens: tokens("output_tokens"),
+        })
+    }
+
+    /// The same message in both transcripts
+    fn matches(&self, other: &Self) -> bool {
+        if self.uuid.is_some() && self.uuid == other.uuid {
+            return true;
+        }
+        self.role == other.role
+            && self.text.trim() == other.text.trim()
+            && self.tool_calls == other.tool_calls
+    }
+}
+
+/// One side of the transcript after the divergence point
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BranchSummary {
+    pub session_id: String,
+    pub messages: Vec<TranscriptEntry>,
+    pub assistant_responses: usize,
+    pub tool_calls: usize,
+    pub tool_errors: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Tools this branch called that the other didn't
+    pub tools_only_here: Vec<String>,
+}
+
+impl BranchSummary {
+    fn new(session_id: &str, messages: &[TranscriptEntry]) -> Self {
+        let mut summary = Self {
+            session_id: session_id.to_string(),
+            messages: messages.to_vec(),
+            ..Default::default()
+        };
+        for entry in messages {
+            if entry.role == "assistant" && !entry.text.trim().is_empty() {
+                summary.assistant_responses += 1;
+            }
+            summary.tool_calls += entry.tool_calls.len();
+            summary.tool_errors += entry.tool_errors;
+            summary.input_tokens += entry.input_tokens;
+            summary.output_tokens += entry.output_tokens;
+        }
+        summary
+    }
+
+    fn tool_names(&self) -> BTreeSet<String> {
+        self.messages
+            .iter()
+            .flat_map(|entry| entry.tool_calls.iter().map(|call| call.name.clone()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionDiff {
+    pub relation: DiffRelation,
+    /// Messages both transcripts share before diverging
+    pub shared_messages: usize,
+    /// Last shared message, the point the forks split from
+    pub last_shared_uuid: Option<String>,
+    pub a: BranchSummary,
+    pub b: BranchSummary,
+}
+
+/// Read the user and assistant messages of a transcript
+pub(super) fn read_transcript(path: &Path) -> Result<Vec<TranscriptEntry>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read session {}: {}", path.display(), e))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|value| TranscriptEntry::from_line(&value))
+        .collect())
+}
+
+/// Align the shared prefix of two transcripts and summarise what follows on each side
+pub fn diff_transcripts(
+    session_a: &str,
+    a: &[TranscriptEntry],
+    session_b: &str,
+    b: &[TranscriptEntry],
+) -> SessionDiff {
+    let shared = a
+        .iter()
+        .zip(b.iter())
+        .take_while(|(a, b)| a.matches(b))
+        .count();
+    let relation = match (shared == a.len(), shared == b.len()) {
+        (true, true) => DiffRelation::Identical,
+        (true, false) => DiffRelation::APrefixOfB,
+        (false, true) => DiffRelation::BPrefixOfA,
+        (false, false) if shared == 0 => DiffRelation::Unrelated,
+        (false, false) => DiffRelation::Diverged,
+    };
+
+    let mut branch_a = BranchSummary::new(session_a, &a[shared..]);
+    let mut branch_b = BranchSummary::new(session_b, &b[shared..]);
+    let (tools_a, tools_b) = (branch_a.tool_names(), branch_b.tool_names());
+    branch_a.tools_only_here = tools_a.difference(&tools_b).cloned().collect();
+    branch_b.tools_only_here = tools_b.difference(&tools_a).cloned().collect();
+
+    SessionDiff {
+        relation,
+        shared_messages: shared,
+        last_shared_uuid: shared.checked_sub(1).and_then(|last| a[last].uuid.clone()),
+        a: branch_a,
+        b: branch_b,
+    }
+}
+
+/// Compare two sessions of a project, e.g. two forks of one conversation
+#[tauri::command]
+pub async fn diff_sessions(
+    project_id: String,
+    session_a: String,
+    session_b: String,
+) -> Result<SessionDiff, String> {
+    let (dir, _) = project_dir(&project_id)?;
+    for id in [&session_a, &session_b] {
+        if !is_valid_uuid(id) {
+            return Err(format!("Invalid session id: {}", id));
+        }
+    }
+    tokio::task::spawn_blocking(move || {
+        let a = read_transcript(&dir.join(format!("{}.jsonl", session_a)))?;
+        let b = read_transcript(&dir.join(format!("{}.jsonl", session_b)))?;
+        Ok(diff_transcripts(&session_a, &a, &session_b, &b))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(uuid: &str, text: &str) -> Value {
+        json!({"type": "user", "uuid": uuid, "message": {"role": "user", "content": text}})
+    }
+
+    fn assistant(uuid: &str, text: &str, tool: Option<(&str, Value)>) -> Value {
+        let mut content = vec![json!({"type": "text", "text": text})];
+        if let Some((name, input)) = tool {
+            content.push(json!({"type": "tool_use", "id": format!("toolu_{}", uuid), "name": name, "input": input}));
+        }
+        json!({
+            "type": "assistant",
+            "uuid": uuid,
+            "message": {"role": "assistant", "content": content, "usage": {"input_tokens": 10, "output_tokens": 5}}
+        })
+    }
+
+    fn entries(values: &[Value]) -> Vec<TranscriptEntry> {
+        values
+            .iter()
+            .filter_map(TranscriptEntry::from_line)
+            .collect()
+    }
+
+    #[test]
+    fn test_forks_align_on_content_and_report_divergence() {
+        let shared = [
+            user("a1", "Fix the failing test"),
+            assistant(
+                "a2",
+                "Looking at it",
+                Some(("Read", json!({"file_path": "lib.rs"}))),
+            ),
+        ];
+        let mut a = shared.to_vec();
+        a.push(assistant(
+            "a3",
+            "Patched the assertion",
+            Some(("Edit", json!({"file_path": "lib.rs"}))),
+        ));
+        // The fork rewrote ids but kept the history
+        let mut b = vec![
+            user("b1", "Fix the failing test"),
+            assistant(
+                "b2",
+                "Looking at it",
+                Some(("Read", json!({"file_path": "lib.rs"}))),
+            ),
+        ];
+        b.push(assistant(
+            "b3",
+            "Running the tests first",
+            Some(("Bash", json!({"command": "cargo test"}))),
+        ));
+        b.push(json!({
+            "type": "user",
+            "uuid": "b4",
+            "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "toolu_b3", "is_error": true, "content": "failed"}]}
+        }));
+
+        let diff = diff_transcripts("A", &entries(&a), "B", &entries(&b));
+        assert_eq!(diff.relation, DiffRelation::Diverged);
+        assert_eq!(diff.shared_messages, 2);
+        assert_eq!(diff.last_shared_uuid.as_deref(), Some("a2"));
+        assert_eq!(diff.a.messages.len(), 1);
+        assert_eq!(diff.a.tools_only_here, vec!["Edit"]);
+        assert_eq!(diff.b.messages.len(), 2);
+        assert_eq!(diff.b.tools_only_here, vec!["Bash"]);
+        assert_eq!(diff.b.tool_errors, 1);
+        assert_eq!(diff.b.output_tokens, 5);
+    }
+
+    #[test]
+    fn test_prefix_identical_and_unrelated() {
+        let a = entries(&[user("m1", "hi"), assistant("m2", "hello", None)]);
+        let b = entries(&[user("m1", "hi")]);
+        assert_eq!(
+            diff_transcripts("A", &a, "B", &a).relation,
+            DiffRelation::Identical
+        );
+        assert_eq!(
+            diff_transcripts("A", &a, "B", &b).relation,
+            DiffRelation::BPrefixOfA
+        );
+        assert_eq!(
+            diff_transcripts("A", &b, "B", &a).relation,
+            DiffRelation::APrefixOfB
+        );
+
+        let other = entries(&[user("x1", "something else")]);
+        let diff = diff_transcripts("A", &a, "B", &other);
+        assert_eq!(diff.relation, DiffRelation::Unrelated);
+        assert_eq!(diff.last_shared_uuid, None);
+    }
+}
@@ -0,0 +1,910 @@
+#![allow(dead_code)]
+
+//! Persistent index of session transcripts. For every JSONL file under `~/.claude/projects`
+//! the store keeps each message's byte offsets, uuid, timestamp, model and token usage, plus
+//! its text for full-text search. Usage stats, transcript search and the windowed fetch API
+//! read the index instead of parsing the files, so a cold start doesn't re-read gigabytes.
+//!
+//! Transcripts are append-only: when a file grows only the new lines are parsed. A file that
+//! shrank or was rewritten is indexed again from scratch, and files that disappeared are
+//! dropped from the index.
+//!
+//! The store lives in the app database but is used through its own connection (see
+//! [`open_store`]), so indexing a large file doesn't hold the shared connection.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::UNIX_EPOCH;
+
+use super::usage::{cost_for_tokens, UsageEntry};
+
+/// Characters of a message's text kept for search
+const MAX_SEARCH_TEXT: usize = 8 * 1024;
+
+/// Input keys that name the file a tool works on
+const TOOL_FILE_KEYS: &[&str] = &["file_path", "notebook_path"];
+
+/// Database the store lives in, set when the app or the headless CLI opens it
+static STORE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Serializes indexing, so two callers never append the same lines
+static INDEXING: Mutex<()> = Mutex::new(());
+
+pub fn init_session_index_tables(conn: &Connection) -> SqliteResult<()> {
+    // Tool calls were first recorded without their results; that table is rebuilt
+    let tool_uses_current: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('session_index_tool_uses')
+         WHERE name = 'tool_use_id'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !tool_uses_current {
+        conn.execute("DROP TABLE IF EXISTS session_index_tool_uses", [])?;
+    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_index_files (
+            path TEXT PRIMARY KEY,
+            project_dir TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            modified_ms INTEGER NOT NULL,
+            indexed_to INTEGER NOT NULL,
+            message_count INTEGER NOT NULL DEFAULT 0,
+            earliest_timestamp TEXT,
+            cwd TEXT,
+            cwd_position INTEGER
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_index_messages (
+            path TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            start_offset INTEGER NOT NULL,
+            end_offset INTEGER NOT NULL,
+            uuid TEXT,
+            timestamp TEXT,
+            session_id TEXT,
+            message_id TEXT,
+            request_id TEXT,
+            model TEXT,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL,
+            PRIMARY KEY (path, position)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_index_messages_uuid
+         ON session_index_messages(path, uuid)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS session_index_text
+         USING fts5(text, path UNINDEXED, position UNINDEXED)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_index_tool_uses (
+            path TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            tool_name TEXT NOT NULL,
+            file_path TEXT,
+            tool_use_id TEXT,
+            is_error BOOLEAN,
+            result_timestamp TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_index_tool_uses_path
+         ON session_index_tool_uses(path)",
+        [],
+    )?;
+    if !tool_uses_current {
+        // Transcripts indexed before tool calls were recorded are indexed again from scratch
+        conn.execute("DELETE FROM session_index_messages", [])?;
+        conn.execute("DELETE FROM session_index_text", [])?;
+        conn.execute("DELETE FROM session_index_files", [])?;
+    }
+    Ok(())
+}
+
+/// Use the database at `db_path` for the store
+pub fn set_store_path(db_path: &Path) {
+    let _ = STORE_PATH.set(db_path.to_path_buf());
+}
+
+/// A connection to the store, if the app database has been opened in this process
+pub fn open_store() -> Option<Connection> {
+    let conn = Connection::open(STORE_PATH.get()?).ok()?;
+    conn.busy_timeout(std::time::Duration::from_secs(5)).ok()?;
+    Some(conn)
+}
+
+/// What the store knows about one transcript
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexedFile {
+    pub size_bytes: i64,
+    pub modified_ms: i64,
+    /// Bytes indexed; a trailing partial line is left for the next update
+    pub indexed_to: i64,
+    pub message_count: i64,
+    pub earliest_timestamp: Option<String>,
+    /// First `cwd` in the file and the message it appeared on
+    pub cwd: Option<(String, i64)>,
+}
+
+/// The parts of a line the store keeps
+#[derive(Debug, Default)]
+struct IndexedLine {
+    uuid: Option<String>,
+    timestamp: Option<String>,
+    session_id: Option<String>,
+    message_id: Option<String>,
+    request_id: Option<String>,
+    model: Option<String>,
+    tokens: [i64; 4],
+    cost_usd: Option<f64>,
+    cwd: Option<String>,
+    text: String,
+    tool_uses: Vec<ToolUse>,
+    tool_results: Vec<ToolResult>,
+}
+
+/// A tool call in an assistant message, with the file it works on if it names one
+#[derive(Debug, Clone, PartialEq)]
+struct ToolUse {
+    id: Option<String>,
+    name: String,
+    file_path: Option<String>,
+}
+
+/// The outcome of a tool call, reported in a later user message
+#[derive(Debug, Clone, PartialEq)]
+struct ToolResult {
+    tool_use_id: String,
+    is_error: bool,
+}
+
+fn parse_line(content: &[u8]) -> IndexedLine {
+    let Ok(json) = serde_json::from_slice::<Value>(content) else {
+        return IndexedLine::default();
+    };
+    let string =
+        |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+    let message = json.get("message");
+    let usage = message.and_then(|message| message.get("usage"));
+    let count = |key: &str| {
+        usage
+            .and_then(|usage| usage.get(key))
+            .and_then(Value::as_u64)
+            .unwrap_or(0) as i64
+    };
+    IndexedLine {
+        uuid: string(&json, "uuid"),
+        timestamp: string(&json, "timestamp"),
+        session_id: string(&json, "sessionId"),
+        message_id: message.and_then(|message| string(message, "id")),
+        request_id: string(&json, "requestId"),
+        model: message.and_then(|message| string(message, "model")),
+        tokens: [
+            count("input_tokens"),
+            count("output_tokens"),
+            count("cache_creation_input_tokens"),
+            count("cache_read_input_tokens"),
+        ],
+        cost_usd: json.get("costUSD").and_then(Value::as_f64),
+        cwd: string(&json, "cwd"),
+        text: message.map(message_text).unwrap_or_default(),
+        tool_uses: message.map(tool_uses).unwrap_or_default(),
+        tool_results: message.map(tool_results).unwrap_or_default(),
+    }
+}
+
+/// Content blocks of a message with the given type
+fn blocks_of<'a>(message: &'a Value, kind: &'a str) -> impl Iterator<Item = &'a Value> {
+    message
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(move |block| block.get("type").and_then(Value::as_str) == Some(kind))
+}
+
+fn tool_uses(message: &Value) -> Vec<ToolUse> {
+    blocks_of(message, "tool_use")
+        .filter_map(|block| {
+            let name = block.get("name").and_then(Value::as_str)?;
+            let input = block.get("input");
+            let file_path = TOOL_FILE_KEYS.iter().find_map(|key| {
+                input
+                    .and_then(|input| input.get(*key))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+            Some(ToolUse {
+                id: block.get("id").and_then(Value::as_str).map(str::to_string),
+                name: name.to_string(),
+                file_path,
+            })
+        })
+        .collect()
+}
+
+fn tool_results(message: &Value) -> Vec<ToolResult> {
+    blocks_of(message, "tool_result")
+        .filter_map(|block| {
+            Some(ToolResult {
+                tool_use_id: block.get("tool_use_id").and_then(Value::as_str)?.to_string(),
+                is_error: block
+                    .get("is_error")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// Text of a message's string content or of its text blocks, cut to the search limit
+fn message_text(message: &Value) -> String {
+    let text = match message.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    text.chars().take(MAX_SEARCH_TEXT).collect()
+}
+
+fn file_row(conn: &Connection, path: &str) -> SqliteResult<Option<IndexedFile>> {
+    conn.query_row(
+        "SELECT size_bytes, modified_ms, indexed_to, message_count, earliest_timestamp, cwd,
+                cwd_position
+         FROM session_index_files WHERE path = ?1",
+        params![path],
+        |row| {
+            let cwd: Option<String> = row.get(5)?;
+            let cwd_position: Option<i64> = row.get(6)?;
+            Ok(IndexedFile {
+                size_bytes: row.get(0)?,
+                modified_ms: row.get(1)?,
+                indexed_to: row.get(2)?,
+                message_count: row.get(3)?,
+                earliest_timestamp: row.get(4)?,
+                cwd: cwd.zip(cwd_position),
+            })
+        },
+    )
+    .optional()
+}
+
+fn forget_file(conn: &Connection, path: &str) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM session_index_messages WHERE path = ?1",
+        params![path],
+    )?;
+    conn.execute(
+        "DELETE FROM session_index_text WHERE path = ?1",
+        params![path],
+    )?;
+    conn.execute(
+        "DELETE FROM session_index_tool_uses WHERE path = ?1",
+        params![path],
+    )?;
+    conn.execute(
+        "DELETE FROM session_index_files WHERE path = ?1",
+        params![path],
+    )?;
+    Ok(())
+}
+
+/// Whether `offset` is the start of the file or just after a newline, as it is in a file
+/// that was only appended to since it was indexed
+fn ends_line_at(file: &mut File, offset: u64) -> std::io::Result<bool> {
+    if offset == 0 {
+        return Ok(true);
+    }
+    let mut byte = [0u8];
+    file.seek(SeekFrom::Start(offset - 1))?;
+    file.read_exact(&mut byte)?;
+    Ok(byte[0] == b'\n')
+}
+
+/// Bring the index of the transcript at `path` up to date, parsing only appended lines.
+/// `project_dir` is the name of the project directory the file is under.
+pub fn index_file(
+    conn: &Connection,
+    path: &Path,
+    project_dir: &str,
+) -> Result<IndexedFile, String> {
+    let _indexing = INDEXING.lock().unwrap_or_else(|e| e.into_inner());
+    let key = path.to_string_lossy().to_string();
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", key, e))?;
+    let size = metadata.len() as i64;
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or(0);
+
+    let stored = file_row(conn, &key).map_err(|e| e.to_string())?;
+    if let Some(stored) = &stored {
+        if stored.size_bytes == size && stored.modified_ms == modified_ms {
+            return Ok(stored.clone());
+        }
+    }
+
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", key, e))?;
+    let mut indexed = stored.unwrap_or_default();
+    let appended = size >= indexed.indexed_to
+        && ends_line_at(&mut file, indexed.indexed_to as u64).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    if !appended {
+        forget_file(&tx, &key).map_err(|e| e.to_string())?;
+        indexed = IndexedFile::default();
+    }
+
+    file.seek(SeekFrom::Start(indexed.indexed_to as u64))
+        .map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(file);
+    let mut offset = indexed.indexed_to;
+    let mut line = Vec::new();
+    {
+        let mut insert_message = tx
+            .prepare(
+                "INSERT OR REPLACE INTO session_index_messages (
+                    path, position, start_offset, end_offset, uuid, timestamp, session_id,
+                    message_id, request_id, model, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_read_tokens, cost_usd
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut insert_text = tx
+            .prepare("INSERT INTO session_index_text (text, path, position) VALUES (?1, ?2, ?3)")
+            .map_err(|e| e.to_string())?;
+        let mut insert_tool_use = tx
+            .prepare(
+                "INSERT INTO session_index_tool_uses (path, position, tool_name, file_path, tool_use_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut record_tool_result = tx
+            .prepare(
+                "UPDATE session_index_tool_uses SET is_error = ?1, result_timestamp = ?2
+                 WHERE path = ?3 AND tool_use_id = ?4",
+            )
+            .map_err(|e| e.to_string())?;
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|e| e.to_string())?;
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+            let content = line.trim_ascii_end();
+            if !content.trim_ascii_start().is_empty() {
+                let position = indexed.message_count;
+                let parsed = parse_line(content);
+                insert_message
+                    .execute(params![
+                        key,
+                        position,
+                        offset,
+                        offset + content.len() as i64,
+                        parsed.uuid,
+                        parsed.timestamp,
+                        parsed.session_id,
+                        parsed.message_id,
+                        parsed.request_id,
+                        parsed.model,
+                        parsed.tokens[0],
+                        parsed.tokens[1],
+                        parsed.tokens[2],
+                        parsed.tokens[3],
+                        parsed.cost_usd,
+                    ])
+                    .map_err(|e| e.to_string())?;
+                if !parsed.text.trim().is_empty() {
+                    insert_text
+                        .execute(params![parsed.text, key, position])
+                        .map_err(|e| e.to_string())?;
+                }
+                for tool_use in &parsed.tool_uses {
+                    insert_tool_use
+                        .execute(params![
+                            key,
+                            position,
+                            tool_use.name,
+                            tool_use.file_path,
+                            tool_use.id
+                        ])
+                        .map_err(|e| e.to_string())?;
+                }
+                for result in &parsed.tool_results {
+                    record_tool_result
+                        .execute(params![
+                            result.is_error,
+                            parsed.timestamp,
+                            key,
+                            result.tool_use_id
+                        ])
+                        .map_err(|e| e.to_string())?;
+                }
+                if let Some(timestamp) = parsed.timestamp {
+                    if indexed
+                        .earliest_timestamp
+                        .as_ref()
+                        .is_none_or(|earliest| timestamp < *earliest)
+                    {
+                        indexed.earliest_timestamp = Some(timestamp);
+                    }
+                }
+                if indexed.cwd.is_none() {
+                    indexed.cwd = parsed.cwd.map(|cwd| (cwd, position));
+                }
+                indexed.message_count += 1;
+            }
+            offset += read as i64;
+        }
+    }
+    indexed.indexed_to = offset;
+    indexed.size_bytes = size;
+    indexed.modified_ms = modified_ms;
+    let (cwd, cwd_position) = indexed.cwd.clone().unzip();
+    tx.execute(
+        "INSERT OR REPLACE INTO session_index_files (
+            path, project_dir, size_bytes, modified_ms, indexed_to, message_count,
+            earliest_timestamp, cwd, cwd_position
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            key,
+            project_dir,
+            indexed.size_bytes,
+            indexed.modified_ms,
+            indexed.indexed_to,
+            indexed.message_count,
+            indexed.earliest_timestamp,
+            cwd,
+            cwd_position,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(indexed)
+}
+
+/// Files are keyed by their canonical path, however the projects directory was reached
+pub(crate) fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Transcripts under the projects directory, each with the name of its project directory
+fn transcripts(projects_dir: &Path) -> Vec<(PathBuf, String)> {
+    let Ok(projects) = std::fs::read_dir(projects_dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for project in projects.flatten() {
+        if !project.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let project_dir = project.file_name().to_string_lossy().to_string();
+        for entry in walkdir::WalkDir::new(project.path())
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
+        {
+            files.push((entry.path().to_path_buf(), project_dir.clone()));
+        }
+    }
+    files
+}
+
+/// Index every transcript under `projects_dir` and drop the ones that are gone
+pub fn index_all(conn: &Connection, projects_dir: &Path) -> Result<usize, String> {
+    let projects_dir = canonical(projects_dir);
+    let files = transcripts(&projects_dir);
+    let mut present = HashSet::new();
+    for (path, project_dir) in &files {
+        match index_file(conn, path, project_dir) {
+            Ok(_) => {
+                present.insert(path.to_string_lossy().to_string());
+            }
+            Err(e) => log::warn!("Failed to index {}: {}", path.display(), e),
+        }
+    }
+
+    let prefix = projects_dir.to_string_lossy().to_string();
+    let stale: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT path FROM session_index_files")
+            .map_err(|e| e.to_string())?;
+        let paths = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        paths
+            .filter_map(Result::ok)
+            .filter(|path| path.starts_with(&prefix) && !present.contains(path))
+            .collect()
+    };
+    for path in stale {
+        forget_file(conn, &path).map_err(|e| e.to_string())?;
+    }
+    Ok(present.len())
+}
+
+/// Usage entries of every transcript under `projects_dir`, read from the index after
+/// updating it. Same results as parsing the files: duplicated messages are counted once,
+/// in the file that started first, and entries are sorted by timestamp.
+pub fn usage_entries(conn: &Connection, projects_dir: &Path) -> Result<Vec<UsageEntry>, String> {
+    index_all(conn, projects_dir)?;
+    read_usage_entries(conn, projects_dir, None)
+}
+
+/// Usage entries of the transcripts of one project directory, from the index as it is
+pub fn project_usage_entries(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_dir: &str,
+) -> Result<Vec<UsageEntry>, String> {
+    read_usage_entries(conn, projects_dir, Some(project_dir))
+}
+
+fn read_usage_entries(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_dir: Option<&str>,
+) -> Result<Vec<UsageEntry>, String> {
+    let prefix = canonical(projects_dir).to_string_lossy().to_string();
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.timestamp, m.model, m.input_tokens, m.output_tokens,
+                    m.cache_creation_tokens, m.cache_read_tokens, m.cost_usd, m.session_id,
+                    m.message_id, m.request_id, m.position, f.project_dir, f.cwd, f.cwd_position
+             FROM session_index_messages m
+             JOIN session_index_files f ON f.path = m.path
+             WHERE substr(f.path, 1, length(?1)) = ?1 AND m.timestamp IS NOT NULL
+               AND (?2 IS NULL OR f.project_dir = ?2)
+               AND (m.input_tokens + m.output_tokens + m.cache_creation_tokens
+                    + m.cache_read_tokens > 0
+                    OR (m.message_id IS NOT NULL AND m.request_id IS NOT NULL))
+             ORDER BY f.earliest_timestamp IS NOT NULL, f.earliest_timestamp, f.path, m.position",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut processed_hashes = HashSet::new();
+    let mut entries = Vec::new();
+    let mut rows = stmt
+        .query(params![prefix, project_dir])
+        .map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let mut read = || -> SqliteResult<Option<UsageEntry>> {
+            let message_id: Option<String> = row.get(8)?;
+            let request_id: Option<String> = row.get(9)?;
+            if let (Some(message_id), Some(request_id)) = (message_id, request_id) {
+                if !processed_hashes.insert(format!("{}:{}", message_id, request_id)) {
+                    return Ok(None);
+                }
+            }
+            let tokens: [u64; 4] = [row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?];
+            if tokens.iter().all(|count| *count == 0) {
+                return Ok(None);
+            }
+            let model: Option<String> = row.get(1)?;
+            let cost_usd: Option<f64> = row.get(6)?;
+            let cost = cost_usd.unwrap_or_else(|| {
+                model
+                    .as_deref()
+                    .map(|model| cost_for_tokens(model, tokens[0], tokens[1], tokens[2], tokens[3]))
+                    .unwrap_or(0.0)
+            });
+            let position: i64 = row.get(10)?;
+            let project_dir: String = row.get(11)?;
+            let cwd: Option<String> = row.get(12)?;
+            let cwd_position: Option<i64> = row.get(13)?;
+            let project_path = match (cwd, cwd_position) {
+                (Some(cwd), Some(cwd_position)) if cwd_position <= position => cwd,
+                _ => project_dir.clone(),
+            };
+            let session_id: Option<String> = row.get(7)?;
+            Ok(Some(UsageEntry {
+                timestamp: row.get(0)?,
+                model: model.unwrap_or_else(|| "unknown".to_string()),
+                input_tokens: tokens[0],
+                output_tokens: tokens[1],
+                cache_creation_tokens: tokens[2],
+                cache_read_tokens: tokens[3],
+                cost,
+                session_id: session_id.unwrap_or(project_dir),
+                project_path,
+            }))
+        };
+        if let Some(entry) = read().map_err(|e| e.to_string())? {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(entries)
+}
+
+/// Messages in an indexed transcript
+pub fn message_count(conn: &Connection, path: &Path) -> Result<usize, String> {
+    Ok(file_row(conn, &path.to_string_lossy())
+        .map_err(|e| e.to_string())?
+        .map(|file| file.message_count as usize)
+        .unwrap_or(0))
+}
+
+/// Position of the last message with `uuid`
+pub fn position_of_uuid(
+    conn: &Connection,
+    path: &Path,
+    uuid: &str,
+) -> Result<Option<usize>, String> {
+    conn.query_row(
+        "SELECT position FROM session_index_messages WHERE path = ?1 AND uuid = ?2
+         ORDER BY position DESC LIMIT 1",
+        params![path.to_string_lossy(), uuid],
+        |row| row.get::<_, i64>(0),
+    )
+    .optional()
+    .map(|position| position.map(|position| position as usize))
+    .map_err(|e| e.to_string())
+}
+
+/// Start and end offsets of messages `range`
+pub fn message_offsets(
+    conn: &Connection,
+    path: &Path,
+    range: std::ops::Range<usize>,
+) -> Result<Vec<(u64, u64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT start_offset, end_offset FROM session_index_messages
+             WHERE path = ?1 AND position >= ?2 AND position < ?3 ORDER BY position",
+        )
+        .map_err(|e| e.to_string())?;
+    let offsets = stmt
+        .query_map(
+            params![path.to_string_lossy(), range.start as i64, range.end as i64],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+        )
+        .map_err(|e| e.to_string())?;
+    offsets
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+/// A message matching a transcript search
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionSearchHit {
+    pub session_id: String,
+    /// Name of the project directory under `~/.claude/projects`
+    pub project_id: String,
+    /// Position of the message, an anchor for `get_session_window`
+    pub position: usize,
+    pub uuid: Option<String>,
+    pub timestamp: Option<String>,
+    /// Text around the match, with matches in [brackets]
+    pub snippet: String,
+}
+
+/// Messages whose text matches every word of `query`, best matches first
+pub fn search(
+    conn: &Connection,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SessionSearchHit>, String> {
+    // Each word as a quoted FTS term, so user input is never parsed as FTS syntax
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.path, t.position, snippet(session_index_text, 0, '[', ']', '…', 16),
+                    f.project_dir, m.uuid, m.timestamp
+             FROM session_index_text t
+             JOIN session_index_files f ON f.path = t.path
+             JOIN session_index_messages m ON m.path = t.path AND m.position = t.position
+             WHERE session_index_text MATCH ?1
+             ORDER BY rank LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let hits = stmt
+        .query_map(params![terms.join(" "), limit as i64], |row| {
+            let path: String = row.get(0)?;
+            Ok(SessionSearchHit {
+                session_id: Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                position: row.get::<_, i64>(1)? as usize,
+                snippet: row.get(2)?,
+                project_id: row.get(3)?,
+                uuid: row.get(4)?,
+                timestamp: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    hits.collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn projects_dir() -> Result<PathBuf, String> {
+    Ok(super::claude::get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects"))
+}
+
+/// Bring the index up to date in the background after launch
+pub fn spawn_session_indexer() {
+    tauri::async_runtime::spawn_blocking(|| {
+        let (Some(conn), Ok(projects_dir)) = (open_store(), projects_dir()) else {
+            return;
+        };
+        match index_all(&conn, &projects_dir) {
+            Ok(files) => log::info!("Indexed {} session transcripts", files),
+            Err(e) => log::warn!("Failed to index session transcripts: {}", e),
+        }
+    });
+}
+
+/// Search the text of every session transcript
+#[tauri::command]
+pub async fn search_sessions(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SessionSearchHit>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = open_store().ok_or("The session index is not available")?;
+        index_all(&conn, &projects_dir()?)?;
+        search(&conn, &query, limit.unwrap_or(50).min(500))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io::Write;
+
+    fn assistant(uuid: &str, id: &str, timestamp: &str, text: &str, output_tokens: u64) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "uuid": uuid,
+            "timestamp": timestamp,
+            "sessionId": "s1",
+            "requestId": format!("req-{}", id),
+            "cwd": "/work/app",
+            "message": {
+                "id": id,
+                "model": "claude-sonnet-4-5",
+                "content": [{ "type": "text", "text": text }],
+                "usage": { "input_tokens": 10, "output_tokens": output_tokens },
+            },
+        })
+        .to_string()
+    }
+
+    fn store() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_session_index_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_usage_from_the_index_matches_parsing_the_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let path = project.join("s1.jsonl");
+        let mut file = File::create(&path).unwrap();
+        writeln!(
+            file,
+            "{}",
+            assistant("u1", "m1", "2025-01-01T10:00:00Z", "hello", 5)
+        )
+        .unwrap();
+        writeln!(
+            file,
+            "{}",
+            assistant("u2", "m1", "2025-01-01T10:00:01Z", "hello", 5)
+        )
+        .unwrap();
+        write!(
+            file,
+            "{}",
+            &assistant("u3", "m2", "2025-01-01T10:01:00Z", "bye", 7)[..20]
+        )
+        .unwrap();
+
+        let conn = store();
+        let entries = usage_entries(&conn, dir.path()).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        writeln!(
+            file,
+            "{}",
+            &assistant("u3", "m2", "2025-01-01T10:01:00Z", "bye", 7)[20..]
+        )
+        .unwrap();
+        let entries = usage_entries(&conn, dir.path()).unwrap();
+        let parsed = super::super::usage::parse_jsonl_file(&path, "-work-app", &mut HashSet::new());
+        assert_eq!(entries.len(), parsed.len());
+        for (indexed, parsed) in entries.iter().zip(&parsed) {
+            assert_eq!(indexed.timestamp, parsed.timestamp);
+            assert_eq!(indexed.output_tokens, parsed.output_tokens);
+            assert_eq!(indexed.session_id, parsed.session_id);
+            assert_eq!(indexed.project_path, parsed.project_path);
+            assert_eq!(indexed.cost, parsed.cost);
+        }
+        assert_eq!(message_count(&conn, &path).unwrap(), 3);
+        assert_eq!(position_of_uuid(&conn, &path, "u3").unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_search_and_reindex_after_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let path = project.join("s1.jsonl");
+        std::fs::write(
+            &path,
+            format!(
+                "{}\n{}\n",
+                assistant(
+                    "u1",
+                    "m1",
+                    "2025-01-01T10:00:00Z",
+                    "the flaky migration test",
+                    5
+                ),
+                assistant("u2", "m2", "2025-01-01T10:00:01Z", "unrelated", 5)
+            ),
+        )
+        .unwrap();
+
+        let conn = store();
+        index_all(&conn, dir.path()).unwrap();
+        let hits = search(&conn, "flaky migration", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].session_id.as_str(), hits[0].position), ("s1", 0));
+        assert!(hits[0].snippet.contains("[flaky]"));
+        assert!(search(&conn, "\"quoted OR", 10).unwrap().is_empty());
+
+        std::fs::write(
+            &path,
+            format!(
+                "{}\n",
+                assistant("u9", "m9", "2025-01-02T00:00:00Z", "rewritten", 1)
+            ),
+        )
+        .unwrap();
+        index_all(&conn, dir.path()).unwrap();
+        assert!(search(&conn, "flaky", 10).unwrap().is_empty());
+        assert_eq!(message_count(&conn, &path).unwrap(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        index_all(&conn, dir.path()).unwrap();
+        assert!(search(&conn, "rewritten", 10).unwrap().is_empty());
+    }
+}
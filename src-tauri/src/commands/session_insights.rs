@@ -0,0 +1,426 @@
+#![allow(dead_code)]
+
+//! Insights bookmarked from sessions, and their export to a project's `CLAUDE.md` or
+//! `docs/decisions.md` so what was learned in a conversation becomes a standing instruction.
+//! Exported entries carry a fingerprint marker, so exporting the same insight twice is a no-op.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::agents::AgentDb;
+
+/// Heading the entries are appended under in `CLAUDE.md`
+const CLAUDE_MD_SECTION: &str = "## Learned in sessions";
+
+/// Marker placed before each exported entry
+const MARKER_PREFIX: &str = "<!-- opcode-insight:";
+
+/// An insight bookmarked from a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInsight {
+    pub id: Option<i64>,
+    pub project_path: String,
+    pub session_id: String,
+    /// `uuid` of the session message it was taken from
+    pub message_id: Option<String>,
+    /// `decision`, `convention`, `gotcha` or `note`
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    pub title: String,
+    pub content: String,
+    pub created_at: Option<String>,
+    pub exported_at: Option<String>,
+    /// File it was last exported to
+    pub exported_to: Option<String>,
+}
+
+fn default_kind() -> String {
+    "note".to_string()
+}
+
+/// Where insights are exported to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InsightTarget {
+    /// `<project>/CLAUDE.md`, read by Claude Code on every session
+    ClaudeMd,
+    /// `<project>/docs/decisions.md`
+    Decisions,
+}
+
+impl InsightTarget {
+    pub fn path(&self, project_path: &str) -> PathBuf {
+        let project = PathBuf::from(project_path);
+        match self {
+            InsightTarget::ClaudeMd => project.join("CLAUDE.md"),
+            InsightTarget::Decisions => project.join("docs").join("decisions.md"),
+        }
+    }
+}
+
+/// One insight in an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedInsight {
+    pub id: i64,
+    pub title: String,
+    /// Already in the file; it won't be appended again
+    pub duplicate: bool,
+}
+
+/// What an export would do, or did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsightExport {
+    pub path: String,
+    pub insights: Vec<PlannedInsight>,
+    /// Text appended to the file
+    pub appended: String,
+    /// The whole file after the export
+    pub content: String,
+}
+
+pub fn init_session_insight_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_insights (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_path TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            message_id TEXT,
+            kind TEXT NOT NULL DEFAULT 'note',
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            exported_at TEXT,
+            exported_to TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_insights_project ON session_insights(project_path)",
+        [],
+    )?;
+    Ok(())
+}
+
+const INSIGHT_COLUMNS: &str =
+    "id, project_path, session_id, message_id, kind, title, content, created_at, exported_at, exported_to";
+
+fn insight_from_row(row: &rusqlite::Row) -> SqliteResult<SessionInsight> {
+    Ok(SessionInsight {
+        id: Some(row.get(0)?),
+        project_path: row.get(1)?,
+        session_id: row.get(2)?,
+        message_id: row.get(3)?,
+        kind: row.get(4)?,
+        title: row.get(5)?,
+        content: row.get(6)?,
+        created_at: row.get(7)?,
+        exported_at: row.get(8)?,
+        exported_to: row.get(9)?,
+    })
+}
+
+fn load_insight(conn: &Connection, id: i64) -> SqliteResult<Option<SessionInsight>> {
+    conn.query_row(
+        &format!("SELECT {} FROM session_insights WHERE id = ?1", INSIGHT_COLUMNS),
+        params![id],
+        insight_from_row,
+    )
+    .optional()
+}
+
+fn load_insights(conn: &Connection, ids: &[i64]) -> Result<Vec<SessionInsight>, String> {
+    ids.iter()
+        .map(|id| {
+            load_insight(conn, *id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Insight {} not found", id))
+        })
+        .collect()
+}
+
+/// Stable fingerprint of an insight's text, ignoring whitespace and case
+pub fn insight_fingerprint(insight: &SessionInsight) -> String {
+    let normalize = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mut hasher = Sha256::new();
+    hasher.update(normalize(&insight.title));
+    hasher.update("\n");
+    hasher.update(normalize(&insight.content));
+    hasher.finalize()[..6].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Markdown for one insight
+pub fn render_insight(insight: &SessionInsight) -> String {
+    let date = insight
+        .created_at
+        .as_deref()
+        .and_then(|created| created.get(..10))
+        .unwrap_or("");
+    let session: String = insight.session_id.chars().take(8).collect();
+    format!(
+        "{}{} -->\n### {}: {}\n\n{}\n\n_From session {}{}_\n",
+        MARKER_PREFIX,
+        insight_fingerprint(insight),
+        capitalize(&insight.kind),
+        insight.title.trim(),
+        insight.content.trim(),
+        session,
+        if date.is_empty() { String::new() } else { format!(", {}", date) }
+    )
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Work out what appending `insights` to `existing` would produce, skipping any already there
+pub fn plan_export(
+    path: &Path,
+    existing: Option<&str>,
+    target: InsightTarget,
+    insights: &[SessionInsight],
+) -> InsightExport {
+    let existing = existing.unwrap_or("");
+    let mut seen: Vec<String> = Vec::new();
+    let mut planned = Vec::new();
+    let mut entries = Vec::new();
+    for insight in insights {
+        let fingerprint = insight_fingerprint(insight);
+        let marker = format!("{}{} -->", MARKER_PREFIX, fingerprint);
+        let duplicate = existing.contains(&marker) || seen.contains(&fingerprint);
+        if !duplicate {
+            seen.push(fingerprint);
+            entries.push(render_insight(insight));
+        }
+        planned.push(PlannedInsight {
+            id: insight.id.unwrap_or_default(),
+            title: insight.title.clone(),
+            duplicate,
+        });
+    }
+
+    let mut appended = String::new();
+    if !entries.is_empty() {
+        if existing.is_empty() && target == InsightTarget::Decisions {
+            appended.push_str("# Decisions\n");
+        }
+        if target == InsightTarget::ClaudeMd && !existing.lines().any(|line| line.trim() == CLAUDE_MD_SECTION) {
+            if !existing.is_empty() || !appended.is_empty() {
+                appended.push('\n');
+            }
+            appended.push_str(CLAUDE_MD_SECTION);
+            appended.push('\n');
+        }
+        for entry in &entries {
+            if !existing.is_empty() || !appended.is_empty() {
+                appended.push('\n');
+            }
+            appended.push_str(entry);
+        }
+    }
+
+    let mut content = existing.to_string();
+    if !appended.is_empty() && !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&appended);
+
+    InsightExport {
+        path: path.to_string_lossy().to_string(),
+        insights: planned,
+        appended,
+        content,
+    }
+}
+
+fn prepare_export(
+    conn: &Connection,
+    project_path: &str,
+    target: InsightTarget,
+    ids: &[i64],
+) -> Result<InsightExport, String> {
+    let insights = load_insights(conn, ids)?;
+    let path = target.path(project_path);
+    let existing = if path.exists() {
+        Some(fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?)
+    } else {
+        None
+    };
+    Ok(plan_export(&path, existing.as_deref(), target, &insights))
+}
+
+/// Bookmark an insight from a session
+#[tauri::command]
+pub async fn add_session_insight(
+    db: State<'_, AgentDb>,
+    insight: SessionInsight,
+) -> Result<SessionInsight, String> {
+    if insight.title.trim().is_empty() {
+        return Err("Insight title cannot be empty".to_string());
+    }
+    if insight.content.trim().is_empty() {
+        return Err("Insight content cannot be empty".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO session_insights (project_path, session_id, message_id, kind, title, content)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            insight.project_path,
+            insight.session_id,
+            insight.message_id,
+            insight.kind.trim().to_lowercase(),
+            insight.title.trim(),
+            insight.content.trim()
+        ],
+    )
+    .map_err(|e| format!("Failed to save insight: {}", e))?;
+    load_insight(&conn, conn.last_insert_rowid())
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Insight disappeared after creation".to_string())
+}
+
+/// Insights bookmarked in a project, newest first
+#[tauri::command]
+pub async fn list_session_insights(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    unexported_only: Option<bool>,
+) -> Result<Vec<SessionInsight>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM session_insights
+             WHERE project_path = ?1 AND (?2 = 0 OR exported_at IS NULL)
+             ORDER BY created_at DESC, id DESC",
+            INSIGHT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let insights = stmt
+        .query_map(params![project_path, unexported_only.unwrap_or(false)], insight_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(insights)
+}
+
+/// Delete a bookmarked insight; exported text stays in the file
+#[tauri::command]
+pub async fn delete_session_insight(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM session_insights WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete insight: {}", e))?;
+    Ok(())
+}
+
+/// Show what exporting the insights would append, without writing anything
+#[tauri::command]
+pub async fn preview_insight_export(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    target: InsightTarget,
+    ids: Vec<i64>,
+) -> Result<InsightExport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    prepare_export(&conn, &project_path, target, &ids)
+}
+
+/// Append the insights to the target file, skipping ones already exported there
+#[tauri::command]
+pub async fn export_insights(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    target: InsightTarget,
+    ids: Vec<i64>,
+) -> Result<InsightExport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let export = prepare_export(&conn, &project_path, target, &ids)?;
+    let path = PathBuf::from(&export.path);
+
+    if !export.appended.is_empty() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        super::config_snapshots::snapshot_config_file(&path, "export session insights")?;
+        fs::write(&path, &export.content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        log::info!("Exported {} insight(s) to {}", ids.len(), path.display());
+    }
+
+    for id in &ids {
+        conn.execute(
+            "UPDATE session_insights SET exported_at = CURRENT_TIMESTAMP, exported_to = ?1 WHERE id = ?2",
+            params![export.path, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insight(id: i64, title: &str, content: &str) -> SessionInsight {
+        SessionInsight {
+            id: Some(id),
+            project_path: "/work/app".to_string(),
+            session_id: "3f2a9c1e-0000-4000-8000-000000000000".to_string(),
+            message_id: None,
+            kind: "decision".to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+            created_at: Some("2026-10-01 12:00:00".to_string()),
+            exported_at: None,
+            exported_to: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_export_appends_section_and_skips_duplicates() {
+        let path = Path::new("/work/app/CLAUDE.md");
+        let first = insight(1, "Use sqlx", "Queries go through sqlx, not diesel.");
+        let export = plan_export(path, Some("# App\n\nRun `make test`."), InsightTarget::ClaudeMd, &[first.clone()]);
+        assert!(export.content.starts_with("# App\n\nRun `make test`.\n\n## Learned in sessions\n\n<!-- opcode-insight:"));
+        assert!(export.content.contains("### Decision: Use sqlx\n\nQueries go through sqlx, not diesel.\n\n_From session 3f2a9c1e, 2026-10-01_\n"));
+
+        // Same text with different spacing is the same insight
+        let again = insight(2, "use  sqlx", "Queries go through sqlx,\nnot diesel.");
+        let second = insight(3, "Feature flags", "Flags live in config/flags.toml.");
+        let export = plan_export(path, Some(&export.content), InsightTarget::ClaudeMd, &[again, second]);
+        assert!(export.insights[0].duplicate);
+        assert!(!export.insights[1].duplicate);
+        assert_eq!(export.content.matches(CLAUDE_MD_SECTION).count(), 1);
+        assert!(export.appended.starts_with("\n<!-- opcode-insight:"));
+    }
+
+    #[test]
+    fn test_decisions_file_is_created_with_heading_and_insights_persist() {
+        let export = plan_export(
+            Path::new("/work/app/docs/decisions.md"),
+            None,
+            InsightTarget::Decisions,
+            &[insight(1, "Monorepo", "Keep the CLI and app in one repo.")],
+        );
+        assert!(export.content.starts_with("# Decisions\n\n<!-- opcode-insight:"));
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_session_insight_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO session_insights (project_path, session_id, title, content) VALUES ('/work/app', 's1', 'T', 'C')",
+            [],
+        )
+        .unwrap();
+        let stored = load_insight(&conn, 1).unwrap().unwrap();
+        assert_eq!(stored.kind, "note");
+        assert!(load_insights(&conn, &[1, 2]).unwrap_err().contains("Insight 2 not found"));
+    }
+}
@@ -0,0 +1,467 @@
+#![allow(dead_code)]
+
+//! Detection and merging of session files that belong to one conversation.
+//!
+//! A crash mid-resume can leave a second JSONL file that repeats the history of the
+//! first (a duplicate) or picks up where it stopped (a continuation). Messages carry a
+//! `uuid` and a `parentUuid`, which is enough to tell how two files relate.
+
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::claude::{get_claude_dir, is_valid_uuid};
+
+/// Directory inside a project's session folder holding pre-merge copies
+const BACKUP_DIR_NAME: &str = ".opcode-merge-backups";
+
+/// How two session files relate
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionRelation {
+    /// Every message of one file is also in the other
+    Duplicate,
+    /// One file resumes the other, sharing some history or pointing at its last messages
+    Continuation,
+}
+
+/// A proposed merge of several session files into the earliest one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMergeProposal {
+    pub target_session_id: String,
+    pub source_session_ids: Vec<String>,
+    /// `continuation` if any source adds new messages, `duplicate` otherwise
+    pub relation: SessionRelation,
+    /// Messages already present in the target
+    pub shared_messages: usize,
+    /// Messages the merge would append to the target
+    pub new_messages: usize,
+}
+
+/// Outcome of a merge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMergeResult {
+    pub target_session_id: String,
+    pub merged_session_ids: Vec<String>,
+    pub appended_messages: usize,
+    /// Copies of every file touched, taken before the merge
+    pub backup_dir: String,
+    /// Agent runs re-pointed at the target session
+    pub updated_agent_runs: usize,
+}
+
+struct SessionFile {
+    id: String,
+    path: PathBuf,
+    lines: Vec<String>,
+    uuids: Vec<String>,
+    /// `parentUuid` of the first message, linking a continuation to its predecessor
+    first_parent: Option<String>,
+    first_timestamp: String,
+}
+
+fn read_session_file(path: &Path) -> Option<SessionFile> {
+    let id = path.file_stem()?.to_str()?.to_string();
+    if !is_valid_uuid(&id) {
+        return None;
+    }
+    let content = fs::read_to_string(path).ok()?;
+    let lines: Vec<String> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect();
+
+    let mut uuids = Vec::new();
+    let mut first_parent = None;
+    let mut first_timestamp = String::new();
+    for value in lines.iter().filter_map(|line| serde_json::from_str::<Value>(line).ok()) {
+        let Some(uuid) = value.get("uuid").and_then(Value::as_str) else {
+            continue;
+        };
+        if uuids.is_empty() {
+            first_parent = value
+                .get("parentUuid")
+                .and_then(Value::as_str)
+                .map(|parent| parent.to_string());
+            first_timestamp = value
+                .get("timestamp")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+        }
+        uuids.push(uuid.to_string());
+    }
+    if uuids.is_empty() {
+        return None;
+    }
+
+    Some(SessionFile {
+        id,
+        path: path.to_path_buf(),
+        lines,
+        uuids,
+        first_parent,
+        first_timestamp,
+    })
+}
+
+fn read_session_files(project_dir: &Path) -> Result<Vec<SessionFile>, String> {
+    let entries = fs::read_dir(project_dir)
+        .map_err(|e| format!("Failed to read project directory: {}", e))?;
+    let mut files: Vec<SessionFile> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
+        .filter_map(|path| read_session_file(&path))
+        .collect();
+    files.sort_by(|a, b| (&a.first_timestamp, &a.id).cmp(&(&b.first_timestamp, &b.id)));
+    Ok(files)
+}
+
+/// How `later` relates to `earlier`, if at all
+fn relation(earlier: &SessionFile, later: &SessionFile) -> Option<SessionRelation> {
+    let earlier_uuids: HashSet<&String> = earlier.uuids.iter().collect();
+    let later_uuids: HashSet<&String> = later.uuids.iter().collect();
+    if later_uuids.is_subset(&earlier_uuids) || earlier_uuids.is_subset(&later_uuids) {
+        return Some(SessionRelation::Duplicate);
+    }
+    let resumes = later
+        .first_parent
+        .as_ref()
+        .is_some_and(|parent| earlier_uuids.contains(parent));
+    if resumes || !earlier_uuids.is_disjoint(&later_uuids) {
+        return Some(SessionRelation::Continuation);
+    }
+    None
+}
+
+/// Group related files and propose merging each group into its earliest file
+fn propose_merges(files: &[SessionFile]) -> Vec<SessionMergeProposal> {
+    // Union-find over file indices
+    let mut parent: Vec<usize> = (0..files.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..files.len() {
+        for j in (i + 1)..files.len() {
+            if relation(&files[i], &files[j]).is_some() {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                // Keep the earliest file as the group's root
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..files.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(target, members)| {
+            let mut seen: HashSet<&String> = files[target].uuids.iter().collect();
+            let mut shared = 0;
+            let mut new = 0;
+            for &i in members.iter().filter(|&&i| i != target) {
+                for uuid in &files[i].uuids {
+                    if seen.insert(uuid) {
+                        new += 1;
+                    } else {
+                        shared += 1;
+                    }
+                }
+            }
+            SessionMergeProposal {
+                target_session_id: files[target].id.clone(),
+                source_session_ids: members
+                    .iter()
+                    .filter(|&&i| i != target)
+                    .map(|&i| files[i].id.clone())
+                    .collect(),
+                relation: if new > 0 {
+                    SessionRelation::Continuation
+                } else {
+                    SessionRelation::Duplicate
+                },
+                shared_messages: shared,
+                new_messages: new,
+            }
+        })
+        .collect()
+}
+
+/// Target lines followed by the sources' messages the target doesn't have yet,
+/// re-labelled with the target's session id
+fn merged_lines(target: &SessionFile, sources: &[&SessionFile]) -> (Vec<String>, usize) {
+    let mut lines = target.lines.clone();
+    let mut seen_uuids: HashSet<String> = target.uuids.iter().cloned().collect();
+    let mut seen_lines: HashSet<String> = target.lines.iter().cloned().collect();
+    let mut appended = 0;
+
+    for source in sources {
+        for line in &source.lines {
+            let Ok(mut value) = serde_json::from_str::<Value>(line) else {
+                continue;
+            };
+            match value.get("uuid").and_then(Value::as_str) {
+                Some(uuid) => {
+                    if !seen_uuids.insert(uuid.to_string()) {
+                        continue;
+                    }
+                    appended += 1;
+                }
+                // Summaries and other entries without a uuid: keep one copy of each
+                None if !seen_lines.insert(line.clone()) => continue,
+                None => {}
+            }
+            if let Some(session_id) = value.get_mut("sessionId") {
+                *session_id = Value::String(target.id.clone());
+            }
+            lines.push(value.to_string());
+        }
+    }
+    (lines, appended)
+}
+
+fn copy_into(backup_dir: &Path, path: &Path, name: &str) -> Result<(), String> {
+    if path.exists() {
+        fs::copy(path, backup_dir.join(name))
+            .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Merge `source_ids` into `target_id` within `project_dir`, backing everything up first.
+/// Returns the backup directory and the number of appended messages.
+fn merge_session_files(
+    project_dir: &Path,
+    todos_dir: &Path,
+    target_id: &str,
+    source_ids: &[String],
+) -> Result<(PathBuf, usize), String> {
+    let load = |id: &str| {
+        read_session_file(&project_dir.join(format!("{}.jsonl", id)))
+            .ok_or_else(|| format!("Session {} not found or empty", id))
+    };
+    let target = load(target_id)?;
+    let mut sources = source_ids
+        .iter()
+        .map(|id| load(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    sources.sort_by(|a, b| a.first_timestamp.cmp(&b.first_timestamp));
+
+    let backup_dir = project_dir
+        .join(BACKUP_DIR_NAME)
+        .join(chrono::Utc::now().format("%Y%m%dT%H%M%S%3f").to_string());
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    for file in std::iter::once(&target).chain(sources.iter()) {
+        copy_into(&backup_dir, &file.path, &format!("{}.jsonl", file.id))?;
+        copy_into(
+            &backup_dir,
+            &todos_dir.join(format!("{}.json", file.id)),
+            &format!("{}.todos.json", file.id),
+        )?;
+    }
+
+    let source_refs: Vec<&SessionFile> = sources.iter().collect();
+    let (lines, appended) = merged_lines(&target, &source_refs);
+    let tmp_path = target.path.with_extension("jsonl.merging");
+    fs::write(&tmp_path, lines.join("\n") + "\n")
+        .map_err(|e| format!("Failed to write merged session: {}", e))?;
+    fs::rename(&tmp_path, &target.path)
+        .map_err(|e| format!("Failed to replace session file: {}", e))?;
+
+    let target_todos = todos_dir.join(format!("{}.json", target.id));
+    for source in &sources {
+        fs::remove_file(&source.path)
+            .map_err(|e| format!("Failed to remove merged session {}: {}", source.id, e))?;
+        // Keep the most recent todo list if the target has none
+        let source_todos = todos_dir.join(format!("{}.json", source.id));
+        if source_todos.exists() {
+            let result = if target_todos.exists() {
+                fs::remove_file(&source_todos)
+            } else {
+                fs::rename(&source_todos, &target_todos)
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to move todos of session {}: {}", source.id, e);
+            }
+        }
+    }
+
+    Ok((backup_dir, appended))
+}
+
+pub(super) fn project_dir(project_id: &str) -> Result<(PathBuf, PathBuf), String> {
+    if project_id.is_empty() || project_id.contains(['/', '\\']) || project_id.contains("..") {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let dir = claude_dir.join("projects").join(project_id);
+    if !dir.is_dir() {
+        return Err(format!("Project directory not found: {}", project_id));
+    }
+    Ok((dir, claude_dir.join("todos")))
+}
+
+/// Find session files in a project that duplicate or continue each other
+#[tauri::command]
+pub async fn analyze_duplicate_sessions(
+    project_id: String,
+) -> Result<Vec<SessionMergeProposal>, String> {
+    let (dir, _) = project_dir(&project_id)?;
+    tokio::task::spawn_blocking(move || read_session_files(&dir).map(|files| propose_merges(&files)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Merge session files into a target session; originals are backed up first
+#[tauri::command]
+pub async fn merge_sessions(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    project_id: String,
+    target_session_id: String,
+    source_session_ids: Vec<String>,
+) -> Result<SessionMergeResult, String> {
+    let (dir, todos_dir) = project_dir(&project_id)?;
+    if source_session_ids.is_empty() {
+        return Err("No sessions to merge".to_string());
+    }
+    for id in std::iter::once(&target_session_id).chain(source_session_ids.iter()) {
+        if !is_valid_uuid(id) {
+            return Err(format!("Invalid session id: {}", id));
+        }
+        if registry.0.get_claude_session_by_id(id)?.is_some() {
+            return Err(format!("Session {} is still running", id));
+        }
+    }
+    if source_session_ids.contains(&target_session_id) {
+        return Err("A session can't be merged into itself".to_string());
+    }
+
+    let target = target_session_id.clone();
+    let sources = source_session_ids.clone();
+    let (backup_dir, appended) = tokio::task::spawn_blocking(move || {
+        merge_session_files(&dir, &todos_dir, &target, &sources)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut updated_agent_runs = 0;
+    for source in &source_session_ids {
+        updated_agent_runs += conn
+            .execute(
+                "UPDATE agent_runs SET session_id = ?1 WHERE session_id = ?2",
+                params![target_session_id, source],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
+    log::info!(
+        "Merged {} session(s) into {} ({} new messages, backup at {})",
+        source_session_ids.len(),
+        target_session_id,
+        appended,
+        backup_dir.display()
+    );
+    Ok(SessionMergeResult {
+        target_session_id,
+        merged_session_ids: source_session_ids,
+        appended_messages: appended,
+        backup_dir: backup_dir.to_string_lossy().to_string(),
+        updated_agent_runs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const A: &str = "aaaaaaaa-0000-4000-8000-000000000001";
+    const B: &str = "bbbbbbbb-0000-4000-8000-000000000002";
+    const C: &str = "cccccccc-0000-4000-8000-000000000003";
+    const D: &str = "dddddddd-0000-4000-8000-000000000004";
+
+    fn write_session(dir: &Path, id: &str, messages: &[(&str, Option<&str>, &str)]) {
+        let lines: Vec<String> = messages
+            .iter()
+            .map(|(uuid, parent, ts)| {
+                json!({"type": "user", "sessionId": id, "uuid": uuid, "parentUuid": parent, "timestamp": ts})
+                    .to_string()
+            })
+            .collect();
+        fs::write(dir.join(format!("{}.jsonl", id)), lines.join("\n")).unwrap();
+    }
+
+    fn fixture(dir: &Path) {
+        write_session(dir, A, &[("m1", None, "2024-05-01T10:00:00Z"), ("m2", Some("m1"), "2024-05-01T10:01:00Z")]);
+        // Resumed after a crash: copies m1..m2 then continues
+        write_session(
+            dir,
+            B,
+            &[
+                ("m1", None, "2024-05-01T10:00:00Z"),
+                ("m2", Some("m1"), "2024-05-01T10:01:00Z"),
+                ("m3", Some("m2"), "2024-05-01T10:05:00Z"),
+            ],
+        );
+        // Continues B without sharing history
+        write_session(dir, C, &[("m4", Some("m3"), "2024-05-01T10:10:00Z")]);
+        // Unrelated conversation
+        write_session(dir, D, &[("x1", None, "2024-05-02T09:00:00Z")]);
+    }
+
+    #[test]
+    fn test_propose_merges_groups_related_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        fixture(dir.path());
+
+        let proposals = propose_merges(&read_session_files(dir.path()).unwrap());
+        assert_eq!(proposals.len(), 1);
+        let proposal = &proposals[0];
+        assert_eq!(proposal.target_session_id, A);
+        assert_eq!(proposal.source_session_ids, vec![B, C]);
+        assert_eq!(proposal.relation, SessionRelation::Continuation);
+        assert_eq!(proposal.shared_messages, 2);
+        assert_eq!(proposal.new_messages, 2);
+    }
+
+    #[test]
+    fn test_merge_appends_new_messages_and_backs_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let todos = tempfile::tempdir().unwrap();
+        fixture(dir.path());
+        fs::write(todos.path().join(format!("{}.json", C)), "[]").unwrap();
+
+        let (backup_dir, appended) =
+            merge_session_files(dir.path(), todos.path(), A, &[C.to_string(), B.to_string()])
+                .unwrap();
+        assert_eq!(appended, 2);
+        assert!(backup_dir.join(format!("{}.jsonl", B)).exists());
+        assert!(backup_dir.join(format!("{}.todos.json", C)).exists());
+        assert!(!dir.path().join(format!("{}.jsonl", B)).exists());
+        assert!(todos.path().join(format!("{}.json", A)).exists());
+
+        let merged = read_session_file(&dir.path().join(format!("{}.jsonl", A))).unwrap();
+        assert_eq!(merged.uuids, vec!["m1", "m2", "m3", "m4"]);
+        assert!(merged.lines.iter().all(|line| line.contains(A)));
+        assert!(propose_merges(&read_session_files(dir.path()).unwrap()).is_empty());
+    }
+}
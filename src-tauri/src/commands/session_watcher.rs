@@ -0,0 +1,336 @@
+#![allow(dead_code)]
+
+//! Watches `~/.claude/projects` for session files written by any Claude Code process,
+//! including ones started from a terminal, and streams their new lines to the frontend.
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::activity::{ActivityKind, NewActivity};
+use super::claude::get_claude_dir;
+use crate::process::ProcessRegistryState;
+
+/// Emitted the first time a session file is seen while watching
+pub const SESSION_DISCOVERED_EVENT: &str = "session-watch:discovered";
+
+/// Emitted for every complete line appended to a watched session file
+pub const SESSION_MESSAGE_EVENT: &str = "session-watch:message";
+
+/// Running watcher; dropping it stops the watch and its event thread
+pub struct SessionWatcher {
+    _watcher: RecommendedWatcher,
+    projects_dir: PathBuf,
+}
+
+/// State holding the active session watcher, if any
+#[derive(Default)]
+pub struct SessionWatcherState(pub Mutex<Option<SessionWatcher>>);
+
+/// Identifies a session file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchedSession {
+    pub project_id: String,
+    pub session_id: String,
+    pub path: String,
+    /// False when the session belongs to a run opcode started
+    pub external: bool,
+}
+
+/// Payload of `session-watch:message`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWatchMessage {
+    pub project_id: String,
+    pub session_id: String,
+    pub external: bool,
+    pub message: Value,
+}
+
+/// Watcher status for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWatcherStatus {
+    pub running: bool,
+    pub projects_dir: Option<String>,
+}
+
+/// Per-file read positions, so only appended lines are reported
+#[derive(Default)]
+pub struct SessionTailer {
+    offsets: HashMap<PathBuf, u64>,
+    /// Trailing text of a line that hasn't been terminated yet
+    partial: HashMap<PathBuf, String>,
+}
+
+impl SessionTailer {
+    /// Start tracking a file from its current end
+    pub fn track_from_end(&mut self, path: &Path) {
+        let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        self.offsets.insert(path.to_path_buf(), len);
+    }
+
+    pub fn is_tracked(&self, path: &Path) -> bool {
+        self.offsets.contains_key(path)
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.offsets.remove(path);
+        self.partial.remove(path);
+    }
+
+    /// Read complete lines appended since the last call, parsed as JSON.
+    /// A file that shrank was rewritten (e.g. merged) and is followed from its new end.
+    pub fn read_new(&mut self, path: &Path) -> std::io::Result<Vec<Value>> {
+        let mut file = fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        let offset = self.offsets.get(path).copied().unwrap_or(0);
+        if len < offset {
+            self.partial.remove(path);
+            self.offsets.insert(path.to_path_buf(), len);
+            return Ok(Vec::new());
+        }
+
+        file.seek(SeekFrom::Start(offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        self.offsets.insert(path.to_path_buf(), offset + bytes.len() as u64);
+
+        let mut text = self.partial.remove(path).unwrap_or_default();
+        text.push_str(&String::from_utf8_lossy(&bytes));
+        let complete = match text.rfind('\n') {
+            Some(end) => {
+                let rest = text.split_off(end + 1);
+                if !rest.is_empty() {
+                    self.partial.insert(path.to_path_buf(), rest);
+                }
+                text
+            }
+            None => {
+                if !text.is_empty() {
+                    self.partial.insert(path.to_path_buf(), text);
+                }
+                return Ok(Vec::new());
+            }
+        };
+
+        Ok(complete
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    log::debug!("Skipping unparsable line in {:?}: {}", path, e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// `(project_id, session_id)` for `<projects_dir>/<project_id>/<session_id>.jsonl`
+pub fn session_file_ids(projects_dir: &Path, path: &Path) -> Option<(String, String)> {
+    if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+        return None;
+    }
+    let relative = path.strip_prefix(projects_dir).ok()?;
+    let mut components = relative.components();
+    let project_id = components.next()?.as_os_str().to_str()?.to_string();
+    let file_name = components.next()?;
+    if components.next().is_some() {
+        return None;
+    }
+    let session_id = Path::new(file_name.as_os_str())
+        .file_stem()?
+        .to_str()?
+        .to_string();
+    Some((project_id, session_id))
+}
+
+fn is_external(app: &AppHandle, session_id: &str) -> bool {
+    app.try_state::<ProcessRegistryState>()
+        .and_then(|registry| registry.0.get_claude_session_by_id(session_id).ok())
+        .map(|info| info.is_none())
+        .unwrap_or(true)
+}
+
+fn handle_path(app: &AppHandle, tailer: &mut SessionTailer, projects_dir: &Path, path: &Path) {
+    let Some((project_id, session_id)) = session_file_ids(projects_dir, path) else {
+        return;
+    };
+    if !path.exists() {
+        tailer.forget(path);
+        return;
+    }
+    let external = is_external(app, &session_id);
+    if !tailer.is_tracked(path) {
+        let session = WatchedSession {
+            project_id: project_id.clone(),
+            session_id: session_id.clone(),
+            path: path.to_string_lossy().to_string(),
+            external,
+        };
+        if let Err(e) = app.emit(SESSION_DISCOVERED_EVENT, &session) {
+            log::warn!("Failed to emit session discovery: {}", e);
+        }
+        if external {
+            super::activity::record_activity(
+                app,
+                NewActivity::new(ActivityKind::SessionDiscovered, "Session started outside opcode")
+                    .session(session_id.clone())
+                    .detail(serde_json::json!({ "project_id": project_id, "path": session.path })),
+            );
+        }
+    }
+
+    match tailer.read_new(path) {
+        Ok(messages) => {
+            for message in messages {
+                let payload = SessionWatchMessage {
+                    project_id: project_id.clone(),
+                    session_id: session_id.clone(),
+                    external,
+                    message,
+                };
+                if let Err(e) = app.emit(SESSION_MESSAGE_EVENT, &payload) {
+                    log::warn!("Failed to emit session message: {}", e);
+                }
+            }
+        }
+        Err(e) => log::debug!("Failed to tail {:?}: {}", path, e),
+    }
+}
+
+fn start_watcher(app: AppHandle) -> Result<SessionWatcher, String> {
+    let projects_dir = get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects");
+    if !projects_dir.is_dir() {
+        return Err(format!("{} does not exist", projects_dir.display()));
+    }
+
+    // Existing files are followed from their current end; history is loaded separately
+    let mut tailer = SessionTailer::default();
+    for project in fs::read_dir(&projects_dir).map_err(|e| e.to_string())?.flatten() {
+        if let Ok(entries) = fs::read_dir(project.path()) {
+            for entry in entries.flatten() {
+                if session_file_ids(&projects_dir, &entry.path()).is_some() {
+                    tailer.track_from_end(&entry.path());
+                }
+            }
+        }
+    }
+
+    let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to create session watcher: {}", e))?;
+    watcher
+        .watch(&projects_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", projects_dir.display(), e))?;
+
+    let dir = projects_dir.clone();
+    std::thread::spawn(move || {
+        // Ends when the watcher, and with it the sender, is dropped
+        for event in rx {
+            match event {
+                Ok(event) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                        for path in &event.paths {
+                            handle_path(&app, &mut tailer, &dir, path);
+                        }
+                    }
+                }
+                Err(e) => log::warn!("Session watcher error: {}", e),
+            }
+        }
+        log::info!("Session watcher stopped");
+    });
+
+    log::info!("Watching {} for session activity", projects_dir.display());
+    Ok(SessionWatcher {
+        _watcher: watcher,
+        projects_dir,
+    })
+}
+
+/// Start streaming activity from all Claude Code sessions on this machine
+#[tauri::command]
+pub async fn start_session_watcher(
+    app: AppHandle,
+    state: State<'_, SessionWatcherState>,
+) -> Result<(), String> {
+    let mut watcher = state.0.lock().map_err(|e| e.to_string())?;
+    if watcher.is_none() {
+        *watcher = Some(start_watcher(app)?);
+    }
+    Ok(())
+}
+
+/// Stop the session watcher
+#[tauri::command]
+pub async fn stop_session_watcher(state: State<'_, SessionWatcherState>) -> Result<(), String> {
+    state.0.lock().map_err(|e| e.to_string())?.take();
+    Ok(())
+}
+
+/// Whether the session watcher is running
+#[tauri::command]
+pub async fn get_session_watcher_status(
+    state: State<'_, SessionWatcherState>,
+) -> Result<SessionWatcherStatus, String> {
+    let watcher = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(SessionWatcherStatus {
+        running: watcher.is_some(),
+        projects_dir: watcher
+            .as_ref()
+            .map(|watcher| watcher.projects_dir.to_string_lossy().to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_tailer_reports_only_complete_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        fs::write(&path, "{\"n\":0}\n").unwrap();
+
+        let mut tailer = SessionTailer::default();
+        tailer.track_from_end(&path);
+        assert!(tailer.read_new(&path).unwrap().is_empty());
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"n\":1}}\n{{\"n\":").unwrap();
+        let values = tailer.read_new(&path).unwrap();
+        assert_eq!(values, vec![serde_json::json!({"n": 1})]);
+
+        write!(file, "2}}\nnot json\n").unwrap();
+        let values = tailer.read_new(&path).unwrap();
+        assert_eq!(values, vec![serde_json::json!({"n": 2})]);
+
+        // Rewritten shorter: follow from the new end
+        fs::write(&path, "{\"n\":9}\n").unwrap();
+        assert!(tailer.read_new(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_session_file_ids() {
+        let projects = Path::new("/home/u/.claude/projects");
+        assert_eq!(
+            session_file_ids(projects, &projects.join("-home-u-app/abc.jsonl")),
+            Some(("-home-u-app".to_string(), "abc".to_string()))
+        );
+        assert_eq!(session_file_ids(projects, &projects.join("-home-u-app/abc.json")), None);
+        assert_eq!(
+            session_file_ids(projects, &projects.join("-home-u-app/.opcode-merge-backups/x/abc.jsonl")),
+            None
+        );
+    }
+}
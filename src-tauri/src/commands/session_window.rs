@@ -0,0 +1,210 @@
+#![allow(dead_code)]
+
+//! Windowed access to session transcripts for virtualized scrolling. Instead of loading a
+//! whole JSONL file, the frontend asks for the messages around an anchor. Message offsets
+//! come from the session index, so any window is read with a seek rather than by scanning
+//! from the top, and only lines appended since the last request are parsed.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::session_index;
+
+/// Most messages returned on either side of the anchor
+const MAX_WINDOW_SIDE: usize = 500;
+
+/// Where to center a window: a message's position in the transcript or its `uuid`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum MessageAnchor {
+    Index(usize),
+    Uuid(String),
+}
+
+/// A slice of a transcript
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionWindow {
+    /// Messages in the whole transcript
+    pub total: usize,
+    /// Position of the first returned message
+    pub start: usize,
+    /// Position of the anchor, `None` for an empty transcript
+    pub anchor_index: Option<usize>,
+    pub messages: Vec<Value>,
+}
+
+/// Read the lines at `offsets` with one seek; lines that aren't valid JSON come back as null
+fn read_messages(path: &Path, offsets: &[(u64, u64)]) -> std::io::Result<Vec<Value>> {
+    let (Some((base, _)), Some((_, end))) = (offsets.first(), offsets.last()) else {
+        return Ok(Vec::new());
+    };
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(*base))?;
+    let mut bytes = vec![0; (end - base) as usize];
+    file.read_exact(&mut bytes)?;
+    Ok(offsets
+        .iter()
+        .map(|(start, end)| {
+            let line = &bytes[(start - base) as usize..(end - base) as usize];
+            serde_json::from_slice(line).unwrap_or(Value::Null)
+        })
+        .collect())
+}
+
+/// Messages `before` and `after` the anchor, or the last messages without one
+pub fn window(
+    conn: &Connection,
+    path: &Path,
+    anchor: Option<&MessageAnchor>,
+    before: usize,
+    after: usize,
+) -> Result<SessionWindow, String> {
+    let project_dir = path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let total = session_index::index_file(conn, path, &project_dir)?.message_count as usize;
+
+    let anchor_index = match anchor {
+        Some(MessageAnchor::Index(index)) => Some(*index).filter(|index| *index < total),
+        Some(MessageAnchor::Uuid(uuid)) => session_index::position_of_uuid(conn, path, uuid)?,
+        None => total.checked_sub(1),
+    };
+    if anchor.is_some() && anchor_index.is_none() {
+        return Err("Anchor message not found in session".to_string());
+    }
+    let (before, after) = (before.min(MAX_WINDOW_SIDE), after.min(MAX_WINDOW_SIDE));
+    let (start, end) = match anchor_index {
+        Some(anchor) => (
+            anchor.saturating_sub(before),
+            (anchor + after + 1).min(total),
+        ),
+        None => (0, 0),
+    };
+    let offsets = session_index::message_offsets(conn, path, start..end)?;
+    let messages =
+        read_messages(path, &offsets).map_err(|e| format!("Failed to read session file: {}", e))?;
+    Ok(SessionWindow {
+        total,
+        start,
+        anchor_index,
+        messages,
+    })
+}
+
+/// Path of a session's transcript in any project
+pub(crate) fn find_session_file(session_id: &str) -> Result<PathBuf, String> {
+    if session_id.is_empty() || session_id.contains(['/', '\\']) || session_id.contains("..") {
+        return Err(format!("Invalid session id: {}", session_id));
+    }
+    let projects_dir = super::claude::get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects");
+    let entries = std::fs::read_dir(&projects_dir)
+        .map_err(|e| format!("Failed to read projects directory: {}", e))?;
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path().join(format!("{}.jsonl", session_id)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("Session file not found: {}", session_id))
+}
+
+/// Messages around `anchor_message` (an index or a message uuid) for virtualized scrolling;
+/// without an anchor, the end of the transcript
+#[tauri::command]
+pub async fn get_session_window(
+    session_id: String,
+    anchor_message: Option<MessageAnchor>,
+    before: usize,
+    after: usize,
+) -> Result<SessionWindow, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = find_session_file(&session_id)?;
+        let conn = session_index::open_store().ok_or("The session index is not available")?;
+        window(&conn, &path, anchor_message.as_ref(), before, after)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn message(n: usize) -> String {
+        format!(r#"{{"type":"user","uuid":"u-{}","n":{}}}"#, n, n)
+    }
+
+    fn store() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        session_index::init_session_index_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_window_around_index_and_uuid_anchors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("s.jsonl");
+        let lines: Vec<String> = (0..10).map(message).collect();
+        std::fs::write(&path, format!("{}\n\nnot json\n", lines.join("\n"))).unwrap();
+        let conn = store();
+
+        let window_at = |anchor: Option<MessageAnchor>, before, after| {
+            window(&conn, &path, anchor.as_ref(), before, after)
+        };
+        let result = window_at(Some(MessageAnchor::Index(5)), 2, 1).unwrap();
+        assert_eq!(result.total, 11);
+        assert_eq!(result.start, 3);
+        let numbers: Vec<u64> = result
+            .messages
+            .iter()
+            .map(|m| m["n"].as_u64().unwrap())
+            .collect();
+        assert_eq!(numbers, [3, 4, 5, 6]);
+
+        let result = window_at(Some(MessageAnchor::Uuid("u-1".to_string())), 5, 0).unwrap();
+        assert_eq!((result.start, result.anchor_index), (0, Some(1)));
+        assert_eq!(result.messages.len(), 2);
+
+        let tail = window_at(None, 1, 5).unwrap();
+        assert_eq!(tail.anchor_index, Some(10));
+        assert_eq!(tail.messages[1], Value::Null);
+
+        assert!(window_at(Some(MessageAnchor::Uuid("missing".to_string())), 1, 1).is_err());
+        assert!(window_at(Some(MessageAnchor::Index(11)), 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_window_follows_a_growing_transcript() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("s.jsonl");
+        let mut file = File::create(&path).unwrap();
+        write!(file, "{}\n{}", message(0), &message(1)[..10]).unwrap();
+        let conn = store();
+
+        assert_eq!(window(&conn, &path, None, 10, 0).unwrap().total, 1);
+
+        write!(file, "{}\n{}\n", &message(1)[10..], message(2)).unwrap();
+        let result = window(&conn, &path, None, 10, 0).unwrap();
+        assert_eq!(result.total, 3);
+        assert_eq!(result.messages[1]["n"], 1);
+        let anchor = MessageAnchor::Uuid("u-2".to_string());
+        assert_eq!(
+            window(&conn, &path, Some(&anchor), 0, 0)
+                .unwrap()
+                .anchor_index,
+            Some(2)
+        );
+
+        std::fs::write(&path, format!("{}\n", message(7))).unwrap();
+        let result = window(&conn, &path, None, 10, 0).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.messages[0]["n"], 7);
+    }
+}
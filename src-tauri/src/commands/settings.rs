@@ -0,0 +1,289 @@
+#![allow(dead_code)]
+
+//! Central access to the `app_settings` table.
+//!
+//! Values are stored as text: strings as-is (which keeps the existing `proxy_*` and
+//! `notify_*` rows readable) and everything else as JSON. Every change made through
+//! `set_setting` is pushed to the subsystems that cache settings in memory and
+//! announced to the frontend with a `settings:changed` event.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Emitter, State};
+
+use super::agents::AgentDb;
+use super::app_lock::is_app_lock_key;
+
+/// Event emitted whenever a setting is written or removed
+pub const SETTINGS_CHANGED_EVENT: &str = "settings:changed";
+
+/// app_settings key recording which migrations have been applied
+const SCHEMA_VERSION_KEY: &str = "settings_schema_version";
+
+const APP_LOCK_KEY_MESSAGE: &str = "App lock settings are managed through the app lock commands";
+
+/// Extra commands allowed in the built-in terminal, on top of the defaults
+pub const TERMINAL_ALLOWED_COMMANDS_KEY: &str = "terminal_allowed_commands";
+
+/// Settings migrations, applied in order; the schema version is the number applied
+const MIGRATIONS: &[fn(&Connection) -> SqliteResult<()>] = &[migrate_drop_empty_proxy_values];
+
+/// Unset proxy fields used to be saved as empty strings; drop them so an unset
+/// setting is always a missing row
+fn migrate_drop_empty_proxy_values(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM app_settings WHERE key LIKE 'proxy\\_%' ESCAPE '\\' AND value = ''",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Payload of the `settings:changed` event; `value` is `None` when the setting was removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub key: String,
+    pub value: Option<Value>,
+}
+
+/// Apply pending settings migrations
+pub fn migrate_settings(conn: &Connection) -> SqliteResult<()> {
+    let current: usize = get_setting_as(conn, SCHEMA_VERSION_KEY).unwrap_or(0);
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        migration(conn)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![SCHEMA_VERSION_KEY, (index + 1).to_string()],
+        )?;
+        log::info!("Applied settings migration {}", index + 1);
+    }
+    Ok(())
+}
+
+/// Convert a JSON value to its stored text form
+fn to_stored(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Convert stored text back to JSON; text that isn't JSON is a plain string
+fn from_stored(stored: String) -> Value {
+    serde_json::from_str(&stored).unwrap_or(Value::String(stored))
+}
+
+fn read_raw(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Read a setting as JSON
+pub fn get_setting_value(conn: &Connection, key: &str) -> Option<Value> {
+    read_raw(conn, key).map(from_stored)
+}
+
+/// Read a setting as `T`, or `None` if it's missing or has the wrong shape
+pub fn get_setting_as<T: DeserializeOwned>(conn: &Connection, key: &str) -> Option<T> {
+    let raw = read_raw(conn, key)?;
+    serde_json::from_str(&raw)
+        .or_else(|_| serde_json::from_value(Value::String(raw)))
+        .ok()
+}
+
+/// Write a setting from JSON, or remove it when `value` is `None`
+pub fn set_setting_value(conn: &Connection, key: &str, value: Option<&Value>) -> Result<(), String> {
+    match value {
+        Some(value) => conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![key, to_stored(value)],
+        ),
+        None => conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key]),
+    }
+    .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+    Ok(())
+}
+
+/// Write a typed setting
+pub fn set_setting_as<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    set_setting_value(conn, key, Some(&value))
+}
+
+/// Push current settings into the subsystems that keep them in memory
+pub fn apply_settings(conn: &Connection) {
+    super::proxy::apply_proxy_settings(&super::proxy::load_proxy_settings(conn));
+    super::terminal::set_extra_allowed_commands(
+        get_setting_as(conn, TERMINAL_ALLOWED_COMMANDS_KEY).unwrap_or_default(),
+    );
+    super::logs::apply_log_level(get_setting_as(conn, super::logs::LOG_LEVEL_KEY));
+    super::pricing::apply_pricing(conn);
+    crate::decoding::apply_output_codepage(get_setting_as(conn, crate::decoding::OUTPUT_CODEPAGE_KEY));
+    super::mcp::apply_mcp_command_timeout(get_setting_as(conn, super::mcp::MCP_COMMAND_TIMEOUT_KEY));
+    super::offline::apply_offline_mode(get_setting_as(conn, super::offline::OFFLINE_MODE_KEY));
+    super::app_lock::apply_app_lock_settings(get_setting_as(conn, super::app_lock::APP_LOCK_KEY));
+    apply_log_scrubber(conn);
+    super::telemetry::apply_telemetry_setting(get_setting_as(conn, super::telemetry::TELEMETRY_ENABLED_KEY));
+    super::tls_settings::apply_tls_settings(get_setting_as(conn, super::tls_settings::TLS_SETTINGS_KEY));
+}
+
+/// Scrub the custom redaction patterns from logs too
+fn apply_log_scrubber(conn: &Connection) {
+    let config: super::redaction::RedactionConfig =
+        get_setting_as(conn, super::redaction::REDACTION_CONFIG_KEY).unwrap_or_default();
+    crate::log_scrubber::set_custom_patterns(&config.custom_patterns);
+}
+
+/// Let subsystems react to changed keys and notify the frontend
+pub fn notify_settings_changed(app: &AppHandle, conn: &Connection, keys: &[&str]) {
+    if keys.iter().any(|key| key.starts_with("proxy_")) {
+        super::proxy::apply_proxy_settings(&super::proxy::load_proxy_settings(conn));
+    }
+    if keys.contains(&TERMINAL_ALLOWED_COMMANDS_KEY) {
+        super::terminal::set_extra_allowed_commands(
+            get_setting_as(conn, TERMINAL_ALLOWED_COMMANDS_KEY).unwrap_or_default(),
+        );
+    }
+    if keys.contains(&super::logs::LOG_LEVEL_KEY) {
+        super::logs::apply_log_level(get_setting_as(conn, super::logs::LOG_LEVEL_KEY));
+    }
+    if keys.contains(&super::pricing::PRICING_SETTINGS_KEY) {
+        super::pricing::apply_pricing(conn);
+    }
+    if keys.contains(&crate::decoding::OUTPUT_CODEPAGE_KEY) {
+        crate::decoding::apply_output_codepage(get_setting_as(conn, crate::decoding::OUTPUT_CODEPAGE_KEY));
+    }
+    if keys.contains(&super::mcp::MCP_COMMAND_TIMEOUT_KEY) {
+        super::mcp::apply_mcp_command_timeout(get_setting_as(conn, super::mcp::MCP_COMMAND_TIMEOUT_KEY));
+    }
+    if keys.contains(&super::offline::OFFLINE_MODE_KEY) {
+        super::offline::apply_offline_mode(get_setting_as(conn, super::offline::OFFLINE_MODE_KEY));
+    }
+    if keys.contains(&super::redaction::REDACTION_CONFIG_KEY) {
+        apply_log_scrubber(conn);
+    }
+    if keys.contains(&super::telemetry::TELEMETRY_ENABLED_KEY) {
+        super::telemetry::apply_telemetry_setting(get_setting_as(conn, super::telemetry::TELEMETRY_ENABLED_KEY));
+    }
+    if keys.contains(&super::tls_settings::TLS_SETTINGS_KEY) {
+        super::tls_settings::apply_tls_settings(get_setting_as(conn, super::tls_settings::TLS_SETTINGS_KEY));
+    }
+    if keys.contains(&super::app_lock::APP_LOCK_KEY) {
+        super::app_lock::apply_app_lock_settings(get_setting_as(conn, super::app_lock::APP_LOCK_KEY));
+    }
+    for key in keys {
+        let change = SettingChange {
+            key: key.to_string(),
+            value: get_setting_value(conn, key),
+        };
+        if let Err(e) = app.emit(SETTINGS_CHANGED_EVENT, &change) {
+            log::warn!("Failed to emit settings change for {}: {}", key, e);
+        }
+    }
+}
+
+/// Get a single setting
+#[tauri::command]
+pub async fn get_setting(db: State<'_, AgentDb>, key: String) -> Result<Option<Value>, String> {
+    if is_app_lock_key(&key) {
+        return Err(APP_LOCK_KEY_MESSAGE.to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(get_setting_value(&conn, &key))
+}
+
+/// Get all settings
+#[tauri::command]
+pub async fn get_all_settings(db: State<'_, AgentDb>) -> Result<BTreeMap<String, Value>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare("SELECT key, value FROM app_settings ORDER BY key ASC")
+        .map_err(|e| e.to_string())?;
+    let settings = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|row| row.ok())
+        .filter(|(key, _)| key != SCHEMA_VERSION_KEY && !is_app_lock_key(key))
+        .map(|(key, value)| (key, from_stored(value)))
+        .collect();
+    Ok(settings)
+}
+
+/// Set a setting, or remove it when `value` is null
+#[tauri::command]
+pub async fn set_setting(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    key: String,
+    value: Option<Value>,
+) -> Result<(), String> {
+    if key == SCHEMA_VERSION_KEY {
+        return Err("The settings schema version is managed by the app".to_string());
+    }
+    if is_app_lock_key(&key) {
+        return Err(APP_LOCK_KEY_MESSAGE.to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_value(&conn, &key, value.as_ref().filter(|value| !value.is_null()))?;
+    notify_settings_changed(&app, &conn, &[key.as_str()]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_typed_roundtrip_keeps_plain_strings_readable() {
+        let conn = conn();
+        set_setting_as(&conn, "proxy_http", &"http://proxy:8080").unwrap();
+        set_setting_as(&conn, "buffer_lines", &5000u32).unwrap();
+        set_setting_as(&conn, TERMINAL_ALLOWED_COMMANDS_KEY, &vec!["npm", "cargo"]).unwrap();
+
+        assert_eq!(read_raw(&conn, "proxy_http").unwrap(), "http://proxy:8080");
+        assert_eq!(get_setting_as::<String>(&conn, "proxy_http").unwrap(), "http://proxy:8080");
+        assert_eq!(get_setting_as::<u32>(&conn, "buffer_lines"), Some(5000));
+        assert_eq!(
+            get_setting_as::<Vec<String>>(&conn, TERMINAL_ALLOWED_COMMANDS_KEY).unwrap(),
+            vec!["npm", "cargo"]
+        );
+        assert_eq!(get_setting_as::<u32>(&conn, "proxy_http"), None);
+
+        set_setting_value(&conn, "buffer_lines", None).unwrap();
+        assert_eq!(get_setting_value(&conn, "buffer_lines"), None);
+    }
+
+    #[test]
+    fn test_migrations_run_once() {
+        let conn = conn();
+        set_setting_value(&conn, "proxy_http", Some(&Value::String(String::new()))).unwrap();
+        set_setting_value(&conn, "proxy_enabled", Some(&Value::Bool(true))).unwrap();
+
+        migrate_settings(&conn).unwrap();
+        assert_eq!(get_setting_value(&conn, "proxy_http"), None);
+        assert_eq!(get_setting_as::<bool>(&conn, "proxy_enabled"), Some(true));
+        assert_eq!(get_setting_as::<usize>(&conn, SCHEMA_VERSION_KEY), Some(MIGRATIONS.len()));
+
+        set_setting_value(&conn, "proxy_http", Some(&Value::String(String::new()))).unwrap();
+        migrate_settings(&conn).unwrap();
+        assert!(get_setting_value(&conn, "proxy_http").is_some());
+    }
+}
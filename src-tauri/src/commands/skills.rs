@@ -495,7 +495,7 @@ pub async fn skill_create(
     let content = format!("{}{}", yaml_frontmatter, markdown_content);
     debug!("写入文件内容长度: {} 字符", content.len());
 
-    fs::write(&skill_file, content)
+    crate::atomic_file::write_atomic(&skill_file, content)
         .map_err(|e| {
             error!("写入文件失败: {}", e);
             format!("写入技能文件失败: {}", e)
@@ -570,7 +570,7 @@ pub async fn skill_update(
 
     // Write updated content
     let content = format!("{}{}", yaml_frontmatter, skill.markdown_content);
-    fs::write(&skill_file, content).map_err(|e| e.to_string())?;
+    crate::atomic_file::write_atomic(&skill_file, content).map_err(|e| e.to_string())?;
 
     skill.yaml_frontmatter = Some(yaml_frontmatter);
     skill.last_modified = chrono::Utc::now().to_rfc3339();
@@ -652,7 +652,7 @@ pub async fn skill_create_file(
     }
 
     // Write the file
-    fs::write(&file_path, content).map_err(|e| e.to_string())?;
+    crate::atomic_file::write_atomic(&file_path, content).map_err(|e| e.to_string())?;
 
     Ok(())
 }
@@ -198,6 +198,63 @@ fn find_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
     Ok(())
 }
 
+/// First Claude Code version that expands custom slash commands in `-p` mode
+pub const PRINT_MODE_SLASH_COMMANDS_MIN_VERSION: semver::Version = semver::Version::new(1, 0, 0);
+
+/// Whether a Claude Code version (as reported by `claude --version`) runs custom slash
+/// commands itself when given `-p "/command args"`
+pub fn supports_print_mode_slash_commands(version: Option<&str>) -> bool {
+    version
+        .and_then(|v| semver::Version::parse(v.trim()).ok())
+        .is_some_and(|v| v >= PRINT_MODE_SLASH_COMMANDS_MIN_VERSION)
+}
+
+/// Prompt and allowed tools for running `command` non-interactively.
+///
+/// With `native` the slash command is passed through for Claude to expand; otherwise the
+/// command body is inlined with `$ARGUMENTS` substituted, for older Claude versions.
+/// Inlined bodies keep `!` bash lines as plain text since only Claude can run them.
+pub fn compose_slash_command_prompt(
+    command: &SlashCommand,
+    args: &str,
+    native: bool,
+) -> (String, Vec<String>) {
+    let args = args.trim();
+    let prompt = if native || command.scope == "default" {
+        if args.is_empty() {
+            command.full_command.clone()
+        } else {
+            format!("{} {}", command.full_command, args)
+        }
+    } else if command.accepts_arguments {
+        command.content.replace("$ARGUMENTS", args)
+    } else if args.is_empty() {
+        command.content.clone()
+    } else {
+        format!("{}\n\n{}", command.content, args)
+    };
+    (prompt, command.allowed_tools.clone())
+}
+
+/// Find a command by its full name, with or without the leading slash
+pub async fn find_slash_command(
+    project_path: &str,
+    name: &str,
+) -> Result<SlashCommand, String> {
+    let wanted = format!("/{}", name.trim().trim_start_matches('/'));
+    slash_commands_list(Some(project_path.to_string()))
+        .await?
+        .into_iter()
+        // Project commands shadow user commands of the same name
+        .filter(|cmd| cmd.full_command == wanted)
+        .min_by_key(|cmd| match cmd.scope.as_str() {
+            "project" => 0,
+            "user" => 1,
+            _ => 2,
+        })
+        .ok_or_else(|| format!("Slash command not found: {}", wanted))
+}
+
 /// Create default/built-in slash commands
 fn create_default_commands() -> Vec<SlashCommand> {
     vec![
@@ -406,7 +463,7 @@ pub async fn slash_command_save(
     full_content.push_str(&content);
 
     // Write file
-    fs::write(&file_path, &full_content)
+    crate::atomic_file::write_atomic(&file_path, &full_content)
         .map_err(|e| format!("Failed to write command file: {}", e))?;
 
     // Load and return the saved command
@@ -471,3 +528,47 @@ fn remove_empty_dirs(dir: &Path) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(scope: &str, content: &str) -> SlashCommand {
+        SlashCommand {
+            id: format!("{}-review", scope),
+            name: "review".to_string(),
+            full_command: "/frontend:review".to_string(),
+            scope: scope.to_string(),
+            namespace: Some("frontend".to_string()),
+            file_path: String::new(),
+            content: content.to_string(),
+            description: None,
+            allowed_tools: vec!["Read".to_string()],
+            has_bash_commands: false,
+            has_file_references: false,
+            accepts_arguments: content.contains("$ARGUMENTS"),
+        }
+    }
+
+    #[test]
+    fn test_compose_prompt_native_and_inlined() {
+        let cmd = command("project", "Review $ARGUMENTS for bugs");
+        let (prompt, tools) = compose_slash_command_prompt(&cmd, " src/app.tsx ", true);
+        assert_eq!(prompt, "/frontend:review src/app.tsx");
+        assert_eq!(tools, vec!["Read"]);
+
+        let (prompt, _) = compose_slash_command_prompt(&cmd, "src/app.tsx", false);
+        assert_eq!(prompt, "Review src/app.tsx for bugs");
+
+        let cmd = command("user", "Review the diff");
+        let (prompt, _) = compose_slash_command_prompt(&cmd, "carefully", false);
+        assert_eq!(prompt, "Review the diff\n\ncarefully");
+    }
+
+    #[test]
+    fn test_print_mode_support_by_version() {
+        assert!(supports_print_mode_slash_commands(Some("1.0.41")));
+        assert!(!supports_print_mode_slash_commands(Some("0.2.9")));
+        assert!(!supports_print_mode_slash_commands(None));
+    }
+}
@@ -0,0 +1,670 @@
+#![allow(dead_code)]
+
+//! Managed SSH tunnels to SSE/HTTP MCP servers running on a remote host. A tunnel forwards a
+//! fixed local port to a port reachable from the host (`ssh -N -L`), so an MCP server added
+//! with the URL `tunnel://<name>/<path>` is stored with `http://127.0.0.1:<port>/<path>` and the
+//! local CLI reaches it like any local server. The local port is chosen once and kept, so the
+//! stored URL stays valid across restarts.
+//!
+//! Each running tunnel has a supervisor that restarts `ssh` with a backoff when the
+//! connection drops. Tunnels used by MCP servers start with the app; all of them are torn
+//! down when it exits.
+
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::remote_hosts::{key_file, load_host, RemoteHost};
+
+/// Event carrying a tunnel's status whenever it changes
+pub const TUNNEL_EVENT: &str = "ssh-tunnel-changed";
+
+/// URL scheme of MCP servers reached through a tunnel
+const TUNNEL_SCHEME: &str = "tunnel://";
+
+/// How long a new `ssh` gets to open the forward before it counts as failed
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Reconnect delays double from the first up to the last
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A connection up this long resets the backoff
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+static TUNNELS: Mutex<Option<HashMap<i64, TunnelHandle>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TunnelState {
+    Connecting,
+    Up,
+    Reconnecting,
+    Stopped,
+}
+
+/// A forward of `127.0.0.1:local_port` to `remote_host:remote_port` as seen from a host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshTunnel {
+    pub id: Option<i64>,
+    pub name: String,
+    pub host_id: i64,
+    /// Target as resolved on the SSH host; `localhost` for servers on the host itself
+    pub remote_host: String,
+    pub remote_port: u16,
+    pub local_port: u16,
+    pub created_at: Option<String>,
+}
+
+/// A tunnel with its live status and the MCP servers using it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelStatus {
+    pub tunnel: SshTunnel,
+    pub state: TunnelState,
+    pub last_error: Option<String>,
+    pub reconnects: u32,
+    pub mcp_servers: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct LiveStatus {
+    state: TunnelState,
+    last_error: Option<String>,
+    reconnects: u32,
+}
+
+/// A running tunnel, shared with its supervisor
+#[derive(Clone)]
+struct TunnelHandle {
+    stop: Arc<AtomicBool>,
+    /// The current `ssh`, killed directly on exit as the supervisor may not get to run
+    pid: Arc<Mutex<Option<u32>>>,
+    status: Arc<Mutex<LiveStatus>>,
+}
+
+pub fn init_tunnel_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ssh_tunnels (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            host_id INTEGER NOT NULL REFERENCES remote_hosts(id) ON DELETE CASCADE,
+            remote_host TEXT NOT NULL DEFAULT 'localhost',
+            remote_port INTEGER NOT NULL,
+            local_port INTEGER NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_server_tunnels (
+            server_name TEXT PRIMARY KEY,
+            tunnel_id INTEGER NOT NULL REFERENCES ssh_tunnels(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const TUNNEL_COLUMNS: &str = "id, name, host_id, remote_host, remote_port, local_port, created_at";
+
+fn row_to_tunnel(row: &rusqlite::Row) -> SqliteResult<SshTunnel> {
+    Ok(SshTunnel {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        host_id: row.get(2)?,
+        remote_host: row.get(3)?,
+        remote_port: row.get(4)?,
+        local_port: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+fn load_tunnel(
+    conn: &Connection,
+    condition: &str,
+    value: &dyn rusqlite::ToSql,
+) -> SqliteResult<Option<SshTunnel>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM ssh_tunnels WHERE {}",
+            TUNNEL_COLUMNS, condition
+        ),
+        [value],
+        row_to_tunnel,
+    )
+    .optional()
+}
+
+fn tunnel_servers(conn: &Connection, tunnel_id: i64) -> SqliteResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT server_name FROM mcp_server_tunnels WHERE tunnel_id = ?1 ORDER BY server_name",
+    )?;
+    let servers = stmt
+        .query_map(params![tunnel_id], |row| row.get(0))?
+        .collect::<SqliteResult<Vec<String>>>();
+    servers
+}
+
+/// A free local port, for a new tunnel to keep
+fn free_local_port() -> Result<u16, String> {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Failed to find a free local port: {}", e))
+}
+
+/// Split `tunnel://<name>/<path>` into the tunnel name and the path
+pub fn parse_tunnel_url(url: &str) -> Option<(&str, &str)> {
+    let rest = url.strip_prefix(TUNNEL_SCHEME)?;
+    let (name, path) = rest.find('/').map_or((rest, ""), |at| rest.split_at(at));
+    Some((name, path)).filter(|(name, _)| !name.is_empty())
+}
+
+impl SshTunnel {
+    /// The `ssh` arguments holding the forward open without running a command
+    fn ssh_args(&self, host: &RemoteHost, key: Option<&std::path::Path>) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "-N".to_string(),
+            "-o".to_string(),
+            "ExitOnForwardFailure=yes".to_string(),
+            "-o".to_string(),
+            "ServerAliveCountMax=3".to_string(),
+            "-L".to_string(),
+            format!(
+                "127.0.0.1:{}:{}:{}",
+                self.local_port, self.remote_host, self.remote_port
+            ),
+        ];
+        args.extend(host.ssh_args(key));
+        args
+    }
+
+    pub fn local_url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.local_port, path)
+    }
+}
+
+/// Record and emit a tunnel's new state. The last error is kept until the tunnel is up.
+fn set_status(
+    app: &AppHandle,
+    tunnel: &SshTunnel,
+    handle: &TunnelHandle,
+    state: TunnelState,
+    error: Option<String>,
+    reconnected: bool,
+) {
+    let Ok(mut status) = handle.status.lock() else {
+        return;
+    };
+    status.state = state;
+    if error.is_some() || state == TunnelState::Up {
+        status.last_error = error;
+    }
+    if reconnected {
+        status.reconnects += 1;
+    }
+    let _ = app.emit(
+        TUNNEL_EVENT,
+        TunnelStatus {
+            tunnel: tunnel.clone(),
+            state,
+            last_error: status.last_error.clone(),
+            reconnects: status.reconnects,
+            mcp_servers: Vec::new(),
+        },
+    );
+}
+
+/// Wait until the local end of the forward accepts connections or `ssh` exits
+async fn wait_until_up(child: &mut tokio::process::Child, port: u16) -> Result<(), String> {
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("ssh exited with {}", status));
+        }
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    Err("Timed out opening the forward".to_string())
+}
+
+/// Keep `ssh` running for `tunnel` until stopped, reconnecting with a backoff
+async fn supervise(app: AppHandle, tunnel: SshTunnel, host: RemoteHost, handle: TunnelHandle) {
+    let mut backoff = MIN_BACKOFF;
+    let mut reconnecting = false;
+    while !handle.stop.load(Ordering::SeqCst) {
+        let state = if reconnecting {
+            TunnelState::Reconnecting
+        } else {
+            TunnelState::Connecting
+        };
+        set_status(&app, &tunnel, &handle, state, None, false);
+        let spawned = key_file(&host).and_then(|key| {
+            tokio::process::Command::new("ssh")
+                .args(tunnel.ssh_args(&host, key.as_ref().map(|key| key.path())))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map(|child| (child, key))
+                .map_err(|e| format!("Failed to run ssh: {}", e))
+        });
+        let error = match spawned {
+            // The key file is deleted once this ssh process is done with it
+            Ok((mut child, _key)) => {
+                if let Ok(mut pid) = handle.pid.lock() {
+                    *pid = child.id();
+                }
+                let started_at = Instant::now();
+                let failure = match wait_until_up(&mut child, tunnel.local_port).await {
+                    Ok(()) => {
+                        info!(
+                            "SSH tunnel '{}' is up on port {}",
+                            tunnel.name, tunnel.local_port
+                        );
+                        set_status(&app, &tunnel, &handle, TunnelState::Up, None, reconnecting);
+                        wait_or_stop(&mut child, &handle.stop).await;
+                        None
+                    }
+                    Err(e) => {
+                        let _ = child.start_kill();
+                        let _ = child.wait().await;
+                        Some(e)
+                    }
+                };
+                if let Ok(mut pid) = handle.pid.lock() {
+                    *pid = None;
+                }
+                if started_at.elapsed() >= STABLE_AFTER {
+                    backoff = MIN_BACKOFF;
+                }
+                stderr_tail(&mut child).await.or(failure)
+            }
+            Err(e) => Some(e),
+        };
+        if handle.stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let error = error.unwrap_or_else(|| "ssh exited".to_string());
+        warn!("SSH tunnel '{}' dropped: {}", tunnel.name, error);
+        set_status(
+            &app,
+            &tunnel,
+            &handle,
+            TunnelState::Reconnecting,
+            Some(error),
+            false,
+        );
+        reconnecting = true;
+        sleep_or_stop(backoff, &handle.stop).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+    set_status(&app, &tunnel, &handle, TunnelState::Stopped, None, false);
+}
+
+/// What ssh reported before exiting, such as a refused forward or a failed login
+async fn stderr_tail(child: &mut tokio::process::Child) -> Option<String> {
+    let stderr = child.stderr.take()?;
+    let mut reader = tokio::io::BufReader::new(stderr);
+    let mut last = None;
+    while let Ok(Some(line)) = crate::claude_binary::read_decoded_line(&mut reader).await {
+        if !line.trim().is_empty() {
+            last = Some(line.trim().to_string());
+        }
+    }
+    last
+}
+
+async fn wait_or_stop(child: &mut tokio::process::Child, stop: &AtomicBool) {
+    loop {
+        tokio::select! {
+            _ = child.wait() => return,
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {
+                if stop.load(Ordering::SeqCst) {
+                    let _ = child.start_kill();
+                    let _ = child.wait().await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn sleep_or_stop(duration: Duration, stop: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline && !stop.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Start supervising `tunnel`, unless it already runs
+fn start(app: &AppHandle, tunnel: SshTunnel, host: RemoteHost) -> Result<(), String> {
+    let id = tunnel.id.ok_or("Tunnel id is required")?;
+    let mut tunnels = TUNNELS.lock().map_err(|e| e.to_string())?;
+    let tunnels = tunnels.get_or_insert_with(HashMap::new);
+    if tunnels.contains_key(&id) {
+        return Ok(());
+    }
+    let handle = TunnelHandle {
+        stop: Arc::new(AtomicBool::new(false)),
+        pid: Arc::new(Mutex::new(None)),
+        status: Arc::new(Mutex::new(LiveStatus {
+            state: TunnelState::Connecting,
+            last_error: None,
+            reconnects: 0,
+        })),
+    };
+    tunnels.insert(id, handle.clone());
+    tauri::async_runtime::spawn(supervise(app.clone(), tunnel, host, handle));
+    Ok(())
+}
+
+/// Kill the `ssh` of a tunnel right away, without waiting for its supervisor
+fn kill_pid(pid: u32) {
+    let _ = if cfg!(target_os = "windows") {
+        std::process::Command::new("taskkill")
+            .args(["/F", "/PID", &pid.to_string()])
+            .output()
+    } else {
+        std::process::Command::new("kill")
+            .args(["-TERM", &pid.to_string()])
+            .output()
+    };
+}
+
+fn stop(id: i64) {
+    let handle = TUNNELS
+        .lock()
+        .ok()
+        .and_then(|mut tunnels| tunnels.as_mut()?.remove(&id));
+    if let Some(handle) = handle {
+        handle.stop.store(true, Ordering::SeqCst);
+        if let Some(pid) = handle.pid.lock().ok().and_then(|pid| *pid) {
+            kill_pid(pid);
+        }
+    }
+}
+
+/// Tear down every tunnel, as the app exits
+pub fn stop_all_tunnels() {
+    let ids: Vec<i64> = TUNNELS
+        .lock()
+        .ok()
+        .and_then(|tunnels| Some(tunnels.as_ref()?.keys().copied().collect()))
+        .unwrap_or_default();
+    for id in ids {
+        stop(id);
+    }
+}
+
+fn status_of(conn: &Connection, tunnel: SshTunnel) -> TunnelStatus {
+    let status = tunnel
+        .id
+        .and_then(|id| {
+            let tunnels = TUNNELS.lock().ok()?;
+            let status = tunnels.as_ref()?.get(&id)?.status.lock().ok()?.clone();
+            Some(status)
+        })
+        .unwrap_or(LiveStatus {
+            state: TunnelState::Stopped,
+            last_error: None,
+            reconnects: 0,
+        });
+    let mcp_servers = tunnel
+        .id
+        .map(|id| tunnel_servers(conn, id).unwrap_or_default())
+        .unwrap_or_default();
+    TunnelStatus {
+        tunnel,
+        state: status.state,
+        last_error: status.last_error,
+        reconnects: status.reconnects,
+        mcp_servers,
+    }
+}
+
+fn start_by_id(app: &AppHandle, conn: &Connection, id: i64) -> Result<SshTunnel, String> {
+    let tunnel = load_tunnel(conn, "id = ?1", &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Tunnel {} not found", id))?;
+    let host = load_host(conn, tunnel.host_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Remote host {} not found", tunnel.host_id))?;
+    start(app, tunnel.clone(), host)?;
+    Ok(tunnel)
+}
+
+/// For an MCP server added with a `tunnel://` URL, the tunnel's local URL and id. The tunnel
+/// is started, so the server can be probed and added right away.
+pub fn resolve_tunnel_url(app: &AppHandle, url: &str) -> Result<Option<(String, i64)>, String> {
+    let Some((name, path)) = parse_tunnel_url(url) else {
+        return Ok(None);
+    };
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tunnel = load_tunnel(&conn, "name = ?1", &name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No SSH tunnel named '{}'", name))?;
+    let id = tunnel.id.ok_or("Tunnel id is required")?;
+    start_by_id(app, &conn, id)?;
+    Ok(Some((tunnel.local_url(path), id)))
+}
+
+/// Remember that `server` reaches its URL through tunnel `tunnel_id`, so the tunnel starts with
+/// the app
+pub fn link_server(app: &AppHandle, server: &str, tunnel_id: i64) -> Result<(), String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO mcp_server_tunnels (server_name, tunnel_id) VALUES (?1, ?2)",
+        params![server, tunnel_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn unlink_server(conn: &Connection, server: &str) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM mcp_server_tunnels WHERE server_name = ?1",
+        params![server],
+    )?;
+    Ok(())
+}
+
+/// Start the tunnels MCP servers use
+pub fn spawn_tunnels(app: AppHandle) {
+    let Some(db) = app.try_state::<AgentDb>() else {
+        return;
+    };
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    let ids: Vec<i64> = conn
+        .prepare("SELECT DISTINCT tunnel_id FROM mcp_server_tunnels")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<SqliteResult<Vec<i64>>>()
+        })
+        .unwrap_or_default();
+    for id in ids {
+        if let Err(e) = start_by_id(&app, &conn, id) {
+            warn!("Failed to start SSH tunnel {}: {}", id, e);
+        }
+    }
+}
+
+/// Forward a local port to `remote_port` on a host (or `remote_host` as the host sees it) and
+/// start it. An existing tunnel to the same target is reused.
+#[tauri::command]
+pub async fn create_tunnel(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    host_id: i64,
+    remote_port: u16,
+    remote_host: Option<String>,
+    name: Option<String>,
+) -> Result<SshTunnel, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let host = load_host(&conn, host_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Remote host {} not found", host_id))?;
+    let remote_host = remote_host
+        .map(|remote_host| remote_host.trim().to_string())
+        .filter(|remote_host| !remote_host.is_empty())
+        .unwrap_or_else(|| "localhost".to_string());
+    // IPv6 addresses must be bracketed, as `-L` separates its parts with colons
+    if remote_host.starts_with('-')
+        || remote_host.contains(char::is_whitespace)
+        || (remote_host.contains(':') && !remote_host.starts_with('['))
+    {
+        return Err("Invalid remote host".to_string());
+    }
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM ssh_tunnels WHERE host_id = ?1 AND remote_host = ?2 AND remote_port = ?3",
+            params![host_id, remote_host, remote_port],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let id = match existing {
+        Some(id) => id,
+        None => {
+            let name = name
+                .map(|name| super::worktrees::slug(&name))
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| {
+                    super::worktrees::slug(&format!("{}-{}", host.name, remote_port))
+                });
+            conn.execute(
+                "INSERT INTO ssh_tunnels (name, host_id, remote_host, remote_port, local_port)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![name, host_id, remote_host, remote_port, free_local_port()?],
+            )
+            .map_err(|e| format!("Failed to create tunnel: {}", e))?;
+            conn.last_insert_rowid()
+        }
+    };
+    start_by_id(&app, &conn, id)
+}
+
+/// List tunnels with their live status and the MCP servers using them
+#[tauri::command]
+pub async fn list_tunnels(db: State<'_, AgentDb>) -> Result<Vec<TunnelStatus>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM ssh_tunnels ORDER BY name",
+            TUNNEL_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let tunnels = stmt
+        .query_map([], row_to_tunnel)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tunnels
+        .into_iter()
+        .map(|tunnel| status_of(&conn, tunnel))
+        .collect())
+}
+
+/// Start a stopped tunnel
+#[tauri::command]
+pub async fn start_tunnel(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<SshTunnel, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    start_by_id(&app, &conn, id)
+}
+
+/// Stop a tunnel, keeping it for later
+#[tauri::command]
+pub async fn stop_tunnel(id: i64) -> Result<(), String> {
+    stop(id);
+    Ok(())
+}
+
+/// Stop and delete a tunnel. MCP servers using it stay configured but can't connect.
+#[tauri::command]
+pub async fn delete_tunnel(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    stop(id);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM mcp_server_tunnels WHERE tunnel_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM ssh_tunnels WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete tunnel: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::remote_hosts::KeySource;
+    use super::*;
+
+    #[test]
+    fn test_tunnel_urls_split_into_name_and_path() {
+        assert_eq!(
+            parse_tunnel_url("tunnel://search/sse"),
+            Some(("search", "/sse"))
+        );
+        assert_eq!(
+            parse_tunnel_url("tunnel://search/mcp/v1?x=1"),
+            Some(("search", "/mcp/v1?x=1"))
+        );
+        assert_eq!(parse_tunnel_url("tunnel://search"), Some(("search", "")));
+        assert_eq!(parse_tunnel_url("tunnel:///sse"), None);
+        assert_eq!(parse_tunnel_url("http://localhost:3000/sse"), None);
+    }
+
+    #[test]
+    fn test_tunnel_forwards_loopback_port_without_a_command() {
+        let host = RemoteHost {
+            id: Some(1),
+            name: "build box".to_string(),
+            host: "build.example.com".to_string(),
+            user: Some("dev".to_string()),
+            port: Some(2222),
+            key_source: KeySource::Agent,
+            path_mappings: Vec::new(),
+            claude_path: None,
+            claude_version: None,
+            detected_at: None,
+        };
+        let tunnel = SshTunnel {
+            id: Some(3),
+            name: "search".to_string(),
+            host_id: 1,
+            remote_host: "localhost".to_string(),
+            remote_port: 8080,
+            local_port: 41234,
+            created_at: None,
+        };
+        let args = tunnel.ssh_args(&host, None);
+        assert_eq!(&args[..2], ["-N", "-o"]);
+        assert!(args.contains(&"127.0.0.1:41234:localhost:8080".to_string()));
+        assert_eq!(
+            args.last().map(String::as_str),
+            Some("dev@build.example.com")
+        );
+        assert_eq!(tunnel.local_url("/sse"), "http://127.0.0.1:41234/sse");
+    }
+}
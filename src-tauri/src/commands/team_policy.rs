@@ -0,0 +1,402 @@
+#![allow(dead_code)]
+
+//! Team policy checked into a repository as `opcode.policy.json`, letting admins hold every
+//! clone of the project to the same rules: MCP servers that must or must not be configured,
+//! permission rules that must be denied or may not be allowed, and the models sessions may
+//! use. Violations are reported by `validate_team_policy`; with `enforce` set, adding a
+//! forbidden server or starting a run in a non-compliant project fails.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Name of the policy file at the project root
+pub const POLICY_FILE_NAME: &str = "opcode.policy.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpPolicy {
+    /// Reject servers added in a scope other than the recommended one
+    pub enforce_recommended_scope: bool,
+    /// Servers that must be configured for the project
+    pub required: Vec<String>,
+    /// Servers that may not be configured in any scope
+    pub forbidden: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PermissionPolicy {
+    /// Rules that must appear in a `permissions.deny` list
+    pub required_deny: Vec<String>,
+    /// Rules that may not appear in any `permissions.allow` list
+    pub forbidden_allow: Vec<String>,
+}
+
+/// Models are matched case-insensitively by substring, so `opus` covers every Opus release
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModelRules {
+    /// When not empty, only these models may be used
+    pub allowed: Vec<String>,
+    pub forbidden: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TeamPolicy {
+    /// Block non-compliant MCP adds and runs instead of only reporting them
+    pub enforce: bool,
+    pub mcp: McpPolicy,
+    pub permissions: PermissionPolicy,
+    pub models: ModelRules,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    MissingMcpServer,
+    ForbiddenMcpServer,
+    MissingDenyRule,
+    ForbiddenAllowRule,
+    ForbiddenModel,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub kind: ViolationKind,
+    /// Server name, permission rule or model
+    pub subject: String,
+    pub message: String,
+}
+
+/// The parts of a project's Claude Code configuration a policy constrains
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    /// Servers configured in any scope
+    pub mcp_servers: BTreeSet<String>,
+    pub allow_rules: BTreeSet<String>,
+    pub deny_rules: BTreeSet<String>,
+    /// Model selected in settings, most specific file first
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyReport {
+    pub policy_path: String,
+    /// The project has a policy file
+    pub found: bool,
+    pub enforce: bool,
+    pub violations: Vec<PolicyViolation>,
+}
+
+pub fn policy_path(project_path: &Path) -> PathBuf {
+    project_path.join(POLICY_FILE_NAME)
+}
+
+/// The project's policy, or `None` when it doesn't have one
+pub fn load_policy(project_path: &Path) -> Result<Option<TeamPolicy>, String> {
+    let path = policy_path(project_path);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Invalid {}: {}", POLICY_FILE_NAME, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn matches_model(model: &str, pattern: &str) -> bool {
+    model.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+impl TeamPolicy {
+    /// Why `model` may not be used, if it may not
+    pub fn model_violation(&self, model: &str) -> Option<PolicyViolation> {
+        let forbidden = self
+            .models
+            .forbidden
+            .iter()
+            .find(|pattern| matches_model(model, pattern));
+        let message = if let Some(pattern) = forbidden {
+            format!(
+                "Model {} is forbidden by the team policy ({})",
+                model, pattern
+            )
+        } else if !self.models.allowed.is_empty()
+            && !self
+                .models
+                .allowed
+                .iter()
+                .any(|pattern| matches_model(model, pattern))
+        {
+            format!(
+                "Model {} is not one of the allowed models: {}",
+                model,
+                self.models.allowed.join(", ")
+            )
+        } else {
+            return None;
+        };
+        Some(PolicyViolation {
+            kind: ViolationKind::ForbiddenModel,
+            subject: model.to_string(),
+            message,
+        })
+    }
+
+    /// Why a server named `name` may not be added, if it may not
+    pub fn server_violation(&self, name: &str) -> Option<PolicyViolation> {
+        self.mcp
+            .forbidden
+            .iter()
+            .any(|forbidden| forbidden == name)
+            .then(|| PolicyViolation {
+                kind: ViolationKind::ForbiddenMcpServer,
+                subject: name.to_string(),
+                message: format!("MCP server {} is forbidden by the team policy", name),
+            })
+    }
+
+    /// Everything in `config` that breaks the policy
+    pub fn evaluate(&self, config: &ProjectConfig) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        for name in &self.mcp.required {
+            if !config.mcp_servers.contains(name) {
+                violations.push(PolicyViolation {
+                    kind: ViolationKind::MissingMcpServer,
+                    subject: name.clone(),
+                    message: format!("Required MCP server {} is not configured", name),
+                });
+            }
+        }
+        violations.extend(
+            config
+                .mcp_servers
+                .iter()
+                .filter_map(|name| self.server_violation(name)),
+        );
+        for rule in &self.permissions.required_deny {
+            if !config.deny_rules.contains(rule) {
+                violations.push(PolicyViolation {
+                    kind: ViolationKind::MissingDenyRule,
+                    subject: rule.clone(),
+                    message: format!("Permission rule {} must be denied", rule),
+                });
+            }
+        }
+        for rule in &self.permissions.forbidden_allow {
+            if config.allow_rules.contains(rule) {
+                violations.push(PolicyViolation {
+                    kind: ViolationKind::ForbiddenAllowRule,
+                    subject: rule.clone(),
+                    message: format!("Permission rule {} may not be allowed", rule),
+                });
+            }
+        }
+        if let Some(model) = &config.model {
+            violations.extend(self.model_violation(model));
+        }
+        violations
+    }
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn object_keys(value: Option<&Value>) -> impl Iterator<Item = String> + '_ {
+    value
+        .and_then(|value| value.as_object())
+        .into_iter()
+        .flat_map(|object| object.keys().cloned())
+}
+
+fn string_list(value: Option<&Value>) -> impl Iterator<Item = String> + '_ {
+    value
+        .and_then(|value| value.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_str().map(str::to_string))
+}
+
+/// Servers, permission rules and model configured for `project_path` across the user and
+/// project scopes
+pub fn collect_project_config(project_path: &Path, home: &Path) -> ProjectConfig {
+    let mut config = ProjectConfig::default();
+
+    if let Some(claude_json) = read_json(&home.join(".claude.json")) {
+        config
+            .mcp_servers
+            .extend(object_keys(claude_json.get("mcpServers")));
+        let project_key = project_path.to_string_lossy();
+        config.mcp_servers.extend(object_keys(
+            claude_json
+                .get("projects")
+                .and_then(|projects| projects.get(project_key.as_ref()))
+                .and_then(|project| project.get("mcpServers")),
+        ));
+    }
+    if let Some(mcp_json) = read_json(&project_path.join(".mcp.json")) {
+        config
+            .mcp_servers
+            .extend(object_keys(mcp_json.get("mcpServers")));
+    }
+
+    let settings_files = [
+        project_path.join(".claude").join("settings.local.json"),
+        project_path.join(".claude").join("settings.json"),
+        home.join(".claude").join("settings.json"),
+    ];
+    for settings in settings_files.iter().filter_map(|path| read_json(path)) {
+        let permissions = settings.get("permissions");
+        config.allow_rules.extend(string_list(
+            permissions.and_then(|permissions| permissions.get("allow")),
+        ));
+        config.deny_rules.extend(string_list(
+            permissions.and_then(|permissions| permissions.get("deny")),
+        ));
+        if config.model.is_none() {
+            config.model = settings
+                .get("model")
+                .and_then(|model| model.as_str())
+                .map(str::to_string);
+        }
+    }
+    config
+}
+
+/// Fail when the project enforces its policy and its configuration or `model` break it
+pub fn check_run_allowed(project_path: &str, model: Option<&str>) -> Result<(), String> {
+    let Some(policy) = load_policy(Path::new(project_path))? else {
+        return Ok(());
+    };
+    if !policy.enforce {
+        return Ok(());
+    }
+    let home = dirs::home_dir().ok_or("Failed to get home directory")?;
+    let mut config = collect_project_config(Path::new(project_path), &home);
+    // The run's own model overrides the one in settings
+    if let Some(model) = model {
+        config.model = Some(model.to_string());
+    }
+    let violations = policy.evaluate(&config);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Blocked by team policy: {}",
+        violations
+            .iter()
+            .map(|violation| violation.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    ))
+}
+
+/// The project's policy file, if it has one
+#[tauri::command]
+pub async fn get_team_policy(project_path: String) -> Result<Option<TeamPolicy>, String> {
+    load_policy(Path::new(&project_path))
+}
+
+/// Check the project's current MCP servers, permission rules and model against its policy
+#[tauri::command]
+pub async fn validate_team_policy(project_path: String) -> Result<PolicyReport, String> {
+    let project = Path::new(&project_path);
+    let policy = load_policy(project)?;
+    let home = dirs::home_dir().ok_or("Failed to get home directory")?;
+    let violations = policy
+        .as_ref()
+        .map(|policy| policy.evaluate(&collect_project_config(project, &home)))
+        .unwrap_or_default();
+    Ok(PolicyReport {
+        policy_path: policy_path(project).to_string_lossy().to_string(),
+        found: policy.is_some(),
+        enforce: policy.is_some_and(|policy| policy.enforce),
+        violations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_evaluate_reports_each_kind_of_violation() {
+        let policy: TeamPolicy = serde_json::from_value(json!({
+            "enforce": true,
+            "mcp": {"required": ["github"], "forbidden": ["filesystem"]},
+            "permissions": {"requiredDeny": ["Bash(rm:*)"], "forbiddenAllow": ["Bash(*)"]},
+            "models": {"allowed": ["sonnet"]}
+        }))
+        .unwrap();
+        let config = ProjectConfig {
+            mcp_servers: ["filesystem".to_string()].into_iter().collect(),
+            allow_rules: ["Bash(*)".to_string()].into_iter().collect(),
+            deny_rules: BTreeSet::new(),
+            model: Some("claude-opus-4-20250514".to_string()),
+        };
+        let kinds: Vec<ViolationKind> = policy
+            .evaluate(&config)
+            .iter()
+            .map(|violation| violation.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ViolationKind::MissingMcpServer,
+                ViolationKind::ForbiddenMcpServer,
+                ViolationKind::MissingDenyRule,
+                ViolationKind::ForbiddenAllowRule,
+                ViolationKind::ForbiddenModel,
+            ]
+        );
+        assert!(policy.model_violation("claude-sonnet-4-20250514").is_none());
+        assert!(TeamPolicy::default().evaluate(&config).is_empty());
+    }
+
+    #[test]
+    fn test_collect_project_config_merges_scopes() {
+        let home = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let project_key = project.path().to_string_lossy().to_string();
+        std::fs::write(
+            home.path().join(".claude.json"),
+            json!({
+                "mcpServers": {"fetch": {}},
+                "projects": {project_key: {"mcpServers": {"secrets": {}}}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            project.path().join(".mcp.json"),
+            json!({"mcpServers": {"github": {}}}).to_string(),
+        )
+        .unwrap();
+        std::fs::create_dir_all(project.path().join(".claude")).unwrap();
+        std::fs::write(
+            project.path().join(".claude").join("settings.json"),
+            json!({"model": "sonnet", "permissions": {"deny": ["Bash(rm:*)"]}}).to_string(),
+        )
+        .unwrap();
+        std::fs::create_dir_all(home.path().join(".claude")).unwrap();
+        std::fs::write(
+            home.path().join(".claude").join("settings.json"),
+            json!({"model": "opus", "permissions": {"allow": ["Read"]}}).to_string(),
+        )
+        .unwrap();
+
+        let config = collect_project_config(project.path(), home.path());
+        let servers: Vec<&str> = config.mcp_servers.iter().map(String::as_str).collect();
+        assert_eq!(servers, vec!["fetch", "github", "secrets"]);
+        assert!(config.deny_rules.contains("Bash(rm:*)"));
+        assert!(config.allow_rules.contains("Read"));
+        assert_eq!(config.model.as_deref(), Some("sonnet"));
+    }
+}
@@ -0,0 +1,365 @@
+#![allow(dead_code)]
+
+//! Opt-in, local-only usage counters: commands invoked, runs executed and errors by kind,
+//! counted per day in the local database. Nothing is ever uploaded; `export_telemetry`
+//! writes the summary to a file the user picks, so they can attach it to a bug report when
+//! maintainers ask for stats.
+//!
+//! Only names are counted, never arguments, prompts, paths or error messages. Counts are
+//! kept in memory and flushed to the database once a minute and at exit.
+
+use chrono::{Duration, Local};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use super::agents::AgentDb;
+use super::error::OpcodeError;
+use super::settings::set_setting_as;
+
+/// Setting key turning the counters on
+pub const TELEMETRY_ENABLED_KEY: &str = "telemetry_enabled";
+
+/// How often in-memory counts are written to the database
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Days of counts kept; older ones are pruned on flush
+const RETENTION_DAYS: i64 = 90;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Counts not yet written to the database
+static PENDING: Mutex<Option<HashMap<(Category, String), i64>>> = Mutex::new(None);
+
+/// What a counter counts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Tauri commands invoked by the frontend
+    Command,
+    /// Agent runs and Claude sessions started
+    Run,
+    /// Errors logged, by `ErrorKind`
+    Error,
+}
+
+impl Category {
+    fn as_str(self) -> &'static str {
+        match self {
+            Category::Command => "command",
+            Category::Run => "run",
+            Category::Error => "error",
+        }
+    }
+}
+
+/// A counter and its total over the summary period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TelemetryCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Totals per counter over the last `days` days, largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetrySummary {
+    pub enabled: bool,
+    pub days: u32,
+    /// First day with any counts, as YYYY-MM-DD
+    pub since: Option<String>,
+    pub commands: Vec<TelemetryCount>,
+    pub runs: Vec<TelemetryCount>,
+    pub errors: Vec<TelemetryCount>,
+}
+
+/// File written by `export_telemetry`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryExport {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub exported_at: String,
+    pub summary: TelemetrySummary,
+}
+
+pub fn init_telemetry_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS telemetry_counters (
+            category TEXT NOT NULL,
+            name TEXT NOT NULL,
+            day TEXT NOT NULL,
+            count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (category, name, day)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Turn counting on or off from the stored setting; off drops counts not yet flushed
+pub fn apply_telemetry_setting(enabled: Option<bool>) {
+    let enabled = enabled.unwrap_or(false);
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        if let Ok(mut pending) = PENDING.lock() {
+            *pending = None;
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Count one occurrence of `name`
+pub fn record(category: Category, name: &str) {
+    if !is_enabled() {
+        return;
+    }
+    // Never block: this runs on every command and from the logger
+    if let Ok(mut pending) = PENDING.try_lock() {
+        *pending
+            .get_or_insert_with(HashMap::new)
+            .entry((category, name.to_string()))
+            .or_insert(0) += 1;
+    }
+}
+
+/// Count a logged error under its kind; the message itself is not kept
+pub fn record_error(message: &str) {
+    if !is_enabled() {
+        return;
+    }
+    let kind = serde_json::to_value(OpcodeError::classify(message))
+        .ok()
+        .and_then(|kind| kind.as_str().map(str::to_string))
+        .unwrap_or_else(|| "internal".to_string());
+    record(Category::Error, &kind);
+}
+
+/// Wrap the invoke handler to count every command by name
+pub fn counted<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        record(Category::Command, invoke.message.command());
+        handler(invoke)
+    }
+}
+
+/// Write pending counts to today's rows and prune expired days
+pub fn flush(conn: &Connection) -> SqliteResult<()> {
+    let pending = match PENDING.lock() {
+        Ok(mut pending) => pending.take(),
+        Err(_) => None,
+    };
+    let Some(pending) = pending else {
+        return Ok(());
+    };
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    for ((category, name), count) in pending {
+        conn.execute(
+            "INSERT INTO telemetry_counters (category, name, day, count) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(category, name, day) DO UPDATE SET count = count + excluded.count",
+            params![category.as_str(), name, today, count],
+        )?;
+    }
+    let cutoff = (Local::now() - Duration::days(RETENTION_DAYS))
+        .format("%Y-%m-%d")
+        .to_string();
+    conn.execute(
+        "DELETE FROM telemetry_counters WHERE day < ?1",
+        params![cutoff],
+    )?;
+    Ok(())
+}
+
+/// Flush counts to the database once a minute
+pub fn spawn_telemetry_flusher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !is_enabled() {
+                continue;
+            }
+            let db = app.state::<AgentDb>();
+            let result = match db.0.lock() {
+                Ok(conn) => flush(&conn),
+                Err(_) => continue,
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to store telemetry counters: {}", e);
+            }
+        }
+    });
+}
+
+/// Keep the counts of the last minute before the app exits
+pub fn flush_on_exit(app: &AppHandle) {
+    if !is_enabled() {
+        return;
+    }
+    if let Some(db) = app.try_state::<AgentDb>() {
+        if let Ok(conn) = db.0.lock() {
+            let _ = flush(&conn);
+        }
+    }
+}
+
+pub fn summarize(conn: &Connection, days: u32) -> SqliteResult<TelemetrySummary> {
+    let cutoff = (Local::now() - Duration::days(i64::from(days.max(1)) - 1))
+        .format("%Y-%m-%d")
+        .to_string();
+    let totals = |category: Category| -> SqliteResult<Vec<TelemetryCount>> {
+        let mut stmt = conn.prepare(
+            "SELECT name, SUM(count) AS total FROM telemetry_counters
+             WHERE category = ?1 AND day >= ?2
+             GROUP BY name ORDER BY total DESC, name",
+        )?;
+        let rows = stmt.query_map(params![category.as_str(), cutoff], |row| {
+            Ok(TelemetryCount {
+                name: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    };
+    let since = conn.query_row(
+        "SELECT MIN(day) FROM telemetry_counters WHERE day >= ?1",
+        params![cutoff],
+        |row| row.get(0),
+    )?;
+    Ok(TelemetrySummary {
+        enabled: is_enabled(),
+        days: days.max(1),
+        since,
+        commands: totals(Category::Command)?,
+        runs: totals(Category::Run)?,
+        errors: totals(Category::Error)?,
+    })
+}
+
+/// Usage counts of the last `days` days (30 by default)
+#[tauri::command]
+pub async fn get_telemetry_summary(
+    db: State<'_, AgentDb>,
+    days: Option<u32>,
+) -> Result<TelemetrySummary, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    flush(&conn).map_err(|e| e.to_string())?;
+    summarize(&conn, days.unwrap_or(30)).map_err(|e| e.to_string())
+}
+
+/// Opt in to or out of the usage counters
+#[tauri::command]
+pub async fn set_telemetry_enabled(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    enabled: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_setting_as(&conn, TELEMETRY_ENABLED_KEY, &enabled)?;
+    super::settings::notify_settings_changed(&app, &conn, &[TELEMETRY_ENABLED_KEY]);
+    Ok(())
+}
+
+/// Write the summary of the last `days` days to `path`, for the user to share
+#[tauri::command]
+pub async fn export_telemetry(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    path: String,
+    days: Option<u32>,
+) -> Result<TelemetryExport, String> {
+    let summary = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        flush(&conn).map_err(|e| e.to_string())?;
+        summarize(&conn, days.unwrap_or(30)).map_err(|e| e.to_string())?
+    };
+    let export = TelemetryExport {
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        exported_at: Local::now().to_rfc3339(),
+        summary,
+    };
+    let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    crate::atomic_file::write_atomic(Path::new(&path), json.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(export)
+}
+
+/// Delete every stored count
+#[tauri::command]
+pub async fn clear_telemetry(db: State<'_, AgentDb>) -> Result<(), String> {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = None;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM telemetry_counters", [])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_flushed_and_summarized_by_category() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_telemetry_tables(&conn).unwrap();
+
+        record(Category::Command, "never_counted_command");
+        apply_telemetry_setting(Some(true));
+        record(Category::Command, "list_agents");
+        record(Category::Command, "list_agents");
+        record(Category::Run, "agent");
+        record_error("Agent not found");
+        flush(&conn).unwrap();
+        record(Category::Command, "list_agents");
+        flush(&conn).unwrap();
+        apply_telemetry_setting(Some(false));
+
+        let summary = summarize(&conn, 30).unwrap();
+        assert_eq!(
+            summary.commands,
+            vec![TelemetryCount {
+                name: "list_agents".to_string(),
+                count: 3
+            }]
+        );
+        assert_eq!(summary.runs[0].name, "agent");
+        assert_eq!(summary.errors[0].name, "not_found");
+        assert!(summary.since.is_some());
+    }
+
+    #[test]
+    fn test_summary_only_covers_the_requested_days() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_telemetry_tables(&conn).unwrap();
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let old = (Local::now() - Duration::days(10))
+            .format("%Y-%m-%d")
+            .to_string();
+        conn.execute(
+            "INSERT INTO telemetry_counters (category, name, day, count) VALUES
+             ('run', 'agent', ?1, 2), ('run', 'agent', ?2, 5)",
+            params![today, old],
+        )
+        .unwrap();
+
+        let week = summarize(&conn, 7).unwrap();
+        assert_eq!(week.runs[0].count, 2);
+        assert_eq!(week.since, Some(today));
+        let month = summarize(&conn, 30).unwrap();
+        assert_eq!(month.runs[0].count, 7);
+        assert_eq!(month.since, Some(old));
+    }
+}
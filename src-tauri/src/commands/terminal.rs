@@ -2,11 +2,33 @@ use tauri::AppHandle;
 use serde::{Deserialize, Serialize};
 use tokio::process::Command as AsyncCommand;
 use std::path::Path;
+use super::error::{ErrorKind, OpcodeError};
+use super::messages::{Message, MessageCode};
+use super::providers::{FsProvider, SystemFs};
 
 /// Command whitelist - only these commands are allowed
 #[allow(dead_code)]
 const ALLOWED_COMMANDS: &[&str] = &["echo", "pwd", "ls", "cat", "grep", "find", "git"];
 
+/// User-allowed commands on top of `ALLOWED_COMMANDS`, kept in sync with the
+/// `terminal_allowed_commands` setting
+static EXTRA_ALLOWED_COMMANDS: std::sync::RwLock<Vec<String>> = std::sync::RwLock::new(Vec::new());
+
+/// Replace the user-allowed commands
+pub fn set_extra_allowed_commands(commands: Vec<String>) {
+    if let Ok(mut extra) = EXTRA_ALLOWED_COMMANDS.write() {
+        *extra = commands;
+    }
+}
+
+fn is_command_allowed(cmd_name: &str) -> bool {
+    ALLOWED_COMMANDS.contains(&cmd_name)
+        || EXTRA_ALLOWED_COMMANDS
+            .read()
+            .map(|extra| extra.iter().any(|allowed| allowed == cmd_name))
+            .unwrap_or(false)
+}
+
 /// Maximum command length limit (4096 characters)
 #[allow(dead_code)]
 const MAX_COMMAND_LENGTH: usize = 4096;
@@ -17,6 +39,26 @@ const MAX_COMMAND_LENGTH: usize = 4096;
 struct ValidationResult {
     is_valid: bool,
     error_message: Option<String>,
+    message: Option<Message>,
+}
+
+impl ValidationResult {
+    fn rejected(message: Message) -> Self {
+        Self {
+            is_valid: false,
+            error_message: Some(message.to_string()),
+            message: Some(message),
+        }
+    }
+
+    fn into_error(self) -> OpcodeError {
+        match self.message {
+            Some(message) => OpcodeError::localized(ErrorKind::Validation, message),
+            None => OpcodeError::validation(
+                self.error_message.unwrap_or("Command validation failed".to_string()),
+            ),
+        }
+    }
 }
 
 /// Validates the command against security rules
@@ -24,10 +66,9 @@ struct ValidationResult {
 fn validate_command(command: &str, working_dir: Option<&String>) -> ValidationResult {
     // Check command length
     if command.len() > MAX_COMMAND_LENGTH {
-        return ValidationResult {
-            is_valid: false,
-            error_message: Some(format!("Command exceeds maximum length of {} characters", MAX_COMMAND_LENGTH)),
-        };
+        return ValidationResult::rejected(
+            Message::new(MessageCode::TerminalCommandTooLong).param("max", MAX_COMMAND_LENGTH),
+        );
     }
 
     // Extract command name (first word)
@@ -36,11 +77,12 @@ fn validate_command(command: &str, working_dir: Option<&String>) -> ValidationRe
         .unwrap_or("");
 
     // Check if command is in whitelist
-    if !ALLOWED_COMMANDS.contains(&cmd_name) {
-        return ValidationResult {
-            is_valid: false,
-            error_message: Some(format!("Command not allowed: {}. Allowed commands: {:?}", cmd_name, ALLOWED_COMMANDS)),
-        };
+    if !is_command_allowed(cmd_name) {
+        return ValidationResult::rejected(
+            Message::new(MessageCode::TerminalCommandNotAllowed)
+                .param("command", cmd_name)
+                .param("allowed", format!("{:?}", ALLOWED_COMMANDS)),
+        );
     }
 
     // Validate working directory if provided
@@ -53,10 +95,7 @@ fn validate_command(command: &str, working_dir: Option<&String>) -> ValidationRe
             #[cfg(not(target_os = "windows"))]
             {
                 if !dir.starts_with("/home") && !dir.starts_with("/tmp") && !dir.starts_with("/var") {
-                    return ValidationResult {
-                        is_valid: false,
-                        error_message: Some("Access to this directory is not allowed".to_string()),
-                    };
+                    return ValidationResult::rejected(Message::new(MessageCode::TerminalDirectoryNotAllowed));
                 }
             }
         }
@@ -65,7 +104,17 @@ fn validate_command(command: &str, working_dir: Option<&String>) -> ValidationRe
     ValidationResult {
         is_valid: true,
         error_message: None,
+        message: None,
+    }
+}
+
+/// Reject `command` unless it passes the same rules as local terminal commands
+pub(crate) fn check_command(command: &str, working_dir: Option<&String>) -> Result<(), OpcodeError> {
+    let validation = validate_command(command, working_dir);
+    if !validation.is_valid {
+        return Err(validation.into_error());
     }
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -83,11 +132,28 @@ pub async fn execute_terminal_command(
     command: String,
     working_dir: Option<String>,
     _app_handle: AppHandle,
-) -> Result<CommandOutput, String> {
+) -> Result<CommandOutput, OpcodeError> {
+    run_terminal_command(&SystemFs, &command, working_dir.as_ref()).await
+}
+
+/// Validate and run `command` through the shell
+pub async fn run_terminal_command(
+    fs: &dyn FsProvider,
+    command: &str,
+    working_dir: Option<&String>,
+) -> Result<CommandOutput, OpcodeError> {
     // Validate command against security rules
-    let validation = validate_command(&command, working_dir.as_ref());
+    let validation = validate_command(command, working_dir);
     if !validation.is_valid {
-        return Err(validation.error_message.unwrap_or("Command validation failed".to_string()));
+        return Err(validation.into_error());
+    }
+    if let Some(dir) = working_dir {
+        if !fs.is_dir(Path::new(dir)) {
+            return Err(OpcodeError::localized(
+                ErrorKind::NotFound,
+                Message::new(MessageCode::TerminalWorkingDirMissing).param("path", dir),
+            ));
+        }
     }
 
     let mut cmd = AsyncCommand::new("sh");
@@ -100,20 +166,20 @@ pub async fn execute_terminal_command(
     // Execute command based on OS
     #[cfg(target_os = "windows")]
     {
-        cmd.arg("-c").arg(&command);
+        cmd.arg("-c").arg(command);
     }
 
     #[cfg(not(target_os = "windows"))]
     {
-        cmd.arg("-c").arg(&command);
+        cmd.arg("-c").arg(command);
     }
 
     let output = cmd.output()
         .await
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
+        .map_err(|e| OpcodeError::new(ErrorKind::Process, format!("Failed to execute command: {}", e)))?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let stdout = crate::decoding::decode_command_output(&output.stdout);
+    let stderr = crate::decoding::decode_command_output(&output.stderr);
     let exit_code = output.status.code().unwrap_or(-1) as i32;
 
     Ok(CommandOutput {
@@ -130,16 +196,19 @@ pub async fn execute_terminal_command_stream(
     command: String,
     working_dir: Option<String>,
     _app_handle: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), OpcodeError> {
     // Validate command against security rules
     let validation = validate_command(&command, working_dir.as_ref());
     if !validation.is_valid {
-        return Err(validation.error_message.unwrap_or("Command validation failed".to_string()));
+        return Err(validation.into_error());
     }
 
     // This would be used with WebSocket for real-time output streaming
     // Implementation would involve spawning a process and streaming stdout/stderr
-    Err("Streaming not yet implemented".to_string())
+    Err(OpcodeError::localized(
+        ErrorKind::Unsupported,
+        Message::new(MessageCode::TerminalStreamingUnsupported),
+    ))
 }
 
 #[cfg(test)]
@@ -148,11 +217,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_execute_command() {
-        let result = execute_terminal_command(
-            "echo test".to_string(),
-            None,
-            AppHandle::default(),
-        ).await.unwrap();
+        let result = run_terminal_command(&SystemFs, "echo test", None).await.unwrap();
 
         assert!(result.stdout.contains("test"));
         assert_eq!(result.exit_code, 0);
@@ -161,21 +226,15 @@ mod tests {
     #[tokio::test]
     async fn test_command_whitelist() {
         // Test allowed command
-        let result = execute_terminal_command(
-            "echo allowed".to_string(),
-            None,
-            AppHandle::default(),
-        ).await;
+        let result = run_terminal_command(&SystemFs, "echo allowed", None).await;
         assert!(result.is_ok(), "echo command should be allowed");
 
         // Test disallowed command
-        let result = execute_terminal_command(
-            "rm -rf /".to_string(),
-            None,
-            AppHandle::default(),
-        ).await;
+        let result = run_terminal_command(&SystemFs, "rm -rf /", None).await;
         assert!(result.is_err(), "rm command should not be allowed");
-        assert!(result.unwrap_err().contains("Command not allowed"));
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Validation);
+        assert!(error.message.contains("Command not allowed"));
     }
 
     #[test]
@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+
+//! Extended-thinking controls for runs. The CLI has no flag for thinking: a token budget is
+//! set with `MAX_THINKING_TOKENS`, and an effort level is requested with the keywords it
+//! recognizes in the prompt (`think`, `think hard`, `think harder`, `ultrathink`). Agents
+//! carry a default in their definition; session starts pass one per run.
+//!
+//! Output lines holding thinking blocks are flagged on the `StreamLine`s sent to the
+//! frontend, so they can be shown apart from the answer.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Smallest budget the API accepts
+pub const MIN_THINKING_BUDGET: u32 = 1024;
+
+/// Largest budget accepted; the API caps it below the model's output limit anyway
+pub const MAX_THINKING_BUDGET: u32 = 128_000;
+
+/// How hard the model should think, from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThinkingEffort {
+    Think,
+    ThinkHard,
+    ThinkHarder,
+    Ultrathink,
+}
+
+impl ThinkingEffort {
+    /// Keyword the CLI maps to this effort
+    pub fn keyword(self) -> &'static str {
+        match self {
+            ThinkingEffort::Think => "think",
+            ThinkingEffort::ThinkHard => "think hard",
+            ThinkingEffort::ThinkHarder => "think harder",
+            ThinkingEffort::Ultrathink => "ultrathink",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunThinking {
+    /// Requested through a keyword on the first line of the prompt
+    pub effort: Option<ThinkingEffort>,
+    /// Thinking tokens per request, through `MAX_THINKING_TOKENS`
+    pub budget_tokens: Option<u32>,
+}
+
+impl RunThinking {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(budget) = self.budget_tokens {
+            if !(MIN_THINKING_BUDGET..=MAX_THINKING_BUDGET).contains(&budget) {
+                return Err(format!(
+                    "Thinking budget must be between {} and {} tokens",
+                    MIN_THINKING_BUDGET, MAX_THINKING_BUDGET
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The prompt with the effort keyword in front of it
+    pub fn apply_to_prompt(&self, prompt: &str) -> String {
+        match self.effort {
+            Some(effort) => format!("{}\n\n{}", effort.keyword(), prompt),
+            None => prompt.to_string(),
+        }
+    }
+
+    /// Extra environment for the process
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        self.budget_tokens
+            .map(|budget| vec![("MAX_THINKING_TOKENS", budget.to_string())])
+            .unwrap_or_default()
+    }
+
+    /// Stored form for the `agents.thinking` column; `None` when thinking is left to the CLI
+    pub fn to_column(&self) -> Option<String> {
+        if *self == Self::default() {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn from_column(value: Option<String>) -> Option<Self> {
+        value.and_then(|value| serde_json::from_str(&value).ok())
+    }
+}
+
+fn is_thinking_block(block: &Value) -> bool {
+    matches!(
+        block.get("type").and_then(Value::as_str),
+        Some("thinking" | "redacted_thinking")
+    )
+}
+
+/// Whether a stream-json line carries thinking: an assistant message with thinking blocks,
+/// or a partial-message event for one
+pub fn has_thinking(line: &str) -> bool {
+    // Cheap check first, as this runs on every line of output
+    if !line.contains("thinking") {
+        return false;
+    }
+    let Ok(json) = serde_json::from_str::<Value>(line) else {
+        return false;
+    };
+    if let Some(content) = json
+        .get("message")
+        .and_then(|message| message.get("content"))
+        .and_then(Value::as_array)
+    {
+        return content.iter().any(is_thinking_block);
+    }
+    let event = json.get("event").unwrap_or(&json);
+    event.get("content_block").is_some_and(is_thinking_block)
+        || event
+            .get("delta")
+            .and_then(|delta| delta.get("type"))
+            .and_then(Value::as_str)
+            == Some("thinking_delta")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effort_and_budget_translate_to_prompt_and_env() {
+        let thinking = RunThinking {
+            effort: Some(ThinkingEffort::ThinkHard),
+            budget_tokens: Some(16_000),
+        };
+        assert!(thinking.validate().is_ok());
+        assert_eq!(
+            thinking.apply_to_prompt("Fix the bug"),
+            "think hard\n\nFix the bug"
+        );
+        assert_eq!(
+            thinking.env(),
+            vec![("MAX_THINKING_TOKENS", "16000".to_string())]
+        );
+
+        let column = thinking.to_column();
+        assert_eq!(RunThinking::from_column(column), Some(thinking));
+        assert_eq!(RunThinking::default().to_column(), None);
+        assert_eq!(RunThinking::default().apply_to_prompt("Fix"), "Fix");
+        assert!(RunThinking {
+            budget_tokens: Some(100),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_thinking_lines_are_detected() {
+        assert!(has_thinking(
+            r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Let me see","signature":"x"}]}}"#
+        ));
+        assert!(has_thinking(
+            r#"{"type":"assistant","message":{"content":[{"type":"redacted_thinking","data":"..."}]}}"#
+        ));
+        assert!(has_thinking(
+            r#"{"type":"stream_event","event":{"type":"content_block_delta","delta":{"type":"thinking_delta","thinking":"hm"}}}"#
+        ));
+        assert!(!has_thinking(
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"I was thinking..."}]}}"#
+        ));
+        assert!(!has_thinking("thinking about it"));
+    }
+}
@@ -0,0 +1,221 @@
+#![allow(dead_code)]
+
+//! Allowed and disallowed tools for a run, passed to the CLI as `--allowedTools` and
+//! `--disallowedTools`. Agents carry rules in their definition; session starts pass them per
+//! run, so a "read-only analysis" run doesn't need changes to the global settings.
+//!
+//! Rules use the CLI's syntax: a tool name, optionally with a specifier (`Bash(git diff:*)`),
+//! or an MCP tool as `mcp__<server>__<tool>` (`mcp__<server>` for all of a server's tools).
+//! Built-in tools are checked against the names the CLI knows, MCP tools against the tools
+//! the last capability probe found on their server.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::mcp_capabilities::load_reports;
+
+/// Tools built into the Claude CLI
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "Bash",
+    "BashOutput",
+    "Edit",
+    "ExitPlanMode",
+    "Glob",
+    "Grep",
+    "KillShell",
+    "LS",
+    "MultiEdit",
+    "NotebookEdit",
+    "NotebookRead",
+    "Read",
+    "SlashCommand",
+    "Task",
+    "TodoWrite",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolRules {
+    /// Only these tools may run; empty allows every tool that isn't disallowed
+    pub allowed_tools: Vec<String>,
+    pub disallowed_tools: Vec<String>,
+}
+
+impl ToolRules {
+    pub fn is_empty(&self) -> bool {
+        self.allowed_tools.is_empty() && self.disallowed_tools.is_empty()
+    }
+
+    /// Check every rule against the built-in tools and the tools of probed MCP servers,
+    /// given as server name to tool names
+    pub fn validate(&self, mcp_tools: &HashMap<String, Vec<String>>) -> Result<(), String> {
+        for rule in self.allowed_tools.iter().chain(&self.disallowed_tools) {
+            validate_rule(rule, mcp_tools)?;
+        }
+        if let Some(rule) = self
+            .allowed_tools
+            .iter()
+            .find(|rule| self.disallowed_tools.contains(rule))
+        {
+            return Err(format!("{} is both allowed and disallowed", rule));
+        }
+        Ok(())
+    }
+
+    /// Add the CLI flags to a run's arguments. An allow list only restricts anything when
+    /// permissions aren't skipped, so `--dangerously-skip-permissions` is dropped with it;
+    /// tools outside the list are then denied, or asked for when prompts are relayed.
+    pub fn apply_to_args(&self, args: &mut Vec<String>) {
+        if !self.allowed_tools.is_empty() {
+            args.retain(|arg| arg != "--dangerously-skip-permissions");
+            args.push("--allowedTools".to_string());
+            args.push(self.allowed_tools.join(","));
+        }
+        if !self.disallowed_tools.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.push(self.disallowed_tools.join(","));
+        }
+    }
+
+    /// Stored form for the `agents.tool_rules` column; `None` when the agent has no rules
+    pub fn to_column(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn from_column(value: Option<String>) -> Option<Self> {
+        value.and_then(|value| serde_json::from_str(&value).ok())
+    }
+}
+
+fn validate_rule(rule: &str, mcp_tools: &HashMap<String, Vec<String>>) -> Result<(), String> {
+    let name = match rule.split_once('(') {
+        Some((name, specifier)) if specifier.ends_with(')') => name,
+        Some(_) => return Err(format!("Tool rule {} has an unclosed specifier", rule)),
+        None => rule,
+    };
+    if name.is_empty() || name.trim() != name {
+        return Err(format!("Invalid tool rule: {:?}", rule));
+    }
+
+    let Some(mcp_name) = name.strip_prefix("mcp__") else {
+        if BUILTIN_TOOLS.contains(&name) {
+            return Ok(());
+        }
+        return Err(format!("Unknown tool: {}", name));
+    };
+    let (server, tool) = match mcp_name.split_once("__") {
+        Some((server, tool)) => (server, Some(tool)),
+        None => (mcp_name, None),
+    };
+    if server.is_empty() {
+        return Err(format!("Invalid MCP tool rule: {}", rule));
+    }
+    match (tool, mcp_tools.get(server)) {
+        (Some(tool), Some(tools)) if !tools.iter().any(|known| known == tool) => Err(format!(
+            "MCP server {} has no tool {} (known: {})",
+            server,
+            tool,
+            tools.join(", ")
+        )),
+        // Servers that were never probed can't be checked
+        _ => Ok(()),
+    }
+}
+
+/// Tools of every probed MCP server, by server name
+pub fn known_mcp_tools(conn: &Connection) -> HashMap<String, Vec<String>> {
+    load_reports(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|report| !report.tools.is_empty())
+        .map(|report| (report.server, report.tools))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(allowed: &[&str], disallowed: &[&str]) -> ToolRules {
+        ToolRules {
+            allowed_tools: allowed.iter().map(|tool| tool.to_string()).collect(),
+            disallowed_tools: disallowed.iter().map(|tool| tool.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_rules_are_validated_against_known_tools() {
+        let mcp_tools = HashMap::from([(
+            "github".to_string(),
+            vec!["create_issue".to_string(), "search_code".to_string()],
+        )]);
+
+        assert!(
+            rules(&["Read", "Grep", "Bash(git diff:*)"], &["Write", "Edit"])
+                .validate(&mcp_tools)
+                .is_ok()
+        );
+        assert!(rules(
+            &[
+                "mcp__github__search_code",
+                "mcp__github",
+                "mcp__unprobed__any"
+            ],
+            &[]
+        )
+        .validate(&mcp_tools)
+        .is_ok());
+
+        assert!(rules(&["Reed"], &[]).validate(&mcp_tools).is_err());
+        assert!(rules(&["Bash(git diff:*"], &[])
+            .validate(&mcp_tools)
+            .is_err());
+        assert!(rules(&["mcp__github__delete_repo"], &[])
+            .validate(&mcp_tools)
+            .is_err());
+        assert!(rules(&["Write"], &["Write"]).validate(&mcp_tools).is_err());
+    }
+
+    #[test]
+    fn test_allow_list_replaces_skipped_permissions() {
+        let mut args = vec![
+            "-p".to_string(),
+            "task".to_string(),
+            "--dangerously-skip-permissions".to_string(),
+        ];
+        rules(&["Read", "Grep"], &["WebFetch"]).apply_to_args(&mut args);
+        assert_eq!(
+            args,
+            vec![
+                "-p",
+                "task",
+                "--allowedTools",
+                "Read,Grep",
+                "--disallowedTools",
+                "WebFetch"
+            ]
+        );
+
+        let mut args = vec!["--dangerously-skip-permissions".to_string()];
+        rules(&[], &["Bash"]).apply_to_args(&mut args);
+        assert_eq!(
+            args,
+            vec![
+                "--dangerously-skip-permissions",
+                "--disallowedTools",
+                "Bash"
+            ]
+        );
+
+        assert_eq!(ToolRules::default().to_column(), None);
+        let stored = rules(&["Read"], &[]);
+        assert_eq!(ToolRules::from_column(stored.to_column()), Some(stored));
+    }
+}
@@ -0,0 +1,274 @@
+#![allow(dead_code)]
+
+//! Tool-usage analytics: how often each tool (built-in or `mcp__<server>__<tool>`) was
+//! called across sessions and agent runs, how often the call failed, and how long it took
+//! until its result arrived. Read from the session index, which records every tool call with
+//! the `is_error` flag and timestamp of its result. Per-server rollups and the probed MCP
+//! servers that were never called show which servers are worth keeping.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use super::mcp_capabilities::load_reports;
+use super::session_index::{canonical, index_all, open_store, projects_dir};
+use super::usage::DateRange;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolUsageStat {
+    pub tool: String,
+    /// MCP server of `mcp__<server>__<tool>` tools
+    pub server: Option<String>,
+    pub invocations: u64,
+    pub failures: u64,
+    /// Failures among calls whose result was seen
+    pub failure_rate: Option<f64>,
+    /// Time from the call to its result
+    pub avg_duration_ms: Option<f64>,
+    /// Transcripts the tool was called in
+    pub sessions: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerUsage {
+    pub server: String,
+    pub invocations: u64,
+    pub failures: u64,
+    /// Distinct tools of the server that were called
+    pub tools_used: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolUsageStats {
+    pub total_invocations: u64,
+    /// Most called first
+    pub tools: Vec<ToolUsageStat>,
+    pub servers: Vec<McpServerUsage>,
+    /// Probed MCP servers none of whose tools were called
+    pub unused_servers: Vec<String>,
+}
+
+/// Server of an `mcp__<server>__<tool>` tool name
+fn mcp_server(tool: &str) -> Option<&str> {
+    let rest = tool.strip_prefix("mcp__")?;
+    rest.split_once("__").map(|(server, _)| server)
+}
+
+/// Tool usage of the transcripts under `projects_dir`, optionally only those of one project
+/// directory, from the index as it is
+pub fn tool_usage_stats(
+    conn: &Connection,
+    projects_dir: &Path,
+    range: &DateRange,
+    project_id: Option<&str>,
+) -> Result<ToolUsageStats, String> {
+    let (start, end) = range.bounds()?;
+    let prefix = canonical(projects_dir).to_string_lossy().to_string();
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.tool_name, COUNT(*), SUM(CASE WHEN t.is_error THEN 1 ELSE 0 END),
+                    COUNT(t.is_error),
+                    AVG(CASE WHEN t.result_timestamp IS NOT NULL
+                        THEN (julianday(t.result_timestamp) - julianday(m.timestamp)) * 86400000.0 END),
+                    COUNT(DISTINCT t.path)
+             FROM session_index_tool_uses t
+             JOIN session_index_messages m ON m.path = t.path AND m.position = t.position
+             JOIN session_index_files f ON f.path = t.path
+             WHERE substr(f.path, 1, length(?1)) = ?1
+               AND (?2 IS NULL OR f.project_dir = ?2)
+               AND (?3 IS NULL OR date(m.timestamp) >= ?3)
+               AND (?4 IS NULL OR date(m.timestamp) <= ?4)
+             GROUP BY t.tool_name
+             ORDER BY COUNT(*) DESC, t.tool_name",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![
+                prefix,
+                project_id,
+                start.map(|day| day.to_string()),
+                end.map(|day| day.to_string())
+            ],
+            |row| {
+                let tool: String = row.get(0)?;
+                let failures = row.get::<_, i64>(2)? as u64;
+                let with_result = row.get::<_, i64>(3)? as u64;
+                Ok(ToolUsageStat {
+                    server: mcp_server(&tool).map(str::to_string),
+                    tool,
+                    invocations: row.get::<_, i64>(1)? as u64,
+                    failures,
+                    failure_rate: (with_result > 0).then(|| failures as f64 / with_result as f64),
+                    avg_duration_ms: row
+                        .get::<_, Option<f64>>(4)?
+                        .filter(|duration| *duration >= 0.0),
+                    sessions: row.get::<_, i64>(5)? as u64,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    let tools = rows
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut servers: BTreeMap<String, McpServerUsage> = BTreeMap::new();
+    for stat in &tools {
+        let Some(server) = &stat.server else {
+            continue;
+        };
+        let usage = servers
+            .entry(server.clone())
+            .or_insert_with(|| McpServerUsage {
+                server: server.clone(),
+                invocations: 0,
+                failures: 0,
+                tools_used: 0,
+            });
+        usage.invocations += stat.invocations;
+        usage.failures += stat.failures;
+        usage.tools_used += 1;
+    }
+
+    let used: HashSet<&str> = servers.keys().map(String::as_str).collect();
+    let mut unused_servers: Vec<String> = load_reports(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|report| report.server)
+        .filter(|server| !used.contains(server.as_str()))
+        .collect();
+    unused_servers.sort();
+    unused_servers.dedup();
+
+    let mut servers: Vec<McpServerUsage> = servers.into_values().collect();
+    servers.sort_by(|a, b| b.invocations.cmp(&a.invocations));
+
+    Ok(ToolUsageStats {
+        total_invocations: tools.iter().map(|stat| stat.invocations).sum(),
+        tools,
+        servers,
+        unused_servers,
+    })
+}
+
+/// Calls, failure rates and durations per tool, and per MCP server, across sessions and
+/// agent runs
+#[tauri::command]
+pub async fn get_tool_usage_stats(
+    range: Option<DateRange>,
+    project_id: Option<String>,
+) -> Result<ToolUsageStats, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = open_store().ok_or("The session index is not available")?;
+        let projects_dir = projects_dir()?;
+        index_all(&conn, &projects_dir)?;
+        tool_usage_stats(
+            &conn,
+            &projects_dir,
+            &range.unwrap_or_default(),
+            project_id.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::session_index::init_session_index_tables;
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn line(kind: &str, timestamp: &str, content: serde_json::Value) -> String {
+        json!({
+            "type": kind,
+            "timestamp": timestamp,
+            "sessionId": "s1",
+            "message": { "role": kind, "content": content },
+        })
+        .to_string()
+    }
+
+    fn call(id: &str, name: &str) -> serde_json::Value {
+        json!({ "type": "tool_use", "id": id, "name": name, "input": {} })
+    }
+
+    fn result(id: &str, is_error: bool) -> serde_json::Value {
+        json!({ "type": "tool_result", "tool_use_id": id, "is_error": is_error, "content": "" })
+    }
+
+    #[test]
+    fn test_counts_failures_and_durations_per_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let mut file = std::fs::File::create(project.join("s1.jsonl")).unwrap();
+        let lines = [
+            line(
+                "assistant",
+                "2025-02-01T10:00:00Z",
+                json!([call("t1", "Bash"), call("t2", "mcp__github__search_code")]),
+            ),
+            line(
+                "user",
+                "2025-02-01T10:00:02Z",
+                json!([result("t1", false), result("t2", true)]),
+            ),
+            line(
+                "assistant",
+                "2025-02-01T10:01:00Z",
+                json!([call("t3", "Bash")]),
+            ),
+            line("user", "2025-02-01T10:01:04Z", json!([result("t3", true)])),
+            line(
+                "assistant",
+                "2025-03-01T09:00:00Z",
+                json!([call("t4", "mcp__github__create_issue")]),
+            ),
+        ];
+        for line in &lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_session_index_tables(&conn).unwrap();
+        index_all(&conn, dir.path()).unwrap();
+
+        let stats = tool_usage_stats(&conn, dir.path(), &DateRange::default(), None).unwrap();
+        assert_eq!(stats.total_invocations, 4);
+        let bash = &stats.tools[0];
+        assert_eq!(bash.tool, "Bash");
+        assert_eq!((bash.invocations, bash.failures), (2, 1));
+        assert_eq!(bash.failure_rate, Some(0.5));
+        assert!((bash.avg_duration_ms.unwrap() - 3000.0).abs() < 1.0);
+
+        let create_issue = stats
+            .tools
+            .iter()
+            .find(|stat| stat.tool == "mcp__github__create_issue")
+            .unwrap();
+        // No result yet, so nothing to derive a rate or duration from
+        assert_eq!(create_issue.failure_rate, None);
+        assert_eq!(create_issue.avg_duration_ms, None);
+        assert_eq!(
+            stats.servers,
+            vec![McpServerUsage {
+                server: "github".to_string(),
+                invocations: 2,
+                failures: 1,
+                tools_used: 2,
+            }]
+        );
+
+        let february = DateRange {
+            start_date: Some("2025-02-01".to_string()),
+            end_date: Some("2025-02-28".to_string()),
+        };
+        let stats = tool_usage_stats(&conn, dir.path(), &february, Some("-work-app")).unwrap();
+        assert_eq!(stats.total_invocations, 3);
+        let stats = tool_usage_stats(&conn, dir.path(), &february, Some("-work-other")).unwrap();
+        assert_eq!(stats.total_invocations, 0);
+    }
+}
@@ -0,0 +1,360 @@
+#![allow(dead_code)]
+
+use log::{error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::app_lock::is_locked;
+use super::background::show_main_window;
+use crate::process::{ProcessRegistryState, ProcessType, RunSummary};
+
+const TRAY_ID: &str = "main";
+
+/// How often the tray checks the registry for started or finished runs
+const TRAY_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// app_settings key holding the favorite agents shown in the tray menu
+const FAVORITES_KEY: &str = "tray_favorite_agents";
+
+const MENU_SHOW: &str = "show";
+const MENU_QUIT: &str = "quit";
+const MENU_STOP_PREFIX: &str = "stop-run:";
+const MENU_FAVORITE_PREFIX: &str = "run-favorite:";
+
+/// An agent that can be started straight from the tray menu
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrayFavorite {
+    pub agent_id: i64,
+    pub project_path: String,
+    /// Task to run; the agent's default task is used when unset
+    pub task: Option<String>,
+}
+
+/// Load the tray favorites from app_settings
+pub fn load_tray_favorites(conn: &Connection) -> Vec<TrayFavorite> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![FAVORITES_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+/// Create the tray icon and start keeping its run list up to date
+pub fn init_tray(app: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("opcode")
+        .menu(&build_menu(app, &[])?)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last_state: Option<(Vec<i64>, bool)> = None;
+        let mut interval = tokio::time::interval(TRAY_REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let runs = running_summaries(&app);
+            let state = (runs.iter().map(|run| run.run_id).collect(), is_locked());
+            if last_state.as_ref() != Some(&state) {
+                if let Err(e) = update_tray(&app, &runs) {
+                    warn!("Failed to update tray menu: {}", e);
+                }
+                last_state = Some(state);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Rebuild the tray menu now, e.g. after a run was stopped or favorites changed
+pub fn refresh_tray(app: &AppHandle) {
+    let runs = running_summaries(app);
+    if let Err(e) = update_tray(app, &runs) {
+        warn!("Failed to update tray menu: {}", e);
+    }
+}
+
+fn running_summaries(app: &AppHandle) -> Vec<RunSummary> {
+    app.try_state::<ProcessRegistryState>()
+        .and_then(|registry| registry.0.get_run_summaries().ok())
+        .unwrap_or_default()
+}
+
+fn update_tray(app: &AppHandle, runs: &[RunSummary]) -> tauri::Result<()> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    tray.set_menu(Some(build_menu(app, runs)?))?;
+    let status = match runs.len() {
+        0 => "opcode".to_string(),
+        1 => "opcode — 1 active run".to_string(),
+        n => format!("opcode — {} active runs", n),
+    };
+    tray.set_tooltip(Some(&status))?;
+    // Only shown next to the icon on macOS
+    tray.set_title(if runs.is_empty() {
+        None
+    } else {
+        Some(runs.len().to_string())
+    })?;
+    Ok(())
+}
+
+fn build_menu(app: &AppHandle, runs: &[RunSummary]) -> tauri::Result<Menu<tauri::Wry>> {
+    let menu = Menu::new(app)?;
+
+    // Run names, tasks and favorites stay hidden until the app is unlocked
+    if is_locked() {
+        menu.append(&MenuItem::with_id(
+            app,
+            "runs-header",
+            "opcode is locked",
+            false,
+            None::<&str>,
+        )?)?;
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+        menu.append(&MenuItem::with_id(app, MENU_SHOW, "Show opcode", true, None::<&str>)?)?;
+        menu.append(&MenuItem::with_id(app, MENU_QUIT, "Quit", true, None::<&str>)?)?;
+        return Ok(menu);
+    }
+
+    let header = match runs.len() {
+        0 => "No active runs".to_string(),
+        n => format!("Active runs ({})", n),
+    };
+    menu.append(&MenuItem::with_id(app, "runs-header", header, false, None::<&str>)?)?;
+    for run in runs {
+        let label = if run.cost_usd > 0.0 {
+            format!("{} (${:.2})", run.label, run.cost_usd)
+        } else {
+            run.label.clone()
+        };
+        let submenu = Submenu::with_id(app, format!("run:{}", run.run_id), label, true)?;
+        submenu.append(&MenuItem::with_id(
+            app,
+            format!("run-task:{}", run.run_id),
+            truncate(&run.task, 60),
+            false,
+            None::<&str>,
+        )?)?;
+        submenu.append(&MenuItem::with_id(
+            app,
+            format!("{}{}", MENU_STOP_PREFIX, run.run_id),
+            "Stop",
+            true,
+            None::<&str>,
+        )?)?;
+        menu.append(&submenu)?;
+    }
+
+    let favorites = favorites_with_names(app);
+    if !favorites.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+        for (index, (_, name)) in favorites.iter().enumerate() {
+            menu.append(&MenuItem::with_id(
+                app,
+                format!("{}{}", MENU_FAVORITE_PREFIX, index),
+                format!("Run {}", name),
+                true,
+                None::<&str>,
+            )?)?;
+        }
+    }
+
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(app, MENU_SHOW, "Show opcode", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app, MENU_QUIT, "Quit", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+/// Favorites paired with their agent names, skipping agents that no longer exist
+fn favorites_with_names(app: &AppHandle) -> Vec<(TrayFavorite, String)> {
+    let Some(db) = app.try_state::<AgentDb>() else {
+        return Vec::new();
+    };
+    let Ok(conn) = db.0.lock() else {
+        return Vec::new();
+    };
+    load_tray_favorites(&conn)
+        .into_iter()
+        .filter_map(|favorite| {
+            conn.query_row(
+                "SELECT name FROM agents WHERE id = ?1",
+                params![favorite.agent_id],
+                |row| row.get::<_, String>(0),
+            )
+            .ok()
+            .map(|name| (favorite, name))
+        })
+        .collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.lines().next().unwrap_or_default();
+    if text.chars().count() > max_chars {
+        format!("{}…", text.chars().take(max_chars).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    match id {
+        MENU_SHOW => show_main_window(app),
+        MENU_QUIT => app.exit(0),
+        // A menu built before locking may still be open; show the lock screen instead
+        _ if is_locked() => show_main_window(app),
+        _ => {
+            if let Some(run_id) = id
+                .strip_prefix(MENU_STOP_PREFIX)
+                .and_then(|run_id| run_id.parse::<i64>().ok())
+            {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    stop_run(&app, run_id).await;
+                    refresh_tray(&app);
+                });
+            } else if let Some(index) = id
+                .strip_prefix(MENU_FAVORITE_PREFIX)
+                .and_then(|index| index.parse::<usize>().ok())
+            {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    run_favorite(&app, index).await;
+                    refresh_tray(&app);
+                });
+            }
+        }
+    }
+}
+
+async fn stop_run(app: &AppHandle, run_id: i64) {
+    let registry = app.state::<ProcessRegistryState>();
+    let process = registry.0.get_process(run_id).ok().flatten();
+    let result = match process.map(|info| info.process_type) {
+        Some(ProcessType::AgentRun { .. }) => super::agents::kill_agent_session(
+            app.clone(),
+            app.state::<AgentDb>(),
+            registry,
+            run_id,
+        )
+        .await
+        .map(|_| ()),
+        Some(ProcessType::ClaudeSession { session_id }) => {
+            super::claude::cancel_claude_execution(app.clone(), Some(session_id)).await
+        }
+        None => Ok(()),
+    };
+    match result {
+        Ok(()) => info!("Stopped run {} from the tray", run_id),
+        Err(e) => error!("Failed to stop run {} from the tray: {}", run_id, e),
+    }
+}
+
+async fn run_favorite(app: &AppHandle, index: usize) {
+    if is_locked() {
+        warn!("Not starting a tray favorite while the app is locked");
+        return;
+    }
+    let Some((favorite, name)) = favorites_with_names(app).into_iter().nth(index) else {
+        return;
+    };
+
+    let default_task = {
+        let db = app.state::<AgentDb>();
+        let conn = match db.0.lock() {
+            Ok(conn) => conn,
+            Err(_) => return,
+        };
+        conn.query_row(
+            "SELECT default_task FROM agents WHERE id = ?1",
+            params![favorite.agent_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .ok()
+        .flatten()
+    };
+
+    // Without a task there's nothing to run unattended; let the UI ask for one
+    let Some(task) = favorite
+        .task
+        .clone()
+        .or(default_task)
+        .filter(|task| !task.trim().is_empty())
+    else {
+        show_main_window(app);
+        let _ = app.emit("tray:run-agent", &favorite);
+        return;
+    };
+
+    match super::agents::execute_agent(
+        app.clone(),
+        favorite.agent_id,
+        favorite.project_path.clone(),
+        task,
+        None,
+        None,
+        None,
+        None,
+        app.state::<AgentDb>(),
+        app.state::<ProcessRegistryState>(),
+    )
+    .await
+    {
+        Ok(run_id) => info!("Started {} from the tray as run {}", name, run_id),
+        Err(e) => error!("Failed to start {} from the tray: {}", name, e),
+    }
+}
+
+/// Get the agents pinned to the tray menu
+#[tauri::command]
+pub async fn get_tray_favorites(db: State<'_, AgentDb>) -> Result<Vec<TrayFavorite>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_tray_favorites(&conn))
+}
+
+/// Set the agents pinned to the tray menu
+#[tauri::command]
+pub async fn set_tray_favorites(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    favorites: Vec<TrayFavorite>,
+) -> Result<(), String> {
+    let value = serde_json::to_string(&favorites)
+        .map_err(|e| format!("Failed to serialize tray favorites: {}", e))?;
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![FAVORITES_KEY, value],
+        )
+        .map_err(|e| format!("Failed to save tray favorites: {}", e))?;
+    }
+    refresh_tray(&app);
+    Ok(())
+}
@@ -10,15 +10,15 @@ use tauri::command;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsageEntry {
-    timestamp: String,
-    model: String,
-    input_tokens: u64,
-    output_tokens: u64,
-    cache_creation_tokens: u64,
-    cache_read_tokens: u64,
-    cost: f64,
-    session_id: String,
-    project_path: String,
+    pub(crate) timestamp: String,
+    pub(crate) model: String,
+    pub(crate) input_tokens: u64,
+    pub(crate) output_tokens: u64,
+    pub(crate) cache_creation_tokens: u64,
+    pub(crate) cache_read_tokens: u64,
+    pub(crate) cost: f64,
+    pub(crate) session_id: String,
+    pub(crate) project_path: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,7 +32,7 @@ pub struct UsageStats {
     total_sessions: u64,
     by_model: Vec<ModelUsage>,
     by_date: Vec<DailyUsage>,
-    by_project: Vec<ProjectUsage>,
+    pub by_project: Vec<ProjectUsage>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,25 +57,14 @@ pub struct DailyUsage {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectUsage {
-    project_path: String,
-    project_name: String,
-    total_cost: f64,
-    total_tokens: u64,
-    session_count: u64,
-    last_used: String,
+    pub project_path: String,
+    pub project_name: String,
+    pub total_cost: f64,
+    pub total_tokens: u64,
+    pub session_count: u64,
+    pub last_used: String,
 }
 
-// Claude 4 pricing constants (per million tokens)
-const OPUS_4_INPUT_PRICE: f64 = 15.0;
-const OPUS_4_OUTPUT_PRICE: f64 = 75.0;
-const OPUS_4_CACHE_WRITE_PRICE: f64 = 18.75;
-const OPUS_4_CACHE_READ_PRICE: f64 = 1.50;
-
-const SONNET_4_INPUT_PRICE: f64 = 3.0;
-const SONNET_4_OUTPUT_PRICE: f64 = 15.0;
-const SONNET_4_CACHE_WRITE_PRICE: f64 = 3.75;
-const SONNET_4_CACHE_READ_PRICE: f64 = 0.30;
-
 #[derive(Debug, Deserialize)]
 struct JsonlEntry {
     timestamp: String,
@@ -104,42 +93,39 @@ struct UsageData {
 }
 
 fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
-    let input_tokens = usage.input_tokens.unwrap_or(0) as f64;
-    let output_tokens = usage.output_tokens.unwrap_or(0) as f64;
-    let cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
-    let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0) as f64;
-
-    // Calculate cost based on model
-    let (input_price, output_price, cache_write_price, cache_read_price) =
-        if model.contains("opus-4") || model.contains("claude-opus-4") {
-            (
-                OPUS_4_INPUT_PRICE,
-                OPUS_4_OUTPUT_PRICE,
-                OPUS_4_CACHE_WRITE_PRICE,
-                OPUS_4_CACHE_READ_PRICE,
-            )
-        } else if model.contains("sonnet-4") || model.contains("claude-sonnet-4") {
-            (
-                SONNET_4_INPUT_PRICE,
-                SONNET_4_OUTPUT_PRICE,
-                SONNET_4_CACHE_WRITE_PRICE,
-                SONNET_4_CACHE_READ_PRICE,
-            )
-        } else {
-            // Return 0 for unknown models to avoid incorrect cost estimations.
-            (0.0, 0.0, 0.0, 0.0)
-        };
-
-    // Calculate cost (prices are per million tokens)
-    let cost = (input_tokens * input_price / 1_000_000.0)
-        + (output_tokens * output_price / 1_000_000.0)
-        + (cache_creation_tokens * cache_write_price / 1_000_000.0)
-        + (cache_read_tokens * cache_read_price / 1_000_000.0);
+    cost_for_tokens(
+        model,
+        usage.input_tokens.unwrap_or(0),
+        usage.output_tokens.unwrap_or(0),
+        usage.cache_creation_input_tokens.unwrap_or(0),
+        usage.cache_read_input_tokens.unwrap_or(0),
+    )
+}
 
-    cost
+/// Estimate the cost in USD of a token count for a model with the active price table
+/// (0 for unknown models)
+pub(crate) fn cost_for_tokens(
+    model: &str,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+) -> f64 {
+    // Return 0 for unknown models to avoid incorrect cost estimations.
+    super::pricing::active_price(model)
+        .map(|price| {
+            super::pricing::cost_at(
+                &price,
+                input_tokens,
+                output_tokens,
+                cache_creation_tokens,
+                cache_read_tokens,
+            )
+        })
+        .unwrap_or(0.0)
 }
 
-fn parse_jsonl_file(
+pub(crate) fn parse_jsonl_file(
     path: &PathBuf,
     encoded_project_name: &str,
     processed_hashes: &mut HashSet<String>,
@@ -230,7 +216,7 @@ fn parse_jsonl_file(
     entries
 }
 
-fn get_earliest_timestamp(path: &PathBuf) -> Option<String> {
+pub(crate) fn get_earliest_timestamp(path: &PathBuf) -> Option<String> {
     if let Ok(content) = fs::read_to_string(path) {
         let mut earliest_timestamp: Option<String> = None;
         for line in content.lines() {
@@ -251,7 +237,15 @@ fn get_earliest_timestamp(path: &PathBuf) -> Option<String> {
     None
 }
 
-fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
+pub(crate) fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
+    // The session index only parses what was appended since the last call
+    if let Some(conn) = super::session_index::open_store() {
+        match super::session_index::usage_entries(&conn, &claude_path.join("projects")) {
+            Ok(entries) => return entries,
+            Err(e) => log::warn!("Session index unavailable, parsing transcripts: {}", e),
+        }
+    }
+
     let mut all_entries = Vec::new();
     let mut processed_hashes = HashSet::new();
     let projects_dir = claude_path.join("projects");
@@ -290,6 +284,56 @@ fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
     all_entries
 }
 
+/// Parse a plain date or an ISO datetime
+pub(crate) fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").or_else(|_| {
+        // Try parsing ISO datetime format
+        DateTime::parse_from_rfc3339(value)
+            .map(|dt| dt.naive_local().date())
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Inclusive range of days for analytics; either end may be open
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DateRange {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+impl DateRange {
+    /// The parsed start and end days
+    pub(crate) fn bounds(&self) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+        let start = self
+            .start_date
+            .as_deref()
+            .map(parse_date)
+            .transpose()
+            .map_err(|e| format!("Invalid start date: {}", e))?;
+        let end = self
+            .end_date
+            .as_deref()
+            .map(parse_date)
+            .transpose()
+            .map_err(|e| format!("Invalid end date: {}", e))?;
+        Ok((start, end))
+    }
+}
+
+/// Day of an RFC 3339 timestamp, if it falls within the bounds
+pub(crate) fn day_in_range(
+    timestamp: &str,
+    (start, end): (Option<NaiveDate>, Option<NaiveDate>),
+) -> Option<NaiveDate> {
+    let date = DateTime::parse_from_rfc3339(timestamp)
+        .ok()?
+        .naive_local()
+        .date();
+    let after_start = start.is_none_or(|start| date >= start);
+    let before_end = end.is_none_or(|end| date <= end);
+    (after_start && before_end).then_some(date)
+}
+
 #[command]
 pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
     let claude_path = dirs::home_dir()
@@ -459,18 +503,8 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
     let all_entries = get_all_usage_entries(&claude_path);
 
     // Parse dates
-    let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").or_else(|_| {
-        // Try parsing ISO datetime format
-        DateTime::parse_from_rfc3339(&start_date)
-            .map(|dt| dt.naive_local().date())
-            .map_err(|e| format!("Invalid start date: {}", e))
-    })?;
-    let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d").or_else(|_| {
-        // Try parsing ISO datetime format
-        DateTime::parse_from_rfc3339(&end_date)
-            .map(|dt| dt.naive_local().date())
-            .map_err(|e| format!("Invalid end date: {}", e))
-    })?;
+    let start = parse_date(&start_date).map_err(|e| format!("Invalid start date: {}", e))?;
+    let end = parse_date(&end_date).map_err(|e| format!("Invalid end date: {}", e))?;
 
     // Filter entries by date range
     let filtered_entries: Vec<_> = all_entries
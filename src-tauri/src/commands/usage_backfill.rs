@@ -0,0 +1,429 @@
+#![allow(dead_code)]
+
+//! Imports usage history from existing Claude Code transcripts into the `usage_entries`
+//! table, so people who used Claude Code before installing opcode see their history right
+//! away. `usage_backfill` runs in the background, reports progress with
+//! `usage-backfill-progress` events and can be stopped with `cancel_usage_backfill`.
+//! Files that haven't changed since they were imported are skipped on later runs.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::cancellation::{CancellationRegistry, CancellationToken};
+use super::db_maintenance::add_column_if_missing;
+use super::pricing::active_price_version;
+use super::settings::{get_setting_as, set_setting_as};
+use super::usage::{get_earliest_timestamp, parse_jsonl_file, UsageEntry};
+
+/// Operation id of the backfill in the cancellation registry; only one runs at a time
+const BACKFILL_OPERATION: &str = "usage-backfill";
+
+/// app_settings key recording when the last backfill finished
+const COMPLETED_AT_KEY: &str = "usage_backfill_completed_at";
+
+const PROGRESS_EVENT: &str = "usage-backfill-progress";
+
+/// Minimum time between progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageBackfillProgress {
+    /// `running`, `completed`, `cancelled` or `failed`
+    pub status: String,
+    pub files_total: usize,
+    pub files_done: usize,
+    pub files_skipped: usize,
+    pub entries_imported: usize,
+    pub current_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageBackfillStatus {
+    pub running: bool,
+    /// True until a backfill has completed, while there is history to import
+    pub offer: bool,
+    pub completed_at: Option<String>,
+    pub imported_files: i64,
+    pub imported_entries: i64,
+}
+
+pub fn init_usage_backfill_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_key TEXT NOT NULL UNIQUE,
+            timestamp TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cost REAL NOT NULL DEFAULT 0,
+            session_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    // Price table the cost was computed with, so it can be recomputed later
+    add_column_if_missing(conn, "usage_entries", "price_version", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_timestamp ON usage_entries(timestamp)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_backfill_files (
+            path TEXT PRIMARY KEY,
+            size_bytes INTEGER NOT NULL,
+            modified_secs INTEGER NOT NULL,
+            entries INTEGER NOT NULL DEFAULT 0,
+            imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Parse `since` as an RFC 3339 timestamp or a plain date
+pub fn parse_since(since: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(since) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(since, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD or RFC 3339", since))
+}
+
+/// Transcripts under `paths`, each with the name of its project directory. A path can be
+/// the projects directory, one project directory, or a single `.jsonl` file.
+pub fn find_transcripts(paths: &[PathBuf], since: Option<SystemTime>) -> Vec<(PathBuf, String)> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for root in paths {
+        for entry in walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
+        {
+            let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+            // A file last written before `since` can't contain newer entries
+            if since
+                .zip(modified)
+                .is_some_and(|(since, modified)| modified < since)
+            {
+                continue;
+            }
+            let path = entry.path().to_path_buf();
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let project = path
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            files.push((path, project));
+        }
+    }
+    // Oldest conversations first, so duplicated messages are attributed to where they started
+    files.sort_by_cached_key(|(path, _)| get_earliest_timestamp(path));
+    files
+}
+
+fn entry_key(entry: &UsageEntry) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        entry.session_id, entry.timestamp, entry.model, entry.input_tokens, entry.output_tokens
+    )
+}
+
+/// Insert entries not imported yet; returns how many were new
+pub fn store_usage_entries(conn: &Connection, entries: &[UsageEntry]) -> SqliteResult<usize> {
+    let price_version = active_price_version();
+    let tx = conn.unchecked_transaction()?;
+    let mut inserted = 0;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO usage_entries (entry_key, timestamp, model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost, session_id, project_path, price_version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+        for entry in entries {
+            inserted += stmt.execute(params![
+                entry_key(entry),
+                entry.timestamp,
+                entry.model,
+                entry.input_tokens as i64,
+                entry.output_tokens as i64,
+                entry.cache_creation_tokens as i64,
+                entry.cache_read_tokens as i64,
+                entry.cost,
+                entry.session_id,
+                entry.project_path,
+                price_version,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(inserted)
+}
+
+/// Size and modification time, used to tell whether a file changed since its import
+fn file_fingerprint(path: &Path) -> (i64, i64) {
+    let metadata = std::fs::metadata(path).ok();
+    let size = metadata.as_ref().map(|m| m.len() as i64).unwrap_or(0);
+    let modified = metadata
+        .and_then(|m| m.modified().ok())
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    (size, modified)
+}
+
+fn already_imported(conn: &Connection, path: &str, fingerprint: (i64, i64)) -> bool {
+    conn.query_row(
+        "SELECT size_bytes, modified_secs FROM usage_backfill_files WHERE path = ?1",
+        params![path],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+    )
+    .optional()
+    .ok()
+    .flatten()
+        == Some(fingerprint)
+}
+
+fn import_file(
+    app: &AppHandle,
+    path: &Path,
+    project: &str,
+    since: Option<&DateTime<Utc>>,
+    processed_hashes: &mut HashSet<String>,
+) -> Result<Option<usize>, String> {
+    let key = path.to_string_lossy().to_string();
+    let fingerprint = file_fingerprint(path);
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if already_imported(&conn, &key, fingerprint) {
+            return Ok(None);
+        }
+    }
+
+    let path_buf = path.to_path_buf();
+    let entries: Vec<UsageEntry> = parse_jsonl_file(&path_buf, project, processed_hashes)
+        .into_iter()
+        .filter(|entry| {
+            since.is_none_or(|since| {
+                DateTime::parse_from_rfc3339(&entry.timestamp)
+                    .is_ok_and(|timestamp| timestamp.with_timezone(&Utc) >= *since)
+            })
+        })
+        .collect();
+
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let inserted = store_usage_entries(&conn, &entries).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO usage_backfill_files (path, size_bytes, modified_secs, entries, imported_at)
+         VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)",
+        params![key, fingerprint.0, fingerprint.1, entries.len() as i64],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(Some(inserted))
+}
+
+fn run_backfill(
+    app: &AppHandle,
+    paths: &[PathBuf],
+    since: Option<DateTime<Utc>>,
+    token: &CancellationToken,
+) -> UsageBackfillProgress {
+    let files = find_transcripts(paths, since.map(SystemTime::from));
+    let mut progress = UsageBackfillProgress {
+        status: "running".to_string(),
+        files_total: files.len(),
+        files_done: 0,
+        files_skipped: 0,
+        entries_imported: 0,
+        current_path: None,
+        error: None,
+    };
+    let _ = app.emit(PROGRESS_EVENT, &progress);
+
+    let mut processed_hashes = HashSet::new();
+    let mut last_emit = Instant::now();
+    for (path, project) in &files {
+        if token.is_cancelled() {
+            progress.status = "cancelled".to_string();
+            return progress;
+        }
+        match import_file(app, path, project, since.as_ref(), &mut processed_hashes) {
+            Ok(Some(inserted)) => progress.entries_imported += inserted,
+            Ok(None) => progress.files_skipped += 1,
+            Err(e) => {
+                progress.status = "failed".to_string();
+                progress.error = Some(format!("Failed to import {}: {}", path.display(), e));
+                return progress;
+            }
+        }
+        progress.files_done += 1;
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            progress.current_path = Some(path.to_string_lossy().to_string());
+            let _ = app.emit(PROGRESS_EVENT, &progress);
+            last_emit = Instant::now();
+        }
+    }
+
+    progress.status = "completed".to_string();
+    progress.current_path = None;
+    progress
+}
+
+fn mark_completed(app: &AppHandle) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    if let Err(e) = set_setting_as(&conn, COMPLETED_AT_KEY, &Utc::now().to_rfc3339()) {
+        log::warn!("Failed to record usage backfill completion: {}", e);
+    }
+}
+
+fn default_projects_dir() -> Result<PathBuf, String> {
+    dirs::home_dir()
+        .map(|home| home.join(".claude").join("projects"))
+        .ok_or_else(|| "Failed to get home directory".to_string())
+}
+
+/// Whether a backfill is running or worth offering, and how much has been imported
+#[tauri::command]
+pub async fn get_usage_backfill_status(
+    db: State<'_, AgentDb>,
+) -> Result<UsageBackfillStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let completed_at: Option<String> = get_setting_as(&conn, COMPLETED_AT_KEY);
+    let count = |sql: &str| -> Result<i64, String> {
+        conn.query_row(sql, [], |row| row.get(0))
+            .map_err(|e| e.to_string())
+    };
+    let running = CancellationRegistry::global()
+        .running()
+        .iter()
+        .any(|id| id == BACKFILL_OPERATION);
+    let has_history = default_projects_dir().is_ok_and(|dir| dir.is_dir());
+    Ok(UsageBackfillStatus {
+        running,
+        offer: !running && completed_at.is_none() && has_history,
+        completed_at,
+        imported_files: count("SELECT COUNT(*) FROM usage_backfill_files")?,
+        imported_entries: count("SELECT COUNT(*) FROM usage_entries")?,
+    })
+}
+
+/// Import usage from existing transcripts in the background. `paths` defaults to
+/// `~/.claude/projects`; `since` (a date or RFC 3339 timestamp) skips older entries.
+#[tauri::command]
+pub async fn usage_backfill(
+    app: AppHandle,
+    paths: Option<Vec<String>>,
+    since: Option<String>,
+) -> Result<(), String> {
+    let since = since.as_deref().map(parse_since).transpose()?;
+    let paths: Vec<PathBuf> = match paths {
+        Some(paths) if !paths.is_empty() => paths.into_iter().map(PathBuf::from).collect(),
+        _ => vec![default_projects_dir()?],
+    };
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        return Err(format!("Path not found: {}", missing.display()));
+    }
+
+    let registry = CancellationRegistry::global();
+    if registry.running().iter().any(|id| id == BACKFILL_OPERATION) {
+        return Err("A usage backfill is already running".to_string());
+    }
+    let guard = registry.register(Some(BACKFILL_OPERATION.to_string()));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let progress = run_backfill(&app, &paths, since, guard.token());
+        match &progress.error {
+            Some(e) => log::error!("Usage backfill failed: {}", e),
+            None => log::info!(
+                "Usage backfill {}: {} entries from {} files",
+                progress.status,
+                progress.entries_imported,
+                progress.files_done
+            ),
+        }
+        if progress.status == "completed" {
+            mark_completed(&app);
+        }
+        let _ = app.emit(PROGRESS_EVENT, &progress);
+    });
+    Ok(())
+}
+
+/// Stop a running backfill; what was imported so far is kept
+#[tauri::command]
+pub async fn cancel_usage_backfill() -> Result<bool, String> {
+    Ok(CancellationRegistry::global().cancel(BACKFILL_OPERATION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since_accepts_dates_and_timestamps() {
+        assert_eq!(
+            parse_since("2025-03-01").unwrap().to_rfc3339(),
+            "2025-03-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since("2025-03-01T12:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2025-03-01T10:30:00+00:00"
+        );
+        assert!(parse_since("last week").is_err());
+    }
+
+    #[test]
+    fn test_transcripts_are_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("notes.txt"), "not a transcript").unwrap();
+        std::fs::write(
+            project.join("s1.jsonl"),
+            concat!(
+                r#"{"timestamp":"2025-03-01T10:00:00Z","sessionId":"s1","requestId":"r1","cwd":"/work/app","message":{"id":"m1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":20}}}"#,
+                "\n",
+                r#"{"timestamp":"2025-03-01T10:01:00Z","sessionId":"s1","requestId":"r2","message":{"id":"m2","model":"claude-sonnet-4","usage":{"input_tokens":0,"output_tokens":0}}}"#,
+                "\n"
+            ),
+        )
+        .unwrap();
+
+        let files = find_transcripts(&[dir.path().to_path_buf()], None);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].1, "-work-app");
+
+        let entries = parse_jsonl_file(&files[0].0, &files[0].1, &mut HashSet::new());
+        let conn = Connection::open_in_memory().unwrap();
+        init_usage_backfill_tables(&conn).unwrap();
+        assert_eq!(store_usage_entries(&conn, &entries).unwrap(), 1);
+        assert_eq!(store_usage_entries(&conn, &entries).unwrap(), 0);
+        let project_path: String = conn
+            .query_row("SELECT project_path FROM usage_entries", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(project_path, "/work/app");
+    }
+}
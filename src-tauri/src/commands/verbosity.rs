@@ -0,0 +1,133 @@
+#![allow(dead_code)]
+
+//! Per-run diagnostics for spawned `claude` processes. A caller can ask for the CLI's debug
+//! logging and a more talkative log level for the MCP servers it starts, so a failing agent
+//! or session can be re-run with diagnostics from the UI.
+
+use serde::{Deserialize, Serialize};
+
+/// Levels accepted for `MCP_LOG_LEVEL`, most to least verbose
+pub const MCP_LOG_LEVELS: &[&str] = &["debug", "info", "notice", "warning", "error"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunVerbosity {
+    /// Pass `--debug` to the CLI
+    pub debug: bool,
+    /// Debug categories to keep, e.g. `api` or `mcp`; `!name` excludes one. Empty keeps all.
+    pub debug_categories: Vec<String>,
+    /// `MCP_LOG_LEVEL` for the MCP servers the run starts; defaults to `debug` with `debug`
+    pub mcp_log_level: Option<String>,
+}
+
+impl RunVerbosity {
+    /// Everything turned up, as used when re-running a failed run with diagnostics
+    pub fn diagnostics() -> Self {
+        Self {
+            debug: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for category in &self.debug_categories {
+            let name = category.strip_prefix('!').unwrap_or(category);
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(format!("Invalid debug category: {}", category));
+            }
+        }
+        if let Some(level) = &self.mcp_log_level {
+            if !MCP_LOG_LEVELS.contains(&level.as_str()) {
+                return Err(format!(
+                    "Invalid MCP log level '{}', expected one of: {}",
+                    level,
+                    MCP_LOG_LEVELS.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Extra CLI arguments; the `=` form keeps the optional filter from swallowing the next
+    /// argument
+    pub fn claude_args(&self) -> Vec<String> {
+        if !self.debug {
+            return Vec::new();
+        }
+        if self.debug_categories.is_empty() {
+            vec!["--debug".to_string()]
+        } else {
+            vec![format!("--debug={}", self.debug_categories.join(","))]
+        }
+    }
+
+    /// Extra environment for the process, inherited by the MCP servers it spawns
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let level = self
+            .mcp_log_level
+            .clone()
+            .or_else(|| self.debug.then(|| "debug".to_string()));
+        level
+            .map(|level| vec![("MCP_LOG_LEVEL", level)])
+            .unwrap_or_default()
+    }
+
+    /// Stored form for the `agent_runs.verbosity` column; `None` when nothing is turned up
+    pub fn to_column(&self) -> Option<String> {
+        if *self == Self::default() {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn from_column(value: Option<String>) -> Option<Self> {
+        value.and_then(|value| serde_json::from_str(&value).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_adds_flag_and_mcp_level() {
+        assert!(RunVerbosity::default().claude_args().is_empty());
+        assert!(RunVerbosity::default().env().is_empty());
+
+        let diagnostics = RunVerbosity::diagnostics();
+        assert_eq!(diagnostics.claude_args(), vec!["--debug"]);
+        assert_eq!(
+            diagnostics.env(),
+            vec![("MCP_LOG_LEVEL", "debug".to_string())]
+        );
+
+        let filtered = RunVerbosity {
+            debug: true,
+            debug_categories: vec!["api".to_string(), "!statsig".to_string()],
+            mcp_log_level: Some("info".to_string()),
+        };
+        assert_eq!(filtered.claude_args(), vec!["--debug=api,!statsig"]);
+        assert_eq!(filtered.env(), vec![("MCP_LOG_LEVEL", "info".to_string())]);
+    }
+
+    #[test]
+    fn test_validate_and_column_round_trip() {
+        let mut verbosity = RunVerbosity::diagnostics();
+        assert!(verbosity.validate().is_ok());
+        assert_eq!(RunVerbosity::default().to_column(), None);
+        assert_eq!(
+            RunVerbosity::from_column(verbosity.to_column()),
+            Some(verbosity.clone())
+        );
+
+        verbosity.debug_categories = vec!["api; rm -rf".to_string()];
+        assert!(verbosity.validate().is_err());
+        verbosity.debug_categories.clear();
+        verbosity.mcp_log_level = Some("loud".to_string());
+        assert!(verbosity.validate().is_err());
+    }
+}
@@ -11,6 +11,16 @@ pub async fn get_app_version() -> Result<String, String> {
     Ok(version)
 }
 
+/// 构建时间（RFC 3339），由 build.rs 以 Unix 时间戳写入
+fn build_timestamp() -> String {
+    env!("OPCODE_BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
 /// 获取详细的版本信息
 #[tauri::command]
 #[allow(dead_code)]
@@ -26,14 +36,289 @@ pub async fn get_version_info() -> Result<serde_json::Value, String> {
         "build_info": {
             "rust_version": env!("CARGO_PKG_RUST_VERSION"),
             "authors": env!("CARGO_PKG_AUTHORS"),
+            "git_commit": env!("OPCODE_GIT_COMMIT"),
+            "git_branch": env!("OPCODE_GIT_BRANCH"),
+            "git_dirty": env!("OPCODE_GIT_DIRTY") == "true",
+            "build_timestamp": build_timestamp(),
+            "target": env!("OPCODE_TARGET"),
+            "profile": if cfg!(debug_assertions) { "debug" } else { "release" },
+            "tauri_version": tauri::VERSION,
+            "webview_version": tauri::webview_version().ok(),
         }
     });
 
     Ok(version_info)
 }
 
+/// 汇总版本、构建、系统和 claude CLI 信息用于问题报告，并复制到剪贴板
+#[tauri::command]
+pub async fn copy_diagnostic_info(app: tauri::AppHandle) -> Result<String, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let info = get_version_info().await?;
+    let build = &info["build_info"];
+    let claude = match crate::claude_binary::find_claude_binary(&app) {
+        Ok(path) => {
+            let version = crate::claude_binary::get_claude_version(&path)
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| "unknown version".to_string());
+            format!("{} ({})", version, path)
+        }
+        Err(_) => "not found".to_string(),
+    };
+
+    let lines = [
+        format!("opcode {}", info["version"].as_str().unwrap_or_default()),
+        format!(
+            "Commit: {} ({}{})",
+            build["git_commit"].as_str().unwrap_or_default(),
+            build["git_branch"].as_str().unwrap_or_default(),
+            if build["git_dirty"].as_bool() == Some(true) { ", dirty" } else { "" }
+        ),
+        format!("Built: {} ({})", build_timestamp(), build["profile"].as_str().unwrap_or_default()),
+        format!("Target: {}", env!("OPCODE_TARGET")),
+        format!("Tauri: {}", tauri::VERSION),
+        format!(
+            "Webview: {}",
+            build["webview_version"].as_str().unwrap_or("unknown")
+        ),
+        format!(
+            "OS: {} {} ({})",
+            std::env::consts::OS,
+            os_version().unwrap_or_default(),
+            std::env::consts::ARCH
+        ),
+        format!("Claude CLI: {}", claude),
+    ];
+    let mut report = lines.join("\n");
+    // 本次会话中最近一次输出管道基准测试的结果
+    if let Some(bench) = super::output_bench::last_result() {
+        report.push_str(&format!("\nOutput pipeline: {}", bench.summary()));
+    }
+
+    app.clipboard()
+        .write_text(report.clone())
+        .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+    Ok(report)
+}
+
+/// 操作系统版本号
+fn os_version() -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        std::process::Command::new("sw_vers").arg("-productVersion").output()
+    } else if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd").args(["/C", "ver"]).output()
+    } else {
+        std::process::Command::new("uname").arg("-r").output()
+    }
+    .ok()?;
+    let version = crate::decoding::decode_command_output(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
 /// 构建时设置的版本信息（用于动态显示）
 #[allow(dead_code)]
 pub fn register_version_commands() {
     println!("Version commands registered");
 }
+
+/// 上游仓库的 GitHub releases API
+const RELEASES_URL: &str = "https://api.github.com/repos/getAsterisk/opcode/releases";
+
+/// 选择更新通道的设置项
+pub const UPDATE_CHANNEL_KEY: &str = "update_channel";
+
+/// 更新检查结果的缓存时间
+const UPDATE_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// 发布通道：stable 只包含正式版本，beta 还包含预发布版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: String,
+    published_at: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+/// 更新检查结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+    pub channel: ReleaseChannel,
+    pub release_name: Option<String>,
+    /// 发布说明（Markdown）
+    pub changelog: Option<String>,
+    /// 当前平台的安装包，没有匹配时为发布页面
+    pub download_url: String,
+    pub published_at: Option<String>,
+    pub checked_at: String,
+}
+
+static UPDATE_CACHE: std::sync::Mutex<Option<(std::time::Instant, UpdateInfo)>> =
+    std::sync::Mutex::new(None);
+
+fn parse_release_version(tag: &str) -> Option<semver::Version> {
+    semver::Version::parse(tag.trim_start_matches('v')).ok()
+}
+
+/// 在通道内选出版本号最高的发布
+fn latest_release(
+    releases: Vec<GithubRelease>,
+    channel: ReleaseChannel,
+) -> Option<(semver::Version, GithubRelease)> {
+    releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .filter(|release| channel == ReleaseChannel::Beta || !release.prerelease)
+        .filter_map(|release| parse_release_version(&release.tag_name).map(|v| (v, release)))
+        .filter(|(version, _)| channel == ReleaseChannel::Beta || version.pre.is_empty())
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+/// 按文件扩展名挑选当前平台的安装包
+fn platform_asset(assets: &[GithubAsset]) -> Option<&GithubAsset> {
+    let extensions: &[&str] = if cfg!(target_os = "macos") {
+        &[".dmg"]
+    } else if cfg!(target_os = "windows") {
+        &[".msi", "-setup.exe", ".exe"]
+    } else {
+        &[".AppImage", ".deb", ".rpm"]
+    };
+    extensions.iter().find_map(|ext| {
+        assets
+            .iter()
+            .find(|asset| asset.name.ends_with(ext) && !asset.name.ends_with(".sig"))
+    })
+}
+
+/// 检查 GitHub 上是否有新版本
+///
+/// 结果缓存一小时，`force` 为 true 时忽略缓存；通道由 `update_channel` 设置决定
+#[tauri::command]
+pub async fn check_for_updates(
+    db: tauri::State<'_, crate::commands::agents::AgentDb>,
+    force: Option<bool>,
+) -> Result<UpdateInfo, String> {
+    let channel: ReleaseChannel = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        crate::commands::settings::get_setting_as(&conn, UPDATE_CHANNEL_KEY).unwrap_or_default()
+    };
+
+    if !force.unwrap_or(false) {
+        if let Ok(cache) = UPDATE_CACHE.lock() {
+            if let Some((checked, info)) = cache.as_ref() {
+                if info.channel == channel && checked.elapsed() < UPDATE_CACHE_TTL {
+                    return Ok(info.clone());
+                }
+            }
+        }
+    }
+
+    let releases: Vec<GithubRelease> = reqwest::Client::new()
+        .get(RELEASES_URL)
+        .header("User-Agent", format!("opcode/{}", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json")
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse releases: {}", e))?;
+
+    let current = parse_release_version(env!("CARGO_PKG_VERSION"))
+        .ok_or("Current version is not valid semver")?;
+    let (latest, release) =
+        latest_release(releases, channel).ok_or("No releases found for this channel")?;
+
+    let info = UpdateInfo {
+        current_version: current.to_string(),
+        latest_version: latest.to_string(),
+        update_available: latest > current,
+        channel,
+        release_name: release.name.clone(),
+        changelog: release.body.clone(),
+        download_url: platform_asset(&release.assets)
+            .map(|asset| asset.browser_download_url.clone())
+            .unwrap_or(release.html_url),
+        published_at: release.published_at,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    if let Ok(mut cache) = UPDATE_CACHE.lock() {
+        *cache = Some((std::time::Instant::now(), info.clone()));
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool) -> GithubRelease {
+        GithubRelease {
+            tag_name: tag.to_string(),
+            name: None,
+            body: None,
+            html_url: format!("https://github.com/getAsterisk/opcode/releases/tag/{}", tag),
+            published_at: None,
+            draft: false,
+            prerelease,
+            assets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_latest_release_respects_channel() {
+        let releases = vec![
+            release("v0.2.0", false),
+            release("v0.3.0-beta.1", true),
+            release("v0.2.1", false),
+            release("nightly", true),
+        ];
+
+        let (stable, _) = latest_release(releases.clone(), ReleaseChannel::Stable).unwrap();
+        assert_eq!(stable.to_string(), "0.2.1");
+        let (beta, _) = latest_release(releases, ReleaseChannel::Beta).unwrap();
+        assert_eq!(beta.to_string(), "0.3.0-beta.1");
+    }
+
+    #[test]
+    fn test_platform_asset_skips_signatures() {
+        let assets: Vec<GithubAsset> = ["opcode.dmg.sig", "opcode.dmg", "opcode.msi", "opcode.AppImage"]
+            .iter()
+            .map(|name| GithubAsset {
+                name: name.to_string(),
+                browser_download_url: format!("https://example.com/{}", name),
+            })
+            .collect();
+        let asset = platform_asset(&assets).unwrap();
+        assert!(!asset.name.ends_with(".sig"));
+    }
+}
@@ -0,0 +1,604 @@
+#![allow(dead_code)]
+
+use ring::hmac;
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+
+/// Number of delivery attempts before a webhook call is given up
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+
+/// Timeout for a single delivery request
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+
+/// Deliveries kept per webhook in the delivery log
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+/// Maximum length of the result summary included in payloads
+const SUMMARY_MAX_CHARS: usize = 500;
+
+/// Events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "run.started")]
+    RunStarted,
+    #[serde(rename = "run.completed")]
+    RunCompleted,
+    #[serde(rename = "run.failed")]
+    RunFailed,
+    /// The daily digest of agent activity
+    #[serde(rename = "digest.daily")]
+    DailyDigest,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::RunStarted => "run.started",
+            WebhookEvent::RunCompleted => "run.completed",
+            WebhookEvent::RunFailed => "run.failed",
+            WebhookEvent::DailyDigest => "digest.daily",
+        }
+    }
+}
+
+/// A JSON body that can be posted to webhook endpoints
+pub trait WebhookPayload: Serialize + Send + Sync + 'static {
+    fn event(&self) -> WebhookEvent;
+
+    /// Run the delivery is about, for the delivery log
+    fn run_id(&self) -> Option<i64> {
+        None
+    }
+}
+
+/// A user-configured webhook endpoint
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Webhook {
+    pub id: Option<i64>,
+    pub name: String,
+    pub url: String,
+    /// Shared secret used for the `X-Opcode-Signature` HMAC header. Only returned when the
+    /// webhook is created; on update, `None` keeps the stored secret and `""` removes it.
+    pub secret: Option<String>,
+    /// Whether a signing secret is stored
+    #[serde(default)]
+    pub has_secret: bool,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    pub created_at: Option<String>,
+}
+
+/// A single recorded delivery attempt chain
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub run_id: Option<i64>,
+    pub success: bool,
+    pub status_code: Option<u16>,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub delivered_at: String,
+}
+
+/// JSON body posted to webhook endpoints
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunWebhookPayload {
+    pub event: WebhookEvent,
+    pub run_id: i64,
+    pub agent_name: String,
+    pub project_path: String,
+    pub task: String,
+    pub status: String,
+    pub duration_ms: Option<i64>,
+    pub cost_usd: Option<f64>,
+    pub summary: Option<String>,
+    pub timestamp: String,
+}
+
+impl WebhookPayload for RunWebhookPayload {
+    fn event(&self) -> WebhookEvent {
+        self.event
+    }
+
+    fn run_id(&self) -> Option<i64> {
+        Some(self.run_id).filter(|id| *id > 0)
+    }
+}
+
+/// Create webhook tables
+pub fn init_webhook_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS webhooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            secret TEXT,
+            events TEXT NOT NULL DEFAULT '[]',
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            run_id INTEGER,
+            success BOOLEAN NOT NULL,
+            status_code INTEGER,
+            attempts INTEGER NOT NULL,
+            error TEXT,
+            delivered_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, delivered_at DESC)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+impl Webhook {
+    /// The webhook without its signing secret, for handing to the frontend
+    fn masked(mut self) -> Self {
+        self.secret = None;
+        self
+    }
+}
+
+fn webhook_from_row(row: &rusqlite::Row) -> rusqlite::Result<Webhook> {
+    let events: String = row.get(4)?;
+    let secret = row.get::<_, Option<String>>(3)?.filter(|s| !s.is_empty());
+    Ok(Webhook {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        url: row.get(2)?,
+        has_secret: secret.is_some(),
+        secret,
+        events: serde_json::from_str(&events).unwrap_or_default(),
+        enabled: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+pub(crate) fn load_webhooks(conn: &Connection) -> SqliteResult<Vec<Webhook>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, url, secret, events, enabled, created_at FROM webhooks ORDER BY id ASC",
+    )?;
+    let webhooks = stmt
+        .query_map([], webhook_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(webhooks)
+}
+
+fn validate_webhook(webhook: &Webhook) -> Result<(), String> {
+    if webhook.name.trim().is_empty() {
+        return Err("Webhook name cannot be empty".to_string());
+    }
+    let url = reqwest::Url::parse(&webhook.url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("Webhook URL must use http or https".to_string());
+    }
+    Ok(())
+}
+
+/// List all configured webhooks, without their signing secrets
+#[tauri::command]
+pub async fn list_webhooks(db: State<'_, AgentDb>) -> Result<Vec<Webhook>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let webhooks = load_webhooks(&conn).map_err(|e| e.to_string())?;
+    Ok(webhooks.into_iter().map(Webhook::masked).collect())
+}
+
+/// Create a new webhook; the response is the only place its secret is shown again
+#[tauri::command]
+pub async fn create_webhook(db: State<'_, AgentDb>, webhook: Webhook) -> Result<Webhook, String> {
+    validate_webhook(&webhook)?;
+    let events = serde_json::to_string(&webhook.events).map_err(|e| e.to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO webhooks (name, url, secret, events, enabled) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![webhook.name, webhook.url, webhook.secret, events, webhook.enabled],
+    )
+    .map_err(|e| format!("Failed to create webhook: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        "SELECT id, name, url, secret, events, enabled, created_at FROM webhooks WHERE id = ?1",
+        params![id],
+        webhook_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Update an existing webhook, keeping the stored secret unless a new one is given
+#[tauri::command]
+pub async fn update_webhook(db: State<'_, AgentDb>, webhook: Webhook) -> Result<Webhook, String> {
+    let id = webhook.id.ok_or("Webhook id is required")?;
+    validate_webhook(&webhook)?;
+    let events = serde_json::to_string(&webhook.events).map_err(|e| e.to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let rows = conn
+        .execute(
+            "UPDATE webhooks SET name = ?1, url = ?2, secret = COALESCE(?3, secret), events = ?4, enabled = ?5 WHERE id = ?6",
+            params![webhook.name, webhook.url, webhook.secret, events, webhook.enabled, id],
+        )
+        .map_err(|e| format!("Failed to update webhook: {}", e))?;
+    if rows == 0 {
+        return Err(format!("Webhook {} not found", id));
+    }
+
+    conn.query_row(
+        "SELECT id, name, url, secret, events, enabled, created_at FROM webhooks WHERE id = ?1",
+        params![id],
+        webhook_from_row,
+    )
+    .map(Webhook::masked)
+    .map_err(|e| e.to_string())
+}
+
+/// Delete a webhook and its delivery log
+#[tauri::command]
+pub async fn delete_webhook(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM webhook_deliveries WHERE webhook_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM webhooks WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete webhook: {}", e))?;
+    Ok(())
+}
+
+/// List recent deliveries, optionally for a single webhook
+#[tauri::command]
+pub async fn list_webhook_deliveries(
+    db: State<'_, AgentDb>,
+    webhook_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<WebhookDelivery>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let limit = limit.unwrap_or(50).clamp(1, DELIVERY_LOG_LIMIT);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, webhook_id, event, run_id, success, status_code, attempts, error, delivered_at
+             FROM webhook_deliveries
+             WHERE (?1 IS NULL OR webhook_id = ?1)
+             ORDER BY id DESC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let deliveries = stmt
+        .query_map(params![webhook_id, limit], |row| {
+            Ok(WebhookDelivery {
+                id: row.get(0)?,
+                webhook_id: row.get(1)?,
+                event: row.get(2)?,
+                run_id: row.get(3)?,
+                success: row.get(4)?,
+                status_code: row.get::<_, Option<i64>>(5)?.map(|c| c as u16),
+                attempts: row.get(6)?,
+                error: row.get(7)?,
+                delivered_at: row.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(deliveries)
+}
+
+/// Send a sample payload to a webhook and record the delivery
+#[tauri::command]
+pub async fn test_webhook(app: AppHandle, id: i64) -> Result<WebhookDelivery, String> {
+    let webhook = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT id, name, url, secret, events, enabled, created_at FROM webhooks WHERE id = ?1",
+            params![id],
+            webhook_from_row,
+        )
+        .map_err(|e| format!("Webhook {} not found: {}", id, e))?
+    };
+
+    let payload = RunWebhookPayload {
+        event: WebhookEvent::RunCompleted,
+        run_id: 0,
+        agent_name: "Test Agent".to_string(),
+        project_path: String::new(),
+        task: "Webhook test".to_string(),
+        status: "completed".to_string(),
+        duration_ms: Some(0),
+        cost_usd: Some(0.0),
+        summary: Some("This is a test delivery from opcode".to_string()),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+
+    Ok(deliver(&app, &webhook, &payload).await)
+}
+
+/// Send `payload` to every enabled webhook subscribed to its event, in the background,
+/// and record it in the activity feed
+pub fn dispatch_run_event(app: &AppHandle, payload: RunWebhookPayload) {
+    super::activity::record_run_event(app, &payload);
+    dispatch(app, payload);
+}
+
+/// Send `payload` to every enabled webhook subscribed to its event, in the background
+pub fn dispatch<P: WebhookPayload>(app: &AppHandle, payload: P) {
+    let webhooks = {
+        let db = match app.try_state::<AgentDb>() {
+            Some(db) => db,
+            None => return,
+        };
+        let conn = match db.0.lock() {
+            Ok(conn) => conn,
+            Err(_) => return,
+        };
+        match load_webhooks(&conn) {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                log::warn!("Failed to load webhooks: {}", e);
+                return;
+            }
+        }
+    };
+
+    let targets: Vec<Webhook> = webhooks
+        .into_iter()
+        .filter(|w| w.enabled && w.events.contains(&payload.event()))
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for webhook in targets {
+            deliver(&app, &webhook, &payload).await;
+        }
+    });
+}
+
+/// Deliver a payload with retries and record the outcome in the delivery log
+async fn deliver<P: WebhookPayload>(
+    app: &AppHandle,
+    webhook: &Webhook,
+    payload: &P,
+) -> WebhookDelivery {
+    let body = serde_json::to_vec(payload).unwrap_or_default();
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+
+    let mut attempts = 0;
+    let mut status_code = None;
+    let mut error = None;
+    let mut success = false;
+
+    while attempts < MAX_DELIVERY_ATTEMPTS {
+        attempts += 1;
+
+        let mut request = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", format!("opcode/{}", env!("CARGO_PKG_VERSION")))
+            .header("X-Opcode-Event", payload.event().as_str());
+        if let Some(secret) = &webhook.secret {
+            request = request.header(
+                "X-Opcode-Signature",
+                format!("sha256={}", hmac_sha256_hex(secret.as_bytes(), &body)),
+            );
+        }
+
+        match request.body(body.clone()).send().await {
+            Ok(response) => {
+                let status = response.status();
+                status_code = Some(status.as_u16());
+                if status.is_success() {
+                    success = true;
+                    error = None;
+                    break;
+                }
+                error = Some(format!("HTTP {}", status));
+                // Client errors won't succeed on retry (except rate limiting)
+                if status.is_client_error() && status.as_u16() != 429 {
+                    break;
+                }
+            }
+            Err(e) => {
+                status_code = None;
+                error = Some(e.to_string());
+            }
+        }
+
+        if attempts < MAX_DELIVERY_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(1 << attempts)).await;
+        }
+    }
+
+    if success {
+        log::info!(
+            "Delivered {} webhook '{}' after {} attempt(s)",
+            payload.event().as_str(),
+            webhook.name,
+            attempts
+        );
+    } else {
+        log::warn!(
+            "Failed to deliver {} webhook '{}': {}",
+            payload.event().as_str(),
+            webhook.name,
+            error.as_deref().unwrap_or("unknown error")
+        );
+    }
+
+    record_delivery(
+        app,
+        webhook.id.unwrap_or_default(),
+        payload,
+        success,
+        status_code,
+        attempts,
+        error,
+    )
+}
+
+fn record_delivery<P: WebhookPayload>(
+    app: &AppHandle,
+    webhook_id: i64,
+    payload: &P,
+    success: bool,
+    status_code: Option<u16>,
+    attempts: u32,
+    error: Option<String>,
+) -> WebhookDelivery {
+    let mut delivery = WebhookDelivery {
+        id: 0,
+        webhook_id,
+        event: payload.event().as_str().to_string(),
+        run_id: payload.run_id(),
+        success,
+        status_code,
+        attempts,
+        error,
+        delivered_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return delivery;
+    };
+
+    let inserted = conn.execute(
+        "INSERT INTO webhook_deliveries (webhook_id, event, run_id, success, status_code, attempts, error, delivered_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            delivery.webhook_id,
+            delivery.event,
+            delivery.run_id,
+            delivery.success,
+            delivery.status_code.map(|c| c as i64),
+            delivery.attempts,
+            delivery.error,
+            delivery.delivered_at
+        ],
+    );
+    if let Err(e) = inserted {
+        log::warn!("Failed to record webhook delivery: {}", e);
+        return delivery;
+    }
+    delivery.id = conn.last_insert_rowid();
+
+    // Keep the delivery log bounded
+    let _ = conn.execute(
+        "DELETE FROM webhook_deliveries WHERE webhook_id = ?1 AND id NOT IN (
+            SELECT id FROM webhook_deliveries WHERE webhook_id = ?1 ORDER BY id DESC LIMIT ?2
+        )",
+        params![webhook_id, DELIVERY_LOG_LIMIT],
+    );
+
+    delivery
+}
+
+/// Extract the total cost and a short result summary from stream-json output
+pub fn summarize_stream_output(output: &str) -> (Option<f64>, Option<String>) {
+    for line in output.lines().rev() {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+            if json.get("type").and_then(|t| t.as_str()) == Some("result") {
+                let cost = json.get("total_cost_usd").and_then(|c| c.as_f64());
+                let summary = json.get("result").and_then(|r| r.as_str()).map(|text| {
+                    if text.chars().count() > SUMMARY_MAX_CHARS {
+                        let truncated: String = text.chars().take(SUMMARY_MAX_CHARS).collect();
+                        format!("{}…", truncated)
+                    } else {
+                        text.to_string()
+                    }
+                });
+                return (cost, summary);
+            }
+        }
+    }
+    (None, None)
+}
+
+/// HMAC-SHA256 (RFC 2104) of `message` keyed with `key`, hex encoded
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, message)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_rfc4231_vectors() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // RFC 4231 test case 6 (key longer than block size)
+        assert_eq!(
+            hmac_sha256_hex(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_masked_webhook_keeps_only_whether_a_secret_is_set() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_webhook_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO webhooks (name, url, secret, events, enabled) VALUES ('ci', 'https://ci.example', 'shh', '[]', 1)",
+            [],
+        )
+        .unwrap();
+
+        let webhook = load_webhooks(&conn).unwrap().remove(0).masked();
+        assert_eq!(webhook.secret, None);
+        assert!(webhook.has_secret);
+    }
+
+    #[test]
+    fn test_summarize_stream_output() {
+        let output = concat!(
+            r#"{"type":"system","subtype":"init","session_id":"abc"}"#,
+            "\n",
+            r#"{"type":"result","subtype":"success","total_cost_usd":0.0123,"result":"Done"}"#,
+            "\n"
+        );
+        let (cost, summary) = summarize_stream_output(output);
+        assert_eq!(cost, Some(0.0123));
+        assert_eq!(summary.as_deref(), Some("Done"));
+
+        assert_eq!(summarize_stream_output("not json"), (None, None));
+    }
+}
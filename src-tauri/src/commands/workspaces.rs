@@ -0,0 +1,383 @@
+#![allow(dead_code)]
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+
+use super::agents::{agent_run_from_row, AgentDb, AgentRun, AGENT_RUN_COLUMNS};
+use super::claude::{get_project_sessions, list_projects, Session};
+use super::mcp::{mcp_read_project_config, MCPServerConfig};
+use super::usage::{get_usage_stats, ProjectUsage};
+
+/// Sessions and runs returned per workspace overview
+const OVERVIEW_ITEM_LIMIT: usize = 50;
+
+/// A named group of project paths, e.g. the packages of a monorepo or related repos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: Option<i64>,
+    pub name: String,
+    pub description: Option<String>,
+    pub project_paths: Vec<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Usage totals across a workspace's projects
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WorkspaceUsage {
+    pub total_cost: f64,
+    pub total_tokens: u64,
+    pub session_count: u64,
+    pub by_project: Vec<ProjectUsage>,
+}
+
+/// MCP servers configured in one member project's `.mcp.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMcpServers {
+    pub project_path: String,
+    pub servers: HashMap<String, MCPServerConfig>,
+}
+
+/// Everything known about a workspace's projects in one place
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceOverview {
+    pub workspace: Workspace,
+    /// Most recent Claude sessions across members, newest first
+    pub sessions: Vec<Session>,
+    /// Most recent agent runs across members, newest first
+    pub agent_runs: Vec<AgentRun>,
+    pub usage: WorkspaceUsage,
+    pub mcp_servers: Vec<WorkspaceMcpServers>,
+    /// Member paths that no longer exist on disk
+    pub missing_paths: Vec<String>,
+}
+
+pub fn init_workspace_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS workspaces (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS workspace_projects (
+            workspace_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (workspace_id, project_path),
+            FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Normalize a member path so the same directory isn't added twice
+fn normalize_project_path(path: &str) -> String {
+    let trimmed = path.trim();
+    let trimmed = trimmed.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        path.trim().to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn validate_workspace(workspace: &Workspace) -> Result<Vec<String>, String> {
+    if workspace.name.trim().is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    let mut paths: Vec<String> = Vec::new();
+    for path in &workspace.project_paths {
+        let path = normalize_project_path(path);
+        if !std::path::Path::new(&path).is_absolute() {
+            return Err(format!("Project path must be absolute: {}", path));
+        }
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn load_workspace(conn: &Connection, id: i64) -> SqliteResult<Option<Workspace>> {
+    let workspace = conn
+        .query_row(
+            "SELECT id, name, description, created_at, updated_at FROM workspaces WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(Workspace {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    project_paths: Vec::new(),
+                    created_at: row.get(3)?,
+                    updated_at: row.get(4)?,
+                })
+            },
+        )
+        .optional()?;
+
+    let Some(mut workspace) = workspace else {
+        return Ok(None);
+    };
+    let mut stmt = conn.prepare(
+        "SELECT project_path FROM workspace_projects WHERE workspace_id = ?1 ORDER BY position ASC",
+    )?;
+    workspace.project_paths = stmt
+        .query_map(params![id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(Some(workspace))
+}
+
+fn save_members(conn: &Connection, workspace_id: i64, paths: &[String]) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM workspace_projects WHERE workspace_id = ?1",
+        params![workspace_id],
+    )?;
+    for (position, path) in paths.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO workspace_projects (workspace_id, project_path, position) VALUES (?1, ?2, ?3)",
+            params![workspace_id, path, position as i64],
+        )?;
+    }
+    Ok(())
+}
+
+/// Workspaces containing `project_path`
+pub fn workspaces_for_project(conn: &Connection, project_path: &str) -> SqliteResult<Vec<i64>> {
+    let mut stmt =
+        conn.prepare("SELECT workspace_id FROM workspace_projects WHERE project_path = ?1")?;
+    let ids = stmt
+        .query_map(params![normalize_project_path(project_path)], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ids)
+}
+
+/// List all workspaces
+#[tauri::command]
+pub async fn list_workspaces(db: State<'_, AgentDb>) -> Result<Vec<Workspace>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM workspaces ORDER BY name ASC")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut workspaces = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(workspace) = load_workspace(&conn, id).map_err(|e| e.to_string())? {
+            workspaces.push(workspace);
+        }
+    }
+    Ok(workspaces)
+}
+
+/// Get a workspace by id
+#[tauri::command]
+pub async fn get_workspace(db: State<'_, AgentDb>, id: i64) -> Result<Workspace, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_workspace(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Workspace {} not found", id))
+}
+
+/// Create a workspace
+#[tauri::command]
+pub async fn create_workspace(
+    db: State<'_, AgentDb>,
+    workspace: Workspace,
+) -> Result<Workspace, String> {
+    let paths = validate_workspace(&workspace)?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO workspaces (name, description) VALUES (?1, ?2)",
+        params![workspace.name.trim(), workspace.description],
+    )
+    .map_err(|e| format!("Failed to create workspace: {}", e))?;
+    let id = tx.last_insert_rowid();
+    save_members(&tx, id, &paths).map_err(|e| format!("Failed to save workspace projects: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_workspace(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Workspace disappeared after creation".to_string())
+}
+
+/// Update a workspace's name, description and projects
+#[tauri::command]
+pub async fn update_workspace(
+    db: State<'_, AgentDb>,
+    workspace: Workspace,
+) -> Result<Workspace, String> {
+    let id = workspace.id.ok_or("Workspace id is required")?;
+    let paths = validate_workspace(&workspace)?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let rows = tx
+        .execute(
+            "UPDATE workspaces SET name = ?1, description = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+            params![workspace.name.trim(), workspace.description, id],
+        )
+        .map_err(|e| format!("Failed to update workspace: {}", e))?;
+    if rows == 0 {
+        return Err(format!("Workspace {} not found", id));
+    }
+    save_members(&tx, id, &paths).map_err(|e| format!("Failed to save workspace projects: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_workspace(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Workspace {} not found", id))
+}
+
+/// Delete a workspace; its projects are left untouched
+#[tauri::command]
+pub async fn delete_workspace(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM workspace_projects WHERE workspace_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM workspaces WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete workspace: {}", e))?;
+    Ok(())
+}
+
+/// Sessions, agent runs, usage and MCP servers across a workspace's projects
+#[tauri::command]
+pub async fn get_workspace_overview(
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<WorkspaceOverview, String> {
+    let (workspace, agent_runs) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let workspace = load_workspace(&conn, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Workspace {} not found", id))?;
+
+        let mut runs = Vec::new();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM agent_runs WHERE project_path = ?1 ORDER BY created_at DESC LIMIT ?2",
+                AGENT_RUN_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        for path in &workspace.project_paths {
+            let member_runs = stmt
+                .query_map(params![path, OVERVIEW_ITEM_LIMIT as i64], agent_run_from_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            runs.extend(member_runs);
+        }
+        runs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        runs.truncate(OVERVIEW_ITEM_LIMIT);
+        drop(stmt);
+        (workspace, runs)
+    };
+
+    let is_member =
+        |path: &str| workspace.project_paths.contains(&normalize_project_path(path));
+
+    let mut sessions = Vec::new();
+    for project in list_projects().await?.into_iter().filter(|p| is_member(&p.path)) {
+        match get_project_sessions(project.id.clone()).await {
+            Ok(project_sessions) => sessions.extend(project_sessions),
+            Err(e) => log::warn!("Failed to load sessions for {}: {}", project.path, e),
+        }
+    }
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.modified_at));
+    sessions.truncate(OVERVIEW_ITEM_LIMIT);
+
+    let mut usage = WorkspaceUsage::default();
+    match get_usage_stats(None) {
+        Ok(stats) => {
+            for project in stats.by_project.into_iter().filter(|p| is_member(&p.project_path)) {
+                usage.total_cost += project.total_cost;
+                usage.total_tokens += project.total_tokens;
+                usage.session_count += project.session_count;
+                usage.by_project.push(project);
+            }
+        }
+        Err(e) => log::warn!("Failed to load usage for workspace {}: {}", id, e),
+    }
+
+    let mut mcp_servers = Vec::new();
+    let mut missing_paths = Vec::new();
+    for path in &workspace.project_paths {
+        if !std::path::Path::new(path).is_dir() {
+            missing_paths.push(path.clone());
+            continue;
+        }
+        match mcp_read_project_config(path.clone()).await {
+            Ok(config) if !config.mcp_servers.is_empty() => mcp_servers.push(WorkspaceMcpServers {
+                project_path: path.clone(),
+                servers: config.mcp_servers,
+            }),
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to read .mcp.json in {}: {}", path, e),
+        }
+    }
+
+    Ok(WorkspaceOverview {
+        workspace,
+        sessions,
+        agent_runs,
+        usage,
+        mcp_servers,
+        missing_paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_are_normalized_and_ordered() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_workspace_tables(&conn).unwrap();
+
+        let workspace = Workspace {
+            id: None,
+            name: "Monorepo".to_string(),
+            description: None,
+            project_paths: vec![
+                "/work/app/".to_string(),
+                "/work/api".to_string(),
+                "/work/app".to_string(),
+            ],
+            created_at: None,
+            updated_at: None,
+        };
+        let paths = validate_workspace(&workspace).unwrap();
+        assert_eq!(paths, vec!["/work/app", "/work/api"]);
+
+        conn.execute("INSERT INTO workspaces (name) VALUES ('Monorepo')", [])
+            .unwrap();
+        save_members(&conn, 1, &paths).unwrap();
+        let loaded = load_workspace(&conn, 1).unwrap().unwrap();
+        assert_eq!(loaded.project_paths, paths);
+        assert_eq!(workspaces_for_project(&conn, "/work/api/").unwrap(), vec![1]);
+
+        let relative = Workspace {
+            project_paths: vec!["work/app".to_string()],
+            ..workspace
+        };
+        assert!(validate_workspace(&relative).is_err());
+    }
+}
@@ -0,0 +1,583 @@
+#![allow(dead_code)]
+
+//! Git worktree isolation for agent runs. Agents with isolation enabled run in a worktree of
+//! their own, on a fresh `opcode/...` branch created from the project's current HEAD, so
+//! parallel runs never edit the same files. When a run completes, its changes are committed
+//! to that branch and the worktree is removed (unless the agent keeps worktrees), leaving the
+//! branch to review and merge like any other. Failed or cancelled runs keep their worktree,
+//! and retries continue in it.
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+
+/// Branch prefix for run worktrees
+const BRANCH_PREFIX: &str = "opcode/";
+
+/// Whether an agent runs in its own worktree
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WorktreePolicy {
+    pub enabled: bool,
+    /// Keep the worktree directory after a successful run instead of removing it
+    pub keep_worktree: bool,
+}
+
+/// A worktree created for an agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunWorktree {
+    pub run_id: i64,
+    /// Top level of the repository the worktree belongs to
+    pub repo_path: String,
+    pub worktree_path: String,
+    pub branch: String,
+    /// Branch (or commit, when detached) the worktree was created from
+    pub base_ref: String,
+    pub base_commit: String,
+    /// `active`, `kept` or `removed`
+    pub status: String,
+    /// Commit holding the run's changes, if it made any
+    pub commit_sha: Option<String>,
+    pub created_at: String,
+    pub removed_at: Option<String>,
+}
+
+/// A worktree just created, before it is tied to a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewWorktree {
+    pub repo_path: String,
+    pub worktree_path: PathBuf,
+    pub branch: String,
+    pub base_ref: String,
+    pub base_commit: String,
+    /// Where the agent runs: the project's directory inside the worktree
+    pub working_dir: PathBuf,
+}
+
+/// Create the worktree tables
+pub fn init_worktree_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_worktree_policies (
+            agent_id INTEGER PRIMARY KEY,
+            enabled BOOLEAN NOT NULL DEFAULT 0,
+            keep_worktree BOOLEAN NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_worktrees (
+            run_id INTEGER PRIMARY KEY,
+            repo_path TEXT NOT NULL,
+            worktree_path TEXT NOT NULL,
+            branch TEXT NOT NULL,
+            base_ref TEXT NOT NULL,
+            base_commit TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            commit_sha TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            removed_at TEXT,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const WORKTREE_COLUMNS: &str =
+    "run_id, repo_path, worktree_path, branch, base_ref, base_commit, status, commit_sha, created_at, removed_at";
+
+fn worktree_from_row(row: &rusqlite::Row) -> SqliteResult<RunWorktree> {
+    Ok(RunWorktree {
+        run_id: row.get(0)?,
+        repo_path: row.get(1)?,
+        worktree_path: row.get(2)?,
+        branch: row.get(3)?,
+        base_ref: row.get(4)?,
+        base_commit: row.get(5)?,
+        status: row.get(6)?,
+        commit_sha: row.get(7)?,
+        created_at: row.get(8)?,
+        removed_at: row.get(9)?,
+    })
+}
+
+pub(crate) fn load_run_worktree(
+    conn: &Connection,
+    run_id: i64,
+) -> SqliteResult<Option<RunWorktree>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM run_worktrees WHERE run_id = ?1",
+            WORKTREE_COLUMNS
+        ),
+        params![run_id],
+        worktree_from_row,
+    )
+    .optional()
+}
+
+/// Load the worktree policy for an agent; isolation is off unless set
+pub fn load_worktree_policy(conn: &Connection, agent_id: i64) -> SqliteResult<WorktreePolicy> {
+    Ok(conn
+        .query_row(
+            "SELECT enabled, keep_worktree FROM agent_worktree_policies WHERE agent_id = ?1",
+            params![agent_id],
+            |row| {
+                Ok(WorktreePolicy {
+                    enabled: row.get(0)?,
+                    keep_worktree: row.get(1)?,
+                })
+            },
+        )
+        .optional()?
+        .unwrap_or_default())
+}
+
+/// Run git in `dir`, returning trimmed stdout or git's error output
+pub(crate) fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW = 0x08000000
+        command.creation_flags(0x08000000);
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(crate::decoding::decode_command_output(&output.stdout)
+            .trim()
+            .to_string())
+    } else {
+        Err(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            crate::decoding::decode_command_output(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Arguments that give git an opcode identity on machines without one, so commits and
+/// merges made for the user don't fail
+pub(crate) fn identity_args(dir: &Path) -> Vec<&'static str> {
+    if git(dir, &["config", "user.email"]).is_ok() {
+        return Vec::new();
+    }
+    vec![
+        "-c",
+        "user.name=opcode",
+        "-c",
+        "user.email=opcode@localhost",
+    ]
+}
+
+/// Branch-safe form of a name
+pub(crate) fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "agent".to_string()
+    } else {
+        slug.chars().take(40).collect()
+    }
+}
+
+/// Create a worktree of the repository containing `project_path` under `worktrees_dir`, on a
+/// new branch from the current HEAD. Uncommitted changes in the project are not carried over.
+pub fn create_worktree(
+    project_path: &str,
+    worktrees_dir: &Path,
+    name: &str,
+) -> Result<NewWorktree, String> {
+    let project = Path::new(project_path);
+    let repo_path = git(project, &["rev-parse", "--show-toplevel"])
+        .map_err(|_| format!("{} is not inside a git repository", project_path))?;
+    let base_commit = git(project, &["rev-parse", "HEAD"])
+        .map_err(|_| format!("{} has no commits to create a worktree from", repo_path))?;
+    let base_ref = git(project, &["symbolic-ref", "--short", "-q", "HEAD"])
+        .ok()
+        .filter(|branch| !branch.is_empty())
+        .unwrap_or_else(|| base_commit.clone());
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let suffix = &id[..8];
+    let branch = format!("{}{}-{}", BRANCH_PREFIX, slug(name), suffix);
+    let repo_name = Path::new(&repo_path)
+        .file_name()
+        .map(|n| slug(&n.to_string_lossy()))
+        .unwrap_or_else(|| "repo".to_string());
+    let worktree_path = worktrees_dir.join(format!("{}-{}-{}", repo_name, slug(name), suffix));
+
+    std::fs::create_dir_all(worktrees_dir)
+        .map_err(|e| format!("Failed to create worktrees directory: {}", e))?;
+    git(
+        Path::new(&repo_path),
+        &[
+            "worktree",
+            "add",
+            "-b",
+            &branch,
+            &worktree_path.to_string_lossy(),
+            &base_commit,
+        ],
+    )?;
+
+    // Run from the same place inside the worktree as the project is inside the repository
+    let relative = std::fs::canonicalize(project)
+        .ok()
+        .zip(std::fs::canonicalize(&repo_path).ok())
+        .and_then(|(project, repo)| project.strip_prefix(repo).ok().map(|p| p.to_path_buf()))
+        .unwrap_or_default();
+
+    Ok(NewWorktree {
+        repo_path,
+        working_dir: worktree_path.join(relative),
+        worktree_path,
+        branch,
+        base_ref,
+        base_commit,
+    })
+}
+
+/// Commit everything the run changed in the worktree to its branch.
+/// Returns the new commit, or `None` when the run changed nothing.
+pub fn commit_worktree(worktree: &Path, message: &str) -> Result<Option<String>, String> {
+    git(worktree, &["add", "-A"])?;
+    if git(worktree, &["status", "--porcelain"])?.is_empty() {
+        return Ok(None);
+    }
+    let mut args = identity_args(worktree);
+    args.extend(["commit", "-q", "--no-verify", "-m", message]);
+    git(worktree, &args)?;
+    git(worktree, &["rev-parse", "HEAD"]).map(Some)
+}
+
+/// Remove a worktree; the branch is deleted too when `delete_branch` is set
+pub fn remove_worktree(
+    repo_path: &str,
+    worktree_path: &str,
+    branch: &str,
+    delete_branch: bool,
+) -> Result<(), String> {
+    let repo = Path::new(repo_path);
+    if Path::new(worktree_path).exists() {
+        git(repo, &["worktree", "remove", "--force", worktree_path])?;
+    } else {
+        let _ = git(repo, &["worktree", "prune"]);
+    }
+    if delete_branch {
+        git(repo, &["branch", "-D", branch])?;
+    }
+    Ok(())
+}
+
+/// Directory the run worktrees are created in
+fn worktrees_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("worktrees"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Whether `path` is inside a git work tree with at least one commit
+pub fn is_git_checkout(path: &str) -> bool {
+    git(Path::new(path), &["rev-parse", "--verify", "-q", "HEAD"]).is_ok()
+}
+
+/// Create a worktree for a run of the agent when its policy asks for one, or when `isolate`
+/// is set and the project is a git checkout. Returns the worktree the run should use
+/// instead of `project_path`.
+pub fn prepare_run_worktree(
+    app: &AppHandle,
+    agent_id: i64,
+    agent_name: &str,
+    project_path: &str,
+    isolate: bool,
+) -> Result<Option<NewWorktree>, String> {
+    let policy = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_worktree_policy(&conn, agent_id).map_err(|e| e.to_string())?
+    };
+    let isolated = isolate && is_git_checkout(project_path);
+    if !policy.enabled && !isolated {
+        return Ok(None);
+    }
+    let worktree = create_worktree(project_path, &worktrees_dir(app)?, agent_name)
+        .map_err(|e| format!("Failed to create worktree for {}: {}", agent_name, e))?;
+    log::info!(
+        "🌿 Created worktree {} on branch {}",
+        worktree.worktree_path.display(),
+        worktree.branch
+    );
+    Ok(Some(worktree))
+}
+
+/// Record the worktree a run was started in
+pub fn record_run_worktree(
+    conn: &Connection,
+    run_id: i64,
+    worktree: &NewWorktree,
+) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO run_worktrees (run_id, repo_path, worktree_path, branch, base_ref, base_commit) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            run_id,
+            worktree.repo_path,
+            worktree.worktree_path.to_string_lossy(),
+            worktree.branch,
+            worktree.base_ref,
+            worktree.base_commit
+        ],
+    )?;
+    Ok(())
+}
+
+/// Wrap up a run's worktree once the run ends. Successful runs have their changes committed
+/// to the branch and, unless the agent keeps worktrees, the worktree removed; a branch with
+/// no changes is deleted along with it. Other runs keep the worktree as it is.
+pub fn finish_run_worktree(
+    conn: &Connection,
+    run_id: i64,
+    succeeded: bool,
+) -> Result<Option<RunWorktree>, String> {
+    let Some(worktree) = load_run_worktree(conn, run_id).map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    if worktree.status != "active" {
+        return Ok(Some(worktree));
+    }
+
+    if !succeeded {
+        conn.execute(
+            "UPDATE run_worktrees SET status = 'kept' WHERE run_id = ?1",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+        return load_run_worktree(conn, run_id).map_err(|e| e.to_string());
+    }
+
+    let agent_id: i64 = conn
+        .query_row(
+            "SELECT agent_id FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let policy = load_worktree_policy(conn, agent_id).map_err(|e| e.to_string())?;
+
+    let commit_sha = commit_worktree(
+        Path::new(&worktree.worktree_path),
+        &format!("opcode agent run {}", run_id),
+    )?;
+    let status = if policy.keep_worktree {
+        "kept"
+    } else {
+        remove_worktree(
+            &worktree.repo_path,
+            &worktree.worktree_path,
+            &worktree.branch,
+            commit_sha.is_none(),
+        )?;
+        "removed"
+    };
+    conn.execute(
+        "UPDATE run_worktrees SET status = ?2, commit_sha = ?3,
+         removed_at = CASE WHEN ?2 = 'removed' THEN CURRENT_TIMESTAMP ELSE removed_at END
+         WHERE run_id = ?1",
+        params![run_id, status, commit_sha],
+    )
+    .map_err(|e| e.to_string())?;
+
+    load_run_worktree(conn, run_id).map_err(|e| e.to_string())
+}
+
+/// Get the worktree policy configured for an agent
+#[tauri::command]
+pub async fn get_agent_worktree_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<WorktreePolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_worktree_policy(&conn, agent_id).map_err(|e| e.to_string())
+}
+
+/// Set whether an agent runs in its own worktree
+#[tauri::command]
+pub async fn set_agent_worktree_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    policy: WorktreePolicy,
+) -> Result<WorktreePolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO agent_worktree_policies (agent_id, enabled, keep_worktree) VALUES (?1, ?2, ?3)
+         ON CONFLICT(agent_id) DO UPDATE SET enabled = ?2, keep_worktree = ?3, updated_at = CURRENT_TIMESTAMP",
+        params![agent_id, policy.enabled, policy.keep_worktree],
+    )
+    .map_err(|e| format!("Failed to save worktree policy: {}", e))?;
+    Ok(policy)
+}
+
+/// List run worktrees, newest first, optionally only those of one repository
+#[tauri::command]
+pub async fn list_run_worktrees(
+    db: State<'_, AgentDb>,
+    repo_path: Option<String>,
+) -> Result<Vec<RunWorktree>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM run_worktrees WHERE ?1 IS NULL OR repo_path = ?1 ORDER BY created_at DESC, run_id DESC",
+            WORKTREE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let worktrees = stmt
+        .query_map(params![repo_path], worktree_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(worktrees)
+}
+
+/// Get the worktree a run used, if any
+#[tauri::command]
+pub async fn get_run_worktree(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<RunWorktree>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_run_worktree(&conn, run_id).map_err(|e| e.to_string())
+}
+
+/// Remove a run's worktree, optionally deleting its branch as well
+#[tauri::command]
+pub async fn remove_run_worktree(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+    delete_branch: bool,
+) -> Result<RunWorktree, String> {
+    if registry.0.get_process(run_id).ok().flatten().is_some() {
+        return Err(format!("Run {} is still running in this worktree", run_id));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let worktree = load_run_worktree(&conn, run_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Run {} has no worktree", run_id))?;
+
+    if worktree.status != "removed" || delete_branch {
+        remove_worktree(
+            &worktree.repo_path,
+            &worktree.worktree_path,
+            &worktree.branch,
+            delete_branch,
+        )?;
+    }
+    conn.execute(
+        "UPDATE run_worktrees SET status = 'removed', removed_at = COALESCE(removed_at, CURRENT_TIMESTAMP) WHERE run_id = ?1",
+        params![run_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    load_run_worktree(&conn, run_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Run {} has no worktree", run_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo(dir: &Path) {
+        git(dir, &["init", "-q", "-b", "main"]).unwrap();
+        std::fs::create_dir_all(dir.join("app")).unwrap();
+        std::fs::write(dir.join("app/lib.rs"), "fn a() {}\n").unwrap();
+        git(dir, &["add", "-A"]).unwrap();
+        git(
+            dir,
+            &[
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@t",
+                "commit",
+                "-q",
+                "-m",
+                "init",
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_worktree_runs_in_project_subdirectory_and_commits_changes() {
+        let repo = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        init_repo(repo.path());
+
+        let project = repo.path().join("app");
+        let worktree =
+            create_worktree(&project.to_string_lossy(), store.path(), "Code Reviewer").unwrap();
+        assert_eq!(worktree.base_ref, "main");
+        assert!(worktree.branch.starts_with("opcode/code-reviewer-"));
+        assert!(worktree.working_dir.ends_with("app"));
+        assert!(worktree.working_dir.join("lib.rs").exists());
+
+        std::fs::write(worktree.working_dir.join("lib.rs"), "fn b() {}\n").unwrap();
+        let commit = commit_worktree(&worktree.worktree_path, "run 1").unwrap();
+        assert!(commit.is_some());
+        assert_eq!(
+            commit_worktree(&worktree.worktree_path, "run 1").unwrap(),
+            None
+        );
+
+        remove_worktree(
+            &worktree.repo_path,
+            &worktree.worktree_path.to_string_lossy(),
+            &worktree.branch,
+            false,
+        )
+        .unwrap();
+        assert!(!worktree.worktree_path.exists());
+        // The branch keeps the run's changes for review
+        let content = git(
+            repo.path(),
+            &["show", &format!("{}:app/lib.rs", worktree.branch)],
+        )
+        .unwrap();
+        assert_eq!(content, "fn b() {}");
+    }
+
+    #[test]
+    fn test_worktree_requires_a_git_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let error =
+            create_worktree(&dir.path().to_string_lossy(), store.path(), "agent").unwrap_err();
+        assert!(error.contains("not inside a git repository"));
+        assert_eq!(slug("  My Agent!! "), "my-agent");
+    }
+}
@@ -0,0 +1,191 @@
+#![allow(dead_code)]
+
+//! Decoding of process output. Every path that turns child process bytes into text goes
+//! through here, so output from a Chinese, Japanese or Cyrillic Windows console reads the
+//! same in the terminal, MCP and process registry views.
+//!
+//! Valid UTF-8 (with or without a BOM) and BOM-marked UTF-16 are always recognized. Anything
+//! else is decoded with the fallback codepage: the one configured under `output_codepage`,
+//! or GBK on Windows when none is set. Bytes that don't fit either are decoded lossily.
+
+use encoding_rs::Encoding;
+use std::sync::RwLock;
+
+/// app_settings key holding the fallback codepage, e.g. `gbk`, `936` or `shift_jis`
+pub const OUTPUT_CODEPAGE_KEY: &str = "output_codepage";
+
+/// Fallback encoding chosen in settings; `None` means the platform default
+static FALLBACK_ENCODING: RwLock<Option<&'static Encoding>> = RwLock::new(None);
+
+/// Windows codepage numbers and the encodings they name
+const WINDOWS_CODEPAGES: &[(u16, &str)] = &[
+    (874, "windows-874"),
+    (932, "shift_jis"),
+    (936, "gbk"),
+    (949, "euc-kr"),
+    (950, "big5"),
+    (1250, "windows-1250"),
+    (1251, "windows-1251"),
+    (1252, "windows-1252"),
+    (1253, "windows-1253"),
+    (1254, "windows-1254"),
+    (1255, "windows-1255"),
+    (1256, "windows-1256"),
+    (1257, "windows-1257"),
+    (1258, "windows-1258"),
+    (54936, "gb18030"),
+    (65001, "utf-8"),
+];
+
+/// Encoding for a codepage given as a label (`gbk`, `shift_jis`) or Windows number (`936`,
+/// `cp936`)
+pub fn encoding_for_codepage(codepage: &str) -> Option<&'static Encoding> {
+    let codepage = codepage.trim();
+    let number = codepage
+        .strip_prefix("cp")
+        .or_else(|| codepage.strip_prefix("CP"))
+        .unwrap_or(codepage);
+    if let Ok(number) = number.parse::<u16>() {
+        let label = WINDOWS_CODEPAGES
+            .iter()
+            .find(|(known, _)| *known == number)
+            .map(|(_, label)| *label)?;
+        return Encoding::for_label(label.as_bytes());
+    }
+    Encoding::for_label(codepage.as_bytes())
+}
+
+/// Set the fallback codepage; `None` restores the platform default
+pub fn set_fallback_codepage(codepage: Option<&str>) -> Result<(), String> {
+    let encoding = match codepage.filter(|codepage| !codepage.trim().is_empty()) {
+        Some(codepage) => Some(
+            encoding_for_codepage(codepage)
+                .ok_or_else(|| format!("Unknown codepage: {}", codepage))?,
+        ),
+        None => None,
+    };
+    if let Ok(mut fallback) = FALLBACK_ENCODING.write() {
+        *fallback = encoding;
+    }
+    Ok(())
+}
+
+/// Apply the codepage stored in settings, ignoring values that don't name an encoding
+pub fn apply_output_codepage(codepage: Option<String>) {
+    if let Err(e) = set_fallback_codepage(codepage.as_deref()) {
+        log::warn!("{}; using the default output encoding", e);
+        let _ = set_fallback_codepage(None);
+    }
+}
+
+fn fallback_encoding() -> Option<&'static Encoding> {
+    let configured = FALLBACK_ENCODING.read().ok().and_then(|fallback| *fallback);
+    if configured.is_some() {
+        return configured;
+    }
+    // Chinese Windows consoles write GBK unless switched to codepage 65001
+    if cfg!(target_os = "windows") {
+        Some(encoding_rs::GBK)
+    } else {
+        None
+    }
+}
+
+/// Decode `bytes`, trying `fallback` when they aren't UTF-8
+pub fn decode_with(bytes: &[u8], fallback: Option<&'static Encoding>) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (decoded, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return decoded.into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    if let Some(encoding) = fallback {
+        let (decoded, had_errors) = encoding.decode_without_bom_handling(bytes);
+        if !had_errors {
+            return decoded.into_owned();
+        }
+    }
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Converts command output bytes to a string with the detected or configured encoding
+pub fn decode_command_output(bytes: &[u8]) -> String {
+    decode_with(bytes, fallback_encoding())
+}
+
+/// Async helper function to read and decode a line with proper encoding handling
+/// This is used for streaming command output where encoding conversion is needed
+pub async fn read_decoded_line<R: tokio::io::AsyncReadExt + Unpin>(
+    reader: &mut tokio::io::BufReader<R>,
+) -> tokio::io::Result<Option<String>> {
+    use tokio::io::AsyncReadExt;
+    let mut buffer = Vec::new();
+    loop {
+        let byte = match reader.read_u8().await {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                if buffer.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+
+        if byte == b'\n' {
+            break;
+        }
+
+        if byte != b'\r' {
+            buffer.push(byte);
+        }
+    }
+
+    if buffer.is_empty() {
+        return Ok(Some(String::new()));
+    }
+
+    Ok(Some(decode_command_output(&buffer)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_detects_utf8_boms_and_falls_back() {
+        // "中文" in GBK
+        let gbk = [0xd6, 0xd0, 0xce, 0xc4];
+        assert_eq!(decode_with(&gbk, Some(encoding_rs::GBK)), "中文");
+        assert_eq!(decode_with(&gbk, None), "\u{fffd}\u{fffd}\u{fffd}\u{fffd}");
+        assert_eq!(
+            decode_with("中文".as_bytes(), Some(encoding_rs::SHIFT_JIS)),
+            "中文"
+        );
+        assert_eq!(decode_with(b"\xef\xbb\xbfok", None), "ok");
+        // UTF-16LE with a BOM, as PowerShell redirects write it
+        assert_eq!(decode_with(&[0xff, 0xfe, b'o', 0, b'k', 0], None), "ok");
+        // Bytes that don't fit the fallback either are decoded lossily
+        assert_eq!(
+            decode_with(&[b'a', 0x81], Some(encoding_rs::GBK)),
+            "a\u{fffd}"
+        );
+    }
+
+    #[test]
+    fn test_codepages_resolve_by_label_or_number() {
+        assert_eq!(encoding_for_codepage("936"), Some(encoding_rs::GBK));
+        assert_eq!(encoding_for_codepage("cp936"), Some(encoding_rs::GBK));
+        assert_eq!(
+            encoding_for_codepage("Shift_JIS"),
+            Some(encoding_rs::SHIFT_JIS)
+        );
+        assert_eq!(
+            encoding_for_codepage("1251"),
+            Some(encoding_rs::WINDOWS_1251)
+        );
+        assert_eq!(encoding_for_codepage("437"), None);
+        assert!(set_fallback_codepage(Some("klingon")).is_err());
+    }
+}
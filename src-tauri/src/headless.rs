@@ -0,0 +1,356 @@
+//! Headless access to the same database and agent pipeline the GUI uses, for the
+//! `opcode-cli` binary and other callers without a Tauri app handle.
+
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use tokio::io::BufReader as TokioBufReader;
+
+use crate::claude_binary::{find_claude_binary_in, read_decoded_line};
+use crate::commands::agent_retry::FailureClassifier;
+use crate::commands::agents::{
+    agent_from_row, agent_run_from_row, build_agent_args, create_agent_system_command,
+    open_database, Agent, AgentRun, AGENT_COLUMNS, AGENT_RUN_COLUMNS,
+};
+use crate::commands::approval_mcp::load_approval_enabled;
+use crate::commands::model_policy::served_model_from_line;
+use crate::commands::provider_profiles::{apply_env, profile_for_run};
+use crate::commands::sandbox::load_agent_sandbox_profile;
+
+/// Bundle identifier from tauri.conf.json; Tauri stores app data under it
+pub const APP_IDENTIFIER: &str = "opcode.asterisk.so";
+
+/// The directory the GUI keeps `agents.db` in
+pub fn default_app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// A line of output from a headless agent run
+#[derive(Debug, Clone, Copy)]
+pub enum RunOutput<'a> {
+    Stdout(&'a str),
+    Stderr(&'a str),
+}
+
+/// Opened app database plus the directory it lives in
+pub struct Headless {
+    app_data_dir: PathBuf,
+    conn: Connection,
+}
+
+impl Drop for Headless {
+    /// Reseal an encrypted database, removing the working copy if this process unsealed it
+    fn drop(&mut self) {
+        crate::commands::encryption::close_database(&self.conn, &self.app_data_dir.join("agents.db"));
+    }
+}
+
+impl Headless {
+    /// Open the app database, creating it if the GUI has never run
+    pub fn open(app_data_dir: Option<PathBuf>) -> Result<Self, String> {
+        let app_data_dir = app_data_dir
+            .or_else(default_app_data_dir)
+            .ok_or("Could not determine the app data directory")?;
+        std::fs::create_dir_all(&app_data_dir)
+            .map_err(|e| format!("Failed to create {}: {}", app_data_dir.display(), e))?;
+        let db_path = app_data_dir.join("agents.db");
+        crate::commands::encryption::unseal_database(&db_path)?;
+        let conn =
+            open_database(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+        crate::commands::session_index::set_store_path(&db_path);
+        Ok(Self { app_data_dir, conn })
+    }
+
+    pub fn app_data_dir(&self) -> &Path {
+        &self.app_data_dir
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Claude binary, honouring the path configured in the GUI
+    pub fn claude_path(&self) -> Result<String, String> {
+        find_claude_binary_in(Some(&self.app_data_dir))
+    }
+
+    pub fn list_agents(&self) -> Result<Vec<Agent>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM agents ORDER BY created_at DESC",
+                AGENT_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let agents = stmt
+            .query_map([], agent_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(agents)
+    }
+
+    /// Look an agent up by id or (case-insensitive) name
+    pub fn find_agent(&self, id_or_name: &str) -> Result<Agent, String> {
+        let agents = self.list_agents()?;
+        let found = match id_or_name.parse::<i64>() {
+            Ok(id) => agents.into_iter().find(|agent| agent.id == Some(id)),
+            Err(_) => agents
+                .into_iter()
+                .find(|agent| agent.name.eq_ignore_ascii_case(id_or_name)),
+        };
+        found.ok_or_else(|| format!("Agent '{}' not found", id_or_name))
+    }
+
+    pub fn get_run(&self, run_id: i64) -> Result<AgentRun, String> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
+                params![run_id],
+                agent_run_from_row,
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn list_runs(&self, agent_id: Option<i64>, limit: usize) -> Result<Vec<AgentRun>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM agent_runs WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY created_at DESC LIMIT ?2",
+                AGENT_RUN_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let runs = stmt
+            .query_map(params![agent_id, limit as i64], agent_run_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(runs)
+    }
+
+    /// Run settings of the agent and project that only the GUI pipeline applies. Running
+    /// without them would drop the run's confinement or move it off its host, so headless
+    /// runs of such agents are refused rather than run unconfined.
+    pub fn unsupported_run_settings(
+        &self,
+        agent_id: i64,
+        project_path: &str,
+    ) -> Result<Vec<&'static str>, String> {
+        let mut unsupported = Vec::new();
+        if load_agent_sandbox_profile(&self.conn, agent_id)
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            unsupported.push("a sandbox profile");
+        }
+        if load_approval_enabled(&self.conn, agent_id).map_err(|e| e.to_string())? {
+            unsupported.push("tool approval");
+        }
+        if crate::commands::remote_hosts::host_for_project(&self.conn, project_path)
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            unsupported.push("a remote host");
+        }
+        if crate::commands::containers::container_for_project(&self.conn, project_path)
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            unsupported.push("a container");
+        }
+        Ok(unsupported)
+    }
+
+    /// Run an agent to completion, recording it in the run history like the GUI does.
+    /// Retries, model fallback, webhooks and the file change manifest are GUI-only.
+    pub async fn run_agent(
+        &self,
+        agent: &Agent,
+        project_path: &str,
+        task: &str,
+        model: Option<String>,
+        mut on_output: impl FnMut(RunOutput<'_>),
+    ) -> Result<AgentRun, String> {
+        let agent_id = agent.id.ok_or("Agent has no id")?;
+        let model = model.unwrap_or_else(|| agent.model.clone());
+        crate::commands::team_policy::check_run_allowed(project_path, Some(&model))?;
+        let unsupported = self.unsupported_run_settings(agent_id, project_path)?;
+        if !unsupported.is_empty() {
+            return Err(format!(
+                "'{}' uses {}, which only the app can apply; run it from opcode",
+                agent.name,
+                unsupported.join(", ")
+            ));
+        }
+        let claude_path = self.claude_path()?;
+        let provider_profile = profile_for_run(&self.conn, project_path, None)?;
+        let provider_env = match &provider_profile {
+            Some(profile) => profile.env_vars()?,
+            None => Vec::new(),
+        };
+
+        self.conn
+            .execute(
+                "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, provider_profile_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    agent_id,
+                    agent.name,
+                    agent.icon,
+                    task,
+                    model,
+                    project_path,
+                    "",
+                    provider_profile.and_then(|profile| profile.id)
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        let run_id = self.conn.last_insert_rowid();
+
+        let args = build_agent_args(agent, task, &model);
+        let mut cmd = create_agent_system_command(&claude_path, args, project_path);
+        for (key, value) in agent.thinking.clone().unwrap_or_default().env() {
+            cmd.env(key, value);
+        }
+        apply_env(&mut cmd, &provider_env);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+
+        self.conn
+            .execute(
+                "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2 WHERE id = ?3",
+                params![
+                    child.id().unwrap_or(0) as i64,
+                    chrono::Utc::now().to_rfc3339(),
+                    run_id
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+        let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+        let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+
+        // Merge both streams into one channel so output is handled in arrival order
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(bool, String)>();
+        let stdout_tx = tx.clone();
+        tokio::spawn(async move {
+            let mut reader = TokioBufReader::new(stdout);
+            while let Ok(Some(line)) = read_decoded_line(&mut reader).await {
+                if stdout_tx.send((false, line)).is_err() {
+                    break;
+                }
+            }
+        });
+        tokio::spawn(async move {
+            let mut reader = TokioBufReader::new(stderr);
+            while let Ok(Some(line)) = read_decoded_line(&mut reader).await {
+                if tx.send((true, line)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut classifier = FailureClassifier::default();
+        let mut session_id = String::new();
+        let mut served_model = None;
+        while let Some((is_stderr, line)) = rx.recv().await {
+            if is_stderr {
+                classifier.observe_stderr(&line);
+                on_output(RunOutput::Stderr(&line));
+                continue;
+            }
+
+            classifier.observe_stdout(&line);
+            if let Some(model) = served_model_from_line(&line) {
+                served_model = Some(model);
+            }
+            if session_id.is_empty() {
+                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
+                    if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
+                        session_id = sid.to_string();
+                    }
+                }
+            }
+            on_output(RunOutput::Stdout(&line));
+        }
+
+        let exit_code = child
+            .wait()
+            .await
+            .map_err(|e| format!("Failed to wait for Claude: {}", e))?
+            .code();
+        let status = if classifier.classify(exit_code).is_some() {
+            "failed"
+        } else {
+            "completed"
+        };
+
+        self.conn
+            .execute(
+                "UPDATE agent_runs SET session_id = ?1, status = ?2, served_model = ?3, completed_at = CURRENT_TIMESTAMP WHERE id = ?4",
+                params![session_id, status, served_model, run_id],
+            )
+            .map_err(|e| e.to_string())?;
+
+        self.get_run(run_id)
+    }
+
+    /// Run `claude mcp <args>` with the configured Claude binary
+    pub fn mcp(&self, args: Vec<String>) -> Result<String, String> {
+        let claude_path = self.claude_path()?;
+        crate::commands::mcp::run_claude_mcp_command(&claude_path, args).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_creates_database_and_finds_agents() {
+        let dir = tempfile::tempdir().unwrap();
+        let headless = Headless::open(Some(dir.path().to_path_buf())).unwrap();
+        headless
+            .connection()
+            .execute(
+                "INSERT INTO agents (name, icon, system_prompt, model) VALUES ('Reviewer', 'bot', 'Review code', 'sonnet')",
+                [],
+            )
+            .unwrap();
+
+        assert!(dir.path().join("agents.db").exists());
+        let agent = headless.find_agent("reviewer").unwrap();
+        assert_eq!(agent.name, "Reviewer");
+        assert_eq!(headless.find_agent(&agent.id.unwrap().to_string()).unwrap().name, "Reviewer");
+        assert!(headless.find_agent("missing").is_err());
+        assert!(headless.list_runs(None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_agents_with_tool_approval_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let headless = Headless::open(Some(dir.path().to_path_buf())).unwrap();
+        let conn = headless.connection();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt, model) VALUES ('Reviewer', 'bot', 'Review code', 'sonnet')",
+            [],
+        )
+        .unwrap();
+        let agent_id = conn.last_insert_rowid();
+        assert!(headless
+            .unsupported_run_settings(agent_id, "/tmp/project")
+            .unwrap()
+            .is_empty());
+
+        conn.execute(
+            "INSERT INTO agent_approval_settings (agent_id, enabled) VALUES (?1, 1)",
+            params![agent_id],
+        )
+        .unwrap();
+        assert_eq!(
+            headless
+                .unsupported_run_settings(agent_id, "/tmp/project")
+                .unwrap(),
+            vec!["tool approval"]
+        );
+    }
+}
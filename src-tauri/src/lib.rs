@@ -2,8 +2,13 @@
 
 // Declare modules
 pub mod checkpoint;
+pub mod atomic_file;
 pub mod claude_binary;
 pub mod commands;
+pub mod decoding;
+pub mod headless;
+pub mod log_scrubber;
+pub mod logger;
 pub mod process;
 pub mod web_server;
 
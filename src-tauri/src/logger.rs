@@ -1,211 +1,358 @@
-use std::fs::OpenOptions;
-use std::io::{self, Write};
-use std::path::PathBuf;
-use std::sync::Mutex;
-
-// Custom writer that writes to both file and stderr
-struct DualWriter {
-    file: Mutex<std::fs::File>,
-}
-
-impl Write for DualWriter {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut file = self.file.lock().unwrap();
-        // Write to file
-        file.write_all(buf)?;
-        // Also write to stderr
-        io::stderr().write_all(buf)?;
-        Ok(buf.len())
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        let mut file = self.file.lock().unwrap();
-        file.flush()?;
-        io::stderr().flush()?;
-        Ok(())
-    }
-}
-
-/// Initialize logger to write to both file and stderr
-/// Logs are written to a "logs" directory next to the executable:
-/// - Development: ./logs/opcode-YYYYMMDD.log (relative to current directory)
-/// - Production: <exe_dir>/logs/opcode-YYYYMMDD.log (next to the .exe file)
-pub fn init_logger() {
-    // Get log directory - prefer exe directory in production, current directory in dev
-    let log_dir = if cfg!(debug_assertions) {
-        // Development mode: use current directory
-        std::env::current_dir()
-            .unwrap_or_else(|_| PathBuf::from("."))
-            .join("logs")
-    } else {
-        // Production mode: use exe directory
-        std::env::current_exe()
-            .ok()
-            .and_then(|exe_path| exe_path.parent().map(|p| p.to_path_buf()))
-            .map(|exe_dir| exe_dir.join("logs"))
-            .unwrap_or_else(|| {
-                // Fallback to current directory if we can't determine exe path
-                std::env::current_dir()
-                    .unwrap_or_else(|_| PathBuf::from("."))
-                    .join("logs")
-            })
-    };
-
-    // Create log directory if it doesn't exist
-    if let Err(e) = std::fs::create_dir_all(&log_dir) {
-        eprintln!("Failed to create log directory {:?}: {}", log_dir, e);
-        // Fallback to stderr only
-        env_logger::Builder::from_default_env()
-            .filter_level(log::LevelFilter::Warn) // Default to warn level to capture all errors and warnings
-            .init();
-        return;
-    }
-
-    // Clean up old log files (keep last 30 days)
-    cleanup_old_logs(&log_dir);
-
-    // Create log file path with timestamp
-    let timestamp = chrono::Local::now().format("%Y%m%d");
-    let log_file = log_dir.join(format!("opcode-{}.log", timestamp));
-
-    // Open log file for appending
-    let file = match OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_file)
-    {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Failed to open log file {:?}: {}", log_file, e);
-            // Fallback to stderr only
-            env_logger::Builder::from_default_env()
-                .filter_level(log::LevelFilter::Warn) // Default to warn level to capture all errors and warnings
-                .init();
-            return;
-        }
-    };
-
-    // Create dual writer that writes to both file and stderr
-    let dual_writer = DualWriter {
-        file: Mutex::new(file),
-    };
-
-    // Configure logger to write to both file and stderr
-    // Default to Info level for Claude-related modules to capture all important logs
-    // Can be overridden by RUST_LOG environment variable
-    let mut builder = env_logger::Builder::from_default_env();
-    
-    // If RUST_LOG is not set, use Info level for Claude modules and Warn for others
-    if std::env::var("RUST_LOG").is_err() {
-        builder.filter_level(log::LevelFilter::Warn); // Default for all modules
-        // Set Info level for Claude-related modules to capture all important logs
-        builder.filter_module("opcode::commands::claude", log::LevelFilter::Info);
-        builder.filter_module("opcode::commands::agents", log::LevelFilter::Info);
-        builder.filter_module("opcode::claude_binary", log::LevelFilter::Info);
-        builder.filter_module("opcode::process", log::LevelFilter::Info);
-    }
-    
-    builder
-        .target(env_logger::Target::Pipe(Box::new(dual_writer)))
-        .format(|buf, record| {
-            use std::io::Write;
-            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-            let module_path = record.module_path().unwrap_or("unknown");
-            let file_path = record.file().unwrap_or("unknown");
-            let line = record.line().map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
-            
-            // Detect Claude-related logs more comprehensively
-            let args_str = record.args().to_string();
-            let is_claude_related = module_path.contains("claude") 
-                || module_path.contains("Claude")
-                || args_str.contains("Claude")
-                || args_str.contains("claude")
-                || args_str.contains("CLAUDE")
-                || file_path.contains("claude");
-            
-            // Enhanced prefix for Claude-related logs
-            let prefix = if is_claude_related {
-                match record.level() {
-                    log::Level::Error => "🔴 [CLAUDE ERROR]",
-                    log::Level::Warn => "⚠️  [CLAUDE WARN]",
-                    log::Level::Info => "ℹ️  [CLAUDE INFO]",
-                    log::Level::Debug => "🔍 [CLAUDE DEBUG]",
-                    log::Level::Trace => "🔎 [CLAUDE TRACE]",
-                }
-            } else {
-                match record.level() {
-                    log::Level::Error => "❌ [ERROR]",
-                    log::Level::Warn => "⚠️  [WARN]",
-                    log::Level::Info => "ℹ️  [INFO]",
-                    log::Level::Debug => "🔍 [DEBUG]",
-                    log::Level::Trace => "🔎 [TRACE]",
-                }
-            };
-            
-            writeln!(
-                buf,
-                "[{}] {} [{}] {}:{} - {}",
-                timestamp,
-                prefix,
-                record.level(),
-                file_path,
-                line,
-                record.args()
-            )
-        })
-        .init();
-
-    log::info!("==========================================");
-    log::info!("Logging initialized successfully");
-    log::info!("Log file: {:?}", log_file);
-    log::info!("Log directory: {:?}", log_dir);
-    if cfg!(debug_assertions) {
-        log::info!("Mode: Development (logs in current directory)");
-    } else {
-        log::info!("Mode: Production (logs next to executable)");
-        if let Ok(exe_path) = std::env::current_exe() {
-            log::info!("Executable: {:?}", exe_path);
-        }
-    }
-    log::info!("Log level: {} (set RUST_LOG environment variable to override)", 
-        std::env::var("RUST_LOG").unwrap_or_else(|_| "warn (info for claude modules)".to_string()));
-    log::info!("==========================================");
-}
-
-/// Clean up old log files, keeping only the last 30 days
-fn cleanup_old_logs(log_dir: &PathBuf) {
-    use std::fs;
-    
-    let entries = match fs::read_dir(log_dir) {
-        Ok(entries) => entries,
-        Err(_) => return,
-    };
-
-    let cutoff_date = chrono::Local::now() - chrono::Duration::days(30);
-    let mut deleted_count = 0;
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("log") {
-            // Try to extract date from filename like "opcode-20240101.log"
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if let Some(date_str) = file_name.strip_prefix("opcode-").and_then(|s| s.strip_suffix(".log")) {
-                    if let Ok(file_date) = chrono::NaiveDate::parse_from_str(date_str, "%Y%m%d") {
-                        if file_date < cutoff_date.date_naive() {
-                            if let Err(e) = fs::remove_file(&path) {
-                                eprintln!("Failed to delete old log file {:?}: {}", path, e);
-                            } else {
-                                deleted_count += 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    if deleted_count > 0 {
-        eprintln!("Cleaned up {} old log file(s)", deleted_count);
-    }
-}
-
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Bundle identifier from tauri.conf.json; logs live in the app data dir it names
+const APP_IDENTIFIER: &str = "opcode.asterisk.so";
+
+/// Size at which the day's log file is continued in a new numbered file
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Formatted log lines kept in memory for crash reports
+const RECENT_LINES_CAPACITY: usize = 200;
+
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Runtime level set with `set_level_override`; 0 means the default filters apply
+static LEVEL_OVERRIDE: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether `AppLogger` is the installed logger (not the stderr-only fallback)
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Highest level the default filters let through
+static DEFAULT_MAX_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Warn as usize);
+
+/// Directory the log files are written to
+pub fn log_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER).join("logs"))
+        .unwrap_or_else(|| {
+            std::env::current_dir()
+                .unwrap_or_else(|_| PathBuf::from("."))
+                .join("logs")
+        })
+}
+
+fn level_from_usize(value: usize) -> Option<LevelFilter> {
+    LevelFilter::iter().find(|level| *level as usize == value)
+}
+
+/// Log at `level` from now on, or go back to the default filters with `None`
+pub fn set_level_override(level: Option<LevelFilter>) {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        return;
+    }
+    LEVEL_OVERRIDE.store(level.map(|l| l as usize).unwrap_or(0), Ordering::Relaxed);
+    let default_max = level_from_usize(DEFAULT_MAX_LEVEL.load(Ordering::Relaxed))
+        .unwrap_or(LevelFilter::Warn);
+    log::set_max_level(level.map_or(default_max, |level| level.max(default_max)));
+}
+
+/// The runtime level override, if one is set
+pub fn level_override() -> Option<LevelFilter> {
+    match LEVEL_OVERRIDE.load(Ordering::Relaxed) {
+        0 => None,
+        value => level_from_usize(value),
+    }
+}
+
+/// Daily log file that moves on to a numbered file once it grows too large
+struct RotatingFile {
+    dir: PathBuf,
+    date: chrono::NaiveDate,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path) -> io::Result<Self> {
+        let date = chrono::Local::now().date_naive();
+        let (file, size) = open_log_file(dir, date)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            date,
+            file,
+            size,
+        })
+    }
+
+    fn rotate_if_needed(&mut self) -> io::Result<()> {
+        let today = chrono::Local::now().date_naive();
+        if today != self.date || self.size >= MAX_LOG_FILE_SIZE {
+            let (file, size) = open_log_file(&self.dir, today)?;
+            self.file = file;
+            self.size = size;
+            self.date = today;
+        }
+        Ok(())
+    }
+}
+
+/// Open the first file for `date` that still has room: opcode-YYYYMMDD.log,
+/// then opcode-YYYYMMDD.1.log and so on
+fn open_log_file(dir: &Path, date: chrono::NaiveDate) -> io::Result<(File, u64)> {
+    let stem = format!("opcode-{}", date.format("%Y%m%d"));
+    let mut index = 0;
+    loop {
+        let name = match index {
+            0 => format!("{}.log", stem),
+            n => format!("{}.{}.log", stem, n),
+        };
+        let path = dir.join(name);
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size < MAX_LOG_FILE_SIZE {
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            return Ok((file, size));
+        }
+        index += 1;
+    }
+}
+
+// Custom writer that writes to both file and stderr
+struct DualWriter {
+    file: RotatingFile,
+}
+
+impl Write for DualWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write to file, starting a new one on a new day or when it's full
+        self.file.rotate_if_needed()?;
+        self.file.file.write_all(buf)?;
+        self.file.size += buf.len() as u64;
+        remember_lines(buf);
+        // Also write to stderr
+        io::stderr().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.file.flush()?;
+        io::stderr().flush()?;
+        Ok(())
+    }
+}
+
+fn remember_lines(buf: &[u8]) {
+    // Never block logging on the buffer
+    let Ok(mut recent) = RECENT_LINES.try_lock() else {
+        return;
+    };
+    for line in String::from_utf8_lossy(buf).lines() {
+        if recent.len() == RECENT_LINES_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(line.to_string());
+    }
+}
+
+/// The most recent log lines, oldest first
+pub fn recent_log_lines() -> Vec<String> {
+    RECENT_LINES
+        .try_lock()
+        .map(|recent| recent.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Logger that applies the default filters unless a runtime level is set
+struct AppLogger {
+    /// Holds the default (RUST_LOG or built-in) filters; never writes
+    filter: env_logger::Logger,
+    /// Writes everything it is given
+    writer: env_logger::Logger,
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match level_override() {
+            Some(level) => metadata.level() <= level,
+            None => self.filter.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        // Errors are counted by kind when the usage counters are on
+        if record.level() == log::Level::Error && crate::commands::telemetry::is_enabled() {
+            crate::commands::telemetry::record_error(&record.args().to_string());
+        }
+        if self.enabled(record.metadata()) {
+            self.writer.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.writer.flush();
+    }
+}
+
+/// Initialize logger to write to both file and stderr
+/// Logs are written to the "logs" directory under the app data dir, one file per day
+/// (opcode-YYYYMMDD.log) that rolls over to numbered files past 10 MB.
+/// The level can be changed at runtime with `set_level_override`.
+pub fn init_logger() {
+    let log_dir = log_dir();
+
+    // Create log directory if it doesn't exist
+    if let Err(e) = std::fs::create_dir_all(&log_dir) {
+        eprintln!("Failed to create log directory {:?}: {}", log_dir, e);
+        // Fallback to stderr only
+        env_logger::Builder::from_default_env()
+            .filter_level(log::LevelFilter::Warn) // Default to warn level to capture all errors and warnings
+            .init();
+        return;
+    }
+
+    // Clean up old log files (keep last 30 days)
+    cleanup_old_logs(&log_dir);
+
+    // Open today's log file for appending
+    let file = match RotatingFile::open(&log_dir) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Failed to open log file in {:?}: {}", log_dir, e);
+            // Fallback to stderr only
+            env_logger::Builder::from_default_env()
+                .filter_level(log::LevelFilter::Warn) // Default to warn level to capture all errors and warnings
+                .init();
+            return;
+        }
+    };
+
+    // Create dual writer that writes to both file and stderr
+    let dual_writer = DualWriter { file };
+
+    // Configure logger to write to both file and stderr
+    // Default to Info level for Claude-related modules to capture all important logs
+    // Can be overridden by RUST_LOG environment variable
+    let mut filter = env_logger::Builder::from_default_env();
+    
+    // If RUST_LOG is not set, use Info level for Claude modules and Warn for others
+    if std::env::var("RUST_LOG").is_err() {
+        filter.filter_level(log::LevelFilter::Warn); // Default for all modules
+        // Set Info level for Claude-related modules to capture all important logs
+        filter.filter_module("opcode::commands::claude", log::LevelFilter::Info);
+        filter.filter_module("opcode::commands::agents", log::LevelFilter::Info);
+        filter.filter_module("opcode::claude_binary", log::LevelFilter::Info);
+        filter.filter_module("opcode::process", log::LevelFilter::Info);
+    }
+    let filter = filter.build();
+    
+    let writer = env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Trace)
+        .target(env_logger::Target::Pipe(Box::new(dual_writer)))
+        .format(|buf, record| {
+            use std::io::Write;
+            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+            let module_path = record.module_path().unwrap_or("unknown");
+            let file_path = record.file().unwrap_or("unknown");
+            let line = record.line().map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
+            
+            // Detect Claude-related logs more comprehensively
+            let args_str = record.args().to_string();
+            let is_claude_related = module_path.contains("claude") 
+                || module_path.contains("Claude")
+                || args_str.contains("Claude")
+                || args_str.contains("claude")
+                || args_str.contains("CLAUDE")
+                || file_path.contains("claude");
+            
+            // Enhanced prefix for Claude-related logs
+            let prefix = if is_claude_related {
+                match record.level() {
+                    log::Level::Error => "🔴 [CLAUDE ERROR]",
+                    log::Level::Warn => "⚠️  [CLAUDE WARN]",
+                    log::Level::Info => "ℹ️  [CLAUDE INFO]",
+                    log::Level::Debug => "🔍 [CLAUDE DEBUG]",
+                    log::Level::Trace => "🔎 [CLAUDE TRACE]",
+                }
+            } else {
+                match record.level() {
+                    log::Level::Error => "❌ [ERROR]",
+                    log::Level::Warn => "⚠️  [WARN]",
+                    log::Level::Info => "ℹ️  [INFO]",
+                    log::Level::Debug => "🔍 [DEBUG]",
+                    log::Level::Trace => "🔎 [TRACE]",
+                }
+            };
+            
+            // Secrets never reach the log file, the crash report buffer or the log viewer
+            writeln!(
+                buf,
+                "[{}] {} [{}] {}:{} - {}",
+                timestamp,
+                prefix,
+                record.level(),
+                file_path,
+                line,
+                crate::log_scrubber::scrub(&args_str)
+            )
+        })
+        .build();
+
+    DEFAULT_MAX_LEVEL.store(filter.filter() as usize, Ordering::Relaxed);
+    let max_level = filter.filter();
+    if log::set_boxed_logger(Box::new(AppLogger { filter, writer })).is_err() {
+        eprintln!("Logger was already initialized");
+        return;
+    }
+    INSTALLED.store(true, Ordering::Relaxed);
+    log::set_max_level(max_level);
+
+    log::info!("==========================================");
+    log::info!("Logging initialized successfully");
+    log::info!("Log directory: {:?}", log_dir);
+    if let Ok(exe_path) = std::env::current_exe() {
+        log::info!("Executable: {:?}", exe_path);
+    }
+    log::info!("Log level: {} (set RUST_LOG environment variable to override)", 
+        std::env::var("RUST_LOG").unwrap_or_else(|_| "warn (info for claude modules)".to_string()));
+    log::info!("==========================================");
+}
+
+/// Date a log file was written on, from names like "opcode-20240101.log" or
+/// "opcode-20240101.2.log"
+pub fn log_file_date(path: &Path) -> Option<chrono::NaiveDate> {
+    let file_name = path.file_name()?.to_str()?;
+    if !file_name.ends_with(".log") {
+        return None;
+    }
+    let date_str = file_name.strip_prefix("opcode-")?.get(..8)?;
+    chrono::NaiveDate::parse_from_str(date_str, "%Y%m%d").ok()
+}
+
+/// Clean up old log files, keeping only the last 30 days
+fn cleanup_old_logs(log_dir: &Path) {
+    use std::fs;
+    
+    let entries = match fs::read_dir(log_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let cutoff_date = chrono::Local::now() - chrono::Duration::days(30);
+    let mut deleted_count = 0;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(file_date) = log_file_date(&path) {
+            if file_date < cutoff_date.date_naive() {
+                if let Err(e) = fs::remove_file(&path) {
+                    eprintln!("Failed to delete old log file {:?}: {}", path, e);
+                } else {
+                    deleted_count += 1;
+                }
+            }
+        }
+    }
+
+    if deleted_count > 0 {
+        eprintln!("Cleaned up {} old log file(s)", deleted_count);
+    }
+}
+
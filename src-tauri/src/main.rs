@@ -1,56 +1,254 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod atomic_file;
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod decoding;
+mod log_scrubber;
 mod logger;
 mod process;
 
 use checkpoint::state::CheckpointState;
+use commands::activity::{clear_activity, get_activity, mark_activity_seen};
+use commands::agent_retry::{
+    get_agent_retry_policy, get_agent_run_retry_chain, set_agent_retry_policy,
+};
 use commands::agents::{
     cleanup_finished_processes, create_agent, delete_agent, execute_agent, export_agent,
     export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
-    get_live_session_output, get_session_output, get_session_status, import_agent,
-    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
+    get_live_output, get_live_run_usage, get_live_session_output, get_session_output,
+    get_session_status, import_agent, import_agent_from_file, import_agent_from_github,
+    init_database,
+    kill_agent_session,
     list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
-    list_running_sessions, load_agent_session_history, set_claude_binary_path,
+    list_running_sessions, load_agent_session_history, rerun_agent_with_diagnostics,
+    set_claude_binary_path,
     stream_session_output, update_agent, AgentDb,
 };
+use commands::app_config::{export_app_config, import_app_config};
+use commands::app_lock::{
+    get_app_lock_status, init_app_lock, lock_app, set_app_lock_settings, unlock_app,
+};
+use commands::approval_mcp::{get_agent_approval, set_agent_approval, ApprovalServerState};
+use commands::artifacts::{
+    get_agent_artifact_globs, list_run_artifacts, open_artifact, set_agent_artifact_globs,
+};
+use commands::auth_status::get_auth_status;
+use commands::attachments::{clear_staged_attachments, stage_attachment, stage_clipboard_image};
+use commands::background::{
+    get_background_mode, get_reattach_state, keep_running_in_background, set_background_mode,
+    show_main_window,
+};
+use commands::cache_savings::get_cache_savings;
+use commands::cancellation::cancel_operation;
+use commands::error_stats::get_error_stats;
+use commands::batches::{
+    cancel_batch, execute_agents_parallel, get_batch_results, get_batch_status, list_batches,
+};
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
     clear_checkpoint_manager, continue_claude_code, create_checkpoint, create_project,
     execute_claude_code, find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff,
     get_checkpoint_settings, get_checkpoint_state_stats, get_claude_session_output,
     get_claude_settings, get_file_server_url, get_home_directory, get_hooks_config, get_project_prompt, get_project_sessions,
-    get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
+    get_recently_modified_files, get_session_timeline, get_system_prompt, interrupt_session,
+    list_checkpoints,
     list_directory_contents, list_project_files, list_projects, list_running_claude_sessions, load_session_history,
-    open_new_session, read_claude_md_file, read_text_file, restore_checkpoint, resume_claude_code,
+    open_new_session, read_claude_md_file, read_text_file, restore_checkpoint, resume_claude_code, run_slash_command,
     save_claude_md_file, save_claude_settings, save_system_prompt, search_files,
     send_claude_message, start_file_server, track_checkpoint_message, track_session_messages, update_checkpoint_settings,
     update_hooks_config, validate_hook_command, ClaudeProcessState, FileServerState,
 };
+use commands::config_backups::{
+    create_config_backup, get_backup_settings, list_config_backups, restore_backup,
+    set_backup_settings, spawn_backup_scheduler,
+};
+use commands::config_merge::{read_config_for_merge, save_config_merged};
+use commands::config_snapshots::{list_config_snapshots, restore_config_snapshot};
+use commands::config_sync::{
+    abort_config_sync, get_config_sync_settings, get_config_sync_status,
+    resolve_config_sync_conflict, set_config_sync_settings, sync_config_now,
+};
+use commands::containers::{
+    build_devcontainer_image, cleanup_run_containers, clear_container_caches,
+    get_project_container, inspect_devcontainer, list_container_caches, list_run_containers,
+    pull_container_image, set_project_container, spawn_container_janitor,
+};
+use commands::crash::{
+    delete_crash_report, get_crash_report, list_crash_reports, upload_crash_report,
+};
+use commands::db_maintenance::{
+    db_create_backup, db_integrity_check, db_list_backups, db_restore_backup, db_vacuum,
+};
+use commands::deep_link::{
+    confirm_deep_link, dismiss_deep_link, get_pending_deep_links, init_deep_links, DeepLinkState,
+};
+use commands::digests::{
+    generate_daily_digest, get_daily_digest, get_digest_settings, list_daily_digests,
+    set_digest_settings, spawn_digest_scheduler,
+};
+use commands::encryption::{
+    get_storage_encryption_status, migrate_storage_encryption, seal_on_exit,
+    spawn_database_sealer,
+};
+use commands::event_broker::{
+    fetch_event_payload, list_event_channels, subscribe_events, unsubscribe_events, EventBroker,
+};
+use commands::experiments::{
+    cancel_experiment, create_experiment, delete_experiment, get_experiment,
+    get_experiment_results, list_experiments, run_experiment,
+};
+use commands::file_changes::get_run_file_changes;
+use commands::file_hotspots::get_file_hotspots;
+use commands::logs::{get_app_logs, set_log_level};
 use commands::mcp::{
     mcp_add, mcp_add_json, mcp_get, mcp_get_config_paths,
     mcp_get_server_status, mcp_list, mcp_read_project_config, mcp_remove,
     mcp_reset_project_choices, mcp_save_project_config, mcp_serve, mcp_test_connection, mcp_update,
 };
+use commands::mcp_capabilities::{mcp_get_capability_reports, mcp_probe_server};
+use commands::mcp_config_watcher::{
+    start_mcp_config_watcher, stop_mcp_config_watcher, McpConfigWatcherState,
+};
+use commands::mcp_guardrails::{
+    get_mcp_server_limits, list_mcp_limit_violations, set_mcp_server_limits,
+    spawn_mcp_guardrails,
+};
+use commands::mcp_import::{mcp_import_preview, mcp_import_servers};
+use commands::mcp_lint::mcp_lint_config;
+use commands::mcp_prerequisites::mcp_check_prerequisites;
+use commands::mcp_reachability::{
+    mcp_check_reachability, mcp_refresh_reachability, spawn_reachability_monitor,
+};
+use commands::mcp_stacks::{
+    apply_stack, create_mcp_stack, delete_mcp_stack, get_mcp_stack, list_mcp_stacks,
+    list_stack_applications, remove_stack, update_mcp_stack,
+};
+use commands::messages::{get_locale, get_message_catalog, set_locale};
 
-use commands::proxy::{apply_proxy_settings, get_proxy_settings, save_proxy_settings};
+use commands::model_policy::{get_agent_model_policy, set_agent_model_policy};
+use commands::notifications::{
+    get_notification_settings, save_notification_settings, send_test_notification,
+};
+use commands::offline::{
+    check_connectivity, discard_queued_mcp_operation, fetch_github_agents_cached,
+    get_offline_status, list_queued_mcp_operations, mcp_list_cached, replay_queued_mcp_operations,
+    set_offline_mode, spawn_connectivity_monitor,
+};
+use commands::output_bench::bench_output_pipeline;
+use commands::permission_relay::{
+    list_pending_permissions, respond_permission, PermissionRelayState,
+};
+use commands::pricing::{
+    estimate_cost, get_price_table, get_pricing_settings, list_price_overrides,
+    list_price_tables, remove_price_override, set_price_override, set_pricing_settings,
+    update_price_table,
+};
+use commands::print_tasks::{get_print_task_backend, run_print_task, set_print_task_backend};
+use commands::project_init::{init_project, list_project_mcp_templates};
+use commands::project_stats::get_project_stats;
+use commands::prompt_templates::{
+    create_prompt_template, delete_prompt_template, get_prompt_template, list_prompt_templates,
+    render_prompt, update_prompt_template,
+};
+use commands::provider_profiles::{
+    create_provider_profile, delete_provider_profile, get_project_provider_profile,
+    list_provider_profiles, set_project_provider_profile, test_provider_profile,
+    update_provider_profile,
+};
+use commands::proxy::{get_proxy_settings, save_proxy_settings};
+use commands::recent_projects::{
+    add_recent_project, get_recent_projects, remove_recent_project, set_recent_project_pinned,
+};
+use commands::redaction::{
+    copy_session_to_clipboard, export_session, get_redaction_config, redact_text,
+    set_redaction_config,
+};
+use commands::remote_hosts::{
+    delete_remote_host, detect_remote_claude, execute_remote_terminal_command,
+    get_project_remote_host, list_remote_hosts, save_remote_host, set_project_remote_host,
+};
+use commands::retention::{
+    get_last_retention_report, get_retention_settings, get_storage_usage, prune_storage,
+    set_retention_settings, spawn_retention_pruner,
+};
+use commands::quick_run::{
+    get_quick_run_shortcut, init_quick_run_shortcut, quick_run, quick_run_search,
+    set_quick_run_shortcut,
+};
+use commands::replay::replay_session;
+use commands::rollback::abort_and_rollback;
+use commands::run_bundle::{export_run_bundle, import_run_bundle, inspect_run_bundle};
+use commands::run_queue::{
+    cancel_queued_run, enqueue_agent_run, get_run_queue_settings, list_run_queue,
+    reprioritize_queued_run, reprioritize_running_run, set_run_queue_settings,
+    spawn_queue_dispatcher, RunQueueState,
+};
+use commands::run_watchdog::{get_agent_watchdog_policy, set_agent_watchdog_policy};
+use commands::sandbox::{
+    create_sandbox_profile, delete_sandbox_profile, get_agent_sandbox_profile,
+    get_run_sandbox_violations, list_sandbox_profiles, set_agent_sandbox_profile,
+    update_sandbox_profile,
+};
+use commands::session_diff::diff_sessions;
+use commands::session_index::{search_sessions, spawn_session_indexer};
+use commands::session_insights::{
+    add_session_insight, delete_session_insight, export_insights, list_session_insights,
+    preview_insight_export,
+};
+use commands::session_merge::{analyze_duplicate_sessions, merge_sessions};
+use commands::session_watcher::{
+    get_session_watcher_status, start_session_watcher, stop_session_watcher, SessionWatcherState,
+};
+use commands::session_window::get_session_window;
+use commands::settings::{get_all_settings, get_setting, set_setting};
 use commands::skills::{
     skill_create, skill_create_file, skill_delete, skill_delete_file, skill_list_all,
     skill_list_by_type, skill_read, skill_read_file, skill_update, skill_validate,
 };
+use commands::ssh_tunnels::{
+    create_tunnel, delete_tunnel, list_tunnels, spawn_tunnels, start_tunnel, stop_all_tunnels,
+    stop_tunnel,
+};
+use commands::team_policy::{get_team_policy, validate_team_policy};
+use commands::telemetry::{
+    clear_telemetry, export_telemetry, flush_on_exit, get_telemetry_summary,
+    set_telemetry_enabled, spawn_telemetry_flusher,
+};
 use commands::terminal::{execute_terminal_command, execute_terminal_command_stream};
+use commands::tls_settings::{
+    get_tls_settings, remove_mcp_client_certificate, set_mcp_client_certificate,
+    set_tls_settings,
+};
+use commands::tokens::{estimate_tokens, truncate_to_budget};
+use commands::tool_usage::get_tool_usage_stats;
+use commands::tray::{get_tray_favorites, init_tray, set_tray_favorites};
 use commands::storage::{
     storage_delete_row, storage_execute_sql, storage_insert_row, storage_list_tables,
     storage_read_table, storage_reset_database, storage_update_row,
 };
-use commands::version::{get_app_version, get_version_info};
+use commands::webhooks::{
+    create_webhook, delete_webhook, list_webhook_deliveries, list_webhooks, test_webhook,
+    update_webhook,
+};
+use commands::version::{
+    check_for_updates, copy_diagnostic_info, get_app_version, get_version_info,
+};
 use commands::usage::{
     get_session_stats, get_usage_by_date_range, get_usage_details, get_usage_stats,
 };
+use commands::usage_backfill::{cancel_usage_backfill, get_usage_backfill_status, usage_backfill};
+use commands::workspaces::{
+    create_workspace, delete_workspace, get_workspace, get_workspace_overview, list_workspaces,
+    update_workspace,
+};
+use commands::worktrees::{
+    get_agent_worktree_policy, get_run_worktree, list_run_worktrees, remove_run_worktree,
+    set_agent_worktree_policy,
+};
 use process::ProcessRegistryState;
 use std::sync::Mutex;
 use tauri::Manager;
@@ -58,75 +256,76 @@ use tauri::Manager;
 #[cfg(target_os = "macos")]
 use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
 
+/// Run the helper Claude starts during agent runs when the binary was started as one, and
+/// return its exit code. Installed builds only ship this binary, so the helpers live here.
+fn run_helper_command() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some(commands::approval_mcp::APPROVAL_MCP_COMMAND) => {
+            commands::approval_mcp::serve_from_args(&args[1..])
+        }
+        Some(commands::replay::REPLAY_STUB_COMMAND) => {
+            commands::replay::stub_hook_from_args(&args[1..])
+        }
+        _ => return None,
+    };
+    Some(match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    })
+}
+
 fn main() {
+    // Helpers talk over stdio and must never start the GUI or its logger
+    if let Some(code) = run_helper_command() {
+        std::process::exit(code);
+    }
+
     // Initialize logger to file
     logger::init_logger();
 
+    // Write a crash report for any panic
+    commands::crash::install_panic_hook();
+
     tauri::Builder::default()
+        // Must come first: later launches (e.g. from an opcode:// link) hand their
+        // arguments to this instance and exit
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            commands::deep_link::handle_second_instance(app, argv);
+        }))
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .setup(|app| {
             // Initialize agents database
-            let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
+            let conn = init_database(&app.handle()).map_err(|e| {
+                log::error!("Failed to initialize agents database: {}", e);
+                e
+            })?;
 
-            // Load and apply proxy settings from the database
-            {
-                let db = AgentDb(Mutex::new(conn));
-                let proxy_settings = match db.0.lock() {
-                    Ok(conn) => {
-                        // Directly query proxy settings from the database
-                        let mut settings = commands::proxy::ProxySettings::default();
-
-                        let keys = vec![
-                            ("proxy_enabled", "enabled"),
-                            ("proxy_http", "http_proxy"),
-                            ("proxy_https", "https_proxy"),
-                            ("proxy_no", "no_proxy"),
-                            ("proxy_all", "all_proxy"),
-                        ];
-
-                        for (db_key, field) in keys {
-                            if let Ok(value) = conn.query_row(
-                                "SELECT value FROM app_settings WHERE key = ?1",
-                                rusqlite::params![db_key],
-                                |row| row.get::<_, String>(0),
-                            ) {
-                                match field {
-                                    "enabled" => settings.enabled = value == "true",
-                                    "http_proxy" => {
-                                        settings.http_proxy = Some(value).filter(|s| !s.is_empty())
-                                    }
-                                    "https_proxy" => {
-                                        settings.https_proxy = Some(value).filter(|s| !s.is_empty())
-                                    }
-                                    "no_proxy" => {
-                                        settings.no_proxy = Some(value).filter(|s| !s.is_empty())
-                                    }
-                                    "all_proxy" => {
-                                        settings.all_proxy = Some(value).filter(|s| !s.is_empty())
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-
-                        log::info!("Loaded proxy settings: enabled={}", settings.enabled);
-                        settings
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to lock database for proxy settings: {}", e);
-                        commands::proxy::ProxySettings::default()
-                    }
-                };
-
-                // Apply the proxy settings
-                apply_proxy_settings(&proxy_settings);
-            }
+            // Apply settings cached outside the database (proxy env vars, terminal whitelist)
+            commands::settings::apply_settings(&conn);
+            commands::messages::apply_saved_locale(&conn);
 
-            // Re-open the connection for the app to manage
-            let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
             app.manage(AgentDb(Mutex::new(conn)));
 
+            // Lock on launch if configured, and after the idle time
+            init_app_lock(&app.handle());
+
+            // Keep the sealed copy of an encrypted database current
+            spawn_database_sealer(app.handle().clone());
+
+            // Store the opt-in usage counters
+            spawn_telemetry_flusher(app.handle().clone());
+
+            // Catch the transcript index up with sessions written while the app was closed
+            spawn_session_indexer();
+
             // Initialize checkpoint state
             let checkpoint_state = CheckpointState::new();
 
@@ -148,15 +347,69 @@ fn main() {
 
             app.manage(checkpoint_state);
 
-            // Initialize process registry
-            app.manage(ProcessRegistryState::default());
+            // Initialize process registry and the live usage ticker
+            let process_registry = ProcessRegistryState::default();
+            process::usage::spawn_usage_ticker(app.handle().clone(), process_registry.0.clone());
+            commands::crash::set_crash_registry(process_registry.0.clone());
+            app.manage(process_registry);
+
+            // Drain the prioritized run queue in the background
+            app.manage(RunQueueState::default());
+            spawn_queue_dispatcher(app.handle().clone());
+
+            // Remove containers left behind by killed or crashed containerized runs
+            spawn_container_janitor(app.handle().clone());
+
+            // Prune old sessions, run logs, artifacts and checkpoints per the retention policies
+            spawn_retention_pruner(app.handle().clone());
+
+            // Write the daily digest of agent activity once its hour has passed
+            spawn_digest_scheduler(app.handle().clone());
+
+            // Back up the database and Claude config to the chosen directory on schedule
+            spawn_backup_scheduler(app.handle().clone());
+
+            // Watch connectivity for offline mode and replay queued MCP operations on recovery
+            spawn_connectivity_monitor(app.handle().clone());
+
+            // Kill stdio MCP servers of running sessions that go over their resource limits
+            spawn_mcp_guardrails(app.handle().clone());
+
+            // Re-probe SSE/HTTP MCP servers so connection failures come with a cause
+            spawn_reachability_monitor(app.handle().clone());
+
+            // Open the SSH tunnels that MCP servers on remote hosts are reached through
+            spawn_tunnels(app.handle().clone());
+
+            // Route event streams to the windows subscribed to them
+            app.manage(EventBroker::default());
 
             // Initialize Claude process state
             app.manage(ClaudeProcessState::default());
 
+            // Permission prompts of sessions started with interactive permissions
+            app.manage(PermissionRelayState::default());
+            app.manage(ApprovalServerState::default());
+
             // Initialize file server state
             app.manage(FileServerState::default());
 
+            // Initialize external session watcher state (started on demand)
+            app.manage(SessionWatcherState::default());
+            app.manage(McpConfigWatcherState::default());
+
+            // Handle opcode:// links, including one the app was launched with
+            app.manage(DeepLinkState::default());
+            init_deep_links(&app.handle());
+
+            // Tray icon with active runs and quick actions
+            if let Err(e) = init_tray(&app.handle()) {
+                log::warn!("Failed to create tray icon: {}", e);
+            }
+
+            // Open the quick-run palette from anywhere, even with the window in the tray
+            init_quick_run_shortcut(&app.handle());
+
             // Apply window vibrancy with rounded corners on macOS
             #[cfg(target_os = "macos")]
             {
@@ -193,7 +446,9 @@ fn main() {
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        // Commands are rejected while the app lock is engaged, and counted when usage
+        // counters are on
+        .invoke_handler(commands::telemetry::counted(commands::app_lock::guard(tauri::generate_handler![
             // Claude & Project Management
             list_projects,
             create_project,
@@ -204,6 +459,7 @@ fn main() {
             get_system_prompt,
             get_project_prompt,
             check_claude_version,
+            get_auth_status,
             save_system_prompt,
             save_claude_settings,
             find_claude_md_files,
@@ -215,6 +471,14 @@ fn main() {
             continue_claude_code,
             resume_claude_code,
             cancel_claude_execution,
+            interrupt_session,
+            respond_permission,
+            list_pending_permissions,
+            get_agent_approval,
+            set_agent_approval,
+            stage_attachment,
+            stage_clipboard_image,
+            clear_staged_attachments,
             list_running_claude_sessions,
             get_claude_session_output,
             list_directory_contents,
@@ -249,6 +513,7 @@ fn main() {
             delete_agent,
             get_agent,
             execute_agent,
+            rerun_agent_with_diagnostics,
             list_agent_runs,
             get_agent_run,
             list_agent_runs_with_metrics,
@@ -259,6 +524,8 @@ fn main() {
             cleanup_finished_processes,
             get_session_output,
             get_live_session_output,
+            get_live_output,
+            get_live_run_usage,
             stream_session_output,
             load_agent_session_history,
             get_claude_binary_path,
@@ -271,11 +538,44 @@ fn main() {
             fetch_github_agents,
             fetch_github_agent_content,
             import_agent_from_github,
+            get_agent_retry_policy,
+            set_agent_retry_policy,
+            get_agent_run_retry_chain,
+            get_agent_watchdog_policy,
+            set_agent_watchdog_policy,
+            get_run_file_changes,
+            list_sandbox_profiles,
+            create_sandbox_profile,
+            update_sandbox_profile,
+            delete_sandbox_profile,
+            get_agent_sandbox_profile,
+            set_agent_sandbox_profile,
+            get_run_sandbox_violations,
+            abort_and_rollback,
+            get_agent_model_policy,
+            set_agent_model_policy,
             // Usage & Analytics
             get_usage_stats,
             get_usage_by_date_range,
             get_usage_details,
             get_session_stats,
+            get_cache_savings,
+            get_error_stats,
+            get_usage_backfill_status,
+            usage_backfill,
+            cancel_usage_backfill,
+            // Model Pricing
+            get_price_table,
+            list_price_tables,
+            update_price_table,
+            get_pricing_settings,
+            set_pricing_settings,
+            list_price_overrides,
+            set_price_override,
+            remove_price_override,
+            estimate_cost,
+            // Cancellation
+            cancel_operation,
             // MCP (Model Context Protocol)
             mcp_add,
             mcp_list,
@@ -290,6 +590,35 @@ fn main() {
             mcp_get_config_paths,
             mcp_read_project_config,
             mcp_save_project_config,
+            mcp_probe_server,
+            mcp_get_capability_reports,
+            start_mcp_config_watcher,
+            stop_mcp_config_watcher,
+            mcp_import_preview,
+            mcp_import_servers,
+            mcp_check_prerequisites,
+            mcp_lint_config,
+            get_mcp_server_limits,
+            set_mcp_server_limits,
+            list_mcp_limit_violations,
+            mcp_check_reachability,
+            mcp_refresh_reachability,
+            // Team Policy
+            get_team_policy,
+            validate_team_policy,
+            // MCP Stacks
+            create_mcp_stack,
+            list_mcp_stacks,
+            get_mcp_stack,
+            update_mcp_stack,
+            delete_mcp_stack,
+            list_stack_applications,
+            apply_stack,
+            remove_stack,
+            // Backend messages
+            get_locale,
+            set_locale,
+            get_message_catalog,
             // Storage Management
             storage_list_tables,
             storage_read_table,
@@ -298,6 +627,12 @@ fn main() {
             storage_insert_row,
             storage_execute_sql,
             storage_reset_database,
+            // Database Maintenance
+            db_vacuum,
+            db_integrity_check,
+            db_list_backups,
+            db_create_backup,
+            db_restore_backup,
             // Terminal Commands
             execute_terminal_command,
             execute_terminal_command_stream,
@@ -306,9 +641,185 @@ fn main() {
             commands::slash_commands::slash_command_get,
             commands::slash_commands::slash_command_save,
             commands::slash_commands::slash_command_delete,
+            run_slash_command,
+            // Project Onboarding
+            init_project,
+            list_project_mcp_templates,
+            // Token Estimation
+            estimate_tokens,
+            truncate_to_budget,
+            // Prompt Templates
+            list_prompt_templates,
+            get_prompt_template,
+            create_prompt_template,
+            update_prompt_template,
+            delete_prompt_template,
+            render_prompt,
+            // Session Insights
+            add_session_insight,
+            list_session_insights,
+            delete_session_insight,
+            preview_insight_export,
+            export_insights,
+            // Run Artifacts
+            get_agent_artifact_globs,
+            set_agent_artifact_globs,
+            list_run_artifacts,
+            open_artifact,
+            // Worktrees
+            get_agent_worktree_policy,
+            set_agent_worktree_policy,
+            list_run_worktrees,
+            get_run_worktree,
+            remove_run_worktree,
+            // Parallel Batches
+            execute_agents_parallel,
+            get_batch_status,
+            list_batches,
+            cancel_batch,
+            get_batch_results,
+            // Run Queue
+            enqueue_agent_run,
+            list_run_queue,
+            cancel_queued_run,
+            reprioritize_queued_run,
+            reprioritize_running_run,
+            get_run_queue_settings,
+            set_run_queue_settings,
+            // Storage Retention
+            get_storage_usage,
+            get_retention_settings,
+            set_retention_settings,
+            prune_storage,
+            get_last_retention_report,
+            // Daily Digests
+            generate_daily_digest,
+            get_daily_digest,
+            list_daily_digests,
+            get_digest_settings,
+            set_digest_settings,
+            // Experiments
+            create_experiment,
+            list_experiments,
+            get_experiment,
+            delete_experiment,
+            run_experiment,
+            cancel_experiment,
+            get_experiment_results,
+            // Activity Feed
+            get_activity,
+            mark_activity_seen,
+            clear_activity,
+            // Session Watching
+            start_session_watcher,
+            stop_session_watcher,
+            get_session_watcher_status,
+            // Session Merging
+            analyze_duplicate_sessions,
+            merge_sessions,
+            // Session Diffing
+            diff_sessions,
+            // Conversation Replay
+            replay_session,
+            // Run Bundles
+            export_run_bundle,
+            inspect_run_bundle,
+            import_run_bundle,
+            // Transcript Export & Redaction
+            export_session,
+            copy_session_to_clipboard,
+            redact_text,
+            get_redaction_config,
+            set_redaction_config,
+            // Recent Projects
+            get_recent_projects,
+            add_recent_project,
+            set_recent_project_pinned,
+            remove_recent_project,
+            // Workspaces
+            list_workspaces,
+            get_workspace,
+            create_workspace,
+            update_workspace,
+            delete_workspace,
+            get_workspace_overview,
             // Proxy Settings
             get_proxy_settings,
             save_proxy_settings,
+            // TLS Settings
+            get_tls_settings,
+            set_tls_settings,
+            set_mcp_client_certificate,
+            remove_mcp_client_certificate,
+            // Notifications
+            get_notification_settings,
+            save_notification_settings,
+            send_test_notification,
+            // Offline Mode
+            get_offline_status,
+            set_offline_mode,
+            check_connectivity,
+            mcp_list_cached,
+            fetch_github_agents_cached,
+            list_queued_mcp_operations,
+            discard_queued_mcp_operation,
+            replay_queued_mcp_operations,
+            // Webhooks
+            list_webhooks,
+            create_webhook,
+            update_webhook,
+            delete_webhook,
+            list_webhook_deliveries,
+            test_webhook,
+            // Tray & Background Mode
+            get_tray_favorites,
+            set_tray_favorites,
+            // Quick Run
+            quick_run_search,
+            quick_run,
+            get_quick_run_shortcut,
+            set_quick_run_shortcut,
+            get_background_mode,
+            set_background_mode,
+            get_reattach_state,
+            // Deep Links
+            get_pending_deep_links,
+            confirm_deep_link,
+            dismiss_deep_link,
+            // Config Snapshots
+            list_config_snapshots,
+            restore_config_snapshot,
+            // Config Merge
+            read_config_for_merge,
+            save_config_merged,
+            // Config Backups
+            get_backup_settings,
+            set_backup_settings,
+            create_config_backup,
+            list_config_backups,
+            restore_backup,
+            // Config Sync
+            get_config_sync_settings,
+            set_config_sync_settings,
+            sync_config_now,
+            get_config_sync_status,
+            resolve_config_sync_conflict,
+            abort_config_sync,
+            // Crash Reports
+            list_crash_reports,
+            get_crash_report,
+            delete_crash_report,
+            upload_crash_report,
+            // Logs
+            get_app_logs,
+            set_log_level,
+            // Settings
+            get_setting,
+            get_all_settings,
+            set_setting,
+            // Config Export & Import
+            export_app_config,
+            import_app_config,
             // Skills Management
             skill_list_all,
             skill_list_by_type,
@@ -323,7 +834,114 @@ fn main() {
             // Version Management
             get_app_version,
             get_version_info,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            check_for_updates,
+            copy_diagnostic_info,
+            // Encryption at Rest
+            get_storage_encryption_status,
+            migrate_storage_encryption,
+            // Event Channels
+            list_event_channels,
+            subscribe_events,
+            unsubscribe_events,
+            fetch_event_payload,
+            // App Lock
+            get_app_lock_status,
+            set_app_lock_settings,
+            lock_app,
+            unlock_app,
+            // Usage Counters
+            get_telemetry_summary,
+            set_telemetry_enabled,
+            export_telemetry,
+            clear_telemetry,
+            // Benchmarks (developer console only)
+            bench_output_pipeline,
+            // Transcript Index
+            get_session_window,
+            search_sessions,
+            get_project_stats,
+            get_tool_usage_stats,
+            get_file_hotspots,
+            // Provider Profiles
+            list_provider_profiles,
+            create_provider_profile,
+            update_provider_profile,
+            delete_provider_profile,
+            get_project_provider_profile,
+            set_project_provider_profile,
+            test_provider_profile,
+            // Remote Hosts
+            list_remote_hosts,
+            save_remote_host,
+            delete_remote_host,
+            detect_remote_claude,
+            get_project_remote_host,
+            set_project_remote_host,
+            execute_remote_terminal_command,
+            // Containers
+            get_project_container,
+            set_project_container,
+            inspect_devcontainer,
+            pull_container_image,
+            build_devcontainer_image,
+            list_run_containers,
+            cleanup_run_containers,
+            list_container_caches,
+            clear_container_caches,
+            // SSH Tunnels
+            create_tunnel,
+            list_tunnels,
+            start_tunnel,
+            stop_tunnel,
+            delete_tunnel,
+            // Print Tasks
+            run_print_task,
+            get_print_task_backend,
+            set_print_task_backend,
+        ])))
+        .build(tauri::generate_context!())
+        .unwrap_or_else(|e| {
+            commands::crash::record_error("tauri", &e.to_string());
+            log::error!("error while building tauri application: {}", e);
+            std::process::exit(1);
+        })
+        .run(|app, event| match event {
+            // Closing the last window asks to exit without a code; stay in the tray instead
+            tauri::RunEvent::ExitRequested {
+                api, code: None, ..
+            } => {
+                if keep_running_in_background(app) {
+                    api.prevent_exit();
+                }
+            }
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Opened { urls } => {
+                for url in urls {
+                    commands::deep_link::handle_deep_link(app, url.as_str());
+                }
+            }
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Reopen {
+                has_visible_windows: false,
+                ..
+            } => show_main_window(app),
+            // Store the last usage counts, then seal an encrypted database and remove its
+            // working copy
+            tauri::RunEvent::Exit => {
+                flush_on_exit(app);
+                seal_on_exit(app);
+                stop_all_tunnels();
+            }
+            // Drop the event subscriptions of closed windows
+            tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Destroyed,
+                ..
+            } => {
+                if let Some(broker) = app.try_state::<EventBroker>() {
+                    broker.remove_window(&label);
+                }
+            }
+            _ => {}
+        });
 }
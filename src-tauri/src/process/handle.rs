@@ -0,0 +1,268 @@
+//! Handles for registered processes. Processes spawned by the app own a `tokio` [`Child`];
+//! sidecar and adopted processes (spawned by the shell plugin, or owned by another part of
+//! the app) are opened by PID instead, so kill, wait and status checks work the same way for
+//! both.
+//!
+//! Claude processes are started in a process group of their own, so interrupting or killing
+//! one reaches the tools and shells it spawned as well.
+
+use tokio::process::Child;
+
+/// A process known only by its PID. It is never reaped here, so whoever actually spawned it
+/// keeps its exit status; the exit code of an adopted process is therefore always unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PidHandle {
+    pid: u32,
+    /// The process leads its own process group, so signals go to the whole group
+    leads_group: bool,
+}
+
+impl PidHandle {
+    /// Open the process with `pid`, failing if no such process is running
+    pub fn open(pid: u32) -> std::io::Result<Self> {
+        let mut handle = Self {
+            pid,
+            leads_group: false,
+        };
+        if pid == 0 || !handle.is_alive() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No running process with PID {}", pid),
+            ));
+        }
+        handle.leads_group = leads_group(pid);
+        Ok(handle)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn leads_group(&self) -> bool {
+        self.leads_group
+    }
+
+    #[cfg(unix)]
+    pub fn is_alive(&self) -> bool {
+        let Ok(pid) = libc::pid_t::try_from(self.pid) else {
+            return false;
+        };
+        // Signal 0 only checks that the process exists and can be signalled
+        let exists = unsafe { libc::kill(pid, 0) } == 0
+            || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+        exists && !self.is_zombie()
+    }
+
+    /// An exited process its parent hasn't reaped yet still answers signal 0
+    #[cfg(unix)]
+    fn is_zombie(&self) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", self.pid))
+            .ok()
+            .and_then(|stat| {
+                // The state follows the parenthesised command name, which may contain spaces
+                let (_, rest) = stat.rsplit_once(')')?;
+                rest.trim_start().chars().next()
+            })
+            .is_some_and(|state| state == 'Z' || state == 'X')
+    }
+
+    #[cfg(windows)]
+    pub fn is_alive(&self) -> bool {
+        let output = std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", self.pid), "/FO", "CSV", "/NH"])
+            .output();
+        match output {
+            Ok(output) => crate::decoding::decode_command_output(&output.stdout)
+                .contains(&format!("\"{}\"", self.pid)),
+            Err(_) => false,
+        }
+    }
+
+    /// Send `signal` to the process, or to its whole group when `group` is set and it leads one
+    #[cfg(unix)]
+    fn signal(&self, signal: libc::c_int, group: bool) -> std::io::Result<()> {
+        let pid = libc::pid_t::try_from(self.pid)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let target = if group && self.leads_group { -pid } else { pid };
+        if unsafe { libc::kill(target, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    /// Ask the process to exit: SIGTERM on Unix, a forced `taskkill` on Windows
+    #[cfg(unix)]
+    pub fn start_kill(&self) -> std::io::Result<()> {
+        self.signal(libc::SIGTERM, false)
+    }
+
+    #[cfg(windows)]
+    pub fn start_kill(&self) -> std::io::Result<()> {
+        taskkill(self.pid, &["/F"])
+    }
+
+    /// Interrupt the process the way Ctrl+C in a terminal would: SIGINT to its process group,
+    /// which the CLI handles by abandoning the current turn and exiting cleanly. Print-mode
+    /// sessions read no stdin, so there is no ESC to send. Windows has no SIGINT for windowless
+    /// processes; `taskkill` without `/F` asks the tree to close instead.
+    #[cfg(unix)]
+    pub fn interrupt(&self) -> std::io::Result<()> {
+        self.signal(libc::SIGINT, true)
+    }
+
+    #[cfg(windows)]
+    pub fn interrupt(&self) -> std::io::Result<()> {
+        taskkill(self.pid, &["/T"])
+    }
+
+    /// Kill the process and everything in its process group immediately
+    #[cfg(unix)]
+    pub fn kill_group(&self) -> std::io::Result<()> {
+        self.signal(libc::SIGKILL, true)
+    }
+
+    #[cfg(windows)]
+    pub fn kill_group(&self) -> std::io::Result<()> {
+        taskkill(self.pid, &["/F", "/T"])
+    }
+}
+
+#[cfg(unix)]
+fn leads_group(pid: u32) -> bool {
+    libc::pid_t::try_from(pid).is_ok_and(|pid| unsafe { libc::getpgid(pid) } == pid)
+}
+
+#[cfg(windows)]
+fn leads_group(_pid: u32) -> bool {
+    // `taskkill /T` walks the tree instead
+    false
+}
+
+#[cfg(windows)]
+fn taskkill(pid: u32, flags: &[&str]) -> std::io::Result<()> {
+    let output = std::process::Command::new("taskkill")
+        .args(flags)
+        .args(["/PID", &pid.to_string()])
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(
+            crate::decoding::decode_command_output(&output.stderr),
+        ))
+    }
+}
+
+/// The handle a registered process is controlled through
+#[derive(Debug)]
+pub enum ManagedChild {
+    /// Spawned by the app, which owns the child and can collect its exit code
+    Spawned(Child),
+    /// A sidecar or adopted process, opened by PID
+    Adopted(PidHandle),
+}
+
+impl ManagedChild {
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            ManagedChild::Spawned(child) => child.id(),
+            ManagedChild::Adopted(handle) => Some(handle.pid()),
+        }
+    }
+
+    pub fn start_kill(&mut self) -> std::io::Result<()> {
+        match self {
+            ManagedChild::Spawned(child) => child.start_kill(),
+            ManagedChild::Adopted(handle) => handle.start_kill(),
+        }
+    }
+
+    /// PID handle for the process, for signals `tokio::process::Child` doesn't offer
+    fn pid_handle(&self) -> std::io::Result<PidHandle> {
+        match self {
+            ManagedChild::Spawned(child) => match child.id() {
+                Some(pid) => PidHandle::open(pid),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "Process has already exited",
+                )),
+            },
+            ManagedChild::Adopted(handle) => Ok(*handle),
+        }
+    }
+
+    pub fn interrupt(&self) -> std::io::Result<()> {
+        self.pid_handle()?.interrupt()
+    }
+
+    pub fn kill_group(&self) -> std::io::Result<()> {
+        self.pid_handle()?.kill_group()
+    }
+
+    /// `Ok(Some(code))` once the process has exited, where `code` is `None` if it was killed
+    /// by a signal or is an adopted process; `Ok(None)` while it is still running
+    pub fn try_wait(&mut self) -> std::io::Result<Option<Option<i32>>> {
+        match self {
+            ManagedChild::Spawned(child) => Ok(child.try_wait()?.map(|status| status.code())),
+            ManagedChild::Adopted(handle) => Ok((!handle.is_alive()).then_some(None)),
+        }
+    }
+}
+
+impl From<Child> for ManagedChild {
+    fn from(child: Child) -> Self {
+        ManagedChild::Spawned(child)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_handle_tracks_and_kills_adopted_process() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let mut adopted = ManagedChild::Adopted(PidHandle::open(child.id()).unwrap());
+        assert_eq!(adopted.id(), Some(child.id()));
+        assert_eq!(adopted.try_wait().unwrap(), None);
+
+        adopted.start_kill().unwrap();
+        // The spawner still reaps the process and keeps its exit status
+        let status = child.wait().unwrap();
+        assert_eq!(status.code(), None);
+        assert_eq!(adopted.try_wait().unwrap(), Some(None));
+    }
+
+    #[test]
+    fn test_interrupt_and_kill_reach_process_group() {
+        use std::os::unix::process::CommandExt;
+
+        // A shell leading its own group, with a background child in the same group
+        let mut leader = std::process::Command::new("sh")
+            .args(["-c", "trap 'exit 7' INT; sleep 30 & wait"])
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let handle = PidHandle::open(leader.id()).unwrap();
+        assert!(handle.leads_group());
+
+        handle.interrupt().unwrap();
+        assert_eq!(leader.wait().unwrap().code(), Some(7));
+        // The background sleep ignores SIGINT, but the group kill still reaches it
+        handle.kill_group().unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_missing_process() {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(PidHandle::open(pid).is_err());
+        assert!(PidHandle::open(0).is_err());
+    }
+}
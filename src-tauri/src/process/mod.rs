@@ -1,3 +1,5 @@
+pub mod handle;
 pub mod registry;
+pub mod usage;
 
 pub use registry::*;
@@ -6,6 +6,9 @@ use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 use tokio::process::Child;
 
+use super::handle::{ManagedChild, PidHandle};
+use super::usage::{RunUsage, RunUsageTracker};
+
 /// Type of process being tracked
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProcessType {
@@ -25,12 +28,66 @@ pub struct ProcessInfo {
     pub model: String,
 }
 
+/// Compact view of a running process for menus and status displays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub run_id: i64,
+    pub process_type: ProcessType,
+    /// Agent name, or the project directory name for interactive sessions
+    pub label: String,
+    pub task: String,
+    pub started_at: DateTime<Utc>,
+    pub cost_usd: f64,
+}
+
+/// Which pipe a line of process output came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// How to stop a running process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptMode {
+    /// SIGINT to the process group, letting the process wind down on its own
+    Graceful,
+    /// SIGKILL to the process group, then clean up the registry entry
+    Hard,
+}
+
+/// A line of process output tagged with its stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamLine {
+    pub stream: OutputStream,
+    pub line: String,
+    /// The line holds extended-thinking blocks rather than the answer
+    #[serde(default)]
+    pub thinking: bool,
+}
+
+impl StreamLine {
+    pub fn new(stream: OutputStream, line: String) -> Self {
+        let thinking = stream == OutputStream::Stdout
+            && crate::commands::thinking::has_thinking(&line);
+        Self {
+            stream,
+            line,
+            thinking,
+        }
+    }
+}
+
 /// Circular buffer for managing live output with bounded memory
 pub struct CircularOutputBuffer {
-    buffer: VecDeque<String>,
+    buffer: VecDeque<(OutputStream, String)>,
     max_lines: usize,
     max_bytes: usize,
     current_bytes: usize,
+    /// When output last arrived, or when the buffer was created; the run's heartbeat
+    last_output_at: std::time::Instant,
 }
 
 impl CircularOutputBuffer {
@@ -45,11 +102,19 @@ impl CircularOutputBuffer {
             max_lines,
             max_bytes,
             current_bytes: 0,
+            last_output_at: std::time::Instant::now(),
         }
     }
 
-    /// Append output to the buffer with automatic cleanup
+    /// Append stdout output to the buffer with automatic cleanup
     pub fn append(&mut self, output: &str) {
+        self.append_from(OutputStream::Stdout, output);
+    }
+
+    /// Append output from `stream` to the buffer with automatic cleanup
+    pub fn append_from(&mut self, stream: OutputStream, output: &str) {
+        // Even a blank line shows the process is alive
+        self.last_output_at = std::time::Instant::now();
         if output.is_empty() {
             return;
         }
@@ -72,18 +137,23 @@ impl CircularOutputBuffer {
         };
 
         // Add the new line
-        self.buffer.push_back(line);
+        self.buffer.push_back((stream, line));
         self.current_bytes += line_bytes;
 
         // Enforce both line and byte limits efficiently
         self.enforce_limits();
     }
 
+    /// How long ago output last arrived
+    pub fn silent_for(&self) -> std::time::Duration {
+        self.last_output_at.elapsed()
+    }
+
     /// Efficiently enforce size limits
     fn enforce_limits(&mut self) {
         // Single loop to check both conditions
         while self.buffer.len() > self.max_lines || self.current_bytes > self.max_bytes {
-            if let Some(old_line) = self.buffer.pop_front() {
+            if let Some((_, old_line)) = self.buffer.pop_front() {
                 self.current_bytes -= old_line.len();
             }
         }
@@ -100,14 +170,35 @@ impl CircularOutputBuffer {
         self.buffer
             .iter()
             .skip(start_idx)
-            .map(|s| s.as_str())
+            .map(|(_, s)| s.as_str())
             .collect::<Vec<_>>()
             .join("")
     }
 
     /// Get all content from the buffer
     pub fn get_all(&self) -> String {
-        self.buffer.iter().map(|s| s.as_str()).collect::<Vec<_>>().join("")
+        self.get_stream(None)
+    }
+
+    /// Get the content of one stream, or of both interleaved when `stream` is `None`
+    pub fn get_stream(&self, stream: Option<OutputStream>) -> String {
+        self.buffer
+            .iter()
+            .filter(|(source, _)| stream.is_none_or(|stream| *source == stream))
+            .map(|(_, s)| s.as_str())
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// Get the buffered lines with their streams, oldest first
+    pub fn get_lines(&self, stream: Option<OutputStream>) -> Vec<StreamLine> {
+        self.buffer
+            .iter()
+            .filter(|(source, _)| stream.is_none_or(|stream| *source == stream))
+            .map(|(source, line)| {
+                StreamLine::new(*source, line.trim_end_matches('\n').to_string())
+            })
+            .collect()
     }
 
     /// Clear the buffer
@@ -169,14 +260,16 @@ pub struct BufferStats {
 #[allow(dead_code)]
 pub struct ProcessHandle {
     pub info: ProcessInfo,
-    pub child: Arc<Mutex<Option<Child>>>,
+    pub child: Arc<Mutex<Option<ManagedChild>>>,
     pub live_output: Arc<Mutex<CircularOutputBuffer>>,
+    pub usage: Arc<Mutex<RunUsageTracker>>,
 }
 
 /// Registry for tracking active agent processes
 pub struct ProcessRegistry {
     processes: Arc<Mutex<HashMap<i64, ProcessHandle>>>, // run_id -> ProcessHandle
     next_id: Arc<Mutex<i64>>, // Auto-incrementing ID for non-agent processes
+    batches: Arc<Mutex<HashMap<String, Vec<i64>>>>, // batch_id -> run_ids started together
 }
 
 impl ProcessRegistry {
@@ -184,6 +277,7 @@ impl ProcessRegistry {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(1000000)), // Start at high number to avoid conflicts
+            batches: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -203,15 +297,17 @@ impl ProcessRegistry {
 
     /// Create a ProcessHandle with common initialization logic
     fn create_handle(
-        _run_id: i64,
+        run_id: i64,
         info: ProcessInfo,
-        child: Option<Child>,
+        child: Option<ManagedChild>,
     ) -> ProcessHandle {
         let (max_lines, max_bytes) = Self::default_buffer_config();
+        let usage = RunUsageTracker::new(run_id, &info.model);
         ProcessHandle {
             info,
             child: Arc::new(Mutex::new(child)),
             live_output: Arc::new(Mutex::new(CircularOutputBuffer::new(max_lines, max_bytes))),
+            usage: Arc::new(Mutex::new(usage)),
         }
     }
 
@@ -240,7 +336,7 @@ impl ProcessRegistry {
             model,
         };
 
-        self.register_process_internal(run_id, process_info, Some(child))
+        self.register_process_internal(run_id, process_info, Some(child.into()))
     }
 
     /// Register a new running agent process using sidecar (similar to register_process but for sidecar children)
@@ -267,8 +363,10 @@ impl ProcessRegistry {
             model,
         };
 
-        // For sidecar processes, we register without the child handle since it's managed differently
-        self.register_process_internal(run_id, process_info, None)
+        // The shell plugin owns the sidecar child, so control it through its PID
+        self.register_process_internal(run_id, process_info, None)?;
+        self.adopt_process(run_id)?;
+        Ok(())
     }
 
     /// Register a new Claude session (without child process - handled separately)
@@ -292,17 +390,71 @@ impl ProcessRegistry {
             model,
         };
 
-        // Register without child - Claude sessions use ClaudeProcessState for process management
+        // ClaudeProcessState owns the child; the registry only observes it by PID
         self.register_process_internal(run_id, process_info, None)?;
+        self.adopt_process(run_id)?;
         Ok(run_id)
     }
 
+    /// Attach a PID handle to a registered process that has no child handle, so kill, wait
+    /// and status checks treat it like a spawned child. Returns false if the process isn't
+    /// registered, already has a handle, or is no longer running.
+    pub fn adopt_process(&self, run_id: i64) -> Result<bool, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let Some(handle) = processes.get(&run_id) else {
+            return Ok(false);
+        };
+        let mut child = handle.child.lock().map_err(|e| e.to_string())?;
+        if child.is_some() {
+            return Ok(false);
+        }
+        match PidHandle::open(handle.info.pid) {
+            Ok(pid_handle) => {
+                *child = Some(ManagedChild::Adopted(pid_handle));
+                Ok(true)
+            }
+            Err(e) => {
+                log::warn!("Could not adopt process {}: {}", run_id, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Feed `reader` into the live output of a registered process, line by line, until it
+    /// reaches EOF. Used for sidecar and adopted processes whose output isn't read by the
+    /// code that spawned them.
+    pub fn attach_output_reader<R>(
+        &self,
+        run_id: i64,
+        stream: OutputStream,
+        reader: R,
+    ) -> Result<tokio::task::JoinHandle<()>, String>
+    where
+        R: tokio::io::AsyncRead + Send + Unpin + 'static,
+    {
+        let live_output = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            processes
+                .get(&run_id)
+                .map(|handle| handle.live_output.clone())
+                .ok_or_else(|| format!("Process {} not found in registry", run_id))?
+        };
+        Ok(tokio::spawn(async move {
+            let mut reader = tokio::io::BufReader::new(reader);
+            while let Ok(Some(line)) = crate::decoding::read_decoded_line(&mut reader).await {
+                if let Ok(mut buffer) = live_output.lock() {
+                    buffer.append_from(stream, &line);
+                }
+            }
+        }))
+    }
+
     /// Internal method to register any process
     fn register_process_internal(
         &self,
         run_id: i64,
         process_info: ProcessInfo,
-        child: Option<Child>,
+        child: Option<ManagedChild>,
     ) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
         let handle = Self::create_handle(run_id, process_info, child);
@@ -367,6 +519,69 @@ impl ProcessRegistry {
             .collect())
     }
 
+    /// Get summaries of all running processes, oldest first
+    pub fn get_run_summaries(&self) -> Result<Vec<RunSummary>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let mut summaries = processes
+            .values()
+            .map(|handle| {
+                let info = &handle.info;
+                let label = match &info.process_type {
+                    ProcessType::AgentRun { agent_name, .. } => agent_name.clone(),
+                    ProcessType::ClaudeSession { .. } => std::path::Path::new(&info.project_path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| info.project_path.clone()),
+                };
+                let cost_usd = handle
+                    .usage
+                    .lock()
+                    .map(|usage| usage.snapshot().cost_usd)
+                    .unwrap_or(0.0);
+                RunSummary {
+                    run_id: info.run_id,
+                    process_type: info.process_type.clone(),
+                    label,
+                    task: info.task.clone(),
+                    started_at: info.started_at,
+                    cost_usd,
+                }
+            })
+            .collect::<Vec<_>>();
+        summaries.sort_by_key(|summary| summary.started_at);
+        Ok(summaries)
+    }
+
+    /// Group runs started together so they can be watched and cancelled as one
+    pub fn register_batch(&self, batch_id: &str, run_ids: Vec<i64>) -> Result<(), String> {
+        let mut batches = self.batches.lock().map_err(|e| e.to_string())?;
+        batches.insert(batch_id.to_string(), run_ids);
+        Ok(())
+    }
+
+    /// Runs of a batch started in this session
+    pub fn get_batch_runs(&self, batch_id: &str) -> Result<Option<Vec<i64>>, String> {
+        let batches = self.batches.lock().map_err(|e| e.to_string())?;
+        Ok(batches.get(batch_id).cloned())
+    }
+
+    /// Runs of a batch whose process is still registered
+    pub fn get_running_batch_runs(&self, batch_id: &str) -> Result<Vec<i64>, String> {
+        let run_ids = self.get_batch_runs(batch_id)?.unwrap_or_default();
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(run_ids
+            .into_iter()
+            .filter(|run_id| processes.contains_key(run_id))
+            .collect())
+    }
+
+    /// Forget a batch once none of its runs are left
+    pub fn remove_batch(&self, batch_id: &str) -> Result<(), String> {
+        let mut batches = self.batches.lock().map_err(|e| e.to_string())?;
+        batches.remove(batch_id);
+        Ok(())
+    }
+
     /// Get a specific running process
     #[allow(dead_code)]
     pub fn get_process(&self, run_id: i64) -> Result<Option<ProcessInfo>, String> {
@@ -443,8 +658,8 @@ impl ProcessRegistry {
                     let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
                     if let Some(child) = child_guard.as_mut() {
                         match child.try_wait() {
-                            Ok(Some(status)) => {
-                                info!("Process {} exited with status: {:?}", run_id, status);
+                            Ok(Some(code)) => {
+                                info!("Process {} exited with code: {:?}", run_id, code);
                                 *child_guard = None; // Clear the child handle
                                 Some(Ok::<(), String>(()))
                             }
@@ -498,6 +713,37 @@ impl ProcessRegistry {
         Ok(true)
     }
 
+    /// Interrupt a process and the process group it leads. A graceful interrupt only delivers
+    /// the signal; the process stays registered until it exits. Returns false if the process
+    /// isn't registered or has no handle.
+    pub async fn interrupt_process(&self, run_id: i64, mode: InterruptMode) -> Result<bool, String> {
+        let child_arc = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => handle.child.clone(),
+                None => return Ok(false),
+            }
+        };
+
+        {
+            let child_guard = child_arc.lock().map_err(|e| e.to_string())?;
+            let Some(child) = child_guard.as_ref() else {
+                return Ok(false);
+            };
+            let result = match mode {
+                InterruptMode::Graceful => child.interrupt(),
+                InterruptMode::Hard => child.kill_group(),
+            };
+            result.map_err(|e| format!("Failed to interrupt process {}: {}", run_id, e))?;
+        }
+
+        if mode == InterruptMode::Hard {
+            // Waits for the exit and removes the registry entry
+            self.kill_process(run_id).await?;
+        }
+        Ok(true)
+    }
+
     /// Kill a process by PID using system commands (fallback method)
     pub fn kill_process_by_pid(&self, run_id: i64, pid: u32) -> Result<bool, String> {
         use log::{error, info, warn};
@@ -572,6 +818,71 @@ impl ProcessRegistry {
         }
     }
 
+    /// Kill a process together with every process it spawned (tool subprocesses, shells, ...)
+    pub async fn kill_process_tree(&self, run_id: i64) -> Result<bool, String> {
+        use log::{info, warn};
+
+        let pid = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => handle.info.pid,
+                None => {
+                    warn!("Process {} not found in registry", run_id);
+                    return Ok(false);
+                }
+            }
+        };
+
+        if cfg!(target_os = "windows") {
+            // /T terminates the whole tree rooted at the PID
+            let _ = std::process::Command::new("taskkill")
+                .args(["/F", "/T", "/PID", &pid.to_string()])
+                .output();
+        } else {
+            // Kill descendants before the parent, most recently discovered first
+            let descendants = Self::collect_descendant_pids(pid);
+            info!(
+                "Killing {} descendant process(es) of PID {}",
+                descendants.len(),
+                pid
+            );
+            for child_pid in descendants.iter().rev() {
+                let _ = std::process::Command::new("kill")
+                    .args(["-KILL", &child_pid.to_string()])
+                    .output();
+            }
+        }
+
+        self.kill_process(run_id).await
+    }
+
+    /// Collect all descendant PIDs of a process using `pgrep -P`
+    fn collect_descendant_pids(pid: u32) -> Vec<u32> {
+        let mut descendants = Vec::new();
+        let mut queue = vec![pid];
+
+        while let Some(parent) = queue.pop() {
+            let output = match std::process::Command::new("pgrep")
+                .args(["-P", &parent.to_string()])
+                .output()
+            {
+                Ok(output) => output,
+                Err(_) => break,
+            };
+            for child in crate::decoding::decode_command_output(&output.stdout)
+                .lines()
+                .filter_map(|line| line.trim().parse::<u32>().ok())
+            {
+                if !descendants.contains(&child) {
+                    descendants.push(child);
+                    queue.push(child);
+                }
+            }
+        }
+
+        descendants
+    }
+
     /// Check if a process is still running by trying to get its status
     #[allow(dead_code)]
     pub async fn is_process_running(&self, run_id: i64) -> Result<bool, String> {
@@ -607,27 +918,133 @@ impl ProcessRegistry {
         }
     }
 
-    /// Append to live output for a process
+    /// Wait for a registered process to exit and return its exit code.
+    /// Returns `Ok(None)` if the exit code is unknown (no handle, adopted process, killed by
+    /// signal, or timed out).
+    pub async fn wait_for_exit(
+        &self,
+        run_id: i64,
+        timeout: std::time::Duration,
+    ) -> Result<Option<i32>, String> {
+        let child_arc = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => handle.child.clone(),
+                None => return Ok(None),
+            }
+        };
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            {
+                let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
+                match child_guard.as_mut() {
+                    Some(child) => match child.try_wait() {
+                        Ok(Some(code)) => {
+                            *child_guard = None;
+                            return Ok(code);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            *child_guard = None;
+                            return Err(e.to_string());
+                        }
+                    },
+                    None => return Ok(None),
+                }
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Append to live output for a process, updating its running usage totals
     pub fn append_live_output(&self, run_id: i64, output: &str) -> Result<(), String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         if let Some(handle) = processes.get(&run_id) {
             let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
             live_output.append(output);
+            let mut usage = handle.usage.lock().map_err(|e| e.to_string())?;
+            usage.observe_line(output);
         }
         Ok(())
     }
 
-    /// Get live output for a process (all available output)
-    pub fn get_live_output(&self, run_id: i64) -> Result<String, String> {
+    /// How long a registered process has gone without producing output on either stream
+    pub fn silent_for(&self, run_id: i64) -> Result<Option<std::time::Duration>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        match processes.get(&run_id) {
+            Some(handle) => {
+                let live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+                Ok(Some(live_output.silent_for()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Append a stderr line to the live output of a process
+    pub fn append_live_stderr(&self, run_id: i64, output: &str) -> Result<(), String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get(&run_id) {
+            let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+            live_output.append_from(OutputStream::Stderr, output);
+        }
+        Ok(())
+    }
+
+    /// Get the running usage totals for a process
+    pub fn get_run_usage(&self, run_id: i64) -> Result<Option<RunUsage>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get(&run_id) {
+            let usage = handle.usage.lock().map_err(|e| e.to_string())?;
+            Ok(Some(usage.snapshot()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Collect usage totals that changed since the last call, for every process
+    pub fn take_usage_updates(&self) -> Result<Vec<RunUsage>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let mut updates = Vec::new();
+        for handle in processes.values() {
+            let mut usage = handle.usage.lock().map_err(|e| e.to_string())?;
+            if let Some(update) = usage.take_update() {
+                updates.push(update);
+            }
+        }
+        Ok(updates)
+    }
+
+    /// Get live output for a process: one stream, or both interleaved when `stream` is `None`
+    pub fn get_live_output(&self, run_id: i64, stream: Option<OutputStream>) -> Result<String, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         if let Some(handle) = processes.get(&run_id) {
             let live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
-            Ok(live_output.get_all())
+            Ok(live_output.get_stream(stream))
         } else {
             Ok(String::new())
         }
     }
 
+    /// Get live output lines for a process, tagged with their stream
+    pub fn get_live_output_lines(
+        &self,
+        run_id: i64,
+        stream: Option<OutputStream>,
+    ) -> Result<Vec<StreamLine>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get(&run_id) {
+            let live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+            Ok(live_output.get_lines(stream))
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
     /// Get recent live output for a process (limited by number of lines)
     pub fn get_recent_live_output(&self, run_id: i64, lines: usize) -> Result<String, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -695,3 +1112,46 @@ impl Default for ProcessRegistryState {
         Self(Arc::new(ProcessRegistry::new()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_keeps_the_stream_of_each_line() {
+        let mut buffer = CircularOutputBuffer::new(100, 4096);
+        buffer.append("{\"type\":\"system\"}");
+        buffer.append_from(OutputStream::Stderr, "warning: retrying");
+        buffer.append("{\"type\":\"result\"}\n");
+
+        assert_eq!(
+            buffer.get_stream(Some(OutputStream::Stdout)),
+            "{\"type\":\"system\"}\n{\"type\":\"result\"}\n"
+        );
+        assert_eq!(buffer.get_stream(Some(OutputStream::Stderr)), "warning: retrying\n");
+        assert_eq!(buffer.get_all().lines().count(), 3);
+        assert_eq!(
+            buffer.get_lines(None)[1],
+            StreamLine {
+                stream: OutputStream::Stderr,
+                line: "warning: retrying".to_string(),
+                thinking: false,
+            }
+        );
+        assert_eq!(buffer.get_recent(1), "{\"type\":\"result\"}\n");
+    }
+
+    #[test]
+    fn test_buffer_evicts_across_streams() {
+        let mut buffer = CircularOutputBuffer::new(10, 4096);
+        for i in 0..8 {
+            buffer.append(&format!("out {}", i));
+            buffer.append_from(OutputStream::Stderr, &format!("err {}", i));
+        }
+        assert_eq!(buffer.len(), 10);
+        let stderr = buffer.get_lines(Some(OutputStream::Stderr));
+        assert_eq!(stderr.len(), 5);
+        assert_eq!(stderr[0].line, "err 3");
+        assert_eq!(buffer.total_bytes(), buffer.get_all().len());
+    }
+}
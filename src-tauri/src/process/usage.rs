@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::AppHandle;
+
+use super::registry::ProcessRegistry;
+use crate::commands::event_broker::{publish, Channel};
+use crate::commands::usage::cost_for_tokens;
+
+/// How often running totals are pushed to the frontend
+const USAGE_TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Running token and cost totals for an active run
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunUsage {
+    pub run_id: i64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// True once the CLI has reported the run's final cost, false while it is an estimate
+    pub cost_is_final: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MessageUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    cost_usd: f64,
+}
+
+/// Accumulates usage from a run's stream-json output.
+///
+/// The CLI repeats a message's usage on every content block it emits for that message,
+/// so usage is tracked per message id and only the latest report for each is counted.
+#[derive(Debug)]
+pub struct RunUsageTracker {
+    usage: RunUsage,
+    model: String,
+    messages: HashMap<String, MessageUsage>,
+    changed: bool,
+}
+
+impl RunUsageTracker {
+    pub fn new(run_id: i64, model: &str) -> Self {
+        Self {
+            usage: RunUsage {
+                run_id,
+                ..RunUsage::default()
+            },
+            model: model.to_string(),
+            messages: HashMap::new(),
+            changed: false,
+        }
+    }
+
+    /// Feed one line of stream-json output
+    pub fn observe_line(&mut self, line: &str) {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(line) else {
+            return;
+        };
+
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("assistant") => {
+                let Some(message) = json.get("message") else {
+                    return;
+                };
+                let Some(usage) = message.get("usage") else {
+                    return;
+                };
+                let model = message
+                    .get("model")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or(&self.model)
+                    .to_string();
+                let id = message
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| format!("#{}", self.messages.len()));
+
+                let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+                let mut message_usage = MessageUsage {
+                    input_tokens: count("input_tokens"),
+                    output_tokens: count("output_tokens"),
+                    cache_creation_tokens: count("cache_creation_input_tokens"),
+                    cache_read_tokens: count("cache_read_input_tokens"),
+                    cost_usd: 0.0,
+                };
+                message_usage.cost_usd = cost_for_tokens(
+                    &model,
+                    message_usage.input_tokens,
+                    message_usage.output_tokens,
+                    message_usage.cache_creation_tokens,
+                    message_usage.cache_read_tokens,
+                );
+
+                self.messages.insert(id, message_usage);
+                self.recompute();
+            }
+            Some("result") => {
+                if let Some(cost) = json.get("total_cost_usd").and_then(|c| c.as_f64()) {
+                    self.usage.cost_usd = cost;
+                    self.usage.cost_is_final = true;
+                    self.changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn recompute(&mut self) {
+        let mut totals = MessageUsage::default();
+        for message in self.messages.values() {
+            totals.input_tokens += message.input_tokens;
+            totals.output_tokens += message.output_tokens;
+            totals.cache_creation_tokens += message.cache_creation_tokens;
+            totals.cache_read_tokens += message.cache_read_tokens;
+            totals.cost_usd += message.cost_usd;
+        }
+
+        let usage = &mut self.usage;
+        usage.input_tokens = totals.input_tokens;
+        usage.output_tokens = totals.output_tokens;
+        usage.cache_creation_tokens = totals.cache_creation_tokens;
+        usage.cache_read_tokens = totals.cache_read_tokens;
+        usage.total_tokens = totals.input_tokens
+            + totals.output_tokens
+            + totals.cache_creation_tokens
+            + totals.cache_read_tokens;
+        if !usage.cost_is_final {
+            usage.cost_usd = totals.cost_usd;
+        }
+        self.changed = true;
+    }
+
+    /// Current running totals
+    pub fn snapshot(&self) -> RunUsage {
+        self.usage.clone()
+    }
+
+    /// Totals if they changed since the last call
+    pub fn take_update(&mut self) -> Option<RunUsage> {
+        if std::mem::take(&mut self.changed) {
+            Some(self.usage.clone())
+        } else {
+            None
+        }
+    }
+}
+
+/// Periodically emit `run:usage-updated` for every active run whose totals changed
+pub fn spawn_usage_ticker(app: AppHandle, registry: Arc<ProcessRegistry>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(USAGE_TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match registry.take_usage_updates() {
+                Ok(updates) => {
+                    for usage in updates {
+                        let _ = publish(&app, Channel::Usage, "run:usage-updated", &usage);
+                    }
+                }
+                Err(e) => log::warn!("Failed to collect run usage: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_message_usage_is_counted_once() {
+        let mut tracker = RunUsageTracker::new(1, "claude-sonnet-4");
+        let line = r#"{"type":"assistant","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":1000,"output_tokens":200}}}"#;
+        tracker.observe_line(line);
+        tracker.observe_line(line);
+        tracker.observe_line(
+            r#"{"type":"assistant","message":{"id":"msg_2","usage":{"input_tokens":500,"output_tokens":100,"cache_read_input_tokens":1000}}}"#,
+        );
+
+        let usage = tracker.take_update().unwrap();
+        assert_eq!(usage.input_tokens, 1500);
+        assert_eq!(usage.output_tokens, 300);
+        assert_eq!(usage.total_tokens, 2800);
+        assert!((usage.cost_usd - 0.0093).abs() < 1e-9);
+        assert!(tracker.take_update().is_none());
+    }
+
+    #[test]
+    fn test_result_cost_is_final() {
+        let mut tracker = RunUsageTracker::new(1, "claude-opus-4");
+        tracker.observe_line(r#"{"type":"result","total_cost_usd":0.42}"#);
+        tracker.observe_line(
+            r#"{"type":"assistant","message":{"id":"msg_1","usage":{"input_tokens":10,"output_tokens":10}}}"#,
+        );
+
+        let usage = tracker.snapshot();
+        assert!(usage.cost_is_final);
+        assert_eq!(usage.cost_usd, 0.42);
+        assert_eq!(usage.total_tokens, 20);
+    }
+}
@@ -279,7 +279,7 @@ async fn save_claude_md_file(
         }
     }
 
-    match fs::write(path, content) {
+    match crate::atomic_file::write_atomic(path, content) {
         Ok(_) => Json(ApiResponse::success(
             "File saved successfully".to_string(),
         )),
@@ -0,0 +1,178 @@
+//! Drives the MCP and terminal command logic with the mock CLI runner and in-memory file
+//! system, without Tauri or a real `claude` binary.
+
+use std::collections::HashMap;
+
+use opcode_lib::commands::cancellation::CancellationToken;
+use opcode_lib::commands::error::ErrorKind;
+use opcode_lib::commands::mcp::{
+    add_server, get_server, list_servers, read_project_config, remove_server, save_project_config,
+    MCPProjectConfig, MCPServerConfig, NewMcpServer,
+};
+use opcode_lib::commands::providers::mock::{MemoryFs, MockClaudeCli};
+use opcode_lib::commands::terminal::run_terminal_command;
+
+const GET_FS: &str = "fs:\n  Scope: User config (available in all your projects)\n  Status: ✓ Connected\n  Type: stdio\n  Command: npx\n  Args: -y @modelcontextprotocol/server-filesystem /tmp\n";
+
+fn no_tools(_: &str) -> Result<Vec<String>, String> {
+    Ok(vec![])
+}
+
+fn args(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn list_servers_fetches_details_and_falls_back_per_server() {
+    let cli = MockClaudeCli::new()
+        .respond(
+            &["list"],
+            "Checking MCP server health...\n\nfs: npx -y @modelcontextprotocol/server-filesystem /tmp - ✓ Connected\nbroken: ./missing - ✗ Failed to connect\n",
+        )
+        .respond(&["get", "fs"], GET_FS)
+        .fail(&["get", "broken"], "boom");
+
+    let servers = list_servers(&cli, &CancellationToken::new(), &no_tools).unwrap();
+    assert_eq!(servers.len(), 2);
+
+    let fs = &servers[0];
+    assert_eq!(fs.name, "fs");
+    assert_eq!(fs.scope, "user");
+    assert_eq!(fs.command.as_deref(), Some("npx"));
+    assert_eq!(fs.args[0], "-y");
+    assert!(fs.is_active);
+
+    let broken = &servers[1];
+    assert!(!broken.is_active);
+    assert!(broken.status.error.as_deref().unwrap().contains("boom"));
+    assert_eq!(cli.calls(), vec![args(&["list"]), args(&["get", "fs"]), args(&["get", "broken"])]);
+}
+
+#[test]
+fn list_servers_handles_empty_output_and_cancellation() {
+    let cli = MockClaudeCli::new().respond(&["list"], "No MCP servers configured. Use `claude mcp add` to add a server.");
+    assert!(list_servers(&cli, &CancellationToken::new(), &no_tools).unwrap().is_empty());
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let error = list_servers(&cli, &token, &no_tools).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Cancelled);
+}
+
+#[test]
+fn get_server_validates_name_and_uses_tool_lookup() {
+    let cli = MockClaudeCli::new().respond(&["get", "fs"], GET_FS);
+    let server = get_server(&cli, "fs".to_string(), &CancellationToken::new(), &|name| {
+        Ok(vec![format!("mcp__{}__read", name)])
+    })
+    .unwrap();
+    assert_eq!(server.tools, Some(vec!["mcp__fs__read".to_string()]));
+
+    let error = get_server(&cli, "bad name;".to_string(), &CancellationToken::new(), &no_tools).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Validation);
+    assert_eq!(cli.calls().len(), 1);
+}
+
+#[test]
+fn add_server_builds_cli_arguments() {
+    let cli = MockClaudeCli::new().respond(
+        &["add", "-s", "project", "-e", "ROOT=/tmp", "fs", "--", "npx", "-y", "server-fs"],
+        "Added stdio MCP server fs",
+    );
+    let result = add_server(
+        &cli,
+        NewMcpServer {
+            name: "fs".to_string(),
+            transport: "stdio".to_string(),
+            command: Some("npx".to_string()),
+            args: args(&["-y", "server-fs"]),
+            env: HashMap::from([("ROOT".to_string(), "/tmp".to_string())]),
+            scope: "project".to_string(),
+            ..Default::default()
+        },
+    );
+    assert!(result.success, "{}", result.message);
+    assert_eq!(result.server_name.as_deref(), Some("fs"));
+}
+
+#[test]
+fn add_server_rejects_invalid_input_without_calling_the_cli() {
+    let cli = MockClaudeCli::new();
+    let result = add_server(
+        &cli,
+        NewMcpServer {
+            name: "fs".to_string(),
+            transport: "stdio".to_string(),
+            command: Some("npx; rm -rf /".to_string()),
+            scope: "local".to_string(),
+            ..Default::default()
+        },
+    );
+    assert!(!result.success);
+    assert!(result.message.starts_with("Invalid command"));
+
+    let result = add_server(
+        &cli,
+        NewMcpServer {
+            name: "remote".to_string(),
+            transport: "sse".to_string(),
+            scope: "local".to_string(),
+            ..Default::default()
+        },
+    );
+    assert_eq!(result.message, "URL is required for SSE transport");
+    assert!(cli.calls().is_empty());
+}
+
+#[test]
+fn remove_server_reports_missing_servers_as_not_found() {
+    let cli = MockClaudeCli::new()
+        .respond(&["remove", "fs"], "Removed MCP server fs\n")
+        .fail(&["remove", "gone"], "No MCP server found with name: gone");
+    assert_eq!(remove_server(&cli, "fs").unwrap(), "Removed MCP server fs");
+    assert_eq!(remove_server(&cli, "gone").unwrap_err().kind, ErrorKind::NotFound);
+}
+
+#[test]
+fn project_config_round_trips_through_the_file_system() {
+    let fs = MemoryFs::new().with_dir("/work/app");
+    assert!(read_project_config(&fs, "/work/app").unwrap().mcp_servers.is_empty());
+
+    let config = MCPProjectConfig {
+        mcp_servers: HashMap::from([(
+            "fs".to_string(),
+            MCPServerConfig {
+                transport_type: "stdio".to_string(),
+                command: "npx".to_string(),
+                args: args(&["server-fs"]),
+                env: HashMap::new(),
+                url: None,
+                headers: None,
+            },
+        )]),
+    };
+    save_project_config(&fs, "/work/app", &config).unwrap();
+    assert!(fs.file("/work/app/.mcp.json").unwrap().contains("\"mcpServers\""));
+    let read = read_project_config(&fs, "/work/app").unwrap();
+    assert_eq!(read.mcp_servers["fs"].args, vec!["server-fs"]);
+
+    let error = save_project_config(&fs, "/missing", &config).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::NotFound);
+
+    let fs = MemoryFs::new().with_file("/work/app/.mcp.json", "{ not json");
+    assert_eq!(read_project_config(&fs, "/work/app").unwrap_err().kind, ErrorKind::Parse);
+}
+
+#[tokio::test]
+async fn terminal_checks_whitelist_and_working_directory() {
+    let fs = MemoryFs::new();
+    let error = run_terminal_command(&fs, "rm -rf /", None).await.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Validation);
+
+    let dir = "/tmp/opcode-missing-dir".to_string();
+    let error = run_terminal_command(&fs, "echo hi", Some(&dir)).await.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::NotFound);
+
+    let output = run_terminal_command(&fs, "echo hi", None).await.unwrap();
+    assert_eq!(output.stdout.trim(), "hi");
+}
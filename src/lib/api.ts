@@ -77,6 +77,49 @@ export interface ClaudeVersionStatus {
   output: string;
 }
 
+/**
+ * How the Claude CLI authenticates
+ */
+export type AuthMethod =
+  | "claude_ai"
+  | "console_api_key"
+  | "api_key_env"
+  | "auth_token_env"
+  | "api_key_helper"
+  | "bedrock"
+  | "vertex"
+  | "none";
+
+/**
+ * The Claude CLI's authentication, for explaining failed runs
+ */
+export interface AuthStatus {
+  /** Method the CLI will use */
+  method: AuthMethod;
+  /** Where the credential comes from, e.g. "environment" */
+  source: string | null;
+  credentials_present: boolean;
+  /** Expiry of the claude.ai login */
+  expires_at: string | null;
+  expired: boolean | null;
+  subscription_type: string | null;
+  account: {
+    email: string | null;
+    display_name: string | null;
+    account_uuid: string | null;
+    organization_name: string | null;
+    organization_uuid: string | null;
+    organization_role: string | null;
+  } | null;
+  /** Last characters of the API key in use */
+  key_hint: string | null;
+  base_url: string | null;
+  /** Other configured methods the active one takes precedence over */
+  overridden: AuthMethod[];
+  /** Likely causes of authentication failures */
+  problems: string[];
+}
+
 /**
  * Represents a CLAUDE.md file found in the project
  */
@@ -128,6 +171,8 @@ export interface Agent {
   hooks?: string; // JSON string of HooksConfiguration
   created_at: string;
   updated_at: string;
+  thinking?: RunThinking | null;
+  tool_rules?: ToolRules | null;
 }
 
 export interface AgentExport {
@@ -165,6 +210,75 @@ export interface AgentRun {
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  verbosity?: RunVerbosity;
+}
+
+/**
+ * Diagnostics requested for a spawned claude process
+ */
+export interface RunVerbosity {
+  /** Pass --debug to the CLI */
+  debug?: boolean;
+  /** Debug categories to keep, e.g. "api" or "!statsig" */
+  debug_categories?: string[];
+  /** MCP_LOG_LEVEL for the run's MCP servers: debug, info, notice, warning or error */
+  mcp_log_level?: string;
+}
+
+export type ThinkingEffort = "think" | "think_hard" | "think_harder" | "ultrathink";
+
+/**
+ * Extended thinking for a run; stream lines holding thinking blocks carry `thinking: true`
+ */
+export interface RunThinking {
+  /** Effort keyword put in front of the prompt */
+  effort?: ThinkingEffort;
+  /** MAX_THINKING_TOKENS, between 1024 and 128000 */
+  budget_tokens?: number;
+}
+
+/**
+ * A tool use waiting for approval, sent as the `run:permission-request` event
+ */
+export interface PermissionRequest {
+  run_id: number;
+  request_id: string;
+  tool_name: string;
+  input: Record<string, any>;
+  tool_use_id: string | null;
+  /** Rules the CLI suggests for always allowing this tool */
+  suggestions: any | null;
+  received_at: string;
+}
+
+/**
+ * Tools a run may or may not use, in the CLI's rule syntax, e.g. "Bash(git diff:*)" or
+ * "mcp__github__search_code"
+ */
+export interface ToolRules {
+  /** Only these tools may run; empty allows every tool that isn't disallowed */
+  allowed_tools?: string[];
+  disallowed_tools?: string[];
+}
+
+/**
+ * Per-run limits; a run that crosses one is stopped with status "budget_exceeded"
+ */
+export interface RunGuards {
+  max_turns?: number;
+  /** Input, output and cache tokens together */
+  max_tokens?: number;
+  max_cost_usd?: number;
+}
+
+/**
+ * Payload of the run:budget-exceeded event
+ */
+export interface BudgetExceeded {
+  run_id: number;
+  limit: 'turns' | 'tokens' | 'cost';
+  limit_value: number;
+  observed: number;
 }
 
 export interface AgentRunMetrics {
@@ -422,6 +536,56 @@ export interface ServerStatus {
   error?: string;
   /** Last checked timestamp */
   last_checked?: number;
+  /** Last reachability probe, for SSE/HTTP servers */
+  reachability?: McpReachabilityProbe | null;
+}
+
+/**
+ * Reachability of a remote MCP server; `failed_stage` is the first check that failed
+ */
+export interface McpReachabilityProbe {
+  url: string;
+  checked_at: number;
+  elapsed_ms: number;
+  addresses: string[];
+  connected_to: string | null;
+  certificate: {
+    valid: boolean;
+    error: string | null;
+    not_after: string | null;
+    days_remaining: number | null;
+  } | null;
+  http_status: number | null;
+  failed_stage: "url" | "dns" | "tcp" | "tls" | "http" | null;
+  cause: string | null;
+}
+
+/**
+ * Extra CA bundle and servers whose certificates opcode's checks don't verify
+ */
+export interface TlsSettings {
+  ca_bundle_path?: string | null;
+  insecure_servers: string[];
+  /** Servers with a client certificate in the keychain; changed with setMcpClientCertificate */
+  client_cert_servers: string[];
+  /** Server whose client certificate the Claude CLI presents */
+  cli_client_cert_server?: string | null;
+}
+
+export interface ClientCertificateInfo {
+  server: string;
+  not_after: string | null;
+  days_remaining: number | null;
+  error: string | null;
+}
+
+export interface TlsStatus {
+  settings: TlsSettings;
+  ca_certificates: number | null;
+  ca_error: string | null;
+  client_certificates: ClientCertificateInfo[];
+  /** Risks of the current settings, to show next to them */
+  warnings: string[];
 }
 
 /**
@@ -488,1879 +652,3901 @@ export interface SlashCommand {
 /**
  * Result of adding a server
  */
+export type OutputStream = "stdout" | "stderr";
+
+/** A line of process output, as sent in `agent-stream:*` and `claude-stream:*` events */
+export interface StreamLine {
+  stream: OutputStream;
+  line: string;
+  /** The line holds a thinking block or a thinking delta */
+  thinking?: boolean;
+}
+
+export interface ScopeRecommendation {
+  scope: string;
+  reasons: string[];
+  /** A team policy requires this scope */
+  enforced: boolean;
+}
+
 export interface AddServerResult {
   success: boolean;
   message: string;
   server_name?: string;
+  recommendation?: ScopeRecommendation;
 }
 
-/**
- * API client for interacting with the Rust backend
- */
-export const api = {
-  // Cache for Claude installations
-  _claudeInstallationsCache: {
-    data: null as ClaudeInstallation[] | null,
-    timestamp: 0,
-    ttl: 5 * 60 * 1000, // 5 minutes cache
-  },
+export interface OfflineStatus {
+  offline: boolean;
+  /** Offline mode was turned on by the user rather than detected */
+  forced: boolean;
+  queued_operations: number;
+}
 
-  /**
-   * Gets the user's home directory path
-   * @returns Promise resolving to the home directory path
-   */
-  async getHomeDirectory(): Promise<string> {
-    try {
-      return await apiCall<string>("get_home_directory");
-    } catch (error) {
-      console.error("Failed to get home directory:", error);
-      return "/";
-    }
-  },
+/** Data served by an offline-aware command */
+export interface Cached<T> {
+  data: T;
+  /** When the data was fetched; null for data fetched just now */
+  cached_at: string | null;
+  /** Served from the cache because fresh data couldn't be fetched */
+  stale: boolean;
+}
 
-  /**
-   * Lists all projects in the ~/.claude/projects directory
-   * @returns Promise resolving to an array of projects
-   */
-  async listProjects(): Promise<Project[]> {
-    try {
-      return await apiCall<Project[]>("list_projects");
-    } catch (error) {
-      console.error("Failed to list projects:", error);
-      throw error;
-    }
-  },
+export type QueuedMcpOperation =
+  | { type: "add"; server: Record<string, any> & { name: string } }
+  | { type: "add_json"; name: string; json_config: string; scope: string }
+  | { type: "remove"; name: string };
 
-  /**
-   * Creates a new project for the given directory path
-   * @param path - The directory path to create a project for
-   * @returns Promise resolving to the created project
-   */
-  async createProject(path: string): Promise<Project> {
-    try {
-      return await apiCall<Project>('create_project', { path });
-    } catch (error) {
-      console.error("Failed to create project:", error);
-      throw error;
-    }
-  },
+export interface QueuedOperation {
+  id: number;
+  operation: QueuedMcpOperation;
+  attempts: number;
+  last_error: string | null;
+  created_at: string;
+}
 
-  /**
-   * Retrieves sessions for a specific project
-   * @param projectId - The ID of the project to retrieve sessions for
-   * @returns Promise resolving to an array of sessions
-   */
-  async getProjectSessions(projectId: string): Promise<Session[]> {
-    try {
-      return await apiCall<Session[]>('get_project_sessions', { projectId });
-    } catch (error) {
-      console.error("Failed to get project sessions:", error);
-      throw error;
-    }
-  },
+export interface ReplayReport {
+  replayed: number;
+  remaining: number;
+  error: string | null;
+}
 
-  /**
-   * Fetch list of agents from GitHub repository
-   * @returns Promise resolving to list of available agents on GitHub
-   */
-  async fetchGitHubAgents(): Promise<GitHubAgentFile[]> {
-    try {
-      return await apiCall<GitHubAgentFile[]>('fetch_github_agents');
-    } catch (error) {
-      console.error("Failed to fetch GitHub agents:", error);
-      throw error;
-    }
-  },
+export interface TranscriptEntry {
+  uuid: string | null;
+  role: "user" | "assistant";
+  timestamp: string | null;
+  text: string;
+  tool_calls: { name: string; input: any }[];
+  tool_errors: number;
+  input_tokens: number;
+  output_tokens: number;
+}
 
-  /**
-   * Fetch and preview a specific agent from GitHub
-   * @param downloadUrl - The download URL for the agent file
-   * @returns Promise resolving to the agent export data
-   */
-  async fetchGitHubAgentContent(downloadUrl: string): Promise<AgentExport> {
-    try {
-      return await apiCall<AgentExport>('fetch_github_agent_content', { downloadUrl });
-    } catch (error) {
-      console.error("Failed to fetch GitHub agent content:", error);
-      throw error;
-    }
-  },
+/** One side of a session diff, after the point the sessions diverged */
+export interface BranchSummary {
+  session_id: string;
+  messages: TranscriptEntry[];
+  assistant_responses: number;
+  tool_calls: number;
+  tool_errors: number;
+  input_tokens: number;
+  output_tokens: number;
+  tools_only_here: string[];
+}
 
-  /**
-   * Import an agent directly from GitHub
-   * @param downloadUrl - The download URL for the agent file
-   * @returns Promise resolving to the imported agent
-   */
-  async importAgentFromGitHub(downloadUrl: string): Promise<Agent> {
-    try {
-      return await apiCall<Agent>('import_agent_from_github', { downloadUrl });
-    } catch (error) {
-      console.error("Failed to import agent from GitHub:", error);
-      throw error;
-    }
-  },
+export interface SessionDiff {
+  relation: "identical" | "a_prefix_of_b" | "b_prefix_of_a" | "diverged" | "unrelated";
+  shared_messages: number;
+  last_shared_uuid: string | null;
+  a: BranchSummary;
+  b: BranchSummary;
+}
 
-  /**
-   * Reads the Claude settings file
-   * @returns Promise resolving to the settings object
-   */
-  async getClaudeSettings(): Promise<ClaudeSettings> {
-    try {
-      const result = await apiCall<{ data: ClaudeSettings }>("get_claude_settings");
+export interface ReplayResult {
+  original_session_id: string;
+  replay_session_id: string;
+  model: string | null;
+  prompts_replayed: number;
+  /** Tool calls answered from the recorded run */
+  stubbed_tool_calls: number;
+  /** Tools called during the replay that the recording had no result for */
+  unmatched_tool_calls: string[];
+  diff: SessionDiff;
+}
 
-      
-      // The Rust backend returns ClaudeSettings { data: ... }
-      // We need to extract the data field
-      if (result && typeof result === 'object' && 'data' in result) {
-        return result.data;
-      }
-      
-      // If the result is already the settings object, return it
-      return result as ClaudeSettings;
-    } catch (error) {
-      console.error("Failed to get Claude settings:", error);
-      throw error;
-    }
-  },
+export interface RedactionReport {
+  counts: Record<string, number>;
+  total: number;
+}
 
-  /**
-   * Opens a new Claude Code session
-   * @param path - Optional path to open the session in
-   * @returns Promise resolving when the session is opened
-   */
-  async openNewSession(path?: string): Promise<string> {
-    try {
-      return await apiCall<string>("open_new_session", { path });
-    } catch (error) {
-      console.error("Failed to open new session:", error);
-      throw error;
-    }
-  },
+export interface BundledFileChange {
+  path: string;
+  change_type: "created" | "modified" | "deleted";
+  additions: number | null;
+  deletions: number | null;
+  /** Content before the run, when the pre-run checkpoint has it */
+  before: string | null;
+  /** Content at export time */
+  after: string | null;
+}
 
-  /**
-   * Reads the CLAUDE.md system prompt file
-   * @returns Promise resolving to the system prompt content
-   */
-  async getSystemPrompt(): Promise<string> {
-    try {
-      return await apiCall<string>("get_system_prompt");
-    } catch (error) {
-      console.error("Failed to get system prompt:", error);
-      throw error;
-    }
-  },
+export interface RunBundle {
+  version: number;
+  exported_at: string;
+  app_version: string;
+  agent: {
+    name: string;
+    icon: string;
+    system_prompt: string;
+    default_task: string | null;
+    model: string;
+    enable_file_read: boolean;
+    enable_file_write: boolean;
+    enable_network: boolean;
+    hooks: string | null;
+  };
+  task: string;
+  model: string;
+  served_model: string | null;
+  verbosity: RunVerbosity | null;
+  status: string;
+  project_name: string;
+  environment: Record<string, string>;
+  file_changes: BundledFileChange[];
+  transcript: any[];
+  redactions: RedactionReport;
+}
 
-  /**
-   * Get project CLAUDE.md prompt content
-   * @param projectPath - Path to the project directory
-   * @returns Promise resolving to the project CLAUDE.md content
-   */
-  async getProjectPrompt(projectPath: string): Promise<string> {
-    try {
-      return await apiCall<string>("get_project_prompt", { projectPath });
-    } catch (error) {
-      console.error("Failed to get project prompt:", error);
-      throw error;
-    }
-  },
+export interface ImportedRunBundle {
+  agent_id: number;
+  agent_name: string;
+  task: string;
+  model: string;
+  restored_files: string[];
+  run_id: number | null;
+}
 
-  /**
-   * Gets the current working directory
-   */
-  async getCurrentWorkingDirectory(): Promise<string> {
-    try {
-      return await apiCall<string>("get_current_working_directory");
-    } catch (error) {
-      console.error("Failed to get current working directory:", error);
-      throw error;
-    }
-  },
+export type QuickRunKind = "agent" | "slash_command" | "recent_task";
 
-  /**
+export interface QuickRunItem {
+  kind: QuickRunKind;
+  /** Stable id to pass back to quickRun, e.g. "agent:3" */
+  id: string;
+  label: string;
+  detail: string | null;
+  score: number;
+  agent_id: number | null;
+  project_path: string | null;
+  task: string | null;
+}
+
+export interface QuickRunOutcome {
+  item: QuickRunItem;
+  /** Run started for a recent task */
+  run_id: number | null;
+}
+
+export type EventChannel = "agent_output" | "claude_output" | "usage" | "mcp_status" | "connectivity";
+
+export interface EncryptionStatus {
+  enabled: boolean;
+  /** Whether the key is in the OS keychain */
+  key_available: boolean;
+  /** When the database was last sealed */
+  sealed_at: string | null;
+  /** Files encrypted or decrypted by the last migration */
+  converted_files: number;
+}
+
+export interface AppLockSettings {
+  enabled: boolean;
+  lock_on_launch: boolean;
+  /** Minutes without activity before locking; null never locks for inactivity */
+  idle_minutes: number | null;
+  method: "password" | "system";
+}
+
+export interface AppLockStatus {
+  settings: AppLockSettings;
+  locked: boolean;
+  password_set: boolean;
+  /** Seconds until unlocking is allowed again after too many failed attempts */
+  retry_after_secs: number | null;
+}
+
+/**
+ * Sent in place of an event payload over its channel's size budget
+ */
+export interface TruncatedPayload {
+  truncated: true;
+  /** Pass to fetchEventPayload for the full payload */
+  payload_id: string;
+  size: number;
+  preview: string;
+}
+
+/**
+ * A usage counter and its total over the summary period
+ */
+export interface TelemetryCount {
+  name: string;
+  count: number;
+}
+
+/**
+ * Local usage counts of the last `days` days, largest first
+ */
+export interface TelemetrySummary {
+  enabled: boolean;
+  days: number;
+  /** First day with any counts, as YYYY-MM-DD */
+  since: string | null;
+  commands: TelemetryCount[];
+  runs: TelemetryCount[];
+  errors: TelemetryCount[];
+}
+
+/**
+ * File written by exportTelemetry
+ */
+export interface TelemetryExport {
+  app_version: string;
+  os: string;
+  arch: string;
+  exported_at: string;
+  summary: TelemetrySummary;
+}
+
+/**
+ * A slice of a session transcript, for virtualized scrolling
+ */
+export interface SessionWindow {
+  /** Messages in the whole transcript */
+  total: number;
+  /** Position of the first returned message */
+  start: number;
+  /** Position of the anchor, null for an empty transcript */
+  anchor_index: number | null;
+  /** Messages in order; lines that aren't valid JSON are null */
+  messages: any[];
+}
+
+/**
+ * A message matching a transcript search
+ */
+export interface SessionSearchHit {
+  session_id: string;
+  /** Name of the project directory under ~/.claude/projects */
+  project_id: string;
+  /** Position of the message, an anchor for getSessionWindow */
+  position: number;
+  uuid: string | null;
+  timestamp: string | null;
+  /** Text around the match, with matches in [brackets] */
+  snippet: string;
+}
+
+/**
+ * Insights of one project, read from the session index
+ */
+export interface ProjectStats {
+  project_id: string;
+  session_count: number;
+  message_count: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
+  total_tokens: number;
+  cost_usd: number;
+  first_activity: string | null;
+  last_activity: string | null;
+  top_tools: { name: string; count: number }[];
+  top_files: { path: string; edits: number }[];
+  /** Seven entries, Sunday first; hours holds 24 message counts in local time */
+  activity_by_weekday: { weekday: number; hours: number[]; total: number }[];
+}
+
+/**
+ * Calls of one tool across sessions and agent runs
+ */
+export interface ToolUsageStat {
+  tool: string;
+  /** MCP server of `mcp__<server>__<tool>` tools */
+  server: string | null;
+  invocations: number;
+  failures: number;
+  /** Failures among calls whose result was seen */
+  failure_rate: number | null;
+  avg_duration_ms: number | null;
+  sessions: number;
+}
+
+/**
+ * Tool usage per tool and per MCP server
+ */
+export interface ToolUsageStats {
+  total_invocations: number;
+  tools: ToolUsageStat[];
+  servers: { server: string; invocations: number; failures: number; tools_used: number }[];
+  /** Probed MCP servers none of whose tools were called */
+  unused_servers: string[];
+}
+
+/**
+ * A file Claude changed often in a project
+ */
+export interface FileHotspot {
+  /** Relative to the session's working directory when inside it */
+  path: string;
+  edits: number;
+  sessions: number;
+  first_edited: string | null;
+  last_edited: string | null;
+  tools: { tool: string; edits: number }[];
+}
+
+/**
+ * The files Claude changed most often in a project, and when
+ */
+export interface FileHotspotReport {
+  project_id: string;
+  total_edits: number;
+  files_edited: number;
+  hotspots: FileHotspot[];
+  /** Oldest first */
+  daily: { date: string; edits: number; files: number }[];
+}
+
+/**
+ * Change that resolves an MCP lint warning
+ */
+export type McpLintFix =
+  | { action: "remove_server" }
+  | { action: "replace_command"; command: string }
+  | { action: "define_variable"; variable: string }
+  | { action: "remove_header"; header: string };
+
+/**
+ * Problem found in an MCP server's configuration
+ */
+export interface McpLintWarning {
+  code: "duplicate_server" | "command_not_found" | "undefined_variable" | "unresolvable_host" | "empty_header";
+  severity: "error" | "warning";
+  scope: string;
+  server: string;
+  /** Config file the server is defined in */
+  path: string;
+  message: string;
+  fix: McpLintFix | null;
+}
+
+/**
+ * Resource limits of a stdio MCP server; null leaves that resource unlimited
+ */
+export interface McpServerLimits {
+  server: string;
+  /** Resident memory of the server and the processes it started */
+  max_rss_mb: number | null;
+  max_cpu_secs: number | null;
+  max_runtime_secs: number | null;
+}
+
+/**
+ * A server killed for going over a limit; also the payload of the mcp:limit-exceeded event
+ */
+export interface McpLimitViolation {
+  id: number | null;
+  server: string;
+  run_id: number;
+  pid: number;
+  limit: "rss" | "cpu" | "runtime";
+  observed: number;
+  max: number;
+  killed: boolean;
+  created_at: string | null;
+}
+
+/**
+ * Named environment for routing the CLI to a backend; secret values live in the OS keychain
+ */
+export interface ProviderProfile {
+  id?: number;
+  name: string;
+  provider: "anthropic" | "bedrock" | "vertex" | "proxy";
+  /** Plain-text variables, e.g. ANTHROPIC_BASE_URL or AWS_REGION */
+  env: Record<string, string>;
+  /** Names of the variables kept in the keychain */
+  secret_vars: string[];
+  created_at?: string;
+}
+
+/**
+ * Result of testing a provider profile's backend
+ */
+export interface ProviderConnectivity {
+  ok: boolean;
+  status: number | null;
+  latency_ms: number;
+  endpoint: string;
+  message: string;
+}
+
+/**
+ * SSH host a project's agents can run on; keys come from the SSH agent or the keychain
+ */
+export interface RemoteHost {
+  id?: number;
+  name: string;
+  /** Host name or ~/.ssh/config alias */
+  host: string;
+  user?: string | null;
+  port?: number | null;
+  key_source: "agent" | "keychain";
+  /** Local directories and where the same checkouts live on the host */
+  path_mappings: { local: string; remote: string }[];
+  /** Set by detectRemoteClaude */
+  claude_path?: string | null;
+  claude_version?: string | null;
+  detected_at?: string | null;
+}
+
+export interface RemoteCommandOutput {
+  stdout: string;
+  stderr: string;
+  exit_code: number;
+}
+
+export interface ContainerConfig {
+  project_path: string;
+  /** Image to run; null uses the project's devcontainer */
+  image: string | null;
+  claude_path: string;
+  /** Mount ~/.claude so the CLI in the container uses the local login */
+  mount_claude_config: boolean;
+  /** Keep node_modules, cargo's target and download caches in volumes reused between runs */
+  cache_volumes: boolean;
+  updated_at?: string | null;
+}
+
+export interface SshTunnel {
+  id?: number | null;
+  name: string;
+  host_id: number;
+  /** Target as resolved on the SSH host */
+  remote_host: string;
+  remote_port: number;
+  local_port: number;
+  created_at?: string | null;
+}
+
+export type TunnelState = "connecting" | "up" | "reconnecting" | "stopped";
+
+export interface TunnelStatus {
+  tunnel: SshTunnel;
+  state: TunnelState;
+  last_error: string | null;
+  reconnects: number;
+  /** MCP servers added with a tunnel://<name>/<path> URL */
+  mcp_servers: string[];
+}
+
+export type PrintTaskKind = "summarize" | "commit_message" | "transcript_analysis";
+
+export type PrintBackend = "cli" | "api";
+
+export interface PrintTaskRequest {
+  kind: PrintTaskKind;
+  /** Text to work on; otherwise read from the session or the project */
+  input?: string | null;
+  /** Session whose transcript summaries and analyses use */
+  session_id?: string | null;
+  /** Project whose staged diff commit messages describe; also picks its provider profile */
+  project_path?: string | null;
+  profile_id?: number | null;
+  model?: string | null;
+}
+
+export interface PrintTaskResult {
+  text: string;
+  backend: PrintBackend;
+  model: string | null;
+  input_tokens: number | null;
+  output_tokens: number | null;
+}
+
+export interface PrintBackendStatus {
+  preference: "auto" | PrintBackend;
+  cli_path: string | null;
+  cli_version: string | null;
+  /** Installed and recent enough for print tasks */
+  cli_usable: boolean;
+  selected: PrintBackend;
+}
+
+export interface ContainerImageProgress {
+  project_path: string;
+  image: string;
+  line: string;
+}
+
+export interface RunContainer {
+  id: string;
+  name: string;
+  image: string;
+  state: string;
+  status: string;
+  created_at: string | null;
+  run_id: number | null;
+  project_path: string | null;
+}
+
+export interface Devcontainer {
+  config_path: string;
+  image: string | null;
+  dockerfile: string | null;
+  build_context: string | null;
+  build_args: Record<string, string>;
+  workspace_folder: string | null;
+  user: string | null;
+  container_env: Record<string, string>;
+}
+
+export interface DigestSettings {
+  /** Write the previous day's digest automatically */
+  enabled: boolean;
+  /** Local hour (0-23) after which the previous day's digest is written */
+  hour: number;
+  /** Post scheduled digests to webhooks subscribed to digest.daily */
+  post_to_webhooks: boolean;
+}
+
+export interface DigestRun {
+  run_id: number;
+  agent_name: string;
+  project_path: string;
+  task: string;
+  status: string;
+}
+
+export interface DigestSummary {
+  day: string;
+  runs: DigestRun[];
+  runs_by_status: Record<string, number>;
+  sessions: number;
+  cost_usd: number;
+  tokens: number;
+  by_project: { project_path: string; cost_usd: number; tokens: number }[];
+  files: { path: string; runs: number; additions: number; deletions: number }[];
+}
+
+/**
+ * A stored daily digest of agent activity
+ */
+export interface DailyDigest {
+  day: string;
+  markdown: string;
+  summary: DigestSummary;
+  generated_at: string;
+  posted: boolean;
+}
+
+export interface BackupSettings {
+  /** Take backups on the schedule */
+  enabled: boolean;
+  /** Absolute path of the directory backups are written to */
+  directory: string | null;
+  interval_hours: number;
+  /** Write one compressed archive instead of a directory */
+  compress: boolean;
+  /** Encrypt the archive with the passphrase in the keychain; implies compress */
+  encrypt: boolean;
+  keep_last: number;
+  max_age_days: number | null;
+}
+
+/**
+ * A backup in the backup directory
+ */
+export interface BackupInfo {
+  path: string;
+  created_at: string;
+  size_bytes: number;
+  archive: boolean;
+  encrypted: boolean;
+}
+
+/**
+ * What restoring one backup entry changes
+ */
+export interface RestoreChange {
+  /** Path inside the backup, such as db/agents.db or claude/settings.json */
+  name: string;
+  target: string;
+  action: "create" | "overwrite" | "unchanged";
+  current_size: number | null;
+  backup_size: number;
+  /** For JSON files: keys as a.b, "added" when only the backup has them */
+  key_changes: { key: string; kind: "added" | "removed" | "changed" }[];
+  /** For the database: tables whose row counts differ */
+  table_changes: { table: string; current_rows: number; backup_rows: number }[];
+}
+
+export interface RestorePlan {
+  backup_path: string;
+  created_at: string;
+  app_version: string;
+  dry_run: boolean;
+  changes: RestoreChange[];
+  /** Database backup taken before the restore, for dbRestoreBackup */
+  pre_restore_backup: string | null;
+}
+
+export interface ConfigSyncSettings {
+  /** Absolute path of the local checkout; created when missing */
+  repo_path: string | null;
+  /** Pulled from and pushed to as origin; without one the repository is only local */
+  remote_url: string | null;
+  branch: string;
+}
+
+export interface ConfigSyncReport {
+  /** Commit of the local changes, or of a resolved merge */
+  committed: string | null;
+  pulled: boolean;
+  pushed: boolean;
+  added: number;
+  updated: number;
+  removed: number;
+  /** Files the merge couldn't reconcile; nothing was imported */
+  conflicts: string[];
+  skipped: string[];
+  push_error: string | null;
+}
+
+export interface ConfigSyncStatus {
+  configured: boolean;
+  last_synced_at: string | null;
+  last_commit: string | null;
+  /** Items added, changed or deleted locally since the last sync */
+  local_changes: number;
+  merging: boolean;
+  conflicts: string[];
+}
+
+export interface ConfigKeyConflict {
+  /** JSON pointer of the key, e.g. /permissions/allow */
+  key: string;
+  base: any | null;
+  /** The value edited in opcode; null when the key was removed */
+  local: any | null;
+  /** The value in the file now */
+  disk: any | null;
+}
+
+export interface ConfigMergeOutcome {
+  /** False while conflicts are unresolved; the file is left as it is */
+  written: boolean;
+  merged: any;
+  conflicts: ConfigKeyConflict[];
+}
+
+/**
+ * API client for interacting with the Rust backend
+ */
+export const api = {
+  // Cache for Claude installations
+  _claudeInstallationsCache: {
+    data: null as ClaudeInstallation[] | null,
+    timestamp: 0,
+    ttl: 5 * 60 * 1000, // 5 minutes cache
+  },
+
+  /**
+   * Gets the user's home directory path
+   * @returns Promise resolving to the home directory path
+   */
+  async getHomeDirectory(): Promise<string> {
+    try {
+      return await apiCall<string>("get_home_directory");
+    } catch (error) {
+      console.error("Failed to get home directory:", error);
+      return "/";
+    }
+  },
+
+  /**
+   * Lists all projects in the ~/.claude/projects directory
+   * @returns Promise resolving to an array of projects
+   */
+  async listProjects(): Promise<Project[]> {
+    try {
+      return await apiCall<Project[]>("list_projects");
+    } catch (error) {
+      console.error("Failed to list projects:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates a new project for the given directory path
+   * @param path - The directory path to create a project for
+   * @returns Promise resolving to the created project
+   */
+  async createProject(path: string): Promise<Project> {
+    try {
+      return await apiCall<Project>('create_project', { path });
+    } catch (error) {
+      console.error("Failed to create project:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Retrieves sessions for a specific project
+   * @param projectId - The ID of the project to retrieve sessions for
+   * @returns Promise resolving to an array of sessions
+   */
+  async getProjectSessions(projectId: string): Promise<Session[]> {
+    try {
+      return await apiCall<Session[]>('get_project_sessions', { projectId });
+    } catch (error) {
+      console.error("Failed to get project sessions:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Fetch list of agents from GitHub repository
+   * @returns Promise resolving to list of available agents on GitHub
+   */
+  async fetchGitHubAgents(): Promise<GitHubAgentFile[]> {
+    try {
+      return await apiCall<GitHubAgentFile[]>('fetch_github_agents');
+    } catch (error) {
+      console.error("Failed to fetch GitHub agents:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Fetch and preview a specific agent from GitHub
+   * @param downloadUrl - The download URL for the agent file
+   * @returns Promise resolving to the agent export data
+   */
+  async fetchGitHubAgentContent(downloadUrl: string): Promise<AgentExport> {
+    try {
+      return await apiCall<AgentExport>('fetch_github_agent_content', { downloadUrl });
+    } catch (error) {
+      console.error("Failed to fetch GitHub agent content:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Import an agent directly from GitHub
+   * @param downloadUrl - The download URL for the agent file
+   * @returns Promise resolving to the imported agent
+   */
+  async importAgentFromGitHub(downloadUrl: string): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('import_agent_from_github', { downloadUrl });
+    } catch (error) {
+      console.error("Failed to import agent from GitHub:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads the Claude settings file
+   * @returns Promise resolving to the settings object
+   */
+  async getClaudeSettings(): Promise<ClaudeSettings> {
+    try {
+      const result = await apiCall<{ data: ClaudeSettings }>("get_claude_settings");
+
+      
+      // The Rust backend returns ClaudeSettings { data: ... }
+      // We need to extract the data field
+      if (result && typeof result === 'object' && 'data' in result) {
+        return result.data;
+      }
+      
+      // If the result is already the settings object, return it
+      return result as ClaudeSettings;
+    } catch (error) {
+      console.error("Failed to get Claude settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Opens a new Claude Code session
+   * @param path - Optional path to open the session in
+   * @returns Promise resolving when the session is opened
+   */
+  async openNewSession(path?: string): Promise<string> {
+    try {
+      return await apiCall<string>("open_new_session", { path });
+    } catch (error) {
+      console.error("Failed to open new session:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads the CLAUDE.md system prompt file
+   * @returns Promise resolving to the system prompt content
+   */
+  async getSystemPrompt(): Promise<string> {
+    try {
+      return await apiCall<string>("get_system_prompt");
+    } catch (error) {
+      console.error("Failed to get system prompt:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Get project CLAUDE.md prompt content
+   * @param projectPath - Path to the project directory
+   * @returns Promise resolving to the project CLAUDE.md content
+   */
+  async getProjectPrompt(projectPath: string): Promise<string> {
+    try {
+      return await apiCall<string>("get_project_prompt", { projectPath });
+    } catch (error) {
+      console.error("Failed to get project prompt:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the current working directory
+   */
+  async getCurrentWorkingDirectory(): Promise<string> {
+    try {
+      return await apiCall<string>("get_current_working_directory");
+    } catch (error) {
+      console.error("Failed to get current working directory:", error);
+      throw error;
+    }
+  },
+
+  /**
    * Checks if Claude Code is installed and gets its version
    * @returns Promise resolving to the version status
    */
-  async checkClaudeVersion(): Promise<ClaudeVersionStatus> {
+  async checkClaudeVersion(): Promise<ClaudeVersionStatus> {
+    try {
+      return await apiCall<ClaudeVersionStatus>("check_claude_version");
+    } catch (error) {
+      console.error("Failed to check Claude version:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Inspects how the Claude CLI is authenticated and what may be wrong with it
+   */
+  async getAuthStatus(): Promise<AuthStatus> {
+    try {
+      return await apiCall<AuthStatus>("get_auth_status");
+    } catch (error) {
+      console.error("Failed to get auth status:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the CLAUDE.md system prompt file
+   * @param content - The new content for the system prompt
+   * @returns Promise resolving when the file is saved
+   */
+  async saveSystemPrompt(content: string): Promise<string> {
+    try {
+      return await apiCall<string>("save_system_prompt", { content });
+    } catch (error) {
+      console.error("Failed to save system prompt:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the Claude settings file
+   * @param settings - The settings object to save
+   * @returns Promise resolving when the settings are saved
+   */
+  async saveClaudeSettings(settings: ClaudeSettings): Promise<string> {
+    try {
+      return await apiCall<string>("save_claude_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save Claude settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Finds all CLAUDE.md files in a project directory
+   * @param projectPath - The absolute path to the project
+   * @returns Promise resolving to an array of CLAUDE.md files
+   */
+  async findClaudeMdFiles(projectPath: string): Promise<ClaudeMdFile[]> {
+    try {
+      return await apiCall<ClaudeMdFile[]>("find_claude_md_files", { projectPath });
+    } catch (error) {
+      console.error("Failed to find CLAUDE.md files:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads a specific CLAUDE.md file
+   * @param filePath - The absolute path to the file
+   * @returns Promise resolving to the file content
+   */
+  async readClaudeMdFile(filePath: string): Promise<string> {
+    try {
+      return await apiCall<string>("read_claude_md_file", { filePath });
+    } catch (error) {
+      console.error("Failed to read CLAUDE.md file:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Read text file content for preview
+   */
+  async readTextFile(filePath: string): Promise<string> {
+    try {
+      return await apiCall<string>("read_text_file", { filePath });
+    } catch (error) {
+      console.error("Failed to read text file:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves a specific CLAUDE.md file
+   * @param filePath - The absolute path to the file
+   * @param content - The new content for the file
+   * @returns Promise resolving when the file is saved
+   */
+  async saveClaudeMdFile(filePath: string, content: string): Promise<string> {
+    try {
+      return await apiCall<string>("save_claude_md_file", { filePath, content });
+    } catch (error) {
+      console.error("Failed to save CLAUDE.md file:", error);
+      throw error;
+    }
+  },
+
+  // Agent API methods
+  
+  /**
+   * Lists all CC agents
+   * @returns Promise resolving to an array of agents
+   */
+  async listAgents(): Promise<Agent[]> {
+    try {
+      return await apiCall<Agent[]>('list_agents');
+    } catch (error) {
+      console.error("Failed to list agents:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates a new agent
+   * @param name - The agent name
+   * @param icon - The icon identifier
+   * @param system_prompt - The system prompt for the agent
+   * @param default_task - Optional default task
+   * @param model - Optional model (defaults to 'sonnet')
+   * @param hooks - Optional hooks configuration as JSON string
+   * @param thinking - Optional extended thinking for the agent's runs
+   * @param toolRules - Optional allowed and disallowed tools for the agent's runs
+   * @returns Promise resolving to the created agent
+   */
+  async createAgent(
+    name: string, 
+    icon: string, 
+    system_prompt: string, 
+    default_task?: string, 
+    model?: string,
+    hooks?: string,
+    thinking?: RunThinking,
+    toolRules?: ToolRules
+  ): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('create_agent', { 
+        name, 
+        icon, 
+        systemPrompt: system_prompt,
+        defaultTask: default_task,
+        model,
+        hooks,
+        thinking,
+        toolRules
+      });
+    } catch (error) {
+      console.error("Failed to create agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Updates an existing agent
+   * @param id - The agent ID
+   * @param name - The updated name
+   * @param icon - The updated icon
+   * @param system_prompt - The updated system prompt
+   * @param default_task - Optional default task
+   * @param model - Optional model
+   * @param hooks - Optional hooks configuration as JSON string
+   * @param thinking - Optional extended thinking for the agent's runs
+   * @param toolRules - Optional allowed and disallowed tools for the agent's runs
+   * @returns Promise resolving to the updated agent
+   */
+  async updateAgent(
+    id: number, 
+    name: string, 
+    icon: string, 
+    system_prompt: string, 
+    default_task?: string, 
+    model?: string,
+    hooks?: string,
+    thinking?: RunThinking,
+    toolRules?: ToolRules
+  ): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('update_agent', { 
+        id, 
+        name, 
+        icon, 
+        systemPrompt: system_prompt,
+        defaultTask: default_task,
+        model,
+        hooks,
+        thinking,
+        toolRules
+      });
+    } catch (error) {
+      console.error("Failed to update agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes an agent
+   * @param id - The agent ID to delete
+   * @returns Promise resolving when the agent is deleted
+   */
+  async deleteAgent(id: number): Promise<void> {
+    try {
+      return await apiCall('delete_agent', { id });
+    } catch (error) {
+      console.error("Failed to delete agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets a single agent by ID
+   * @param id - The agent ID
+   * @returns Promise resolving to the agent
+   */
+  async getAgent(id: number): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('get_agent', { id });
+    } catch (error) {
+      console.error("Failed to get agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Exports a single agent to JSON format
+   * @param id - The agent ID to export
+   * @returns Promise resolving to the JSON string
+   */
+  async exportAgent(id: number): Promise<string> {
+    try {
+      return await apiCall<string>('export_agent', { id });
+    } catch (error) {
+      console.error("Failed to export agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports an agent from JSON data
+   * @param jsonData - The JSON string containing the agent export
+   * @returns Promise resolving to the imported agent
+   */
+  async importAgent(jsonData: string): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('import_agent', { jsonData });
+    } catch (error) {
+      console.error("Failed to import agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports an agent from a file
+   * @param filePath - The path to the JSON file
+   * @returns Promise resolving to the imported agent
+   */
+  async importAgentFromFile(filePath: string): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('import_agent_from_file', { filePath });
+    } catch (error) {
+      console.error("Failed to import agent from file:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Executes an agent
+   * @param agentId - The agent ID to execute
+   * @param projectPath - The project path to run the agent in
+   * @param task - The task description
+   * @param model - Optional model override
+   * @param verbosity - Optional diagnostics (--debug, MCP_LOG_LEVEL) for the run
+   * @param guards - Optional turn, token and cost limits for the run
+   * @returns Promise resolving to the run ID when execution starts
+   */
+  async executeAgent(agentId: number, projectPath: string, task: string, model?: string, verbosity?: RunVerbosity, providerProfileId?: number, guards?: RunGuards): Promise<number> {
+    try {
+      return await apiCall<number>('execute_agent', { agentId, projectPath, task, model, verbosity, providerProfileId, guards });
+    } catch (error) {
+      console.error("Failed to execute agent:", error);
+      // Return a sentinel value to indicate error
+      throw new Error(`Failed to execute agent: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Runs a finished agent run's task again with debug logging for the CLI and its MCP servers
+   * @param runId - The run to repeat
+   * @returns Promise resolving to the new run ID
+   */
+  async rerunAgentWithDiagnostics(runId: number): Promise<number> {
+    return apiCall<number>('rerun_agent_with_diagnostics', { runId });
+  },
+
+  /**
+   * Whether an agent's runs ask for approval before using tools
+   * @param agentId - The agent
+   */
+  async getAgentApproval(agentId: number): Promise<boolean> {
+    return apiCall<boolean>('get_agent_approval', { agentId });
+  },
+
+  /**
+   * Turns tool approval on or off for an agent's runs; requests arrive as
+   * `run:permission-request` events and are answered with respondPermission
+   * @param agentId - The agent
+   * @param enabled - Whether runs wait for approval
+   */
+  async setAgentApproval(agentId: number, enabled: boolean): Promise<boolean> {
+    return apiCall<boolean>('set_agent_approval', { agentId, enabled });
+  },
+
+  /**
+   * Lists agent runs without metrics (basic info only)
+   * @param agentId - Optional agent ID to filter runs
+   * @returns Promise resolving to an array of agent runs
+   */
+  async listAgentRuns(agentId?: number): Promise<AgentRunWithMetrics[]> {
+    try {
+      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs', { agentId });
+    } catch (error) {
+      console.error("Failed to list agent runs:", error);
+      // Return empty array instead of throwing to prevent UI crashes
+      return [];
+    }
+  },
+
+  /**
+   * Lists agent runs with metrics (includes token counts and duration)
+   * @param agentId - Optional agent ID to filter runs
+   * @returns Promise resolving to an array of agent runs with metrics
+   */
+  async listAgentRunsWithMetrics(agentId?: number): Promise<AgentRunWithMetrics[]> {
+    try {
+      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs_with_metrics', { agentId });
+    } catch (error) {
+      console.error("Failed to list agent runs with metrics:", error);
+      // Return empty array instead of throwing to prevent UI crashes
+      return [];
+    }
+  },
+
+  /**
+   * Gets a single agent run by ID with metrics
+   * @param id - The run ID
+   * @returns Promise resolving to the agent run with metrics
+   */
+  async getAgentRun(id: number): Promise<AgentRunWithMetrics> {
+    try {
+      return await apiCall<AgentRunWithMetrics>('get_agent_run', { id });
+    } catch (error) {
+      console.error("Failed to get agent run:", error);
+      throw new Error(`Failed to get agent run: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Gets a single agent run by ID with real-time metrics from JSONL
+   * @param id - The run ID
+   * @returns Promise resolving to the agent run with metrics
+   */
+  async getAgentRunWithRealTimeMetrics(id: number): Promise<AgentRunWithMetrics> {
+    try {
+      return await apiCall<AgentRunWithMetrics>('get_agent_run_with_real_time_metrics', { id });
+    } catch (error) {
+      console.error("Failed to get agent run with real-time metrics:", error);
+      throw new Error(`Failed to get agent run with real-time metrics: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Lists all currently running agent sessions
+   * @returns Promise resolving to list of running agent sessions
+   */
+  async listRunningAgentSessions(): Promise<AgentRun[]> {
+    try {
+      return await apiCall<AgentRun[]>('list_running_sessions');
+    } catch (error) {
+      console.error("Failed to list running agent sessions:", error);
+      throw new Error(`Failed to list running agent sessions: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Kills a running agent session
+   * @param runId - The run ID to kill
+   * @returns Promise resolving to whether the session was successfully killed
+   */
+  async killAgentSession(runId: number): Promise<boolean> {
+    try {
+      return await apiCall<boolean>('kill_agent_session', { runId });
+    } catch (error) {
+      console.error("Failed to kill agent session:", error);
+      throw new Error(`Failed to kill agent session: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Gets the status of a specific agent session
+   * @param runId - The run ID to check
+   * @returns Promise resolving to the session status or null if not found
+   */
+  async getSessionStatus(runId: number): Promise<string | null> {
+    try {
+      return await apiCall<string | null>('get_session_status', { runId });
+    } catch (error) {
+      console.error("Failed to get session status:", error);
+      throw new Error(`Failed to get session status: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Cleanup finished processes and update their status
+   * @returns Promise resolving to list of run IDs that were cleaned up
+   */
+  async cleanupFinishedProcesses(): Promise<number[]> {
+    try {
+      return await apiCall<number[]>('cleanup_finished_processes');
+    } catch (error) {
+      console.error("Failed to cleanup finished processes:", error);
+      throw new Error(`Failed to cleanup finished processes: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Get real-time output for a running session (with live output fallback)
+   * @param runId - The run ID to get output for
+   * @returns Promise resolving to the current session output (JSONL format)
+   */
+  async getSessionOutput(runId: number): Promise<string> {
+    try {
+      return await apiCall<string>('get_session_output', { runId });
+    } catch (error) {
+      console.error("Failed to get session output:", error);
+      throw new Error(`Failed to get session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Get live output directly from process stdout buffer
+   * @param runId - The run ID to get live output for
+   * @param stream - Stream to read; defaults to stdout
+   * @returns Promise resolving to the current live output
+   */
+  async getLiveSessionOutput(runId: number, stream?: OutputStream): Promise<string> {
+    try {
+      return await apiCall<string>('get_live_session_output', { runId, stream });
+    } catch (error) {
+      console.error("Failed to get live session output:", error);
+      throw new Error(`Failed to get live session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Get live output lines tagged with the stream they came from
+   * @param runId - The run ID to get live output for
+   * @param stream - Only return lines from this stream
+   */
+  async getLiveOutput(runId: number, stream?: OutputStream): Promise<StreamLine[]> {
+    try {
+      return await apiCall<StreamLine[]>('get_live_output', { runId, stream });
+    } catch (error) {
+      console.error("Failed to get live output:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Start streaming real-time output for a running session
+   * @param runId - The run ID to stream output for
+   * @returns Promise that resolves when streaming starts
+   */
+  async streamSessionOutput(runId: number): Promise<void> {
+    try {
+      return await apiCall<void>('stream_session_output', { runId });
+    } catch (error) {
+      console.error("Failed to start streaming session output:", error);
+      throw new Error(`Failed to start streaming session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Loads the JSONL history for a specific session
+   */
+  async loadSessionHistory(sessionId: string, projectId: string): Promise<any[]> {
+    return apiCall("load_session_history", { sessionId, projectId });
+  },
+
+  /**
+   * Loads the JSONL history for a specific agent session
+   * Similar to loadSessionHistory but searches across all project directories
+   * @param sessionId - The session ID (UUID)
+   * @returns Promise resolving to array of session messages
+   */
+  async loadAgentSessionHistory(sessionId: string): Promise<any[]> {
+    try {
+      return await apiCall<any[]>('load_agent_session_history', { sessionId });
+    } catch (error) {
+      console.error("Failed to load agent session history:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Executes a new interactive Claude Code session with streaming output
+   */
+  async executeClaudeCode(projectPath: string, prompt: string, model: string, verbosity?: RunVerbosity, providerProfileId?: number, thinking?: RunThinking, interactivePermissions?: boolean, toolRules?: ToolRules, guards?: RunGuards): Promise<void> {
+    return apiCall("execute_claude_code", { projectPath, prompt, model, verbosity, providerProfileId, thinking, interactivePermissions, toolRules, guards });
+  },
+
+  /**
+   * Continues an existing Claude Code conversation with streaming output
+   */
+  async continueClaudeCode(projectPath: string, prompt: string, model: string, verbosity?: RunVerbosity, providerProfileId?: number, thinking?: RunThinking, interactivePermissions?: boolean, toolRules?: ToolRules, guards?: RunGuards): Promise<void> {
+    return apiCall("continue_claude_code", { projectPath, prompt, model, verbosity, providerProfileId, thinking, interactivePermissions, toolRules, guards });
+  },
+
+  /**
+   * Resumes an existing Claude Code session by ID with streaming output
+   */
+  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string, verbosity?: RunVerbosity, providerProfileId?: number, thinking?: RunThinking, interactivePermissions?: boolean, toolRules?: ToolRules, guards?: RunGuards): Promise<void> {
+    return apiCall("resume_claude_code", { projectPath, sessionId, prompt, model, verbosity, providerProfileId, thinking, interactivePermissions, toolRules, guards });
+  },
+
+  /**
+   * Cancels the currently running Claude Code execution
+   * @param sessionId - Optional session ID to cancel a specific session
+   */
+  async cancelClaudeExecution(sessionId?: string): Promise<void> {
+    return apiCall("cancel_claude_execution", { sessionId });
+  },
+
+  /**
+   * Interrupts a running Claude session
+   * @param sessionId - The session to interrupt
+   * @param force - Kill the session and its subprocesses immediately instead of interrupting gracefully
+   * @returns Promise resolving to whether the session was found and signalled
+   */
+  async interruptSession(sessionId: string, force?: boolean): Promise<boolean> {
+    return apiCall("interrupt_session", { sessionId, force });
+  },
+
+  /**
+   * Answers a tool permission request of a session started with interactive permissions
+   * @param runId - The run that asked
+   * @param requestId - The request_id of the `run:permission-request` event
+   * @param allow - Whether the tool may run
+   * @param message - Reason given to Claude when denying
+   */
+  async respondPermission(runId: number, requestId: string, allow: boolean, message?: string): Promise<void> {
+    return apiCall("respond_permission", { runId, requestId, allow, message });
+  },
+
+  /**
+   * Lists permission requests still waiting for an answer
+   * @param runId - Only those of this run
+   */
+  async listPendingPermissions(runId?: number): Promise<PermissionRequest[]> {
+    return apiCall("list_pending_permissions", { runId });
+  },
+
+  /**
+   * Lists all currently running Claude sessions
+   * @returns Promise resolving to list of running Claude sessions
+   */
+  async listRunningClaudeSessions(): Promise<any[]> {
+    return apiCall("list_running_claude_sessions");
+  },
+
+  /**
+   * Gets live output from a Claude session
+   * @param sessionId - The session ID to get output for
+   * @returns Promise resolving to the current live output
+   */
+  async getClaudeSessionOutput(sessionId: string): Promise<string> {
+    return apiCall("get_claude_session_output", { sessionId });
+  },
+
+  /**
+   * Sends a message to Claude and returns the response (simplified API for terminal session)
+   */
+  async sendClaudeMessage(params: {
+    prompt: string;
+    model: string;
+    project_path?: string;
+    session_id?: string;
+  }): Promise<{
+    response: string;
+    session_id?: string;
+    usage?: { input_tokens: number; output_tokens: number };
+    tool_results?: Array<{ name: string; content: string; success: boolean }>;
+  }> {
+    return apiCall("send_claude_message", params);
+  },
+
+  /**
+   * Lists files and directories in a given path
+   */
+  async listDirectoryContents(directoryPath: string): Promise<FileEntry[]> {
+    return apiCall("list_directory_contents", { directoryPath });
+  },
+
+  /**
+   * Searches for files and directories matching a pattern
+   */
+  async searchFiles(basePath: string, query: string): Promise<FileEntry[]> {
+    return apiCall("search_files", { basePath, query });
+  },
+
+  /**
+   * Recursively lists all files and directories in a project root
+   */
+  async listProjectFiles(projectPath: string): Promise<FileEntry[]> {
+    return apiCall("list_project_files", { projectPath });
+  },
+
+  /**
+   * Starts a local HTTP server to serve project files
+   * @returns Promise resolving to server URL and port
+   */
+  async startFileServer(projectPath: string): Promise<{ url: string; port: number; already_running?: boolean }> {
+    return apiCall("start_file_server", { projectPath });
+  },
+
+  /**
+   * Gets the current file server URL if running
+   * @returns Promise resolving to server URL or null
+   */
+  async getFileServerUrl(): Promise<string | null> {
+    return apiCall("get_file_server_url", {});
+  },
+
+  /**
+   * Gets overall usage statistics
+   * @returns Promise resolving to usage statistics
+   */
+  async getUsageStats(): Promise<UsageStats> {
+    try {
+      return await apiCall<UsageStats>("get_usage_stats");
+    } catch (error) {
+      console.error("Failed to get usage stats:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets usage statistics filtered by date range
+   * @param startDate - Start date (ISO format)
+   * @param endDate - End date (ISO format)
+   * @returns Promise resolving to usage statistics
+   */
+  async getUsageByDateRange(startDate: string, endDate: string): Promise<UsageStats> {
+    try {
+      return await apiCall<UsageStats>("get_usage_by_date_range", { startDate, endDate });
+    } catch (error) {
+      console.error("Failed to get usage by date range:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets usage statistics grouped by session
+   * @param since - Optional start date (YYYYMMDD)
+   * @param until - Optional end date (YYYYMMDD)
+   * @param order - Optional sort order ('asc' or 'desc')
+   * @returns Promise resolving to an array of session usage data
+   */
+  async getSessionStats(
+    since?: string,
+    until?: string,
+    order?: "asc" | "desc"
+  ): Promise<ProjectUsage[]> {
+    try {
+      return await apiCall<ProjectUsage[]>("get_session_stats", {
+        since,
+        until,
+        order,
+      });
+    } catch (error) {
+      console.error("Failed to get session stats:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets detailed usage entries with optional filtering
+   * @param limit - Optional limit for number of entries
+   * @returns Promise resolving to array of usage entries
+   */
+  async getUsageDetails(limit?: number): Promise<UsageEntry[]> {
+    try {
+      return await apiCall<UsageEntry[]>("get_usage_details", { limit });
+    } catch (error) {
+      console.error("Failed to get usage details:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates a checkpoint for the current session state
+   */
+  async createCheckpoint(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    messageIndex?: number,
+    description?: string
+  ): Promise<CheckpointResult> {
+    return apiCall("create_checkpoint", {
+      sessionId,
+      projectId,
+      projectPath,
+      messageIndex,
+      description
+    });
+  },
+
+  /**
+   * Restores a session to a specific checkpoint
+   */
+  async restoreCheckpoint(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string
+  ): Promise<CheckpointResult> {
+    return apiCall("restore_checkpoint", {
+      checkpointId,
+      sessionId,
+      projectId,
+      projectPath
+    });
+  },
+
+  /**
+   * Lists all checkpoints for a session
+   */
+  async listCheckpoints(
+    sessionId: string,
+    projectId: string,
+    projectPath: string
+  ): Promise<Checkpoint[]> {
+    return apiCall("list_checkpoints", {
+      sessionId,
+      projectId,
+      projectPath
+    });
+  },
+
+  /**
+   * Forks a new timeline branch from a checkpoint
+   */
+  async forkFromCheckpoint(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    newSessionId: string,
+    description?: string
+  ): Promise<CheckpointResult> {
+    return apiCall("fork_from_checkpoint", {
+      checkpointId,
+      sessionId,
+      projectId,
+      projectPath,
+      newSessionId,
+      description
+    });
+  },
+
+  /**
+   * Gets the timeline for a session
+   */
+  async getSessionTimeline(
+    sessionId: string,
+    projectId: string,
+    projectPath: string
+  ): Promise<SessionTimeline> {
+    return apiCall("get_session_timeline", {
+      sessionId,
+      projectId,
+      projectPath
+    });
+  },
+
+  /**
+   * Updates checkpoint settings for a session
+   */
+  async updateCheckpointSettings(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    autoCheckpointEnabled: boolean,
+    checkpointStrategy: CheckpointStrategy
+  ): Promise<void> {
+    return apiCall("update_checkpoint_settings", {
+      sessionId,
+      projectId,
+      projectPath,
+      autoCheckpointEnabled,
+      checkpointStrategy
+    });
+  },
+
+  /**
+   * Gets diff between two checkpoints
+   */
+  async getCheckpointDiff(
+    fromCheckpointId: string,
+    toCheckpointId: string,
+    sessionId: string,
+    projectId: string
+  ): Promise<CheckpointDiff> {
+    try {
+      return await apiCall<CheckpointDiff>("get_checkpoint_diff", {
+        fromCheckpointId,
+        toCheckpointId,
+        sessionId,
+        projectId
+      });
+    } catch (error) {
+      console.error("Failed to get checkpoint diff:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Tracks a message for checkpointing
+   */
+  async trackCheckpointMessage(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    message: string
+  ): Promise<void> {
+    try {
+      await apiCall("track_checkpoint_message", {
+        sessionId,
+        projectId,
+        projectPath,
+        message
+      });
+    } catch (error) {
+      console.error("Failed to track checkpoint message:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Checks if auto-checkpoint should be triggered
+   */
+  async checkAutoCheckpoint(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    message: string
+  ): Promise<boolean> {
+    try {
+      return await apiCall<boolean>("check_auto_checkpoint", {
+        sessionId,
+        projectId,
+        projectPath,
+        message
+      });
+    } catch (error) {
+      console.error("Failed to check auto checkpoint:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Triggers cleanup of old checkpoints
+   */
+  async cleanupOldCheckpoints(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    keepCount: number
+  ): Promise<number> {
+    try {
+      return await apiCall<number>("cleanup_old_checkpoints", {
+        sessionId,
+        projectId,
+        projectPath,
+        keepCount
+      });
+    } catch (error) {
+      console.error("Failed to cleanup old checkpoints:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets checkpoint settings for a session
+   */
+  async getCheckpointSettings(
+    sessionId: string,
+    projectId: string,
+    projectPath: string
+  ): Promise<{
+    auto_checkpoint_enabled: boolean;
+    checkpoint_strategy: CheckpointStrategy;
+    total_checkpoints: number;
+    current_checkpoint_id?: string;
+  }> {
+    try {
+      return await apiCall("get_checkpoint_settings", {
+        sessionId,
+        projectId,
+        projectPath
+      });
+    } catch (error) {
+      console.error("Failed to get checkpoint settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Clears checkpoint manager for a session (cleanup on session end)
+   */
+  async clearCheckpointManager(sessionId: string): Promise<void> {
+    try {
+      await apiCall("clear_checkpoint_manager", { sessionId });
+    } catch (error) {
+      console.error("Failed to clear checkpoint manager:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Tracks a batch of messages for a session for checkpointing
+   */
+  trackSessionMessages: (
+    sessionId: string, 
+    projectId: string, 
+    projectPath: string, 
+    messages: string[]
+  ): Promise<void> =>
+    apiCall("track_session_messages", { sessionId, projectId, projectPath, messages }),
+
+  /**
+   * Adds a new MCP server
+   */
+  async mcpAdd(
+    name: string,
+    transport: string,
+    command?: string,
+    args: string[] = [],
+    env: Record<string, string> = {},
+    url?: string,
+    scope: string = "local",
+    headers: Record<string, string> = {},
+    projectPath?: string
+  ): Promise<AddServerResult> {
+    try {
+      return await apiCall<AddServerResult>("mcp_add", {
+        name,
+        transport,
+        command,
+        args,
+        env,
+        url,
+        scope,
+        headers,
+        projectPath
+      });
+    } catch (error) {
+      console.error("Failed to add MCP server:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists all configured MCP servers
+   * @param operationId - Optional id for aborting the call with cancelOperation
+   */
+  async mcpList(operationId?: string): Promise<MCPServer[]> {
+    try {
+
+      const result = await apiCall<MCPServer[]>("mcp_list", { operationId });
+
+      return result;
+    } catch (error) {
+      console.error("API: Failed to list MCP servers:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets details for a specific MCP server
+   */
+  async mcpGet(name: string, operationId?: string): Promise<MCPServer> {
+    try {
+      return await apiCall<MCPServer>("mcp_get", { name, operationId });
+    } catch (error) {
+      console.error("Failed to get MCP server:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Cancels a running operation started with an operation id
+   * @returns true if an operation with that id was running
+   */
+  async cancelOperation(id: string): Promise<boolean> {
+    try {
+      return await apiCall<boolean>("cancel_operation", { id });
+    } catch (error) {
+      console.error("Failed to cancel operation:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Removes an MCP server
+   */
+  async mcpRemove(name: string): Promise<string> {
+    try {
+      return await apiCall<string>("mcp_remove", { name });
+    } catch (error) {
+      console.error("Failed to remove MCP server:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Updates an existing MCP server (remove + add)
+   */
+  async mcpUpdate(
+    oldName: string,
+    name: string,
+    transport: string,
+    command?: string,
+    args: string[] = [],
+    env: Record<string, string> = {},
+    url?: string,
+    scope: string = "local",
+    headers: Record<string, string> = {}
+  ): Promise<AddServerResult> {
+    try {
+      return await apiCall<AddServerResult>("mcp_update", {
+        old_name: oldName,
+        name,
+        transport,
+        command,
+        args,
+        env,
+        url,
+        scope,
+        headers,
+      });
+    } catch (error) {
+      console.error("Failed to update MCP server:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Adds an MCP server from JSON configuration
+   */
+  async mcpAddJson(name: string, jsonConfig: string, scope: string = "local"): Promise<AddServerResult> {
+    try {
+      return await apiCall<AddServerResult>("mcp_add_json", { name, jsonConfig, scope });
+    } catch (error) {
+      console.error("Failed to add MCP server from JSON:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Starts Claude Code as an MCP server
+   */
+  async mcpServe(): Promise<string> {
+    try {
+      return await apiCall<string>("mcp_serve");
+    } catch (error) {
+      console.error("Failed to start MCP server:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Tests connection to an MCP server
+   */
+  async mcpTestConnection(name: string): Promise<string> {
+    try {
+      return await apiCall<string>("mcp_test_connection", { name });
+    } catch (error) {
+      console.error("Failed to test MCP connection:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Resets project-scoped server approval choices
+   */
+  async mcpResetProjectChoices(): Promise<string> {
+    try {
+      return await apiCall<string>("mcp_reset_project_choices");
+    } catch (error) {
+      console.error("Failed to reset project choices:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the status of MCP servers
+   */
+  async mcpGetServerStatus(): Promise<Record<string, ServerStatus>> {
+    try {
+      return await apiCall<Record<string, ServerStatus>>("mcp_get_server_status");
+    } catch (error) {
+      console.error("Failed to get server status:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the MCP configuration file paths
+   */
+  async mcpGetConfigPaths(projectPath?: string): Promise<MCPConfigPaths> {
+    try {
+      return await apiCall<MCPConfigPaths>("mcp_get_config_paths", { project_path: projectPath });
+    } catch (error) {
+      console.error("Failed to get MCP config paths:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads .mcp.json from the current project
+   */
+  async mcpReadProjectConfig(projectPath: string): Promise<MCPProjectConfig> {
+    try {
+      return await apiCall<MCPProjectConfig>("mcp_read_project_config", { projectPath });
+    } catch (error) {
+      console.error("Failed to read project MCP config:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves .mcp.json to the current project
+   */
+  async mcpSaveProjectConfig(projectPath: string, config: MCPProjectConfig): Promise<string> {
+    try {
+      return await apiCall<string>("mcp_save_project_config", { projectPath, config });
+    } catch (error) {
+      console.error("Failed to save project MCP config:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Get the stored Claude binary path from settings
+   * @returns Promise resolving to the path if set, null otherwise
+   */
+  async getClaudeBinaryPath(): Promise<string | null> {
+    try {
+      return await apiCall<string | null>("get_claude_binary_path");
+    } catch (error) {
+      console.error("Failed to get Claude binary path:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Set the Claude binary path in settings
+   * @param path - The absolute path to the Claude binary
+   * @returns Promise resolving when the path is saved
+   */
+  async setClaudeBinaryPath(path: string): Promise<void> {
+    try {
+      return await apiCall<void>("set_claude_binary_path", { path });
+    } catch (error) {
+      console.error("Failed to set Claude binary path:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * List all available Claude installations on the system
+   * Uses caching to avoid repeated checks (cache expires after 5 minutes)
+   * @returns Promise resolving to an array of Claude installations
+   */
+  async listClaudeInstallations(forceRefresh = false): Promise<ClaudeInstallation[]> {
+    try {
+      const now = Date.now();
+
+      // Return cached data if it's still valid and not forcing refresh
+      if (!forceRefresh && this._claudeInstallationsCache.data &&
+          (now - this._claudeInstallationsCache.timestamp) < this._claudeInstallationsCache.ttl) {
+        console.log("Using cached Claude installations");
+        return this._claudeInstallationsCache.data;
+      }
+
+      // Fetch fresh data
+      console.log("Fetching fresh Claude installations");
+      const installations = await apiCall<ClaudeInstallation[]>("list_claude_installations");
+
+      // Update cache
+      this._claudeInstallationsCache = {
+        data: installations,
+        timestamp: now,
+        ttl: this._claudeInstallationsCache.ttl,
+      };
+
+      return installations;
+    } catch (error) {
+      console.error("Failed to list Claude installations:", error);
+      throw error;
+    }
+  },
+
+  // Storage API methods
+
+  /**
+   * Lists all tables in the SQLite database
+   * @returns Promise resolving to an array of table information
+   */
+  async storageListTables(): Promise<any[]> {
+    try {
+      return await apiCall<any[]>("storage_list_tables");
+    } catch (error) {
+      console.error("Failed to list tables:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads table data with pagination
+   * @param tableName - Name of the table to read
+   * @param page - Page number (1-indexed)
+   * @param pageSize - Number of rows per page
+   * @param searchQuery - Optional search query
+   * @returns Promise resolving to table data with pagination info
+   */
+  async storageReadTable(
+    tableName: string,
+    page: number,
+    pageSize: number,
+    searchQuery?: string
+  ): Promise<any> {
+    try {
+      return await apiCall<any>("storage_read_table", {
+        tableName,
+        page,
+        pageSize,
+        searchQuery,
+      });
+    } catch (error) {
+      console.error("Failed to read table:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Updates a row in a table
+   * @param tableName - Name of the table
+   * @param primaryKeyValues - Map of primary key column names to values
+   * @param updates - Map of column names to new values
+   * @returns Promise resolving when the row is updated
+   */
+  async storageUpdateRow(
+    tableName: string,
+    primaryKeyValues: Record<string, any>,
+    updates: Record<string, any>
+  ): Promise<void> {
+    try {
+      return await apiCall<void>("storage_update_row", {
+        tableName,
+        primaryKeyValues,
+        updates,
+      });
+    } catch (error) {
+      console.error("Failed to update row:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes a row from a table
+   * @param tableName - Name of the table
+   * @param primaryKeyValues - Map of primary key column names to values
+   * @returns Promise resolving when the row is deleted
+   */
+  async storageDeleteRow(
+    tableName: string,
+    primaryKeyValues: Record<string, any>
+  ): Promise<void> {
+    try {
+      return await apiCall<void>("storage_delete_row", {
+        tableName,
+        primaryKeyValues,
+      });
+    } catch (error) {
+      console.error("Failed to delete row:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Inserts a new row into a table
+   * @param tableName - Name of the table
+   * @param values - Map of column names to values
+   * @returns Promise resolving to the last insert row ID
+   */
+  async storageInsertRow(
+    tableName: string,
+    values: Record<string, any>
+  ): Promise<number> {
+    try {
+      return await apiCall<number>("storage_insert_row", {
+        tableName,
+        values,
+      });
+    } catch (error) {
+      console.error("Failed to insert row:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Executes a raw SQL query
+   * @param query - SQL query string
+   * @returns Promise resolving to query result
+   */
+  async storageExecuteSql(query: string): Promise<any> {
+    try {
+      return await apiCall<any>("storage_execute_sql", { query });
+    } catch (error) {
+      console.error("Failed to execute SQL:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Resets the entire database
+   * @returns Promise resolving when the database is reset
+   */
+  async storageResetDatabase(): Promise<void> {
+    try {
+      return await apiCall<void>("storage_reset_database");
+    } catch (error) {
+      console.error("Failed to reset database:", error);
+      throw error;
+    }
+  },
+
+  // Theme settings helpers
+
+  /**
+   * Gets a setting from the app_settings table
+   * @param key - The setting key to retrieve
+   * @returns Promise resolving to the setting value or null if not found
+   */
+  async getSetting(key: string): Promise<string | null> {
+    try {
+      // Fast path: check localStorage mirror to avoid startup flicker
+      if (typeof window !== 'undefined' && 'localStorage' in window) {
+        const cached = window.localStorage.getItem(`app_setting:${key}`);
+        if (cached !== null) {
+          return cached;
+        }
+      }
+      // Use storageReadTable to safely query the app_settings table
+      const result = await this.storageReadTable('app_settings', 1, 1000);
+      const setting = result?.data?.find((row: any) => row.key === key);
+      return setting?.value || null;
+    } catch (error) {
+      console.error(`Failed to get setting ${key}:`, error);
+      return null;
+    }
+  },
+
+  /**
+   * Saves a setting to the app_settings table (insert or update)
+   * @param key - The setting key
+   * @param value - The setting value
+   * @returns Promise resolving when the setting is saved
+   */
+  async saveSetting(key: string, value: string): Promise<void> {
+    try {
+      // Mirror to localStorage for instant availability on next startup
+      if (typeof window !== 'undefined' && 'localStorage' in window) {
+        try {
+          window.localStorage.setItem(`app_setting:${key}`, value);
+        } catch (_ignore) {
+          // best-effort; continue to persist in DB
+        }
+      }
+      // Try to update first
+      try {
+        await this.storageUpdateRow(
+          'app_settings',
+          { key },
+          { value }
+        );
+      } catch (updateError) {
+        // If update fails (row doesn't exist), insert new row
+        await this.storageInsertRow('app_settings', { key, value });
+      }
+    } catch (error) {
+      console.error(`Failed to save setting ${key}:`, error);
+      throw error;
+    }
+  },
+
+  /**
+   * Get hooks configuration for a specific scope
+   * @param scope - The configuration scope: 'user', 'project', or 'local'
+   * @param projectPath - Project path (required for project and local scopes)
+   * @returns Promise resolving to the hooks configuration
+   */
+  async getHooksConfig(scope: 'user' | 'project' | 'local', projectPath?: string): Promise<HooksConfiguration> {
+    try {
+      return await apiCall<HooksConfiguration>("get_hooks_config", { scope, projectPath });
+    } catch (error) {
+      console.error("Failed to get hooks config:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Update hooks configuration for a specific scope
+   * @param scope - The configuration scope: 'user', 'project', or 'local'
+   * @param hooks - The hooks configuration to save
+   * @param projectPath - Project path (required for project and local scopes)
+   * @returns Promise resolving to success message
+   */
+  async updateHooksConfig(
+    scope: 'user' | 'project' | 'local',
+    hooks: HooksConfiguration,
+    projectPath?: string
+  ): Promise<string> {
+    try {
+      return await apiCall<string>("update_hooks_config", { scope, projectPath, hooks });
+    } catch (error) {
+      console.error("Failed to update hooks config:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Validate a hook command syntax
+   * @param command - The shell command to validate
+   * @returns Promise resolving to validation result
+   */
+  async validateHookCommand(command: string): Promise<{ valid: boolean; message: string }> {
+    try {
+      return await apiCall<{ valid: boolean; message: string }>("validate_hook_command", { command });
+    } catch (error) {
+      console.error("Failed to validate hook command:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Get merged hooks configuration (respecting priority)
+   * @param projectPath - The project path
+   * @returns Promise resolving to merged hooks configuration
+   */
+  async getMergedHooksConfig(projectPath: string): Promise<HooksConfiguration> {
+    try {
+      const [userHooks, projectHooks, localHooks] = await Promise.all([
+        this.getHooksConfig('user'),
+        this.getHooksConfig('project', projectPath),
+        this.getHooksConfig('local', projectPath)
+      ]);
+
+      // Use HooksManager for merging
+      return HooksManager.mergeConfigs(userHooks, projectHooks, localHooks);
+    } catch (error) {
+      console.error("Failed to get merged hooks config:", error);
+      throw error;
+    }
+  },
+
+  // Slash Commands API methods
+
+  /**
+   * Lists all available slash commands
+   * @param projectPath - Optional project path to include project-specific commands
+   * @returns Promise resolving to array of slash commands
+   */
+  async slashCommandsList(projectPath?: string): Promise<SlashCommand[]> {
+    try {
+      return await apiCall<SlashCommand[]>("slash_commands_list", { projectPath });
+    } catch (error) {
+      console.error("Failed to list slash commands:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets a single slash command by ID
+   * @param commandId - Unique identifier of the command
+   * @returns Promise resolving to the slash command
+   */
+  async slashCommandGet(commandId: string): Promise<SlashCommand> {
+    try {
+      return await apiCall<SlashCommand>("slash_command_get", { commandId });
+    } catch (error) {
+      console.error("Failed to get slash command:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates or updates a slash command
+   * @param scope - Command scope: "project" or "user"
+   * @param name - Command name (without prefix)
+   * @param namespace - Optional namespace for organization
+   * @param content - Markdown content of the command
+   * @param description - Optional description
+   * @param allowedTools - List of allowed tools for this command
+   * @param projectPath - Required for project scope commands
+   * @returns Promise resolving to the saved command
+   */
+  async slashCommandSave(
+    scope: string,
+    name: string,
+    namespace: string | undefined,
+    content: string,
+    description: string | undefined,
+    allowedTools: string[],
+    projectPath?: string
+  ): Promise<SlashCommand> {
     try {
-      return await apiCall<ClaudeVersionStatus>("check_claude_version");
+      return await apiCall<SlashCommand>("slash_command_save", {
+        scope,
+        name,
+        namespace,
+        content,
+        description,
+        allowedTools,
+        projectPath
+      });
     } catch (error) {
-      console.error("Failed to check Claude version:", error);
+      console.error("Failed to save slash command:", error);
       throw error;
     }
   },
 
   /**
-   * Saves the CLAUDE.md system prompt file
-   * @param content - The new content for the system prompt
-   * @returns Promise resolving when the file is saved
+   * Deletes a slash command
+   * @param commandId - Unique identifier of the command to delete
+   * @param projectPath - Optional project path for deleting project commands
+   * @returns Promise resolving to deletion message
    */
-  async saveSystemPrompt(content: string): Promise<string> {
+  async slashCommandDelete(commandId: string, projectPath?: string): Promise<string> {
     try {
-      return await apiCall<string>("save_system_prompt", { content });
+      return await apiCall<string>("slash_command_delete", { commandId, projectPath });
     } catch (error) {
-      console.error("Failed to save system prompt:", error);
+      console.error("Failed to delete slash command:", error);
       throw error;
     }
   },
 
+  // Skills API methods
+
   /**
-   * Saves the Claude settings file
-   * @param settings - The settings object to save
-   * @returns Promise resolving when the settings are saved
+   * Lists all skills (both personal and project)
+   * @returns Promise resolving to array of skills
    */
-  async saveClaudeSettings(settings: ClaudeSettings): Promise<string> {
+  async skillListAll(): Promise<Skill[]> {
     try {
-      return await apiCall<string>("save_claude_settings", { settings });
+      return await apiCall<Skill[]>("skill_list_all");
     } catch (error) {
-      console.error("Failed to save Claude settings:", error);
+      console.error("Failed to list all skills:", error);
       throw error;
     }
   },
 
   /**
-   * Finds all CLAUDE.md files in a project directory
-   * @param projectPath - The absolute path to the project
-   * @returns Promise resolving to an array of CLAUDE.md files
+   * Lists skills by type (personal or project)
+   * @param skillType - Type of skills to list: "personal" or "project"
+   * @returns Promise resolving to array of skills
    */
-  async findClaudeMdFiles(projectPath: string): Promise<ClaudeMdFile[]> {
+  async skillListByType(skillType: string): Promise<Skill[]> {
     try {
-      return await apiCall<ClaudeMdFile[]>("find_claude_md_files", { projectPath });
+      return await apiCall<Skill[]>("skill_list_by_type", { skillType });
     } catch (error) {
-      console.error("Failed to find CLAUDE.md files:", error);
+      console.error(`Failed to list ${skillType} skills:`, error);
       throw error;
     }
   },
 
   /**
-   * Reads a specific CLAUDE.md file
-   * @param filePath - The absolute path to the file
-   * @returns Promise resolving to the file content
+   * Reads a skill by name and type
+   * @param name - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @returns Promise resolving to the skill
    */
-  async readClaudeMdFile(filePath: string): Promise<string> {
+  async skillRead(name: string, skillType: string): Promise<Skill> {
     try {
-      return await apiCall<string>("read_claude_md_file", { filePath });
+      return await apiCall<Skill>("skill_read", { name, skill_type: skillType });
     } catch (error) {
-      console.error("Failed to read CLAUDE.md file:", error);
+      console.error(`Failed to read skill ${name}:`, error);
       throw error;
     }
   },
 
   /**
-   * Read text file content for preview
+   * Creates a new skill
+   * @param name - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @param description - The skill description
+   * @param markdownContent - The markdown content
+   * @param allowedTools - Optional list of allowed tools
+   * @returns Promise resolving to the created skill
    */
-  async readTextFile(filePath: string): Promise<string> {
+  async skillCreate(
+    name: string,
+    skillType: string,
+    description: string,
+    markdownContent: string,
+    allowedTools?: string[]
+  ): Promise<Skill> {
     try {
-      return await apiCall<string>("read_text_file", { filePath });
+      return await apiCall<Skill>("skill_create", {
+        name,
+        skillType,
+        description,
+        markdownContent,
+        allowedTools,
+      });
     } catch (error) {
-      console.error("Failed to read text file:", error);
+      console.error(`Failed to create skill ${name}:`, error);
       throw error;
     }
   },
 
   /**
-   * Saves a specific CLAUDE.md file
-   * @param filePath - The absolute path to the file
-   * @param content - The new content for the file
-   * @returns Promise resolving when the file is saved
+   * Updates an existing skill
+   * @param name - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @param description - Optional updated description
+   * @param markdownContent - Optional updated markdown content
+   * @param allowedTools - Optional updated allowed tools
+   * @returns Promise resolving to the updated skill
    */
-  async saveClaudeMdFile(filePath: string, content: string): Promise<string> {
+  async skillUpdate(
+    name: string,
+    skillType: string,
+    description?: string,
+    markdownContent?: string,
+    allowedTools?: string[]
+  ): Promise<Skill> {
     try {
-      return await apiCall<string>("save_claude_md_file", { filePath, content });
+      return await apiCall<Skill>("skill_update", {
+        name,
+        skillType,
+        description,
+        markdownContent,
+        allowedTools,
+      });
     } catch (error) {
-      console.error("Failed to save CLAUDE.md file:", error);
+      console.error(`Failed to update skill ${name}:`, error);
       throw error;
     }
   },
 
-  // Agent API methods
-  
   /**
-   * Lists all CC agents
-   * @returns Promise resolving to an array of agents
+   * Deletes a skill
+   * @param name - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @returns Promise resolving when the skill is deleted
    */
-  async listAgents(): Promise<Agent[]> {
+  async skillDelete(name: string, skillType: string): Promise<void> {
     try {
-      return await apiCall<Agent[]>('list_agents');
+      return await apiCall<void>("skill_delete", { name, skillType });
     } catch (error) {
-      console.error("Failed to list agents:", error);
+      console.error(`Failed to delete skill ${name}:`, error);
       throw error;
     }
   },
 
   /**
-   * Creates a new agent
-   * @param name - The agent name
-   * @param icon - The icon identifier
-   * @param system_prompt - The system prompt for the agent
-   * @param default_task - Optional default task
-   * @param model - Optional model (defaults to 'sonnet')
-   * @param hooks - Optional hooks configuration as JSON string
-   * @returns Promise resolving to the created agent
+   * Validates a skill format
+   * @param name - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @param description - The skill description
+   * @param markdownContent - The markdown content
+   * @returns Promise resolving to validation result
    */
-  async createAgent(
-    name: string, 
-    icon: string, 
-    system_prompt: string, 
-    default_task?: string, 
-    model?: string,
-    hooks?: string
-  ): Promise<Agent> {
+  async skillValidate(
+    name: string,
+    skillType: string,
+    description: string,
+    markdownContent: string
+  ): Promise<ValidationResult> {
     try {
-      return await apiCall<Agent>('create_agent', { 
-        name, 
-        icon, 
-        systemPrompt: system_prompt,
-        defaultTask: default_task,
-        model,
-        hooks
+      return await apiCall<ValidationResult>("skill_validate", {
+        name,
+        skillType,
+        description,
+        markdownContent,
+      });
+    } catch (error) {
+      console.error(`Failed to validate skill ${name}:`, error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates a file in a skill directory
+   * @param skillName - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @param fileName - The file name to create
+   * @param content - The file content
+   * @returns Promise resolving when the file is created
+   */
+  async skillCreateFile(skillName: string, skillType: string, fileName: string, content: string): Promise<void> {
+    try {
+      return await apiCall<void>("skill_create_file", {
+        skillName,
+        skillType,
+        fileName,
+        content,
+      });
+    } catch (error) {
+      console.error(`Failed to create file ${fileName} in skill ${skillName}:`, error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads a file from a skill directory
+   * @param skillName - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @param fileName - The file name to read
+   * @returns Promise resolving to the file content
+   */
+  async skillReadFile(skillName: string, skillType: string, fileName: string): Promise<string> {
+    try {
+      return await apiCall<string>("skill_read_file", {
+        skillName,
+        skillType,
+        fileName,
+      });
+    } catch (error) {
+      console.error(`Failed to read file ${fileName} from skill ${skillName}:`, error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes a file from a skill directory
+   * @param skillName - The skill name
+   * @param skillType - The skill type: "personal" or "project"
+   * @param fileName - The file name to delete
+   * @returns Promise resolving when the file is deleted
+   */
+  async skillDeleteFile(skillName: string, skillType: string, fileName: string): Promise<void> {
+    try {
+      return await apiCall<void>("skill_delete_file", {
+        skillName,
+        skillType,
+        fileName,
       });
     } catch (error) {
-      console.error("Failed to create agent:", error);
+      console.error(`Failed to delete file ${fileName} from skill ${skillName}:`, error);
       throw error;
     }
   },
 
   /**
-   * Updates an existing agent
-   * @param id - The agent ID
-   * @param name - The updated name
-   * @param icon - The updated icon
-   * @param system_prompt - The updated system prompt
-   * @param default_task - Optional default task
-   * @param model - Optional model
-   * @param hooks - Optional hooks configuration as JSON string
-   * @returns Promise resolving to the updated agent
+   * Gets the application version
+   * @returns Promise resolving to the version string
    */
-  async updateAgent(
-    id: number, 
-    name: string, 
-    icon: string, 
-    system_prompt: string, 
-    default_task?: string, 
-    model?: string,
-    hooks?: string
-  ): Promise<Agent> {
+  async getAppVersion(): Promise<string> {
     try {
-      return await apiCall<Agent>('update_agent', { 
-        id, 
-        name, 
-        icon, 
-        systemPrompt: system_prompt,
-        defaultTask: default_task,
-        model,
-        hooks
-      });
+      return await apiCall<string>("get_app_version");
     } catch (error) {
-      console.error("Failed to update agent:", error);
+      console.error("Failed to get app version:", error);
       throw error;
     }
   },
 
   /**
-   * Deletes an agent
-   * @param id - The agent ID to delete
-   * @returns Promise resolving when the agent is deleted
+   * Gets detailed version information
+   * @returns Promise resolving to version info object
    */
-  async deleteAgent(id: number): Promise<void> {
+  async getVersionInfo(): Promise<any> {
     try {
-      return await apiCall('delete_agent', { id });
+      return await apiCall<any>("get_version_info");
     } catch (error) {
-      console.error("Failed to delete agent:", error);
+      console.error("Failed to get version info:", error);
       throw error;
     }
   },
 
   /**
-   * Gets a single agent by ID
-   * @param id - The agent ID
-   * @returns Promise resolving to the agent
+   * Gets the locale backend messages are rendered in
+   * @returns Promise resolving to a locale tag such as "en" or "zh-CN"
    */
-  async getAgent(id: number): Promise<Agent> {
+  async getLocale(): Promise<string> {
     try {
-      return await apiCall<Agent>('get_agent', { id });
+      return await apiCall<string>("get_locale");
     } catch (error) {
-      console.error("Failed to get agent:", error);
+      console.error("Failed to get locale:", error);
       throw error;
     }
   },
 
   /**
-   * Exports a single agent to JSON format
-   * @param id - The agent ID to export
-   * @returns Promise resolving to the JSON string
+   * Sets the locale backend messages are rendered in
+   * @param locale - Locale tag, e.g. "zh-CN"
+   * @returns Promise resolving to the locale actually applied
    */
-  async exportAgent(id: number): Promise<string> {
+  async setLocale(locale: string): Promise<string> {
     try {
-      return await apiCall<string>('export_agent', { id });
+      return await apiCall<string>("set_locale", { locale });
     } catch (error) {
-      console.error("Failed to export agent:", error);
+      console.error("Failed to set locale:", error);
       throw error;
     }
   },
 
   /**
-   * Imports an agent from JSON data
-   * @param jsonData - The JSON string containing the agent export
-   * @returns Promise resolving to the imported agent
+   * Gets the backend message templates by code, with {param} placeholders
+   * @param locale - Locale tag; defaults to the current backend locale
    */
-  async importAgent(jsonData: string): Promise<Agent> {
+  async getMessageCatalog(locale?: string): Promise<Record<string, string>> {
     try {
-      return await apiCall<Agent>('import_agent', { jsonData });
+      return await apiCall<Record<string, string>>("get_message_catalog", { locale });
     } catch (error) {
-      console.error("Failed to import agent:", error);
+      console.error("Failed to get message catalog:", error);
       throw error;
     }
   },
 
   /**
-   * Imports an agent from a file
-   * @param filePath - The path to the JSON file
-   * @returns Promise resolving to the imported agent
+   * Gets whether the app is offline and how many MCP operations are queued
    */
-  async importAgentFromFile(filePath: string): Promise<Agent> {
+  async getOfflineStatus(): Promise<OfflineStatus> {
     try {
-      return await apiCall<Agent>('import_agent_from_file', { filePath });
+      return await apiCall<OfflineStatus>("get_offline_status");
     } catch (error) {
-      console.error("Failed to import agent from file:", error);
+      console.error("Failed to get offline status:", error);
       throw error;
     }
   },
 
   /**
-   * Executes an agent
-   * @param agentId - The agent ID to execute
-   * @param projectPath - The project path to run the agent in
-   * @param task - The task description
-   * @param model - Optional model override
-   * @returns Promise resolving to the run ID when execution starts
+   * Turns offline mode on or off; turning it off replays queued MCP operations
    */
-  async executeAgent(agentId: number, projectPath: string, task: string, model?: string): Promise<number> {
+  async setOfflineMode(enabled: boolean): Promise<OfflineStatus> {
     try {
-      return await apiCall<number>('execute_agent', { agentId, projectPath, task, model });
+      return await apiCall<OfflineStatus>("set_offline_mode", { enabled });
     } catch (error) {
-      console.error("Failed to execute agent:", error);
-      // Return a sentinel value to indicate error
-      throw new Error(`Failed to execute agent: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to set offline mode:", error);
+      throw error;
     }
   },
 
   /**
-   * Lists agent runs without metrics (basic info only)
-   * @param agentId - Optional agent ID to filter runs
-   * @returns Promise resolving to an array of agent runs
+   * Probes connectivity now instead of waiting for the background check
    */
-  async listAgentRuns(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async checkConnectivity(): Promise<OfflineStatus> {
     try {
-      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs', { agentId });
+      return await apiCall<OfflineStatus>("check_connectivity");
     } catch (error) {
-      console.error("Failed to list agent runs:", error);
-      // Return empty array instead of throwing to prevent UI crashes
-      return [];
+      console.error("Failed to check connectivity:", error);
+      throw error;
     }
   },
 
   /**
-   * Lists agent runs with metrics (includes token counts and duration)
-   * @param agentId - Optional agent ID to filter runs
-   * @returns Promise resolving to an array of agent runs with metrics
+   * Lists MCP servers, falling back to the last good list when offline
    */
-  async listAgentRunsWithMetrics(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async mcpListCached(): Promise<Cached<MCPServer[]>> {
     try {
-      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs_with_metrics', { agentId });
+      return await apiCall<Cached<MCPServer[]>>("mcp_list_cached");
     } catch (error) {
-      console.error("Failed to list agent runs with metrics:", error);
-      // Return empty array instead of throwing to prevent UI crashes
-      return [];
+      console.error("Failed to list MCP servers:", error);
+      throw error;
     }
   },
 
   /**
-   * Gets a single agent run by ID with metrics
-   * @param id - The run ID
-   * @returns Promise resolving to the agent run with metrics
+   * Fetches the GitHub agent catalog, falling back to the last good copy when offline
    */
-  async getAgentRun(id: number): Promise<AgentRunWithMetrics> {
+  async fetchGitHubAgentsCached(): Promise<Cached<GitHubAgentFile[]>> {
     try {
-      return await apiCall<AgentRunWithMetrics>('get_agent_run', { id });
+      return await apiCall<Cached<GitHubAgentFile[]>>("fetch_github_agents_cached");
     } catch (error) {
-      console.error("Failed to get agent run:", error);
-      throw new Error(`Failed to get agent run: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to fetch GitHub agents:", error);
+      throw error;
     }
   },
 
   /**
-   * Gets a single agent run by ID with real-time metrics from JSONL
-   * @param id - The run ID
-   * @returns Promise resolving to the agent run with metrics
+   * Lists MCP operations waiting for connectivity, oldest first
    */
-  async getAgentRunWithRealTimeMetrics(id: number): Promise<AgentRunWithMetrics> {
+  async listQueuedMcpOperations(): Promise<QueuedOperation[]> {
     try {
-      return await apiCall<AgentRunWithMetrics>('get_agent_run_with_real_time_metrics', { id });
+      return await apiCall<QueuedOperation[]>("list_queued_mcp_operations");
     } catch (error) {
-      console.error("Failed to get agent run with real-time metrics:", error);
-      throw new Error(`Failed to get agent run with real-time metrics: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to list queued MCP operations:", error);
+      throw error;
     }
   },
 
   /**
-   * Lists all currently running agent sessions
-   * @returns Promise resolving to list of running agent sessions
+   * Drops a queued MCP operation without running it
    */
-  async listRunningAgentSessions(): Promise<AgentRun[]> {
+  async discardQueuedMcpOperation(id: number): Promise<void> {
     try {
-      return await apiCall<AgentRun[]>('list_running_sessions');
+      await apiCall("discard_queued_mcp_operation", { id });
     } catch (error) {
-      console.error("Failed to list running agent sessions:", error);
-      throw new Error(`Failed to list running agent sessions: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to discard queued MCP operation:", error);
+      throw error;
     }
   },
 
   /**
-   * Kills a running agent session
-   * @param runId - The run ID to kill
-   * @returns Promise resolving to whether the session was successfully killed
+   * Replays queued MCP operations now
    */
-  async killAgentSession(runId: number): Promise<boolean> {
+  async replayQueuedMcpOperations(): Promise<ReplayReport> {
     try {
-      return await apiCall<boolean>('kill_agent_session', { runId });
+      return await apiCall<ReplayReport>("replay_queued_mcp_operations");
     } catch (error) {
-      console.error("Failed to kill agent session:", error);
-      throw new Error(`Failed to kill agent session: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to replay queued MCP operations:", error);
+      throw error;
     }
   },
 
   /**
-   * Gets the status of a specific agent session
-   * @param runId - The run ID to check
-   * @returns Promise resolving to the session status or null if not found
+   * Compares two sessions of a project, such as two forks of one conversation
+   * @param projectId - Project directory name under ~/.claude/projects
    */
-  async getSessionStatus(runId: number): Promise<string | null> {
+  async diffSessions(projectId: string, sessionA: string, sessionB: string): Promise<SessionDiff> {
     try {
-      return await apiCall<string | null>('get_session_status', { runId });
+      return await apiCall<SessionDiff>("diff_sessions", { projectId, sessionA, sessionB });
     } catch (error) {
-      console.error("Failed to get session status:", error);
-      throw new Error(`Failed to get session status: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to diff sessions:", error);
+      throw error;
     }
   },
 
   /**
-   * Cleanup finished processes and update their status
-   * @returns Promise resolving to list of run IDs that were cleaned up
+   * Replays a session's prompts with tool results stubbed from the recorded run
+   * and diffs the new transcript against the original
+   * @param model - Model to replay against; defaults to the recorded model
    */
-  async cleanupFinishedProcesses(): Promise<number[]> {
+  async replaySession(projectId: string, sessionId: string, model?: string): Promise<ReplayResult> {
     try {
-      return await apiCall<number[]>('cleanup_finished_processes');
+      return await apiCall<ReplayResult>("replay_session", { projectId, sessionId, model });
     } catch (error) {
-      console.error("Failed to cleanup finished processes:", error);
-      throw new Error(`Failed to cleanup finished processes: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to replay session:", error);
+      throw error;
     }
   },
 
   /**
-   * Get real-time output for a running session (with live output fallback)
-   * @param runId - The run ID to get output for
-   * @returns Promise resolving to the current session output (JSONL format)
+   * Exports an agent run as a redacted, reproducible bundle
+   * @returns What the redaction replaced
    */
-  async getSessionOutput(runId: number): Promise<string> {
+  async exportRunBundle(runId: number, path: string): Promise<RedactionReport> {
     try {
-      return await apiCall<string>('get_session_output', { runId });
+      return await apiCall<RedactionReport>("export_run_bundle", { runId, path });
     } catch (error) {
-      console.error("Failed to get session output:", error);
-      throw new Error(`Failed to get session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to export run bundle:", error);
+      throw error;
     }
   },
 
   /**
-   * Get live output directly from process stdout buffer
-   * @param runId - The run ID to get live output for
-   * @returns Promise resolving to the current live output
+   * Reads a run bundle without importing it
    */
-  async getLiveSessionOutput(runId: number): Promise<string> {
+  async inspectRunBundle(path: string): Promise<RunBundle> {
     try {
-      return await apiCall<string>('get_live_session_output', { runId });
+      return await apiCall<RunBundle>("inspect_run_bundle", { path });
     } catch (error) {
-      console.error("Failed to get live session output:", error);
-      throw new Error(`Failed to get live session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to inspect run bundle:", error);
+      throw error;
     }
   },
 
   /**
-   * Start streaming real-time output for a running session
-   * @param runId - The run ID to stream output for
-   * @returns Promise that resolves when streaming starts
+   * Imports a run bundle, recreating its agent
+   * @param projectPath - Project to restore files into and run the task in
+   * @param restoreFiles - Put the changed files back to their pre-run content
+   * @param execute - Run the bundled task again
    */
-  async streamSessionOutput(runId: number): Promise<void> {
+  async importRunBundle(
+    path: string,
+    projectPath?: string,
+    restoreFiles?: boolean,
+    execute?: boolean
+  ): Promise<ImportedRunBundle> {
     try {
-      return await apiCall<void>('stream_session_output', { runId });
+      return await apiCall<ImportedRunBundle>("import_run_bundle", { path, projectPath, restoreFiles, execute });
     } catch (error) {
-      console.error("Failed to start streaming session output:", error);
-      throw new Error(`Failed to start streaming session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to import run bundle:", error);
+      throw error;
     }
   },
 
   /**
-   * Loads the JSONL history for a specific session
+   * Fuzzy-matches agents, slash commands and recent tasks for the quick-run palette
    */
-  async loadSessionHistory(sessionId: string, projectId: string): Promise<any[]> {
-    return apiCall("load_session_history", { sessionId, projectId });
+  async quickRunSearch(query: string, projectPath?: string, limit?: number): Promise<QuickRunItem[]> {
+    try {
+      return await apiCall<QuickRunItem[]>("quick_run_search", { query, projectPath, limit });
+    } catch (error) {
+      console.error("Failed to search quick run:", error);
+      throw error;
+    }
   },
 
   /**
-   * Loads the JSONL history for a specific agent session
-   * Similar to loadSessionHistory but searches across all project directories
-   * @param sessionId - The session ID (UUID)
-   * @returns Promise resolving to array of session messages
+   * Dispatches a palette entry: recent tasks run again, agents and slash commands are
+   * handed back through the "quick-run:selected" event
+   * @param itemId - Entry to dispatch; the best match for the query when omitted
    */
-  async loadAgentSessionHistory(sessionId: string): Promise<any[]> {
+  async quickRun(query: string, projectPath?: string, itemId?: string): Promise<QuickRunOutcome> {
     try {
-      return await apiCall<any[]>('load_agent_session_history', { sessionId });
+      return await apiCall<QuickRunOutcome>("quick_run", { query, projectPath, itemId });
     } catch (error) {
-      console.error("Failed to load agent session history:", error);
+      console.error("Failed to quick run:", error);
       throw error;
     }
   },
 
   /**
-   * Executes a new interactive Claude Code session with streaming output
+   * Gets the global shortcut that opens the quick-run palette; empty when disabled
    */
-  async executeClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return apiCall("execute_claude_code", { projectPath, prompt, model });
+  async getQuickRunShortcut(): Promise<string> {
+    try {
+      return await apiCall<string>("get_quick_run_shortcut");
+    } catch (error) {
+      console.error("Failed to get quick run shortcut:", error);
+      throw error;
+    }
   },
 
   /**
-   * Continues an existing Claude Code conversation with streaming output
+   * Sets the global quick-run shortcut, e.g. "Alt+Space"; empty disables it
    */
-  async continueClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return apiCall("continue_claude_code", { projectPath, prompt, model });
+  async setQuickRunShortcut(shortcut: string): Promise<void> {
+    try {
+      return await apiCall<void>("set_quick_run_shortcut", { shortcut });
+    } catch (error) {
+      console.error("Failed to set quick run shortcut:", error);
+      throw error;
+    }
   },
 
   /**
-   * Resumes an existing Claude Code session by ID with streaming output
+   * Lists the channels backend events are published on
    */
-  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string): Promise<void> {
-    return apiCall("resume_claude_code", { projectPath, sessionId, prompt, model });
+  async listEventChannels(): Promise<EventChannel[]> {
+    try {
+      return await apiCall<EventChannel[]>("list_event_channels");
+    } catch (error) {
+      console.error("Failed to list event channels:", error);
+      throw error;
+    }
   },
 
   /**
-   * Cancels the currently running Claude Code execution
-   * @param sessionId - Optional session ID to cancel a specific session
+   * Subscribes this window to event channels. Once subscribed, the window only receives
+   * the channels it subscribed to.
+   * @returns The window's subscriptions
    */
-  async cancelClaudeExecution(sessionId?: string): Promise<void> {
-    return apiCall("cancel_claude_execution", { sessionId });
+  async subscribeEvents(channels: EventChannel[]): Promise<EventChannel[]> {
+    try {
+      return await apiCall<EventChannel[]>("subscribe_events", { channels });
+    } catch (error) {
+      console.error("Failed to subscribe to events:", error);
+      throw error;
+    }
   },
 
   /**
-   * Lists all currently running Claude sessions
-   * @returns Promise resolving to list of running Claude sessions
+   * Unsubscribes this window from event channels
+   * @returns The window's remaining subscriptions
    */
-  async listRunningClaudeSessions(): Promise<any[]> {
-    return apiCall("list_running_claude_sessions");
+  async unsubscribeEvents(channels: EventChannel[]): Promise<EventChannel[]> {
+    try {
+      return await apiCall<EventChannel[]>("unsubscribe_events", { channels });
+    } catch (error) {
+      console.error("Failed to unsubscribe from events:", error);
+      throw error;
+    }
   },
 
   /**
-   * Gets live output from a Claude session
-   * @param sessionId - The session ID to get output for
-   * @returns Promise resolving to the current live output
+   * Fetches the full payload of an event that arrived as a TruncatedPayload
    */
-  async getClaudeSessionOutput(sessionId: string): Promise<string> {
-    return apiCall("get_claude_session_output", { sessionId });
+  async fetchEventPayload(payloadId: string): Promise<any> {
+    try {
+      return await apiCall<any>("fetch_event_payload", { payloadId });
+    } catch (error) {
+      console.error("Failed to fetch event payload:", error);
+      throw error;
+    }
   },
 
   /**
-   * Sends a message to Claude and returns the response (simplified API for terminal session)
+   * Gets whether local stores are encrypted at rest
    */
-  async sendClaudeMessage(params: {
-    prompt: string;
-    model: string;
-    project_path?: string;
-    session_id?: string;
-  }): Promise<{
-    response: string;
-    session_id?: string;
-    usage?: { input_tokens: number; output_tokens: number };
-    tool_results?: Array<{ name: string; content: string; success: boolean }>;
-  }> {
-    return apiCall("send_claude_message", params);
+  async getStorageEncryptionStatus(): Promise<EncryptionStatus> {
+    try {
+      return await apiCall<EncryptionStatus>("get_storage_encryption_status");
+    } catch (error) {
+      console.error("Failed to get storage encryption status:", error);
+      throw error;
+    }
   },
 
   /**
-   * Lists files and directories in a given path
+   * Turns encryption at rest on or off, converting the database, backups and artifacts
+   * in place
    */
-  async listDirectoryContents(directoryPath: string): Promise<FileEntry[]> {
-    return apiCall("list_directory_contents", { directoryPath });
+  async migrateStorageEncryption(enable: boolean): Promise<EncryptionStatus> {
+    try {
+      return await apiCall<EncryptionStatus>("migrate_storage_encryption", { enable });
+    } catch (error) {
+      console.error("Failed to migrate storage encryption:", error);
+      throw error;
+    }
   },
 
   /**
-   * Searches for files and directories matching a pattern
+   * Gets whether the app is locked, with the app lock settings
    */
-  async searchFiles(basePath: string, query: string): Promise<FileEntry[]> {
-    return apiCall("search_files", { basePath, query });
+  async getAppLockStatus(): Promise<AppLockStatus> {
+    try {
+      return await apiCall<AppLockStatus>("get_app_lock_status");
+    } catch (error) {
+      console.error("Failed to get app lock status:", error);
+      throw error;
+    }
   },
 
   /**
-   * Recursively lists all files and directories in a project root
+   * Updates the app lock settings
+   * @param password - New app password, required before enabling password unlock
    */
-  async listProjectFiles(projectPath: string): Promise<FileEntry[]> {
-    return apiCall("list_project_files", { projectPath });
+  async setAppLockSettings(settings: AppLockSettings, password?: string): Promise<AppLockStatus> {
+    try {
+      return await apiCall<AppLockStatus>("set_app_lock_settings", { settings, password });
+    } catch (error) {
+      console.error("Failed to set app lock settings:", error);
+      throw error;
+    }
   },
 
   /**
-   * Starts a local HTTP server to serve project files
-   * @returns Promise resolving to server URL and port
+   * Locks the app now
    */
-  async startFileServer(projectPath: string): Promise<{ url: string; port: number; already_running?: boolean }> {
-    return apiCall("start_file_server", { projectPath });
+  async lockApp(): Promise<void> {
+    try {
+      return await apiCall<void>("lock_app");
+    } catch (error) {
+      console.error("Failed to lock app:", error);
+      throw error;
+    }
   },
 
   /**
-   * Gets the current file server URL if running
-   * @returns Promise resolving to server URL or null
+   * Unlocks the app with the app password, or through the system prompt when no password
+   * is given and system unlock is configured
    */
-  async getFileServerUrl(): Promise<string | null> {
-    return apiCall("get_file_server_url", {});
+  async unlockApp(password?: string): Promise<AppLockStatus> {
+    try {
+      return await apiCall<AppLockStatus>("unlock_app", { password });
+    } catch (error) {
+      console.error("Failed to unlock app:", error);
+      throw error;
+    }
   },
 
   /**
-   * Gets overall usage statistics
-   * @returns Promise resolving to usage statistics
+   * Gets the local usage counts of the last `days` days (30 by default)
    */
-  async getUsageStats(): Promise<UsageStats> {
+  async getTelemetrySummary(days?: number): Promise<TelemetrySummary> {
     try {
-      return await apiCall<UsageStats>("get_usage_stats");
+      return await apiCall<TelemetrySummary>("get_telemetry_summary", { days });
     } catch (error) {
-      console.error("Failed to get usage stats:", error);
+      console.error("Failed to get telemetry summary:", error);
       throw error;
     }
   },
 
   /**
-   * Gets usage statistics filtered by date range
-   * @param startDate - Start date (ISO format)
-   * @param endDate - End date (ISO format)
-   * @returns Promise resolving to usage statistics
+   * Opts in to or out of the local usage counters
    */
-  async getUsageByDateRange(startDate: string, endDate: string): Promise<UsageStats> {
+  async setTelemetryEnabled(enabled: boolean): Promise<void> {
     try {
-      return await apiCall<UsageStats>("get_usage_by_date_range", { startDate, endDate });
+      return await apiCall<void>("set_telemetry_enabled", { enabled });
     } catch (error) {
-      console.error("Failed to get usage by date range:", error);
+      console.error("Failed to set telemetry:", error);
       throw error;
     }
   },
 
   /**
-   * Gets usage statistics grouped by session
-   * @param since - Optional start date (YYYYMMDD)
-   * @param until - Optional end date (YYYYMMDD)
-   * @param order - Optional sort order ('asc' or 'desc')
-   * @returns Promise resolving to an array of session usage data
+   * Writes the usage summary to a file for the user to share; nothing is uploaded
    */
-  async getSessionStats(
-    since?: string,
-    until?: string,
-    order?: "asc" | "desc"
-  ): Promise<ProjectUsage[]> {
+  async exportTelemetry(path: string, days?: number): Promise<TelemetryExport> {
     try {
-      return await apiCall<ProjectUsage[]>("get_session_stats", {
-        since,
-        until,
-        order,
-      });
+      return await apiCall<TelemetryExport>("export_telemetry", { path, days });
     } catch (error) {
-      console.error("Failed to get session stats:", error);
+      console.error("Failed to export telemetry:", error);
       throw error;
     }
   },
 
   /**
-   * Gets detailed usage entries with optional filtering
-   * @param limit - Optional limit for number of entries
-   * @returns Promise resolving to array of usage entries
+   * Deletes every stored usage count
    */
-  async getUsageDetails(limit?: number): Promise<UsageEntry[]> {
+  async clearTelemetry(): Promise<void> {
     try {
-      return await apiCall<UsageEntry[]>("get_usage_details", { limit });
+      return await apiCall<void>("clear_telemetry");
     } catch (error) {
-      console.error("Failed to get usage details:", error);
+      console.error("Failed to clear telemetry:", error);
       throw error;
     }
   },
 
   /**
-   * Creates a checkpoint for the current session state
+   * Gets the messages around an anchor (a message index or uuid), or the end of the
+   * transcript without one
    */
-  async createCheckpoint(
+  async getSessionWindow(
     sessionId: string,
-    projectId: string,
-    projectPath: string,
-    messageIndex?: number,
-    description?: string
-  ): Promise<CheckpointResult> {
-    return apiCall("create_checkpoint", {
-      sessionId,
-      projectId,
-      projectPath,
-      messageIndex,
-      description
-    });
+    anchorMessage: number | string | null,
+    before: number,
+    after: number
+  ): Promise<SessionWindow> {
+    try {
+      return await apiCall<SessionWindow>("get_session_window", {
+        sessionId,
+        anchorMessage,
+        before,
+        after,
+      });
+    } catch (error) {
+      console.error("Failed to get session window:", error);
+      throw error;
+    }
   },
 
   /**
-   * Restores a session to a specific checkpoint
+   * Searches the text of every session transcript, best matches first
    */
-  async restoreCheckpoint(
-    checkpointId: string,
-    sessionId: string,
-    projectId: string,
-    projectPath: string
-  ): Promise<CheckpointResult> {
-    return apiCall("restore_checkpoint", {
-      checkpointId,
-      sessionId,
-      projectId,
-      projectPath
-    });
+  async searchSessions(query: string, limit?: number): Promise<SessionSearchHit[]> {
+    try {
+      return await apiCall<SessionSearchHit[]>("search_sessions", { query, limit });
+    } catch (error) {
+      console.error("Failed to search sessions:", error);
+      throw error;
+    }
   },
 
   /**
-   * Lists all checkpoints for a session
+   * Gets sessions, usage, most-used tools, most-edited files and weekly activity of a project
+   * @param projectId - Name of the project directory under ~/.claude/projects
    */
-  async listCheckpoints(
-    sessionId: string,
-    projectId: string,
-    projectPath: string
-  ): Promise<Checkpoint[]> {
-    return apiCall("list_checkpoints", {
-      sessionId,
-      projectId,
-      projectPath
-    });
+  async getProjectStats(projectId: string): Promise<ProjectStats> {
+    try {
+      return await apiCall<ProjectStats>("get_project_stats", { projectId });
+    } catch (error) {
+      console.error("Failed to get project stats:", error);
+      throw error;
+    }
   },
 
   /**
-   * Forks a new timeline branch from a checkpoint
+   * Gets call counts, failure rates and durations per tool and per MCP server
+   * @param range - Inclusive days as YYYY-MM-DD; either end may be left open
+   * @param projectId - Only count sessions of this project directory
    */
-  async forkFromCheckpoint(
-    checkpointId: string,
-    sessionId: string,
-    projectId: string,
-    projectPath: string,
-    newSessionId: string,
-    description?: string
-  ): Promise<CheckpointResult> {
-    return apiCall("fork_from_checkpoint", {
-      checkpointId,
-      sessionId,
-      projectId,
-      projectPath,
-      newSessionId,
-      description
-    });
+  async getToolUsageStats(
+    range?: { start_date?: string; end_date?: string },
+    projectId?: string
+  ): Promise<ToolUsageStats> {
+    try {
+      return await apiCall<ToolUsageStats>("get_tool_usage_stats", { range, projectId });
+    } catch (error) {
+      console.error("Failed to get tool usage stats:", error);
+      throw error;
+    }
   },
 
   /**
-   * Gets the timeline for a session
+   * Gets the files Claude changed most often in a project, from its Edit and Write calls
+   * @param project - Name of the project directory under ~/.claude/projects
+   * @param range - Inclusive days as YYYY-MM-DD; either end may be left open
    */
-  async getSessionTimeline(
-    sessionId: string,
-    projectId: string,
-    projectPath: string
-  ): Promise<SessionTimeline> {
-    return apiCall("get_session_timeline", {
-      sessionId,
-      projectId,
-      projectPath
-    });
+  async getFileHotspots(
+    project: string,
+    range?: { start_date?: string; end_date?: string }
+  ): Promise<FileHotspotReport> {
+    try {
+      return await apiCall<FileHotspotReport>("get_file_hotspots", { project, range });
+    } catch (error) {
+      console.error("Failed to get file hotspots:", error);
+      throw error;
+    }
   },
 
   /**
-   * Updates checkpoint settings for a session
+   * Checks the MCP config files for common mistakes, optionally only in one scope
    */
-  async updateCheckpointSettings(
-    sessionId: string,
-    projectId: string,
-    projectPath: string,
-    autoCheckpointEnabled: boolean,
-    checkpointStrategy: CheckpointStrategy
-  ): Promise<void> {
-    return apiCall("update_checkpoint_settings", {
-      sessionId,
-      projectId,
-      projectPath,
-      autoCheckpointEnabled,
-      checkpointStrategy
-    });
+  async mcpLintConfig(scope?: string, projectPath?: string): Promise<McpLintWarning[]> {
+    try {
+      return await apiCall<McpLintWarning[]>("mcp_lint_config", { scope, projectPath });
+    } catch (error) {
+      console.error("Failed to lint MCP config:", error);
+      throw error;
+    }
+  },
+
+  async getMcpServerLimits(): Promise<McpServerLimits[]> {
+    try {
+      return await apiCall<McpServerLimits[]>("get_mcp_server_limits");
+    } catch (error) {
+      console.error("Failed to get MCP server limits:", error);
+      throw error;
+    }
   },
 
   /**
-   * Gets diff between two checkpoints
+   * Sets a stdio server's resource limits; leaving every limit null removes them
    */
-  async getCheckpointDiff(
-    fromCheckpointId: string,
-    toCheckpointId: string,
-    sessionId: string,
-    projectId: string
-  ): Promise<CheckpointDiff> {
+  async setMcpServerLimits(limits: McpServerLimits): Promise<void> {
     try {
-      return await apiCall<CheckpointDiff>("get_checkpoint_diff", {
-        fromCheckpointId,
-        toCheckpointId,
-        sessionId,
-        projectId
-      });
+      return await apiCall<void>("set_mcp_server_limits", { limits });
     } catch (error) {
-      console.error("Failed to get checkpoint diff:", error);
+      console.error("Failed to set MCP server limits:", error);
       throw error;
     }
   },
 
   /**
-   * Tracks a message for checkpointing
+   * Lists MCP servers killed for going over a limit, newest first
    */
-  async trackCheckpointMessage(
-    sessionId: string,
-    projectId: string,
-    projectPath: string,
-    message: string
-  ): Promise<void> {
+  async listMcpLimitViolations(limit?: number): Promise<McpLimitViolation[]> {
     try {
-      await apiCall("track_checkpoint_message", {
-        sessionId,
-        projectId,
-        projectPath,
-        message
-      });
+      return await apiCall<McpLimitViolation[]>("list_mcp_limit_violations", { limit });
     } catch (error) {
-      console.error("Failed to track checkpoint message:", error);
+      console.error("Failed to list MCP limit violations:", error);
       throw error;
     }
   },
 
   /**
-   * Checks if auto-checkpoint should be triggered
+   * Probes a server URL (DNS, TCP, TLS certificate, HTTP status) before adding it
    */
-  async checkAutoCheckpoint(
-    sessionId: string,
-    projectId: string,
-    projectPath: string,
-    message: string
-  ): Promise<boolean> {
+  async mcpCheckReachability(
+    url: string,
+    headers?: Record<string, string>,
+    server?: string
+  ): Promise<McpReachabilityProbe> {
     try {
-      return await apiCall<boolean>("check_auto_checkpoint", {
-        sessionId,
-        projectId,
-        projectPath,
-        message
+      return await apiCall<McpReachabilityProbe>("mcp_check_reachability", {
+        url,
+        headers,
+        server,
       });
     } catch (error) {
-      console.error("Failed to check auto checkpoint:", error);
+      console.error("Failed to check MCP server reachability:", error);
       throw error;
     }
   },
 
   /**
-   * Triggers cleanup of old checkpoints
+   * Probes every configured SSE/HTTP server now, by server name
    */
-  async cleanupOldCheckpoints(
-    sessionId: string,
-    projectId: string,
-    projectPath: string,
-    keepCount: number
-  ): Promise<number> {
+  async mcpRefreshReachability(): Promise<Record<string, McpReachabilityProbe>> {
     try {
-      return await apiCall<number>("cleanup_old_checkpoints", {
-        sessionId,
-        projectId,
-        projectPath,
-        keepCount
-      });
+      return await apiCall<Record<string, McpReachabilityProbe>>("mcp_refresh_reachability");
     } catch (error) {
-      console.error("Failed to cleanup old checkpoints:", error);
+      console.error("Failed to refresh MCP server reachability:", error);
       throw error;
     }
   },
 
   /**
-   * Gets checkpoint settings for a session
+   * Gets the TLS settings, with the state of the CA bundle
    */
-  async getCheckpointSettings(
-    sessionId: string,
-    projectId: string,
-    projectPath: string
-  ): Promise<{
-    auto_checkpoint_enabled: boolean;
-    checkpoint_strategy: CheckpointStrategy;
-    total_checkpoints: number;
-    current_checkpoint_id?: string;
-  }> {
+  async getTlsSettings(): Promise<TlsStatus> {
     try {
-      return await apiCall("get_checkpoint_settings", {
-        sessionId,
-        projectId,
-        projectPath
-      });
+      return await apiCall<TlsStatus>("get_tls_settings");
     } catch (error) {
-      console.error("Failed to get checkpoint settings:", error);
+      console.error("Failed to get TLS settings:", error);
       throw error;
     }
   },
 
   /**
-   * Clears checkpoint manager for a session (cleanup on session end)
+   * Saves the TLS settings; a CA bundle that can't be read is refused
    */
-  async clearCheckpointManager(sessionId: string): Promise<void> {
+  async setTlsSettings(settings: TlsSettings): Promise<TlsStatus> {
     try {
-      await apiCall("clear_checkpoint_manager", { sessionId });
+      return await apiCall<TlsStatus>("set_tls_settings", { settings });
     } catch (error) {
-      console.error("Failed to clear checkpoint manager:", error);
+      console.error("Failed to save TLS settings:", error);
       throw error;
     }
   },
 
   /**
-   * Tracks a batch of messages for a session for checkpointing
+   * Stores a client certificate and PKCS#8 key (PEM) for an mTLS server in the keychain
    */
-  trackSessionMessages: (
-    sessionId: string, 
-    projectId: string, 
-    projectPath: string, 
-    messages: string[]
-  ): Promise<void> =>
-    apiCall("track_session_messages", { sessionId, projectId, projectPath, messages }),
+  async setMcpClientCertificate(
+    server: string,
+    certificate: string,
+    key: string
+  ): Promise<ClientCertificateInfo> {
+    try {
+      return await apiCall<ClientCertificateInfo>("set_mcp_client_certificate", {
+        server,
+        certificate,
+        key,
+      });
+    } catch (error) {
+      console.error("Failed to set MCP client certificate:", error);
+      throw error;
+    }
+  },
 
   /**
-   * Adds a new MCP server
+   * Stops presenting a client certificate for a server
    */
-  async mcpAdd(
-    name: string,
-    transport: string,
-    command?: string,
-    args: string[] = [],
-    env: Record<string, string> = {},
-    url?: string,
-    scope: string = "local",
-    headers: Record<string, string> = {}
-  ): Promise<AddServerResult> {
+  async removeMcpClientCertificate(server: string): Promise<TlsStatus> {
     try {
-      return await apiCall<AddServerResult>("mcp_add", {
-        name,
-        transport,
-        command,
-        args,
-        env,
-        url,
-        scope,
-        headers
-      });
+      return await apiCall<TlsStatus>("remove_mcp_client_certificate", { server });
     } catch (error) {
-      console.error("Failed to add MCP server:", error);
+      console.error("Failed to remove MCP client certificate:", error);
       throw error;
     }
   },
 
   /**
-   * Lists all configured MCP servers
+   * Lists provider profiles
    */
-  async mcpList(): Promise<MCPServer[]> {
+  async listProviderProfiles(): Promise<ProviderProfile[]> {
     try {
-
-      const result = await apiCall<MCPServer[]>("mcp_list");
-
-      return result;
+      return await apiCall<ProviderProfile[]>("list_provider_profiles");
     } catch (error) {
-      console.error("API: Failed to list MCP servers:", error);
+      console.error("Failed to list provider profiles:", error);
       throw error;
     }
   },
 
   /**
-   * Gets details for a specific MCP server
+   * Creates a provider profile; secrets are stored in the OS keychain
    */
-  async mcpGet(name: string): Promise<MCPServer> {
+  async createProviderProfile(profile: ProviderProfile, secrets?: Record<string, string>): Promise<ProviderProfile> {
     try {
-      return await apiCall<MCPServer>("mcp_get", { name });
+      return await apiCall<ProviderProfile>("create_provider_profile", { profile, secrets });
     } catch (error) {
-      console.error("Failed to get MCP server:", error);
+      console.error("Failed to create provider profile:", error);
       throw error;
     }
   },
 
   /**
-   * Removes an MCP server
+   * Updates a provider profile; secrets left empty keep their stored value
    */
-  async mcpRemove(name: string): Promise<string> {
+  async updateProviderProfile(profile: ProviderProfile, secrets?: Record<string, string>): Promise<ProviderProfile> {
     try {
-      return await apiCall<string>("mcp_remove", { name });
+      return await apiCall<ProviderProfile>("update_provider_profile", { profile, secrets });
     } catch (error) {
-      console.error("Failed to remove MCP server:", error);
+      console.error("Failed to update provider profile:", error);
       throw error;
     }
   },
 
   /**
-   * Updates an existing MCP server (remove + add)
+   * Deletes a provider profile and unassigns it from projects
    */
-  async mcpUpdate(
-    oldName: string,
-    name: string,
-    transport: string,
-    command?: string,
-    args: string[] = [],
-    env: Record<string, string> = {},
-    url?: string,
-    scope: string = "local",
-    headers: Record<string, string> = {}
-  ): Promise<AddServerResult> {
+  async deleteProviderProfile(id: number): Promise<void> {
     try {
-      return await apiCall<AddServerResult>("mcp_update", {
-        old_name: oldName,
-        name,
-        transport,
-        command,
-        args,
-        env,
-        url,
-        scope,
-        headers,
-      });
+      return await apiCall("delete_provider_profile", { id });
     } catch (error) {
-      console.error("Failed to update MCP server:", error);
+      console.error("Failed to delete provider profile:", error);
       throw error;
     }
   },
 
   /**
-   * Adds an MCP server from JSON configuration
+   * Gets the provider profile assigned to a project
    */
-  async mcpAddJson(name: string, jsonConfig: string, scope: string = "local"): Promise<AddServerResult> {
+  async getProjectProviderProfile(projectPath: string): Promise<ProviderProfile | null> {
     try {
-      return await apiCall<AddServerResult>("mcp_add_json", { name, jsonConfig, scope });
+      return await apiCall<ProviderProfile | null>("get_project_provider_profile", { projectPath });
     } catch (error) {
-      console.error("Failed to add MCP server from JSON:", error);
+      console.error("Failed to get project provider profile:", error);
       throw error;
     }
   },
 
   /**
-   * Starts Claude Code as an MCP server
+   * Assigns a provider profile to a project, or clears it with null
    */
-  async mcpServe(): Promise<string> {
+  async setProjectProviderProfile(projectPath: string, profileId: number | null): Promise<void> {
     try {
-      return await apiCall<string>("mcp_serve");
+      return await apiCall("set_project_provider_profile", { projectPath, profileId });
     } catch (error) {
-      console.error("Failed to start MCP server:", error);
+      console.error("Failed to set project provider profile:", error);
       throw error;
     }
   },
 
   /**
-   * Tests connection to an MCP server
+   * Checks that a provider profile's backend is reachable and accepts its credentials
    */
-  async mcpTestConnection(name: string): Promise<string> {
+  async testProviderProfile(id: number): Promise<ProviderConnectivity> {
     try {
-      return await apiCall<string>("mcp_test_connection", { name });
+      return await apiCall<ProviderConnectivity>("test_provider_profile", { id });
     } catch (error) {
-      console.error("Failed to test MCP connection:", error);
+      console.error("Failed to test provider profile:", error);
       throw error;
     }
   },
 
   /**
-   * Resets project-scoped server approval choices
+   * Lists SSH remote hosts
    */
-  async mcpResetProjectChoices(): Promise<string> {
+  async listRemoteHosts(): Promise<RemoteHost[]> {
     try {
-      return await apiCall<string>("mcp_reset_project_choices");
+      return await apiCall<RemoteHost[]>("list_remote_hosts");
     } catch (error) {
-      console.error("Failed to reset project choices:", error);
+      console.error("Failed to list remote hosts:", error);
       throw error;
     }
   },
 
   /**
-   * Gets the status of MCP servers
+   * Creates or updates a remote host; a private key is stored in the keychain
    */
-  async mcpGetServerStatus(): Promise<Record<string, ServerStatus>> {
+  async saveRemoteHost(host: RemoteHost, privateKey?: string): Promise<RemoteHost> {
     try {
-      return await apiCall<Record<string, ServerStatus>>("mcp_get_server_status");
+      return await apiCall<RemoteHost>("save_remote_host", { host, privateKey });
     } catch (error) {
-      console.error("Failed to get server status:", error);
+      console.error("Failed to save remote host:", error);
       throw error;
     }
   },
 
   /**
-   * Gets the MCP configuration file paths
+   * Deletes a remote host and unassigns it from projects
    */
-  async mcpGetConfigPaths(projectPath?: string): Promise<MCPConfigPaths> {
+  async deleteRemoteHost(id: number): Promise<void> {
     try {
-      return await apiCall<MCPConfigPaths>("mcp_get_config_paths", { project_path: projectPath });
+      return await apiCall<void>("delete_remote_host", { id });
     } catch (error) {
-      console.error("Failed to get MCP config paths:", error);
+      console.error("Failed to delete remote host:", error);
       throw error;
     }
   },
 
   /**
-   * Reads .mcp.json from the current project
+   * Finds the claude binary on a host and records its path and version
    */
-  async mcpReadProjectConfig(projectPath: string): Promise<MCPProjectConfig> {
+  async detectRemoteClaude(id: number): Promise<RemoteHost> {
     try {
-      return await apiCall<MCPProjectConfig>("mcp_read_project_config", { projectPath });
+      return await apiCall<RemoteHost>("detect_remote_claude", { id });
     } catch (error) {
-      console.error("Failed to read project MCP config:", error);
+      console.error("Failed to detect claude on remote host:", error);
       throw error;
     }
   },
 
   /**
-   * Saves .mcp.json to the current project
+   * Gets the host a project's agents run on
    */
-  async mcpSaveProjectConfig(projectPath: string, config: MCPProjectConfig): Promise<string> {
+  async getProjectRemoteHost(projectPath: string): Promise<RemoteHost | null> {
     try {
-      return await apiCall<string>("mcp_save_project_config", { projectPath, config });
+      return await apiCall<RemoteHost | null>("get_project_remote_host", { projectPath });
     } catch (error) {
-      console.error("Failed to save project MCP config:", error);
+      console.error("Failed to get project remote host:", error);
       throw error;
     }
   },
 
   /**
-   * Get the stored Claude binary path from settings
-   * @returns Promise resolving to the path if set, null otherwise
+   * Runs a project's agents on a host, or locally again with null
    */
-  async getClaudeBinaryPath(): Promise<string | null> {
+  async setProjectRemoteHost(projectPath: string, hostId: number | null): Promise<void> {
     try {
-      return await apiCall<string | null>("get_claude_binary_path");
+      return await apiCall<void>("set_project_remote_host", { projectPath, hostId });
     } catch (error) {
-      console.error("Failed to get Claude binary path:", error);
+      console.error("Failed to set project remote host:", error);
       throw error;
     }
   },
 
   /**
-   * Set the Claude binary path in settings
-   * @param path - The absolute path to the Claude binary
-   * @returns Promise resolving when the path is saved
+   * Runs an allowed terminal command on a host, in the remote counterpart of workingDir
    */
-  async setClaudeBinaryPath(path: string): Promise<void> {
+  async executeRemoteTerminalCommand(
+    hostId: number,
+    command: string,
+    workingDir?: string
+  ): Promise<RemoteCommandOutput> {
     try {
-      return await apiCall<void>("set_claude_binary_path", { path });
+      return await apiCall<RemoteCommandOutput>("execute_remote_terminal_command", {
+        hostId,
+        command,
+        workingDir,
+      });
     } catch (error) {
-      console.error("Failed to set Claude binary path:", error);
+      console.error("Failed to run remote command:", error);
       throw error;
     }
   },
 
   /**
-   * List all available Claude installations on the system
-   * Uses caching to avoid repeated checks (cache expires after 5 minutes)
-   * @returns Promise resolving to an array of Claude installations
+   * Gets how a project's agents run in a container, if they do
    */
-  async listClaudeInstallations(forceRefresh = false): Promise<ClaudeInstallation[]> {
+  async getProjectContainer(projectPath: string): Promise<ContainerConfig | null> {
     try {
-      const now = Date.now();
-
-      // Return cached data if it's still valid and not forcing refresh
-      if (!forceRefresh && this._claudeInstallationsCache.data &&
-          (now - this._claudeInstallationsCache.timestamp) < this._claudeInstallationsCache.ttl) {
-        console.log("Using cached Claude installations");
-        return this._claudeInstallationsCache.data;
-      }
-
-      // Fetch fresh data
-      console.log("Fetching fresh Claude installations");
-      const installations = await apiCall<ClaudeInstallation[]>("list_claude_installations");
-
-      // Update cache
-      this._claudeInstallationsCache = {
-        data: installations,
-        timestamp: now,
-        ttl: this._claudeInstallationsCache.ttl,
-      };
-
-      return installations;
+      return await apiCall<ContainerConfig | null>("get_project_container", { projectPath });
     } catch (error) {
-      console.error("Failed to list Claude installations:", error);
+      console.error("Failed to get project container:", error);
       throw error;
     }
   },
 
-  // Storage API methods
-
   /**
-   * Lists all tables in the SQLite database
-   * @returns Promise resolving to an array of table information
+   * Runs a project's agents in a container, or on the host again with null
    */
-  async storageListTables(): Promise<any[]> {
+  async setProjectContainer(projectPath: string, config: ContainerConfig | null): Promise<void> {
     try {
-      return await apiCall<any[]>("storage_list_tables");
+      return await apiCall("set_project_container", { projectPath, config });
     } catch (error) {
-      console.error("Failed to list tables:", error);
+      console.error("Failed to set project container:", error);
       throw error;
     }
   },
 
   /**
-   * Reads table data with pagination
-   * @param tableName - Name of the table to read
-   * @param page - Page number (1-indexed)
-   * @param pageSize - Number of rows per page
-   * @param searchQuery - Optional search query
-   * @returns Promise resolving to table data with pagination info
+   * Reads the project's devcontainer.json as a container run would use it
    */
-  async storageReadTable(
-    tableName: string,
-    page: number,
-    pageSize: number,
-    searchQuery?: string
-  ): Promise<any> {
+  async inspectDevcontainer(projectPath: string): Promise<Devcontainer> {
     try {
-      return await apiCall<any>("storage_read_table", {
-        tableName,
-        page,
-        pageSize,
-        searchQuery,
-      });
+      return await apiCall<Devcontainer>("inspect_devcontainer", { projectPath });
     } catch (error) {
-      console.error("Failed to read table:", error);
+      console.error("Failed to inspect devcontainer:", error);
       throw error;
     }
   },
 
   /**
-   * Updates a row in a table
-   * @param tableName - Name of the table
-   * @param primaryKeyValues - Map of primary key column names to values
-   * @param updates - Map of column names to new values
-   * @returns Promise resolving when the row is updated
+   * Pulls the image a project's runs use; progress arrives as "container-image-progress" events
    */
-  async storageUpdateRow(
-    tableName: string,
-    primaryKeyValues: Record<string, any>,
-    updates: Record<string, any>
-  ): Promise<void> {
+  async pullContainerImage(projectPath: string): Promise<string> {
     try {
-      return await apiCall<void>("storage_update_row", {
-        tableName,
-        primaryKeyValues,
-        updates,
-      });
+      return await apiCall<string>("pull_container_image", { projectPath });
     } catch (error) {
-      console.error("Failed to update row:", error);
+      console.error("Failed to pull container image:", error);
       throw error;
     }
   },
 
   /**
-   * Deletes a row from a table
-   * @param tableName - Name of the table
-   * @param primaryKeyValues - Map of primary key column names to values
-   * @returns Promise resolving when the row is deleted
+   * Builds the project's devcontainer image; progress arrives as "container-image-progress" events
    */
-  async storageDeleteRow(
-    tableName: string,
-    primaryKeyValues: Record<string, any>
-  ): Promise<void> {
+  async buildDevcontainerImage(projectPath: string): Promise<string> {
     try {
-      return await apiCall<void>("storage_delete_row", {
-        tableName,
-        primaryKeyValues,
-      });
+      return await apiCall<string>("build_devcontainer_image", { projectPath });
     } catch (error) {
-      console.error("Failed to delete row:", error);
+      console.error("Failed to build devcontainer image:", error);
       throw error;
     }
   },
 
   /**
-   * Inserts a new row into a table
-   * @param tableName - Name of the table
-   * @param values - Map of column names to values
-   * @returns Promise resolving to the last insert row ID
+   * Lists the containers started for agent runs, including stopped ones
    */
-  async storageInsertRow(
-    tableName: string,
-    values: Record<string, any>
-  ): Promise<number> {
+  async listRunContainers(): Promise<RunContainer[]> {
     try {
-      return await apiCall<number>("storage_insert_row", {
-        tableName,
-        values,
-      });
+      return await apiCall<RunContainer[]>("list_run_containers");
     } catch (error) {
-      console.error("Failed to insert row:", error);
+      console.error("Failed to list run containers:", error);
       throw error;
     }
   },
 
   /**
-   * Executes a raw SQL query
-   * @param query - SQL query string
-   * @returns Promise resolving to query result
+   * Removes the containers of runs that have ended, returning their names
    */
-  async storageExecuteSql(query: string): Promise<any> {
+  async cleanupRunContainers(): Promise<string[]> {
     try {
-      return await apiCall<any>("storage_execute_sql", { query });
+      return await apiCall<string[]>("cleanup_run_containers");
     } catch (error) {
-      console.error("Failed to execute SQL:", error);
+      console.error("Failed to clean up run containers:", error);
       throw error;
     }
   },
 
   /**
-   * Resets the entire database
-   * @returns Promise resolving when the database is reset
+   * Lists the cache volumes of a project, or all of them
    */
-  async storageResetDatabase(): Promise<void> {
+  async listContainerCaches(projectPath?: string): Promise<string[]> {
     try {
-      return await apiCall<void>("storage_reset_database");
+      return await apiCall<string[]>("list_container_caches", { projectPath });
     } catch (error) {
-      console.error("Failed to reset database:", error);
+      console.error("Failed to list container caches:", error);
       throw error;
     }
   },
 
-  // Theme settings helpers
+  /**
+   * Removes the cache volumes of a project, or all of them, returning their names
+   */
+  async clearContainerCaches(projectPath?: string): Promise<string[]> {
+    try {
+      return await apiCall<string[]>("clear_container_caches", { projectPath });
+    } catch (error) {
+      console.error("Failed to clear container caches:", error);
+      throw error;
+    }
+  },
 
   /**
-   * Gets a setting from the app_settings table
-   * @param key - The setting key to retrieve
-   * @returns Promise resolving to the setting value or null if not found
+   * Forwards a local port to a port on a remote host and starts it; MCP servers can then use
+   * tunnel://<name>/<path> URLs
    */
-  async getSetting(key: string): Promise<string | null> {
+  async createTunnel(
+    hostId: number,
+    remotePort: number,
+    remoteHost?: string,
+    name?: string
+  ): Promise<SshTunnel> {
     try {
-      // Fast path: check localStorage mirror to avoid startup flicker
-      if (typeof window !== 'undefined' && 'localStorage' in window) {
-        const cached = window.localStorage.getItem(`app_setting:${key}`);
-        if (cached !== null) {
-          return cached;
-        }
-      }
-      // Use storageReadTable to safely query the app_settings table
-      const result = await this.storageReadTable('app_settings', 1, 1000);
-      const setting = result?.data?.find((row: any) => row.key === key);
-      return setting?.value || null;
+      return await apiCall<SshTunnel>("create_tunnel", { hostId, remotePort, remoteHost, name });
     } catch (error) {
-      console.error(`Failed to get setting ${key}:`, error);
-      return null;
+      console.error("Failed to create tunnel:", error);
+      throw error;
     }
   },
 
   /**
-   * Saves a setting to the app_settings table (insert or update)
-   * @param key - The setting key
-   * @param value - The setting value
-   * @returns Promise resolving when the setting is saved
+   * Lists SSH tunnels with their live status; changes arrive as "ssh-tunnel-changed" events
    */
-  async saveSetting(key: string, value: string): Promise<void> {
+  async listTunnels(): Promise<TunnelStatus[]> {
     try {
-      // Mirror to localStorage for instant availability on next startup
-      if (typeof window !== 'undefined' && 'localStorage' in window) {
-        try {
-          window.localStorage.setItem(`app_setting:${key}`, value);
-        } catch (_ignore) {
-          // best-effort; continue to persist in DB
-        }
-      }
-      // Try to update first
-      try {
-        await this.storageUpdateRow(
-          'app_settings',
-          { key },
-          { value }
-        );
-      } catch (updateError) {
-        // If update fails (row doesn't exist), insert new row
-        await this.storageInsertRow('app_settings', { key, value });
-      }
+      return await apiCall<TunnelStatus[]>("list_tunnels");
     } catch (error) {
-      console.error(`Failed to save setting ${key}:`, error);
+      console.error("Failed to list tunnels:", error);
       throw error;
     }
   },
 
   /**
-   * Get hooks configuration for a specific scope
-   * @param scope - The configuration scope: 'user', 'project', or 'local'
-   * @param projectPath - Project path (required for project and local scopes)
-   * @returns Promise resolving to the hooks configuration
+   * Starts a stopped tunnel
    */
-  async getHooksConfig(scope: 'user' | 'project' | 'local', projectPath?: string): Promise<HooksConfiguration> {
+  async startTunnel(id: number): Promise<SshTunnel> {
     try {
-      return await apiCall<HooksConfiguration>("get_hooks_config", { scope, projectPath });
+      return await apiCall<SshTunnel>("start_tunnel", { id });
     } catch (error) {
-      console.error("Failed to get hooks config:", error);
+      console.error("Failed to start tunnel:", error);
       throw error;
     }
   },
 
   /**
-   * Update hooks configuration for a specific scope
-   * @param scope - The configuration scope: 'user', 'project', or 'local'
-   * @param hooks - The hooks configuration to save
-   * @param projectPath - Project path (required for project and local scopes)
-   * @returns Promise resolving to success message
+   * Stops a tunnel, keeping it for later
    */
-  async updateHooksConfig(
-    scope: 'user' | 'project' | 'local',
-    hooks: HooksConfiguration,
-    projectPath?: string
-  ): Promise<string> {
+  async stopTunnel(id: number): Promise<void> {
     try {
-      return await apiCall<string>("update_hooks_config", { scope, projectPath, hooks });
+      return await apiCall("stop_tunnel", { id });
     } catch (error) {
-      console.error("Failed to update hooks config:", error);
+      console.error("Failed to stop tunnel:", error);
       throw error;
     }
   },
 
   /**
-   * Validate a hook command syntax
-   * @param command - The shell command to validate
-   * @returns Promise resolving to validation result
+   * Stops and deletes a tunnel
    */
-  async validateHookCommand(command: string): Promise<{ valid: boolean; message: string }> {
+  async deleteTunnel(id: number): Promise<void> {
     try {
-      return await apiCall<{ valid: boolean; message: string }>("validate_hook_command", { command });
+      return await apiCall("delete_tunnel", { id });
     } catch (error) {
-      console.error("Failed to validate hook command:", error);
+      console.error("Failed to delete tunnel:", error);
       throw error;
     }
   },
 
   /**
-   * Get merged hooks configuration (respecting priority)
-   * @param projectPath - The project path
-   * @returns Promise resolving to merged hooks configuration
+   * Runs a one-shot text task (summary, commit message, transcript analysis) through the CLI
+   * or, without a usable CLI, the API
    */
-  async getMergedHooksConfig(projectPath: string): Promise<HooksConfiguration> {
+  async runPrintTask(request: PrintTaskRequest): Promise<PrintTaskResult> {
     try {
-      const [userHooks, projectHooks, localHooks] = await Promise.all([
-        this.getHooksConfig('user'),
-        this.getHooksConfig('project', projectPath),
-        this.getHooksConfig('local', projectPath)
-      ]);
-
-      // Use HooksManager for merging
-      return HooksManager.mergeConfigs(userHooks, projectHooks, localHooks);
+      return await apiCall<PrintTaskResult>("run_print_task", { request });
     } catch (error) {
-      console.error("Failed to get merged hooks config:", error);
+      console.error("Failed to run print task:", error);
       throw error;
     }
   },
 
-  // Slash Commands API methods
-
   /**
-   * Lists all available slash commands
-   * @param projectPath - Optional project path to include project-specific commands
-   * @returns Promise resolving to array of slash commands
+   * Gets which backend print tasks use
    */
-  async slashCommandsList(projectPath?: string): Promise<SlashCommand[]> {
+  async getPrintTaskBackend(): Promise<PrintBackendStatus> {
     try {
-      return await apiCall<SlashCommand[]>("slash_commands_list", { projectPath });
+      return await apiCall<PrintBackendStatus>("get_print_task_backend");
     } catch (error) {
-      console.error("Failed to list slash commands:", error);
+      console.error("Failed to get print task backend:", error);
       throw error;
     }
   },
 
   /**
-   * Gets a single slash command by ID
-   * @param commandId - Unique identifier of the command
-   * @returns Promise resolving to the slash command
+   * Pins print tasks to the CLI or the API, or picks automatically
    */
-  async slashCommandGet(commandId: string): Promise<SlashCommand> {
+  async setPrintTaskBackend(preference: "auto" | PrintBackend): Promise<PrintBackendStatus> {
     try {
-      return await apiCall<SlashCommand>("slash_command_get", { commandId });
+      return await apiCall<PrintBackendStatus>("set_print_task_backend", { preference });
     } catch (error) {
-      console.error("Failed to get slash command:", error);
+      console.error("Failed to set print task backend:", error);
       throw error;
     }
   },
 
   /**
-   * Creates or updates a slash command
-   * @param scope - Command scope: "project" or "user"
-   * @param name - Command name (without prefix)
-   * @param namespace - Optional namespace for organization
-   * @param content - Markdown content of the command
-   * @param description - Optional description
-   * @param allowedTools - List of allowed tools for this command
-   * @param projectPath - Required for project scope commands
-   * @returns Promise resolving to the saved command
+   * Writes the digest for a day (YYYY-MM-DD, today by default), optionally posting it to webhooks
    */
-  async slashCommandSave(
-    scope: string,
-    name: string,
-    namespace: string | undefined,
-    content: string,
-    description: string | undefined,
-    allowedTools: string[],
-    projectPath?: string
-  ): Promise<SlashCommand> {
+  async generateDailyDigest(day?: string, post?: boolean): Promise<DailyDigest> {
     try {
-      return await apiCall<SlashCommand>("slash_command_save", {
-        scope,
-        name,
-        namespace,
-        content,
-        description,
-        allowedTools,
-        projectPath
-      });
+      return await apiCall<DailyDigest>("generate_daily_digest", { day, post });
     } catch (error) {
-      console.error("Failed to save slash command:", error);
+      console.error("Failed to generate daily digest:", error);
       throw error;
     }
   },
 
   /**
-   * Deletes a slash command
-   * @param commandId - Unique identifier of the command to delete
-   * @param projectPath - Optional project path for deleting project commands
-   * @returns Promise resolving to deletion message
+   * Gets the stored digest for a day, if one was written
    */
-  async slashCommandDelete(commandId: string, projectPath?: string): Promise<string> {
+  async getDailyDigest(day: string): Promise<DailyDigest | null> {
     try {
-      return await apiCall<string>("slash_command_delete", { commandId, projectPath });
+      return await apiCall<DailyDigest | null>("get_daily_digest", { day });
     } catch (error) {
-      console.error("Failed to delete slash command:", error);
+      console.error("Failed to get daily digest:", error);
       throw error;
     }
   },
 
-  // Skills API methods
-
   /**
-   * Lists all skills (both personal and project)
-   * @returns Promise resolving to array of skills
+   * Lists stored digests, newest first
    */
-  async skillListAll(): Promise<Skill[]> {
+  async listDailyDigests(limit?: number): Promise<DailyDigest[]> {
     try {
-      return await apiCall<Skill[]>("skill_list_all");
+      return await apiCall<DailyDigest[]>("list_daily_digests", { limit });
     } catch (error) {
-      console.error("Failed to list all skills:", error);
+      console.error("Failed to list daily digests:", error);
       throw error;
     }
   },
 
-  /**
-   * Lists skills by type (personal or project)
-   * @param skillType - Type of skills to list: "personal" or "project"
-   * @returns Promise resolving to array of skills
-   */
-  async skillListByType(skillType: string): Promise<Skill[]> {
+  async getDigestSettings(): Promise<DigestSettings> {
     try {
-      return await apiCall<Skill[]>("skill_list_by_type", { skillType });
+      return await apiCall<DigestSettings>("get_digest_settings");
     } catch (error) {
-      console.error(`Failed to list ${skillType} skills:`, error);
+      console.error("Failed to get digest settings:", error);
       throw error;
     }
   },
 
-  /**
-   * Reads a skill by name and type
-   * @param name - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @returns Promise resolving to the skill
-   */
-  async skillRead(name: string, skillType: string): Promise<Skill> {
+  async setDigestSettings(settings: DigestSettings): Promise<DigestSettings> {
     try {
-      return await apiCall<Skill>("skill_read", { name, skill_type: skillType });
+      return await apiCall<DigestSettings>("set_digest_settings", { settings });
     } catch (error) {
-      console.error(`Failed to read skill ${name}:`, error);
+      console.error("Failed to save digest settings:", error);
       throw error;
     }
   },
 
-  /**
-   * Creates a new skill
-   * @param name - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @param description - The skill description
-   * @param markdownContent - The markdown content
-   * @param allowedTools - Optional list of allowed tools
-   * @returns Promise resolving to the created skill
-   */
-  async skillCreate(
-    name: string,
-    skillType: string,
-    description: string,
-    markdownContent: string,
-    allowedTools?: string[]
-  ): Promise<Skill> {
+  async getBackupSettings(): Promise<BackupSettings> {
     try {
-      return await apiCall<Skill>("skill_create", {
-        name,
-        skillType,
-        description,
-        markdownContent,
-        allowedTools,
-      });
+      return await apiCall<BackupSettings>("get_backup_settings");
     } catch (error) {
-      console.error(`Failed to create skill ${name}:`, error);
+      console.error("Failed to get backup settings:", error);
       throw error;
     }
   },
 
   /**
-   * Updates an existing skill
-   * @param name - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @param description - Optional updated description
-   * @param markdownContent - Optional updated markdown content
-   * @param allowedTools - Optional updated allowed tools
-   * @returns Promise resolving to the updated skill
+   * Saves the backup settings
+   * @param passphrase - Replaces the passphrase kept in the keychain for encrypted backups
    */
-  async skillUpdate(
-    name: string,
-    skillType: string,
-    description?: string,
-    markdownContent?: string,
-    allowedTools?: string[]
-  ): Promise<Skill> {
+  async setBackupSettings(settings: BackupSettings, passphrase?: string): Promise<BackupSettings> {
     try {
-      return await apiCall<Skill>("skill_update", {
-        name,
-        skillType,
-        description,
-        markdownContent,
-        allowedTools,
-      });
+      return await apiCall<BackupSettings>("set_backup_settings", { settings, passphrase });
     } catch (error) {
-      console.error(`Failed to update skill ${name}:`, error);
+      console.error("Failed to save backup settings:", error);
       throw error;
     }
   },
 
   /**
-   * Deletes a skill
-   * @param name - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @returns Promise resolving when the skill is deleted
+   * Takes a backup now with the saved settings
    */
-  async skillDelete(name: string, skillType: string): Promise<void> {
+  async createConfigBackup(): Promise<BackupInfo> {
     try {
-      return await apiCall<void>("skill_delete", { name, skillType });
+      return await apiCall<BackupInfo>("create_config_backup");
     } catch (error) {
-      console.error(`Failed to delete skill ${name}:`, error);
+      console.error("Failed to create backup:", error);
       throw error;
     }
   },
 
   /**
-   * Validates a skill format
-   * @param name - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @param description - The skill description
-   * @param markdownContent - The markdown content
-   * @returns Promise resolving to validation result
+   * Lists the backups in the backup directory, newest first
    */
-  async skillValidate(
-    name: string,
-    skillType: string,
-    description: string,
-    markdownContent: string
-  ): Promise<ValidationResult> {
+  async listConfigBackups(): Promise<BackupInfo[]> {
     try {
-      return await apiCall<ValidationResult>("skill_validate", {
-        name,
-        skillType,
-        description,
-        markdownContent,
-      });
+      return await apiCall<BackupInfo[]>("list_config_backups");
     } catch (error) {
-      console.error(`Failed to validate skill ${name}:`, error);
+      console.error("Failed to list backups:", error);
       throw error;
     }
   },
 
   /**
-   * Creates a file in a skill directory
-   * @param skillName - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @param fileName - The file name to create
-   * @param content - The file content
-   * @returns Promise resolving when the file is created
+   * Restores a backup; with dryRun only returns what the restore would change
+   * @param passphrase - For encrypted backups; defaults to the one in the keychain
    */
-  async skillCreateFile(skillName: string, skillType: string, fileName: string, content: string): Promise<void> {
+  async restoreBackup(path: string, dryRun?: boolean, passphrase?: string): Promise<RestorePlan> {
     try {
-      return await apiCall<void>("skill_create_file", {
-        skillName,
-        skillType,
-        fileName,
-        content,
-      });
+      return await apiCall<RestorePlan>("restore_backup", { path, dryRun, passphrase });
     } catch (error) {
-      console.error(`Failed to create file ${fileName} in skill ${skillName}:`, error);
+      console.error("Failed to restore backup:", error);
+      throw error;
+    }
+  },
+
+  async getConfigSyncSettings(): Promise<ConfigSyncSettings> {
+    try {
+      return await apiCall<ConfigSyncSettings>("get_config_sync_settings");
+    } catch (error) {
+      console.error("Failed to get config sync settings:", error);
+      throw error;
+    }
+  },
+
+  async setConfigSyncSettings(settings: ConfigSyncSettings): Promise<ConfigSyncSettings> {
+    try {
+      return await apiCall<ConfigSyncSettings>("set_config_sync_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save config sync settings:", error);
       throw error;
     }
   },
 
   /**
-   * Reads a file from a skill directory
-   * @param skillName - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @param fileName - The file name to read
-   * @returns Promise resolving to the file content
+   * Commits local configuration changes, pulls and imports remote ones, and pushes
    */
-  async skillReadFile(skillName: string, skillType: string, fileName: string): Promise<string> {
+  async syncConfigNow(): Promise<ConfigSyncReport> {
     try {
-      return await apiCall<string>("skill_read_file", {
-        skillName,
-        skillType,
-        fileName,
-      });
+      return await apiCall<ConfigSyncReport>("sync_config_now");
     } catch (error) {
-      console.error(`Failed to read file ${fileName} from skill ${skillName}:`, error);
+      console.error("Failed to sync configuration:", error);
+      throw error;
+    }
+  },
+
+  async getConfigSyncStatus(): Promise<ConfigSyncStatus> {
+    try {
+      return await apiCall<ConfigSyncStatus>("get_config_sync_status");
+    } catch (error) {
+      console.error("Failed to get config sync status:", error);
       throw error;
     }
   },
 
   /**
-   * Deletes a file from a skill directory
-   * @param skillName - The skill name
-   * @param skillType - The skill type: "personal" or "project"
-   * @param fileName - The file name to delete
-   * @returns Promise resolving when the file is deleted
+   * Resolves a conflicting file by keeping one side; returns the conflicts left
    */
-  async skillDeleteFile(skillName: string, skillType: string, fileName: string): Promise<void> {
+  async resolveConfigSyncConflict(path: string, keep: "local" | "remote"): Promise<string[]> {
     try {
-      return await apiCall<void>("skill_delete_file", {
-        skillName,
-        skillType,
-        fileName,
-      });
+      return await apiCall<string[]>("resolve_config_sync_conflict", { path, keep });
     } catch (error) {
-      console.error(`Failed to delete file ${fileName} from skill ${skillName}:`, error);
+      console.error("Failed to resolve config sync conflict:", error);
+      throw error;
+    }
+  },
+
+  async abortConfigSync(): Promise<void> {
+    try {
+      return await apiCall<void>("abort_config_sync");
+    } catch (error) {
+      console.error("Failed to abort config sync:", error);
       throw error;
     }
   },
 
   /**
-   * Gets the application version
-   * @returns Promise resolving to the version string
+   * Reads a Claude settings file or .mcp.json for editing; what's read becomes the merge base
    */
-  async getAppVersion(): Promise<string> {
+  async readConfigForMerge(path: string): Promise<any> {
     try {
-      return await apiCall<string>("get_app_version");
+      return await apiCall<any>("read_config_for_merge", { path });
     } catch (error) {
-      console.error("Failed to get app version:", error);
+      console.error("Failed to read config file:", error);
       throw error;
     }
   },
 
   /**
-   * Gets detailed version information
-   * @returns Promise resolving to version info object
+   * Saves an edited settings file or .mcp.json merged with changes made elsewhere
+   * @param resolutions - Side to keep per conflicting key (JSON pointer)
    */
-  async getVersionInfo(): Promise<any> {
+  async saveConfigMerged(
+    path: string,
+    content: any,
+    resolutions?: Record<string, "local" | "disk">
+  ): Promise<ConfigMergeOutcome> {
     try {
-      return await apiCall<any>("get_version_info");
+      return await apiCall<ConfigMergeOutcome>("save_config_merged", { path, content, resolutions });
     } catch (error) {
-      console.error("Failed to get version info:", error);
+      console.error("Failed to save config file:", error);
       throw error;
     }
   },